@@ -0,0 +1,21 @@
+-- Bundled config, used when no init.lua is found in $XDG_CONFIG_HOME/scape or $XDG_CONFIG_DIRS.
+-- It only provides basic key bindings, outputs are set up by the first-run wizard, which writes
+-- the user config.
+local scape = require("scape")
+
+local function bind(key, mods, callback)
+	scape.map_key({ key = key, mods = mods, callback = callback })
+end
+
+bind("Return", "super", function()
+	scape.spawn({ command = "foot" })
+end)
+bind("q", "shift|super", function()
+	scape.close_window()
+end)
+bind("e", "shift|super", function()
+	scape.quit()
+end)
+bind("r", "shift|super", function()
+	scape.reload()
+end)
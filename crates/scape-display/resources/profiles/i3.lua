@@ -0,0 +1,40 @@
+-- i3-like defaults: alt as modifier, vim-style movement keys
+local scape = require("scape")
+
+local profile = {
+	terminal = "i3-sensible-terminal",
+	launcher = "dmenu_run",
+	mods = "alt",
+}
+
+function profile.set_zones(output)
+	scape.set_zones({
+		{ name = "left", x = 0, y = 0, width = output.width / 2, height = output.height },
+		{ name = "right", x = output.width / 2, y = 0, width = output.width / 2, height = output.height },
+		{ name = "full", x = 0, y = 0, width = output.width, height = output.height, default = true },
+	})
+end
+
+local function bind(key, mods, callback)
+	scape.map_key({ key = key, mods = mods, callback = callback })
+end
+
+bind("Return", profile.mods, function()
+	scape.spawn({ command = profile.terminal })
+end)
+bind("d", profile.mods, function()
+	scape.spawn({ command = profile.launcher })
+end)
+bind("q", "shift|" .. profile.mods, function()
+	scape.close()
+end)
+bind("e", "shift|" .. profile.mods, function()
+	scape.quit()
+end)
+for key, zone in pairs({ h = "left", Left = "left", l = "right", Right = "right", f = "full" }) do
+	bind(key, profile.mods, function()
+		scape.move_to_zone(zone)
+	end)
+end
+
+return profile
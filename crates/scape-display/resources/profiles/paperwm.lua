@@ -0,0 +1,38 @@
+-- PaperWM-like defaults: a wide center column with narrow peeking columns next to it
+local scape = require("scape")
+
+local profile = {
+	terminal = "kgx",
+	mods = "super",
+}
+
+function profile.set_zones(output)
+	local side = output.width / 6
+	scape.set_zones({
+		{ name = "left", x = 0, y = 0, width = side, height = output.height },
+		{ name = "center", x = side, y = 0, width = side * 4, height = output.height, default = true },
+		{ name = "right", x = side * 5, y = 0, width = side, height = output.height },
+	})
+end
+
+local function bind(key, mods, callback)
+	scape.map_key({ key = key, mods = mods, callback = callback })
+end
+
+bind("Return", profile.mods, function()
+	scape.spawn({ command = profile.terminal })
+end)
+bind("q", "shift|" .. profile.mods, function()
+	scape.close()
+end)
+bind("Left", profile.mods, function()
+	scape.move_to_zone("left")
+end)
+bind("Right", profile.mods, function()
+	scape.move_to_zone("right")
+end)
+bind("Up", profile.mods, function()
+	scape.move_to_zone("center")
+end)
+
+return profile
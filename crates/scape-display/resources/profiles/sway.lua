@@ -0,0 +1,44 @@
+-- sway-like defaults: super as modifier, terminal on super+Return
+local scape = require("scape")
+
+local profile = {
+	terminal = "foot",
+	launcher = "wmenu-run",
+	mods = "super",
+}
+
+function profile.set_zones(output)
+	scape.set_zones({
+		{ name = "left", x = 0, y = 0, width = output.width / 2, height = output.height },
+		{ name = "right", x = output.width / 2, y = 0, width = output.width / 2, height = output.height },
+		{ name = "full", x = 0, y = 0, width = output.width, height = output.height, default = true },
+	})
+end
+
+local function bind(key, mods, callback)
+	scape.map_key({ key = key, mods = mods, callback = callback })
+end
+
+bind("Return", profile.mods, function()
+	scape.spawn({ command = profile.terminal })
+end)
+bind("d", profile.mods, function()
+	scape.spawn({ command = profile.launcher })
+end)
+bind("q", "shift|" .. profile.mods, function()
+	scape.close()
+end)
+bind("e", "shift|" .. profile.mods, function()
+	scape.quit()
+end)
+bind("Left", profile.mods, function()
+	scape.move_to_zone("left")
+end)
+bind("Right", profile.mods, function()
+	scape.move_to_zone("right")
+end)
+bind("f", profile.mods, function()
+	scape.move_to_zone("full")
+end)
+
+return profile
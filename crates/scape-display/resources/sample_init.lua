@@ -1,7 +1,11 @@
 scape = require("scape")
 
 scape.on_startup(function()
-	scape.spawn("wezterm")
+	-- Entries start in order, one with wait_for holds back the following ones until it is ready
+	scape.startup({
+		{ command = "mako", wait_for = { dbus_name = "org.freedesktop.Notifications" }, timeout = 5 },
+		{ command = "wezterm" },
+	})
 end)
 
 local space = "main"
@@ -21,6 +25,9 @@ scape.on_connector_change(function(outputs)
 			main_output,
 		},
 	})
+	-- Mode, transform, scale and position of single outputs can be changed as well, e.g.
+	-- scape.configure_output({ name = "DP-1", mode = "2560x1440@144", transform = "90", position = { 1920, 0 } })
+	-- Windows in zones keep 8 pixels between each other and 16 pixels to the edges of the output
 	scape.set_zones({
 		{
 			name = "left",
@@ -44,7 +51,12 @@ scape.on_connector_change(function(outputs)
 			width = outputs[1].width / 4,
 			height = outputs[1].height,
 		},
-	})
+	}, { inner = 8, outer = 16 })
+	-- Named zone sets can be switched at runtime instead, windows of vanishing zones reflow, e.g.
+	-- scape.set_zone_layouts(main_output.name, {
+	-- 	{ name = "columns", zones = { { name = "left", ... }, { name = "right", ... } } },
+	-- 	{ name = "main_stack", zones = { { name = "main", ... }, { name = "stack", ... } } },
+	-- })
 end)
 
 scape.map_key({
@@ -89,3 +101,605 @@ scape.map_key({
 		scape.move_to_zone("mid")
 	end,
 })
+-- Windows can also be moved to a position in global coordinates, taking them out of their zone
+scape.map_key({
+	key = "c",
+	mods = "super",
+	callback = function()
+		scape.move_window(nil, { x = 0, y = 0 })
+	end,
+})
+-- Focus the nearest window to the left, below, above or to the right
+for key, direction in pairs({ h = "left", j = "down", k = "up", l = "right" }) do
+	scape.map_key({
+		key = key,
+		mods = "alt|super",
+		callback = function()
+			scape.focus_direction(direction)
+		end,
+	})
+end
+-- Jump back to the previously focused window, alt-tab goes through the windows in the same order
+scape.map_key({
+	key = "Tab",
+	mods = "super",
+	callback = function()
+		scape.focus_previous()
+	end,
+})
+-- Swap the focused window with the window in the neighboring zone
+for _, direction in ipairs({ "Left", "Right", "Up", "Down" }) do
+	scape.map_key({
+		key = direction,
+		mods = "alt|super",
+		callback = function()
+			scape.swap_windows(nil, direction:lower())
+		end,
+	})
+end
+-- Resize the focused window, windows in a zone move the edge shared with the neighboring zones
+for key, resize in pairs({
+	Left = { "right", -32 },
+	Right = { "right", 32 },
+	Up = { "bottom", -32 },
+	Down = { "bottom", 32 },
+}) do
+	scape.map_key({
+		key = key,
+		mods = "ctrl|super",
+		callback = function()
+			scape.resize_window(resize[1], resize[2])
+		end,
+	})
+end
+-- Move the focused window to the neighboring output
+scape.map_key({
+	key = "Left",
+	mods = "shift|super",
+	callback = function()
+		scape.move_window_to_output(nil, "left")
+	end,
+})
+scape.map_key({
+	key = "Right",
+	mods = "shift|super",
+	callback = function()
+		scape.move_window_to_output(nil, "right")
+	end,
+})
+-- Screenshots are saved to XDG_PICTURES_DIR of ~/.config/user-dirs.dirs or ~/Pictures, shift selects a region and copies it to the clipboard
+scape.map_key({
+	key = "Print",
+	mods = "super",
+	callback = function()
+		scape.screenshot("output")
+	end,
+})
+scape.map_key({
+	key = "Print",
+	mods = "shift|super",
+	callback = function()
+		scape.screenshot("region", { clipboard = true })
+	end,
+})
+-- Record the output under the pointer to XDG_VIDEOS_DIR of ~/.config/user-dirs.dirs or ~/Videos until the binding is pressed again
+scape.map_key({
+	key = "Print",
+	mods = "ctrl|super",
+	callback = function()
+		scape.toggle_recording()
+	end,
+})
+
+-- Magnify the region around the pointer, zooming back out to 1 turns the magnifier off
+scape.map_key({
+	key = "equal",
+	mods = "ctrl|super",
+	callback = function()
+		scape.zoom(0.5)
+	end,
+})
+scape.map_key({
+	key = "minus",
+	mods = "ctrl|super",
+	callback = function()
+		scape.zoom(-0.5)
+	end,
+})
+-- Take a closer look only while the key is held
+scape.map_key({
+	key = "n",
+	mods = "ctrl|super",
+	callback = function()
+		scape.zoom(3)
+	end,
+	release_callback = function()
+		scape.zoom(-3)
+	end,
+})
+
+-- Window rules are applied when a window is mapped, later rules override earlier ones
+scape.add_rule({
+	matches = { app_id = "pavucontrol" },
+	properties = { floating = true, size = { width = 800, height = 600 }, ssd = true },
+})
+scape.add_rule({
+	matches = { app_id = "wezterm" },
+	properties = { zone = "mid", opacity = 0.95 },
+})
+-- Fade out the terminal while another window is focused
+scape.add_rule({ matches = { app_id = "Alacritty" }, opacity = 0.9, opacity_unfocused = 0.8 })
+-- Scroll faster in the browser and scroll the timeline of the video editor horizontally
+scape.add_rule({
+	matches = { app_id = "firefox" },
+	properties = { scroll_factor = 1.5 },
+})
+scape.add_rule({
+	matches = { app_id = "org.kde.kdenlive" },
+	properties = { swap_scroll_axes = true },
+})
+
+-- The config is reloaded automatically when it changes, or manually with super+shift+r
+scape.map_key({
+	key = "r",
+	mods = "shift|super",
+	callback = function()
+		scape.reload()
+	end,
+})
+
+-- Super+Escape always releases the pointer from games which lock or confine it
+scape.on_pointer_capture(function(captured, app_id)
+	print("Pointer captured: " .. tostring(captured) .. " by " .. tostring(app_id))
+end)
+
+scape.on_window_open(function(window)
+	print("Window " .. window.id .. " opened: " .. window.app_id .. " on " .. window.space)
+end)
+
+scape.on_window_title_changed(function(window)
+	print("Window " .. window.id .. " is now titled " .. window.title)
+end)
+
+-- Closing a window which does not respond a second time kills its application
+scape.on_window_not_responding(function(window)
+	scape.spawn({
+		command = "notify-send",
+		args = { window.app_id .. " is not responding", "Close it again to kill it" },
+	})
+end)
+
+-- Split the output into 1, 2, 3 or 2x2 zones on the fly
+for key, split in pairs({ ["1"] = { 1, 1 }, ["2"] = { 2, 1 }, ["3"] = { 3, 1 }, ["4"] = { 2, 2 } }) do
+	scape.map_key({
+		key = key,
+		mods = "super",
+		callback = function()
+			scape.quick_layout(split[1], split[2])
+		end,
+	})
+end
+
+-- Show pressed keys for screencasts, keys typed into the password manager are masked
+scape.set_keystroke_visualizer({ position = "bottom_right", fade = 2.0, masked_app_ids = { "org.keepassxc.KeePassXC" } })
+scape.map_key({
+	key = "k",
+	mods = "super",
+	callback = function()
+		scape.toggle_keystroke_visualizer()
+	end,
+})
+
+-- Briefly show caps lock and num lock changes, latched sticky modifiers are shown as well
+scape.set_modifier_indicators({ lock_keys = true, sticky_modifiers = true })
+
+-- Keys are named like xkb keysyms, so media and function keys can be bound as well
+for key, step in pairs({ XF86AudioRaiseVolume = "5%+", XF86AudioLowerVolume = "5%-" }) do
+	scape.map_key({
+		key = key,
+		callback = function()
+			scape.spawn({ command = "wpctl", args = { "set-volume", "@DEFAULT_AUDIO_SINK@", step } })
+		end,
+	})
+end
+
+-- Chords press keys one after another: super+x followed by b starts the browser, the keys
+-- which can follow are shown until the chord is finished or times out
+scape.set_key_chords({ timeout = 2, hint = true })
+scape.map_key({
+	keys = "super+x b",
+	description = "Browser",
+	callback = function()
+		scape.spawn({ command = "firefox" })
+	end,
+})
+scape.map_key({
+	keys = "super+x t",
+	description = "Terminal",
+	callback = function()
+		scape.spawn({ command = "foot" })
+	end,
+})
+
+-- Command lines are split into words like a shell does, shell = true runs them with sh -c so
+-- pipes and command substitution work
+scape.map_key({
+	keys = "super+x h",
+	description = "Process viewer",
+	callback = function()
+		scape.spawn("foot -e htop")
+	end,
+})
+scape.map_key({
+	keys = "super+x u",
+	description = "Uptime",
+	callback = function()
+		scape.spawn({ command = 'notify-send Uptime "$(uptime --pretty)"', shell = true })
+	end,
+})
+
+-- Launch mode: super+o followed by a single key starts an application, escape leaves the mode
+scape.map_key({
+	key = "o",
+	mods = "super",
+	callback = function()
+		scape.enter_mode("launch")
+	end,
+})
+for key, command in pairs({ t = "foot", f = "firefox" }) do
+	scape.map_key({
+		mode = "launch",
+		key = key,
+		callback = function()
+			scape.spawn({ command = command })
+			scape.enter_mode("default")
+		end,
+	})
+end
+
+-- Start the browser in the right zone, a placeholder reserves the zone until its window appears
+scape.map_key({
+	key = "w",
+	mods = "shift|super",
+	callback = function()
+		scape.spawn({ command = "firefox", zone = "right" })
+	end,
+})
+
+-- Open a terminal in the projects directory, env adds variables to the ones of the compositor
+scape.map_key({
+	key = "Return",
+	mods = "shift|super",
+	callback = function()
+		scape.spawn({
+			command = "foot",
+			cwd = os.getenv("HOME") .. "/projects",
+			env = { EDITOR = "nvim" },
+		})
+	end,
+})
+
+-- Timers run a callback once after a delay or repeatedly, scape.clear_timer(id) stops them
+scape.set_interval(60 * 1000, function()
+	print(os.date("%H:%M") .. ": " .. #scape.get_windows() .. " windows open")
+end)
+
+-- Run a command without blocking the compositor and use its output once it finished
+scape.map_key({
+	key = "u",
+	mods = "super",
+	callback = function()
+		scape.spawn_capture({ command = "uptime", args = { "--pretty" } }, function(code, stdout, stderr)
+			if code == 0 then
+				print(stdout)
+			else
+				print("uptime failed: " .. stderr)
+			end
+		end)
+	end,
+})
+
+-- Show the names of the outputs to write the layout, hold shift for a test pattern
+scape.map_key({
+	key = "p",
+	mods = "super",
+	callback = function()
+		scape.identify_outputs()
+	end,
+})
+scape.map_key({
+	key = "p",
+	mods = "shift|super",
+	callback = function()
+		scape.toggle_test_pattern()
+	end,
+})
+
+-- Switch between spaces with super+1..3, move the focused window there with ctrl
+for index, name in ipairs({ "main", "web", "chat" }) do
+	scape.create_space(name)
+	scape.map_key({
+		key = tostring(index),
+		mods = "super",
+		callback = function()
+			scape.switch_space(name)
+		end,
+	})
+	scape.map_key({
+		key = tostring(index),
+		mods = "ctrl|super",
+		callback = function()
+			scape.move_window_to_space(nil, name)
+		end,
+	})
+end
+
+-- Layouts as bookmarks: F1 saves the current arrangement, shift+F1 restores it and starts the
+-- browser and the terminal if they are not running
+scape.map_key({
+	key = "F1",
+	mods = "super",
+	callback = function()
+		scape.save_layout("work")
+	end,
+})
+scape.map_key({
+	key = "F1",
+	mods = "shift|super",
+	callback = function()
+		scape.restore_layout("work", { commands = { firefox = "firefox", foot = "foot" } })
+	end,
+})
+
+-- A terminal which drops down with super+grave and follows to the space it is toggled on
+scape.map_key({
+	key = "grave",
+	mods = "super",
+	callback = function()
+		scape.toggle_scratchpad({ name = "term", command = "alacritty --class scratch" })
+	end,
+})
+
+-- Lock the session after 5 minutes without input, turn the outputs off after 10 minutes and
+-- suspend after 30 minutes
+scape.set_idle_timeouts({
+	lock = 300,
+	screen_off = 600,
+	suspend = 1800,
+	lock_command = { command = "swaylock", args = { "--daemonize" } },
+})
+
+-- German and US layouts with caps lock as additional escape key, super+space switches between them
+scape.set_keyboard({ layouts = { "de", "us" }, options = "caps:escape" })
+scape.map_key({
+	key = "space",
+	mods = "super",
+	callback = function()
+		scape.cycle_keyboard_layout()
+	end,
+})
+
+-- Keys repeat 40 times per second after being held for 250ms, in applications and for bindings
+-- mapped with `repeat = true`
+scape.set_repeat({ rate = 40, delay = 250 })
+
+-- Pixels a press on a title bar has to be dragged before the window moves, for mouse and touch
+scape.set_drag_threshold(8)
+
+-- Focus the window under the pointer after it rested on it for 100ms, without raising it
+scape.set_focus({ follows_mouse = true, delay_ms = 100, raise_on_focus = false })
+
+-- Dragged windows fill the zone they are dropped on, the zone is highlighted while dragging.
+-- "snap" snaps windows dropped at the left or right edge of an output instead, "float" keeps them
+-- where they are dropped.
+scape.set_drag_drop({ drop = "zone", highlight = true })
+
+-- Tap to click and natural scrolling for all devices, a flat pointer acceleration for the mouse.
+-- Device names are listed by `libinput list-devices`.
+scape.configure_input({ tap = true, natural_scroll = true })
+-- scape.configure_input({ device = "Logitech G Pro", accel_profile = "flat", accel_speed = 0.3 })
+
+-- Kiosk mode for digital signage: the application is shown fullscreen and restarted when it exits.
+-- All key bindings and the screen lock are disabled until the escape sequence is pressed.
+-- scape.kiosk({
+-- 	command = "firefox",
+-- 	args = { "--kiosk", "https://example.org" },
+-- 	escape = { key = "Escape", mods = "ctrl|alt|shift" },
+-- })
+
+-- Manage a kiosk remotely by forwarding its command socket over SSH:
+--   ssh -N -L /tmp/kiosk.sock:/run/user/1000/scape-wayland-1.sock kiosk@host &
+--   SCAPE_SOCKET=/tmp/kiosk.sock SCAPE_TOKEN=secret scape cli list-windows
+-- Requests of users not listed in uids need the token then, the i3 socket only accepts those users.
+-- scape.set_command_auth({ token = "secret", uids = { 1000 } })
+
+-- Commands can be bound to keys and run from scripts, e.g. `scape cli run split 3 1`
+scape.register_command("split", function(columns, rows)
+	scape.quick_layout(tonumber(columns) or 2, tonumber(rows) or 1)
+end)
+scape.map_key({
+	key = "g",
+	mods = "super",
+	callback = function()
+		scape.run_command("split", "2", "2")
+	end,
+})
+
+-- Strip formatting and surrounding whitespace from the clipboard before pasting it
+scape.map_key({
+	key = "v",
+	mods = "super|shift",
+	callback = function()
+		scape.get_clipboard(function(text)
+			if text then
+				scape.set_clipboard((text:gsub("^%s+", ""):gsub("%s+$", "")))
+			end
+		end)
+	end,
+})
+
+-- List all windows with their position
+scape.map_key({
+	key = "i",
+	mods = "super",
+	callback = function()
+		for _, window in ipairs(scape.get_windows()) do
+			print(window.id .. " " .. window.app_id .. " at " .. window.x .. "," .. window.y .. " on " .. window.space)
+		end
+	end,
+})
+
+-- Switch between light and dark mode, applications follow through the settings portal
+scape.map_key({
+	key = "t",
+	mods = "shift|super",
+	callback = function()
+		scape.toggle_theme_variant()
+	end,
+})
+
+-- Enlarge applications with a tiny user interface, press again to reset
+scape.map_key({
+	key = "z",
+	mods = "super",
+	callback = function()
+		scape.zoom_window(1.5)
+	end,
+})
+
+-- Pop the focused window out of its zone, press again to put it back
+scape.map_key({
+	key = "space",
+	mods = "shift|super",
+	callback = function()
+		scape.toggle_floating()
+	end,
+})
+
+-- Keep the focused window visible when switching spaces, press again to leave it on its space
+scape.map_key({
+	key = "s",
+	mods = "super",
+	callback = function()
+		scape.toggle_sticky()
+	end,
+})
+
+-- Keep the focused window above all other windows, press again to unpin it
+scape.map_key({
+	key = "s",
+	mods = "shift|super",
+	callback = function()
+		scape.toggle_pin()
+	end,
+})
+
+scape.map_key({
+	key = "f",
+	mods = "super",
+	callback = function()
+		scape.toggle_fullscreen()
+	end,
+})
+scape.map_key({
+	key = "f",
+	mods = "shift|super",
+	callback = function()
+		scape.toggle_maximize()
+	end,
+})
+
+scape.map_key({
+	key = "q",
+	mods = "shift|super",
+	callback = function()
+		scape.close_window()
+	end,
+})
+
+-- Scale the output under the pointer in steps of 25%, super+0 resets it
+scape.map_key({
+	key = "equal",
+	mods = "super",
+	callback = function()
+		scape.change_scale(25)
+	end,
+})
+scape.map_key({
+	key = "minus",
+	mods = "super",
+	callback = function()
+		scape.change_scale(-25)
+	end,
+})
+scape.map_key({
+	key = "0",
+	mods = "super",
+	callback = function()
+		scape.set_scale(100)
+	end,
+})
+
+-- Drag windows with super+left and resize them with super+right, anywhere inside the window
+scape.map_key({
+	key = "l",
+	mods = "super",
+	callback = function()
+		scape.cycle_zone_layout()
+	end,
+})
+
+-- Split the zone of the focused window into halves side by side or above each other, and merge
+-- them back. Every space remembers its own splits.
+scape.map_key({
+	keys = "super+x s",
+	description = "Split zone side by side",
+	callback = function()
+		scape.split_zone("horizontal")
+	end,
+})
+scape.map_key({
+	keys = "super+x v",
+	description = "Split zone above each other",
+	callback = function()
+		scape.split_zone("vertical")
+	end,
+})
+scape.map_key({
+	keys = "super+x m",
+	description = "Merge zone",
+	callback = function()
+		scape.merge_zone()
+	end,
+})
+
+-- Show the windows of the zone of the focused window as tabs, and switch between them
+scape.map_key({
+	keys = "super+x w",
+	description = "Toggle tabs",
+	callback = function()
+		scape.toggle_tabbed()
+	end,
+})
+for key, offset in pairs({ bracketright = 1, bracketleft = -1 }) do
+	scape.map_key({
+		key = key,
+		mods = "super",
+		callback = function()
+			scape.cycle_tab(offset)
+		end,
+	})
+end
+
+scape.map_button({ button = "left", mods = "super", action = "move" })
+scape.map_button({ button = "right", mods = "super", action = "resize" })
+
+-- Move the focused window with the arrow keys, hold shift for larger steps. Held keys repeat.
+scape.set_move_mode({ step = 16, large_step = 128, grid = 16 })
+scape.map_key({
+	key = "m",
+	mods = "super",
+	callback = function()
+		scape.toggle_move_mode()
+	end,
+})
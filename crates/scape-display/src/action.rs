@@ -1,10 +1,20 @@
-use std::{process::Command, sync::atomic::Ordering};
+use std::{collections::HashMap, process::Command, sync::atomic::Ordering};
 
-use mlua::Function as LuaFunction;
+use smithay::utils::Transform;
 use tracing::{error, info, warn};
 
 use crate::{
-    dbus::portals::screen_cast::NODE_ID, pipewire::Pipewire, workspace_window::WorkspaceWindow,
+    composition::{MoveTarget, ZoneTarget},
+    config::LuaCallback,
+    dbus::portals::screen_cast::NODE_ID,
+    direction::Direction,
+    error::{Result, ScapeError},
+    grabs::ResizeEdge,
+    pipewire::Pipewire,
+    screenshot::ScreenshotTarget,
+    spaces::OutputTarget,
+    workspace_window::WorkspaceWindow,
+    zone_tree::SplitAxis,
     State,
 };
 
@@ -14,26 +24,158 @@ pub enum Action {
     Quit,
     /// Trigger a vt-switch
     VtSwitch(i32),
-    /// Spawn a command
-    Spawn { command: String, args: Vec<String> },
+    /// Spawn a command, its first window replaces a placeholder in the zone if one is given
+    Spawn {
+        command: String,
+        args: Vec<String>,
+        zone: Option<String>,
+        /// Environment variables overriding the ones of the compositor
+        env: Vec<(String, String)>,
+        /// Working directory, the one of the compositor if none is given
+        cwd: Option<String>,
+    },
     /// Focus or spawn a command
     FocusOrSpawn { app_id: String, command: String },
     /// Scales output up/down
     ChangeScale { percentage_points: isize },
     /// Sets output scale
     SetScale { percentage: usize },
-    /// Rotate output
-    RotateOutput { output: usize, rotation: usize },
-    /// Move window to zone
-    MoveWindow { window: Option<usize>, zone: String },
+    /// Rotate or flip the output with the name, the output under the pointer if none is given
+    RotateOutput {
+        output: Option<String>,
+        transform: Transform,
+    },
+    /// Move the window with the id, or the focused window, to a zone, a position or a space
+    MoveWindow {
+        window: Option<u64>,
+        target: MoveTarget,
+    },
+    /// Exchange the window with the id, or the focused window, with the window in the zone
+    SwapWindows {
+        window: Option<u64>,
+        zone: ZoneTarget,
+    },
+    /// Focus the window with the id
+    FocusWindow { window: u64 },
+    /// Pop the window with the id, or the focused window, out of its zone into a centered
+    /// floating state, or put a floating window back
+    ToggleFloating { window: Option<u64> },
+    /// Show the window with the id, or the focused window, on every space of its output, or
+    /// keep it on its current space again
+    ToggleSticky { window: Option<u64> },
+    /// Keep the window with the id, or the focused window, above all other windows, or put it
+    /// back into the normal stacking order
+    TogglePin { window: Option<u64> },
+    /// Move the edge of the window with the id, or of the focused window, outwards by `delta`
+    /// pixels. Windows in a zone resize their zone together with its neighbors.
+    ResizeWindow {
+        window: Option<u64>,
+        edge: ResizeEdge,
+        delta: i32,
+    },
+    /// Make the window with the id, or the focused window, cover its output or leave fullscreen
+    ToggleFullscreen { window: Option<u64> },
+    /// Maximize or unmaximize the window with the id, or the focused window
+    ToggleMaximize { window: Option<u64> },
+    /// Split the focused output into a grid of zones and move its windows into them
+    QuickLayout { columns: u32, rows: u32 },
+    /// Activate the zone layout with the name, or the next zone layout, of the output with the
+    /// name, the output under the pointer if none is given
+    CycleZoneLayout {
+        output: Option<String>,
+        layout: Option<String>,
+    },
+    /// Split the zone with the name, or the zone of the focused window, into two halves
+    SplitZone {
+        zone: Option<String>,
+        axis: SplitAxis,
+    },
+    /// Merge the zone with the name, or the zone of the focused window, back with its sibling
+    MergeZone { zone: Option<String> },
+    /// Show the windows of the zone with the name, or of the zone of the focused window, as tabs
+    /// or side by side again
+    ToggleTabbed { zone: Option<String> },
+    /// Focus the window the offset of tabs away from the focused window in its zone
+    CycleTab { offset: i32 },
     /// Run Lua callback
-    Callback(LuaFunction<'static>),
-    /// Tab through windows
+    Callback(LuaCallback),
+    /// Tab through the windows of the focused space, the most recently focused first
     Tab { index: usize },
+    /// Focus the window which was focused before the focused window on its space
+    FocusPrevious,
+    /// Focus the nearest window in the direction
+    FocusDirection(Direction),
     /// Close current window
     Close,
+    /// Ask the window with the id, or the focused window, to close. Closing a window again which
+    /// did not respond kills its client.
+    CloseWindow { window: Option<u64> },
+    /// Kill the client of the window with the id, or of the focused window
+    KillWindow { window: Option<u64> },
     /// Start pipewire video stream
     StartVideoStream,
+    /// Save a PNG of the target in the screenshot directory and optionally copy it to the
+    /// clipboard. A region is selected by dragging the pointer first.
+    Screenshot {
+        target: ScreenshotTarget,
+        clipboard: bool,
+    },
+    /// Abort the region selection of a screenshot
+    CancelScreenshot,
+    /// Start or stop recording the output with the name, the output under the pointer if none
+    /// is given
+    ToggleRecording { output: Option<String> },
+    /// Change the magnification of the output under the pointer, the magnified region follows
+    /// the pointer
+    Zoom { delta: f64 },
+    /// Enter or leave the keyboard move mode for the focused window
+    ToggleMoveMode,
+    /// Move the window of the move mode by the given distance
+    MoveModeStep { x: i32, y: i32 },
+    /// Release the pointer from the lock or confinement of a client
+    BreakPointerConstraint,
+    /// Activate the keys mapped in the given mode instead of the current ones
+    EnterKeyMode { mode: String },
+    /// Show or hide the overlay with recently pressed keys and buttons
+    ToggleKeystrokeVisualizer,
+    /// Switch between the light and the dark theme
+    ToggleThemeVariant,
+    /// Enlarge the focused window by the given factor, resets it if it is already zoomed by it
+    ZoomWindow { zoom: f64 },
+    /// Show the space on the output, the output under the pointer if none is given
+    SwitchSpace {
+        space: String,
+        output: Option<String>,
+    },
+    /// Move the window with the id, or the focused window, to the space shown on the output
+    MoveWindowToOutput {
+        window: Option<u64>,
+        output: OutputTarget,
+    },
+    /// Leave the kiosk mode, the kiosk application is not restarted anymore
+    LeaveKiosk,
+    /// Show name, mode and scale on every output for a few seconds
+    IdentifyOutputs,
+    /// Show or hide a test pattern with alignment grid on every output
+    ToggleTestPattern,
+    /// Activate the next keyboard layout of the keymap
+    CycleKeyboardLayout,
+    /// Remember the spaces and zones of all windows under the name
+    SaveLayout { name: String },
+    /// Move the windows back to a saved layout, spawning missing ones with the command of their
+    /// app id
+    RestoreLayout {
+        name: String,
+        commands: HashMap<String, String>,
+    },
+    /// Show or hide the window of the named scratchpad, the command is started if it has none
+    ToggleScratchpad {
+        name: String,
+        command: String,
+        args: Vec<String>,
+    },
+    /// Run a command registered by the config with `register_command`
+    RunCommand { name: String, args: Vec<String> },
     /// Do nothing
     None,
 }
@@ -45,6 +187,12 @@ impl State {
             warn!("No action is executed, since session is locked");
             return;
         }
+        if let Err(err) = self.try_execute(action) {
+            error!(%err, "Failed to execute action");
+        }
+    }
+
+    pub fn try_execute(&mut self, action: Action) -> Result<()> {
         match action {
             Action::Quit => {
                 self.stop_loop();
@@ -57,51 +205,103 @@ impl State {
                     error!(vt, "Error switching vt: {}", err);
                 }
             }
-            Action::Spawn { command, args } => self.spawn(&command, &args),
-            Action::ChangeScale {
-                percentage_points: _,
-            } => todo!(),
-            Action::SetScale { percentage: _ } => todo!(),
-            Action::RotateOutput {
-                output: _,
-                rotation: _,
-            } => todo!(),
-            Action::MoveWindow { window: _, zone } => {
-                let (space_name, _) = self.spaces.iter().next().unwrap();
-                let keyboard = self.seat.as_ref().unwrap().get_keyboard().unwrap();
-                if let Some(focus) = keyboard.current_focus() {
-                    if let Ok(window) = WorkspaceWindow::try_from(focus) {
-                        self.place_window(
-                            &space_name.to_owned(),
-                            &window,
-                            false,
-                            Some(&zone),
-                            true,
-                        );
-                    }
+            Action::Spawn {
+                command,
+                args,
+                zone,
+                env,
+                cwd,
+            } => {
+                let pid = self.spawn(&command, &args, &env, cwd.as_deref());
+                if let (Some(pid), Some(zone)) = (pid, zone) {
+                    self.show_window_placeholder(&command, pid, zone)?;
+                }
+            }
+            Action::ChangeScale { percentage_points } => {
+                let output = self.output_under_pointer().ok_or(ScapeError::NoOutputs)?;
+                let percentage =
+                    (output.current_scale().fractional_scale() * 100.0).round() as isize;
+                let percentage = percentage.saturating_add(percentage_points).max(0) as usize;
+                self.set_output_scale(&output, percentage);
+            }
+            Action::SetScale { percentage } => {
+                let output = self.output_under_pointer().ok_or(ScapeError::NoOutputs)?;
+                self.set_output_scale(&output, percentage);
+            }
+            Action::RotateOutput { output, transform } => {
+                let output = match output {
+                    Some(output_name) => self
+                        .outputs
+                        .get(&output_name)
+                        .cloned()
+                        .ok_or(ScapeError::OutputNotFound(output_name))?,
+                    None => self.output_under_pointer().ok_or(ScapeError::NoOutputs)?,
+                };
+                self.set_output_transform(&output, transform);
+            }
+            Action::MoveWindow { window, target } => self.move_window(window, &target)?,
+            Action::SwapWindows { window, zone } => self.swap_windows(window, &zone)?,
+            Action::FocusWindow { window } => {
+                let (space_name, window) = self.window_by_id(window)?;
+                self.show_window(window, &space_name)?;
+            }
+            Action::ToggleFloating { window } => self.toggle_floating(window)?,
+            Action::ToggleSticky { window } => self.toggle_sticky(window)?,
+            Action::TogglePin { window } => self.toggle_pin(window)?,
+            Action::ResizeWindow {
+                window,
+                edge,
+                delta,
+            } => self.resize_window(window, edge, delta)?,
+            Action::ToggleFullscreen { window } => self.toggle_fullscreen(window)?,
+            Action::ToggleMaximize { window } => self.toggle_maximize(window)?,
+            Action::QuickLayout { columns, rows } => self.apply_quick_layout(columns, rows)?,
+            Action::CycleZoneLayout { output, layout } => {
+                self.cycle_zone_layout(output, layout.as_deref())?
+            }
+            Action::SplitZone { zone, axis } => self.split_zone(zone, axis)?,
+            Action::MergeZone { zone } => self.merge_zone(zone)?,
+            Action::ToggleTabbed { zone } => self.toggle_tabbed(zone)?,
+            Action::CycleTab { offset } => self.cycle_tab(offset),
+            Action::EnterKeyMode { mode } => self.enter_key_mode(mode),
+            Action::ToggleThemeVariant => self.toggle_theme_variant(),
+            Action::ToggleKeystrokeVisualizer => self.toggle_keystroke_visualizer()?,
+            Action::ZoomWindow { zoom } => {
+                let Some(focus) = self.keyboard()?.current_focus() else {
+                    return Ok(());
+                };
+                if let Ok(WorkspaceWindow::ApplicationWindow(window)) =
+                    WorkspaceWindow::try_from(focus)
+                {
+                    let zoom = if window.zoom() == zoom { 1.0 } else { zoom };
+                    window.set_zoom(zoom);
+                    info!(app_id = window.app_id(), zoom, "Window zoom changed");
+                    self.backend_data.schedule_render();
                 }
             }
             Action::Close => {
-                let (_, space) = self.spaces.iter_mut().next().unwrap();
+                let space_name = self.focused_space().ok_or(ScapeError::NoSpace)?;
+                let space = self.space_mut(&space_name)?;
                 if let Some(window) = space.elements().last().cloned() {
                     if window.close() {
                         space.unmap_elem(&window);
                     }
                 }
             }
-            Action::Tab { index } => {
-                let (space_name, space) = self.spaces.iter().next().unwrap();
-                let maybe_window = space.elements().rev().nth(index).cloned();
-                if let Some(window) = maybe_window {
-                    self.focus_window(window, &space_name.to_owned());
-                }
-            }
-            Action::Callback(callback) => callback.call(()).unwrap(),
+            Action::CloseWindow { window } => self.close_window(window)?,
+            Action::KillWindow { window } => self.kill_window(window)?,
+            Action::Tab { index } => self.focus_tab(index),
+            Action::FocusPrevious => self.focus_previous(),
+            Action::FocusDirection(direction) => self.focus_direction(direction)?,
+            Action::Callback(callback) => self.call_config("Binding callback", &callback, ()),
             Action::FocusOrSpawn { app_id, command } => {
                 if !self.focus_window_by_app_id(app_id) {
                     self.execute(Action::Spawn {
                         command,
                         args: Vec::new(),
+                        zone: None,
+                        env: Vec::new(),
+                        cwd: None,
                     });
                 }
             }
@@ -111,22 +311,20 @@ impl State {
                         Ok(pipewire) => self.pipewire = Some(pipewire),
                         Err(err) => {
                             error!("Failed to initialize pipewire: {}", err);
-                            return;
+                            return Ok(());
                         }
                     }
                 }
 
                 let Some(gbm_device) = self.backend_data.gbm_device() else {
                     error!("No gbm device available");
-                    return;
+                    return Ok(());
+                };
+                let Some(pipewire) = self.pipewire.as_ref() else {
+                    return Ok(());
                 };
 
-                match self
-                    .pipewire
-                    .as_ref()
-                    .unwrap()
-                    .start_video_stream(gbm_device)
-                {
+                match pipewire.start_video_stream(gbm_device) {
                     Ok(stream) => {
                         info!("Pipewire video stream started");
                         NODE_ID.store(stream.node_id(), Ordering::SeqCst);
@@ -135,30 +333,80 @@ impl State {
                     Err(err) => error!(?err, "Failed to start pipewire video stream"),
                 }
             }
+            Action::Screenshot { target, clipboard } => self.screenshot(target, clipboard)?,
+            Action::CancelScreenshot => self.cancel_screenshot(),
+            Action::ToggleRecording { output } => self.toggle_recording(output)?,
+            Action::Zoom { delta } => self.zoom(delta)?,
+            Action::ToggleMoveMode => self.toggle_move_mode()?,
+            Action::MoveModeStep { x, y } => self.move_mode_step((x, y).into())?,
+            Action::BreakPointerConstraint => self.break_pointer_constraint()?,
+            Action::SwitchSpace { space, output } => {
+                self.switch_space(&space, output.as_deref())?
+            }
+            Action::MoveWindowToOutput { window, output } => {
+                self.move_window_to_output(window, &output)?
+            }
+            Action::LeaveKiosk => self.leave_kiosk(),
+            Action::IdentifyOutputs => self.identify_outputs(),
+            Action::ToggleTestPattern => self.toggle_test_pattern(),
+            Action::CycleKeyboardLayout => self.cycle_keyboard_layout()?,
+            Action::SaveLayout { name } => self.save_layout(name),
+            Action::RestoreLayout { name, commands } => self.restore_layout(&name, &commands)?,
+            Action::ToggleScratchpad {
+                name,
+                command,
+                args,
+            } => self.toggle_scratchpad(name, command, args),
+            Action::RunCommand { name, args } => self.run_user_command(&name, args)?,
             Action::None => {}
         }
+        Ok(())
+    }
+
+    /// Command which connects to this compositor, both for wayland and X11 clients
+    pub fn command(&self, command: &str, args: &[String]) -> Command {
+        let mut cmd = Command::new(command);
+        cmd.args(args).envs(
+            self.socket_name
+                .clone()
+                .map(|v| ("WAYLAND_DISPLAY", v))
+                .into_iter()
+                .chain(
+                    self.xwayland_state
+                        .as_ref()
+                        .and_then(|v| v.display_number)
+                        .map(|v| ("DISPLAY", format!(":{}", v))),
+                ),
+        );
+        cmd
     }
 
-    fn spawn(&self, command: &str, args: &[String]) {
-        info!(command, "Starting program");
+    /// Starts the command and returns the id of its process. The program gets an activation
+    /// token, so it is allowed to focus its first window.
+    pub fn spawn(
+        &mut self,
+        command: &str,
+        args: &[String],
+        env: &[(String, String)],
+        cwd: Option<&str>,
+    ) -> Option<u32> {
+        info!(command, ?cwd, "Starting program");
 
-        if let Err(e) = Command::new(command)
-            .args(args)
-            .envs(
-                self.socket_name
-                    .clone()
-                    .map(|v| ("WAYLAND_DISPLAY", v))
-                    .into_iter()
-                    .chain(
-                        self.xwayland_state
-                            .as_ref()
-                            .and_then(|v| v.display_number)
-                            .map(|v| ("DISPLAY", format!(":{}", v))),
-                    ),
-            )
-            .spawn()
-        {
-            error!(command, err = %e, "Failed to start program");
+        let (token, _) = self.xdg_activation_state.create_external_token(None);
+        let mut cmd = self.command(command, args);
+        cmd.env("XDG_ACTIVATION_TOKEN", token.as_str())
+            .env("DESKTOP_STARTUP_ID", token.as_str())
+            .envs(env.iter().map(|(key, value)| (key, value)));
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+
+        match cmd.spawn() {
+            Ok(child) => Some(child.id()),
+            Err(e) => {
+                error!(command, err = %e, "Failed to start program");
+                None
+            }
         }
     }
 }
@@ -1,5 +1,6 @@
 use crate::State;
-use crate::{focus::PointerFocusTarget, ssd::HEADER_BAR_HEIGHT};
+use crate::{focus::PointerFocusTarget, ssd::HEADER_BAR_HEIGHT, stacking::StackingLayer};
+use smithay::backend::input::ButtonState;
 use smithay::input::touch::TouchTarget;
 use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
 use smithay::utils::Size;
@@ -33,6 +34,7 @@ use smithay::{
     render_elements,
     utils::{user_data::UserDataMap, IsAlive, Logical, Physical, Point, Rectangle, Scale, Serial},
     wayland::{
+        alpha_modifier::AlphaModifierSurfaceCachedState,
         compositor::{with_states, SurfaceData as WlSurfaceData},
         dmabuf::DmabufFeedback,
         seat::WaylandFocus,
@@ -40,31 +42,52 @@ use smithay::{
     },
 };
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tracing::{error, warn};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ApplicationWindow(pub Window);
 
+struct WindowOpacity {
+    focused: Cell<f32>,
+    unfocused: Cell<f32>,
+}
+
+/// Whether the window or one of its popups has the keyboard focus
+struct WindowFocused(Cell<bool>);
+
+/// Factor the content of the window is enlarged by, independent of the output scale
+struct WindowZoom(Cell<f64>);
+
+struct WindowStackingLayer(Cell<StackingLayer>);
+
+const MIN_ZOOM: f64 = 0.25;
+const MAX_ZOOM: f64 = 4.0;
+
+static NEXT_WINDOW_ID: AtomicU64 = AtomicU64::new(1);
+
+struct WindowId(u64);
+
 impl ApplicationWindow {
     pub fn surface_under(
         &self,
         location: Point<f64, Logical>,
         window_type: WindowSurfaceType,
-    ) -> Option<(PointerFocusTarget, Point<i32, Logical>)> {
+    ) -> Option<(PointerFocusTarget, Point<f64, Logical>)> {
         let state = self.decoration_state();
         if state.is_ssd && location.y < HEADER_BAR_HEIGHT as f64 {
             return Some((PointerFocusTarget::SSD(SSD(self.clone())), Point::default()));
         }
         let offset = if state.is_ssd {
-            Point::from((0, HEADER_BAR_HEIGHT))
+            Point::from((0.0, HEADER_BAR_HEIGHT as f64))
         } else {
             Point::default()
         };
 
-        let surface_under = self
-            .0
-            .surface_under(location - offset.to_f64(), window_type);
+        let content_location = (location - offset).downscale(self.zoom());
+        let surface_under = self.0.surface_under(content_location, window_type);
         let (under, loc) = match self.0.underlying_surface() {
             WindowSurface::Wayland(_) => {
                 surface_under.map(|(surface, loc)| (PointerFocusTarget::WlSurface(surface), loc))
@@ -73,7 +96,10 @@ impl ApplicationWindow {
                 surface_under.map(|(_, loc)| (PointerFocusTarget::X11Surface(s.clone()), loc))
             }
         }?;
-        Some((under, loc + offset))
+        // Clients receive the position relative to the focus location, which has to be moved for
+        // zoomed windows, so the client sees the unscaled position in its surface
+        let surface_location = content_location - loc.to_f64();
+        Some((under, location - surface_location))
     }
 
     pub fn with_surfaces<F>(&self, processor: F)
@@ -138,6 +164,21 @@ impl ApplicationWindow {
         self.0.wl_surface()
     }
 
+    /// Opacity multiplier requested by the client through wp_alpha_modifier_v1
+    pub fn alpha_multiplier(&self) -> f32 {
+        self.wl_surface()
+            .and_then(|surface| {
+                with_states(&surface, |states| {
+                    states
+                        .cached_state
+                        .get::<AlphaModifierSurfaceCachedState>()
+                        .current()
+                        .multiplier_f32()
+                })
+            })
+            .unwrap_or(1.0)
+    }
+
     pub fn user_data(&self) -> &UserDataMap {
         self.0.user_data()
     }
@@ -159,6 +200,110 @@ impl ApplicationWindow {
         }
     }
 
+    pub fn title(&self) -> String {
+        match self.0.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => with_states(toplevel.wl_surface(), |states| {
+                states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .title
+                    .clone()
+                    .unwrap_or_default()
+            }),
+            WindowSurface::X11(x11_surface) => x11_surface.title(),
+        }
+    }
+
+    /// Identifier of the window, unique for the lifetime of the compositor
+    pub fn id(&self) -> u64 {
+        self.user_data()
+            .insert_if_missing(|| WindowId(NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed)));
+        self.user_data().get::<WindowId>().unwrap().0
+    }
+
+    /// Opacity of the whole window, set by window rules. It depends on whether the window is
+    /// focused.
+    pub fn opacity(&self) -> f32 {
+        self.user_data()
+            .get::<WindowOpacity>()
+            .map(|opacity| {
+                if self.is_focused() {
+                    opacity.focused.get()
+                } else {
+                    opacity.unfocused.get()
+                }
+            })
+            .unwrap_or(1.0)
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.user_data()
+            .get::<WindowFocused>()
+            .is_some_and(|focused| focused.0.get())
+    }
+
+    pub fn set_focused(&self, focused: bool) {
+        self.user_data()
+            .insert_if_missing(|| WindowFocused(Cell::new(false)));
+        self.user_data()
+            .get::<WindowFocused>()
+            .unwrap()
+            .0
+            .set(focused);
+    }
+
+    /// Factor the window is enlarged by, see [`ApplicationWindow::set_zoom`]
+    pub fn zoom(&self) -> f64 {
+        self.user_data()
+            .get::<WindowZoom>()
+            .map(|zoom| zoom.0.get())
+            .unwrap_or(1.0)
+    }
+
+    /// Renders the window enlarged by `zoom` without the client noticing, input is transformed
+    /// accordingly. Meant for applications with a tiny user interface.
+    pub fn set_zoom(&self, zoom: f64) {
+        self.user_data()
+            .insert_if_missing(|| WindowZoom(Cell::new(1.0)));
+        self.user_data()
+            .get::<WindowZoom>()
+            .unwrap()
+            .0
+            .set(zoom.clamp(MIN_ZOOM, MAX_ZOOM));
+    }
+
+    pub fn stacking_layer(&self) -> StackingLayer {
+        self.user_data()
+            .get::<WindowStackingLayer>()
+            .map(|layer| layer.0.get())
+            .unwrap_or_default()
+    }
+
+    /// Moves the window into another layer of the stacking order, it has to be raised in its
+    /// space afterwards for the space to sort it in
+    pub fn set_stacking_layer(&self, layer: StackingLayer) {
+        self.user_data()
+            .insert_if_missing(|| WindowStackingLayer(Cell::new(StackingLayer::Normal)));
+        self.user_data()
+            .get::<WindowStackingLayer>()
+            .unwrap()
+            .0
+            .set(layer);
+    }
+
+    pub fn set_opacity(&self, focused: f32, unfocused: f32) {
+        self.user_data().insert_if_missing(|| WindowOpacity {
+            focused: Cell::new(1.0),
+            unfocused: Cell::new(1.0),
+        });
+        let opacity = self.user_data().get::<WindowOpacity>().unwrap();
+        opacity.focused.set(focused.clamp(0.0, 1.0));
+        opacity.unfocused.set(unfocused.clamp(0.0, 1.0));
+    }
+
     pub fn close(&self) {
         match self.0.underlying_surface() {
             WindowSurface::Wayland(toplevel) => toplevel.send_close(),
@@ -201,6 +346,82 @@ impl ApplicationWindow {
         }
     }
 
+    /// Marks the window as fullscreen, the new state is sent with the next configure
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        match self.0.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => toplevel.with_pending_state(|state| {
+                if fullscreen {
+                    state.states.set(xdg_toplevel::State::Fullscreen);
+                } else {
+                    state.states.unset(xdg_toplevel::State::Fullscreen);
+                }
+            }),
+            WindowSurface::X11(x11_surface) => {
+                if let Err(e) = x11_surface.set_fullscreen(fullscreen) {
+                    warn!(%e, "Unable to set fullscreen state of x11 surface");
+                }
+            }
+        }
+    }
+
+    /// Whether the window is fullscreen, including a state which was not sent yet
+    pub fn is_fullscreen(&self) -> bool {
+        match self.0.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => toplevel
+                .with_pending_state(|state| state.states.contains(xdg_toplevel::State::Fullscreen)),
+            WindowSurface::X11(x11_surface) => x11_surface.is_fullscreen(),
+        }
+    }
+
+    /// Whether the window is maximized, including a state which was not sent yet
+    pub fn is_maximized(&self) -> bool {
+        match self.0.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => toplevel
+                .with_pending_state(|state| state.states.contains(xdg_toplevel::State::Maximized)),
+            WindowSurface::X11(x11_surface) => x11_surface.is_maximized(),
+        }
+    }
+
+    /// Marks the window as maximized, the new state is sent with the next configure
+    pub fn set_maximized(&self, maximized: bool) {
+        match self.0.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => toplevel.with_pending_state(|state| {
+                if maximized {
+                    state.states.set(xdg_toplevel::State::Maximized);
+                } else {
+                    state.states.unset(xdg_toplevel::State::Maximized);
+                }
+            }),
+            WindowSurface::X11(x11_surface) => {
+                if let Err(e) = x11_surface.set_maximized(maximized) {
+                    warn!(%e, "Unable to set maximized state of x11 surface");
+                }
+            }
+        }
+    }
+
+    /// Tells the client whether the window is hidden, so it can stop rendering. X11 clients
+    /// have no equivalent and keep rendering.
+    pub fn set_suspended(&self, suspended: bool) {
+        let WindowSurface::Wayland(toplevel) = self.0.underlying_surface() else {
+            return;
+        };
+        let changed = toplevel.with_pending_state(|state| {
+            if state.states.contains(xdg_toplevel::State::Suspended) == suspended {
+                return false;
+            }
+            if suspended {
+                state.states.set(xdg_toplevel::State::Suspended);
+            } else {
+                state.states.unset(xdg_toplevel::State::Suspended);
+            }
+            true
+        });
+        if changed && toplevel.is_initial_configure_sent() {
+            toplevel.send_pending_configure();
+        }
+    }
+
     pub fn resize(&self, location: Point<i32, Logical>, size: Size<i32, Logical>) {
         match &self.0.underlying_surface() {
             WindowSurface::Wayland(xdg) => {
@@ -261,10 +482,10 @@ impl PointerTarget<State> for SSD {
         }
     }
 
-    fn motion(&self, _seat: &Seat<State>, _data: &mut State, event: &MotionEvent) {
+    fn motion(&self, seat: &Seat<State>, data: &mut State, event: &MotionEvent) {
         let mut state = self.0.decoration_state();
         if state.is_ssd {
-            state.header_bar.pointer_enter(event.location);
+            state.header_bar.moved(seat, data, &self.0, event.location);
         }
     }
 
@@ -276,10 +497,13 @@ impl PointerTarget<State> for SSD {
     ) {
     }
 
-    fn button(&self, seat: &Seat<State>, data: &mut State, event: &ButtonEvent) {
+    fn button(&self, _seat: &Seat<State>, data: &mut State, event: &ButtonEvent) {
         let mut state = self.0.decoration_state();
         if state.is_ssd {
-            state.header_bar.clicked(seat, data, &self.0, event.serial);
+            match event.state {
+                ButtonState::Pressed => state.header_bar.pressed(event.serial),
+                ButtonState::Released => state.header_bar.released(data, &self.0),
+            }
         }
     }
 
@@ -362,43 +586,41 @@ impl PointerTarget<State> for SSD {
 impl TouchTarget<State> for SSD {
     fn down(
         &self,
-        seat: &Seat<State>,
-        data: &mut State,
+        _seat: &Seat<State>,
+        _data: &mut State,
         event: &smithay::input::touch::DownEvent,
         _seq: Serial,
     ) {
         let mut state = self.0.decoration_state();
         if state.is_ssd {
             state.header_bar.pointer_enter(event.location);
-            state
-                .header_bar
-                .touch_down(seat, data, &self.0, event.serial);
+            state.header_bar.pressed(event.serial);
         }
     }
 
     fn up(
         &self,
-        seat: &Seat<State>,
+        _seat: &Seat<State>,
         data: &mut State,
-        event: &smithay::input::touch::UpEvent,
+        _event: &smithay::input::touch::UpEvent,
         _seq: Serial,
     ) {
         let mut state = self.0.decoration_state();
         if state.is_ssd {
-            state.header_bar.touch_up(seat, data, &self.0, event.serial);
+            state.header_bar.released(data, &self.0);
         }
     }
 
     fn motion(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
+        seat: &Seat<State>,
+        data: &mut State,
         event: &smithay::input::touch::MotionEvent,
         _seq: Serial,
     ) {
         let mut state = self.0.decoration_state();
         if state.is_ssd {
-            state.header_bar.pointer_enter(event.location);
+            state.header_bar.moved(seat, data, &self.0, event.location);
         }
     }
 
@@ -427,7 +649,7 @@ impl TouchTarget<State> for SSD {
 
 impl SpaceElement for ApplicationWindow {
     fn geometry(&self) -> Rectangle<i32, Logical> {
-        let mut geo = SpaceElement::geometry(&self.0);
+        let mut geo = zoom_rect(SpaceElement::geometry(&self.0), self.zoom());
         if self.decoration_state().is_ssd {
             geo.size.h += HEADER_BAR_HEIGHT;
         }
@@ -435,7 +657,7 @@ impl SpaceElement for ApplicationWindow {
     }
 
     fn bbox(&self) -> Rectangle<i32, Logical> {
-        let mut bbox = SpaceElement::bbox(&self.0);
+        let mut bbox = zoom_rect(SpaceElement::bbox(&self.0), self.zoom());
         if self.decoration_state().is_ssd {
             bbox.size.h += HEADER_BAR_HEIGHT;
         }
@@ -443,19 +665,23 @@ impl SpaceElement for ApplicationWindow {
     }
 
     fn is_in_input_region(&self, point: &Point<f64, Logical>) -> bool {
+        let zoom = self.zoom();
         if self.decoration_state().is_ssd {
             point.y < HEADER_BAR_HEIGHT as f64
                 || SpaceElement::is_in_input_region(
                     &self.0,
-                    &(*point - Point::from((0.0, HEADER_BAR_HEIGHT as f64))),
+                    &(*point - Point::from((0.0, HEADER_BAR_HEIGHT as f64))).downscale(zoom),
                 )
         } else {
-            SpaceElement::is_in_input_region(&self.0, point)
+            SpaceElement::is_in_input_region(&self.0, &point.downscale(zoom))
         }
     }
 
     fn z_index(&self) -> u8 {
-        SpaceElement::z_index(&self.0)
+        match self.stacking_layer() {
+            StackingLayer::Normal => SpaceElement::z_index(&self.0),
+            layer => layer.z_index(),
+        }
     }
 
     fn set_activate(&self, activated: bool) {
@@ -505,32 +731,52 @@ where
         alpha: f32,
     ) -> Vec<C> {
         let window_bbox = SpaceElement::bbox(&self.0);
+        let alpha = alpha * self.opacity();
+        let zoom = self.zoom();
+        // The header bar keeps its size, only the content of the window is enlarged
+        let content_scale = Scale::from((scale.x * zoom, scale.y * zoom));
 
         if self.decoration_state().is_ssd && !window_bbox.is_empty() {
-            let window_geo = SpaceElement::geometry(&self.0);
+            let window_geo = zoom_rect(SpaceElement::geometry(&self.0), zoom);
 
             let mut state = self.decoration_state();
             let width = window_geo.size.w;
             state.header_bar.redraw(width as u32);
+            // The surface tree applies the alpha modifier itself, but the header bar is ours
             let mut vec = AsRenderElements::<R>::render_elements::<WindowRenderElement<R>>(
                 &state.header_bar,
                 renderer,
                 location,
                 scale,
-                alpha,
+                alpha * self.alpha_multiplier(),
             );
 
             location.y += (scale.y * HEADER_BAR_HEIGHT as f64) as i32;
 
-            let window_elements =
-                AsRenderElements::render_elements(&self.0, renderer, location, scale, alpha);
+            let window_elements = AsRenderElements::render_elements(
+                &self.0,
+                renderer,
+                location,
+                content_scale,
+                alpha,
+            );
             vec.extend(window_elements);
             vec.into_iter().map(C::from).collect()
         } else {
-            AsRenderElements::render_elements(&self.0, renderer, location, scale, alpha)
+            AsRenderElements::render_elements(&self.0, renderer, location, content_scale, alpha)
                 .into_iter()
                 .map(C::from)
                 .collect()
         }
     }
 }
+
+fn zoom_rect(rect: Rectangle<i32, Logical>, zoom: f64) -> Rectangle<i32, Logical> {
+    if zoom == 1.0 {
+        return rect;
+    }
+    Rectangle::from_loc_and_size(
+        rect.loc.to_f64().upscale(zoom).to_i32_round(),
+        rect.size.to_f64().upscale(zoom).to_i32_round(),
+    )
+}
@@ -0,0 +1,311 @@
+//! Built-in bar along the top edge of every output. It shows an indicator for every space and an
+//! entry for every window on the space shown on the output. Clicking an indicator shows its space,
+//! clicking an entry focuses its window and hovering an entry shows a thumbnail of the window
+//! below it. Dragging a window onto an indicator moves it to the space. The bar covers the top of
+//! the output, zones should leave room for it.
+
+use crate::{
+    render::CustomRenderElements, state::active_space, thumbnail::WindowThumbnail,
+    workspace_window::WorkspaceWindow, State,
+};
+use smithay::{
+    backend::renderer::{
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            Kind,
+        },
+        ImportAll, ImportMem, Renderer,
+    },
+    desktop::Space,
+    output::Output,
+    utils::{Logical, Point, Rectangle, Scale},
+};
+use tracing::{info, warn};
+
+/// Height of the bar in logical pixels
+pub const BAR_HEIGHT: i32 = 24;
+/// Width of a space indicator
+const INDICATOR_WIDTH: i32 = 24;
+/// Width of a window entry if there is enough room
+const ENTRY_WIDTH: i32 = 160;
+/// Space around and between the items of the bar
+const ITEM_GAP: i32 = 2;
+/// Area below a hovered entry the thumbnail of its window is fitted into
+const THUMBNAIL_SIZE: (i32, i32) = (320, 200);
+
+/// Something in the bar which reacts to the pointer
+#[derive(Debug, Clone, PartialEq)]
+pub enum BarItem {
+    Space(String),
+    Window {
+        space_name: String,
+        window: WorkspaceWindow,
+    },
+}
+
+#[derive(Debug)]
+struct BarEntry {
+    item: BarItem,
+    geometry: Rectangle<i32, Logical>,
+    buffer: SolidColorBuffer,
+}
+
+/// Bar of one output
+#[derive(Debug)]
+pub struct Bar {
+    output: Output,
+    geometry: Rectangle<i32, Logical>,
+    background: SolidColorBuffer,
+    entries: Vec<BarEntry>,
+}
+
+impl Bar {
+    pub fn render_elements<R>(
+        &self,
+        space: &Space<WorkspaceWindow>,
+        output: &Output,
+    ) -> Vec<CustomRenderElements<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+    {
+        if self.output != *output {
+            return Vec::new();
+        }
+        let Some(output_geometry) = space.output_geometry(output) else {
+            return Vec::new();
+        };
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let element = |buffer: &SolidColorBuffer, geometry: Rectangle<i32, Logical>| {
+            CustomRenderElements::Solid(SolidColorRenderElement::from_buffer(
+                buffer,
+                (geometry.loc - output_geometry.loc).to_physical_precise_round(scale),
+                scale,
+                1.0,
+                Kind::Unspecified,
+            ))
+        };
+        // Entries first, they are drawn above the background
+        self.entries
+            .iter()
+            .map(|entry| element(&entry.buffer, entry.geometry))
+            .chain(std::iter::once(element(&self.background, self.geometry)))
+            .collect()
+    }
+}
+
+impl State {
+    /// Shows or hides the bar on all outputs
+    pub fn set_bar(&mut self, enabled: bool) {
+        info!(enabled, "Setting bar");
+        self.bar_enabled = enabled;
+        if !enabled {
+            self.bars.clear();
+            self.update_bar_hover();
+        }
+        self.backend_data.schedule_render();
+    }
+
+    /// Whether the location is on a bar, the pointer does not reach clients there
+    pub fn bar_contains(&self, location: Point<f64, Logical>) -> bool {
+        self.bars
+            .iter()
+            .any(|bar| bar.geometry.to_f64().contains(location))
+    }
+
+    /// Item of a bar at the location and its geometry
+    pub fn bar_item_at(
+        &self,
+        location: Point<f64, Logical>,
+    ) -> Option<(BarItem, Rectangle<i32, Logical>)> {
+        self.bars
+            .iter()
+            .flat_map(|bar| &bar.entries)
+            .find(|entry| entry.geometry.to_f64().contains(location))
+            .map(|entry| (entry.item.clone(), entry.geometry))
+    }
+
+    /// Handles a click on a bar, returns false if the location is not on a bar
+    pub fn click_bar(&mut self, location: Point<f64, Logical>) -> bool {
+        if !self.bar_contains(location) {
+            return false;
+        }
+        match self.bar_item_at(location) {
+            Some((BarItem::Space(space_name), _)) => {
+                let output = self
+                    .bars
+                    .iter()
+                    .find(|bar| bar.geometry.to_f64().contains(location))
+                    .map(|bar| bar.output.name());
+                if let Err(err) = self.switch_space(&space_name, output.as_deref()) {
+                    warn!(?err, space_name, "Unable to switch space from the bar");
+                }
+            }
+            Some((BarItem::Window { space_name, window }, _)) => {
+                self.focus_window(window, &space_name);
+            }
+            None => {}
+        }
+        true
+    }
+
+    /// Shows a thumbnail of the window whose entry is under the pointer, and hides it again once
+    /// the pointer leaves the entry. Called after every pointer motion.
+    pub fn update_bar_hover(&mut self) {
+        let hovered = self
+            .bar_item_at(self.pointer_location())
+            .and_then(|(item, geometry)| match item {
+                BarItem::Window { window, .. } => Some((window, geometry)),
+                BarItem::Space(_) => None,
+            });
+        if hovered.as_ref().map(|(window, _)| window) == self.bar_hover.as_ref() {
+            return;
+        }
+
+        match hovered {
+            Some((window, geometry)) => {
+                let thumbnail_geometry = Rectangle::from_loc_and_size(
+                    (geometry.loc.x, geometry.loc.y + geometry.size.h + ITEM_GAP),
+                    THUMBNAIL_SIZE,
+                );
+                self.bar_hover = Some(window.clone());
+                self.window_thumbnail = Some(WindowThumbnail {
+                    window,
+                    geometry: thumbnail_geometry,
+                });
+                self.backend_data.schedule_render();
+            }
+            None => {
+                // Only the thumbnail shown by the bar is hidden, not one shown by the config
+                let hover = self.bar_hover.take();
+                if self
+                    .window_thumbnail
+                    .as_ref()
+                    .is_some_and(|thumbnail| Some(&thumbnail.window) == hover.as_ref())
+                {
+                    self.hide_window_thumbnail();
+                }
+            }
+        }
+    }
+
+    /// Updates the bars of the outputs before they are rendered
+    pub fn refresh_bars(&mut self) {
+        if !self.bar_enabled {
+            return;
+        }
+        let mut previous = std::mem::take(&mut self.bars);
+        let palette = *self.theme.palette();
+        let focused = self.focused_window();
+        let mut space_names = self.spaces.keys().cloned().collect::<Vec<_>>();
+        space_names.sort();
+
+        let mut bars = Vec::new();
+        for output in self.outputs.values() {
+            let Some(active) = active_space(output) else {
+                continue;
+            };
+            let Some(space) = self.spaces.get(&active) else {
+                continue;
+            };
+            let output_geometry = self.output_rect(output);
+            let geometry = Rectangle::from_loc_and_size(
+                output_geometry.loc,
+                (output_geometry.size.w, BAR_HEIGHT),
+            );
+            let item_height = BAR_HEIGHT - 2 * ITEM_GAP;
+
+            let mut items = space_names
+                .iter()
+                .enumerate()
+                .map(|(index, space_name)| {
+                    let x = geometry.loc.x + ITEM_GAP + index as i32 * (INDICATOR_WIDTH + ITEM_GAP);
+                    let color = if *space_name == active {
+                        palette.border_focused
+                    } else {
+                        palette.border
+                    };
+                    (
+                        BarItem::Space(space_name.clone()),
+                        Rectangle::from_loc_and_size(
+                            (x, geometry.loc.y + ITEM_GAP),
+                            (INDICATOR_WIDTH, item_height),
+                        ),
+                        color,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let mut windows = space
+                .elements()
+                .filter(|window| matches!(window, WorkspaceWindow::ApplicationWindow(_)))
+                .filter(|window| space.outputs_for_element(window).contains(output))
+                .cloned()
+                .collect::<Vec<_>>();
+            windows.sort_by_key(WorkspaceWindow::id);
+            if !windows.is_empty() {
+                // Windows start after a wider gap behind the indicators
+                let start = geometry.loc.x
+                    + ITEM_GAP
+                    + space_names.len() as i32 * (INDICATOR_WIDTH + ITEM_GAP)
+                    + INDICATOR_WIDTH;
+                let room = geometry.loc.x + geometry.size.w - start;
+                let width = (room / windows.len() as i32 - ITEM_GAP).clamp(1, ENTRY_WIDTH);
+                for (index, window) in windows.into_iter().enumerate() {
+                    let color = if focused.as_ref() == Some(&window) {
+                        palette.border_focused
+                    } else {
+                        palette.header_bar
+                    };
+                    items.push((
+                        BarItem::Window {
+                            space_name: active.clone(),
+                            window,
+                        },
+                        Rectangle::from_loc_and_size(
+                            (
+                                start + index as i32 * (width + ITEM_GAP),
+                                geometry.loc.y + ITEM_GAP,
+                            ),
+                            (width, item_height),
+                        ),
+                        color,
+                    ));
+                }
+            }
+
+            // The buffers are kept, so that an unchanged bar causes no damage
+            let mut bar = previous
+                .iter()
+                .position(|bar| bar.output == *output)
+                .map(|index| previous.swap_remove(index))
+                .unwrap_or_else(|| Bar {
+                    output: output.clone(),
+                    geometry,
+                    background: SolidColorBuffer::default(),
+                    entries: Vec::new(),
+                });
+            bar.geometry = geometry;
+            bar.background.update(geometry.size, palette.background);
+            let mut buffers = std::mem::take(&mut bar.entries)
+                .into_iter()
+                .map(|entry| entry.buffer)
+                .collect::<Vec<_>>();
+            buffers.resize_with(items.len(), SolidColorBuffer::default);
+            bar.entries = items
+                .into_iter()
+                .zip(buffers)
+                .map(|((item, geometry, color), mut buffer)| {
+                    buffer.update(geometry.size, color);
+                    BarEntry {
+                        item,
+                        geometry,
+                        buffer,
+                    }
+                })
+                .collect();
+            bars.push(bar);
+        }
+        self.bars = bars;
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{config::LuaCallback, State};
+use calloop::{generic::Generic, Interest, Mode, PostAction};
+use smithay::wayland::selection::{
+    data_device::{
+        current_data_device_selection_userdata, request_data_device_client_selection,
+        set_data_device_selection,
+    },
+    SelectionTarget,
+};
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Write},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    sync::Arc,
+    thread,
+};
+use tracing::{info, warn};
+
+/// Mime types of text, in the order they are requested from the owner of the clipboard
+const TEXT_MIME_TYPES: [&str; 4] = [
+    "text/plain;charset=utf-8",
+    "text/plain",
+    "UTF8_STRING",
+    "STRING",
+];
+/// Mime type of screenshots offered in the clipboard
+const IMAGE_MIME_TYPE: &str = "image/png";
+/// Clipboards larger than this are not read
+const MAX_CLIPBOARD_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Selection which the compositor offers in place of a wayland client
+#[derive(Debug, Clone)]
+pub enum SelectionData {
+    /// Selection of an X11 client, transferred through Xwayland
+    Xwayland,
+    /// Text set by the config
+    Text(Arc<str>),
+    /// PNG image of a screenshot
+    Image(Arc<[u8]>),
+}
+
+/// Pipe of which only the reading end is non-blocking, the writing end is handed to clients
+fn pipe() -> io::Result<(File, OwnedFd)> {
+    let mut fds = [0; 2];
+    // SAFETY: the array is valid for writes of two file descriptors
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: pipe2 succeeded, so both file descriptors are open and owned by nobody else
+    let (reader, writer) = unsafe { (File::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    // SAFETY: the file descriptor stays open as long as `reader` lives
+    let res = unsafe {
+        let flags = libc::fcntl(reader.as_raw_fd(), libc::F_GETFL);
+        libc::fcntl(reader.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK)
+    };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((reader, writer))
+}
+
+/// Writes text set by the config to a client which pastes it
+pub fn send_text(text: Arc<str>, fd: OwnedFd) {
+    send_bytes(text.into(), fd);
+}
+
+/// Writes a screenshot to a client which pastes it
+pub fn send_image(png: Arc<[u8]>, fd: OwnedFd) {
+    send_bytes(png, fd);
+}
+
+fn send_bytes(bytes: Arc<[u8]>, fd: OwnedFd) {
+    // The client may read slowly or not at all, the compositor must not wait for it
+    thread::spawn(move || {
+        if let Err(err) = File::from(fd).write_all(&bytes) {
+            warn!(?err, "Unable to send clipboard");
+        }
+    });
+}
+
+impl State {
+    /// Offers the text to clients until another one sets the clipboard
+    pub fn set_clipboard(&mut self, text: String) {
+        info!(len = text.len(), "Setting clipboard");
+        let mime_types = TEXT_MIME_TYPES.map(str::to_string).to_vec();
+        self.offer_selection(mime_types, SelectionData::Text(text.into()));
+    }
+
+    /// Offers the PNG image to clients until another one sets the clipboard
+    pub fn set_clipboard_image(&mut self, png: Vec<u8>) {
+        info!(len = png.len(), "Setting clipboard to image");
+        let mime_types = vec![IMAGE_MIME_TYPE.to_string()];
+        self.offer_selection(mime_types, SelectionData::Image(png.into()));
+    }
+
+    fn offer_selection(&mut self, mime_types: Vec<String>, data: SelectionData) {
+        if let Some(xwm) = self.xwayland_state.as_mut().and_then(|x| x.wm.as_mut()) {
+            let res = xwm.new_selection(SelectionTarget::Clipboard, Some(mime_types.clone()));
+            if let Err(err) = res {
+                warn!(?err, "Failed to set Xwayland clipboard");
+            }
+        }
+        let Ok(seat) = self.seat() else {
+            warn!("Unable to set clipboard without a seat");
+            return;
+        };
+        set_data_device_selection(&self.display_handle, seat, mime_types, data);
+    }
+
+    /// Reads the text of the clipboard and passes it to the callback, `nil` is passed if the
+    /// clipboard holds no text
+    pub fn get_clipboard(&mut self, callback: LuaCallback) {
+        let Ok(seat) = self.seat() else {
+            warn!("Unable to get clipboard without a seat");
+            self.call_config("Clipboard callback", &callback, None::<String>);
+            return;
+        };
+        let selection = current_data_device_selection_userdata(seat);
+        if let Some(SelectionData::Text(text)) = &selection {
+            let text = text.to_string();
+            self.call_config("Clipboard callback", &callback, text);
+            return;
+        }
+
+        let (reader, writer) = match pipe() {
+            Ok(pipe) => pipe,
+            Err(err) => {
+                warn!(?err, "Unable to create pipe for the clipboard");
+                self.call_config("Clipboard callback", &callback, None::<String>);
+                return;
+            }
+        };
+        let requested = match selection {
+            Some(SelectionData::Xwayland) => {
+                let loop_handle = self.loop_handle.clone();
+                self.xwayland_state
+                    .as_mut()
+                    .and_then(|x| x.wm.as_mut())
+                    .is_some_and(|xwm| {
+                        xwm.send_selection(
+                            SelectionTarget::Clipboard,
+                            TEXT_MIME_TYPES[0].to_string(),
+                            writer,
+                            loop_handle,
+                        )
+                        .is_ok()
+                    })
+            }
+            Some(SelectionData::Image(_)) => false,
+            // The mime types of the client are unknown, the first one it accepts is read
+            _ => TEXT_MIME_TYPES.iter().any(|mime_type| {
+                writer.try_clone().is_ok_and(|fd| {
+                    request_data_device_client_selection(seat, mime_type.to_string(), fd).is_ok()
+                })
+            }),
+        };
+        if !requested {
+            info!("Clipboard holds no text");
+            self.call_config("Clipboard callback", &callback, None::<String>);
+            return;
+        }
+        self.read_clipboard(reader, callback);
+    }
+
+    /// Collects what the owner of the clipboard writes into the pipe until it closes it
+    fn read_clipboard(&mut self, reader: File, callback: LuaCallback) {
+        let mut content = Vec::new();
+        let mut callback = Some(callback);
+        let res = self.loop_handle.insert_source(
+            Generic::new(reader, Interest::READ, Mode::Level),
+            move |_, reader, state| {
+                let mut reader: &File = reader.as_ref();
+                let mut chunk = [0; 4096];
+                let text = loop {
+                    match reader.read(&mut chunk) {
+                        Ok(0) => break Some(String::from_utf8_lossy(&content).into_owned()),
+                        Ok(read) if content.len() + read > MAX_CLIPBOARD_LENGTH => {
+                            warn!("Clipboard is too large to be read");
+                            break None;
+                        }
+                        Ok(read) => content.extend_from_slice(&chunk[..read]),
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                            return Ok(PostAction::Continue)
+                        }
+                        Err(err) => {
+                            warn!(?err, "Error reading clipboard");
+                            break None;
+                        }
+                    }
+                };
+                if let Some(callback) = callback.take() {
+                    state.call_config("Clipboard callback", &callback, text);
+                }
+                Ok(PostAction::Remove)
+            },
+        );
+        if let Err(err) = res {
+            warn!(?err, "Unable to read clipboard");
+        }
+    }
+}
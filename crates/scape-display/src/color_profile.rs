@@ -0,0 +1,380 @@
+use crate::State;
+use anyhow::{bail, ensure, Context, Result};
+use std::{fs, path::PathBuf};
+use tracing::info;
+
+const ICC_HEADER_SIZE: usize = 128;
+const ICC_SIGNATURE: &[u8; 4] = b"acsp";
+const TAG_VCGT: &[u8; 4] = b"vcgt";
+const TAG_DESC: &[u8; 4] = b"desc";
+const VCGT_TYPE_TABLE: u32 = 0;
+const VCGT_TYPE_FORMULA: u32 = 1;
+
+/// Video card gamma table of an ICC profile, one curve per channel with values from 0 to 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vcgt {
+    red: Vec<f64>,
+    green: Vec<f64>,
+    blue: Vec<f64>,
+}
+
+/// Gamma ramp in the format expected by DRM
+#[derive(Debug, Clone, PartialEq)]
+pub struct GammaRamp {
+    pub red: Vec<u16>,
+    pub green: Vec<u16>,
+    pub blue: Vec<u16>,
+}
+
+impl GammaRamp {
+    pub fn linear(size: usize) -> Self {
+        let channel = (0..size).map(|i| ramp_value(i, size)).collect::<Vec<_>>();
+        GammaRamp {
+            red: channel.clone(),
+            green: channel.clone(),
+            blue: channel,
+        }
+    }
+}
+
+/// An ICC profile assigned to an output
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorProfile {
+    pub path: PathBuf,
+    pub description: Option<String>,
+    vcgt: Option<Vcgt>,
+}
+
+impl ColorProfile {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let data = fs::read(&path)
+            .with_context(|| format!("Unable to read color profile {}", path.display()))?;
+        Self::parse(path, &data)
+    }
+
+    fn parse(path: PathBuf, data: &[u8]) -> Result<Self> {
+        ensure!(
+            data.len() >= ICC_HEADER_SIZE + 4,
+            "Color profile is too short"
+        );
+        ensure!(&data[36..40] == ICC_SIGNATURE, "Not an ICC profile");
+
+        let tag_count = read_u32(data, ICC_HEADER_SIZE)? as usize;
+        let mut vcgt = None;
+        let mut description = None;
+        for index in 0..tag_count {
+            let entry = ICC_HEADER_SIZE + 4 + index * 12;
+            let signature = data.get(entry..entry + 4).context("Truncated tag table")?;
+            let offset = read_u32(data, entry + 4)? as usize;
+            let size = read_u32(data, entry + 8)? as usize;
+            let tag = data
+                .get(offset..offset.saturating_add(size))
+                .context("Tag exceeds color profile")?;
+
+            if signature == TAG_VCGT {
+                vcgt = Some(parse_vcgt(tag)?);
+            } else if signature == TAG_DESC {
+                description = parse_description(tag);
+            }
+        }
+
+        Ok(ColorProfile {
+            path,
+            description,
+            vcgt,
+        })
+    }
+
+    /// The gamma ramp with `size` entries per channel, which loads the video card gamma table of
+    /// the profile. Profiles without such a table result in a linear ramp.
+    pub fn gamma_ramp(&self, size: usize) -> GammaRamp {
+        let Some(vcgt) = &self.vcgt else {
+            return GammaRamp::linear(size);
+        };
+        GammaRamp {
+            red: resample(&vcgt.red, size),
+            green: resample(&vcgt.green, size),
+            blue: resample(&vcgt.blue, size),
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = data
+        .get(offset..)
+        .and_then(|data| data.get(..2))
+        .context("Unexpected end of tag")?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data
+        .get(offset..)
+        .and_then(|data| data.get(..4))
+        .context("Unexpected end of tag")?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads a s15Fixed16Number
+fn read_fixed(data: &[u8], offset: usize) -> Result<f64> {
+    Ok(read_u32(data, offset)? as i32 as f64 / 65536.0)
+}
+
+fn parse_vcgt(tag: &[u8]) -> Result<Vcgt> {
+    ensure!(
+        tag.get(0..4) == Some(TAG_VCGT.as_slice()),
+        "Invalid vcgt tag type"
+    );
+
+    match read_u32(tag, 8)? {
+        VCGT_TYPE_TABLE => {
+            let channels = read_u16(tag, 12)? as usize;
+            let entries = read_u16(tag, 14)? as usize;
+            let entry_size = read_u16(tag, 16)? as usize;
+            ensure!(entries > 1, "vcgt table needs at least two entries");
+
+            let read_entry = |index: usize| -> Result<f64> {
+                let offset = 18 + index * entry_size;
+                match entry_size {
+                    1 => Ok(*tag.get(offset).context("Unexpected end of tag")? as f64 / 255.0),
+                    2 => Ok(read_u16(tag, offset)? as f64 / 65535.0),
+                    _ => bail!("Unsupported vcgt entry size {entry_size}"),
+                }
+            };
+            let read_channel = |channel: usize| -> Result<Vec<f64>> {
+                // Single channel tables apply the same curve to all channels
+                let channel = if channels == 1 { 0 } else { channel };
+                (0..entries)
+                    .map(|index| read_entry(channel * entries + index))
+                    .collect()
+            };
+            ensure!(
+                channels == 1 || channels == 3,
+                "Unsupported vcgt channel count"
+            );
+
+            Ok(Vcgt {
+                red: read_channel(0)?,
+                green: read_channel(1)?,
+                blue: read_channel(2)?,
+            })
+        }
+        VCGT_TYPE_FORMULA => {
+            let read_channel = |channel: usize| -> Result<Vec<f64>> {
+                let offset = 12 + channel * 12;
+                let gamma = read_fixed(tag, offset)?;
+                let min = read_fixed(tag, offset + 4)?;
+                let max = read_fixed(tag, offset + 8)?;
+                Ok((0..=u8::MAX)
+                    .map(|i| min + (max - min) * (i as f64 / 255.0).powf(gamma))
+                    .collect())
+            };
+
+            Ok(Vcgt {
+                red: read_channel(0)?,
+                green: read_channel(1)?,
+                blue: read_channel(2)?,
+            })
+        }
+        gamma_type => bail!("Unknown vcgt gamma type {gamma_type}"),
+    }
+}
+
+/// Reads the ASCII description of a textDescriptionType (ICC v2) tag. Descriptions of ICC v4
+/// profiles are multi localized unicode and not supported.
+fn parse_description(tag: &[u8]) -> Option<String> {
+    if tag.get(0..4)? != TAG_DESC {
+        return None;
+    }
+    let len = read_u32(tag, 8).ok()? as usize;
+    let text = tag.get(12..12 + len)?;
+    let text = text.split(|byte| *byte == 0).next()?;
+    Some(String::from_utf8_lossy(text).into_owned())
+}
+
+/// Linearly interpolates `curve` to `size` entries
+fn resample(curve: &[f64], size: usize) -> Vec<u16> {
+    (0..size)
+        .map(|i| {
+            let position = if size > 1 {
+                i as f64 / (size - 1) as f64 * (curve.len() - 1) as f64
+            } else {
+                0.0
+            };
+            let lower = position.floor() as usize;
+            let upper = (lower + 1).min(curve.len() - 1);
+            let fraction = position - lower as f64;
+            let value = curve[lower] + (curve[upper] - curve[lower]) * fraction;
+            (value.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16
+        })
+        .collect()
+}
+
+fn ramp_value(index: usize, size: usize) -> u16 {
+    if size < 2 {
+        return u16::MAX;
+    }
+    (index as f64 / (size - 1) as f64 * u16::MAX as f64).round() as u16
+}
+
+impl State {
+    /// Loads the ICC profile at `path` and applies its gamma table to the output, `None` resets
+    /// the output to a linear gamma ramp.
+    // TODO: Advertise the profile to clients once wp-color-management-v1 is available
+    pub fn set_color_profile(&mut self, output_name: &str, path: Option<&str>) -> Result<()> {
+        let output = self
+            .outputs
+            .get(output_name)
+            .with_context(|| format!("Output {output_name} does not exist"))?
+            .clone();
+        let profile = path.map(ColorProfile::load).transpose()?;
+
+        self.backend_data
+            .set_gamma(&output, profile.as_ref())
+            .with_context(|| format!("Unable to set gamma of output {output_name}"))?;
+        info!(
+            output_name,
+            profile = ?profile.as_ref().map(|profile| &profile.path),
+            description = ?profile.as_ref().and_then(|profile| profile.description.as_ref()),
+            "Color profile applied"
+        );
+        match profile {
+            Some(profile) => self.color_profiles.insert(output_name.to_string(), profile),
+            None => self.color_profiles.remove(output_name),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(value: f64) -> [u8; 4] {
+        ((value * 65536.0) as i32).to_be_bytes()
+    }
+
+    /// vcgt tag with a table of 16 bit entries, one curve per channel
+    fn table_vcgt(curves: &[&[u16]]) -> Vec<u8> {
+        let mut tag = TAG_VCGT.to_vec();
+        tag.extend([0; 4]);
+        tag.extend(VCGT_TYPE_TABLE.to_be_bytes());
+        tag.extend((curves.len() as u16).to_be_bytes());
+        tag.extend((curves[0].len() as u16).to_be_bytes());
+        tag.extend(2u16.to_be_bytes());
+        for value in curves.iter().flat_map(|curve| curve.iter()) {
+            tag.extend(value.to_be_bytes());
+        }
+        tag
+    }
+
+    fn formula_vcgt(gamma: f64, min: f64, max: f64) -> Vec<u8> {
+        let mut tag = TAG_VCGT.to_vec();
+        tag.extend([0; 4]);
+        tag.extend(VCGT_TYPE_FORMULA.to_be_bytes());
+        for _ in 0..3 {
+            tag.extend(fixed(gamma));
+            tag.extend(fixed(min));
+            tag.extend(fixed(max));
+        }
+        tag
+    }
+
+    /// Profile with the tags, only the fields which are parsed are set in the header
+    fn profile(tags: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0; ICC_HEADER_SIZE];
+        data[36..40].copy_from_slice(ICC_SIGNATURE);
+        data.extend((tags.len() as u32).to_be_bytes());
+        let mut offset = data.len() + tags.len() * 12;
+        for (signature, tag) in tags {
+            data.extend(signature.as_slice());
+            data.extend((offset as u32).to_be_bytes());
+            data.extend((tag.len() as u32).to_be_bytes());
+            offset += tag.len();
+        }
+        for (_, tag) in tags {
+            data.extend(tag);
+        }
+        data
+    }
+
+    #[test]
+    fn table_vcgt_per_channel() {
+        let tag = table_vcgt(&[&[0, u16::MAX], &[0, 32768], &[u16::MAX, 0]]);
+        let vcgt = parse_vcgt(&tag).unwrap();
+        assert_eq!(vcgt.red, vec![0.0, 1.0]);
+        assert!((vcgt.green[1] - 0.5).abs() < 0.001);
+        assert_eq!(vcgt.blue, vec![1.0, 0.0]);
+    }
+
+    #[test]
+    fn single_channel_table_vcgt_applies_to_all_channels() {
+        let vcgt = parse_vcgt(&table_vcgt(&[&[0, u16::MAX]])).unwrap();
+        assert_eq!(vcgt.red, vcgt.green);
+        assert_eq!(vcgt.red, vcgt.blue);
+    }
+
+    #[test]
+    fn formula_vcgt() {
+        let vcgt = parse_vcgt(&formula_vcgt(1.0, 0.0, 1.0)).unwrap();
+        assert_eq!(vcgt.red.len(), 256);
+        assert_eq!(vcgt.red[0], 0.0);
+        assert_eq!(vcgt.red[255], 1.0);
+
+        let vcgt = parse_vcgt(&formula_vcgt(2.0, 0.25, 0.75)).unwrap();
+        assert_eq!(vcgt.green[0], 0.25);
+        assert_eq!(vcgt.green[255], 0.75);
+        assert!(vcgt.green[128] < 0.5);
+    }
+
+    #[test]
+    fn resample_interpolates() {
+        assert_eq!(resample(&[0.0, 1.0], 3), vec![0, 32768, u16::MAX]);
+        assert_eq!(resample(&[0.0, 0.5, 1.0], 2), vec![0, u16::MAX]);
+        assert_eq!(resample(&[1.0, 0.0], 1), vec![u16::MAX]);
+        // Values are clamped
+        assert_eq!(resample(&[-1.0, 2.0], 2), vec![0, u16::MAX]);
+    }
+
+    #[test]
+    fn read_checks_bounds() {
+        let data = [0x12, 0x34, 0x56, 0x78];
+        assert_eq!(read_u16(&data, 2).unwrap(), 0x5678);
+        assert!(read_u16(&data, 3).is_err());
+        assert_eq!(read_u32(&data, 0).unwrap(), 0x12345678);
+        assert!(read_u32(&data, 1).is_err());
+        assert!(read_u32(&data, usize::MAX - 1).is_err());
+    }
+
+    #[test]
+    fn truncated_vcgt_is_rejected() {
+        let tag = table_vcgt(&[&[0, u16::MAX], &[0, u16::MAX], &[0, u16::MAX]]);
+        assert!(parse_vcgt(&tag[..tag.len() - 1]).is_err());
+        let tag = formula_vcgt(1.0, 0.0, 1.0);
+        assert!(parse_vcgt(&tag[..tag.len() - 1]).is_err());
+        assert!(parse_vcgt(&tag[..8]).is_err());
+        assert!(parse_vcgt(b"desc\0\0\0\0\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn profile_with_vcgt() {
+        let data = profile(&[(TAG_VCGT, table_vcgt(&[&[u16::MAX, 0]]))]);
+        let profile = ColorProfile::parse("test.icc".into(), &data).unwrap();
+        assert_eq!(profile.description, None);
+        let ramp = profile.gamma_ramp(3);
+        assert_eq!(ramp.red, vec![u16::MAX, 32768, 0]);
+        assert_eq!(ramp.red, ramp.blue);
+    }
+
+    #[test]
+    fn truncated_profile_is_rejected() {
+        let data = profile(&[(TAG_VCGT, table_vcgt(&[&[0, u16::MAX]]))]);
+        assert!(ColorProfile::parse("test.icc".into(), &data[..ICC_HEADER_SIZE]).is_err());
+        // The tag table is there, but the tag is cut off
+        assert!(ColorProfile::parse("test.icc".into(), &data[..data.len() - 2]).is_err());
+        let mut data = data;
+        data[36..40].copy_from_slice(b"nope");
+        assert!(ColorProfile::parse("test.icc".into(), &data).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Optional authentication of the command sockets. Without it every program which can reach a
+//! socket controls the compositor, which is fine while it sits in the private runtime directory
+//! of the user. Once a socket is forwarded, e.g. over SSH to manage a kiosk remotely, connections
+//! are only trusted if their user is allowed, otherwise each request has to carry the token in
+//! its `token` field.
+
+use crate::State;
+use std::{
+    mem,
+    os::{fd::AsRawFd, unix::net::UnixStream},
+};
+use tracing::info;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CommandAuth {
+    /// Token untrusted connections have to send along with every request
+    pub token: Option<String>,
+    /// Users whose connections are trusted without a token
+    pub uids: Vec<u32>,
+}
+
+impl CommandAuth {
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some() || !self.uids.is_empty()
+    }
+
+    /// Whether connections of the user are trusted without a token
+    pub fn trusts(&self, uid: Option<u32>) -> bool {
+        !self.is_enabled() || uid.is_some_and(|uid| self.uids.contains(&uid))
+    }
+
+    /// Whether the token of a request on an untrusted connection is the configured one
+    pub fn accepts_token(&self, token: Option<&str>) -> bool {
+        match (&self.token, token) {
+            (Some(expected), Some(token)) => {
+                constant_time_eq(expected.as_bytes(), token.as_bytes())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl State {
+    /// Requires connections of the command sockets to authenticate, connections which are open
+    /// already are kept
+    pub fn set_command_auth(&mut self, auth: CommandAuth) {
+        info!(
+            token = auth.token.is_some(),
+            uids = ?auth.uids,
+            "Setting command socket authentication"
+        );
+        self.command_auth = auth;
+    }
+}
+
+/// Compares without stopping at the first difference, so that the time it takes does not tell
+/// how much of a guessed token is right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// User of the process on the other end of the connection, taken from `SO_PEERCRED`
+pub fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut length = mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut credentials as *mut libc::ucred).cast(),
+            &mut length,
+        )
+    };
+    (res == 0).then_some(credentials.uid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn everyone_is_trusted_without_auth() {
+        let auth = CommandAuth::default();
+        assert!(auth.trusts(Some(1000)));
+        assert!(auth.trusts(None));
+    }
+
+    #[test]
+    fn only_allowed_users_are_trusted() {
+        let auth = CommandAuth {
+            token: None,
+            uids: vec![1000],
+        };
+        assert!(auth.trusts(Some(1000)));
+        assert!(!auth.trusts(Some(1001)));
+        assert!(!auth.trusts(None));
+        assert!(!auth.accepts_token(Some("")));
+    }
+
+    #[test]
+    fn token_has_to_match() {
+        let auth = CommandAuth {
+            token: Some("secret".to_string()),
+            uids: Vec::new(),
+        };
+        assert!(!auth.trusts(Some(1000)));
+        assert!(auth.accepts_token(Some("secret")));
+        assert!(!auth.accepts_token(Some("secreT")));
+        assert!(!auth.accepts_token(Some("secret2")));
+        assert!(!auth.accepts_token(None));
+    }
+
+    #[test]
+    fn peer_is_this_process() {
+        let (a, _b) = UnixStream::pair().unwrap();
+        assert_eq!(peer_uid(&a), Some(unsafe { libc::getuid() }));
+    }
+}
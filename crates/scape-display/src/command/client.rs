@@ -1 +1,171 @@
-// nothing yet
+use super::{encode_line, socket_path, Request, Response, SOCKET_ENV, TOKEN_ENV};
+use crate::{config::ConfigOutputSettings, synthetic_input::SyntheticEvent};
+use anyhow::{anyhow, Context};
+use scape_shared::{CliCommand, OutputSetting, PressState, SyntheticInput};
+use serde_json::Value;
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+/// Time the compositor has to answer a request
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn request(command: CliCommand) -> anyhow::Result<Value> {
+    let request = match command {
+        CliCommand::CloseWindow { window_name } => Request::CloseWindow { window_name },
+        CliCommand::Run { name, args } => Request::RunCommand { name, args },
+        CliCommand::ListWindows => Request::GetWindows,
+        CliCommand::ListOutputs => Request::GetOutputs,
+        CliCommand::ListSpaces => Request::GetSpaces,
+        CliCommand::Focus { id } => Request::FocusWindow { id },
+        CliCommand::MoveWindow {
+            id,
+            zone,
+            position,
+            space,
+        } => Request::MoveWindow {
+            id,
+            zone,
+            position: position.map(|position| (position[0], position[1])),
+            space,
+        },
+        CliCommand::SwitchSpace { name, output } => Request::SwitchSpace {
+            space: name,
+            output,
+        },
+        CliCommand::Quit => Request::Quit,
+        CliCommand::ReloadConfig => Request::ReloadConfig,
+        CliCommand::Output { name, setting } => {
+            let mut settings = ConfigOutputSettings {
+                name,
+                ..Default::default()
+            };
+            match setting {
+                OutputSetting::Scale { scale } => settings.scale = Some(scale),
+                OutputSetting::Position { x, y } => settings.position = Some((x, y)),
+                OutputSetting::Mode { mode } => settings.mode = Some(mode),
+                OutputSetting::Transform { transform } => settings.transform = Some(transform),
+                OutputSetting::Off => settings.enabled = Some(false),
+                OutputSetting::On => settings.enabled = Some(true),
+            }
+            Request::ConfigureOutput(settings)
+        }
+        CliCommand::CreateOutput { mode } => Request::CreateOutput { mode },
+        CliCommand::DestroyOutput { name } => Request::DestroyOutput { name },
+        CliCommand::Input { event } => Request::Input(synthetic_event(event)),
+        CliCommand::Subscribe { events } => Request::Subscribe {
+            events: events
+                .iter()
+                .map(|event| {
+                    serde_json::from_value(Value::from(event.as_str()))
+                        .with_context(|| format!("Unknown event {event}"))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        },
+        // Sent as given, so scripts can use every request without a matching command
+        CliCommand::Msg { request } => {
+            return serde_json::from_str(&request).context("Request is not valid JSON")
+        }
+    };
+    Ok(serde_json::to_value(request)?)
+}
+
+fn synthetic_event(input: SyntheticInput) -> SyntheticEvent {
+    match input {
+        SyntheticInput::Key { key, state } => SyntheticEvent::Key {
+            key,
+            pressed: state == PressState::Press,
+        },
+        SyntheticInput::Motion { dx, dy } => SyntheticEvent::PointerMotion { dx, dy },
+        SyntheticInput::Position { x, y } => SyntheticEvent::PointerPosition { x, y },
+        SyntheticInput::Button { button, state } => SyntheticEvent::PointerButton {
+            button,
+            pressed: state == PressState::Press,
+        },
+        SyntheticInput::Scroll {
+            horizontal,
+            vertical,
+        } => SyntheticEvent::PointerAxis {
+            horizontal,
+            vertical,
+        },
+        SyntheticInput::TouchDown { slot, x, y } => SyntheticEvent::TouchDown { slot, x, y },
+        SyntheticInput::TouchMotion { slot, x, y } => SyntheticEvent::TouchMotion { slot, x, y },
+        SyntheticInput::TouchUp { slot } => SyntheticEvent::TouchUp { slot },
+    }
+}
+
+/// Command socket of the compositor this program runs in, found through the environment
+fn find_socket() -> anyhow::Result<PathBuf> {
+    if let Some(path) = env::var_os(SOCKET_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+    let wayland_display = env::var("WAYLAND_DISPLAY")
+        .context("Neither SCAPE_SOCKET nor WAYLAND_DISPLAY is set, is scape running?")?;
+    Ok(socket_path(&wayland_display))
+}
+
+/// Sends the request to the running compositor and returns the data of its response together
+/// with the connection, which stays open for subscriptions
+fn connect(mut request: Value) -> anyhow::Result<(Value, BufReader<UnixStream>)> {
+    if let (Some(token), Some(request)) = (env::var_os(TOKEN_ENV), request.as_object_mut()) {
+        let token = token
+            .into_string()
+            .map_err(|_| anyhow!("{TOKEN_ENV} is not valid unicode"))?;
+        request.insert("token".to_string(), Value::from(token));
+    }
+    let path = find_socket()?;
+    let mut stream = UnixStream::connect(&path)
+        .with_context(|| format!("Unable to connect to scape at {}", path.display()))?;
+    stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+    stream
+        .write_all(encode_line(&request)?.as_bytes())
+        .context("Unable to send request")?;
+
+    let mut response = String::new();
+    let mut reader = BufReader::new(stream);
+    reader
+        .read_line(&mut response)
+        .context("No response from scape")?;
+    let response: Response =
+        serde_json::from_str(&response).context("Malformed response from scape")?;
+    let data = Result::<Value, String>::from(response).map_err(|message| anyhow!(message))?;
+    Ok((data, reader))
+}
+
+/// Prints the events the connection subscribed to as they arrive, until the compositor stops
+fn print_events(mut reader: BufReader<UnixStream>) -> anyhow::Result<()> {
+    // Events arrive whenever something happens
+    reader.get_ref().set_read_timeout(None)?;
+
+    let mut stdout = io::stdout().lock();
+    let mut line = String::new();
+    while reader
+        .read_line(&mut line)
+        .context("Unable to read event")?
+        > 0
+    {
+        stdout.write_all(line.as_bytes())?;
+        stdout.flush()?;
+        line.clear();
+    }
+    Ok(())
+}
+
+/// Sends the command to the running compositor, waits until it was executed and prints the
+/// result of queries
+pub fn run(command: CliCommand) -> anyhow::Result<()> {
+    let subscribe = matches!(command, CliCommand::Subscribe { .. });
+    let (data, reader) = connect(request(command)?)?;
+    if subscribe {
+        return print_events(reader);
+    }
+    if !data.is_null() {
+        println!("{}", serde_json::to_string_pretty(&data)?);
+    }
+    Ok(())
+}
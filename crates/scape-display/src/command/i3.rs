@@ -0,0 +1,684 @@
+//! Subset of the IPC protocol of i3, which sway speaks as well, so that bars and scripts written
+//! for them work with scape. Every message starts with the magic string, followed by the length
+//! and the type of its JSON payload as native endian 32-bit integers. Spaces are presented as
+//! workspaces, windows as floating containers on them.
+
+use super::{auth::peer_uid, outgoing::Outgoing, CommandEvent, WindowChange};
+use crate::{
+    action::Action,
+    composition::MoveTarget,
+    config::{ConfigMode, ConfigOutputDevice, ConfigWindow},
+    layout::transform_name,
+    state::active_space,
+    State,
+};
+use calloop::{generic::Generic, Interest, Mode, PostAction};
+use serde_json::{json, Value};
+use smithay::{
+    output::Output,
+    utils::{Logical, Rectangle},
+};
+use std::{
+    env,
+    io::{ErrorKind, Read},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+};
+use tracing::{info, warn};
+
+/// Variables under which i3 and sway advertise their socket
+const SOCKET_ENVS: [&str; 2] = ["I3SOCK", "SWAYSOCK"];
+const MAGIC: &[u8] = b"i3-ipc";
+const HEADER_LENGTH: usize = MAGIC.len() + 8;
+/// Messages longer than this are rejected instead of buffering them
+const MAX_MESSAGE_LENGTH: usize = 64 * 1024;
+
+const RUN_COMMAND: u32 = 0;
+const GET_WORKSPACES: u32 = 1;
+const SUBSCRIBE: u32 = 2;
+const GET_OUTPUTS: u32 = 3;
+const GET_TREE: u32 = 4;
+const GET_VERSION: u32 = 7;
+
+/// Events have the highest bit of their type set, the workspace event is the first one
+const WORKSPACE_EVENT: u32 = 1 << 31;
+const OUTPUT_EVENT: u32 = WORKSPACE_EVENT | 1;
+const WINDOW_EVENT: u32 = WORKSPACE_EVENT | 3;
+
+/// Ids of outputs and workspaces start here, so they do not collide with the ids of windows
+const OUTPUT_ID_BASE: u64 = 1 << 40;
+const WORKSPACE_ID_BASE: u64 = 1 << 41;
+/// Output which holds the workspaces that are not shown, like in i3
+const HIDDEN_OUTPUT: &str = "__i3";
+
+/// Path of the i3 socket of the compositor with the wayland socket `wayland_display`
+fn socket_path(wayland_display: &str) -> PathBuf {
+    super::socket_path(wayland_display).with_extension("i3.sock")
+}
+
+/// Connection which receives the events of the named types, like `workspace` or `window`
+#[derive(Debug)]
+pub struct I3Subscriber {
+    outgoing: Outgoing,
+    events: Vec<String>,
+}
+
+fn encode_message(message_type: u32, payload: &Value) -> Vec<u8> {
+    let payload = payload.to_string();
+    let mut message = Vec::with_capacity(HEADER_LENGTH + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    message
+}
+
+/// Splits the first complete message off the buffer
+fn decode_message(buffer: &mut Vec<u8>) -> Result<Option<(u32, Vec<u8>)>, String> {
+    if buffer.len() < HEADER_LENGTH {
+        return Ok(None);
+    }
+    if !buffer.starts_with(MAGIC) {
+        return Err("Message does not start with the i3-ipc magic string".to_string());
+    }
+    let length = u32::from_ne_bytes(buffer[6..10].try_into().unwrap()) as usize;
+    let message_type = u32::from_ne_bytes(buffer[10..14].try_into().unwrap());
+    if length > MAX_MESSAGE_LENGTH {
+        return Err("Message is too long".to_string());
+    }
+    if buffer.len() < HEADER_LENGTH + length {
+        return Ok(None);
+    }
+    let payload = buffer[HEADER_LENGTH..HEADER_LENGTH + length].to_vec();
+    buffer.drain(..HEADER_LENGTH + length);
+    Ok(Some((message_type, payload)))
+}
+
+/// Number i3 derives from the leading digits of a workspace name, -1 if it has none
+fn workspace_number(name: &str) -> i64 {
+    let digits = name
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>();
+    digits.parse().unwrap_or(-1)
+}
+
+fn rect(rectangle: Rectangle<i32, Logical>) -> Value {
+    json!({
+        "x": rectangle.loc.x,
+        "y": rectangle.loc.y,
+        "width": rectangle.size.w,
+        "height": rectangle.size.h,
+    })
+}
+
+fn window_node(window: &ConfigWindow) -> Value {
+    let rect = json!({
+        "x": window.x,
+        "y": window.y,
+        "width": window.width,
+        "height": window.height,
+    });
+    json!({
+        "id": window.id,
+        "type": "floating_con",
+        "name": window.title,
+        "app_id": window.app_id,
+        "focused": window.focused,
+        "visible": true,
+        "urgent": false,
+        "layout": "none",
+        "border": "none",
+        "fullscreen_mode": 0,
+        "rect": rect,
+        "window_rect": rect,
+        "shell": "xdg_shell",
+        "nodes": [],
+        "floating_nodes": [],
+    })
+}
+
+fn mode(mode: &ConfigMode) -> Value {
+    json!({
+        "width": mode.width,
+        "height": mode.height,
+        "refresh": (mode.refresh * 1000.0).round() as i64,
+    })
+}
+
+/// Words of `move [container|window] to workspace [number] <name>` or
+/// `move [container|window] absolute position <x> [px] <y> [px]` after `move`
+fn move_target(words: &[&str]) -> Option<MoveTarget> {
+    let words = match words {
+        ["container" | "window", words @ ..] => words,
+        words => words,
+    };
+    match words {
+        ["to", "workspace", "number", name @ ..] | ["to", "workspace", name @ ..]
+            if !name.is_empty() =>
+        {
+            Some(MoveTarget::Space(name.join(" ")))
+        }
+        ["absolute", "position", x, "px", y, "px"]
+        | ["absolute", "position", x, y, "px"]
+        | ["absolute", "position", x, "px", y]
+        | ["absolute", "position", x, y] => Some(MoveTarget::Position(
+            (x.parse().ok()?, y.parse().ok()?).into(),
+        )),
+        _ => None,
+    }
+}
+
+impl State {
+    /// Listens for i3 IPC messages next to the wayland socket `socket_name`
+    pub fn start_i3_server(&mut self, socket_name: &str) {
+        let path = socket_path(socket_name);
+        if self.listen(&path, State::accept_i3_connection) {
+            for socket_env in SOCKET_ENVS {
+                env::set_var(socket_env, &path);
+            }
+        }
+    }
+
+    /// Answers the messages of the connection until the client closes it. Unlike connections of
+    /// the command socket, i3 clients send many messages over the same connection. They cannot
+    /// send a token, so only trusted users may connect once authentication is configured.
+    fn accept_i3_connection(&mut self, stream: UnixStream) {
+        let uid = peer_uid(&stream);
+        if !self.command_auth.trusts(uid) {
+            warn!(uid, "Rejecting i3 connection of an untrusted user");
+            return;
+        }
+        if let Err(err) = stream.set_nonblocking(true) {
+            warn!(?err, "Unable to set i3 connection to non-blocking");
+            return;
+        }
+        let outgoing = match stream.try_clone() {
+            Ok(stream) => Outgoing::new(stream),
+            Err(err) => {
+                warn!(?err, "Unable to clone i3 connection");
+                return;
+            }
+        };
+        let mut buffer = Vec::new();
+        let res = self.loop_handle.insert_source(
+            Generic::new(stream, Interest::READ, Mode::Level),
+            move |_, stream, state| {
+                let mut stream: &UnixStream = stream.as_ref();
+                let mut chunk = [0; 1024];
+                loop {
+                    match stream.read(&mut chunk) {
+                        Ok(0) => return Ok(PostAction::Remove),
+                        Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            warn!(?err, "Error reading i3 message");
+                            return Ok(PostAction::Remove);
+                        }
+                    }
+                }
+
+                loop {
+                    let (message_type, payload) = match decode_message(&mut buffer) {
+                        Ok(Some(message)) => message,
+                        Ok(None) => return Ok(PostAction::Continue),
+                        Err(err) => {
+                            warn!(err, "Closing i3 connection");
+                            return Ok(PostAction::Remove);
+                        }
+                    };
+                    let reply = state.handle_i3_message(message_type, &payload, &outgoing);
+                    // Large replies like the tree are sent while the compositor goes on, a client
+                    // which does not read them is dropped
+                    let reply = encode_message(message_type, &reply);
+                    if let Err(err) = outgoing.send(&state.loop_handle, &reply) {
+                        warn!(?err, "Unable to send i3 reply");
+                        return Ok(PostAction::Remove);
+                    }
+                }
+            },
+        );
+        if let Err(err) = res {
+            warn!(?err, "Unable to read i3 messages");
+        }
+    }
+
+    fn handle_i3_message(
+        &mut self,
+        message_type: u32,
+        payload: &[u8],
+        outgoing: &Outgoing,
+    ) -> Value {
+        match message_type {
+            RUN_COMMAND => {
+                let commands = String::from_utf8_lossy(payload);
+                info!(%commands, "Received i3 command");
+                Value::from(self.run_i3_commands(&commands))
+            }
+            GET_WORKSPACES => Value::from(self.i3_workspaces()),
+            SUBSCRIBE => {
+                let subscription = serde_json::from_slice::<Vec<String>>(payload)
+                    .map_err(|err| err.to_string())
+                    .map(|events| I3Subscriber {
+                        outgoing: outgoing.clone(),
+                        events,
+                    });
+                match subscription {
+                    Ok(subscriber) => {
+                        info!(events = ?subscriber.events, "Adding i3 event subscriber");
+                        self.i3_subscribers.borrow_mut().push(subscriber);
+                        json!({ "success": true })
+                    }
+                    Err(error) => json!({ "success": false, "error": error }),
+                }
+            }
+            GET_OUTPUTS => Value::from(self.i3_outputs()),
+            GET_TREE => self.i3_tree(),
+            GET_VERSION => json!({
+                "major": 1,
+                "minor": 0,
+                "patch": 0,
+                "human_readable": format!("scape {}", env!("CARGO_PKG_VERSION")),
+                "loaded_config_file_name": "",
+            }),
+            _ => json!({
+                "success": false,
+                "error": format!("Unsupported message type {message_type}"),
+            }),
+        }
+    }
+
+    /// Runs the commands separated by `;` or `,` and returns the result of each
+    fn run_i3_commands(&mut self, commands: &str) -> Vec<Value> {
+        split_i3_commands(commands)
+            .into_iter()
+            .map(|command| match self.run_i3_command(command) {
+                Ok(()) => json!({ "success": true }),
+                Err(error) => json!({ "success": false, "parse_error": false, "error": error }),
+            })
+            .collect()
+    }
+
+    /// Runs a single command. Criteria only support `con_id`, without them the command applies
+    /// to the focused window.
+    fn run_i3_command(&mut self, command: &str) -> Result<(), String> {
+        if self.session_lock.is_some() {
+            return Err("The session is locked".to_string());
+        }
+        let (window, command) = match command.strip_prefix('[') {
+            Some(command) => {
+                let (criteria, command) = command
+                    .split_once(']')
+                    .ok_or_else(|| "Criteria are not closed".to_string())?;
+                let window = criteria
+                    .trim()
+                    .strip_prefix("con_id=")
+                    .and_then(|id| id.trim_matches('"').parse::<u64>().ok())
+                    .ok_or_else(|| format!("Unsupported criteria {criteria}"))?;
+                (Some(window), command.trim())
+            }
+            None => (None, command),
+        };
+
+        let words = command.split_whitespace().collect::<Vec<_>>();
+        let action = match words.as_slice() {
+            ["exec", ..] => {
+                let command_line = command["exec".len()..].trim();
+                let command_line = command_line
+                    .strip_prefix("--no-startup-id")
+                    .unwrap_or(command_line);
+                Action::Spawn {
+                    command: "sh".to_string(),
+                    args: vec!["-c".to_string(), command_line.trim().to_string()],
+                    zone: None,
+                    env: Vec::new(),
+                    cwd: None,
+                }
+            }
+            ["workspace", "number", name @ ..] | ["workspace", name @ ..] if !name.is_empty() => {
+                let space = name.join(" ");
+                // i3 creates workspaces when they are switched to
+                self.create_space(space.clone());
+                Action::SwitchSpace {
+                    space,
+                    output: None,
+                }
+            }
+            ["focus"] => Action::FocusWindow {
+                window: window.ok_or_else(|| "focus needs a con_id criteria".to_string())?,
+            },
+            ["floating", "toggle"] => Action::ToggleFloating { window },
+            ["sticky", "toggle"] => Action::ToggleSticky { window },
+            ["fullscreen"] | ["fullscreen", "toggle"] => Action::ToggleFullscreen { window },
+            ["kill"] => return self.close_window(window).map_err(|err| err.to_string()),
+            ["move", words @ ..] => {
+                let target =
+                    move_target(words).ok_or_else(|| format!("Unsupported command {command}"))?;
+                if let MoveTarget::Space(space) = &target {
+                    self.create_space(space.clone());
+                }
+                Action::MoveWindow { window, target }
+            }
+            ["exit"] => Action::Quit,
+            _ => return Err(format!("Unsupported command {command}")),
+        };
+        self.try_execute(action).map_err(|err| err.to_string())
+    }
+
+    fn sorted_outputs(&self) -> Vec<Output> {
+        let mut outputs = self.outputs.values().cloned().collect::<Vec<_>>();
+        outputs.sort_by_key(Output::name);
+        outputs
+    }
+
+    /// Names of the spaces, ordered like i3 orders workspaces
+    fn sorted_spaces(&self) -> Vec<String> {
+        let mut spaces = self.spaces.keys().cloned().collect::<Vec<_>>();
+        // Numbered workspaces come first, like in i3
+        spaces.sort_by_key(|name| {
+            let number = workspace_number(name);
+            (number < 0, number, name.clone())
+        });
+        spaces
+    }
+
+    fn i3_workspace(&self, index: usize, name: &str, focused_space: Option<&str>) -> Value {
+        let output = self
+            .spaces
+            .get(name)
+            .and_then(|space| space.outputs().next().cloned());
+        let geometry = output
+            .as_ref()
+            .map(|output| self.output_rect(output))
+            .unwrap_or_default();
+        json!({
+            "id": WORKSPACE_ID_BASE + index as u64,
+            "type": "workspace",
+            "num": workspace_number(name),
+            "name": name,
+            "visible": output.is_some(),
+            "focused": focused_space == Some(name),
+            "urgent": false,
+            "layout": "splith",
+            "output": output.map_or_else(|| HIDDEN_OUTPUT.to_string(), |output| output.name()),
+            "rect": rect(geometry),
+        })
+    }
+
+    fn i3_workspaces(&self) -> Vec<Value> {
+        let focused_space = self.focused_space();
+        self.sorted_spaces()
+            .iter()
+            .enumerate()
+            .map(|(index, name)| self.i3_workspace(index, name, focused_space.as_deref()))
+            .collect()
+    }
+
+    fn i3_outputs(&self) -> Vec<Value> {
+        self.sorted_outputs()
+            .iter()
+            .enumerate()
+            .map(|(index, output)| {
+                let device = ConfigOutputDevice::from(output);
+                let current_mode = device.modes.iter().find(|mode| mode.current);
+                json!({
+                    "id": OUTPUT_ID_BASE + index as u64,
+                    "type": "output",
+                    "name": device.name,
+                    "make": device.make,
+                    "model": device.model,
+                    "serial": device.serial.as_deref().unwrap_or("Unknown"),
+                    "active": !self.disabled_outputs.contains_key(&device.name),
+                    "primary": false,
+                    "scale": output.current_scale().fractional_scale(),
+                    "transform": transform_name(output.current_transform()),
+                    "current_workspace": active_space(output),
+                    "rect": rect(self.output_rect(output)),
+                    "modes": device.modes.iter().map(mode).collect::<Vec<_>>(),
+                    "current_mode": current_mode.map(mode),
+                })
+            })
+            .collect()
+    }
+
+    /// Outputs with the workspaces shown on them, the windows of a workspace float on it
+    fn i3_tree(&self) -> Value {
+        let snapshot = self.config_snapshot();
+        let mut workspaces = self.i3_workspaces();
+        for workspace in &mut workspaces {
+            let name = workspace["name"].as_str().unwrap_or_default().to_string();
+            let windows = snapshot
+                .windows
+                .iter()
+                .filter(|window| window.space == name)
+                .map(window_node)
+                .collect::<Vec<_>>();
+            workspace["nodes"] = json!([]);
+            workspace["floating_nodes"] = Value::from(windows);
+        }
+
+        let mut outputs = self
+            .sorted_outputs()
+            .iter()
+            .enumerate()
+            .map(|(index, output)| {
+                json!({
+                    "id": OUTPUT_ID_BASE + index as u64,
+                    "type": "output",
+                    "name": output.name(),
+                    "rect": rect(self.output_rect(output)),
+                    "nodes": [],
+                    "floating_nodes": [],
+                })
+            })
+            .collect::<Vec<_>>();
+        outputs.push(json!({
+            "id": OUTPUT_ID_BASE + outputs.len() as u64,
+            "type": "output",
+            "name": HIDDEN_OUTPUT,
+            "rect": rect(Rectangle::default()),
+            "nodes": [],
+            "floating_nodes": [],
+        }));
+        for workspace in workspaces {
+            // A space shown on several outputs is listed under the first one
+            let output = outputs
+                .iter_mut()
+                .find(|output| output["name"] == workspace["output"]);
+            if let Some(Value::Array(nodes)) = output.map(|output| &mut output["nodes"]) {
+                nodes.push(workspace);
+            }
+        }
+
+        let root_rect = self
+            .outputs
+            .values()
+            .map(|output| self.output_rect(output))
+            .reduce(|a, b| a.merge(b))
+            .unwrap_or_default();
+        json!({
+            "id": 1,
+            "type": "root",
+            "name": "root",
+            "rect": rect(root_rect),
+            "focused": false,
+            "nodes": outputs,
+            "floating_nodes": [],
+        })
+    }
+
+    /// Translates the event for the i3 connections which subscribed to its type
+    pub(super) fn broadcast_i3_event(&self, event: &CommandEvent) {
+        let mut subscribers = self.i3_subscribers.borrow_mut();
+        let (name, event_type) = match event {
+            CommandEvent::Window { .. } => ("window", WINDOW_EVENT),
+            CommandEvent::Space { .. } => ("workspace", WORKSPACE_EVENT),
+            CommandEvent::Output { .. } => ("output", OUTPUT_EVENT),
+        };
+        if !subscribers
+            .iter()
+            .any(|subscriber| subscriber.events.iter().any(|event| event == name))
+        {
+            return;
+        }
+
+        let payload = match event {
+            CommandEvent::Window { change, window } => {
+                let change = match change {
+                    WindowChange::Opened => "new",
+                    WindowChange::Closed => "close",
+                    WindowChange::Focused => "focus",
+                    WindowChange::TitleChanged => "title",
+                };
+                json!({ "change": change, "container": window_node(window) })
+            }
+            CommandEvent::Space { space, .. } => {
+                let focused_space = self.focused_space();
+                let current = self
+                    .sorted_spaces()
+                    .iter()
+                    .position(|name| name == space)
+                    .map(|index| self.i3_workspace(index, space, focused_space.as_deref()));
+                json!({ "change": "focus", "current": current, "old": null })
+            }
+            CommandEvent::Output { .. } => json!({ "change": "unspecified" }),
+        };
+        let message = encode_message(event_type, &payload);
+        subscribers.retain(|subscriber| {
+            if !subscriber.events.iter().any(|event| event == name) {
+                return true;
+            }
+            match subscriber.outgoing.send(&self.loop_handle, &message) {
+                Ok(()) => true,
+                Err(err) => {
+                    info!(?err, "Removing i3 event subscriber");
+                    false
+                }
+            }
+        });
+    }
+}
+
+/// Splits the commands at `;` and `,` outside of quotes, so that the command line of
+/// `exec notify-send "a, b"` stays in one piece. Backslashes escape the next character outside of
+/// single quotes.
+fn split_i3_commands(commands: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in commands.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (None | Some('"'), '\\') => escaped = true,
+            (Some(quote_char), c) if c == quote_char => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ';' | ',') => {
+                split.push(commands[start..index].trim());
+                start = index + c.len_utf8();
+            }
+            (None, _) => {}
+        }
+    }
+    split.push(commands[start..].trim());
+    split.retain(|command| !command.is_empty());
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(length: u32, message_type: u32) -> Vec<u8> {
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&length.to_ne_bytes());
+        header.extend_from_slice(&message_type.to_ne_bytes());
+        header
+    }
+
+    #[test]
+    fn decodes_an_encoded_message() {
+        let payload = json!(["workspace"]);
+        let mut buffer = encode_message(SUBSCRIBE, &payload);
+        let (message_type, decoded) = decode_message(&mut buffer).unwrap().unwrap();
+        assert_eq!(message_type, SUBSCRIBE);
+        assert_eq!(serde_json::from_slice::<Value>(&decoded).unwrap(), payload);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn waits_for_a_complete_header() {
+        let mut buffer = Vec::new();
+        assert_eq!(decode_message(&mut buffer), Ok(None));
+        let mut buffer = header(2, RUN_COMMAND)[..HEADER_LENGTH - 1].to_vec();
+        assert_eq!(decode_message(&mut buffer), Ok(None));
+        assert_eq!(buffer.len(), HEADER_LENGTH - 1);
+    }
+
+    #[test]
+    fn waits_for_a_complete_payload() {
+        let mut buffer = header(4, RUN_COMMAND);
+        buffer.extend_from_slice(b"ex");
+        assert_eq!(decode_message(&mut buffer), Ok(None));
+        buffer.extend_from_slice(b"it");
+        assert_eq!(
+            decode_message(&mut buffer),
+            Ok(Some((RUN_COMMAND, b"exit".to_vec())))
+        );
+    }
+
+    #[test]
+    fn splits_off_one_message_at_a_time() {
+        let mut buffer = encode_message(GET_TREE, &json!(""));
+        buffer.extend(encode_message(GET_VERSION, &json!("")));
+        buffer.extend_from_slice(&MAGIC[..2]);
+        assert_eq!(decode_message(&mut buffer).unwrap().unwrap().0, GET_TREE);
+        assert_eq!(decode_message(&mut buffer).unwrap().unwrap().0, GET_VERSION);
+        assert_eq!(decode_message(&mut buffer), Ok(None));
+        assert_eq!(buffer, &MAGIC[..2]);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut buffer = header(0, GET_TREE);
+        buffer[0] = b'I';
+        assert!(decode_message(&mut buffer).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_lengths() {
+        let mut buffer = header(MAX_MESSAGE_LENGTH as u32 + 1, RUN_COMMAND);
+        assert!(decode_message(&mut buffer).is_err());
+        let mut buffer = header(u32::MAX, RUN_COMMAND);
+        assert!(decode_message(&mut buffer).is_err());
+        // The longest message is still accepted once it is complete
+        let mut buffer = header(MAX_MESSAGE_LENGTH as u32, RUN_COMMAND);
+        assert_eq!(decode_message(&mut buffer), Ok(None));
+        buffer.resize(HEADER_LENGTH + MAX_MESSAGE_LENGTH, b' ');
+        assert!(decode_message(&mut buffer).unwrap().is_some());
+    }
+
+    #[test]
+    fn splits_commands_outside_of_quotes() {
+        assert_eq!(
+            split_i3_commands("workspace 2; focus left, kill"),
+            ["workspace 2", "focus left", "kill"]
+        );
+        assert_eq!(
+            split_i3_commands(r#"exec notify-send "a, b"; workspace 2"#),
+            [r#"exec notify-send "a, b""#, "workspace 2"]
+        );
+        assert_eq!(
+            split_i3_commands("exec sh -c 'echo a; echo b',kill"),
+            ["exec sh -c 'echo a; echo b'", "kill"]
+        );
+        assert_eq!(
+            split_i3_commands(r#"exec echo "say \"hi; bye\"" ;; exec echo a\;b"#),
+            [r#"exec echo "say \"hi; bye\"""#, r"exec echo a\;b"]
+        );
+    }
+}
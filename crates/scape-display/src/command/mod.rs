@@ -1,4 +1,215 @@
-// const SOCKET_PATH: &str = "command.sock";
+//! Socket through which `scape cli` and other programs talk to a running compositor. A
+//! connection sends a single request line and gets a single response line back, both are JSON
+//! objects. Requests are tagged by their `request` field, e.g.
+//! `{"request": "run_command", "name": "terminal", "args": []}`.
+//!
+//! To manage a remote instance, e.g. a kiosk, its socket is forwarded over SSH and the same CLI
+//! talks to the forwarded socket:
+//!
+//! ```sh
+//! ssh -N -L /tmp/kiosk.sock:/run/user/1000/scape-wayland-1.sock kiosk@host &
+//! SCAPE_SOCKET=/tmp/kiosk.sock SCAPE_TOKEN=secret scape cli list-windows
+//! ```
+//!
+//! The remote config should require the token with `scape.set_command_auth`, or allow the user
+//! SSH connects as.
 
+use crate::{
+    config::{ConfigOutputDevice, ConfigOutputSettings, ConfigWindow},
+    synthetic_input::SyntheticEvent,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{env, path::PathBuf};
+
+pub mod auth;
 pub mod client;
+pub mod i3;
+mod outgoing;
 pub mod server;
+
+/// Variable which tells programs started by the compositor where its command socket is
+pub const SOCKET_ENV: &str = "SCAPE_SOCKET";
+/// Variable with the token `scape cli` sends along with its requests
+pub const TOKEN_ENV: &str = "SCAPE_TOKEN";
+
+/// Path of the command socket of the compositor with the wayland socket `wayland_display`
+pub fn socket_path(wayland_display: &str) -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join(format!("scape-{wayland_display}.sock"))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    CloseWindow {
+        window_name: String,
+    },
+    /// Runs a command registered by the config
+    RunCommand {
+        name: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Runs a chunk of Lua in the config, which changes settings through the `scape` module
+    RunLua {
+        code: String,
+    },
+    GetWindows,
+    GetOutputs,
+    GetSpaces,
+    FocusWindow {
+        id: u64,
+    },
+    /// Moves the window into the zone, to the global position of its top left corner or to the
+    /// space, exactly one of them has to be given
+    MoveWindow {
+        id: u64,
+        #[serde(default)]
+        zone: Option<String>,
+        #[serde(default)]
+        position: Option<(i32, i32)>,
+        #[serde(default)]
+        space: Option<String>,
+    },
+    /// Shows the space on the output, the output under the pointer if none is given
+    SwitchSpace {
+        space: String,
+        #[serde(default)]
+        output: Option<String>,
+    },
+    Quit,
+    /// Executes the config again, windows are kept
+    ReloadConfig,
+    /// Changes an output immediately, unset settings are kept
+    ConfigureOutput(ConfigOutputSettings),
+    /// Adds an output which is only rendered offscreen, e.g. for screencasting. The mode is in
+    /// the form of `1920x1080` or `1920x1080@60`, the response data is the name of the output.
+    CreateOutput {
+        mode: String,
+    },
+    /// Removes an output added by `create_output`
+    DestroyOutput {
+        name: String,
+    },
+    /// Passes an input event to the input handler as if it came from a device, only allowed if
+    /// the compositor was started with `--allow-synthetic-input`
+    Input(SyntheticEvent),
+    /// Keeps the connection open and sends the events of the kinds, one per line, after the
+    /// response
+    Subscribe {
+        events: Vec<EventKind>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Window,
+    /// Other compositors call spaces workspaces
+    #[serde(alias = "workspace")]
+    Space,
+    Output,
+}
+
+/// Event sent to the connections which subscribed to its kind
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CommandEvent {
+    Window {
+        change: WindowChange,
+        window: ConfigWindow,
+    },
+    Space {
+        change: SpaceChange,
+        space: String,
+        output: String,
+    },
+    Output {
+        change: OutputChange,
+        output: ConfigOutputDevice,
+    },
+}
+
+impl CommandEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            CommandEvent::Window { .. } => EventKind::Window,
+            CommandEvent::Space { .. } => EventKind::Space,
+            CommandEvent::Output { .. } => EventKind::Output,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowChange {
+    Opened,
+    Closed,
+    Focused,
+    TitleChanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpaceChange {
+    /// The space is shown on the output now
+    Switched,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputChange {
+    Added,
+    Removed,
+    /// Mode, transform, scale or position changed
+    Configured,
+}
+
+/// Answer to a request, `data` holds the result of queries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub data: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<Result<Value, String>> for Response {
+    fn from(result: Result<Value, String>) -> Self {
+        match result {
+            Ok(data) => Response {
+                success: true,
+                data,
+                error: None,
+            },
+            Err(error) => Response {
+                success: false,
+                data: Value::Null,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+impl From<Response> for Result<Value, String> {
+    fn from(response: Response) -> Self {
+        if response.success {
+            Ok(response.data)
+        } else {
+            Err(response
+                .error
+                .unwrap_or_else(|| "Request failed".to_string()))
+        }
+    }
+}
+
+/// Serializes the message as a single line, JSON escapes line breaks inside of strings
+pub fn encode_line(message: &impl Serialize) -> serde_json::Result<String> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    Ok(line)
+}
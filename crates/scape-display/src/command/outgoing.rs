@@ -0,0 +1,133 @@
+//! Writes to connections without blocking the compositor. What the socket does not take right
+//! away is kept and sent once the socket is writable again.
+
+use crate::State;
+use calloop::{generic::Generic, Interest, LoopHandle, Mode, PostAction};
+use std::{
+    cell::RefCell,
+    io::{self, ErrorKind, Write},
+    os::unix::net::UnixStream,
+    rc::Rc,
+};
+use tracing::warn;
+
+/// Clients which fall this far behind with reading are dropped
+const MAX_PENDING_LENGTH: usize = 4 * 1024 * 1024;
+
+#[derive(Debug)]
+struct Inner {
+    stream: UnixStream,
+    pending: Vec<u8>,
+    /// Whether a source waits for the socket to become writable
+    flushing: bool,
+    failed: bool,
+}
+
+/// Non-blocking connection together with the bytes which were not sent yet. The connection stays
+/// open until they are sent, also if everything else dropped it already.
+#[derive(Debug, Clone)]
+pub struct Outgoing(Rc<RefCell<Inner>>);
+
+impl Outgoing {
+    pub fn new(stream: UnixStream) -> Self {
+        Outgoing(Rc::new(RefCell::new(Inner {
+            stream,
+            pending: Vec::new(),
+            flushing: false,
+            failed: false,
+        })))
+    }
+
+    /// Sends the bytes after the ones which are still pending. Fails if the connection is closed
+    /// or the client does not keep up with reading.
+    pub fn send(&self, loop_handle: &LoopHandle<'static, State>, bytes: &[u8]) -> io::Result<()> {
+        let mut inner = self.0.borrow_mut();
+        if inner.failed {
+            return Err(ErrorKind::BrokenPipe.into());
+        }
+        if inner.pending.is_empty() {
+            let written = write_some(&inner.stream, bytes)?;
+            if written == bytes.len() {
+                return Ok(());
+            }
+            inner.pending.extend_from_slice(&bytes[written..]);
+        } else {
+            inner.pending.extend_from_slice(bytes);
+        }
+        if inner.pending.len() > MAX_PENDING_LENGTH {
+            inner.failed = true;
+            inner.pending.clear();
+            return Err(io::Error::new(
+                ErrorKind::OutOfMemory,
+                "Client does not read what it is sent",
+            ));
+        }
+
+        if !inner.flushing {
+            let stream = inner.stream.try_clone()?;
+            let outgoing = self.clone();
+            loop_handle
+                .insert_source(
+                    Generic::new(stream, Interest::WRITE, Mode::Level),
+                    move |_, _, _| Ok(outgoing.flush()),
+                )
+                .map_err(|err| io::Error::other(err.to_string()))?;
+            inner.flushing = true;
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> PostAction {
+        let mut inner = self.0.borrow_mut();
+        let inner = &mut *inner;
+        match write_some(&inner.stream, &inner.pending) {
+            Ok(written) => {
+                inner.pending.drain(..written);
+            }
+            Err(err) => {
+                warn!(?err, "Unable to send to connection");
+                inner.failed = true;
+                inner.pending.clear();
+            }
+        }
+        if !inner.pending.is_empty() {
+            return PostAction::Continue;
+        }
+        inner.flushing = false;
+        PostAction::Remove
+    }
+}
+
+/// Writes as much as the socket takes without blocking, returns how much that was
+fn write_some(mut stream: &UnixStream, bytes: &[u8]) -> io::Result<usize> {
+    let mut written = 0;
+    while written < bytes.len() {
+        match stream.write(&bytes[written..]) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(count) => written += count,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn write_some_stops_when_the_socket_is_full() {
+        let (sender, mut receiver) = UnixStream::pair().unwrap();
+        sender.set_nonblocking(true).unwrap();
+        let bytes = vec![7; 16 * 1024 * 1024];
+        let written = write_some(&sender, &bytes).unwrap();
+        assert!(written > 0 && written < bytes.len());
+
+        let mut received = vec![0; written];
+        receiver.read_exact(&mut received).unwrap();
+        assert!(received.iter().all(|byte| *byte == 7));
+    }
+}
@@ -1,27 +1,304 @@
-// use tokio::{net::UnixListener, runtime::Builder};
-// use tokio_stream::wrappers::UnixListenerStream;
-//
-// use super::SOCKET_PATH;
-//
-// pub fn run() {
-//     let server = UnixListener::bind(SOCKET_PATH).unwrap();
-//     let _stream = UnixListenerStream::new(server);
-//
-//     let _runtime = Builder::new_multi_thread()
-//         .worker_threads(2)
-//         .thread_name("scape-command-server-worker")
-//         .build()
-//         .unwrap();
-//
-//     // runtime.spawn({
-//     //     let x = server.accept();
-//     // });
-//     //     server
-//     //         .incoming()
-//     //         .into_future()
-//     //         .map_err(|(e, _)| e)
-//     //         .and_then(|(sock, _)| io::read_to_end(sock.unwrap(), Vec::new()))
-//     //         .map(|bytes| println!("{}", serde_json::from_slice::<String>(&bytes.1).unwrap()))
-//     //         .map_err(|e| panic!("{:?}", e)),
-//     // );
-// }
+use super::{
+    auth::peer_uid, encode_line, outgoing::Outgoing, socket_path, CommandEvent, EventKind, Request,
+    Response, SOCKET_ENV,
+};
+use crate::{action::Action, composition::MoveTarget, State};
+use calloop::{generic::Generic, Interest, Mode, PostAction};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    env, fs,
+    io::{self, ErrorKind, Read},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+};
+use tracing::{info, warn};
+
+/// Requests longer than this are rejected instead of buffering them
+const MAX_REQUEST_LENGTH: usize = 64 * 1024;
+
+/// Connection which receives events after its subscribe request
+#[derive(Debug)]
+pub struct CommandSubscriber {
+    outgoing: Outgoing,
+    events: Vec<EventKind>,
+}
+
+impl State {
+    /// Listens for requests of `scape cli` next to the wayland socket `socket_name`
+    pub fn start_command_server(&mut self, socket_name: &str) {
+        let path = socket_path(socket_name);
+        if self.listen(&path, State::accept_command_connection) {
+            env::set_var(SOCKET_ENV, &path);
+        }
+    }
+
+    /// Passes every connection to the socket at the path to `accept`, returns whether the socket
+    /// was created
+    pub(super) fn listen(&mut self, path: &Path, accept: fn(&mut State, UnixStream)) -> bool {
+        // A compositor which crashed leaves its socket behind
+        let _ = fs::remove_file(path);
+        let listener = match UnixListener::bind(path) {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!(?err, path = %path.display(), "Unable to create socket");
+                return false;
+            }
+        };
+        if let Err(err) = listener.set_nonblocking(true) {
+            warn!(?err, path = %path.display(), "Unable to set socket to non-blocking");
+            return false;
+        }
+
+        let res = self.loop_handle.insert_source(
+            Generic::new(listener, Interest::READ, Mode::Level),
+            move |_, listener, state| {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, _)) => accept(state, stream),
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            warn!(?err, "Error accepting connection");
+                            break;
+                        }
+                    }
+                }
+                Ok(PostAction::Continue)
+            },
+        );
+        if let Err(err) = res {
+            warn!(?err, path = %path.display(), "Unable to listen on socket");
+            return false;
+        }
+        info!(path = %path.display(), "Listening on socket");
+        true
+    }
+
+    /// Reads the request of the connection without blocking the compositor and answers it.
+    /// Connections of users which are not trusted have to send the token with their request.
+    fn accept_command_connection(&mut self, stream: UnixStream) {
+        if let Err(err) = stream.set_nonblocking(true) {
+            warn!(?err, "Unable to set command connection to non-blocking");
+            return;
+        }
+        let outgoing = match stream.try_clone() {
+            Ok(stream) => Outgoing::new(stream),
+            Err(err) => {
+                warn!(?err, "Unable to clone command connection");
+                return;
+            }
+        };
+        let uid = peer_uid(&stream);
+        let trusted = self.command_auth.trusts(uid);
+        if !trusted && self.command_auth.token.is_none() {
+            warn!(uid, "Rejecting command connection of an untrusted user");
+            let response = Response::from(Err("Not authorized".to_string()));
+            if let Ok(response) = encode_line(&response) {
+                let _ = outgoing.send(&self.loop_handle, response.as_bytes());
+            }
+            return;
+        }
+        let mut request = Vec::new();
+        let res = self.loop_handle.insert_source(
+            Generic::new(stream, Interest::READ, Mode::Level),
+            move |_, stream, state| {
+                let mut stream: &UnixStream = stream.as_ref();
+                let mut chunk = [0; 1024];
+                loop {
+                    match stream.read(&mut chunk) {
+                        // The client closed the connection without finishing its request
+                        Ok(0) => return Ok(PostAction::Remove),
+                        Ok(read) => request.extend_from_slice(&chunk[..read]),
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            warn!(?err, "Error reading command request");
+                            return Ok(PostAction::Remove);
+                        }
+                    }
+                }
+
+                let response = match request.iter().position(|byte| *byte == b'\n') {
+                    Some(end) => state.handle_command_request(&request[..end], trusted, &outgoing),
+                    None if request.len() > MAX_REQUEST_LENGTH => {
+                        Err("Request is too long".to_string())
+                    }
+                    None => return Ok(PostAction::Continue),
+                };
+                // Large responses are sent while the compositor goes on
+                let res = encode_line(&Response::from(response))
+                    .map_err(io::Error::from)
+                    .and_then(|response| outgoing.send(&state.loop_handle, response.as_bytes()));
+                if let Err(err) = res {
+                    warn!(?err, "Unable to send command response");
+                }
+                Ok(PostAction::Remove)
+            },
+        );
+        if let Err(err) = res {
+            warn!(?err, "Unable to read command request");
+        }
+    }
+
+    fn handle_command_request(
+        &mut self,
+        request: &[u8],
+        trusted: bool,
+        outgoing: &Outgoing,
+    ) -> Result<Value, String> {
+        let mut request = serde_json::from_slice::<Value>(request)
+            .map_err(|err| format!("Malformed request: {err}"))?;
+        let token = request
+            .as_object_mut()
+            .and_then(|request| request.remove("token"));
+        if !trusted
+            && !self
+                .command_auth
+                .accepts_token(token.as_ref().and_then(Value::as_str))
+        {
+            warn!("Rejecting command request with a wrong token");
+            return Err("Not authorized".to_string());
+        }
+        let request = serde_json::from_value::<Request>(request)
+            .map_err(|err| format!("Malformed request: {err}"))?;
+        info!(?request, "Received command request");
+        if self.session_lock.is_some() {
+            return Err("The session is locked".to_string());
+        }
+
+        let action = match request {
+            Request::CloseWindow { window_name } => Action::CloseWindow {
+                window: Some(self.window_id_by_name(&window_name)?),
+            },
+            Request::RunCommand { name, args } => Action::RunCommand { name, args },
+            Request::RunLua { code } => {
+                let result = self.run_lua(&code).map_err(|err| err.to_string())?;
+                return Ok(result.map(Value::from).unwrap_or_default());
+            }
+            Request::GetWindows => return to_data(self.config_snapshot().windows),
+            Request::GetOutputs => return to_data(self.config_snapshot().outputs),
+            Request::GetSpaces => return to_data(self.config_snapshot().spaces),
+            Request::FocusWindow { id } => Action::FocusWindow { window: id },
+            Request::MoveWindow {
+                id,
+                zone,
+                position,
+                space,
+            } => {
+                let target = match (zone, position, space) {
+                    (Some(zone), None, None) => MoveTarget::Zone(zone),
+                    (None, Some(position), None) => MoveTarget::Position(position.into()),
+                    (None, None, Some(space)) => MoveTarget::Space(space),
+                    _ => {
+                        return Err("Exactly one of zone, position and space is needed".to_string())
+                    }
+                };
+                Action::MoveWindow {
+                    window: Some(id),
+                    target,
+                }
+            }
+            Request::SwitchSpace { space, output } => Action::SwitchSpace { space, output },
+            Request::Quit => Action::Quit,
+            Request::ConfigureOutput(settings) => {
+                return self
+                    .configure_output(settings)
+                    .map(|()| Value::Null)
+                    .map_err(|err| format!("{err:#}"))
+            }
+            Request::CreateOutput { mode } => {
+                return self
+                    .create_virtual_output(&mode)
+                    .map(Value::from)
+                    .map_err(|err| format!("{err:#}"))
+            }
+            Request::DestroyOutput { name } => {
+                return self
+                    .destroy_virtual_output(&name)
+                    .map(|()| Value::Null)
+                    .map_err(|err| format!("{err:#}"))
+            }
+            Request::Input(event) => {
+                return self
+                    .inject_input(event)
+                    .map(|()| Value::Null)
+                    .map_err(|err| format!("{err:#}"))
+            }
+            Request::ReloadConfig => {
+                return self
+                    .try_reload_config()
+                    .map(|()| Value::Null)
+                    .map_err(|err| format!("{err:#}"))
+            }
+            Request::Subscribe { events } => {
+                info!(?events, "Adding event subscriber");
+                self.command_subscribers
+                    .borrow_mut()
+                    .push(CommandSubscriber {
+                        outgoing: outgoing.clone(),
+                        events,
+                    });
+                return Ok(Value::Null);
+            }
+        };
+        self.try_execute(action)
+            .map(|()| Value::Null)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Id of the window whose id, title or app id is the name, in this order
+    fn window_id_by_name(&self, name: &str) -> Result<u64, String> {
+        let windows = self.config_snapshot().windows;
+        let by_id = name
+            .parse::<u64>()
+            .ok()
+            .filter(|id| windows.iter().any(|window| window.id == Some(*id)));
+        by_id
+            .or_else(|| {
+                windows
+                    .iter()
+                    .find(|window| window.title == name)
+                    .or_else(|| windows.iter().find(|window| window.app_id == name))
+                    .and_then(|window| window.id)
+            })
+            .ok_or_else(|| format!("There is no window named {name}"))
+    }
+}
+
+impl State {
+    /// Sends the event to the connections which subscribed to its kind. Connections which were
+    /// closed or do not keep up with reading are dropped.
+    pub fn broadcast_event(&self, event: CommandEvent) {
+        self.broadcast_i3_event(&event);
+        let mut subscribers = self.command_subscribers.borrow_mut();
+        let kind = event.kind();
+        if !subscribers
+            .iter()
+            .any(|subscriber| subscriber.events.contains(&kind))
+        {
+            return;
+        }
+        let line = match encode_line(&event) {
+            Ok(line) => line,
+            Err(err) => {
+                warn!(?err, "Unable to encode event");
+                return;
+            }
+        };
+        subscribers.retain(|subscriber| {
+            if !subscriber.events.contains(&kind) {
+                return true;
+            }
+            match subscriber.outgoing.send(&self.loop_handle, line.as_bytes()) {
+                Ok(()) => true,
+                Err(err) => {
+                    info!(?err, "Removing event subscriber");
+                    false
+                }
+            }
+        });
+    }
+}
+
+fn to_data(data: impl Serialize) -> Result<Value, String> {
+    serde_json::to_value(data).map_err(|err| err.to_string())
+}
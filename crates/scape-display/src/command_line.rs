@@ -0,0 +1,109 @@
+//! Splitting of command lines into the program and its arguments like a POSIX shell splits
+//! words, without expanding variables or globs
+
+use anyhow::bail;
+
+/// Splits the command line at unquoted whitespace. Single quotes keep everything literally,
+/// double quotes and backslashes escape like in a shell, so `foot -e 'htop -d 5'` results in
+/// `foot`, `-e` and `htop -d 5`.
+pub fn split_command_line(command_line: &str) -> anyhow::Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command_line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => bail!("Unterminated single quote in {command_line}"),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Within double quotes a backslash only escapes these characters
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => bail!("Unterminated double quote in {command_line}"),
+                        },
+                        Some(c) => word.push(c),
+                        None => bail!("Unterminated double quote in {command_line}"),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                // Line continuation
+                Some('\n') => {}
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => bail!("Trailing backslash in {command_line}"),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(command_line: &str) -> Vec<String> {
+        split_command_line(command_line).unwrap()
+    }
+
+    #[test]
+    fn splits_at_unquoted_whitespace() {
+        assert_eq!(split("  foot\t--server \n"), ["foot", "--server"]);
+        assert!(split("").is_empty());
+        assert!(split(" \t").is_empty());
+    }
+
+    #[test]
+    fn single_quotes_keep_everything() {
+        assert_eq!(split("foot -e 'htop -d 5'"), ["foot", "-e", "htop -d 5"]);
+        assert_eq!(split(r#"echo 'a "b" \c $d'"#), ["echo", r#"a "b" \c $d"#]);
+        assert_eq!(split("echo a'b c'd"), ["echo", "ab cd"]);
+    }
+
+    #[test]
+    fn double_quotes_escape_some_characters() {
+        assert_eq!(split(r#"echo "a b""#), ["echo", "a b"]);
+        assert_eq!(split(r#"echo "say \"hi\"""#), ["echo", r#"say "hi""#]);
+        assert_eq!(split(r#"echo "\\ \$ \` \n""#), ["echo", r#"\ $ ` \n"#]);
+        assert_eq!(split("echo \"a\\\nb\""), ["echo", "ab"]);
+        assert_eq!(split(r#"echo "'a'""#), ["echo", "'a'"]);
+    }
+
+    #[test]
+    fn empty_quotes_are_words() {
+        assert_eq!(split("echo '' \"\""), ["echo", "", ""]);
+        assert_eq!(split("''"), [""]);
+    }
+
+    #[test]
+    fn backslash_escapes_outside_quotes() {
+        assert_eq!(split(r"echo a\ b \'c"), ["echo", "a b", "'c"]);
+        assert_eq!(split("echo a\\\nb"), ["echo", "ab"]);
+    }
+
+    #[test]
+    fn incomplete_command_lines_fail() {
+        assert!(split_command_line(r"echo a\").is_err());
+        assert!(split_command_line("echo 'a").is_err());
+        assert!(split_command_line(r#"echo "a"#).is_err());
+        assert!(split_command_line(r#"echo "a\"#).is_err());
+    }
+}
@@ -1,17 +1,84 @@
-use crate::{config::ConfigZone, state::WindowRule, workspace_window::WorkspaceWindow, State};
+use crate::{
+    config::ConfigZone,
+    direction::Direction,
+    error::{Result, ScapeError},
+    shell::{floating_state, set_floating_state, FloatingState},
+    state::active_space,
+    workspace_window::WorkspaceWindow,
+    zone_tabs::TAB_BAR_HEIGHT,
+    State,
+};
 use smithay::{
-    desktop::layer_map_for_output,
-    utils::{Logical, Rectangle, SERIAL_COUNTER},
+    desktop::{layer_map_for_output, space::SpaceElement},
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
 };
 use tracing::{info, warn};
 
-#[derive(Debug)]
+/// Name prefix of the zones generated by quick layouts
+const QUICK_ZONE_PREFIX: &str = "quick";
+/// Zone edges this close to the edge of their output count as outer edges, configs often leave a
+/// pixel between zones
+pub const EDGE_TOLERANCE: i32 = 2;
+/// Share of the usable area of its output a window may cover at most when it is made floating
+const FLOATING_SIZE_PERCENTAGE: i32 = 75;
+
+#[derive(Debug, Clone)]
 pub struct Zone {
     pub name: String,
     pub geometry: Rectangle<i32, Logical>,
     pub default: bool,
 }
 
+/// Spacing windows in zones keep, `inner` between neighboring zones and `outer` to the edges of
+/// the output
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ZoneGaps {
+    pub inner: i32,
+    pub outer: i32,
+}
+
+impl ZoneGaps {
+    /// Shrinks the zone by the outer gap at the edges of the usable area of its output and by
+    /// half the inner gap at all other edges
+    fn apply(
+        &self,
+        zone: Rectangle<i32, Logical>,
+        area: Option<Rectangle<i32, Logical>>,
+    ) -> Rectangle<i32, Logical> {
+        let leading = |zone_edge: i32, area_edge: Option<i32>| {
+            if area_edge.is_some_and(|area_edge| zone_edge <= area_edge + EDGE_TOLERANCE) {
+                self.outer
+            } else {
+                self.inner / 2
+            }
+        };
+        let trailing = |zone_edge: i32, area_edge: Option<i32>| {
+            if area_edge.is_some_and(|area_edge| zone_edge >= area_edge - EDGE_TOLERANCE) {
+                self.outer
+            } else {
+                self.inner - self.inner / 2
+            }
+        };
+        let left = leading(zone.loc.x, area.map(|area| area.loc.x));
+        let top = leading(zone.loc.y, area.map(|area| area.loc.y));
+        let right = trailing(
+            zone.loc.x + zone.size.w,
+            area.map(|area| area.loc.x + area.size.w),
+        );
+        let bottom = trailing(
+            zone.loc.y + zone.size.h,
+            area.map(|area| area.loc.y + area.size.h),
+        );
+        Rectangle::from_loc_and_size(
+            (zone.loc.x + left, zone.loc.y + top),
+            (
+                (zone.size.w - left - right).max(1),
+                (zone.size.h - top - bottom).max(1),
+            ),
+        )
+    }
+}
+
 impl From<ConfigZone> for Zone {
     fn from(value: ConfigZone) -> Self {
         Self {
@@ -22,6 +89,31 @@ impl From<ConfigZone> for Zone {
     }
 }
 
+/// Zone a window is swapped into, either by its name or next to the zone of the window
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZoneTarget {
+    Direction(Direction),
+    Name(String),
+}
+
+impl ZoneTarget {
+    /// Directions are given as `left`, `right`, `up` or `down`, anything else is a zone name
+    pub fn from_name(name: &str) -> Self {
+        Direction::from_name(name)
+            .map(ZoneTarget::Direction)
+            .unwrap_or_else(|| ZoneTarget::Name(name.to_string()))
+    }
+}
+
+/// Where a window is moved to
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoveTarget {
+    Zone(String),
+    /// Top left corner of the window in global coordinates, the window leaves its zone
+    Position(Point<i32, Logical>),
+    Space(String),
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum WindowPosition {
     New,
@@ -39,21 +131,33 @@ impl State {
         zone: Option<&str>,
         send_configure: bool,
     ) -> Rectangle<i32, Logical> {
+        if let Some(geometry) =
+            self.place_kiosk_window(space_name, window, activate, send_configure)
+        {
+            return geometry;
+        }
         let pointer_location = self.pointer_location();
-        let space = self.spaces.get_mut(space_name).unwrap();
+        self.apply_theme_to_window(window);
+        let properties = self.window_properties(window);
+        let spawn_zone = self.spawn_zone(window);
+        let scratchpad = self.claim_scratchpad_window(window);
 
-        let (size, position) = if let Some(zone_name) = zone {
-            let zone = &self.zones[zone_name];
-            (zone.geometry.size, zone.geometry.loc)
-        } else if let Some(rule) = &self.window_rules.get(&window.app_id()) {
-            let zone = &self.zones[&rule.zone];
-            (zone.geometry.size, zone.geometry.loc)
-        } else if let Some(default_zone_name) = &self.default_zone {
-            let zone = &self.zones[default_zone_name];
-            (zone.geometry.size, zone.geometry.loc)
+        let zone = zone.or(spawn_zone.as_deref());
+        let floating = scratchpad || (zone.is_none() && properties.floating.unwrap_or(false));
+        let zone_geometry = zone
+            .or(properties.zone.as_deref())
+            .or(self.default_zone.as_deref())
+            .and_then(|zone_name| self.zones.get(zone_name))
+            .map(|zone| self.zone_window_geometry(zone));
+        let space = self.spaces.get_mut(space_name).unwrap();
+        let (mut size, mut position) = if let Some(geometry) = zone_geometry.filter(|_| !floating) {
+            (geometry.size, geometry.loc)
         } else {
             ((2560, 1440).into(), (100, 100).into())
         };
+        if let Some(rule_size) = properties.size {
+            size = rule_size;
+        }
 
         let output = space
             .output_under(pointer_location)
@@ -69,24 +173,370 @@ impl State {
             })
             .unwrap_or_else(|| Rectangle::from_loc_and_size((0, 0), (800, 800)));
 
+        if floating {
+            if properties.size.is_none() {
+                let geometry_size = window.geometry().size;
+                if geometry_size.w > 0 && geometry_size.h > 0 {
+                    size = geometry_size;
+                }
+            }
+            position = output_geometry.loc
+                + Point::from((
+                    (output_geometry.size.w - size.w) / 2,
+                    (output_geometry.size.h - size.h) / 2,
+                ));
+        }
+
         window.position(position, size, output_geometry.size, send_configure);
 
         space.map_element(window.clone(), position, activate);
         Rectangle::from_loc_and_size(position, size)
     }
 
-    pub fn set_zones(&mut self, zones: Vec<Zone>) {
+    /// Replaces the zones. Windows which fill a zone move along with it, e.g. when the config
+    /// recomputes the zones for a changed output.
+    pub fn set_zones(&mut self, zones: Vec<Zone>, gaps: ZoneGaps) {
+        // Bottom to top, so that mapping the windows again keeps their order
+        let zoned_windows = self
+            .spaces
+            .iter()
+            .flat_map(|(space_name, space)| {
+                space
+                    .elements()
+                    .filter(|window| matches!(window, WorkspaceWindow::ApplicationWindow(_)))
+                    .filter_map(move |window| {
+                        let location = space.element_location(window)?;
+                        let size = window.geometry().size;
+                        let geometry = Rectangle::from_loc_and_size(location, size);
+                        Some((space_name.clone(), window.clone(), geometry))
+                    })
+            })
+            .filter_map(|(space_name, window, geometry)| {
+                let zone_name = self.zone_at(geometry)?;
+                Some((space_name, window, geometry, zone_name))
+            })
+            .collect::<Vec<_>>();
+
         self.zones.clear();
+        self.zone_gaps = gaps;
         for zone in zones {
             if zone.default {
                 self.default_zone = Some(zone.name.clone());
             }
             self.zones.insert(zone.name.clone(), zone);
         }
+        // Zones split at runtime are split again, also when the config moved them
+        self.apply_zone_trees();
+
+        for (space_name, window, geometry, zone_name) in zoned_windows {
+            let Some(zone) = self.zones.get(&zone_name) else {
+                continue;
+            };
+            if self.zone_window_geometry(zone) != geometry {
+                self.place_window(&space_name, &window, false, Some(&zone_name), true);
+            }
+        }
+    }
+
+    /// Area a window in the zone fills, the zone without its gaps and the tab bar of tabbed zones
+    pub fn zone_window_geometry(&self, zone: &Zone) -> Rectangle<i32, Logical> {
+        let area = self.outputs.values().find_map(|output| {
+            let geometry = self
+                .spaces
+                .values()
+                .find_map(|space| space.output_geometry(output))?;
+            let usable_zone = layer_map_for_output(output).non_exclusive_zone();
+            Some(Rectangle::from_loc_and_size(
+                geometry.loc + usable_zone.loc,
+                usable_zone.size,
+            ))
+            .filter(|area| area.overlaps(zone.geometry))
+        });
+        let geometry = self.zone_gaps.apply(zone.geometry, area);
+        if !self.tabbed_zones.contains(&zone.name) {
+            return geometry;
+        }
+        Rectangle::from_loc_and_size(
+            (geometry.loc.x, geometry.loc.y + TAB_BAR_HEIGHT),
+            (geometry.size.w, (geometry.size.h - TAB_BAR_HEIGHT).max(1)),
+        )
+    }
+
+    /// Zone a window with the geometry was placed in. Windows in a zone are mapped at its
+    /// location, the size can differ slightly.
+    pub fn zone_at(&self, geometry: Rectangle<i32, Logical>) -> Option<String> {
+        let zones_at_location = self
+            .zones
+            .values()
+            .map(|zone| (zone, self.zone_window_geometry(zone)))
+            .filter(|(_, zone_geometry)| zone_geometry.loc == geometry.loc)
+            .collect::<Vec<_>>();
+        zones_at_location
+            .iter()
+            .find(|(_, zone_geometry)| zone_geometry.size == geometry.size)
+            .or_else(|| zones_at_location.first())
+            .map(|(zone, _)| zone.name.clone())
+    }
+
+    /// Smallest zone containing the location, so that a zone nested in another one can be hit
+    pub fn zone_under(&self, location: Point<f64, Logical>) -> Option<String> {
+        self.zones
+            .values()
+            .filter(|zone| zone.geometry.to_f64().contains(location))
+            .min_by_key(|zone| zone.geometry.size.w * zone.geometry.size.h)
+            .map(|zone| zone.name.clone())
+    }
+
+    /// Splits the output under the pointer into a grid of `columns` x `rows` zones and moves the
+    /// windows on it into them, topmost window first. The generated zones replace the zones of
+    /// the output until the config sets zones again, zones of other outputs are kept.
+    pub fn apply_quick_layout(&mut self, columns: u32, rows: u32) -> Result<()> {
+        let space_name = self.space_under_pointer()?;
+        let pointer_location = self.pointer_location();
+        let space = self.space(&space_name)?;
+        let output = space
+            .output_under(pointer_location)
+            .next()
+            .or_else(|| space.outputs().next())
+            .cloned()
+            .ok_or_else(|| ScapeError::NoOutput(space_name.clone()))?;
+        let output_geometry = space
+            .output_geometry(&output)
+            .ok_or_else(|| ScapeError::NoOutput(space_name.clone()))?;
+        let usable_zone = layer_map_for_output(&output).non_exclusive_zone();
+        let area =
+            Rectangle::from_loc_and_size(output_geometry.loc + usable_zone.loc, usable_zone.size);
+
+        let (columns, rows) = (columns.max(1) as i32, rows.max(1) as i32);
+        let zones = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .enumerate()
+            .map(|(index, (column, row))| {
+                let x = area.loc.x + area.size.w * column / columns;
+                let y = area.loc.y + area.size.h * row / rows;
+                let width = area.loc.x + area.size.w * (column + 1) / columns - x;
+                let height = area.loc.y + area.size.h * (row + 1) / rows - y;
+                Zone {
+                    name: format!("{QUICK_ZONE_PREFIX}-{}-{}", output.name(), index + 1),
+                    geometry: Rectangle::from_loc_and_size((x, y), (width, height)),
+                    default: index == 0,
+                }
+            })
+            .collect::<Vec<_>>();
+        let zone_names = zones
+            .iter()
+            .map(|zone| zone.name.clone())
+            .collect::<Vec<_>>();
+
+        let windows = space
+            .elements()
+            .rev()
+            .filter(|window| matches!(window, WorkspaceWindow::ApplicationWindow(_)))
+            .filter(|window| space.outputs_for_element(window).contains(&output))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        info!(
+            output = output.name(),
+            columns, rows, "Applying quick layout"
+        );
+        let zones = replace_output_zones(self.zones.values().cloned(), output_geometry, zones);
+        self.set_zones(zones, self.zone_gaps);
+        for (index, window) in windows.iter().enumerate() {
+            let zone = &zone_names[index % zone_names.len()];
+            self.place_window(&space_name, window, false, Some(zone), true);
+        }
+        self.backend_data.schedule_render();
+        Ok(())
     }
 
+    /// Exchanges the zone of the window with the id, or of the focused window, with the topmost
+    /// window in the target zone. Without a window there the window just moves into the zone.
+    pub fn swap_windows(&mut self, window_id: Option<u64>, target: &ZoneTarget) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (space_name, window) = self.window_by_id(window_id)?;
+        let space = self.space(&space_name)?;
+        let location = space
+            .element_location(&window)
+            .ok_or_else(|| ScapeError::WindowNotMapped(space_name.clone()))?;
+        let geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+        let Some(zone) = self.zone_at(geometry) else {
+            info!(window_id, "Not swapping window, it is in no zone");
+            return Ok(());
+        };
+
+        let target_zone = match target {
+            ZoneTarget::Name(zone_name) => {
+                if !self.zones.contains_key(zone_name) {
+                    return Err(ScapeError::ZoneNotFound(zone_name.clone()));
+                }
+                zone_name.clone()
+            }
+            ZoneTarget::Direction(direction) => {
+                let zone_geometry = self.zones[&zone].geometry;
+                let zones = self
+                    .zones
+                    .values()
+                    .filter(|other| other.name != zone)
+                    .map(|other| (other.name.clone(), other.geometry));
+                let Some(zone_name) = direction.closest(zone_geometry, zones) else {
+                    info!(?direction, zone, "No zone to swap the window with");
+                    return Ok(());
+                };
+                zone_name
+            }
+        };
+        if target_zone == zone {
+            return Ok(());
+        }
+
+        let other_window = space
+            .elements()
+            .rev()
+            .filter(|other| matches!(other, WorkspaceWindow::ApplicationWindow(_)))
+            .filter(|other| **other != window)
+            .find(|other| {
+                space.element_location(other).is_some_and(|location| {
+                    let geometry = Rectangle::from_loc_and_size(location, other.geometry().size);
+                    self.zone_at(geometry).as_ref() == Some(&target_zone)
+                })
+            })
+            .cloned();
+        info!(window_id, zone, target_zone, "Swapping windows");
+        if let Some(other_window) = other_window {
+            self.place_window(&space_name, &other_window, false, Some(&zone), true);
+        }
+        self.place_window(&space_name, &window, true, Some(&target_zone), true);
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Pops the window with the id, or the focused window, out of its zone and centers it on its
+    /// output. Toggling a floating window puts it back into the zone or at the geometry it had.
+    pub fn toggle_floating(&mut self, window_id: Option<u64>) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (space_name, window) = self.window_by_id(window_id)?;
+        let Some(surface) = window.wl_surface().map(|surface| surface.into_owned()) else {
+            warn!(
+                window_id,
+                "Unable to toggle floating, the window has no surface yet"
+            );
+            return Ok(());
+        };
+
+        if let Some(previous) = floating_state(&surface) {
+            set_floating_state(&surface, None);
+            info!(window_id, zone = ?previous.zone, "Returning window from floating");
+            match previous.zone.filter(|zone| self.zones.contains_key(zone)) {
+                Some(zone) => {
+                    self.place_window(&space_name, &window, false, Some(&zone), true);
+                }
+                None => self.place_window_at(&space_name, &window, previous.geometry, true)?,
+            }
+            self.backend_data.schedule_render();
+            return Ok(());
+        }
+
+        let space = self.space(&space_name)?;
+        let location = space
+            .element_location(&window)
+            .ok_or_else(|| ScapeError::WindowNotMapped(space_name.clone()))?;
+        let geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+        let output = space
+            .outputs_for_element(&window)
+            .into_iter()
+            .next()
+            .or_else(|| space.outputs().next().cloned())
+            .ok_or_else(|| ScapeError::NoOutput(space_name.clone()))?;
+        let output_geometry = space
+            .output_geometry(&output)
+            .ok_or_else(|| ScapeError::NoOutput(space_name.clone()))?;
+        let usable_zone = layer_map_for_output(&output).non_exclusive_zone();
+        let area =
+            Rectangle::from_loc_and_size(output_geometry.loc + usable_zone.loc, usable_zone.size);
+
+        let zone = self.zone_at(geometry);
+        // Windows filling a large zone would still cover it, they shrink to the floating size
+        let size = (
+            geometry
+                .size
+                .w
+                .min(area.size.w * FLOATING_SIZE_PERCENTAGE / 100),
+            geometry
+                .size
+                .h
+                .min(area.size.h * FLOATING_SIZE_PERCENTAGE / 100),
+        );
+        let floating = Rectangle::from_loc_and_size(
+            area.loc + Point::from(((area.size.w - size.0) / 2, (area.size.h - size.1) / 2)),
+            size,
+        );
+        info!(window_id, ?zone, "Making window floating");
+        set_floating_state(&surface, Some(FloatingState { zone, geometry }));
+        self.place_window_at(&space_name, &window, floating, true)?;
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Moves the window with the id, or the focused window if none is given, to the target
+    pub fn move_window(&mut self, window_id: Option<u64>, target: &MoveTarget) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        match target {
+            MoveTarget::Zone(zone) => {
+                if !self.zones.contains_key(zone) {
+                    return Err(ScapeError::ZoneNotFound(zone.clone()));
+                }
+                let (space_name, window) = self.window_by_id(window_id)?;
+                info!(window_id, zone, "Moving window to zone");
+                self.place_window(&space_name, &window, false, Some(zone), true);
+            }
+            MoveTarget::Position(position) => {
+                let (space_name, window) = self.window_by_id(window_id)?;
+                info!(window_id, ?position, "Moving window to position");
+                let geometry = Rectangle::from_loc_and_size(*position, window.geometry().size);
+                self.place_window_at(&space_name, &window, geometry, true)?;
+            }
+            MoveTarget::Space(space_name) => {
+                self.move_window_to_space(Some(window_id), space_name)?;
+            }
+        }
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Maps the window at the geometry and raises it
+    pub fn place_window_at(
+        &mut self,
+        space_name: &str,
+        window: &WorkspaceWindow,
+        geometry: Rectangle<i32, Logical>,
+        send_configure: bool,
+    ) -> Result<()> {
+        let space = self.space_mut(space_name)?;
+        let bounds = space
+            .outputs_for_element(window)
+            .first()
+            .and_then(|output| space.output_geometry(output))
+            .map_or(geometry.size, |output_geometry| output_geometry.size);
+        window.position(geometry.loc, geometry.size, bounds, send_configure);
+        space.map_element(window.clone(), geometry.loc, true);
+        Ok(())
+    }
+
+    /// Focuses a window with the app id, the focused space is searched first. A window on a hidden
+    /// space is shown by switching to its space.
     pub fn focus_window_by_app_id(&mut self, app_id: String) -> bool {
-        if let Some((space_name, space)) = self.spaces.iter().next() {
+        let focused_space = self.focused_space();
+        let mut space_names = self.spaces.keys().cloned().collect::<Vec<_>>();
+        space_names.sort_by_key(|space_name| Some(space_name) != focused_space.as_ref());
+        for space_name in space_names {
+            let space = &self.spaces[&space_name];
             let mut window_result = None;
             let mut last = false;
             for (i, window) in space.elements().rev().enumerate() {
@@ -103,26 +553,176 @@ impl State {
                 };
             }
             if let Some(window) = window_result {
-                self.focus_window(window, &space_name.to_owned());
+                if let Err(err) = self.show_window(window, &space_name) {
+                    warn!(%err, app_id, "Unable to show window");
+                    return false;
+                }
                 return true;
             }
         }
         false
     }
 
+    /// Focuses the nearest visible window in the direction of the focused window, or of the
+    /// pointer if no window is focused
+    pub fn focus_direction(&mut self, direction: Direction) -> Result<()> {
+        let focused = self
+            .focused_window()
+            .and_then(|window| Some((self.space_of_window(&window)?, window)));
+        let (space_name, from) = match &focused {
+            Some((space_name, window)) => {
+                let location = self
+                    .space(space_name)?
+                    .element_location(window)
+                    .ok_or_else(|| ScapeError::WindowNotMapped(space_name.clone()))?;
+                let geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+                (space_name.clone(), geometry)
+            }
+            None => {
+                let output = self.output_under_pointer().ok_or(ScapeError::NoOutputs)?;
+                let space_name = active_space(&output).ok_or(ScapeError::NoSpace)?;
+                let location = self.pointer_location().to_i32_round();
+                (space_name, Rectangle::from_loc_and_size(location, (1, 1)))
+            }
+        };
+
+        let space = self.space(&space_name)?;
+        let windows = space
+            .elements()
+            .filter(|window| matches!(window, WorkspaceWindow::ApplicationWindow(_)))
+            .filter(|window| focused.as_ref().map(|(_, focused)| focused) != Some(*window))
+            .filter(|window| !space.outputs_for_element(window).is_empty())
+            .filter_map(|window| {
+                let location = space.element_location(window)?;
+                let geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+                Some((window.clone(), geometry))
+            });
+        let Some(window) = direction.closest(from, windows) else {
+            info!(?direction, "No window to focus");
+            return Ok(());
+        };
+        info!(
+            app_id = window.app_id(),
+            ?direction,
+            "Focusing window in direction"
+        );
+        self.focus_window(window, &space_name);
+        Ok(())
+    }
+
     pub fn focus_window(&mut self, window: WorkspaceWindow, space_name: &str) {
         let Some(space) = self.spaces.get_mut(space_name) else {
             warn!(%space_name, ?window, "Trying to focus window, but space does not exist");
             return;
         };
         space.raise_element(&window, true);
-        let keyboard = self.seat.as_ref().unwrap().get_keyboard().unwrap();
+        let window = self
+            .raise_child_toplevels(space_name, &window)
+            .unwrap_or(window);
+        let keyboard = match self.keyboard() {
+            Ok(keyboard) => keyboard,
+            Err(err) => {
+                warn!(%err, "Unable to focus window");
+                return;
+            }
+        };
         let serial = SERIAL_COUNTER.next_serial();
         keyboard.set_focus(self, Some(window.into()), serial);
     }
+}
+
+/// The zones with those overlapping the output replaced by `replacement`. The replacement only
+/// provides the default zone if the default zone was on the output.
+fn replace_output_zones(
+    zones: impl IntoIterator<Item = Zone>,
+    output_geometry: Rectangle<i32, Logical>,
+    replacement: Vec<Zone>,
+) -> Vec<Zone> {
+    let mut zones = zones
+        .into_iter()
+        .filter(|zone| !zone.geometry.overlaps(output_geometry))
+        .collect::<Vec<_>>();
+    let has_default = zones.iter().any(|zone| zone.default);
+    zones.extend(replacement.into_iter().map(|zone| Zone {
+        default: zone.default && !has_default,
+        ..zone
+    }));
+    zones
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(name: &str, (x, y, w, h): (i32, i32, i32, i32), default: bool) -> Zone {
+        Zone {
+            name: name.to_string(),
+            geometry: Rectangle::from_loc_and_size((x, y), (w, h)),
+            default,
+        }
+    }
+
+    fn names(zones: &[Zone]) -> Vec<&str> {
+        let mut names = zones
+            .iter()
+            .map(|zone| zone.name.as_str())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn quick_layout_keeps_zones_of_other_outputs() {
+        let left_output = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+        let zones = vec![
+            zone("left", (0, 0, 960, 1080), true),
+            zone("right", (960, 0, 960, 1080), false),
+            zone("second-left", (1920, 0, 1280, 1024), false),
+            zone("second-right", (3200, 0, 1280, 1024), false),
+        ];
+        let replacement = vec![
+            zone("quick-DP-1-1", (0, 0, 640, 1080), true),
+            zone("quick-DP-1-2", (640, 0, 640, 1080), false),
+            zone("quick-DP-1-3", (1280, 0, 640, 1080), false),
+        ];
+
+        let zones = replace_output_zones(zones, left_output, replacement);
+        assert_eq!(
+            names(&zones),
+            [
+                "quick-DP-1-1",
+                "quick-DP-1-2",
+                "quick-DP-1-3",
+                "second-left",
+                "second-right"
+            ]
+        );
+        let default = zones.iter().find(|zone| zone.default).unwrap();
+        assert_eq!(default.name, "quick-DP-1-1");
+    }
+
+    #[test]
+    fn quick_layout_keeps_default_zone_of_other_output() {
+        let second_output = Rectangle::from_loc_and_size((1920, 0), (2560, 1024));
+        let zones = vec![
+            zone("left", (0, 0, 1920, 1080), true),
+            zone("second", (1920, 0, 2560, 1024), false),
+        ];
+        let replacement = vec![
+            zone("quick-HDMI-A-1-1", (1920, 0, 1280, 1024), true),
+            zone("quick-HDMI-A-1-2", (3200, 0, 1280, 1024), false),
+        ];
 
-    pub fn add_window_rule(&mut self, window_rule: WindowRule) {
-        self.window_rules
-            .insert(window_rule.app_id.clone(), window_rule);
+        let zones = replace_output_zones(zones, second_output, replacement);
+        assert_eq!(
+            names(&zones),
+            ["left", "quick-HDMI-A-1-1", "quick-HDMI-A-1-2"]
+        );
+        let defaults = zones
+            .iter()
+            .filter(|zone| zone.default)
+            .map(|zone| zone.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(defaults, ["left"]);
     }
 }
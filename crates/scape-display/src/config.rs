@@ -1,28 +1,107 @@
 use crate::action::Action;
+use crate::command::auth::CommandAuth;
+use crate::command::{CommandEvent, OutputChange, WindowChange};
+use crate::command_line::split_command_line;
+use crate::composition::{MoveTarget, ZoneGaps, ZoneTarget};
 use crate::config_watcher::ConfigWatcher;
-use crate::input_handler::Mods;
-use crate::state::ActiveSpace;
-use crate::state::WindowRule;
+use crate::direction::Direction;
+use crate::error::ScapeError;
+use crate::focus::FocusConfig;
+use crate::idle::IdleTimeouts;
+use crate::input_config::{accel_profile_from_name, InputConfig};
+use crate::input_handler::{KeyBinding, KeyboardConfig, Mods, DEFAULT_KEY_MODE};
+use crate::key_chord::{ChordKey, KeyChord, KeyChordConfig};
+use crate::key_repeat::KeyRepeatConfig;
+use crate::keystroke_visualizer::{KeystrokeVisualizerConfig, OverlayCorner};
+use crate::kiosk::KioskConfig;
+use crate::layout::transform_from_name;
+use crate::lua_timer::next_timer_id;
+use crate::modifier_indicator::ModifierIndicatorConfig;
+use crate::move_mode::MoveModeConfig;
+use crate::output_profile::{OutputProfile, ProfileOutput, DEFAULT_PROFILE_SPACE};
+use crate::pointer_bindings::{button_from_name, ButtonBinding};
+use crate::recording::{RecordingConfig, VideoCodec};
+use crate::screenshot::ScreenshotTarget;
+use crate::snap::{DragConfig, DropBehavior};
+use crate::spaces::OutputTarget;
+use crate::startup::{ReadyCondition, StartupEntry};
+use crate::theme::{Palette, Theme, ThemeVariant};
+use crate::udev::OutputSerial;
+use crate::window_resize::resize_edge_from_name;
+use crate::window_rules::{WindowMatch, WindowProperties, WindowRule};
+use crate::workspace_window::WorkspaceWindow;
+use crate::zone_layout::ZoneLayout;
+use crate::zone_tree::SplitAxis;
 use crate::State;
-use calloop::LoopHandle;
+use anyhow::{ensure, Context};
+use calloop::timer::{TimeoutAction, Timer};
+use calloop::{EventLoop, LoopHandle};
 use mlua::prelude::*;
-use mlua::Table;
+use mlua::{RegistryKey, Table, Variadic};
 use scape_shared::GlobalArgs;
+use serde::{Deserialize, Serialize};
+use smithay::desktop::space::SpaceElement;
+use smithay::desktop::Space;
 use smithay::output::Output;
 use smithay::output::Scale;
 use smithay::utils::Logical;
 use smithay::utils::Point;
-use std::collections::HashMap;
+use smithay::utils::Rectangle;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::OsStr;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+use tracing::error;
 use tracing::info;
 use tracing::warn;
-use xkbcommon::xkb::Keysym;
+use xkbcommon::xkb::{self, Keysym};
+
+/// Function of the config kept in the registry of its Lua instance. Handles are cheap to clone,
+/// the function is freed once the last handle is dropped.
+#[derive(Debug, Clone)]
+pub struct LuaCallback(Rc<RegistryKey>);
+
+impl LuaCallback {
+    pub fn function<'lua>(&self, lua: &'lua Lua) -> LuaResult<LuaFunction<'lua>> {
+        lua.registry_value(&self.0)
+    }
+}
+
+impl<'lua> FromLua<'lua> for LuaCallback {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let function = LuaFunction::from_lua(value, lua)?;
+        Ok(LuaCallback(Rc::new(lua.create_registry_value(function)?)))
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
     lua: Lua,
-    on_startup: Option<LuaFunction<'static>>,
-    on_connector_change: Option<LuaFunction<'static>>,
+    on_startup: Option<LuaCallback>,
+    on_shutdown: Option<LuaCallback>,
+    on_suspend: Option<LuaCallback>,
+    on_resume: Option<LuaCallback>,
+    on_connector_change: Option<LuaCallback>,
+    on_output_added: Option<LuaCallback>,
+    on_output_removed: Option<LuaCallback>,
+    on_pointer_capture: Option<LuaCallback>,
+    on_window_open: Option<LuaCallback>,
+    on_window_close: Option<LuaCallback>,
+    on_window_focus: Option<LuaCallback>,
+    on_window_title_changed: Option<LuaCallback>,
+    on_window_not_responding: Option<LuaCallback>,
+    /// Commands registered by the config, run by key bindings or `scape cli run`
+    commands: HashMap<String, LuaCallback>,
+    first_run: bool,
+    /// The user config failed to run, the bundled default config is used instead
+    fallback: bool,
+    /// Recent failures of the config, newest last, shown in the debug UI
+    errors: RefCell<VecDeque<String>>,
+    /// Config file given on the command line, the XDG config directories are searched otherwise
+    path: Option<PathBuf>,
 }
 
 impl Config {
@@ -30,13 +109,125 @@ impl Config {
         Config {
             lua: Lua::new(),
             on_startup: None,
+            on_shutdown: None,
+            on_suspend: None,
+            on_resume: None,
             on_connector_change: None,
+            on_output_added: None,
+            on_output_removed: None,
+            on_pointer_capture: None,
+            on_window_open: None,
+            on_window_close: None,
+            on_window_focus: None,
+            on_window_title_changed: None,
+            on_window_not_responding: None,
+            commands: HashMap::new(),
+            first_run: false,
+            fallback: false,
+            errors: RefCell::new(VecDeque::new()),
+            path: None,
         }
     }
 
     pub fn stop(&mut self) {
         self.on_startup = None;
+        self.on_shutdown = None;
+        self.on_suspend = None;
+        self.on_resume = None;
         self.on_connector_change = None;
+        self.on_output_added = None;
+        self.on_output_removed = None;
+        self.on_pointer_capture = None;
+        self.on_window_open = None;
+        self.on_window_close = None;
+        self.on_window_focus = None;
+        self.on_window_title_changed = None;
+        self.on_window_not_responding = None;
+        self.commands.clear();
+    }
+
+    /// Calls a callback of the config. A failing callback is logged with its traceback and shown
+    /// in the debug UI, but does not affect the compositor otherwise.
+    pub fn call<'lua>(
+        &'lua self,
+        name: &str,
+        callback: &LuaCallback,
+        args: impl IntoLuaMulti<'lua>,
+    ) {
+        let res = callback
+            .function(&self.lua)
+            .and_then(|function| function.call::<_, ()>(args));
+        if let Err(err) = res {
+            self.report_error(name, err.to_string());
+        }
+    }
+
+    /// Runs a compiled config chunk, failures are reported like the ones of callbacks
+    fn run_chunk(&self, name: &str, chunk: &LuaFunction<'_>) -> LuaResult<()> {
+        let res = chunk.call::<_, ()>(());
+        if let Err(err) = &res {
+            self.report_error(name, err.to_string());
+        }
+        res
+    }
+
+    /// Frees the functions of callbacks which are not referenced anymore, e.g. the key bindings
+    /// of the config before it was reloaded
+    fn expire_callbacks(&self) {
+        self.lua.expire_registry_values();
+    }
+
+    /// Logs a failure of the config and keeps it for the debug UI
+    pub fn report_error(&self, name: &str, message: String) {
+        error!("{name} failed: {message}");
+        let mut errors = self.errors.borrow_mut();
+        if errors.len() == MAX_REPORTED_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(format!("{name}: {message}"));
+    }
+
+    /// Recent failures of the config, newest last
+    pub fn errors(&self) -> Vec<String> {
+        self.errors.borrow().iter().cloned().collect()
+    }
+
+    /// The config file given on the command line or the first `scape/init.lua` in
+    /// `$XDG_CONFIG_HOME` and `$XDG_CONFIG_DIRS`. `None` if no config exists, in which case the
+    /// bundled default config is used.
+    fn file(&self) -> anyhow::Result<Option<PathBuf>> {
+        if let Some(path) = &self.path {
+            ensure!(
+                path.is_file(),
+                "Config file {} does not exist",
+                path.display()
+            );
+            return Ok(Some(path.clone()));
+        }
+        let xdg_dirs = xdg::BaseDirectories::with_prefix(XDG_PREFIX)?;
+        Ok(xdg_dirs.find_config_file(CONFIG_FILE_NAME))
+    }
+
+    /// Directory which is watched for config changes
+    fn watch_dir(&self) -> anyhow::Result<PathBuf> {
+        match self.file()? {
+            Some(path) => Ok(path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."))
+                .to_path_buf()),
+            None => Ok(xdg::BaseDirectories::with_prefix(XDG_PREFIX)?.get_config_home()),
+        }
+    }
+
+    /// Whether a change of the file at `path` requires a reload
+    fn is_config_file(&self, path: &Path) -> bool {
+        let file_name = self
+            .path
+            .as_deref()
+            .and_then(Path::file_name)
+            .unwrap_or(OsStr::new(CONFIG_FILE_NAME));
+        path.file_name() == Some(file_name)
     }
 }
 
@@ -54,27 +245,416 @@ impl State {
     pub fn on_startup(&mut self) {
         info!("running on startup");
         if let Some(on_startup) = &self.config.on_startup {
-            on_startup.call::<_, ()>(()).unwrap();
+            self.call_config("on_startup callback", on_startup, ());
+        }
+    }
+
+    /// Notifies the config that the compositor stops, after the event loop ended
+    pub fn on_shutdown(&mut self) {
+        info!("running on shutdown");
+        if let Some(on_shutdown) = &self.config.on_shutdown {
+            self.call_config("on_shutdown callback", on_shutdown, ());
+        }
+    }
+
+    /// Notifies the config that the system is about to suspend
+    pub fn on_suspend(&mut self) {
+        info!("running on suspend");
+        if let Some(on_suspend) = &self.config.on_suspend {
+            self.call_config("on_suspend callback", on_suspend, ());
+        }
+    }
+
+    /// Notifies the config that the system woke up from suspend
+    pub fn on_resume(&mut self) {
+        info!("running on resume");
+        if let Some(on_resume) = &self.config.on_resume {
+            self.call_config("on_resume callback", on_resume, ());
         }
     }
 
+    /// Applies the matching output profile, or runs the on_connector_change callback if none
+    /// matches, once connectors stopped changing for a moment
     pub fn on_connector_change(&mut self) {
+        if let Some(token) = self.connector_change_timer.take() {
+            self.loop_handle.remove(token);
+        }
+
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(CONNECTOR_CHANGE_DEBOUNCE),
+            |_, _, state| {
+                state.connector_change_timer = None;
+                state.run_on_connector_change();
+                TimeoutAction::Drop
+            },
+        );
+        match res {
+            Ok(token) => self.connector_change_timer = Some(token),
+            Err(err) => warn!(?err, "Unable to debounce connector change"),
+        }
+    }
+
+    fn run_on_connector_change(&mut self) {
+        info!("running on connector change");
+        if self.apply_output_profiles() {
+            return;
+        }
+        if let Some(on_connector_change) = &self.config.on_connector_change {
+            let config_outputs = self
+                .outputs
+                .values()
+                .map(|output| {
+                    let mut config_output = ConfigOutput::from(output);
+                    config_output.disabled = self.disabled_outputs.contains_key(&output.name());
+                    config_output
+                })
+                .collect();
+
+            self.call_config(
+                "on_connector_change callback",
+                on_connector_change,
+                config_outputs,
+            );
+        } else if self.config.first_run {
+            self.show_first_run_wizard();
+        } else if self.config.fallback {
+            self.apply_fallback_layout();
+        } else {
+            info!("No on_connector_change callback set");
+        }
+    }
+
+    /// Notifies the config about a newly connected output. It runs right away, before the
+    /// debounced on_connector_change callback.
+    pub fn on_output_added(&self, output: &Output) {
+        if let Some(on_output_added) = &self.config.on_output_added {
+            let device = ConfigOutputDevice::from(output);
+            self.call_config("on_output_added callback", on_output_added, device);
+        }
+        self.broadcast_event(CommandEvent::Output {
+            change: OutputChange::Added,
+            output: ConfigOutputDevice::from(output),
+        });
+    }
+
+    /// Notifies the config about a disconnected output
+    pub fn on_output_removed(&self, output: &Output) {
+        if let Some(on_output_removed) = &self.config.on_output_removed {
+            let device = ConfigOutputDevice::from(output);
+            self.call_config("on_output_removed callback", on_output_removed, device);
+        }
+        self.broadcast_event(CommandEvent::Output {
+            change: OutputChange::Removed,
+            output: ConfigOutputDevice::from(output),
+        });
+    }
+
+    /// Notifies the config that a client started or stopped locking or confining the pointer
+    pub fn on_pointer_capture(&self, captured: bool, app_id: Option<String>) {
+        if let Some(on_pointer_capture) = &self.config.on_pointer_capture {
+            self.call_config(
+                "on_pointer_capture callback",
+                on_pointer_capture,
+                (captured, app_id),
+            );
+        }
+    }
+
+    /// Notifies the config that a window got mapped
+    pub fn on_window_open(&self, window: &WorkspaceWindow, space_name: &str) {
+        let config_window = self.config_window(window, space_name);
+        self.broadcast_window_event(WindowChange::Opened, &config_window);
+        self.call_window_callback(&self.config.on_window_open, "on_window_open", config_window);
+    }
+
+    /// Notifies the config that a window got unmapped or destroyed
+    pub fn on_window_close(&self, window: &WorkspaceWindow, space_name: &str) {
+        let config_window = self.config_window(window, space_name);
+        self.broadcast_window_event(WindowChange::Closed, &config_window);
+        self.call_window_callback(
+            &self.config.on_window_close,
+            "on_window_close",
+            config_window,
+        );
+    }
+
+    /// Notifies the config that a window received keyboard focus
+    pub fn on_window_focus(&self, window: &WorkspaceWindow, space_name: &str) {
+        let config_window = self.config_window(window, space_name);
+        self.broadcast_window_event(WindowChange::Focused, &config_window);
+        self.call_window_callback(
+            &self.config.on_window_focus,
+            "on_window_focus",
+            config_window,
+        );
+    }
+
+    /// Notifies the config that the title of a window changed
+    pub fn on_window_title_changed(&self, window: &WorkspaceWindow, space_name: &str) {
+        let config_window = self.config_window(window, space_name);
+        self.broadcast_window_event(WindowChange::TitleChanged, &config_window);
+        self.call_window_callback(
+            &self.config.on_window_title_changed,
+            "on_window_title_changed",
+            config_window,
+        );
+    }
+
+    /// Notifies the config that a window did not close or answer in time after it was asked to
+    /// close. Closing it again kills its client.
+    pub fn on_window_not_responding(&self, window: &WorkspaceWindow, space_name: &str) {
+        let config_window = self.config_window(window, space_name);
+        self.call_window_callback(
+            &self.config.on_window_not_responding,
+            "on_window_not_responding",
+            config_window,
+        );
+    }
+
+    /// Runs the command the config registered under the name, the arguments are passed to it
+    pub fn run_user_command(&self, name: &str, args: Vec<String>) -> crate::error::Result<()> {
+        let callback = self
+            .config
+            .commands
+            .get(name)
+            .ok_or_else(|| ScapeError::CommandNotFound(name.to_string()))?;
+        info!(name, ?args, "Running command");
+        self.refresh_config_snapshot();
+        let res = callback
+            .function(&self.config.lua)
+            .and_then(|function| function.call::<_, ()>(Variadic::from_iter(args)));
+        res.map_err(|err| {
+            self.config
+                .report_error(&format!("Command {name}"), err.to_string());
+            ScapeError::from(err)
+        })
+    }
+
+    /// Runs a chunk of Lua with access to the `scape` module of the config. Its result is
+    /// returned if Lua can convert it to a string.
+    pub fn run_lua(&self, code: &str) -> crate::error::Result<Option<String>> {
+        info!("Running Lua chunk");
+        self.refresh_config_snapshot();
+        let lua = &self.config.lua;
+        let value = lua
+            .load(code)
+            .set_name("command socket")
+            .eval::<LuaValue>()?;
+        let result = lua.coerce_string(value)?;
+        Ok(result.map(|result| result.to_string_lossy().into_owned()))
+    }
+
+    fn broadcast_window_event(&self, change: WindowChange, window: &ConfigWindow) {
+        self.broadcast_event(CommandEvent::Window {
+            change,
+            window: window.clone(),
+        });
+    }
+
+    fn call_window_callback(
+        &self,
+        callback: &Option<LuaCallback>,
+        name: &str,
+        window: ConfigWindow,
+    ) {
+        if let Some(callback) = callback {
+            self.call_config(&format!("{name} callback"), callback, window);
+        }
+    }
+
+    pub fn focused_window(&self) -> Option<WorkspaceWindow> {
+        let focus = self.keyboard().ok()?.current_focus()?;
+        WorkspaceWindow::try_from(focus).ok()
+    }
+
+    fn config_window(&self, window: &WorkspaceWindow, space_name: &str) -> ConfigWindow {
+        let focused = self.focused_window().as_ref() == Some(window);
+        ConfigWindow::new(window, self.spaces.get(space_name), space_name, focused)
+    }
+
+    /// Updates the state returned by `get_windows`, `get_outputs` and `get_spaces`. Lua can not
+    /// access the state directly, so it queries this snapshot instead. It is only updated before
+    /// the config runs, Lua can not observe changes in between.
+    fn refresh_config_snapshot(&self) {
+        self.config.lua.set_app_data(self.config_snapshot());
+    }
+
+    /// Calls a callback of the config, see [`Config::call`]
+    pub fn call_config<'lua>(
+        &'lua self,
+        name: &str,
+        callback: &LuaCallback,
+        args: impl IntoLuaMulti<'lua>,
+    ) {
+        self.refresh_config_snapshot();
+        self.config.call(name, callback, args);
+    }
+
+    /// Windows, outputs and spaces in the form the config and the command socket get them
+    pub fn config_snapshot(&self) -> ConfigSnapshot {
+        let focused_window = self.focused_window();
+        let mut windows = Vec::new();
+        let mut spaces = Vec::new();
+        for (space_name, space) in &self.spaces {
+            let space_windows = space
+                .elements()
+                .filter(|window| window.id().is_some())
+                .map(|window| {
+                    let focused = focused_window.as_ref() == Some(window);
+                    ConfigWindow::new(window, Some(space), space_name, focused)
+                })
+                .collect::<Vec<_>>();
+            spaces.push(ConfigSpace {
+                name: space_name.clone(),
+                outputs: space.outputs().map(Output::name).collect(),
+                windows: space_windows
+                    .iter()
+                    .filter_map(|window| window.id)
+                    .collect(),
+            });
+            windows.extend(space_windows);
+        }
+        let outputs = self
+            .outputs
+            .values()
+            .map(|output| {
+                let mut config_output = ConfigOutput::from(output);
+                config_output.disabled = self.disabled_outputs.contains_key(&config_output.name);
+                config_output
+            })
+            .collect();
+
+        ConfigSnapshot {
+            windows,
+            outputs,
+            spaces,
+        }
+    }
+
+    /// Reloads the config once its files stopped changing for a moment
+    pub fn schedule_config_reload(&mut self) {
+        if let Some(token) = self.config_reload_timer.take() {
+            self.loop_handle.remove(token);
+        }
+
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(CONFIG_RELOAD_DEBOUNCE),
+            |_, _, state| {
+                state.config_reload_timer = None;
+                state.reload_config();
+                TimeoutAction::Drop
+            },
+        );
+        match res {
+            Ok(token) => self.config_reload_timer = Some(token),
+            Err(err) => warn!(?err, "Unable to debounce config reload"),
+        }
+    }
+
+    /// Reloads the config, failures are logged
+    pub fn reload_config(&mut self) {
+        if let Err(err) = self.try_reload_config() {
+            error!("{err:#}");
+        }
+    }
+
+    /// Executes the config again. Key bindings and window rules are replaced by the ones of the
+    /// new config and the on_connector_change callback runs again to update layout and zones.
+    /// A config with syntax errors is rejected and the current config is kept.
+    pub fn try_reload_config(&mut self) -> anyhow::Result<()> {
+        info!("Reloading config");
+        let chunk = self
+            .config
+            .file()
+            .and_then(|file| {
+                let chunk = compile_config(&self.config.lua, file)?;
+                // Kept in the registry, so that it does not borrow the config while the state is
+                // cleared
+                Ok(self.config.lua.create_registry_value(chunk)?)
+            })
+            .context("Unable to reload config, keeping the current one")?;
+
+        self.clear_key_map();
+        self.clear_lua_timers();
+        self.window_rules.clear();
+        self.input_configs.clear();
+        self.output_profiles.clear();
+        self.config.fallback = false;
+        self.refresh_config_snapshot();
+        let res = self
+            .config
+            .lua
+            .registry_value::<LuaFunction<'_>>(&chunk)
+            .and_then(|chunk| self.config.run_chunk("Reloaded config", &chunk));
+        drop(chunk);
+        self.config.expire_callbacks();
+
+        // Lua exports apply their changes in idle callbacks, so the layout has to be updated
+        // after them
         self.loop_handle.insert_idle(|state| {
-            info!("running on connector change");
-            if let Some(on_connector_change) = &state.config.on_connector_change {
-                let config_outputs = state.outputs.values().map(Into::into).collect();
-
-                on_connector_change
-                    .call::<Vec<ConfigOutput>, ()>(config_outputs)
-                    .unwrap();
-            } else {
-                info!("No on_connector_change callback set");
+            if state.config.on_connector_change.is_some() || !state.output_profiles.is_empty() {
+                state.run_on_connector_change();
             }
         });
+        res.context("Reloaded config failed")
+    }
+
+    /// Replaces a config which failed to run by the bundled default config, so that the session
+    /// stays usable until the config is fixed and reloaded
+    fn use_default_config(&mut self) {
+        warn!("Using the default config, because the config failed");
+        self.config.stop();
+        self.clear_key_map();
+        self.clear_lua_timers();
+        self.window_rules.clear();
+        self.input_configs.clear();
+        self.output_profiles.clear();
+        self.config.fallback = true;
+        self.refresh_config_snapshot();
+        match compile_config(&self.config.lua, None) {
+            Ok(chunk) => {
+                // Failures are reported by run_chunk
+                let _ = self.config.run_chunk("Default config", &chunk);
+            }
+            Err(err) => self
+                .config
+                .report_error("Default config", format!("{err:#}")),
+        }
+        self.config.expire_callbacks();
+    }
+
+    pub fn write_generated_config(&mut self, config: &str) -> anyhow::Result<()> {
+        let xdg_dirs = xdg::BaseDirectories::with_prefix(XDG_PREFIX)?;
+        let config_path = xdg_dirs.place_config_file(CONFIG_FILE_NAME)?;
+        fs::write(&config_path, config)?;
+        info!(?config_path, "Wrote generated config");
+
+        self.config.first_run = false;
+        self.refresh_config_snapshot();
+        self.config.lua.load(config).exec()?;
+        Ok(())
     }
 }
 
 const LUA_MODULE_NAME: &str = "scape";
+/// Directory of the config inside of the XDG config directories
+const XDG_PREFIX: &str = "scape";
+const CONFIG_FILE_NAME: &str = "init.lua";
+/// Config which is used if the user has none yet
+const DEFAULT_CONFIG: &str = include_str!("../resources/default_init.lua");
+/// Connector changes arriving within this duration are handled by a single callback run
+const CONNECTOR_CHANGE_DEBOUNCE: Duration = Duration::from_millis(500);
+/// Config changes arriving within this duration, e.g. while an editor saves, cause one reload
+const CONFIG_RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+/// Number of config failures which are kept for the debug UI
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// Built-in keybinding/zone profiles which can be loaded with `scape.use_profile(name)`
+const PROFILES: [(&str, &str); 3] = [
+    ("sway", include_str!("../resources/profiles/sway.lua")),
+    ("i3", include_str!("../resources/profiles/i3.lua")),
+    ("paperwm", include_str!("../resources/profiles/paperwm.lua")),
+];
 
 fn load_lua_config(state: &mut State, args: &GlobalArgs) -> anyhow::Result<()> {
     let loop_handle = state.loop_handle.clone();
@@ -88,32 +668,142 @@ fn load_lua_config(state: &mut State, args: &GlobalArgs) -> anyhow::Result<()> {
             })?,
     )?;
 
-    if let Some(config_path) = &args.config {
-        let user_config = fs::read(config_path.as_str())?;
-        let config = state.config.lua.load(&user_config);
-        config.exec()?;
-    } else {
-        let xdg_dirs = xdg::BaseDirectories::with_prefix("scape").unwrap();
-        for path in xdg_dirs.list_config_files("") {
-            let user_config = fs::read(path)?;
-            let config = state.config.lua.load(&user_config);
-            config.exec()?;
+    state.config.path = args.config.as_ref().map(PathBuf::from);
+    let config_file = state.config.file()?;
+    info!(?config_file, "Loading config");
+    state.config.first_run = config_file.is_none();
+    state.refresh_config_snapshot();
+    let res = compile_config(&state.config.lua, config_file)
+        .and_then(|chunk| Ok(chunk.call::<_, ()>(())?));
+    if let Err(err) = res {
+        state.config.report_error("Config", format!("{err:#}"));
+        // The exports of the broken config are applied in idle callbacks, so the default config
+        // has to replace them afterwards
+        state.loop_handle.insert_idle(State::use_default_config);
+    }
+
+    let watcher = ConfigWatcher::new(state.config.watch_dir()?);
+    state
+        .loop_handle
+        .insert_source(watcher, |path, _, state| {
+            if state.config.is_config_file(&path) {
+                state.schedule_config_reload();
+            }
+        })
+        .map_err(|err| err.error)?;
+
+    Ok(())
+}
+
+/// Runs the config without a compositor, so that mistakes are found before it is loaded. The
+/// changes of the exports are dropped and callbacks are not called. Returns the warnings, errors
+/// fail the check.
+pub fn check_config(args: &GlobalArgs) -> anyhow::Result<Vec<String>> {
+    // Exports queue their changes in the loop, which is never run
+    let event_loop = EventLoop::<State>::try_new()?;
+    let loop_handle = event_loop.handle();
+    let mut config = Config::new();
+    config.path = args.config.as_ref().map(PathBuf::from);
+    let _: Table = config.lua.load_from_function(
+        LUA_MODULE_NAME,
+        config
+            .lua
+            .create_function(move |lua: &Lua, _modname: String| {
+                init_config_module(lua, loop_handle.clone())
+            })?,
+    )?;
+    config.lua.set_app_data(ConfigCheck::default());
+
+    let config_file = config.file()?;
+    info!(?config_file, "Checking config");
+    compile_config(&config.lua, config_file)?.call::<_, ()>(())?;
+
+    let check = config
+        .lua
+        .remove_app_data::<ConfigCheck>()
+        .unwrap_or_default();
+    ensure!(check.errors.is_empty(), check.errors.join("\n"));
+    let warnings = check
+        .rule_zones
+        .into_iter()
+        .filter(|(zone, _)| !check.zones.contains(zone))
+        .map(|(zone, call_site)| {
+            // Zones are often set by callbacks, which the check does not run
+            format!("{call_site}: Rule refers to zone {zone}, which is not set at startup")
+        })
+        .collect();
+    Ok(warnings)
+}
+
+/// Definitions collected by `scape --check`, which can only be validated together
+#[derive(Debug, Default)]
+struct ConfigCheck {
+    zones: HashSet<String>,
+    /// Zones referred to by rules, with the place of the rule in the config
+    rule_zones: Vec<(String, String)>,
+    errors: Vec<String>,
+}
+
+/// Place in the config which called the running export, like `init.lua:12`
+fn call_site(lua: &Lua) -> String {
+    let Some(debug) = lua.inspect_stack(1) else {
+        return "config".to_string();
+    };
+    let source = debug.source().short_src.unwrap_or_default().into_owned();
+    format!("{source}:{}", debug.curr_line())
+}
+
+/// Reports zones with the same name or without area while the config is checked
+fn check_zones(lua: &Lua, zones: &[ConfigZone]) {
+    let call_site = call_site(lua);
+    let Some(mut check) = lua.app_data_mut::<ConfigCheck>() else {
+        return;
+    };
+    let mut names = HashSet::new();
+    for zone in zones {
+        if !names.insert(&zone.name) {
+            let error = format!("{call_site}: Zone {} is set more than once", zone.name);
+            check.errors.push(error);
         }
+        if zone.width <= 0 || zone.height <= 0 {
+            let error = format!("{call_site}: Zone {} has no area", zone.name);
+            check.errors.push(error);
+        }
+        check.zones.insert(zone.name.clone());
+    }
+    if zones.iter().filter(|zone| zone.default).count() > 1 {
+        check
+            .errors
+            .push(format!("{call_site}: More than one zone is the default"));
+    }
+}
 
-        state
-            .loop_handle
-            .insert_source(
-                ConfigWatcher::new(xdg_dirs.get_config_home()),
-                |path, _, state| {
-                    let user_config = fs::read(path).unwrap();
-                    let config = state.config.lua.load(&user_config);
-                    config.exec().unwrap();
-                },
-            )
-            .unwrap();
+/// Remembers the zone of a rule while the config is checked, it is compared to the zones once
+/// the config ran
+fn check_rule_zone(lua: &Lua, zone: Option<&str>) {
+    let Some(zone) = zone else {
+        return;
+    };
+    let call_site = call_site(lua);
+    if let Some(mut check) = lua.app_data_mut::<ConfigCheck>() {
+        check.rule_zones.push((zone.to_string(), call_site));
     }
+}
 
-    Ok(())
+/// Reads and compiles the config file without running it, `None` compiles the default config
+fn compile_config(lua: &Lua, file: Option<PathBuf>) -> anyhow::Result<LuaFunction<'_>> {
+    let Some(path) = file else {
+        return Ok(lua
+            .load(DEFAULT_CONFIG)
+            .set_name("default config")
+            .into_function()?);
+    };
+    let user_config =
+        fs::read(&path).with_context(|| format!("Unable to read config {}", path.display()))?;
+    Ok(lua
+        .load(&user_config)
+        .set_name(path.to_string_lossy())
+        .into_function()?)
 }
 
 fn init_config_module<'lua>(
@@ -125,11 +815,7 @@ fn init_config_module<'lua>(
     let lh = loop_handle.clone();
     exports.set(
         "on_startup",
-        lua.create_function(move |_, callback: LuaFunction<'_>| {
-            // SAFETY: The callback is valid as long as the lua instance is alive.
-            // The lua instance is never dropped, therefore the lifetime of the callback is
-            // effectively 'static.
-            let callback: LuaFunction<'static> = unsafe { std::mem::transmute(callback) };
+        lua.create_function(move |_, callback: LuaCallback| {
             lh.insert_idle(move |state| {
                 state.config.on_startup = Some(callback);
             });
@@ -139,15 +825,10 @@ fn init_config_module<'lua>(
 
     let lh = loop_handle.clone();
     exports.set(
-        "on_connector_change",
-        lua.create_function(move |_, callback: LuaFunction<'_>| {
-            info!("Setting up on_connector_change");
-            // SAFETY: The callback is valid as long as the lua instance is alive.
-            // The lua instance is never dropped, therefore the lifetime of the callback is
-            // effectively 'static.
-            let callback: LuaFunction<'static> = unsafe { std::mem::transmute(callback) };
+        "on_shutdown",
+        lua.create_function(move |_, callback: LuaCallback| {
             lh.insert_idle(move |state| {
-                state.config.on_connector_change = Some(callback);
+                state.config.on_shutdown = Some(callback);
             });
             Ok(())
         })?,
@@ -155,13 +836,10 @@ fn init_config_module<'lua>(
 
     let lh = loop_handle.clone();
     exports.set(
-        "spawn",
-        lua.create_function(move |_, spawn: ConfigSpawn| {
+        "on_suspend",
+        lua.create_function(move |_, callback: LuaCallback| {
             lh.insert_idle(move |state| {
-                state.execute(Action::Spawn {
-                    command: spawn.command,
-                    args: spawn.args,
-                });
+                state.config.on_suspend = Some(callback);
             });
             Ok(())
         })?,
@@ -169,10 +847,10 @@ fn init_config_module<'lua>(
 
     let lh = loop_handle.clone();
     exports.set(
-        "set_zones",
-        lua.create_function(move |_, zones: Vec<ConfigZone>| {
+        "on_resume",
+        lua.create_function(move |_, callback: LuaCallback| {
             lh.insert_idle(move |state| {
-                state.set_zones(zones.into_iter().map(Into::into).collect());
+                state.config.on_resume = Some(callback);
             });
             Ok(())
         })?,
@@ -180,10 +858,11 @@ fn init_config_module<'lua>(
 
     let lh = loop_handle.clone();
     exports.set(
-        "map_key",
-        lua.create_function(move |_, params: ConfigMapKey| {
+        "on_connector_change",
+        lua.create_function(move |_, callback: LuaCallback| {
+            info!("Setting up on_connector_change");
             lh.insert_idle(move |state| {
-                state.map_key(params.key, params.mods, params.callback);
+                state.config.on_connector_change = Some(callback);
             });
             Ok(())
         })?,
@@ -191,40 +870,43 @@ fn init_config_module<'lua>(
 
     let lh = loop_handle.clone();
     exports.set(
-        "move_to_zone",
-        lua.create_function(move |_, zone: String| {
-            lh.insert_idle(move |state| state.execute(Action::MoveWindow { window: None, zone }));
+        "on_output_added",
+        lua.create_function(move |_, callback: LuaCallback| {
+            lh.insert_idle(move |state| {
+                state.config.on_output_added = Some(callback);
+            });
             Ok(())
         })?,
     )?;
 
     let lh = loop_handle.clone();
     exports.set(
-        "focus_or_spawn",
-        lua.create_function(move |_, (command, app_id)| {
-            lh.insert_idle(move |state| state.execute(Action::FocusOrSpawn { app_id, command }));
+        "on_output_removed",
+        lua.create_function(move |_, callback: LuaCallback| {
+            lh.insert_idle(move |state| {
+                state.config.on_output_removed = Some(callback);
+            });
             Ok(())
         })?,
     )?;
 
     let lh = loop_handle.clone();
     exports.set(
-        "close",
-        lua.create_function(move |_, ()| {
-            lh.insert_idle(move |state| state.execute(Action::Close));
+        "on_pointer_capture",
+        lua.create_function(move |_, callback: LuaCallback| {
+            lh.insert_idle(move |state| {
+                state.config.on_pointer_capture = Some(callback);
+            });
             Ok(())
         })?,
     )?;
 
     let lh = loop_handle.clone();
     exports.set(
-        "window_rule",
-        lua.create_function(move |_, window_rule: ConfigWindowRule| {
+        "on_window_open",
+        lua.create_function(move |_, callback: LuaCallback| {
             lh.insert_idle(move |state| {
-                state.add_window_rule(WindowRule {
-                    app_id: window_rule.app_id,
-                    zone: window_rule.zone,
-                })
+                state.config.on_window_open = Some(callback);
             });
             Ok(())
         })?,
@@ -232,10 +914,10 @@ fn init_config_module<'lua>(
 
     let lh = loop_handle.clone();
     exports.set(
-        "toggle_debug_ui",
-        lua.create_function(move |_, ()| {
+        "on_window_close",
+        lua.create_function(move |_, callback: LuaCallback| {
             lh.insert_idle(move |state| {
-                state.toggle_debug_ui();
+                state.config.on_window_close = Some(callback);
             });
             Ok(())
         })?,
@@ -243,10 +925,10 @@ fn init_config_module<'lua>(
 
     let lh = loop_handle.clone();
     exports.set(
-        "quit",
-        lua.create_function(move |_, ()| {
+        "on_window_not_responding",
+        lua.create_function(move |_, callback: LuaCallback| {
             lh.insert_idle(move |state| {
-                state.execute(Action::Quit);
+                state.config.on_window_not_responding = Some(callback);
             });
             Ok(())
         })?,
@@ -254,304 +936,2460 @@ fn init_config_module<'lua>(
 
     let lh = loop_handle.clone();
     exports.set(
-        "start_video_stream",
-        lua.create_function(move |_, ()| {
+        "on_window_focus",
+        lua.create_function(move |_, callback: LuaCallback| {
             lh.insert_idle(move |state| {
-                state.execute(Action::StartVideoStream);
+                state.config.on_window_focus = Some(callback);
             });
             Ok(())
         })?,
     )?;
 
+    let lh = loop_handle.clone();
     exports.set(
-        "set_layout",
-        lua.create_function(move |_, layout: ConfigLayout| {
-            info!("New layout received");
-            loop_handle.insert_idle(move |state| {
-                info!("New layout will be set");
-                for (space_name, config_outputs) in layout.spaces {
-                    let space = state.spaces.entry(space_name.clone()).or_default();
-
-                    for config_output in &config_outputs {
-                        let Some(output) = state.outputs.get(&config_output.name) else {
-                            warn!(output_name = %config_output.name, "Output not found");
-                            continue;
-                        };
-
-                        let position: Point<i32, Logical> =
-                            (config_output.x, config_output.y).into();
-                        output.change_current_state(
-                            None,
-                            None,
-                            Some(Scale::Integer(config_output.scale)),
-                            Some(position),
-                        );
-                        space.map_output(output, position);
-                        if config_output.default {
-                            output
-                                .user_data()
-                                .get_or_insert_threadsafe(|| ActiveSpace(space_name.clone()));
-                        }
-                    }
-
-                    // clean up no longer mapped outputs
-                    for (output_name, output) in &state.outputs {
-                        if !config_outputs
-                            .iter()
-                            .any(|config_output| config_output.name == *output_name)
-                        {
-                            space.unmap_output(output);
-                        }
-                    }
-                }
-
-                // fixup window coordinates
-                // let space_names = state.spaces.keys().cloned().collect::<Vec<_>>();
-                // for space_name in space_names {
-                //     state.fixup_positions(&space_name);
-                // }
+        "on_window_title_changed",
+        lua.create_function(move |_, callback: LuaCallback| {
+            lh.insert_idle(move |state| {
+                state.config.on_window_title_changed = Some(callback);
+            });
+            Ok(())
+        })?,
+    )?;
 
-                state.start_outputs();
+    let lh = loop_handle.clone();
+    exports.set(
+        "register_command",
+        lua.create_function(move |_, (name, callback): (String, LuaCallback)| {
+            lh.insert_idle(move |state| {
+                state.config.commands.insert(name, callback);
             });
             Ok(())
         })?,
     )?;
 
-    Ok(exports)
-}
+    let lh = loop_handle.clone();
+    exports.set(
+        "run_command",
+        lua.create_function(move |_, (name, args): (String, Variadic<String>)| {
+            let args = args.into_iter().collect();
+            lh.insert_idle(move |state| state.execute(Action::RunCommand { name, args }));
+            Ok(())
+        })?,
+    )?;
 
-struct ConfigLayout {
-    spaces: HashMap<String, Vec<ConfigOutput>>,
-}
+    let lh = loop_handle.clone();
+    exports.set(
+        "break_pointer_constraint",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.execute(Action::BreakPointerConstraint));
+            Ok(())
+        })?,
+    )?;
 
-impl<'lua> FromLua<'lua> for ConfigLayout {
-    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
-        let table = value.as_table().unwrap().to_owned();
+    exports.set(
+        "get_windows",
+        lua.create_function(|lua, ()| {
+            let snapshot = lua.app_data_ref::<ConfigSnapshot>();
+            Ok(snapshot
+                .map(|snapshot| snapshot.windows.clone())
+                .unwrap_or_default())
+        })?,
+    )?;
 
-        let mut spaces = HashMap::new();
-        for pair in table.pairs() {
-            let (space_name, config_outputs) = pair.unwrap();
+    exports.set(
+        "get_outputs",
+        lua.create_function(|lua, ()| {
+            let snapshot = lua.app_data_ref::<ConfigSnapshot>();
+            Ok(snapshot
+                .map(|snapshot| snapshot.outputs.clone())
+                .unwrap_or_default())
+        })?,
+    )?;
 
-            spaces.insert(space_name, config_outputs);
-        }
+    exports.set(
+        "get_spaces",
+        lua.create_function(|lua, ()| {
+            let snapshot = lua.app_data_ref::<ConfigSnapshot>();
+            Ok(snapshot
+                .map(|snapshot| snapshot.spaces.clone())
+                .unwrap_or_default())
+        })?,
+    )?;
 
-        Ok(ConfigLayout { spaces })
-    }
-}
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_clipboard",
+        lua.create_function(move |_, text: String| {
+            lh.insert_idle(move |state| state.set_clipboard(text));
+            Ok(())
+        })?,
+    )?;
 
-struct ConfigOutput {
-    name: String,
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
-    default: bool,
-    disabled: bool,
-    scale: i32,
-}
+    let lh = loop_handle.clone();
+    exports.set(
+        "get_clipboard",
+        lua.create_function(move |_, callback: LuaCallback| {
+            lh.insert_idle(move |state| state.get_clipboard(callback));
+            Ok(())
+        })?,
+    )?;
 
-impl From<&Output> for ConfigOutput {
-    fn from(value: &Output) -> Self {
-        let mode = value.preferred_mode().unwrap();
-        let location = value.current_location();
-        ConfigOutput {
+    let lh = loop_handle.clone();
+    exports.set(
+        "spawn",
+        lua.create_function(move |_, spawn: ConfigSpawn| {
+            lh.insert_idle(move |state| {
+                state.execute(Action::Spawn {
+                    command: spawn.command,
+                    args: spawn.args,
+                    zone: spawn.zone,
+                    env: spawn.env.into_iter().collect(),
+                    cwd: spawn.cwd,
+                });
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "spawn_capture",
+        lua.create_function(move |_, (spawn, callback): (ConfigSpawn, LuaCallback)| {
+            lh.insert_idle(move |state| {
+                state.spawn_capture(&spawn.command, &spawn.args, callback);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_timeout",
+        lua.create_function(move |_, (ms, callback): (u64, LuaCallback)| {
+            let id = next_timer_id();
+            lh.insert_idle(move |state| {
+                state.add_lua_timer(id, Duration::from_millis(ms), false, callback);
+            });
+            Ok(id)
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_interval",
+        lua.create_function(move |_, (ms, callback): (u64, LuaCallback)| {
+            let id = next_timer_id();
+            // An interval of 0 would block the event loop
+            let interval = Duration::from_millis(ms.max(1));
+            lh.insert_idle(move |state| state.add_lua_timer(id, interval, true, callback));
+            Ok(id)
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "clear_timer",
+        lua.create_function(move |_, id: u64| {
+            lh.insert_idle(move |state| state.remove_lua_timer(id));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "startup",
+        lua.create_function(move |_, entries: Vec<ConfigStartupEntry>| {
+            lh.insert_idle(move |state| {
+                state.run_startup(entries.into_iter().map(Into::into).collect());
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_zones",
+        lua.create_function(
+            move |lua, (zones, gaps): (Vec<ConfigZone>, Option<ConfigZoneGaps>)| {
+                check_zones(lua, &zones);
+                let gaps = gaps.map(|ConfigZoneGaps(gaps)| gaps).unwrap_or_default();
+                lh.insert_idle(move |state| {
+                    state.set_zones(zones.into_iter().map(Into::into).collect(), gaps);
+                });
+                Ok(())
+            },
+        )?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "map_key",
+        lua.create_function(move |_, params: ConfigMapKey| {
+            lh.insert_idle(move |state| {
+                if let [(mods, key)] = params.keys[..] {
+                    state.map_key(params.mode, key, mods, params.binding);
+                } else {
+                    state.map_key_chord(KeyChord {
+                        mode: params.mode,
+                        keys: params.keys,
+                        binding: params.binding,
+                        description: params.description,
+                    });
+                }
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "map_button",
+        lua.create_function(move |_, params: ConfigMapButton| {
+            lh.insert_idle(move |state| {
+                state.map_button(params.button, params.mods, params.binding);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "enter_mode",
+        lua.create_function(move |_, mode: String| {
+            lh.insert_idle(move |state| state.execute(Action::EnterKeyMode { mode }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "move_to_zone",
+        lua.create_function(move |lua, (zone, window): (String, LuaValue<'_>)| {
+            let window = window_id_from_lua(lua, window)?;
+            let target = MoveTarget::Zone(zone);
+            lh.insert_idle(move |state| state.execute(Action::MoveWindow { window, target }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "move_window",
+        lua.create_function(
+            move |lua, (window, target): (LuaValue<'_>, ConfigMoveTarget)| {
+                let window = window_id_from_lua(lua, window)?;
+                let ConfigMoveTarget(target) = target;
+                lh.insert_idle(move |state| state.execute(Action::MoveWindow { window, target }));
+                Ok(())
+            },
+        )?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "focus_previous",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.execute(Action::FocusPrevious));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "focus_direction",
+        lua.create_function(move |_, direction: String| {
+            let direction = Direction::from_name(&direction)
+                .ok_or_else(|| LuaError::runtime(format!("Unknown direction {direction}")))?;
+            lh.insert_idle(move |state| state.execute(Action::FocusDirection(direction)));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "swap_windows",
+        lua.create_function(move |lua, (window, zone): (LuaValue<'_>, String)| {
+            let window = window_id_from_lua(lua, window)?;
+            let zone = ZoneTarget::from_name(&zone);
+            lh.insert_idle(move |state| state.execute(Action::SwapWindows { window, zone }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "quick_layout",
+        lua.create_function(move |_, (columns, rows): (u32, Option<u32>)| {
+            let rows = rows.unwrap_or(1);
+            lh.insert_idle(move |state| state.execute(Action::QuickLayout { columns, rows }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_zone_layouts",
+        lua.create_function(
+            move |lua, args: (String, Vec<ConfigZoneLayout>, Option<ConfigZoneGaps>)| {
+                let (output, layouts, gaps) = args;
+                for layout in &layouts {
+                    check_zones(lua, &layout.zones);
+                }
+                let gaps = gaps.map(|ConfigZoneGaps(gaps)| gaps).unwrap_or_default();
+                lh.insert_idle(move |state| {
+                    let layouts = layouts.into_iter().map(Into::into).collect();
+                    state.set_zone_layouts(output, layouts, gaps);
+                });
+                Ok(())
+            },
+        )?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "cycle_zone_layout",
+        lua.create_function(move |_, output: Option<String>| {
+            lh.insert_idle(move |state| {
+                state.execute(Action::CycleZoneLayout {
+                    output,
+                    layout: None,
+                })
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_zone_layout",
+        lua.create_function(move |_, (layout, output): (String, Option<String>)| {
+            lh.insert_idle(move |state| {
+                state.execute(Action::CycleZoneLayout {
+                    output,
+                    layout: Some(layout),
+                })
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "split_zone",
+        lua.create_function(move |_, (axis, zone): (String, Option<String>)| {
+            let axis = SplitAxis::from_name(&axis)
+                .ok_or_else(|| LuaError::runtime(format!("Unknown split axis {axis}")))?;
+            lh.insert_idle(move |state| state.execute(Action::SplitZone { zone, axis }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "merge_zone",
+        lua.create_function(move |_, zone: Option<String>| {
+            lh.insert_idle(move |state| state.execute(Action::MergeZone { zone }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_tabbed",
+        lua.create_function(move |_, zone: Option<String>| {
+            lh.insert_idle(move |state| state.execute(Action::ToggleTabbed { zone }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "cycle_tab",
+        lua.create_function(move |_, offset: Option<i32>| {
+            let offset = offset.unwrap_or(1);
+            lh.insert_idle(move |state| state.execute(Action::CycleTab { offset }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_idle_timeouts",
+        lua.create_function(move |_, ConfigIdleTimeouts(timeouts): ConfigIdleTimeouts| {
+            lh.insert_idle(move |state| state.set_idle_timeouts(timeouts));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "configure_input",
+        lua.create_function(move |_, ConfigInput(input_config): ConfigInput| {
+            lh.insert_idle(move |state| state.configure_input(input_config));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "kiosk",
+        lua.create_function(move |_, ConfigKiosk(kiosk): ConfigKiosk| {
+            lh.insert_idle(move |state| state.start_kiosk(kiosk));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "create_space",
+        lua.create_function(move |_, space_name: String| {
+            lh.insert_idle(move |state| state.create_space(space_name));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "save_layout",
+        lua.create_function(move |_, name: String| {
+            lh.insert_idle(move |state| state.execute(Action::SaveLayout { name }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "restore_layout",
+        lua.create_function(move |_, (name, options): (String, Option<Table<'_>>)| {
+            let commands = options
+                .map(|options| options.get::<_, Option<HashMap<String, String>>>("commands"))
+                .transpose()?
+                .flatten()
+                .unwrap_or_default();
+            lh.insert_idle(move |state| state.execute(Action::RestoreLayout { name, commands }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_scratchpad",
+        lua.create_function(move |_, scratchpad: ConfigScratchpad| {
+            lh.insert_idle(move |state| {
+                state.execute(Action::ToggleScratchpad {
+                    name: scratchpad.name,
+                    command: scratchpad.command,
+                    args: scratchpad.args,
+                })
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "switch_space",
+        lua.create_function(move |_, (space, output): (String, Option<String>)| {
+            lh.insert_idle(move |state| state.execute(Action::SwitchSpace { space, output }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "move_window_to_space",
+        lua.create_function(move |lua, (window, space): (LuaValue<'_>, String)| {
+            let window = window_id_from_lua(lua, window)?;
+            let target = MoveTarget::Space(space);
+            lh.insert_idle(move |state| state.execute(Action::MoveWindow { window, target }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "move_window_to_output",
+        lua.create_function(move |lua, (window, output): (LuaValue<'_>, String)| {
+            let window = window_id_from_lua(lua, window)?;
+            let output = OutputTarget::from_name(&output);
+            lh.insert_idle(move |state| {
+                state.execute(Action::MoveWindowToOutput { window, output })
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "resize_window",
+        lua.create_function(
+            move |lua, (edge, delta, window): (String, i32, LuaValue<'_>)| {
+                let edge = resize_edge_from_name(&edge)
+                    .map_err(|err| LuaError::runtime(err.to_string()))?;
+                let window = window_id_from_lua(lua, window)?;
+                lh.insert_idle(move |state| {
+                    state.execute(Action::ResizeWindow {
+                        window,
+                        edge,
+                        delta,
+                    })
+                });
+                Ok(())
+            },
+        )?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_floating",
+        lua.create_function(move |lua, window: LuaValue<'_>| {
+            let window = window_id_from_lua(lua, window)?;
+            lh.insert_idle(move |state| state.execute(Action::ToggleFloating { window }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_sticky",
+        lua.create_function(move |lua, window: LuaValue<'_>| {
+            let window = window_id_from_lua(lua, window)?;
+            lh.insert_idle(move |state| state.execute(Action::ToggleSticky { window }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_pin",
+        lua.create_function(move |lua, window: LuaValue<'_>| {
+            let window = window_id_from_lua(lua, window)?;
+            lh.insert_idle(move |state| state.execute(Action::TogglePin { window }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_fullscreen",
+        lua.create_function(move |lua, window: LuaValue<'_>| {
+            let window = window_id_from_lua(lua, window)?;
+            lh.insert_idle(move |state| state.execute(Action::ToggleFullscreen { window }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_maximize",
+        lua.create_function(move |lua, window: LuaValue<'_>| {
+            let window = window_id_from_lua(lua, window)?;
+            lh.insert_idle(move |state| state.execute(Action::ToggleMaximize { window }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "close_window",
+        lua.create_function(move |lua, window: LuaValue<'_>| {
+            let window = window_id_from_lua(lua, window)?;
+            lh.insert_idle(move |state| state.execute(Action::CloseWindow { window }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "kill_window",
+        lua.create_function(move |lua, window: LuaValue<'_>| {
+            let window = window_id_from_lua(lua, window)?;
+            lh.insert_idle(move |state| state.execute(Action::KillWindow { window }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "identify_outputs",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.execute(Action::IdentifyOutputs));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_test_pattern",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.execute(Action::ToggleTestPattern));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "zoom_window",
+        lua.create_function(move |_, zoom: f64| {
+            lh.insert_idle(move |state| state.execute(Action::ZoomWindow { zoom }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "change_scale",
+        lua.create_function(move |_, percentage_points: isize| {
+            lh.insert_idle(move |state| state.execute(Action::ChangeScale { percentage_points }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "rotate_output",
+        lua.create_function(move |_, (transform, output): (String, Option<String>)| {
+            let transform = transform_from_name(&transform)
+                .map_err(|err| LuaError::runtime(err.to_string()))?;
+            lh.insert_idle(move |state| state.execute(Action::RotateOutput { output, transform }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_scale",
+        lua.create_function(move |_, percentage: usize| {
+            lh.insert_idle(move |state| state.execute(Action::SetScale { percentage }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "focus_or_spawn",
+        lua.create_function(move |_, (command, app_id)| {
+            lh.insert_idle(move |state| state.execute(Action::FocusOrSpawn { app_id, command }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "close",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.execute(Action::Close));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "window_rule",
+        lua.create_function(move |lua, window_rule: ConfigWindowRule| {
+            check_rule_zone(lua, Some(&window_rule.zone));
+            lh.insert_idle(move |state| {
+                state.add_window_rule(WindowRule {
+                    matches: WindowMatch {
+                        app_id: Some(window_rule.app_id),
+                        title: None,
+                    },
+                    properties: WindowProperties {
+                        zone: Some(window_rule.zone),
+                        ..Default::default()
+                    },
+                })
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "add_rule",
+        lua.create_function(move |lua, window_rule: ConfigRule| {
+            check_rule_zone(lua, window_rule.zone.as_deref());
+            lh.insert_idle(move |state| state.add_window_rule(window_rule.into()));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_keyboard_layout",
+        lua.create_function(move |_, layout: String| {
+            lh.insert_idle(move |state| {
+                state.set_keyboard_layout(&layout);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_keyboard",
+        lua.create_function(move |_, keyboard: ConfigKeyboard| {
+            lh.insert_idle(move |state| {
+                let mut config = state.keyboard_config.clone();
+                keyboard.apply(&mut config);
+                state.set_keyboard_config(config);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "cycle_keyboard_layout",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.execute(Action::CycleKeyboardLayout));
+            Ok(())
+        })?,
+    )?;
+
+    exports.set(
+        "use_profile",
+        lua.create_function(|lua, name: String| {
+            let Some((_, profile)) = PROFILES.iter().find(|(profile, _)| *profile == name) else {
+                return Err(LuaError::runtime(format!("Unknown profile: {name}")));
+            };
+            info!(%name, "Loading profile");
+            lua.load(*profile)
+                .set_name(format!("profile {name}"))
+                .eval::<LuaValue<'_>>()
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_theme",
+        lua.create_function(move |_, config_theme: ConfigTheme| {
+            lh.insert_idle(move |state| {
+                let mut theme = state.theme.clone();
+                config_theme.apply(&mut theme);
+                state.set_theme(theme);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_drag_threshold",
+        lua.create_function(move |_, threshold: f64| {
+            if !threshold.is_finite() || threshold < 0.0 {
+                return Err(LuaError::runtime(format!(
+                    "Drag threshold has to be a positive distance, got {threshold}"
+                )));
+            }
+            lh.insert_idle(move |state| state.drag_threshold = threshold);
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_focus",
+        lua.create_function(move |_, focus: ConfigFocus| {
+            lh.insert_idle(move |state| {
+                let mut config = state.focus_config;
+                focus.apply(&mut config);
+                state.set_focus_config(config);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_drag_drop",
+        lua.create_function(move |_, drag: ConfigDrag| {
+            lh.insert_idle(move |state| drag.apply(&mut state.drag_config));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_repeat",
+        lua.create_function(move |_, repeat: ConfigKeyRepeat| {
+            lh.insert_idle(move |state| {
+                let mut config = state.key_repeat_config;
+                repeat.apply(&mut config);
+                state.set_key_repeat(config);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_key_chords",
+        lua.create_function(move |_, chords: ConfigKeyChords| {
+            lh.insert_idle(move |state| {
+                let mut config = state.key_chord_config.clone();
+                chords.apply(&mut config);
+                state.set_key_chord_config(config);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_theme_variant",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.execute(Action::ToggleThemeVariant));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_debug_ui",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| {
+                state.toggle_debug_ui();
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "reload",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.reload_config());
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "quit",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| {
+                state.execute(Action::Quit);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_move_mode",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.execute(Action::ToggleMoveMode));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_move_mode",
+        lua.create_function(move |_, move_mode: ConfigMoveMode| {
+            lh.insert_idle(move |state| move_mode.apply(&mut state.move_mode_config));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "screenshot",
+        lua.create_function(move |lua, (target, options): (String, Option<Table<'_>>)| {
+            let (output, window, clipboard) = match options {
+                Some(options) => (
+                    options.get("output")?,
+                    window_id_from_lua(lua, options.get("window")?)?,
+                    options
+                        .get::<_, Option<bool>>("clipboard")?
+                        .unwrap_or(false),
+                ),
+                None => (None, None, false),
+            };
+            let target = ScreenshotTarget::from_name(&target, output, window)
+                .ok_or_else(|| LuaError::runtime(format!("Unknown screenshot target {target}")))?;
+            lh.insert_idle(move |state| state.execute(Action::Screenshot { target, clipboard }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_screenshot_dir",
+        lua.create_function(move |_, directory: String| {
+            lh.insert_idle(move |state| state.screenshot_dir = Some(PathBuf::from(directory)));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_recording",
+        lua.create_function(move |_, output: Option<String>| {
+            lh.insert_idle(move |state| state.execute(Action::ToggleRecording { output }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_recording",
+        lua.create_function(move |_, recording: ConfigRecording| {
+            lh.insert_idle(move |state| recording.apply(&mut state.recording_config));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "zoom",
+        lua.create_function(move |_, delta: f64| {
+            lh.insert_idle(move |state| state.execute(Action::Zoom { delta }));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "toggle_keystroke_visualizer",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.execute(Action::ToggleKeystrokeVisualizer));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_keystroke_visualizer",
+        lua.create_function(move |_, keystroke_visualizer: ConfigKeystrokeVisualizer| {
+            lh.insert_idle(move |state| {
+                let mut config = state.keystroke_visualizer_config.clone();
+                keystroke_visualizer.apply(&mut config);
+                state.set_keystroke_visualizer_config(config);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_modifier_indicators",
+        lua.create_function(move |_, modifier_indicators: ConfigModifierIndicators| {
+            lh.insert_idle(move |state| {
+                let mut config = state.modifier_indicator_config.clone();
+                modifier_indicators.apply(&mut config);
+                state.set_modifier_indicator_config(config);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "show_window_thumbnail",
+        lua.create_function(move |_, thumbnail: ConfigThumbnail| {
+            lh.insert_idle(move |state| {
+                let geometry = Rectangle::from_loc_and_size(
+                    (thumbnail.x, thumbnail.y),
+                    (thumbnail.width, thumbnail.height),
+                );
+                state.show_window_thumbnail(&thumbnail.app_id, geometry);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "hide_window_thumbnail",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| state.hide_window_thumbnail());
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_command_auth",
+        lua.create_function(move |_, auth: Option<Table>| {
+            let auth = match auth {
+                Some(auth) => CommandAuth {
+                    token: auth.get("token")?,
+                    uids: auth.get::<_, Option<Vec<u32>>>("uids")?.unwrap_or_default(),
+                },
+                None => CommandAuth::default(),
+            };
+            lh.insert_idle(move |state| state.set_command_auth(auth));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_bar",
+        lua.create_function(move |_, enabled: bool| {
+            lh.insert_idle(move |state| state.set_bar(enabled));
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "set_color_profile",
+        lua.create_function(move |_, (output_name, path): (String, Option<String>)| {
+            lh.insert_idle(move |state| {
+                if let Err(err) = state.set_color_profile(&output_name, path.as_deref()) {
+                    error!(?err, output_name, "Unable to set color profile");
+                }
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "start_video_stream",
+        lua.create_function(move |_, ()| {
+            lh.insert_idle(move |state| {
+                state.execute(Action::StartVideoStream);
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "configure_output",
+        lua.create_function(
+            move |_, (settings, on_error): (ConfigOutputSettings, Option<LuaCallback>)| {
+                lh.insert_idle(move |state| {
+                    let Err(err) = state.configure_output(settings) else {
+                        return;
+                    };
+                    error!(?err, "Output was not configured");
+                    if let Some(on_error) = on_error {
+                        state.call_config(
+                            "configure_output error callback",
+                            &on_error,
+                            format!("{err:#}"),
+                        );
+                    }
+                });
+                Ok(())
+            },
+        )?,
+    )?;
+
+    let lh = loop_handle.clone();
+    exports.set(
+        "add_output_profile",
+        lua.create_function(
+            move |_, ConfigOutputProfile(profile): ConfigOutputProfile| {
+                lh.insert_idle(move |state| state.add_output_profile(profile));
+                Ok(())
+            },
+        )?,
+    )?;
+
+    exports.set(
+        "set_layout",
+        lua.create_function(
+            move |_, (layout, on_error): (ConfigLayout, Option<LuaCallback>)| {
+                info!("New layout received");
+                loop_handle.insert_idle(move |state| {
+                    info!("New layout will be set");
+                    let Err(err) = state.apply_layout(layout) else {
+                        return;
+                    };
+                    error!(?err, "Layout was not applied");
+                    if let Some(on_error) = on_error {
+                        state.call_config(
+                            "set_layout error callback",
+                            &on_error,
+                            format!("{err:#}"),
+                        );
+                    }
+                });
+                Ok(())
+            },
+        )?,
+    )?;
+
+    Ok(exports)
+}
+
+pub struct ConfigLayout {
+    pub spaces: HashMap<String, Vec<ConfigOutput>>,
+}
+
+impl<'lua> FromLua<'lua> for ConfigLayout {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let mut spaces = HashMap::new();
+        for pair in table.pairs() {
+            let (space_name, config_outputs) = pair?;
+
+            spaces.insert(space_name, config_outputs);
+        }
+
+        Ok(ConfigLayout { spaces })
+    }
+}
+
+/// Settings for a single output, unset settings are not changed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfigOutputSettings {
+    pub name: String,
+    /// Mode in the form of `2560x1440@144`, the refresh rate is optional
+    #[serde(default)]
+    pub mode: Option<String>,
+    #[serde(default)]
+    pub transform: Option<String>,
+    #[serde(default)]
+    pub scale: Option<f64>,
+    #[serde(default)]
+    pub position: Option<(i32, i32)>,
+    /// Turns the output off or on
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+impl<'lua> FromLua<'lua> for ConfigOutputSettings {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigOutputSettings {
+            name: table.get("name")?,
+            mode: table.get("mode")?,
+            transform: table.get("transform")?,
+            scale: table.get("scale")?,
+            position: position_from_table(&table)?,
+            enabled: table.get("enabled")?,
+        })
+    }
+}
+
+/// The position can either be given as `{x = 0, y = 0}` or as `{0, 0}`
+fn position_from_table(table: &Table) -> LuaResult<Option<(i32, i32)>> {
+    Ok(table.get::<_, Option<Table>>("position")?.map(|position| {
+        let x = position
+            .get("x")
+            .or_else(|_| position.get(1))
+            .unwrap_or_default();
+        let y = position
+            .get("y")
+            .or_else(|_| position.get(2))
+            .unwrap_or_default();
+        (x, y)
+    }))
+}
+
+/// Output profile in the form of `{name, space, outputs, callback}`. Each output is given as
+/// `{output, mode, transform, scale, position, disabled}`, where `output` is the connector name,
+/// `make model serial` or `*`.
+struct ConfigOutputProfile(OutputProfile);
+
+impl<'lua> FromLua<'lua> for ConfigOutputProfile {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let mut outputs = Vec::new();
+        for output in table.get::<_, Table>("outputs")?.sequence_values::<Table>() {
+            let output = output?;
+            let scale = output.get::<_, Option<i32>>("scale")?.unwrap_or(1);
+            if scale < 1 {
+                return Err(LuaError::runtime(format!("Invalid output scale: {scale}")));
+            }
+            outputs.push(ProfileOutput {
+                criteria: output.get("output")?,
+                mode: output.get("mode")?,
+                transform: output.get("transform")?,
+                scale,
+                position: position_from_table(&output)?,
+                disabled: output
+                    .get::<_, Option<bool>>("disabled")?
+                    .unwrap_or_default(),
+            });
+        }
+
+        Ok(ConfigOutputProfile(OutputProfile {
+            name: table.get("name")?,
+            space: table
+                .get::<_, Option<String>>("space")?
+                .unwrap_or_else(|| DEFAULT_PROFILE_SPACE.to_string()),
+            outputs,
+            callback: table.get("callback")?,
+        }))
+    }
+}
+
+/// Compositor state the config can query without going through the event loop
+#[derive(Debug, Default)]
+pub struct ConfigSnapshot {
+    pub windows: Vec<ConfigWindow>,
+    pub outputs: Vec<ConfigOutput>,
+    pub spaces: Vec<ConfigSpace>,
+}
+
+/// Window handle passed to the window callbacks and returned by `get_windows`
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigWindow {
+    /// `None` for windows drawn by the compositor itself
+    pub id: Option<u64>,
+    pub app_id: String,
+    pub title: String,
+    pub space: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub focused: bool,
+}
+
+impl ConfigWindow {
+    pub fn new(
+        window: &WorkspaceWindow,
+        space: Option<&Space<WorkspaceWindow>>,
+        space_name: &str,
+        focused: bool,
+    ) -> Self {
+        let location = space
+            .and_then(|space| space.element_location(window))
+            .unwrap_or_default();
+        let size = window.geometry().size;
+        ConfigWindow {
+            id: window.id(),
+            app_id: window.app_id(),
+            title: window.title(),
+            space: space_name.to_string(),
+            x: location.x,
+            y: location.y,
+            width: size.w,
+            height: size.h,
+            focused,
+        }
+    }
+}
+
+impl<'lua> IntoLua<'lua> for ConfigWindow {
+    fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let lua_window = lua.create_table().unwrap();
+        lua_window.set("id", self.id).unwrap();
+        lua_window.set("app_id", self.app_id).unwrap();
+        lua_window.set("title", self.title).unwrap();
+        lua_window.set("space", self.space).unwrap();
+        lua_window.set("x", self.x).unwrap();
+        lua_window.set("y", self.y).unwrap();
+        lua_window.set("width", self.width).unwrap();
+        lua_window.set("height", self.height).unwrap();
+        lua_window.set("focused", self.focused).unwrap();
+        lua_window.into_lua(lua)
+    }
+}
+
+/// Space returned by `get_spaces`
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSpace {
+    pub name: String,
+    /// Names of the outputs showing the space
+    pub outputs: Vec<String>,
+    /// Ids of the windows on the space, topmost last
+    pub windows: Vec<u64>,
+}
+
+impl<'lua> IntoLua<'lua> for ConfigSpace {
+    fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let lua_space = lua.create_table().unwrap();
+        lua_space.set("name", self.name).unwrap();
+        lua_space.set("outputs", self.outputs).unwrap();
+        lua_space.set("windows", self.windows).unwrap();
+        lua_space.into_lua(lua)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigOutput {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub default: bool,
+    pub disabled: bool,
+    pub scale: i32,
+}
+
+impl From<&Output> for ConfigOutput {
+    fn from(value: &Output) -> Self {
+        let mode = value
+            .current_mode()
+            .or_else(|| value.preferred_mode())
+            .unwrap();
+        let location = value.current_location();
+        ConfigOutput {
             name: value.name(),
             x: location.x,
             y: location.y,
             width: mode.size.w,
             height: mode.size.h,
-            default: true,   // FIXME: set proper value
-            disabled: false, // FIXME: set proper value
+            default: true, // FIXME: set proper value
+            disabled: false,
             scale: value.current_scale().integer_scale(),
         }
     }
 }
 
-impl<'lua> IntoLua<'lua> for ConfigOutput {
-    fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
-        let lua_output = lua.create_table().unwrap();
-        lua_output.set("name", self.name).unwrap();
-        lua_output.set("x", self.x).unwrap();
-        lua_output.set("y", self.y).unwrap();
-        lua_output.set("width", self.width).unwrap();
-        lua_output.set("height", self.height).unwrap();
-        lua_output.set("default", self.default).unwrap();
-        lua_output.set("disabled", self.disabled).unwrap();
-        lua_output.set("scale", self.scale).unwrap();
-        lua_output.into_lua(lua)
+impl<'lua> IntoLua<'lua> for ConfigOutput {
+    fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let lua_output = lua.create_table().unwrap();
+        lua_output.set("name", self.name).unwrap();
+        lua_output.set("x", self.x).unwrap();
+        lua_output.set("y", self.y).unwrap();
+        lua_output.set("width", self.width).unwrap();
+        lua_output.set("height", self.height).unwrap();
+        lua_output.set("default", self.default).unwrap();
+        lua_output.set("disabled", self.disabled).unwrap();
+        lua_output.set("scale", self.scale).unwrap();
+        lua_output.into_lua(lua)
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigOutput {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigOutput {
+            name: table.get("name")?,
+            x: table.get("x")?,
+            y: table.get("y")?,
+            width: table.get("width")?,
+            height: table.get("height")?,
+            default: table.get("default")?,
+            disabled: table.get("disabled")?,
+            scale: table.get("scale")?,
+        })
+    }
+}
+
+/// Connected output with the identity read from its EDID and the modes it supports
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigOutputDevice {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    /// Not every display reports a serial number
+    pub serial: Option<String>,
+    pub modes: Vec<ConfigMode>,
+}
+
+impl From<&Output> for ConfigOutputDevice {
+    fn from(value: &Output) -> Self {
+        let properties = value.physical_properties();
+        let preferred_mode = value.preferred_mode();
+        let current_mode = value.current_mode();
+        let modes = value
+            .modes()
+            .into_iter()
+            .map(|mode| ConfigMode {
+                width: mode.size.w,
+                height: mode.size.h,
+                refresh: mode.refresh as f64 / 1000.0,
+                preferred: preferred_mode == Some(mode),
+                current: current_mode == Some(mode),
+            })
+            .collect();
+        ConfigOutputDevice {
+            name: value.name(),
+            make: properties.make,
+            model: properties.model,
+            serial: value
+                .user_data()
+                .get::<OutputSerial>()
+                .map(|serial| serial.0.clone()),
+            modes,
+        }
+    }
+}
+
+impl<'lua> IntoLua<'lua> for ConfigOutputDevice {
+    fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let lua_device = lua.create_table()?;
+        lua_device.set("name", self.name)?;
+        lua_device.set("make", self.make)?;
+        lua_device.set("model", self.model)?;
+        lua_device.set("serial", self.serial)?;
+        lua_device.set("modes", self.modes)?;
+        lua_device.into_lua(lua)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigMode {
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate in Hz
+    pub refresh: f64,
+    pub preferred: bool,
+    pub current: bool,
+}
+
+impl<'lua> IntoLua<'lua> for ConfigMode {
+    fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let lua_mode = lua.create_table()?;
+        // In the form `configure_output` accepts
+        let mode = format!("{}x{}@{}", self.width, self.height, self.refresh);
+        lua_mode.set("mode", mode)?;
+        lua_mode.set("width", self.width)?;
+        lua_mode.set("height", self.height)?;
+        lua_mode.set("refresh", self.refresh)?;
+        lua_mode.set("preferred", self.preferred)?;
+        lua_mode.set("current", self.current)?;
+        lua_mode.into_lua(lua)
+    }
+}
+
+pub struct ConfigZone {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub default: bool,
+}
+
+impl<'lua> FromLua<'lua> for ConfigZone {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigZone {
+            name: table.get("name")?,
+            x: table.get("x")?,
+            y: table.get("y")?,
+            width: table.get("width")?,
+            height: table.get("height")?,
+            default: table.get("default").unwrap_or_default(),
+        })
+    }
+}
+
+/// Named set of zones in the form of `{name, zones}`
+struct ConfigZoneLayout {
+    name: String,
+    zones: Vec<ConfigZone>,
+}
+
+impl<'lua> FromLua<'lua> for ConfigZoneLayout {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigZoneLayout {
+            name: table.get("name")?,
+            zones: table.get("zones")?,
+        })
+    }
+}
+
+impl From<ConfigZoneLayout> for ZoneLayout {
+    fn from(value: ConfigZoneLayout) -> Self {
+        ZoneLayout {
+            name: value.name,
+            zones: value.zones.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Target of a window in the form of `{zone}`, `{space}` or `{x, y}` in global coordinates
+struct ConfigMoveTarget(MoveTarget);
+
+impl<'lua> FromLua<'lua> for ConfigMoveTarget {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        if let Some(zone) = table.get::<_, Option<String>>("zone")? {
+            return Ok(ConfigMoveTarget(MoveTarget::Zone(zone)));
+        }
+        if let Some(space) = table.get::<_, Option<String>>("space")? {
+            return Ok(ConfigMoveTarget(MoveTarget::Space(space)));
+        }
+        match (
+            table.get::<_, Option<i32>>("x")?,
+            table.get::<_, Option<i32>>("y")?,
+        ) {
+            (Some(x), Some(y)) => Ok(ConfigMoveTarget(MoveTarget::Position((x, y).into()))),
+            _ => Err(LuaError::runtime(
+                "Window target needs a zone, a space or x and y",
+            )),
+        }
+    }
+}
+
+/// Gaps of zones in the form of `{inner, outer}` in pixels
+struct ConfigZoneGaps(ZoneGaps);
+
+impl<'lua> FromLua<'lua> for ConfigZoneGaps {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let gap = |name: &str| -> LuaResult<i32> {
+            match table.get::<_, Option<i32>>(name)?.unwrap_or_default() {
+                gap if gap < 0 => Err(LuaError::runtime(format!(
+                    "Zone gap {name} must not be negative, got {gap}"
+                ))),
+                gap => Ok(gap),
+            }
+        };
+
+        Ok(ConfigZoneGaps(ZoneGaps {
+            inner: gap("inner")?,
+            outer: gap("outer")?,
+        }))
+    }
+}
+
+/// Parses modifiers in the form of `shift|super`
+fn mods_from_table(table: &Table) -> Mods {
+    let mut mods = Mods::default();
+    for mod_key in table
+        .get::<_, String>("mods")
+        .unwrap_or_default()
+        .split('|')
+    {
+        add_mod(&mut mods, mod_key);
+    }
+    mods
+}
+
+fn add_mod(mods: &mut Mods, mod_key: &str) {
+    match mod_key {
+        "shift" => mods.shift = true,
+        "logo" | "super" => mods.logo = true,
+        "ctrl" => mods.ctrl = true,
+        "alt" => mods.alt = true,
+        "" => {}
+        _ => warn!(%mod_key, "Unhandled mod key"),
+    }
+}
+
+/// Parses the keys of a chord in the form of `super+space b`
+fn chord_keys_from_str(keys: &str) -> LuaResult<Vec<ChordKey>> {
+    let keys = keys
+        .split_whitespace()
+        .map(|step| {
+            let (mod_keys, name) = step.rsplit_once('+').unwrap_or(("", step));
+            let mut mods = Mods::default();
+            for mod_key in mod_keys.split('+') {
+                add_mod(&mut mods, mod_key);
+            }
+            let key = keysym_from_name(name, &mut mods)?;
+            Ok((mods, key))
+        })
+        .collect::<LuaResult<Vec<_>>>()?;
+    if keys.is_empty() {
+        return Err(LuaError::runtime(
+            "The keys of a key binding must not be empty",
+        ));
+    }
+    Ok(keys)
+}
+
+/// Window given as a window table or as its id, the focused window is used for nil
+fn window_id_from_lua(lua: &Lua, window: LuaValue<'_>) -> LuaResult<Option<u64>> {
+    match window {
+        LuaValue::Nil => Ok(None),
+        LuaValue::Table(window) => window.get("id"),
+        window => Ok(Some(u64::from_lua(window, lua)?)),
+    }
+}
+
+/// Keysym of a key name, any xkb keysym name like `Print` or `XF86AudioRaiseVolume` is accepted.
+/// A single uppercase character implies the shift modifier.
+fn keysym_from_name(name: &str, mods: &mut Mods) -> LuaResult<Keysym> {
+    let mut chars = name.chars();
+    if let (Some(mut c), None) = (chars.next(), chars.next()) {
+        if c.is_uppercase() {
+            mods.shift = true;
+        }
+        if mods.shift {
+            c = c.to_uppercase().next().unwrap();
+        }
+        return Ok(Keysym::from_char(c));
+    }
+
+    // Older configs spell the media keys with an underscore, like `XF86_AudioPlay`
+    let name = name.replacen("XF86_", "XF86", 1);
+    let keysym = [xkb::KEYSYM_NO_FLAGS, xkb::KEYSYM_CASE_INSENSITIVE]
+        .into_iter()
+        .map(|flags| xkb::keysym_from_name(&name, flags))
+        .find(|keysym| *keysym != Keysym::NoSymbol)
+        .ok_or_else(|| LuaError::runtime(format!("Unknown key: {name}")))?;
+    Ok(keysym)
+}
+
+/// Key binding in the form of `{key, mods, callback}` or `{keys, callback}` for a chord with the
+/// keys separated by spaces like `keys = "super+space b"`. Single keys can additionally have a
+/// `release_callback`, which is called once the key is released again.
+struct ConfigMapKey {
+    /// Key mode the key is mapped in, see `enter_mode`
+    mode: String,
+    keys: Vec<ChordKey>,
+    binding: KeyBinding,
+    /// Shown in the hint of chords
+    description: Option<String>,
+}
+
+impl<'lua> FromLua<'lua> for ConfigMapKey {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let keys = match table.get::<_, Option<String>>("keys")? {
+            Some(keys) => chord_keys_from_str(&keys)?,
+            None => {
+                let mut mods = mods_from_table(&table);
+                let key = keysym_from_name(&table.get::<_, String>("key")?, &mut mods)?;
+                vec![(mods, key)]
+            }
+        };
+
+        let callback = table.get("callback")?;
+
+        Ok(ConfigMapKey {
+            mode: table
+                .get::<_, Option<String>>("mode")?
+                .unwrap_or_else(|| DEFAULT_KEY_MODE.to_string()),
+            keys,
+            binding: KeyBinding {
+                callback,
+                repeat: table.get::<_, Option<bool>>("repeat")?.unwrap_or_default(),
+                release_callback: table.get("release_callback")?,
+            },
+            description: table.get("description")?,
+        })
+    }
+}
+
+/// Button binding in the form of `{button, mods, action}` or `{button, mods, callback}`
+struct ConfigMapButton {
+    button: u32,
+    mods: Mods,
+    binding: ButtonBinding,
+}
+
+impl<'lua> FromLua<'lua> for ConfigMapButton {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let name = table.get::<_, String>("button")?;
+        let button = button_from_name(&name)
+            .ok_or_else(|| LuaError::runtime(format!("Unknown button: {name}")))?;
+
+        let binding = match table.get::<_, Option<String>>("action")?.as_deref() {
+            Some("move") => ButtonBinding::Move,
+            Some("resize") => ButtonBinding::Resize,
+            Some(action) => {
+                return Err(LuaError::runtime(format!(
+                    "Unknown button action: {action}"
+                )));
+            }
+            None => ButtonBinding::Callback(table.get("callback")?),
+        };
+
+        Ok(ConfigMapButton {
+            button,
+            mods: mods_from_table(&table),
+            binding,
+        })
+    }
+}
+
+struct ConfigWindowRule {
+    app_id: String,
+    zone: String,
+}
+
+impl<'lua> FromLua<'lua> for ConfigWindowRule {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigWindowRule {
+            app_id: table.get("app_id")?,
+            zone: table.get("zone")?,
+        })
+    }
+}
+
+/// Window rule in the form of `{matches = {app_id, title}, properties = {...}}`, the properties
+/// can also be set next to `matches`
+struct ConfigRule {
+    app_id: Option<String>,
+    title: Option<String>,
+    floating: Option<bool>,
+    zone: Option<String>,
+    space: Option<String>,
+    width: Option<i32>,
+    height: Option<i32>,
+    opacity: Option<f32>,
+    opacity_unfocused: Option<f32>,
+    ssd: Option<bool>,
+    scroll_factor: Option<f64>,
+    swap_scroll_axes: Option<bool>,
+}
+
+impl From<ConfigRule> for WindowRule {
+    fn from(value: ConfigRule) -> Self {
+        WindowRule {
+            matches: WindowMatch {
+                app_id: value.app_id,
+                title: value.title,
+            },
+            properties: WindowProperties {
+                floating: value.floating,
+                zone: value.zone,
+                space: value.space,
+                size: value.width.zip(value.height).map(Into::into),
+                opacity: value.opacity,
+                opacity_unfocused: value.opacity_unfocused,
+                ssd: value.ssd,
+                scroll_factor: value.scroll_factor,
+                swap_scroll_axes: value.swap_scroll_axes,
+            },
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigRule {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+        let matches: Table<'_> = table.get("matches")?;
+        let properties = table
+            .get::<_, Option<Table<'_>>>("properties")?
+            .unwrap_or_else(|| table.clone());
+        let size: Option<Table<'_>> = properties.get("size")?;
+
+        Ok(ConfigRule {
+            app_id: matches.get("app_id")?,
+            title: matches.get("title")?,
+            floating: properties.get("floating")?,
+            zone: properties.get("zone")?,
+            space: properties.get("space")?,
+            width: size.as_ref().map(|size| size.get("width")).transpose()?,
+            height: size.as_ref().map(|size| size.get("height")).transpose()?,
+            opacity: properties.get("opacity")?,
+            opacity_unfocused: properties.get("opacity_unfocused")?,
+            ssd: properties.get("ssd")?,
+            scroll_factor: properties.get("scroll_factor")?,
+            swap_scroll_axes: properties.get("swap_scroll_axes")?,
+        })
     }
 }
 
-impl<'lua> FromLua<'lua> for ConfigOutput {
-    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
-        let table = value.as_table().unwrap();
+/// Command in the form of `{command, args, shell, zone, env, cwd}` or only the command line. The
+/// command line is split into words like a shell does, `args` are appended to them.
+struct ConfigSpawn {
+    command: String,
+    args: Vec<String>,
+    /// Zone the first window is placed in, a placeholder is shown there until it appears
+    zone: Option<String>,
+    /// Environment variables which are set in addition to the ones of the compositor
+    env: HashMap<String, String>,
+    /// Working directory, the one of the compositor if none is given
+    cwd: Option<String>,
+}
 
-        Ok(ConfigOutput {
-            name: table.get("name").unwrap(),
-            x: table.get("x").unwrap(),
-            y: table.get("y").unwrap(),
-            width: table.get("width").unwrap(),
-            height: table.get("height").unwrap(),
-            default: table.get("default").unwrap(),
-            disabled: table.get("disabled").unwrap(),
-            scale: table.get("scale").unwrap(),
+impl<'lua> FromLua<'lua> for ConfigSpawn {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        if let LuaValue::String(command_line) = &value {
+            let (command, args) = command_from_line(command_line.to_str()?, Vec::new(), false)?;
+            return Ok(Self {
+                command,
+                args,
+                zone: None,
+                env: HashMap::new(),
+                cwd: None,
+            });
+        }
+        let table = Table::from_lua(value, lua)?;
+        let (command, args) = command_from_table(&table)?;
+
+        Ok(Self {
+            command,
+            args,
+            zone: table.get("zone")?,
+            env: table.get("env").unwrap_or_default(),
+            cwd: table.get("cwd")?,
         })
     }
 }
 
-pub struct ConfigZone {
-    pub name: String,
-    pub x: i32,
-    pub y: i32,
-    pub width: i32,
-    pub height: i32,
-    pub default: bool,
+/// Program and arguments of a table with `command`, `args` and `shell`
+fn command_from_table(table: &Table<'_>) -> LuaResult<(String, Vec<String>)> {
+    let command_line: String = table.get("command")?;
+    let args = table
+        .get::<_, Option<Vec<String>>>("args")?
+        .unwrap_or_default();
+    let shell = table.get::<_, Option<bool>>("shell")?.unwrap_or_default();
+    command_from_line(&command_line, args, shell)
 }
 
-impl<'lua> FromLua<'lua> for ConfigZone {
-    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
-        let table = value.as_table().unwrap();
+/// Splits the command line into the program and its arguments followed by `args`. With `shell`
+/// the line is run by `sh -c` instead, which makes pipes and redirections work, `args` are its
+/// positional parameters then.
+fn command_from_line(
+    command_line: &str,
+    args: Vec<String>,
+    shell: bool,
+) -> LuaResult<(String, Vec<String>)> {
+    if shell {
+        let mut shell_args = vec!["-c".to_string(), command_line.to_string(), "sh".to_string()];
+        shell_args.extend(args);
+        return Ok(("sh".to_string(), shell_args));
+    }
+    let mut words = split_command_line(command_line)
+        .map_err(|err| LuaError::runtime(format!("{err:#}")))?
+        .into_iter();
+    let command = words
+        .next()
+        .ok_or_else(|| LuaError::runtime("Command must not be empty"))?;
+    Ok((command, words.chain(args).collect()))
+}
 
-        Ok(ConfigZone {
-            name: table.get("name").unwrap(),
-            x: table.get("x").unwrap(),
-            y: table.get("y").unwrap(),
-            width: table.get("width").unwrap(),
-            height: table.get("height").unwrap(),
-            default: table.get("default").unwrap_or_default(),
+/// Scratchpad in the form of `{name, command, args, shell}`, arguments can be part of the command
+/// as well
+struct ConfigScratchpad {
+    name: String,
+    command: String,
+    args: Vec<String>,
+}
+
+impl<'lua> FromLua<'lua> for ConfigScratchpad {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let (command, args) = command_from_table(&table)?;
+
+        Ok(Self {
+            name: table.get("name")?,
+            command,
+            args,
         })
     }
 }
 
-struct ConfigMapKey {
-    key: Keysym,
-    mods: Mods,
-    callback: LuaFunction<'static>,
+/// Idle timeouts in seconds in the form of `{lock, screen_off, suspend, lock_command = {command,
+/// args}}`
+struct ConfigIdleTimeouts(IdleTimeouts);
+
+impl<'lua> FromLua<'lua> for ConfigIdleTimeouts {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let timeout = |name: &str| -> LuaResult<Option<Duration>> {
+            match table.get::<_, Option<f64>>(name)? {
+                Some(seconds) if seconds > 0.0 => Ok(Some(Duration::from_secs_f64(seconds))),
+                Some(seconds) => Err(LuaError::runtime(format!(
+                    "Idle timeout {name} has to be positive, got {seconds}"
+                ))),
+                None => Ok(None),
+            }
+        };
+        let lock_command = table
+            .get::<_, Option<LuaValue<'_>>>("lock_command")?
+            .map(|value| ConfigSpawn::from_lua(value, lua))
+            .transpose()?
+            .map(|spawn| (spawn.command, spawn.args));
+
+        Ok(ConfigIdleTimeouts(IdleTimeouts {
+            lock: timeout("lock")?,
+            screen_off: timeout("screen_off")?,
+            suspend: timeout("suspend")?,
+            lock_command,
+        }))
+    }
 }
 
-impl<'lua> FromLua<'lua> for ConfigMapKey {
-    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
-        let table = value.as_table().unwrap();
-
-        let mut mods = Mods::default();
-        for mod_key in table
-            .get::<_, String>("mods")
-            .unwrap_or_default()
-            .split('|')
-        {
-            match mod_key {
-                "shift" => mods.shift = true,
-                "logo" | "super" => mods.logo = true,
-                "ctrl" => mods.ctrl = true,
-                "alt" => mods.alt = true,
-                "" => {}
-                _ => warn!(%mod_key, "Unhandled mod key"),
+/// libinput settings in the form of `{device, tap, natural_scroll, accel_profile, accel_speed}`,
+/// all devices are configured if no device name is given
+struct ConfigInput(InputConfig);
+
+impl<'lua> FromLua<'lua> for ConfigInput {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let accel_profile = table
+            .get::<_, Option<String>>("accel_profile")?
+            .map(|name| {
+                accel_profile_from_name(&name).ok_or_else(|| {
+                    LuaError::runtime(format!(
+                        "Unknown acceleration profile {name}, expected flat or adaptive"
+                    ))
+                })
+            })
+            .transpose()?;
+
+        Ok(ConfigInput(InputConfig {
+            device: table.get("device")?,
+            tap: table.get("tap")?,
+            natural_scroll: table.get("natural_scroll")?,
+            accel_profile,
+            accel_speed: table.get("accel_speed")?,
+        }))
+    }
+}
+
+/// Kiosk mode in the form of `{command, args, shell, escape = {key, mods}}`, the escape sequence
+/// defaults to ctrl+alt+shift+Escape
+struct ConfigKiosk(KioskConfig);
+
+impl<'lua> FromLua<'lua> for ConfigKiosk {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let (escape_key, escape_mods) = match table.get::<_, Option<Table>>("escape")? {
+            Some(escape) => {
+                let mut mods = mods_from_table(&escape);
+                let key = keysym_from_name(&escape.get::<_, String>("key")?, &mut mods)?;
+                (key, mods)
             }
+            None => (
+                Keysym::Escape,
+                Mods {
+                    ctrl: true,
+                    alt: true,
+                    shift: true,
+                    ..Default::default()
+                },
+            ),
+        };
+
+        let (command, args) = command_from_table(&table)?;
+
+        Ok(ConfigKiosk(KioskConfig {
+            command,
+            args,
+            escape_key,
+            escape_mods,
+        }))
+    }
+}
+
+/// Startup entry in the form of `{command, args, shell, wait_for = {app_id} or {dbus_name},
+/// timeout}`
+struct ConfigStartupEntry {
+    command: String,
+    args: Vec<String>,
+    app_id: Option<String>,
+    dbus_name: Option<String>,
+    /// Seconds to wait for the entry to become ready
+    timeout: Option<f64>,
+}
+
+impl From<ConfigStartupEntry> for StartupEntry {
+    fn from(value: ConfigStartupEntry) -> Self {
+        let wait_for = match (value.app_id, value.dbus_name) {
+            (Some(app_id), _) => Some(ReadyCondition::Window { app_id }),
+            (None, Some(name)) => Some(ReadyCondition::DbusName { name }),
+            (None, None) => None,
+        };
+        StartupEntry {
+            command: value.command,
+            args: value.args,
+            wait_for,
+            timeout: Duration::from_secs_f64(value.timeout.unwrap_or(10.0).max(0.0)),
         }
+    }
+}
 
-        let key = match table.get::<_, String>("key").unwrap().as_str() {
-            "Left" => Keysym::Left,
-            "Right" => Keysym::Right,
-            "Up" => Keysym::Up,
-            "Down" => Keysym::Down,
-            "F1" => Keysym::F1,
-            "F2" => Keysym::F2,
-            "F3" => Keysym::F3,
-            "F4" => Keysym::F4,
-            "F5" => Keysym::F5,
-            "F6" => Keysym::F6,
-            "F7" => Keysym::F7,
-            "F8" => Keysym::F8,
-            "F9" => Keysym::F9,
-            "F10" => Keysym::F10,
-            "F11" => Keysym::F11,
-            "F12" => Keysym::F12,
-            "F13" => Keysym::F13,
-            "F14" => Keysym::F14,
-            "F15" => Keysym::F15,
-            "F16" => Keysym::F16,
-            "F17" => Keysym::F17,
-            "F18" => Keysym::F18,
-            "F19" => Keysym::F19,
-            "F20" => Keysym::F20,
-            "F21" => Keysym::F21,
-            "F22" => Keysym::F22,
-            "F23" => Keysym::F23,
-            "F24" => Keysym::F24,
-            "F25" => Keysym::F25,
-            "F26" => Keysym::F26,
-            "F27" => Keysym::F27,
-            "F28" => Keysym::F28,
-            "F29" => Keysym::F29,
-            "F30" => Keysym::F30,
-            "F31" => Keysym::F31,
-            "F32" => Keysym::F32,
-            "F33" => Keysym::F33,
-            "F34" => Keysym::F34,
-            "F35" => Keysym::F35,
-            "XF86_AudioPlay" => Keysym::XF86_AudioPlay,
-            "XF86_AudioNext" => Keysym::XF86_AudioNext,
-            "XF86_AudioPrev" => Keysym::XF86_AudioPrev,
-            "XF86_AudioMute" => Keysym::XF86_AudioMute,
-            "XF86_AudioRaiseVolume" => Keysym::XF86_AudioRaiseVolume,
-            "XF86_AudioLowerVolume" => Keysym::XF86_AudioLowerVolume,
-            key => {
-                let mut c = key.chars().next().unwrap();
-                if c.is_uppercase() {
-                    mods.shift = true;
-                }
-                if mods.shift {
-                    c = c.to_uppercase().next().unwrap();
-                }
-                Keysym::from_char(c)
+impl<'lua> FromLua<'lua> for ConfigStartupEntry {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+        let (command, args) = command_from_table(&table)?;
+        let (app_id, dbus_name) = match table.get::<_, Option<Table>>("wait_for")? {
+            Some(wait_for) => (wait_for.get("app_id")?, wait_for.get("dbus_name")?),
+            None => (None, None),
+        };
+
+        Ok(ConfigStartupEntry {
+            command,
+            args,
+            app_id,
+            dbus_name,
+            timeout: table.get("timeout")?,
+        })
+    }
+}
+
+struct ConfigThumbnail {
+    app_id: String,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl<'lua> FromLua<'lua> for ConfigThumbnail {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigThumbnail {
+            app_id: table.get("app_id")?,
+            x: table.get("x")?,
+            y: table.get("y")?,
+            width: table.get("width")?,
+            height: table.get("height")?,
+        })
+    }
+}
+
+struct ConfigKeystrokeVisualizer {
+    position: Option<String>,
+    /// Seconds until a keystroke faded out
+    fade: Option<f64>,
+    masked_app_ids: Option<Vec<String>>,
+}
+
+impl ConfigKeystrokeVisualizer {
+    fn apply(self, config: &mut KeystrokeVisualizerConfig) {
+        if let Some(position) = self.position {
+            match OverlayCorner::from_name(&position) {
+                Some(corner) => config.corner = corner,
+                None => warn!(%position, "Unknown keystroke visualizer position"),
+            }
+        }
+        if let Some(fade) = self.fade {
+            config.fade = Duration::from_secs_f64(fade.max(0.1));
+        }
+        if let Some(masked_app_ids) = self.masked_app_ids {
+            config.masked_app_ids = masked_app_ids;
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigKeystrokeVisualizer {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigKeystrokeVisualizer {
+            position: table.get("position")?,
+            fade: table.get("fade")?,
+            masked_app_ids: table.get("masked_app_ids")?,
+        })
+    }
+}
+
+/// Focus behavior in the form of `{follows_mouse, delay_ms, raise_on_focus}`
+struct ConfigFocus {
+    follows_mouse: Option<bool>,
+    delay: Option<Duration>,
+    raise_on_focus: Option<bool>,
+}
+
+impl ConfigFocus {
+    fn apply(self, config: &mut FocusConfig) {
+        if let Some(follows_mouse) = self.follows_mouse {
+            config.follows_mouse = follows_mouse;
+        }
+        if let Some(delay) = self.delay {
+            config.delay = delay;
+        }
+        if let Some(raise_on_focus) = self.raise_on_focus {
+            config.raise_on_focus = raise_on_focus;
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigFocus {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigFocus {
+            follows_mouse: table.get("follows_mouse")?,
+            delay: table
+                .get::<_, Option<u64>>("delay_ms")?
+                .map(Duration::from_millis),
+            raise_on_focus: table.get("raise_on_focus")?,
+        })
+    }
+}
+
+/// Drop behavior of dragged windows in the form of `{drop, highlight}`, `drop` is one of
+/// `"snap"`, `"zone"` or `"float"`
+struct ConfigDrag {
+    drop: Option<DropBehavior>,
+    highlight: Option<bool>,
+}
+
+impl ConfigDrag {
+    fn apply(self, config: &mut DragConfig) {
+        if let Some(drop) = self.drop {
+            config.drop = drop;
+        }
+        if let Some(highlight) = self.highlight {
+            config.highlight = highlight;
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigDrag {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let drop = match table.get::<_, Option<String>>("drop")?.as_deref() {
+            Some("snap") => Some(DropBehavior::EdgeSnap),
+            Some("zone") => Some(DropBehavior::Zone),
+            Some("float") => Some(DropBehavior::Float),
+            Some(drop) => return Err(LuaError::runtime(format!("Unknown drop behavior: {drop}"))),
+            None => None,
+        };
+
+        Ok(ConfigDrag {
+            drop,
+            highlight: table.get("highlight")?,
+        })
+    }
+}
+
+/// Chord settings in the form of `{timeout, hint}`, the timeout is given in seconds
+struct ConfigKeyChords {
+    timeout: Option<Duration>,
+    hint: Option<bool>,
+}
+
+impl ConfigKeyChords {
+    fn apply(self, config: &mut KeyChordConfig) {
+        if let Some(timeout) = self.timeout {
+            config.timeout = timeout;
+        }
+        if let Some(hint) = self.hint {
+            config.hint = hint;
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigKeyChords {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let timeout = match table.get::<_, Option<f64>>("timeout")? {
+            Some(seconds) if seconds > 0.0 => Some(Duration::from_secs_f64(seconds)),
+            Some(seconds) => {
+                return Err(LuaError::runtime(format!(
+                    "Key chord timeout has to be positive, got {seconds}"
+                )))
             }
+            None => None,
         };
 
-        // SAFETY: The callback is valid as long as the lua instance is alive.
-        // The lua instance is never dropped, therefore the lifetime of the callback is
-        // effectively 'static.
-        let callback = unsafe {
-            std::mem::transmute::<LuaFunction<'_>, LuaFunction<'_>>(
-                table.get::<_, LuaFunction<'_>>("callback").unwrap(),
-            )
+        Ok(ConfigKeyChords {
+            timeout,
+            hint: table.get("hint")?,
+        })
+    }
+}
+
+/// Key repeat in the form of `{rate, delay}`, repeats per second and milliseconds until the first
+/// repeat
+struct ConfigKeyRepeat {
+    rate: Option<i32>,
+    delay: Option<i32>,
+}
+
+impl ConfigKeyRepeat {
+    fn apply(self, config: &mut KeyRepeatConfig) {
+        if let Some(rate) = self.rate {
+            config.rate = rate;
+        }
+        if let Some(delay) = self.delay {
+            config.delay = delay;
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigKeyRepeat {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let value = |name: &str| -> LuaResult<Option<i32>> {
+            match table.get::<_, Option<i32>>(name)? {
+                Some(value) if value < 0 => Err(LuaError::runtime(format!(
+                    "Key repeat {name} must not be negative, got {value}"
+                ))),
+                value => Ok(value),
+            }
         };
 
-        Ok(ConfigMapKey {
-            key,
-            mods,
-            callback,
+        Ok(ConfigKeyRepeat {
+            rate: value("rate")?,
+            delay: value("delay")?,
         })
     }
 }
 
-struct ConfigWindowRule {
-    app_id: String,
-    zone: String,
+/// Keymap in the form of `{layouts, variant, model, options}`, layouts and variants are either
+/// lists or strings separated by commas
+struct ConfigKeyboard {
+    layouts: Option<Vec<String>>,
+    variants: Option<Vec<String>>,
+    model: Option<String>,
+    options: Option<String>,
 }
 
-impl<'lua> FromLua<'lua> for ConfigWindowRule {
-    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
-        let table = value.as_table().unwrap();
+impl ConfigKeyboard {
+    fn apply(self, config: &mut KeyboardConfig) {
+        if let Some(layouts) = self.layouts {
+            config.layouts = layouts;
+            // Variants belong to the layouts they were given for
+            config.variants.clear();
+        }
+        if let Some(variants) = self.variants {
+            config.variants = variants;
+        }
+        if self.model.is_some() {
+            config.model = self.model;
+        }
+        if self.options.is_some() {
+            config.options = self.options;
+        }
+    }
+}
 
-        Ok(ConfigWindowRule {
-            app_id: table.get("app_id").unwrap(),
-            zone: table.get("zone").unwrap(),
+impl<'lua> FromLua<'lua> for ConfigKeyboard {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let list = |name: &str| -> LuaResult<Option<Vec<String>>> {
+            Ok(match table.get::<_, LuaValue<'_>>(name)? {
+                LuaValue::Nil => None,
+                LuaValue::String(list) => {
+                    Some(list.to_str()?.split(',').map(str::to_string).collect())
+                }
+                value => Some(Vec::<String>::from_lua(value, lua)?),
+            })
+        };
+
+        Ok(ConfigKeyboard {
+            layouts: list("layouts")?,
+            variants: list("variant")?,
+            model: table.get("model")?,
+            options: table.get("options")?,
         })
     }
 }
 
-struct ConfigSpawn {
-    command: String,
-    args: Vec<String>,
+struct ConfigModifierIndicators {
+    lock_keys: Option<bool>,
+    sticky_modifiers: Option<bool>,
 }
 
-impl<'lua> FromLua<'lua> for ConfigSpawn {
-    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
-        let table = value.as_table().unwrap();
+impl ConfigModifierIndicators {
+    fn apply(self, config: &mut ModifierIndicatorConfig) {
+        if let Some(lock_keys) = self.lock_keys {
+            config.lock_keys = lock_keys;
+        }
+        if let Some(sticky_modifiers) = self.sticky_modifiers {
+            config.sticky_modifiers = sticky_modifiers;
+        }
+    }
+}
 
-        Ok(Self {
-            command: table.get("command").unwrap(),
-            args: table.get("args").unwrap_or_default(),
+impl<'lua> FromLua<'lua> for ConfigModifierIndicators {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigModifierIndicators {
+            lock_keys: table.get("lock_keys")?,
+            sticky_modifiers: table.get("sticky_modifiers")?,
+        })
+    }
+}
+
+struct ConfigMoveMode {
+    step: Option<i32>,
+    large_step: Option<i32>,
+    grid: Option<i32>,
+}
+
+impl ConfigMoveMode {
+    fn apply(self, config: &mut MoveModeConfig) {
+        if let Some(step) = self.step {
+            config.step = step;
+        }
+        if let Some(large_step) = self.large_step {
+            config.large_step = large_step;
+        }
+        if let Some(grid) = self.grid {
+            config.grid = grid;
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigMoveMode {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigMoveMode {
+            step: table.get("step")?,
+            large_step: table.get("large_step")?,
+            grid: table.get("grid")?,
+        })
+    }
+}
+
+struct ConfigRecording {
+    directory: Option<String>,
+    codec: Option<VideoCodec>,
+    fps: Option<u32>,
+}
+
+impl ConfigRecording {
+    fn apply(self, config: &mut RecordingConfig) {
+        if let Some(directory) = self.directory {
+            config.directory = Some(PathBuf::from(directory));
+        }
+        if let Some(codec) = self.codec {
+            config.codec = codec;
+        }
+        if let Some(fps) = self.fps {
+            config.fps = fps;
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigRecording {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        let codec = table
+            .get::<_, Option<String>>("codec")?
+            .map(|codec| {
+                VideoCodec::from_name(&codec)
+                    .ok_or_else(|| LuaError::runtime(format!("Unknown codec {codec}")))
+            })
+            .transpose()?;
+
+        Ok(ConfigRecording {
+            directory: table.get("directory")?,
+            codec,
+            fps: table.get("fps")?,
+        })
+    }
+}
+
+struct ConfigTheme {
+    variant: Option<String>,
+    light: HashMap<String, [f32; 4]>,
+    dark: HashMap<String, [f32; 4]>,
+    font_size: Option<f32>,
+    border_width: Option<i32>,
+    spacing: Option<i32>,
+    light_from: Option<u32>,
+    dark_from: Option<u32>,
+}
+
+impl ConfigTheme {
+    fn apply(self, theme: &mut Theme) {
+        match self.variant.as_deref() {
+            Some("light") => {
+                theme.variant = ThemeVariant::Light;
+                theme.auto_switch = None;
+            }
+            Some("dark") => {
+                theme.variant = ThemeVariant::Dark;
+                theme.auto_switch = None;
+            }
+            Some("auto") => theme.auto_switch = Some(theme.auto_switch.unwrap_or_default()),
+            Some(variant) => warn!(%variant, "Unknown theme variant"),
+            None => {}
+        }
+        if let Some(auto_switch) = &mut theme.auto_switch {
+            if let Some(light_from) = self.light_from {
+                auto_switch.light_from = light_from;
+            }
+            if let Some(dark_from) = self.dark_from {
+                auto_switch.dark_from = dark_from;
+            }
+        }
+
+        apply_colors(&mut theme.light, self.light);
+        apply_colors(&mut theme.dark, self.dark);
+
+        if let Some(font_size) = self.font_size {
+            theme.font_size = font_size;
+        }
+        if let Some(border_width) = self.border_width {
+            theme.border_width = border_width;
+        }
+        if let Some(spacing) = self.spacing {
+            theme.spacing = spacing;
+        }
+    }
+}
+
+fn apply_colors(palette: &mut Palette, colors: HashMap<String, [f32; 4]>) {
+    for (name, color) in colors {
+        if !palette.set_color(&name, color) {
+            warn!(%name, "Unknown theme color");
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ConfigTheme {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let table = Table::from_lua(value, lua)?;
+
+        Ok(ConfigTheme {
+            variant: table.get("variant")?,
+            light: table.get("light").unwrap_or_default(),
+            dark: table.get("dark").unwrap_or_default(),
+            font_size: table.get("font_size")?,
+            border_width: table.get("border_width")?,
+            spacing: table.get("spacing")?,
+            light_from: table.get("light_from")?,
+            dark_from: table.get("dark_from")?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrong_types_are_errors() {
+        let lua = Lua::new();
+        let value = |code: &str| lua.load(code).eval::<LuaValue>().unwrap();
+
+        assert!(matches!(
+            ConfigFocus::from_lua(value("true"), &lua),
+            Err(LuaError::FromLuaConversionError { .. })
+        ));
+        assert!(ConfigMapKey::from_lua(value("'super+a'"), &lua).is_err());
+        assert!(ConfigSpawn::from_lua(value("42"), &lua).is_err());
+        assert!(ConfigSpawn::from_lua(value("{ command = 'foot', cwd = {} }"), &lua).is_err());
+        assert!(
+            ConfigOutputSettings::from_lua(value("{ name = 'DP-1', scale = 'big' }"), &lua)
+                .is_err()
+        );
+        assert!(
+            ConfigOutputSettings::from_lua(value("{ name = 'DP-1', position = 1 }"), &lua).is_err()
+        );
+        assert!(ConfigTheme::from_lua(value("{ font_size = 'large' }"), &lua).is_err());
+        assert!(ConfigKeystrokeVisualizer::from_lua(value("{ fade = 'slow' }"), &lua).is_err());
+        assert!(ConfigThumbnail::from_lua(
+            value("{ app_id = 'foot', x = 0, y = 0, width = 'wide', height = 10 }"),
+            &lua
+        )
+        .is_err());
+        assert!(ConfigStartupEntry::from_lua(
+            value("{ command = 'foot', wait_for = 'foot' }"),
+            &lua
+        )
+        .is_err());
+        assert!(
+            ConfigKiosk::from_lua(value("{ command = 'foot', escape = { key = {} } }"), &lua)
+                .is_err()
+        );
+        assert!(ConfigModifierIndicators::from_lua(value("true"), &lua).is_err());
+        assert!(ConfigMoveMode::from_lua(value("{ step = 'far' }"), &lua).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::theme::ThemeVariant;
+use calloop::channel::{Channel, Event};
 use calloop::EventLoop;
 use tracing::{error, info};
 use zbus::Connection;
@@ -11,7 +13,8 @@ struct DbusState {
 
 // TODO: Think about if all dbus services should run on the same thread
 
-pub fn run_dbus_services() -> anyhow::Result<()> {
+/// Runs the dbus services, `theme_variants` receives every change of the compositor theme
+pub fn run_dbus_services(theme_variants: Channel<ThemeVariant>) -> anyhow::Result<()> {
     let mut event_loop = EventLoop::<'static, DbusState>::try_new()?;
     let loop_handle = event_loop.handle();
     let (executor, scheduler) = calloop::futures::executor()?;
@@ -30,6 +33,23 @@ pub fn run_dbus_services() -> anyhow::Result<()> {
         })
         .unwrap();
 
+    loop_handle
+        .insert_source(theme_variants, |event, (), state| {
+            let Event::Msg(variant) = event else {
+                return;
+            };
+            if !portals::settings::set_color_scheme(variant) {
+                return;
+            }
+            for connection in &state.connections {
+                let result = zbus::block_on(portals::settings::color_scheme_changed(connection));
+                if let Err(err) = result {
+                    error!(%err, "Unable to broadcast the color scheme");
+                }
+            }
+        })
+        .unwrap();
+
     // let future = org_gnome_mutter_screencast::start();
     // scheduler.schedule(future)?;
     let future = portals::start();
@@ -1,4 +1,5 @@
 use screen_cast::ScreenCast;
+use settings::Settings;
 use std::collections::HashMap;
 use zbus::export::serde;
 use zbus::{connection, zvariant, Connection};
@@ -6,6 +7,7 @@ use zbus::{connection, zvariant, Connection};
 pub mod request;
 pub mod screen_cast;
 pub mod session;
+pub mod settings;
 
 const PORTAL_RESPONSE_SUCCESS: u32 = 0;
 const PORTAL_RESPONSE_CANCELLED: u32 = 1;
@@ -41,6 +43,7 @@ pub async fn start() -> anyhow::Result<Connection> {
     Ok(connection::Builder::session()?
         .name("org.freedesktop.impl.portal.desktop.scape")?
         .serve_at("/org/freedesktop/portal/desktop", ScreenCast::default())?
+        .serve_at("/org/freedesktop/portal/desktop", Settings)?
         .build()
         .await?)
 }
@@ -0,0 +1,91 @@
+use crate::theme::ThemeVariant;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{fdo, interface, Connection, SignalContext};
+
+const SETTINGS_PATH: &str = "/org/freedesktop/portal/desktop";
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+/// Values of `color-scheme` in the `org.freedesktop.appearance` namespace
+const COLOR_SCHEME_NO_PREFERENCE: u32 = 0;
+const COLOR_SCHEME_PREFER_DARK: u32 = 1;
+const COLOR_SCHEME_PREFER_LIGHT: u32 = 2;
+
+static COLOR_SCHEME: AtomicU32 = AtomicU32::new(COLOR_SCHEME_NO_PREFERENCE);
+
+/// Stores the color scheme matching `variant`, returns false if it did not change
+pub fn set_color_scheme(variant: ThemeVariant) -> bool {
+    let color_scheme = match variant {
+        ThemeVariant::Light => COLOR_SCHEME_PREFER_LIGHT,
+        ThemeVariant::Dark => COLOR_SCHEME_PREFER_DARK,
+    };
+    COLOR_SCHEME.swap(color_scheme, Ordering::Relaxed) != color_scheme
+}
+
+/// Notifies clients of the settings portal about the current color scheme
+pub async fn color_scheme_changed(connection: &Connection) -> zbus::Result<()> {
+    let ctxt = SignalContext::new(connection, SETTINGS_PATH)?;
+    Settings::setting_changed(
+        &ctxt,
+        APPEARANCE_NAMESPACE,
+        COLOR_SCHEME_KEY,
+        Value::U32(COLOR_SCHEME.load(Ordering::Relaxed)),
+    )
+    .await
+}
+
+/// Whether `namespace` is requested by one of the `patterns`, which may end with a `*`
+fn namespace_requested(patterns: &[String], namespace: &str) -> bool {
+    patterns.is_empty()
+        || patterns
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => namespace.starts_with(prefix),
+                None => pattern == namespace,
+            })
+}
+
+#[derive(Debug, Default)]
+pub struct Settings;
+
+#[interface(name = "org.freedesktop.impl.portal.Settings")]
+impl Settings {
+    async fn read_all(
+        &self,
+        namespaces: Vec<String>,
+    ) -> HashMap<String, HashMap<String, OwnedValue>> {
+        let mut settings = HashMap::new();
+        if namespace_requested(&namespaces, APPEARANCE_NAMESPACE) {
+            let appearance = HashMap::from([(
+                COLOR_SCHEME_KEY.to_string(),
+                OwnedValue::from(COLOR_SCHEME.load(Ordering::Relaxed)),
+            )]);
+            settings.insert(APPEARANCE_NAMESPACE.to_string(), appearance);
+        }
+        settings
+    }
+
+    async fn read(&self, namespace: &str, key: &str) -> fdo::Result<OwnedValue> {
+        if namespace == APPEARANCE_NAMESPACE && key == COLOR_SCHEME_KEY {
+            return Ok(OwnedValue::from(COLOR_SCHEME.load(Ordering::Relaxed)));
+        }
+        Err(fdo::Error::Failed(format!(
+            "Setting {namespace} {key} not found"
+        )))
+    }
+
+    #[zbus(signal)]
+    async fn setting_changed(
+        ctxt: &SignalContext<'_>,
+        namespace: &str,
+        key: &str,
+        value: Value<'_>,
+    ) -> zbus::Result<()>;
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        1
+    }
+}
@@ -0,0 +1,51 @@
+use smithay::utils::{Logical, Point, Rectangle};
+
+/// Direction of actions which move or focus relative to the current window or output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let direction = match name {
+            "left" => Direction::Left,
+            "right" => Direction::Right,
+            "up" => Direction::Up,
+            "down" => Direction::Down,
+            _ => return None,
+        };
+        Some(direction)
+    }
+
+    /// Candidate whose center lies in the direction of the center of `from` and is closest to
+    /// it. Candidates off to the side count as further away, so that the one straight ahead wins.
+    pub fn closest<T>(
+        self,
+        from: Rectangle<i32, Logical>,
+        candidates: impl IntoIterator<Item = (T, Rectangle<i32, Logical>)>,
+    ) -> Option<T> {
+        let from = center(from);
+        candidates
+            .into_iter()
+            .filter_map(|(candidate, geometry)| {
+                let offset = center(geometry) - from;
+                let (ahead, aside) = match self {
+                    Direction::Left => (-offset.x, offset.y),
+                    Direction::Right => (offset.x, offset.y),
+                    Direction::Up => (-offset.y, offset.x),
+                    Direction::Down => (offset.y, offset.x),
+                };
+                (ahead > 0).then(|| (candidate, ahead as i64 + 2 * aside.unsigned_abs() as i64))
+            })
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(candidate, _)| candidate)
+    }
+}
+
+fn center(geometry: Rectangle<i32, Logical>) -> Point<i32, Logical> {
+    geometry.loc + Point::from((geometry.size.w / 2, geometry.size.h / 2))
+}
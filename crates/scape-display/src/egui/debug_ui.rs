@@ -3,7 +3,7 @@ use crate::{
     workspace_window::WorkspaceWindow,
     State,
 };
-use egui::Context;
+use egui::{Context, RichText};
 use smithay::desktop::space::SpaceElement;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -24,6 +24,7 @@ struct Window {
 #[derive(Debug, PartialEq, Clone)]
 pub struct DebugState {
     spaces: Vec<Space>,
+    lua_errors: Vec<String>,
 }
 
 impl From<&State> for DebugState {
@@ -50,7 +51,10 @@ impl From<&State> for DebugState {
             })
             .collect();
 
-        DebugState { spaces }
+        DebugState {
+            spaces,
+            lua_errors: value.config.errors(),
+        }
     }
 }
 
@@ -90,6 +94,15 @@ impl DebugUi {
                         ));
                     }
                 }
+
+                if !debug_state.lua_errors.is_empty() {
+                    ui.separator();
+                    ui.heading("Lua errors");
+                    let color = ui.visuals().error_fg_color;
+                    for error in &debug_state.lua_errors {
+                        ui.label(RichText::new(error).monospace().color(color));
+                    }
+                }
             }
         });
     }
@@ -105,14 +118,15 @@ impl State {
     pub fn toggle_debug_ui(&mut self) {
         match self.debug_ui.take() {
             Some(window) => {
-                if let Some(space) = self.spaces.values_mut().next() {
-                    space.unmap_elem(&WorkspaceWindow::from(window));
+                let window = WorkspaceWindow::from(window);
+                for space in self.spaces.values_mut() {
+                    space.unmap_elem(&window);
                 }
             }
             None => {
                 let window = EguiWindow::new(DebugUi::default());
                 self.debug_ui = Some(window.clone());
-                if let Some(space_name) = self.spaces.keys().next().cloned() {
+                if let Ok(space_name) = self.space_under_pointer() {
                     self.place_window(
                         &space_name,
                         &WorkspaceWindow::from(window),
@@ -0,0 +1,353 @@
+use crate::{
+    egui_window::{EguiAppState, EguiWindow},
+    state::set_active_space,
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use egui::Context;
+use smithay::utils::{Logical, Point};
+use tracing::{error, info};
+
+const DEFAULT_SPACE: &str = "main";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum ModifierKey {
+    Super,
+    Alt,
+}
+
+impl ModifierKey {
+    fn lua_name(&self) -> &'static str {
+        match self {
+            ModifierKey::Super => "super",
+            ModifierKey::Alt => "alt",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum OutputArrangement {
+    Horizontal,
+    Vertical,
+    FirstOnly,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum ZonePreset {
+    Single,
+    Halves,
+    Thirds,
+    Centered,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FirstRunWizard {
+    keyboard_layout: String,
+    modifier: ModifierKey,
+    arrangement: OutputArrangement,
+    zone_preset: ZonePreset,
+    terminal: String,
+    output_names: Vec<String>,
+    finished: bool,
+}
+
+impl FirstRunWizard {
+    pub fn new(output_names: Vec<String>) -> Self {
+        FirstRunWizard {
+            keyboard_layout: String::from("us"),
+            modifier: ModifierKey::Super,
+            arrangement: OutputArrangement::Horizontal,
+            zone_preset: ZonePreset::Centered,
+            terminal: String::from("wezterm"),
+            output_names,
+            finished: false,
+        }
+    }
+
+    /// Returns the generated config once the user confirmed the wizard
+    pub fn finished_config(&self) -> Option<String> {
+        self.finished.then(|| self.generate_config())
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Welcome to scape");
+            ui.label("No config was found. Pick a few basics to generate an initial init.lua.");
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Keyboard layout");
+                ui.text_edit_singleline(&mut self.keyboard_layout);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Modifier key");
+                ui.radio_value(&mut self.modifier, ModifierKey::Super, "Super");
+                ui.radio_value(&mut self.modifier, ModifierKey::Alt, "Alt");
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Terminal");
+                ui.text_edit_singleline(&mut self.terminal);
+            });
+            ui.separator();
+
+            ui.label(format!("Outputs: {}", self.output_names.join(", ")));
+            egui::ComboBox::from_label("Output arrangement")
+                .selected_text(match self.arrangement {
+                    OutputArrangement::Horizontal => "Side by side",
+                    OutputArrangement::Vertical => "Stacked",
+                    OutputArrangement::FirstOnly => "First output only",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.arrangement,
+                        OutputArrangement::Horizontal,
+                        "Side by side",
+                    );
+                    ui.selectable_value(
+                        &mut self.arrangement,
+                        OutputArrangement::Vertical,
+                        "Stacked",
+                    );
+                    ui.selectable_value(
+                        &mut self.arrangement,
+                        OutputArrangement::FirstOnly,
+                        "First output only",
+                    );
+                });
+
+            egui::ComboBox::from_label("Zone preset")
+                .selected_text(match self.zone_preset {
+                    ZonePreset::Single => "Single",
+                    ZonePreset::Halves => "Halves",
+                    ZonePreset::Thirds => "Thirds",
+                    ZonePreset::Centered => "Centered",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.zone_preset, ZonePreset::Single, "Single");
+                    ui.selectable_value(&mut self.zone_preset, ZonePreset::Halves, "Halves");
+                    ui.selectable_value(&mut self.zone_preset, ZonePreset::Thirds, "Thirds");
+                    ui.selectable_value(&mut self.zone_preset, ZonePreset::Centered, "Centered");
+                });
+            ui.separator();
+
+            if ui.button("Write config").clicked() {
+                self.finished = true;
+            }
+        });
+    }
+
+    /// Generates the init.lua. User input is written with Rust's debug quoting, its escapes are
+    /// valid in Lua string literals as well.
+    fn generate_config(&self) -> String {
+        let modifier = self.modifier.lua_name();
+        let position_outputs = match self.arrangement {
+            OutputArrangement::Horizontal => {
+                "\tlocal x = 0\n\
+                 \tfor _, output in ipairs(outputs) do\n\
+                 \t\toutput.x = x\n\
+                 \t\toutput.y = 0\n\
+                 \t\tx = x + output.width\n\
+                 \tend\n"
+            }
+            OutputArrangement::Vertical => {
+                "\tlocal y = 0\n\
+                 \tfor _, output in ipairs(outputs) do\n\
+                 \t\toutput.x = 0\n\
+                 \t\toutput.y = y\n\
+                 \t\ty = y + output.height\n\
+                 \tend\n"
+            }
+            OutputArrangement::FirstOnly => {
+                "\toutputs = { outputs[1] }\n\
+                 \toutputs[1].x = 0\n\
+                 \toutputs[1].y = 0\n"
+            }
+        };
+        let zones = match self.zone_preset {
+            ZonePreset::Single => vec![("full", "0", "w", true)],
+            ZonePreset::Halves => vec![
+                ("left", "0", "w / 2", true),
+                ("right", "w / 2", "w / 2", false),
+            ],
+            ZonePreset::Thirds => vec![
+                ("left", "0", "w / 3", false),
+                ("mid", "w / 3", "w / 3", true),
+                ("right", "w / 3 * 2", "w / 3", false),
+            ],
+            ZonePreset::Centered => vec![
+                ("left", "0", "w / 4", false),
+                ("mid", "w / 4", "w / 2", true),
+                ("right", "w / 4 * 3", "w / 4", false),
+            ],
+        };
+        let zones_lua = zones
+            .iter()
+            .map(|(name, x, width, default)| {
+                format!(
+                    "\t\t{{ name = \"{name}\", x = {x}, y = 0, width = {width}, height = h, default = {default} }},\n"
+                )
+            })
+            .collect::<String>();
+        let zone_keys = match (zones.first(), zones.last()) {
+            (Some((left, ..)), Some((right, ..))) if zones.len() > 1 => {
+                [("Left", left), ("Right", right)]
+                    .iter()
+                    .map(|(key, zone)| {
+                        format!(
+                            "scape.map_key({{\n\
+                             \tkey = \"{key}\",\n\
+                             \tmods = \"{modifier}\",\n\
+                             \tcallback = function()\n\
+                             \t\tscape.move_to_zone(\"{zone}\")\n\
+                             \tend,\n\
+                             }})\n"
+                        )
+                    })
+                    .collect::<String>()
+            }
+            _ => String::new(),
+        };
+
+        format!(
+            "-- Generated by the scape first-run wizard.\n\
+             -- Edit this file freely, it is reloaded whenever it changes.\n\
+             scape = require(\"scape\")\n\
+             \n\
+             local terminal = {terminal:?}\n\
+             \n\
+             -- Keyboard layout as understood by xkb, e.g. \"us\" or \"de\"\n\
+             scape.set_keyboard_layout({layout:?})\n\
+             \n\
+             scape.on_startup(function()\n\
+             \tscape.spawn({{ command = terminal }})\n\
+             end)\n\
+             \n\
+             -- Called whenever a monitor is plugged in or removed\n\
+             scape.on_connector_change(function(outputs)\n\
+             {position_outputs}\
+             \tfor i, output in ipairs(outputs) do\n\
+             \t\toutput.default = i == 1\n\
+             \t\toutput.disabled = false\n\
+             \t\toutput.scale = 1\n\
+             \tend\n\
+             \n\
+             \tscape.set_layout({{\n\
+             \t\t{space} = outputs,\n\
+             \t}})\n\
+             \n\
+             \t-- Zones are the areas windows get placed into\n\
+             \tlocal w = outputs[1].width\n\
+             \tlocal h = outputs[1].height\n\
+             \tscape.set_zones({{\n\
+             {zones_lua}\
+             \t}})\n\
+             end)\n\
+             \n\
+             -- Key bindings, mods are separated by \"|\", e.g. \"shift|{modifier}\"\n\
+             scape.map_key({{\n\
+             \tkey = \"Return\",\n\
+             \tmods = \"{modifier}\",\n\
+             \tcallback = function()\n\
+             \t\tscape.spawn({{ command = terminal }})\n\
+             \tend,\n\
+             }})\n\
+             scape.map_key({{\n\
+             \tkey = \"q\",\n\
+             \tmods = \"shift|{modifier}\",\n\
+             \tcallback = function()\n\
+             \t\tscape.close()\n\
+             \tend,\n\
+             }})\n\
+             {zone_keys}",
+            terminal = self.terminal,
+            layout = self.keyboard_layout,
+            space = DEFAULT_SPACE,
+        )
+    }
+}
+
+impl From<FirstRunWizard> for EguiAppState {
+    fn from(first_run_wizard: FirstRunWizard) -> Self {
+        EguiAppState::FirstRunWizard(first_run_wizard)
+    }
+}
+
+impl State {
+    pub fn show_first_run_wizard(&mut self) {
+        // The wizard has to be displayed somewhere before the user configured the outputs
+        self.apply_fallback_layout();
+
+        if self.first_run_wizard.is_some() {
+            return;
+        }
+
+        info!("No config found, showing first-run wizard");
+        let window = EguiWindow::new(FirstRunWizard::new(self.outputs.keys().cloned().collect()));
+        self.first_run_wizard = Some(window.clone());
+        self.place_window(
+            DEFAULT_SPACE,
+            &WorkspaceWindow::from(window),
+            true,
+            None,
+            true,
+        );
+    }
+
+    /// Lays out all outputs next to each other, used while no config sets up the outputs
+    pub fn apply_fallback_layout(&mut self) {
+        let space = self.spaces.entry(DEFAULT_SPACE.to_string()).or_default();
+        let mut x = 0;
+        for (index, output) in self.outputs.values().enumerate() {
+            let position: Point<i32, Logical> = (x, 0).into();
+            output.change_current_state(None, None, None, Some(position));
+            space.map_output(output, position);
+            if index == 0 {
+                set_active_space(output, DEFAULT_SPACE);
+            }
+            x += output
+                .current_mode()
+                .map(|mode| mode.size.w)
+                .unwrap_or_default();
+        }
+        self.start_outputs();
+    }
+
+    pub fn finish_first_run_wizard(&mut self, config: String) {
+        let Some(window) = self.first_run_wizard.take() else {
+            return;
+        };
+        if let Some(space) = self.spaces.get_mut(DEFAULT_SPACE) {
+            space.unmap_elem(&WorkspaceWindow::from(window));
+        }
+
+        if let Err(err) = self.write_generated_config(&config) {
+            error!(?err, "Unable to apply the generated config");
+            return;
+        }
+
+        // The config registers its callbacks in idle callbacks, run them afterwards
+        self.loop_handle.insert_idle(|state| {
+            state.on_connector_change();
+            state.on_startup();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_input_is_quoted() {
+        let mut wizard = FirstRunWizard::new(vec![]);
+        wizard.terminal = String::from("foot\" os.execute(\"rm\") --\\");
+        wizard.keyboard_layout = String::from("de\nx");
+
+        let config = wizard.generate_config();
+
+        assert!(config.contains(r#"local terminal = "foot\" os.execute(\"rm\") --\\""#));
+        assert!(config.contains(r#"scape.set_keyboard_layout("de\nx")"#));
+    }
+}
@@ -41,6 +41,7 @@ use tracing::error;
 use xkbcommon::xkb::Keycode;
 
 pub mod debug_ui;
+pub mod first_run_wizard;
 mod input;
 
 pub use self::input::{convert_button, convert_key, convert_modifiers};
@@ -418,7 +419,7 @@ impl EguiState {
             let physical_area = inner.next_area.to_physical(int_scale);
             {
                 let mut frame = renderer.render(physical_area.size, Transform::Normal)?;
-                frame.clear(Color32F::BLACK, &[physical_area])?;
+                frame.clear(Color32F::TRANSPARENT, &[physical_area])?;
                 painter.paint_and_update_textures(
                     [physical_area.size.w as u32, physical_area.size.h as u32],
                     int_scale as f32,
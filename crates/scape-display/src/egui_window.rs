@@ -3,12 +3,20 @@ use std::sync::{Arc, Mutex};
 use crate::{
     egui::{
         debug_ui::{DebugState, DebugUi},
+        first_run_wizard::FirstRunWizard,
         EguiState,
     },
+    key_chord::ChordHint,
+    keystroke_visualizer::KeystrokeVisualizer,
+    modifier_indicator::ModifierIndicator,
+    output_identifier::OutputIdentifier,
     render::AsGlowRenderer,
+    snap::{SnapAssist, SnapAssistSelection},
+    theme::{Theme, ThemeVariant},
+    window_placeholder::WindowPlaceholder,
     State,
 };
-use egui::Context;
+use egui::{Context, TextStyle, Vec2, Visuals};
 use smithay::{
     backend::{
         input::KeyState,
@@ -36,18 +44,53 @@ use tracing::error;
 #[derive(PartialEq, Debug, Clone)]
 pub enum EguiAppState {
     DebugUi(DebugUi),
+    FirstRunWizard(FirstRunWizard),
+    SnapAssist(SnapAssist),
+    KeystrokeVisualizer(KeystrokeVisualizer),
+    WindowPlaceholder(WindowPlaceholder),
+    OutputIdentifier(OutputIdentifier),
+    ModifierIndicator(ModifierIndicator),
+    ChordHint(ChordHint),
 }
 
 impl EguiAppState {
     fn udpate_ui(&mut self, ctx: &Context) {
         match self {
             EguiAppState::DebugUi(debug_ui) => debug_ui.show(ctx),
+            EguiAppState::FirstRunWizard(first_run_wizard) => first_run_wizard.show(ctx),
+            EguiAppState::SnapAssist(snap_assist) => snap_assist.show(ctx),
+            EguiAppState::KeystrokeVisualizer(keystroke_visualizer) => {
+                keystroke_visualizer.show(ctx)
+            }
+            EguiAppState::WindowPlaceholder(window_placeholder) => window_placeholder.show(ctx),
+            EguiAppState::OutputIdentifier(output_identifier) => output_identifier.show(ctx),
+            EguiAppState::ModifierIndicator(modifier_indicator) => modifier_indicator.show(ctx),
+            EguiAppState::ChordHint(chord_hint) => chord_hint.show(ctx),
         }
     }
 
+    /// Overlays which only display something let the input pass through to the windows below
+    fn is_interactive(&self) -> bool {
+        !matches!(
+            self,
+            EguiAppState::KeystrokeVisualizer(_)
+                | EguiAppState::WindowPlaceholder(_)
+                | EguiAppState::OutputIdentifier(_)
+                | EguiAppState::ModifierIndicator(_)
+                | EguiAppState::ChordHint(_)
+        )
+    }
+
     pub fn app_id(&self) -> String {
         match self {
             EguiAppState::DebugUi(_) => "scape::debug_ui".to_string(),
+            EguiAppState::FirstRunWizard(_) => "scape::first_run_wizard".to_string(),
+            EguiAppState::SnapAssist(_) => "scape::snap_assist".to_string(),
+            EguiAppState::KeystrokeVisualizer(_) => "scape::keystroke_visualizer".to_string(),
+            EguiAppState::WindowPlaceholder(_) => "scape::window_placeholder".to_string(),
+            EguiAppState::OutputIdentifier(_) => "scape::output_identifier".to_string(),
+            EguiAppState::ModifierIndicator(_) => "scape::modifier_indicator".to_string(),
+            EguiAppState::ChordHint(_) => "scape::chord_hint".to_string(),
         }
     }
 }
@@ -71,6 +114,48 @@ impl EguiWindow {
     pub fn update_debug_ui(&mut self, debug_state: DebugState) -> bool {
         match &mut *self.app_state.lock().unwrap() {
             EguiAppState::DebugUi(debug_ui) => debug_ui.update(debug_state),
+            _ => false,
+        }
+    }
+
+    pub fn first_run_config(&self) -> Option<String> {
+        match &*self.app_state.lock().unwrap() {
+            EguiAppState::FirstRunWizard(first_run_wizard) => first_run_wizard.finished_config(),
+            _ => None,
+        }
+    }
+
+    pub fn snap_assist_selection(&self) -> Option<SnapAssistSelection> {
+        match &*self.app_state.lock().unwrap() {
+            EguiAppState::SnapAssist(snap_assist) => snap_assist.selection(),
+            _ => None,
+        }
+    }
+
+    pub fn with_keystroke_visualizer<T>(
+        &self,
+        f: impl FnOnce(&mut KeystrokeVisualizer) -> T,
+    ) -> Option<T> {
+        match &mut *self.app_state.lock().unwrap() {
+            EguiAppState::KeystrokeVisualizer(visualizer) => Some(f(visualizer)),
+            _ => None,
+        }
+    }
+
+    pub fn with_modifier_indicator<T>(
+        &self,
+        f: impl FnOnce(&mut ModifierIndicator) -> T,
+    ) -> Option<T> {
+        match &mut *self.app_state.lock().unwrap() {
+            EguiAppState::ModifierIndicator(indicator) => Some(f(indicator)),
+            _ => None,
+        }
+    }
+
+    pub fn with_chord_hint<T>(&self, f: impl FnOnce(&mut ChordHint) -> T) -> Option<T> {
+        match &mut *self.app_state.lock().unwrap() {
+            EguiAppState::ChordHint(hint) => Some(f(hint)),
+            _ => None,
         }
     }
 
@@ -81,6 +166,25 @@ impl EguiWindow {
     pub fn position(&self, size: Size<i32, Logical>) {
         self.egui_state.set_size(size);
     }
+
+    pub fn set_theme(&self, theme: &Theme) {
+        let ctx = self.egui_state.context();
+        ctx.set_visuals(match theme.variant {
+            ThemeVariant::Light => Visuals::light(),
+            ThemeVariant::Dark => Visuals::dark(),
+        });
+        ctx.style_mut(|style| {
+            for (text_style, font_id) in style.text_styles.iter_mut() {
+                font_id.size = match text_style {
+                    TextStyle::Heading => theme.font_size * 1.4,
+                    TextStyle::Small => theme.font_size * 0.7,
+                    _ => theme.font_size,
+                };
+            }
+            let spacing = theme.spacing as f32;
+            style.spacing.item_spacing = Vec2::new(spacing, spacing / 2.0);
+        });
+    }
 }
 
 impl PartialEq for EguiWindow {
@@ -104,7 +208,7 @@ impl SpaceElement for EguiWindow {
         &self,
         point: &smithay::utils::Point<f64, smithay::utils::Logical>,
     ) -> bool {
-        self.egui_state.is_in_input_region(point)
+        self.app_state.lock().unwrap().is_interactive() && self.egui_state.is_in_input_region(point)
     }
 
     fn set_activate(&self, activated: bool) {
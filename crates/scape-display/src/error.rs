@@ -0,0 +1,46 @@
+use smithay::reexports::x11rb::errors::ConnectionError;
+
+/// Errors of compositor operations which should be logged instead of crashing the session
+#[derive(Debug, thiserror::Error)]
+pub enum ScapeError {
+    #[error("The seat is not initialized yet")]
+    NoSeat,
+    #[error("The seat has no keyboard")]
+    NoKeyboard,
+    #[error("The seat has no pointer")]
+    NoPointer,
+    #[error("No space exists yet")]
+    NoSpace,
+    #[error("Space {0} does not exist")]
+    SpaceNotFound(String),
+    #[error("Window {0} does not exist")]
+    WindowNotFound(u64),
+    #[error("Window is not mapped in space {0}")]
+    WindowNotMapped(String),
+    #[error("Zone {0} does not exist")]
+    ZoneNotFound(String),
+    #[error("Zone {0} was not split from another zone")]
+    ZoneNotSplit(String),
+    #[error("Zone layout {0} does not exist")]
+    ZoneLayoutNotFound(String),
+    #[error("Output {0} has no zone layouts")]
+    NoZoneLayouts(String),
+    #[error("No command is registered as {0}")]
+    CommandNotFound(String),
+    #[error("No layout was saved as {0}")]
+    LayoutNotFound(String),
+    #[error("Output {0} does not exist")]
+    OutputNotFound(String),
+    #[error("No output is mapped in space {0}")]
+    NoOutput(String),
+    #[error("No output exists yet")]
+    NoOutputs,
+    #[error("No pointer grab is active")]
+    NoGrab,
+    #[error("X11 request failed: {0}")]
+    X11(#[from] ConnectionError),
+    #[error("Lua callback failed: {0}")]
+    Lua(#[from] mlua::Error),
+}
+
+pub type Result<T, E = ScapeError> = std::result::Result<T, E>;
@@ -1,4 +1,9 @@
-use std::borrow::Cow;
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use std::{borrow::Cow, time::Duration};
+use tracing::warn;
 
 pub use smithay::{
     backend::input::KeyState,
@@ -13,19 +18,22 @@ pub use smithay::{
     wayland::seat::WaylandFocus,
 };
 use smithay::{
-    desktop::{Window, WindowSurface},
+    desktop::{layer_map_for_output, Window, WindowSurface},
     input::pointer::{
         GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent, GesturePinchEndEvent,
         GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
         GestureSwipeUpdateEvent,
     },
-    wayland::session_lock::LockSurface,
+    utils::SERIAL_COUNTER,
+    wayland::{session_lock::LockSurface, shell::wlr_layer::Layer as WlrLayer},
 };
 use smithay::{input::touch::TouchTarget, xwayland::X11Surface};
 
 use crate::{
     application_window::{ApplicationWindow, SSD},
     egui_window::EguiWindow,
+    protocols::input_timestamps::InputTimestampsKind,
+    state::active_space,
     workspace_window::WorkspaceWindow,
     State,
 };
@@ -87,6 +95,7 @@ impl PointerTarget<State> for PointerFocusTarget {
     }
 
     fn motion(&self, seat: &Seat<State>, data: &mut State, event: &MotionEvent) {
+        data.send_input_timestamp(InputTimestampsKind::Pointer, self.wl_surface().as_deref());
         match self {
             PointerFocusTarget::WlSurface(w) => PointerTarget::motion(w, seat, data, event),
             PointerFocusTarget::X11Surface(w) => PointerTarget::motion(w, seat, data, event),
@@ -109,6 +118,7 @@ impl PointerTarget<State> for PointerFocusTarget {
     }
 
     fn button(&self, seat: &Seat<State>, data: &mut State, event: &ButtonEvent) {
+        data.send_input_timestamp(InputTimestampsKind::Pointer, self.wl_surface().as_deref());
         match self {
             PointerFocusTarget::WlSurface(w) => PointerTarget::button(w, seat, data, event),
             PointerFocusTarget::X11Surface(w) => PointerTarget::button(w, seat, data, event),
@@ -118,6 +128,7 @@ impl PointerTarget<State> for PointerFocusTarget {
     }
 
     fn axis(&self, seat: &Seat<State>, data: &mut State, frame: AxisFrame) {
+        data.send_input_timestamp(InputTimestampsKind::Pointer, self.wl_surface().as_deref());
         match self {
             PointerFocusTarget::WlSurface(w) => PointerTarget::axis(w, seat, data, frame),
             PointerFocusTarget::X11Surface(w) => PointerTarget::axis(w, seat, data, frame),
@@ -346,6 +357,7 @@ impl KeyboardTarget<State> for KeyboardFocusTarget {
         serial: Serial,
         time: u32,
     ) {
+        data.send_input_timestamp(InputTimestampsKind::Keyboard, self.wl_surface().as_deref());
         match self {
             KeyboardFocusTarget::Window(w) => match w.underlying_surface() {
                 WindowSurface::Wayland(w) => {
@@ -410,6 +422,7 @@ impl TouchTarget<State> for PointerFocusTarget {
         event: &smithay::input::touch::DownEvent,
         seq: Serial,
     ) {
+        data.send_input_timestamp(InputTimestampsKind::Touch, self.wl_surface().as_deref());
         match self {
             PointerFocusTarget::WlSurface(w) => TouchTarget::down(w, seat, data, event, seq),
             PointerFocusTarget::X11Surface(w) => TouchTarget::down(w, seat, data, event, seq),
@@ -426,6 +439,7 @@ impl TouchTarget<State> for PointerFocusTarget {
         event: &smithay::input::touch::UpEvent,
         seq: Serial,
     ) {
+        data.send_input_timestamp(InputTimestampsKind::Touch, self.wl_surface().as_deref());
         match self {
             PointerFocusTarget::WlSurface(w) => TouchTarget::up(w, seat, data, event, seq),
             PointerFocusTarget::X11Surface(w) => TouchTarget::up(w, seat, data, event, seq),
@@ -442,6 +456,7 @@ impl TouchTarget<State> for PointerFocusTarget {
         event: &smithay::input::touch::MotionEvent,
         seq: Serial,
     ) {
+        data.send_input_timestamp(InputTimestampsKind::Touch, self.wl_surface().as_deref());
         match self {
             PointerFocusTarget::WlSurface(w) => TouchTarget::motion(w, seat, data, event, seq),
             PointerFocusTarget::X11Surface(w) => TouchTarget::motion(w, seat, data, event, seq),
@@ -634,3 +649,148 @@ impl From<KeyboardFocusTarget> for PointerFocusTarget {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FocusConfig {
+    /// Focus the window under the pointer once the pointer rests on it
+    pub follows_mouse: bool,
+    /// Time the pointer has to stay on a window until it is focused
+    pub delay: Duration,
+    /// Raise windows which are focused by the pointer
+    pub raise_on_focus: bool,
+}
+
+impl Default for FocusConfig {
+    fn default() -> Self {
+        FocusConfig {
+            follows_mouse: false,
+            delay: Duration::from_millis(100),
+            raise_on_focus: false,
+        }
+    }
+}
+
+/// Window which is focused once the pointer rested on it for the focus delay
+#[derive(Debug)]
+pub struct PendingFocus {
+    window: WorkspaceWindow,
+    timer: RegistrationToken,
+}
+
+impl State {
+    pub fn set_focus_config(&mut self, config: FocusConfig) {
+        self.focus_config = config;
+        self.cancel_pending_focus();
+    }
+
+    /// Moves the keyboard focus to the window under the pointer if focus follows the mouse.
+    /// Popups, layer surfaces and overlays keep the focus they got.
+    pub fn focus_follows_pointer(&mut self) {
+        if !self.focus_config.follows_mouse || self.session_lock.is_some() {
+            return;
+        }
+        let (Ok(pointer), Ok(keyboard)) = (self.pointer(), self.keyboard()) else {
+            return;
+        };
+        // A pressed button grabs the pointer, e.g. while selecting text into another window
+        if pointer.is_grabbed() || keyboard.is_grabbed() {
+            self.cancel_pending_focus();
+            return;
+        }
+        let focus = keyboard.current_focus();
+        if !matches!(focus, None | Some(KeyboardFocusTarget::Window(_))) {
+            self.cancel_pending_focus();
+            return;
+        }
+        let Some((window, space_name)) = self.window_under_pointer() else {
+            self.cancel_pending_focus();
+            return;
+        };
+        if focus
+            .and_then(|focus| WorkspaceWindow::try_from(focus).ok())
+            .as_ref()
+            == Some(&window)
+        {
+            self.cancel_pending_focus();
+            return;
+        }
+        if self
+            .pending_focus
+            .as_ref()
+            .is_some_and(|pending_focus| pending_focus.window == window)
+        {
+            return;
+        }
+
+        self.cancel_pending_focus();
+        if self.focus_config.delay.is_zero() {
+            self.focus_pointer_window(window, &space_name);
+            return;
+        }
+        let pending_window = window.clone();
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(self.focus_config.delay),
+            move |_, _, state| {
+                state.pending_focus = None;
+                // The window may have moved away from the resting pointer in the meantime
+                if let Some((window, space_name)) = state.window_under_pointer() {
+                    if window == pending_window {
+                        state.focus_pointer_window(window, &space_name);
+                    }
+                }
+                TimeoutAction::Drop
+            },
+        );
+        match res {
+            Ok(timer) => self.pending_focus = Some(PendingFocus { window, timer }),
+            Err(err) => warn!(?err, "Unable to start focus delay"),
+        }
+    }
+
+    pub fn cancel_pending_focus(&mut self) {
+        if let Some(pending_focus) = self.pending_focus.take() {
+            self.loop_handle.remove(pending_focus.timer);
+        }
+    }
+
+    /// Application window under the pointer with its space, unless a layer surface above the
+    /// windows is under it
+    fn window_under_pointer(&self) -> Option<(WorkspaceWindow, String)> {
+        let pointer_location = self.pointer_location();
+        let output = self.output_under_pointer()?;
+        let space_name = active_space(&output)?;
+        let space = self.spaces.get(&space_name)?;
+
+        let output_location = space.output_geometry(&output)?.loc.to_f64();
+        let layers = layer_map_for_output(&output);
+        let layer_location = pointer_location - output_location;
+        if layers
+            .layer_under(WlrLayer::Overlay, layer_location)
+            .is_some()
+            || layers.layer_under(WlrLayer::Top, layer_location).is_some()
+        {
+            return None;
+        }
+
+        let (window, _) = space.element_under(pointer_location)?;
+        if !matches!(window, WorkspaceWindow::ApplicationWindow(_))
+            || window
+                .x11_surface()
+                .is_some_and(|surface| surface.is_override_redirect())
+        {
+            return None;
+        }
+        Some((window.clone(), space_name))
+    }
+
+    fn focus_pointer_window(&mut self, window: WorkspaceWindow, space_name: &str) {
+        if self.focus_config.raise_on_focus {
+            self.focus_window(window, space_name);
+            return;
+        }
+        let Ok(keyboard) = self.keyboard() else {
+            return;
+        };
+        keyboard.set_focus(self, Some(window.into()), SERIAL_COUNTER.next_serial());
+    }
+}
@@ -0,0 +1,111 @@
+//! Windows of every space in the order they were focused. Jumping back to the previous window and
+//! alt-tab follow this order instead of the stacking order, which changes with every raise.
+
+use crate::{workspace_window::WorkspaceWindow, State};
+use tracing::info;
+
+/// Ids of the windows of a space, the most recently focused one first
+#[derive(Debug, Default)]
+pub struct FocusHistory(Vec<u64>);
+
+impl FocusHistory {
+    fn push(&mut self, window_id: u64) {
+        self.0.retain(|id| *id != window_id);
+        self.0.insert(0, window_id);
+    }
+}
+
+impl State {
+    /// Moves the window to the front of the history of its space. Windows chosen with alt-tab are
+    /// only recorded once alt is released, so that tabbing further keeps the order.
+    pub fn record_focus(&mut self, window: &WorkspaceWindow, space_name: &str) {
+        let Some(window_id) = window.id() else {
+            return;
+        };
+        if self.tab_index > 0 {
+            return;
+        }
+        let windows = self.space_window_ids(space_name);
+        let history = self
+            .focus_history
+            .entry(space_name.to_string())
+            .or_default();
+        // Closed windows and windows which moved to another space are forgotten
+        history.0.retain(|id| windows.contains(id));
+        history.push(window_id);
+    }
+
+    /// Records the focused window, e.g. once alt-tab ended
+    pub fn record_focused_window(&mut self) {
+        let Some(window) = self.focused_window() else {
+            return;
+        };
+        if let Some(space_name) = self.space_of_window(&window) {
+            self.record_focus(&window, &space_name);
+        }
+    }
+
+    /// Windows of the space, the most recently focused first, followed by windows which were
+    /// never focused from the top of the stack down
+    pub fn windows_by_focus(&self, space_name: &str) -> Vec<WorkspaceWindow> {
+        let Some(space) = self.spaces.get(space_name) else {
+            return Vec::new();
+        };
+        let mut windows = space
+            .elements()
+            .rev()
+            .filter(|window| window.id().is_some())
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(history) = self.focus_history.get(space_name) {
+            // Stable, so windows missing in the history keep their stacking order
+            windows.sort_by_key(|window| {
+                let id = window.id().unwrap_or_default();
+                history
+                    .0
+                    .iter()
+                    .position(|focused| *focused == id)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        windows
+    }
+
+    /// Focuses the window which was focused before the focused one on the focused space
+    pub fn focus_previous(&mut self) {
+        let Some(space_name) = self.focused_space() else {
+            return;
+        };
+        let focused = self.focused_window();
+        let Some(window) = self
+            .windows_by_focus(&space_name)
+            .into_iter()
+            .find(|window| Some(window) != focused.as_ref())
+        else {
+            info!(space_name, "No previous window to focus");
+            return;
+        };
+        info!(app_id = window.app_id(), "Focusing previous window");
+        self.focus_window(window, &space_name);
+    }
+
+    /// Focuses the window `index` positions back in the history of the focused space
+    pub fn focus_tab(&mut self, index: usize) {
+        let Some(space_name) = self.focused_space() else {
+            return;
+        };
+        let windows = self.windows_by_focus(&space_name);
+        if windows.is_empty() {
+            return;
+        }
+        let window = windows[index % windows.len()].clone();
+        self.focus_window(window, &space_name);
+    }
+
+    fn space_window_ids(&self, space_name: &str) -> Vec<u64> {
+        self.spaces
+            .get(space_name)
+            .map(|space| space.elements().filter_map(WorkspaceWindow::id).collect())
+            .unwrap_or_default()
+    }
+}
@@ -1,3 +1,4 @@
+use crate::snap::DropTarget;
 use crate::workspace_window::WorkspaceWindow;
 use crate::{focus::PointerFocusTarget, state::State};
 use smithay::input::touch::{
@@ -22,6 +23,8 @@ pub struct PointerMoveSurfaceGrab {
     pub window: WorkspaceWindow,
     pub space_name: String,
     pub initial_window_location: Point<i32, Logical>,
+    /// Where the window goes if it is dropped at the current pointer location
+    pub drop_target: Option<DropTarget>,
 }
 
 impl PointerGrab<State> for PointerMoveSurfaceGrab {
@@ -38,11 +41,11 @@ impl PointerGrab<State> for PointerMoveSurfaceGrab {
         let delta = event.location - self.start_data.location;
         let new_location = self.initial_window_location.to_f64() + delta;
 
-        data.spaces.get_mut(&self.space_name).unwrap().map_element(
-            self.window.clone(),
-            new_location.to_i32_round(),
-            true,
-        );
+        let space = data.spaces.get_mut(&self.space_name).unwrap();
+        space.map_element(self.window.clone(), new_location.to_i32_round(), true);
+
+        self.drop_target = data.drop_target(&self.space_name, event.location);
+        data.preview_drop_target(self.drop_target.as_ref());
     }
 
     fn relative_motion(
@@ -63,6 +66,9 @@ impl PointerGrab<State> for PointerMoveSurfaceGrab {
     ) {
         handle.button(data, event);
         if handle.current_pressed().is_empty() {
+            if let Some(target) = self.drop_target.take() {
+                data.drop_window(&self.space_name, &self.window, target);
+            }
             // No more buttons are pressed, release the grab.
             handle.unset_grab(self, data, event.serial, event.time, true);
         }
@@ -157,7 +163,9 @@ impl PointerGrab<State> for PointerMoveSurfaceGrab {
         handle.gesture_hold_end(data, event);
     }
 
-    fn unset(&mut self, _data: &mut State) {}
+    fn unset(&mut self, data: &mut State) {
+        data.set_snap_preview(None);
+    }
 }
 
 pub struct TouchMoveSurfaceGrab {
@@ -212,12 +220,13 @@ impl TouchGrab<State> for TouchMoveSurfaceGrab {
 
         let delta = event.location - self.start_data.location;
         let new_location = self.initial_window_location.to_f64() + delta;
-        // TODO: find out from which space the window is
-        data.spaces.values_mut().next().unwrap().map_element(
-            self.window.clone(),
-            new_location.to_i32_round(),
-            true,
-        );
+        if let Some(space) = data
+            .spaces
+            .values_mut()
+            .find(|space| space.elements().any(|window| *window == self.window))
+        {
+            space.map_element(self.window.clone(), new_location.to_i32_round(), true);
+        }
     }
 
     fn frame(
@@ -0,0 +1,206 @@
+use crate::{action::Action, State};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Program which locks the session if the config does not set one
+const DEFAULT_LOCK_COMMAND: &str = "swaylock";
+
+/// Durations without input after which the session is locked, the outputs are turned off and the
+/// system is suspended. Unset stages are skipped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdleTimeouts {
+    pub lock: Option<Duration>,
+    pub screen_off: Option<Duration>,
+    pub suspend: Option<Duration>,
+    /// Command and arguments of the program locking the session
+    pub lock_command: Option<(String, Vec<String>)>,
+}
+
+#[derive(Debug)]
+pub struct IdleState {
+    timeouts: IdleTimeouts,
+    last_activity: Instant,
+    locked: bool,
+    screen_off: bool,
+    suspended: bool,
+    timer: Option<RegistrationToken>,
+}
+
+impl Default for IdleState {
+    fn default() -> Self {
+        IdleState {
+            timeouts: IdleTimeouts::default(),
+            last_activity: Instant::now(),
+            locked: false,
+            screen_off: false,
+            suspended: false,
+            timer: None,
+        }
+    }
+}
+
+impl IdleState {
+    /// Whether the outputs are turned off, they must not be rendered then
+    pub fn screen_off(&self) -> bool {
+        self.screen_off
+    }
+
+    /// Time until the next stage which did not run yet, `None` if all ran
+    fn next_stage_in(&self, now: Instant) -> Option<Duration> {
+        let idle = now - self.last_activity;
+        [
+            (self.timeouts.lock, self.locked),
+            (self.timeouts.screen_off, self.screen_off),
+            (self.timeouts.suspend, self.suspended),
+        ]
+        .into_iter()
+        .filter(|(_, done)| !done)
+        .filter_map(|(timeout, _)| timeout)
+        .map(|timeout| timeout.saturating_sub(idle))
+        .min()
+    }
+}
+
+impl State {
+    pub fn set_idle_timeouts(&mut self, timeouts: IdleTimeouts) {
+        info!(?timeouts, "Setting idle timeouts");
+        self.idle.timeouts = timeouts;
+        self.idle.last_activity = Instant::now();
+        self.idle.locked = false;
+        self.idle.suspended = false;
+        self.set_screen_off(false);
+        if let Some(timer) = self.idle.timer.take() {
+            self.loop_handle.remove(timer);
+        }
+        self.start_idle_timer();
+    }
+
+    /// Restarts the idle stages, called for every input event
+    pub fn notify_activity(&mut self) {
+        self.idle.last_activity = Instant::now();
+        self.idle.locked = false;
+        self.idle.suspended = false;
+        if self.idle.screen_off {
+            info!("Activity after idle, turning outputs on");
+            self.set_screen_off(false);
+        }
+        if self.idle.timer.is_none() {
+            self.start_idle_timer();
+        }
+    }
+
+    fn start_idle_timer(&mut self) {
+        let Some(delay) = self.idle.next_stage_in(Instant::now()) else {
+            return;
+        };
+        let res = self
+            .loop_handle
+            .insert_source(Timer::from_duration(delay), |_, _, state| {
+                state.run_idle_stages();
+                match state.idle.next_stage_in(Instant::now()) {
+                    Some(delay) => TimeoutAction::ToDuration(delay),
+                    None => {
+                        state.idle.timer = None;
+                        TimeoutAction::Drop
+                    }
+                }
+            });
+        match res {
+            Ok(token) => self.idle.timer = Some(token),
+            Err(err) => warn!(?err, "Unable to start idle timer"),
+        }
+    }
+
+    fn run_idle_stages(&mut self) {
+        if self.kiosk.is_some() {
+            // Kiosk applications are meant to be visible all the time
+            self.idle.last_activity = Instant::now();
+            return;
+        }
+        let idle = self.idle.last_activity.elapsed();
+        let reached = |timeout: Option<Duration>| timeout.is_some_and(|timeout| idle >= timeout);
+
+        if !self.idle.locked && reached(self.idle.timeouts.lock) {
+            self.idle.locked = true;
+            if self.session_lock.is_none() {
+                let (command, args) = self
+                    .idle
+                    .timeouts
+                    .lock_command
+                    .clone()
+                    .unwrap_or_else(|| (DEFAULT_LOCK_COMMAND.to_string(), Vec::new()));
+                info!(command, "Locking idle session");
+                self.execute(Action::Spawn {
+                    command,
+                    args,
+                    zone: None,
+                    env: Vec::new(),
+                    cwd: None,
+                });
+            }
+        }
+        if !self.idle.screen_off && reached(self.idle.timeouts.screen_off) {
+            info!("Turning outputs off after idle");
+            self.set_screen_off(true);
+        }
+        if !self.idle.suspended && reached(self.idle.timeouts.suspend) {
+            self.idle.suspended = true;
+            info!("Suspending after idle");
+            suspend();
+        }
+    }
+
+    fn set_screen_off(&mut self, screen_off: bool) {
+        if self.idle.screen_off == screen_off {
+            return;
+        }
+        self.idle.screen_off = screen_off;
+        let outputs = self
+            .outputs
+            .iter()
+            .filter(|(name, _)| !self.disabled_outputs.contains_key(*name))
+            .map(|(_, output)| output.clone())
+            .collect::<Vec<_>>();
+        for output in outputs {
+            if let Err(err) = self.backend_data.set_output_power(&output, !screen_off) {
+                warn!(
+                    ?err,
+                    output = output.name(),
+                    "Unable to change output power"
+                );
+            }
+        }
+        // No frames are rendered while the outputs are off
+        self.update_window_suspension();
+    }
+}
+
+/// Asks logind to suspend the system, without blocking the event loop
+fn suspend() {
+    let res = thread::Builder::new()
+        .name("suspend".to_string())
+        .spawn(|| {
+            let res = zbus::blocking::Connection::system().and_then(|connection| {
+                connection.call_method(
+                    Some("org.freedesktop.login1"),
+                    "/org/freedesktop/login1",
+                    Some("org.freedesktop.login1.Manager"),
+                    "Suspend",
+                    &(false,),
+                )
+            });
+            if let Err(err) = res {
+                warn!(%err, "Unable to suspend");
+            }
+        });
+    if let Err(err) = res {
+        warn!(?err, "Unable to start suspend thread");
+    }
+}
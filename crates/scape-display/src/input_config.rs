@@ -0,0 +1,79 @@
+use crate::State;
+use smithay::reexports::input::{AccelProfile, Device};
+use tracing::{info, warn};
+
+/// libinput settings for the devices with the name, or all devices if no name is given
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputConfig {
+    pub device: Option<String>,
+    pub tap: Option<bool>,
+    pub natural_scroll: Option<bool>,
+    pub accel_profile: Option<AccelProfile>,
+    pub accel_speed: Option<f64>,
+}
+
+impl InputConfig {
+    pub fn matches(&self, device: &Device) -> bool {
+        self.device
+            .as_ref()
+            .map_or(true, |name| *name == device.name())
+    }
+
+    /// Applies the settings the device supports, others are skipped
+    pub fn apply(&self, device: &mut Device) {
+        let name = device.name();
+        if let Some(tap) = self.tap.filter(|_| device.config_tap_finger_count() > 0) {
+            if let Err(err) = device.config_tap_set_enabled(tap) {
+                warn!(?err, name, "Unable to configure tapping");
+            }
+        }
+        if let Some(natural_scroll) = self
+            .natural_scroll
+            .filter(|_| device.config_scroll_has_natural_scroll())
+        {
+            if let Err(err) = device.config_scroll_set_natural_scroll_enabled(natural_scroll) {
+                warn!(?err, name, "Unable to configure natural scrolling");
+            }
+        }
+        if device.config_accel_is_available() {
+            if let Some(accel_profile) = self.accel_profile {
+                if let Err(err) = device.config_accel_set_profile(accel_profile) {
+                    warn!(?err, name, "Unable to configure acceleration profile");
+                }
+            }
+            if let Some(accel_speed) = self.accel_speed {
+                if let Err(err) = device.config_accel_set_speed(accel_speed.clamp(-1.0, 1.0)) {
+                    warn!(?err, name, "Unable to configure acceleration speed");
+                }
+            }
+        }
+    }
+}
+
+pub fn accel_profile_from_name(name: &str) -> Option<AccelProfile> {
+    match name {
+        "flat" => Some(AccelProfile::Flat),
+        "adaptive" => Some(AccelProfile::Adaptive),
+        _ => None,
+    }
+}
+
+impl State {
+    /// Applies the settings to all present devices and to devices which are added later. Settings
+    /// for the same device replace the previous ones.
+    pub fn configure_input(&mut self, config: InputConfig) {
+        info!(?config, "Configuring input devices");
+        self.input_configs
+            .retain(|input_config| input_config.device != config.device);
+        self.input_configs.push(config);
+        self.backend_data
+            .configure_input_devices(&self.input_configs);
+    }
+}
+
+/// Applies the matching configs in order, so device specific configs can override general ones
+pub fn configure_input_device(device: &mut Device, configs: &[InputConfig]) {
+    for config in configs.iter().filter(|config| config.matches(device)) {
+        config.apply(device);
+    }
+}
@@ -1,21 +1,24 @@
 use crate::action::Action;
-use crate::{focus::PointerFocusTarget, State};
-use mlua::Function as LuaFunction;
+use crate::config::LuaCallback;
+use crate::pointer_bindings::ButtonBinding;
+use crate::pointer_capture::is_break_pointer_constraint_key;
+use crate::state::active_space;
+use crate::{focus::PointerFocusTarget, workspace_window::WorkspaceWindow, State};
 use smithay::backend::input::GestureSwipeUpdateEvent;
 use smithay::backend::input::{GesturePinchUpdateEvent, TouchEvent};
 use smithay::input::pointer;
 use smithay::input::touch::{DownEvent, UpEvent};
 use smithay::{
     backend::input::{
-        self, AbsolutePositionEvent, Axis, AxisSource, Device, DeviceCapability, Event,
+        AbsolutePositionEvent, Axis, AxisSource, Device, DeviceCapability, Event,
         GestureBeginEvent, GestureEndEvent, InputBackend, InputEvent, KeyState, KeyboardKeyEvent,
         PointerAxisEvent, PointerButtonEvent, PointerMotionEvent, ProximityState,
         TabletToolButtonEvent, TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent,
         TabletToolTipState,
     },
-    desktop::{layer_map_for_output, WindowSurfaceType},
+    desktop::{layer_map_for_output, space::SpaceElement, Space, WindowSurfaceType},
     input::{
-        keyboard::{keysyms as xkb, FilterResult, Keysym, ModifiersState},
+        keyboard::{keysyms as xkb, FilterResult, Keysym, ModifiersState, XkbConfig},
         pointer::{
             AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent,
             GesturePinchBeginEvent, GesturePinchEndEvent, GestureSwipeBeginEvent,
@@ -24,7 +27,7 @@ use smithay::{
     },
     output::Output,
     reexports::wayland_server::{protocol::wl_pointer, DisplayHandle},
-    utils::{Logical, Point, Serial, SERIAL_COUNTER as SCOUNTER},
+    utils::{Logical, Point, Rectangle, Serial, SERIAL_COUNTER as SCOUNTER},
     wayland::{
         compositor::with_states,
         input_method::InputMethodSeat,
@@ -36,7 +39,41 @@ use smithay::{
     },
 };
 use std::convert::TryInto;
-use tracing::debug;
+use tracing::{debug, info, warn};
+
+/// Key mode which is active unless the config entered another one
+pub const DEFAULT_KEY_MODE: &str = "default";
+
+/// Keymap of all keyboards, variants are given per layout
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardConfig {
+    pub layouts: Vec<String>,
+    pub variants: Vec<String>,
+    pub model: Option<String>,
+    /// XKB options like `caps:escape`, separated by commas
+    pub options: Option<String>,
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        KeyboardConfig {
+            layouts: vec!["de".to_string()],
+            variants: Vec::new(),
+            model: None,
+            options: None,
+        }
+    }
+}
+
+/// Callback of a key mapped by the config
+#[derive(Debug, Clone)]
+pub struct KeyBinding {
+    pub callback: LuaCallback,
+    /// Call the callback again with the key repeat rate while the key is held
+    pub repeat: bool,
+    /// Called once the key is released after the callback was called for its press
+    pub release_callback: Option<LuaCallback>,
+}
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Mods {
@@ -67,12 +104,98 @@ impl From<ModifiersState> for Mods {
 }
 
 impl State {
-    pub fn map_key(&mut self, key: Keysym, mods: Mods, callback: LuaFunction<'static>) {
-        self.key_maps.entry(mods).or_default().insert(key, callback);
+    pub fn map_key(&mut self, mode: String, key: Keysym, mods: Mods, binding: KeyBinding) {
+        self.key_maps
+            .entry(mode)
+            .or_default()
+            .entry(mods)
+            .or_default()
+            .insert(key, binding);
+    }
+
+    /// Binding of the keys in the active key mode
+    pub fn key_binding(&self, modifiers: ModifiersState, keysym: Keysym) -> Option<&KeyBinding> {
+        self.key_maps
+            .get(&self.key_mode)
+            .and_then(|maps| maps.get(&modifiers.into()))
+            .and_then(|maps| maps.get(&keysym))
+    }
+
+    /// Callback to call on the release of the key whose binding triggered the action
+    fn release_callback(
+        &self,
+        action: &Action,
+        modifiers: ModifiersState,
+        keysym: Keysym,
+    ) -> Option<LuaCallback> {
+        match action {
+            Action::Callback(_) => self
+                .key_binding(modifiers, keysym)?
+                .release_callback
+                .clone(),
+            _ => None,
+        }
     }
 
     pub fn clear_key_map(&mut self) {
         self.key_maps.clear();
+        // The callbacks belong to the config which is replaced
+        self.held_bindings.clear();
+        self.key_chords.clear();
+        self.cancel_key_chord();
+        self.button_maps.clear();
+        self.key_mode = DEFAULT_KEY_MODE.to_string();
+    }
+
+    /// Only the keys mapped in `mode` trigger callbacks until another mode is entered
+    pub fn enter_key_mode(&mut self, mode: String) {
+        if !self.key_maps.contains_key(&mode) && mode != DEFAULT_KEY_MODE {
+            warn!(mode, "Entering key mode without mapped keys");
+        }
+        info!(mode, "Entering key mode");
+        self.key_mode = mode;
+    }
+
+    pub fn set_keyboard_layout(&mut self, layout: &str) {
+        self.set_keyboard_config(KeyboardConfig {
+            layouts: layout.split(',').map(str::to_string).collect(),
+            variants: Vec::new(),
+            ..self.keyboard_config.clone()
+        });
+    }
+
+    /// Replaces the keymap, the first layout is active afterwards
+    pub fn set_keyboard_config(&mut self, config: KeyboardConfig) {
+        info!(?config, "Setting keyboard config");
+        let keyboard = self.seat.as_ref().unwrap().get_keyboard().unwrap();
+        let layout = config.layouts.join(",");
+        let variant = config.variants.join(",");
+        let xkb_config = XkbConfig {
+            model: config.model.as_deref().unwrap_or_default(),
+            layout: &layout,
+            variant: &variant,
+            options: config.options.clone(),
+            ..Default::default()
+        };
+        if let Err(err) = keyboard.set_xkb_config(self, xkb_config) {
+            warn!(?err, layout, "Unable to set keyboard config");
+            return;
+        }
+        self.keyboard_config = config;
+    }
+
+    /// Activates the next layout of the keymap, after the last one the first one
+    pub fn cycle_keyboard_layout(&mut self) -> crate::error::Result<()> {
+        let keyboard = self.keyboard()?;
+        let layout = keyboard.with_xkb_state(self, |mut context| {
+            context.cycle_next_layout();
+            context.active_layout()
+        });
+        info!(
+            layout = self.keyboard_config.layouts.get(layout.0 as usize),
+            "Switched keyboard layout"
+        );
+        Ok(())
     }
 
     // fn process_common_key_action(&mut self, action: KeyAction) {
@@ -154,19 +277,14 @@ impl State {
         &mut self,
         evt: B::KeyboardKeyEvent,
     ) -> Option<Action> {
-        let space = &self
-            .spaces // FIXME: handle multiple spaces
-            .iter()
-            .next()
-            .unwrap()
-            .1;
-
         let keycode = evt.key_code();
         let evt_state = evt.state();
         debug!(?keycode, ?evt_state, "key");
         let serial = SCOUNTER.next_serial();
         let time = Event::time_msec(&evt);
         let mut suppressed_keys = self.suppressed_keys.clone();
+        let mut repeated_keysym = None;
+        let mut release_callback = None;
         let seat = self.seat.as_ref()?;
         let keyboard = seat.get_keyboard().unwrap();
 
@@ -180,7 +298,7 @@ impl State {
             if data.keyboard_interactivity == KeyboardInteractivity::Exclusive
                 && (data.layer == WlrLayer::Top || data.layer == WlrLayer::Overlay)
             {
-                let surface = space.outputs().find_map(|o| {
+                let surface = self.outputs.values().find_map(|o| {
                     let map = layer_map_for_output(o);
                     let cloned = map.layers().find(|l| l.layer_surface() == &layer).cloned();
                     cloned
@@ -195,8 +313,11 @@ impl State {
             }
         }
 
-        let inhibited = space
-            .element_under(self.pointer_location())
+        let pointer_location = self.pointer_location();
+        let inhibited = self
+            .space_at(pointer_location)
+            .and_then(|space_name| self.spaces.get(&space_name))
+            .and_then(|space| space.element_under(pointer_location))
             .and_then(|(window, _)| {
                 let surface = window.wl_surface()?;
                 self.seat
@@ -221,9 +342,12 @@ impl State {
                     keysym = ::xkbcommon::xkb::keysym_get_name(keysym),
                     "keysym"
                 );
+                state.update_modifier_indicator(*modifiers);
 
-                if !modifiers.alt {
+                if !modifiers.alt && state.tab_index > 0 {
                     state.tab_index = 0;
+                    // The window tabbed to only counts as focused once alt is released
+                    state.record_focused_window();
                 }
 
                 // If the key is pressed and triggered a action
@@ -232,11 +356,16 @@ impl State {
                 // so that we can decide on a release if the key
                 // should be forwarded to the client or not.
                 if let KeyState::Pressed = evt_state {
-                    if !inhibited {
+                    state.visualize_key(*modifiers, keysym);
+                    if !inhibited || is_break_pointer_constraint_key(*modifiers, keysym) {
                         let action = state.process_keyboard_shortcut(*modifiers, keysym);
 
-                        if action.is_some() {
+                        if let Some(action) = &action {
                             suppressed_keys.push(keysym);
+                            if state.action_repeats(action, *modifiers, keysym) {
+                                repeated_keysym = Some(keysym);
+                            }
+                            release_callback = state.release_callback(action, *modifiers, keysym);
                         }
 
                         action
@@ -258,6 +387,25 @@ impl State {
         );
 
         self.suppressed_keys = suppressed_keys;
+        match evt_state {
+            KeyState::Pressed => {
+                if let Some(callback) = release_callback {
+                    self.held_bindings.insert(keycode, callback);
+                }
+                match repeated_keysym {
+                    Some(keysym) => self.start_key_repeat(keycode, keysym),
+                    // Like with clients, pressing another key ends the repetition
+                    None => self.stop_key_repeat(),
+                }
+            }
+            KeyState::Released => {
+                self.key_released(keycode);
+                // The keycode is used, the keysym changes if the modifiers are released first
+                if let Some(callback) = self.held_bindings.remove(&keycode) {
+                    return Some(Action::Callback(callback));
+                }
+            }
+        }
         match action {
             None | Some(Action::None) => None,
             _ => action,
@@ -268,8 +416,41 @@ impl State {
         let serial = SCOUNTER.next_serial();
         let button = evt.button_code();
         let state = wl_pointer::ButtonState::from(evt.state());
+        // Buttons select the region of a screenshot instead of reaching clients
+        if self.region_select_button(state == wl_pointer::ButtonState::Pressed) {
+            return;
+        }
         if wl_pointer::ButtonState::Pressed == state {
+            self.visualize_button(button);
+            // Clicks on the bar reach no client
+            if self.click_bar(self.pointer_location()) {
+                self.suppressed_buttons.push(button);
+                return;
+            }
+            // Clicking a tab switches to its window, the click reaches no client
+            if let Some((space_name, window)) = self.tab_at(self.pointer_location()) {
+                self.suppressed_buttons.push(button);
+                self.focus_window(window, &space_name);
+                return;
+            }
             self.update_keyboard_focus(self.pointer_location(), serial);
+            // Move and resize grabs still receive the button, so they end once it is released
+            let res = match self.button_binding(button) {
+                Some(ButtonBinding::Move) => self.start_pointer_move(button, serial),
+                Some(ButtonBinding::Resize) => self.start_pointer_resize(button, serial),
+                Some(ButtonBinding::Callback(callback)) => {
+                    self.suppressed_buttons.push(button);
+                    self.execute(Action::Callback(callback));
+                    return;
+                }
+                None => Ok(()),
+            };
+            if let Err(err) = res {
+                warn!(%err, button, "Unable to start pointer grab");
+            }
+        } else if self.suppressed_buttons.contains(&button) {
+            self.suppressed_buttons.retain(|b| *b != button);
+            return;
         };
         let Some(pointer) = self.pointer.clone() else {
             return;
@@ -307,24 +488,9 @@ impl State {
             && (!keyboard.is_grabbed() || input_method.keyboard_grabbed())
             && !touch.map(|touch| touch.is_grabbed()).unwrap_or(false)
         {
-            let output = self
-                .spaces // FIXME: handle multiple spaces
-                .iter()
-                .next()
-                .unwrap()
-                .1
-                .output_under(pointer_location)
-                .next()
-                .cloned();
+            let output = self.output_at(pointer_location);
             if let Some(output) = output.as_ref() {
-                let output_geo = self
-                    .spaces // FIXME: handle multiple spaces
-                    .iter()
-                    .next()
-                    .unwrap()
-                    .1
-                    .output_geometry(output)
-                    .unwrap();
+                let output_geo = self.output_rect(output);
 
                 let layers = layer_map_for_output(output);
                 if let Some(layer) = layers
@@ -345,27 +511,26 @@ impl State {
                 }
             }
 
-            if let Some((window, _)) = self
-                .spaces // FIXME: handle multiple spaces
-                .iter()
-                .next()
-                .unwrap()
-                .1
-                .element_under(pointer_location)
-                .map(|(w, p)| (w.clone(), p))
-            {
+            let space_name = output.as_ref().and_then(active_space);
+            if let Some((space_name, window)) = space_name.and_then(|space_name| {
+                let (window, _) = self
+                    .spaces
+                    .get(&space_name)?
+                    .element_under(pointer_location)?;
+                Some((space_name, window.clone()))
+            }) {
                 if let Some(surface) = window.x11_surface() {
                     // users should not be able to focus override redirect windows
                     if surface.is_override_redirect() {
                         return;
                     }
                 }
-                self.spaces // FIXME: handle multiple spaces
-                    .iter_mut()
-                    .next()
-                    .unwrap()
-                    .1
-                    .raise_element(&window, true);
+                if let Some(space) = self.spaces.get_mut(&space_name) {
+                    space.raise_element(&window, true);
+                }
+                let window = self
+                    .raise_child_toplevels(&space_name, &window)
+                    .unwrap_or(window);
                 if let Some(surface) = window.x11_surface() {
                     let Some(ref mut xwayland_state) = &mut self.xwayland_state else {
                         return;
@@ -382,14 +547,7 @@ impl State {
             }
 
             if let Some(output) = output.as_ref() {
-                let output_geo = self
-                    .spaces // FIXME: handle multiple spaces
-                    .iter()
-                    .next()
-                    .unwrap()
-                    .1
-                    .output_geometry(output)
-                    .unwrap();
+                let output_geo = self.output_rect(output);
                 let layers = layer_map_for_output(output);
                 if let Some(layer) = layers
                     .layer_under(WlrLayer::Bottom, pointer_location)
@@ -410,22 +568,39 @@ impl State {
         }
     }
 
+    /// Like [`Space::element_under`], but skips a toplevel which is dragged along with the
+    /// pointer, so that the drop target below it receives the pointer focus
+    fn element_under<'a>(
+        &self,
+        space: &'a Space<WorkspaceWindow>,
+        pos: Point<f64, Logical>,
+    ) -> Option<(&'a WorkspaceWindow, Point<i32, Logical>)> {
+        let Some((dragged, _)) = self.dragged_toplevel() else {
+            return space.element_under(pos);
+        };
+        space
+            .elements()
+            .rev()
+            .filter(|window| **window != dragged)
+            .find_map(|window| {
+                let render_location = space.element_location(window)? - window.geometry().loc;
+                window
+                    .is_in_input_region(&(pos - render_location.to_f64()))
+                    .then_some((window, render_location))
+            })
+    }
+
     pub fn surface_under(
         &self,
         pos: Point<f64, Logical>,
     ) -> Option<(PointerFocusTarget, Point<f64, Logical>)> {
-        let space = &self
-            .spaces // FIXME: handle multiple spaces
-            .iter()
-            .next()
-            .unwrap()
-            .1;
-        let output = space.outputs().find(|o| {
-            let geometry = space.output_geometry(o).unwrap();
-            geometry.contains(pos.to_i32_round())
-        })?;
-        let output_geo = space.output_geometry(output).unwrap();
-        let layers = layer_map_for_output(output);
+        if self.bar_contains(pos) {
+            return None;
+        }
+        let output = self.output_at(pos)?;
+        let output_geo = self.output_rect(&output);
+        let space = self.spaces.get(&active_space(&output)?)?;
+        let layers = layer_map_for_output(&output);
 
         let mut under = None;
         if let Some(focus) = layers
@@ -441,16 +616,16 @@ impl State {
                     .map(|(surface, loc)| {
                         (
                             PointerFocusTarget::from(surface),
-                            loc + layer_loc + output_geo.loc,
+                            (loc + layer_loc + output_geo.loc).to_f64(),
                         )
                     })
             })
         {
             under = Some(focus)
-        } else if let Some(focus) = space.element_under(pos).and_then(|(window, loc)| {
+        } else if let Some(focus) = self.element_under(space, pos).and_then(|(window, loc)| {
             window
                 .surface_under(pos - loc.to_f64(), WindowSurfaceType::ALL)
-                .map(|(surface, surf_loc)| (surface, surf_loc + loc))
+                .map(|(surface, surf_loc)| (surface, surf_loc + loc.to_f64()))
         }) {
             under = Some(focus);
         } else if let Some(focus) = layers
@@ -466,7 +641,7 @@ impl State {
                     .map(|(surface, loc)| {
                         (
                             PointerFocusTarget::from(surface),
-                            loc + layer_loc + output_geo.loc,
+                            (loc + layer_loc + output_geo.loc).to_f64(),
                         )
                     })
             })
@@ -474,24 +649,38 @@ impl State {
             under = Some(focus)
         };
 
-        under.map(|(s, l)| (s, l.to_f64()))
+        under
     }
 
     fn on_pointer_axis<B: InputBackend>(&mut self, evt: B::PointerAxisEvent) {
-        let horizontal_amount = evt.amount(input::Axis::Horizontal).unwrap_or_else(|| {
-            evt.amount_v120(input::Axis::Horizontal).unwrap_or(0.0) * 3.0 / 120.
-        });
-        let vertical_amount = evt
-            .amount(input::Axis::Vertical)
-            .unwrap_or_else(|| evt.amount_v120(input::Axis::Vertical).unwrap_or(0.0) * 3.0 / 120.);
-        let horizontal_amount_discrete = evt.amount_v120(input::Axis::Horizontal);
-        let vertical_amount_discrete = evt.amount_v120(input::Axis::Vertical);
+        let (scroll_factor, swap_axes) = self.pointer_focus_scroll();
+        // Axis of the event the client receives as `axis`
+        let source_axis = |axis: Axis| match axis {
+            Axis::Horizontal if swap_axes => Axis::Vertical,
+            Axis::Vertical if swap_axes => Axis::Horizontal,
+            axis => axis,
+        };
+        let amount = |axis: Axis| {
+            let axis = source_axis(axis);
+            let amount = evt
+                .amount(axis)
+                .unwrap_or_else(|| evt.amount_v120(axis).unwrap_or(0.0) * 3.0 / 120.);
+            amount * scroll_factor
+        };
+        let amount_discrete = |axis: Axis| {
+            evt.amount_v120(source_axis(axis))
+                .map(|v120| v120 * scroll_factor)
+        };
+        let horizontal_amount = amount(Axis::Horizontal);
+        let vertical_amount = amount(Axis::Vertical);
+        let horizontal_amount_discrete = amount_discrete(Axis::Horizontal);
+        let vertical_amount_discrete = amount_discrete(Axis::Vertical);
 
         {
             let mut frame = AxisFrame::new(evt.time_msec()).source(evt.source());
             if horizontal_amount != 0.0 {
-                frame = frame
-                    .relative_direction(Axis::Horizontal, evt.relative_direction(Axis::Horizontal));
+                let direction = evt.relative_direction(source_axis(Axis::Horizontal));
+                frame = frame.relative_direction(Axis::Horizontal, direction);
                 frame = frame.value(Axis::Horizontal, horizontal_amount);
                 if let Some(discrete) = horizontal_amount_discrete {
                     frame = frame.v120(Axis::Horizontal, discrete as i32);
@@ -500,8 +689,8 @@ impl State {
                 frame = frame.stop(Axis::Horizontal);
             }
             if vertical_amount != 0.0 {
-                frame = frame
-                    .relative_direction(Axis::Vertical, evt.relative_direction(Axis::Vertical));
+                let direction = evt.relative_direction(source_axis(Axis::Vertical));
+                frame = frame.relative_direction(Axis::Vertical, direction);
                 frame = frame.value(Axis::Vertical, vertical_amount);
                 if let Some(discrete) = vertical_amount_discrete {
                     frame = frame.v120(Axis::Vertical, discrete as i32);
@@ -510,10 +699,10 @@ impl State {
                 frame = frame.stop(Axis::Vertical);
             }
             if evt.source() == AxisSource::Finger {
-                if evt.amount(Axis::Horizontal) == Some(0.0) {
+                if evt.amount(source_axis(Axis::Horizontal)) == Some(0.0) {
                     frame = frame.stop(Axis::Horizontal);
                 }
-                if evt.amount(Axis::Vertical) == Some(0.0) {
+                if evt.amount(source_axis(Axis::Vertical)) == Some(0.0) {
                     frame = frame.stop(Axis::Vertical);
                 }
             }
@@ -531,6 +720,7 @@ impl State {
         event: InputEvent<B>,
         output_name: &str,
     ) {
+        self.notify_activity();
         match event {
             InputEvent::Keyboard { event } => {
                 if let Some(action) = self.keyboard_key_to_action::<B>(event) {
@@ -559,15 +749,9 @@ impl State {
         evt: B::PointerMotionAbsoluteEvent,
         output: &Output,
     ) {
-        let space = &self
-            .spaces // FIXME: handle multiple spaces
-            .iter()
-            .next()
-            .unwrap()
-            .1;
-        let output_geo = space.output_geometry(output).unwrap();
+        let output_geo = self.output_rect(output);
 
-        let pos = evt.position_transformed(output_geo.size) + output_geo.loc.to_f64();
+        let pos = position_on_output(&evt, output, output_geo);
         let serial = SCOUNTER.next_serial();
 
         let pointer = self.pointer.clone().unwrap();
@@ -582,6 +766,9 @@ impl State {
             },
         );
         pointer.frame(self);
+        self.update_toplevel_drag();
+        self.update_bar_hover();
+        self.focus_follows_pointer();
     }
 
     pub fn release_all_keys(&mut self) {
@@ -601,6 +788,16 @@ impl State {
 
 impl State {
     pub fn process_input_event<B: InputBackend>(&mut self, event: InputEvent<B>) {
+        if let Some(time) = event_time(&event) {
+            self.input_time_usec = time;
+        }
+        if !matches!(
+            event,
+            InputEvent::DeviceAdded { .. } | InputEvent::DeviceRemoved { .. }
+        ) {
+            self.notify_activity();
+        }
+
         match event {
             InputEvent::Keyboard { event, .. } => {
                 if let Some(action) = self.keyboard_key_to_action::<B>(event) {
@@ -673,6 +870,8 @@ impl State {
     fn on_pointer_move<B: InputBackend>(&mut self, evt: B::PointerMotionEvent) {
         // TODO: Can we do this better?
         self.backend_data.schedule_render();
+        // Constraints are deactivated by smithay when they are destroyed
+        self.update_pointer_capture();
 
         let mut pointer_location = self.pointer_location();
 
@@ -726,25 +925,42 @@ impl State {
             return;
         }
 
-        pointer_location += evt.delta();
+        // Once unlocked, continue from the position the client hinted during the lock
+        let previous_location = self
+            .pointer_lock_hint
+            .take()
+            .map(|hint| self.clamp_coords(hint))
+            .unwrap_or(pointer_location);
+        pointer_location = previous_location + evt.delta();
 
         // clamp to screen limits
         // this event is never generated by winit
         pointer_location = self.clamp_coords(pointer_location);
 
-        let new_under = self.surface_under(pointer_location);
-
-        // If confined, don't move pointer if it would go outside surface or region
+        // If confined, slide along the edge of the surface or region instead of leaving it
         if pointer_confined {
             if let Some((surface, surface_loc)) = &under {
-                if new_under.as_ref().and_then(|(under, _)| under.wl_surface())
-                    != surface.wl_surface()
+                let is_inside = |state: &Self, location: Point<f64, Logical>| {
+                    state
+                        .surface_under(location)
+                        .as_ref()
+                        .and_then(|(under, _)| under.wl_surface())
+                        == surface.wl_surface()
+                        && confine_region.as_ref().map_or(true, |region| {
+                            region.contains((location - *surface_loc).to_i32_round())
+                        })
+                };
+                let candidates: [Point<f64, Logical>; 3] = [
+                    pointer_location,
+                    (pointer_location.x, previous_location.y).into(),
+                    (previous_location.x, pointer_location.y).into(),
+                ];
+                match candidates
+                    .into_iter()
+                    .find(|location| is_inside(self, *location))
                 {
-                    pointer.frame(self);
-                    return;
-                }
-                if let Some(region) = confine_region {
-                    if !region.contains((pointer_location - *surface_loc).to_i32_round()) {
+                    Some(location) => pointer_location = location,
+                    None => {
                         pointer.frame(self);
                         return;
                     }
@@ -752,6 +968,8 @@ impl State {
             }
         }
 
+        let new_under = self.surface_under(pointer_location);
+
         pointer.motion(
             self,
             under,
@@ -762,11 +980,20 @@ impl State {
             },
         );
         pointer.frame(self);
+        self.update_toplevel_drag();
+
+        let new_under =
+            new_under.and_then(|(target, loc)| Some((target.wl_surface()?.into_owned(), loc)));
+        // A broken constraint stays inactive until the pointer leaves its surface
+        if self.broken_pointer_constraint.as_ref() != new_under.as_ref().map(|(surface, _)| surface)
+        {
+            self.broken_pointer_constraint = None;
+        }
 
         // If pointer is now in a constraint region, activate it
         // TODO Anywhere else pointer is moved needs to do this
         if let Some((under, surface_location)) =
-            new_under.and_then(|(target, loc)| Some((target.wl_surface()?.into_owned(), loc)))
+            new_under.filter(|_| self.broken_pointer_constraint.is_none())
         {
             with_pointer_constraint(&under, &pointer, |constraint| match constraint {
                 Some(constraint) if !constraint.is_active() => {
@@ -781,6 +1008,9 @@ impl State {
                 _ => {}
             });
         }
+        self.update_pointer_capture();
+        self.update_bar_hover();
+        self.focus_follows_pointer();
     }
 
     fn on_pointer_move_absolute<B: InputBackend>(&mut self, evt: B::PointerMotionAbsoluteEvent) {
@@ -789,23 +1019,17 @@ impl State {
 
         let serial = SCOUNTER.next_serial();
 
-        let space = &self
-            .spaces // FIXME: handle multiple spaces
-            .iter()
-            .next()
-            .unwrap()
-            .1;
-
-        let max_x = space
-            .outputs()
-            .fold(0, |acc, o| acc + space.output_geometry(o).unwrap().size.w);
-
-        let max_h_output = space
-            .outputs()
-            .max_by_key(|o| space.output_geometry(o).unwrap().size.h)
-            .unwrap();
+        let max_x = self
+            .outputs
+            .values()
+            .fold(0, |acc, o| acc + self.output_rect(o).size.w);
 
-        let max_y = space.output_geometry(max_h_output).unwrap().size.h;
+        let max_y = self
+            .outputs
+            .values()
+            .map(|o| self.output_rect(o).size.h)
+            .max()
+            .unwrap_or_default();
 
         let mut pointer_location = (evt.x_transformed(max_x), evt.y_transformed(max_y)).into();
 
@@ -825,25 +1049,22 @@ impl State {
             },
         );
         pointer.frame(self);
+        self.update_toplevel_drag();
+        self.update_bar_hover();
+        self.focus_follows_pointer();
     }
 
     fn on_tablet_tool_axis<B: InputBackend>(&mut self, evt: B::TabletToolAxisEvent) {
         let tablet_seat = self.seat.as_ref().unwrap().tablet_seat();
 
-        let space = &self
-            .spaces // FIXME: handle multiple spaces
-            .iter()
-            .next()
-            .unwrap()
-            .1;
-
-        let output_geometry = space
-            .outputs()
+        let output_geometry = self
+            .outputs
+            .values()
             .next()
-            .map(|o| space.output_geometry(o).unwrap());
+            .map(|o| (o.clone(), self.output_rect(o)));
 
-        if let Some(rect) = output_geometry {
-            let pointer_location = evt.position_transformed(rect.size) + rect.loc.to_f64();
+        if let Some((output, rect)) = output_geometry {
+            let pointer_location = position_on_output(&evt, &output, rect);
 
             let pointer = self.pointer.clone().unwrap();
             let under = self.surface_under(pointer_location);
@@ -895,23 +1116,17 @@ impl State {
     fn on_tablet_tool_proximity<B: InputBackend>(&mut self, evt: B::TabletToolProximityEvent) {
         let tablet_seat = self.seat.as_ref().unwrap().tablet_seat();
 
-        let space = &self
-            .spaces // FIXME: handle multiple spaces
-            .iter()
-            .next()
-            .unwrap()
-            .1;
-
-        let output_geometry = space
-            .outputs()
+        let output_geometry = self
+            .outputs
+            .values()
             .next()
-            .map(|o| space.output_geometry(o).unwrap());
+            .map(|o| (o.clone(), self.output_rect(o)));
 
-        if let Some(rect) = output_geometry {
+        if let Some((output, rect)) = output_geometry {
             let tool = evt.tool();
             tablet_seat.add_tool::<Self>(self, &self.display_handle.clone(), &tool);
 
-            let pointer_location = evt.position_transformed(rect.size) + rect.loc.to_f64();
+            let pointer_location = position_on_output(&evt, &output, rect);
 
             let pointer = self.pointer.clone().unwrap();
             let under = self.surface_under(pointer_location);
@@ -992,7 +1207,9 @@ impl State {
 
     fn on_gesture_swipe_begin<B: InputBackend>(&mut self, evt: B::GestureSwipeBeginEvent) {
         let serial = SCOUNTER.next_serial();
-        let pointer = self.pointer.clone().unwrap();
+        let Some(pointer) = self.pointer.clone() else {
+            return;
+        };
         pointer.gesture_swipe_begin(
             self,
             &GestureSwipeBeginEvent {
@@ -1004,7 +1221,9 @@ impl State {
     }
 
     fn on_gesture_swipe_update<B: InputBackend>(&mut self, evt: B::GestureSwipeUpdateEvent) {
-        let pointer = self.pointer.clone().unwrap();
+        let Some(pointer) = self.pointer.clone() else {
+            return;
+        };
         pointer.gesture_swipe_update(
             self,
             &pointer::GestureSwipeUpdateEvent {
@@ -1016,7 +1235,9 @@ impl State {
 
     fn on_gesture_swipe_end<B: InputBackend>(&mut self, evt: B::GestureSwipeEndEvent) {
         let serial = SCOUNTER.next_serial();
-        let pointer = self.pointer.clone().unwrap();
+        let Some(pointer) = self.pointer.clone() else {
+            return;
+        };
         pointer.gesture_swipe_end(
             self,
             &GestureSwipeEndEvent {
@@ -1029,7 +1250,9 @@ impl State {
 
     fn on_gesture_pinch_begin<B: InputBackend>(&mut self, evt: B::GesturePinchBeginEvent) {
         let serial = SCOUNTER.next_serial();
-        let pointer = self.pointer.clone().unwrap();
+        let Some(pointer) = self.pointer.clone() else {
+            return;
+        };
         pointer.gesture_pinch_begin(
             self,
             &GesturePinchBeginEvent {
@@ -1041,7 +1264,9 @@ impl State {
     }
 
     fn on_gesture_pinch_update<B: InputBackend>(&mut self, evt: B::GesturePinchUpdateEvent) {
-        let pointer = self.pointer.clone().unwrap();
+        let Some(pointer) = self.pointer.clone() else {
+            return;
+        };
         pointer.gesture_pinch_update(
             self,
             &pointer::GesturePinchUpdateEvent {
@@ -1055,7 +1280,9 @@ impl State {
 
     fn on_gesture_pinch_end<B: InputBackend>(&mut self, evt: B::GesturePinchEndEvent) {
         let serial = SCOUNTER.next_serial();
-        let pointer = self.pointer.clone().unwrap();
+        let Some(pointer) = self.pointer.clone() else {
+            return;
+        };
         pointer.gesture_pinch_end(
             self,
             &GesturePinchEndEvent {
@@ -1068,7 +1295,9 @@ impl State {
 
     fn on_gesture_hold_begin<B: InputBackend>(&mut self, evt: B::GestureHoldBeginEvent) {
         let serial = SCOUNTER.next_serial();
-        let pointer = self.pointer.clone().unwrap();
+        let Some(pointer) = self.pointer.clone() else {
+            return;
+        };
         pointer.gesture_hold_begin(
             self,
             &GestureHoldBeginEvent {
@@ -1081,7 +1310,9 @@ impl State {
 
     fn on_gesture_hold_end<B: InputBackend>(&mut self, evt: B::GestureHoldEndEvent) {
         let serial = SCOUNTER.next_serial();
-        let pointer = self.pointer.clone().unwrap();
+        let Some(pointer) = self.pointer.clone() else {
+            return;
+        };
         pointer.gesture_hold_end(
             self,
             &GestureHoldEndEvent {
@@ -1104,20 +1335,9 @@ impl State {
 
         let output = output?;
 
-        // TODO: Handle multiple spaces
-        let output_geometry = self
-            .spaces
-            .values()
-            .next()
-            .unwrap()
-            .output_geometry(output)?;
+        let output_geometry = self.output_rect(output);
 
-        let transform = output.current_transform();
-        let size = transform.invert().transform_size(output_geometry.size);
-        Some(
-            transform.transform_point_in(evt.position_transformed(size), &size.to_f64())
-                + output_geometry.loc.to_f64(),
-        )
+        Some(position_on_output(evt, output, output_geometry))
     }
 
     fn on_touch_down<B: InputBackend>(&mut self, evt: B::TouchDownEvent) {
@@ -1191,29 +1411,22 @@ impl State {
     }
 
     fn clamp_coords(&self, pos: Point<f64, Logical>) -> Point<f64, Logical> {
-        let space = &self
-            .spaces // FIXME: handle multiple spaces
-            .iter()
-            .next()
-            .unwrap()
-            .1;
-
-        if space.outputs().next().is_none() {
+        if self.outputs.is_empty() {
             return pos;
         }
 
         let (pos_x, pos_y) = pos.into();
-        let max_x = space
-            .outputs()
-            .fold(0, |acc, o| acc + space.output_geometry(o).unwrap().size.w);
+        let max_x = self
+            .outputs
+            .values()
+            .fold(0, |acc, o| acc + self.output_rect(o).size.w);
         let clamped_x = pos_x.clamp(0.0, max_x as f64);
-        let max_y = space
-            .outputs()
-            .find(|o| {
-                let geo = space.output_geometry(o).unwrap();
-                geo.contains((clamped_x as i32, 0))
-            })
-            .map(|o| space.output_geometry(o).unwrap().size.h);
+        let max_y = self
+            .outputs
+            .values()
+            .map(|o| self.output_rect(o))
+            .find(|geo| geo.contains((clamped_x as i32, 0)))
+            .map(|geo| geo.size.h);
 
         if let Some(max_y) = max_y {
             let clamped_y = pos_y.clamp(0.0, max_y as f64);
@@ -1248,11 +1461,17 @@ impl State {
 // }
 
 impl State {
-    fn process_keyboard_shortcut(
+    pub fn process_keyboard_shortcut(
         &mut self,
         modifiers: ModifiersState,
         keysym: Keysym,
     ) -> Option<Action> {
+        if let Some(kiosk) = &self.kiosk {
+            // Only the escape sequence works, so that the kiosk can not be left by accident
+            return kiosk
+                .is_escape(modifiers, keysym)
+                .then_some(Action::LeaveKiosk);
+        }
         if modifiers.ctrl && modifiers.alt && keysym == Keysym::BackSpace
             || modifiers.logo && keysym == Keysym::Q
         {
@@ -1264,15 +1483,63 @@ impl State {
             Some(Action::VtSwitch(
                 (keysym.raw() - xkb::KEY_XF86Switch_VT_1 + 1) as i32,
             ))
+        } else if is_break_pointer_constraint_key(modifiers, keysym)
+            && self.pointer_capture.is_some()
+        {
+            Some(Action::BreakPointerConstraint)
+        } else if let Some(action) = self.move_mode_key(modifiers, keysym) {
+            Some(action)
+        } else if let Some(action) = self.region_select_key(keysym) {
+            Some(action)
         } else if modifiers.alt && keysym == Keysym::Tab {
             self.tab_index += 1;
             Some(Action::Tab {
                 index: self.tab_index,
             })
+        } else if self.key_chord_pending() {
+            self.continue_key_chord(modifiers, keysym)
         } else {
-            let maps = self.key_maps.get(&modifiers.into())?;
-            let callback = maps.get(&keysym)?;
-            Some(Action::Callback(callback.clone()))
+            match self.key_binding(modifiers, keysym) {
+                Some(binding) => Some(Action::Callback(binding.callback.clone())),
+                // The key is consumed, the chord is finished or abandoned by the next keys
+                None if self.start_key_chord(modifiers, keysym) => Some(Action::None),
+                // Escape always leaves a mode, so a mode without a way out can not lock the user in
+                None if keysym == Keysym::Escape && self.key_mode != DEFAULT_KEY_MODE => {
+                    Some(Action::EnterKeyMode {
+                        mode: DEFAULT_KEY_MODE.to_string(),
+                    })
+                }
+                None => None,
+            }
         }
     }
 }
+
+/// Position of an absolute event on the output. Devices like touchscreens report positions on
+/// the panel, which are rotated together with the output.
+fn position_on_output<B: InputBackend, E: AbsolutePositionEvent<B>>(
+    evt: &E,
+    output: &Output,
+    output_geometry: Rectangle<i32, Logical>,
+) -> Point<f64, Logical> {
+    let transform = output.current_transform();
+    let size = transform.invert().transform_size(output_geometry.size);
+    transform.transform_point_in(evt.position_transformed(size), &size.to_f64())
+        + output_geometry.loc.to_f64()
+}
+
+/// Microsecond timestamp of the events which are forwarded with input-timestamps
+fn event_time<B: InputBackend>(event: &InputEvent<B>) -> Option<u64> {
+    let time = match event {
+        InputEvent::Keyboard { event, .. } => Event::time(event),
+        InputEvent::PointerMotion { event, .. } => Event::time(event),
+        InputEvent::PointerMotionAbsolute { event, .. } => Event::time(event),
+        InputEvent::PointerButton { event, .. } => Event::time(event),
+        InputEvent::PointerAxis { event, .. } => Event::time(event),
+        InputEvent::TouchDown { event } => Event::time(event),
+        InputEvent::TouchUp { event } => Event::time(event),
+        InputEvent::TouchMotion { event } => Event::time(event),
+        _ => return None,
+    };
+    Some(time)
+}
@@ -0,0 +1,469 @@
+use crate::{
+    action::Action,
+    egui_window::{EguiAppState, EguiWindow},
+    input_handler::{KeyBinding, Mods},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use egui::{Align2, Area, Context, Frame, Grid, Id, RichText};
+use smithay::{
+    input::keyboard::{Keysym, ModifiersState},
+    utils::{Logical, Point, Rectangle, Size},
+};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const HINT_WIDTH: i32 = 480;
+const HINT_HEIGHT: i32 = 360;
+/// Distance of the hint to the top edge of the output
+const HINT_MARGIN: i32 = 64;
+
+/// Key of a chord with the modifiers held while it is pressed
+pub type ChordKey = (Mods, Keysym);
+
+/// Binding which is triggered by pressing its keys one after another, e.g. `super+space b`
+#[derive(Debug, Clone)]
+pub struct KeyChord {
+    /// Key mode the chord is mapped in
+    pub mode: String,
+    pub keys: Vec<ChordKey>,
+    pub binding: KeyBinding,
+    /// Shown next to the last key in the hint
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyChordConfig {
+    /// A started chord is abandoned if the next key is not pressed in time
+    pub timeout: Duration,
+    /// Show the keys which can follow while a chord is started
+    pub hint: bool,
+}
+
+impl Default for KeyChordConfig {
+    fn default() -> Self {
+        KeyChordConfig {
+            timeout: Duration::from_secs(2),
+            hint: true,
+        }
+    }
+}
+
+/// Keys of a started chord
+#[derive(Debug)]
+pub struct PendingChord {
+    keys: Vec<ChordKey>,
+    /// The timeout starts again with every key
+    last_key: Instant,
+    timer: Option<RegistrationToken>,
+    /// Hint window and the space it is mapped in
+    hint: Option<(EguiWindow, String)>,
+}
+
+impl PendingChord {
+    fn timed_out(&self, now: Instant, timeout: Duration) -> bool {
+        now.saturating_duration_since(self.last_key) >= timeout
+    }
+}
+
+/// Outcome of the keys pressed since a chord was started
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ChordStep {
+    /// The keys are all keys of the chord with the index
+    Complete(usize),
+    /// Chords start with the keys, but need more of them
+    Prefix,
+    /// No chord starts with the keys
+    Mismatch,
+}
+
+/// Matches the keys against the chords of the mode, a chord they complete wins over longer ones
+fn chord_step(chords: &[KeyChord], mode: &str, keys: &[ChordKey]) -> ChordStep {
+    let mut step = ChordStep::Mismatch;
+    for (index, chord) in chords.iter().enumerate() {
+        if chord.mode != mode || !chord.keys.starts_with(keys) {
+            continue;
+        }
+        if chord.keys.len() == keys.len() {
+            return ChordStep::Complete(index);
+        }
+        step = ChordStep::Prefix;
+    }
+    step
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChordHint {
+    pressed: String,
+    /// Keys which can follow with the descriptions of their chords
+    next: Vec<(String, String)>,
+}
+
+impl ChordHint {
+    pub fn show(&mut self, ctx: &Context) {
+        Area::new(Id::new("chord_hint"))
+            .anchor(Align2::CENTER_TOP, [0.0, 0.0])
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(RichText::new(&self.pressed).heading().strong());
+                    ui.separator();
+                    Grid::new("chord_hint_keys").striped(true).show(ui, |ui| {
+                        for (key, description) in &self.next {
+                            ui.label(RichText::new(key).monospace().strong());
+                            ui.label(description);
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+}
+
+impl From<ChordHint> for EguiAppState {
+    fn from(chord_hint: ChordHint) -> Self {
+        EguiAppState::ChordHint(chord_hint)
+    }
+}
+
+fn chord_key_label((mods, keysym): &ChordKey) -> String {
+    let mut label = String::new();
+    for (pressed, name) in [
+        (mods.logo, "Super"),
+        (mods.ctrl, "Ctrl"),
+        (mods.alt, "Alt"),
+        (mods.shift, "Shift"),
+    ] {
+        if pressed {
+            label.push_str(name);
+            label.push('+');
+        }
+    }
+    label.push_str(&::xkbcommon::xkb::keysym_get_name(*keysym));
+    label
+}
+
+impl State {
+    /// Maps the chord, replacing a chord with the same keys in the same mode
+    pub fn map_key_chord(&mut self, chord: KeyChord) {
+        self.key_chords
+            .retain(|mapped| mapped.mode != chord.mode || mapped.keys != chord.keys);
+        self.key_chords.push(chord);
+    }
+
+    pub fn set_key_chord_config(&mut self, config: KeyChordConfig) {
+        self.key_chord_config = config;
+    }
+
+    /// Starts a chord if the key is the first key of one in the active mode
+    pub fn start_key_chord(&mut self, modifiers: ModifiersState, keysym: Keysym) -> bool {
+        let key = (Mods::from(modifiers), keysym);
+        let starts_chord = self
+            .key_chords
+            .iter()
+            .any(|chord| chord.mode == self.key_mode && chord.keys.first() == Some(&key));
+        if starts_chord {
+            info!(key = chord_key_label(&key), "Starting key chord");
+            self.pending_chord = Some(PendingChord {
+                keys: vec![key],
+                last_key: Instant::now(),
+                timer: None,
+                hint: None,
+            });
+            self.refresh_pending_chord();
+        }
+        starts_chord
+    }
+
+    /// Whether a chord is started. A chord whose timeout passed is abandoned, even if its timer
+    /// did not run yet because the compositor was busy.
+    pub fn key_chord_pending(&mut self) -> bool {
+        let Some(pending_chord) = &self.pending_chord else {
+            return false;
+        };
+        if pending_chord.timed_out(Instant::now(), self.key_chord_config.timeout) {
+            info!("Key chord timed out");
+            self.cancel_key_chord();
+            return false;
+        }
+        true
+    }
+
+    /// Continues the started chord with the key. Any key which does not belong to a chord ends
+    /// it without reaching the focused window. Modifiers are not handled, they are part of the
+    /// next key.
+    pub fn continue_key_chord(
+        &mut self,
+        modifiers: ModifiersState,
+        keysym: Keysym,
+    ) -> Option<Action> {
+        if keysym.is_modifier_key() {
+            return None;
+        }
+        let mut keys = self.pending_chord.as_ref()?.keys.clone();
+        keys.push((Mods::from(modifiers), keysym));
+
+        match chord_step(&self.key_chords, &self.key_mode, &keys) {
+            ChordStep::Complete(index) => {
+                let callback = self.key_chords[index].binding.callback.clone();
+                self.cancel_key_chord();
+                return Some(Action::Callback(callback));
+            }
+            ChordStep::Prefix => {
+                if let Some(pending_chord) = &mut self.pending_chord {
+                    pending_chord.keys = keys;
+                    self.refresh_pending_chord();
+                }
+            }
+            ChordStep::Mismatch => {
+                info!(keys = ?keys, "Keys match no chord");
+                self.cancel_key_chord();
+            }
+        }
+        Some(Action::None)
+    }
+
+    pub fn cancel_key_chord(&mut self) {
+        let Some(pending_chord) = self.pending_chord.take() else {
+            return;
+        };
+        if let Some(timer) = pending_chord.timer {
+            self.loop_handle.remove(timer);
+        }
+        if let Some((window, space_name)) = pending_chord.hint {
+            if let Some(space) = self.spaces.get_mut(&space_name) {
+                space.unmap_elem(&WorkspaceWindow::from(window));
+            }
+            self.backend_data.schedule_render();
+        }
+    }
+
+    /// Restarts the timeout and shows the keys which can follow in the hint
+    fn refresh_pending_chord(&mut self) {
+        let Some(mut pending_chord) = self.pending_chord.take() else {
+            return;
+        };
+        if let Some(timer) = pending_chord.timer.take() {
+            self.loop_handle.remove(timer);
+        }
+        pending_chord.last_key = Instant::now();
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(self.key_chord_config.timeout),
+            |_, _, state| {
+                if let Some(pending_chord) = &mut state.pending_chord {
+                    pending_chord.timer = None;
+                }
+                state.cancel_key_chord();
+                TimeoutAction::Drop
+            },
+        );
+        match res {
+            Ok(timer) => pending_chord.timer = Some(timer),
+            Err(err) => warn!(?err, "Unable to start key chord timeout"),
+        }
+
+        if self.key_chord_config.hint {
+            let content = self.chord_hint(&pending_chord.keys);
+            match &pending_chord.hint {
+                Some((window, _)) => {
+                    window.with_chord_hint(|hint| *hint = content);
+                }
+                None => pending_chord.hint = self.show_chord_hint(content),
+            }
+            self.backend_data.schedule_render();
+        }
+        self.pending_chord = Some(pending_chord);
+    }
+
+    fn chord_hint(&self, pressed: &[ChordKey]) -> ChordHint {
+        let next = self
+            .key_chords
+            .iter()
+            .filter(|chord| {
+                chord.mode == self.key_mode
+                    && chord.keys.len() > pressed.len()
+                    && chord.keys.starts_with(pressed)
+            })
+            .map(|chord| {
+                let rest = chord.keys[pressed.len()..]
+                    .iter()
+                    .map(chord_key_label)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (rest, chord.description.clone().unwrap_or_default())
+            })
+            .collect();
+        ChordHint {
+            pressed: pressed
+                .iter()
+                .map(chord_key_label)
+                .collect::<Vec<_>>()
+                .join(" "),
+            next,
+        }
+    }
+
+    fn show_chord_hint(&mut self, content: ChordHint) -> Option<(EguiWindow, String)> {
+        let space_name = self.space_under_pointer().ok()?;
+        let space = &self.spaces[&space_name];
+        let output_geometry = space
+            .output_under(self.pointer_location())
+            .next()
+            .or_else(|| space.outputs().next())
+            .and_then(|output| space.output_geometry(output))?;
+
+        let size = Size::<i32, Logical>::from((HINT_WIDTH, HINT_HEIGHT));
+        let geometry = Rectangle::from_loc_and_size(
+            output_geometry.loc
+                + Point::<i32, Logical>::from(((output_geometry.size.w - size.w) / 2, HINT_MARGIN)),
+            size,
+        );
+
+        let window = EguiWindow::new(content);
+        self.apply_theme_to_window(&WorkspaceWindow::from(window.clone()));
+        window.position(geometry.size);
+        self.spaces.get_mut(&space_name)?.map_element(
+            WorkspaceWindow::from(window.clone()),
+            geometry.loc,
+            false,
+        );
+        Some((window, space_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LuaCallback;
+    use mlua::{FromLua, Lua, Value as LuaValue};
+
+    fn key(keysym: Keysym) -> ChordKey {
+        (Mods::default(), keysym)
+    }
+
+    fn super_key(keysym: Keysym) -> ChordKey {
+        let mods = Mods {
+            logo: true,
+            ..Default::default()
+        };
+        (mods, keysym)
+    }
+
+    fn chord(lua: &Lua, mode: &str, keys: Vec<ChordKey>) -> KeyChord {
+        let function = lua.create_function(|_, ()| Ok(())).unwrap();
+        KeyChord {
+            mode: mode.to_string(),
+            keys,
+            binding: KeyBinding {
+                callback: LuaCallback::from_lua(LuaValue::Function(function), lua).unwrap(),
+                repeat: false,
+                release_callback: None,
+            },
+            description: None,
+        }
+    }
+
+    fn chords(lua: &Lua) -> Vec<KeyChord> {
+        vec![
+            chord(
+                lua,
+                "default",
+                vec![super_key(Keysym::space), key(Keysym::b)],
+            ),
+            chord(
+                lua,
+                "default",
+                vec![super_key(Keysym::space), key(Keysym::w), key(Keysym::q)],
+            ),
+            chord(
+                lua,
+                "resize",
+                vec![super_key(Keysym::space), key(Keysym::x)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn started_chords_are_prefixes() {
+        let lua = Lua::new();
+        let chords = chords(&lua);
+        let keys = [super_key(Keysym::space)];
+        assert_eq!(chord_step(&chords, "default", &keys), ChordStep::Prefix);
+        let keys = [super_key(Keysym::space), key(Keysym::w)];
+        assert_eq!(chord_step(&chords, "default", &keys), ChordStep::Prefix);
+    }
+
+    #[test]
+    fn all_keys_complete_the_chord() {
+        let lua = Lua::new();
+        let chords = chords(&lua);
+        let keys = [super_key(Keysym::space), key(Keysym::b)];
+        assert_eq!(
+            chord_step(&chords, "default", &keys),
+            ChordStep::Complete(0)
+        );
+        let keys = [super_key(Keysym::space), key(Keysym::w), key(Keysym::q)];
+        assert_eq!(
+            chord_step(&chords, "default", &keys),
+            ChordStep::Complete(1)
+        );
+    }
+
+    #[test]
+    fn complete_chord_wins_over_longer_ones() {
+        let lua = Lua::new();
+        let mut chords = chords(&lua);
+        chords.push(chord(
+            &lua,
+            "default",
+            vec![super_key(Keysym::space), key(Keysym::w)],
+        ));
+        let keys = [super_key(Keysym::space), key(Keysym::w)];
+        assert_eq!(
+            chord_step(&chords, "default", &keys),
+            ChordStep::Complete(3)
+        );
+    }
+
+    #[test]
+    fn other_keys_do_not_match() {
+        let lua = Lua::new();
+        let chords = chords(&lua);
+        // Wrong key, missing modifier and too many keys all end the chord
+        let keys = [super_key(Keysym::space), key(Keysym::c)];
+        assert_eq!(chord_step(&chords, "default", &keys), ChordStep::Mismatch);
+        let keys = [key(Keysym::space), key(Keysym::b)];
+        assert_eq!(chord_step(&chords, "default", &keys), ChordStep::Mismatch);
+        let keys = [super_key(Keysym::space), key(Keysym::b), key(Keysym::b)];
+        assert_eq!(chord_step(&chords, "default", &keys), ChordStep::Mismatch);
+    }
+
+    #[test]
+    fn chords_of_other_modes_do_not_match() {
+        let lua = Lua::new();
+        let chords = chords(&lua);
+        let keys = [super_key(Keysym::space), key(Keysym::x)];
+        assert_eq!(chord_step(&chords, "default", &keys), ChordStep::Mismatch);
+        assert_eq!(chord_step(&chords, "resize", &keys), ChordStep::Complete(2));
+    }
+
+    #[test]
+    fn chord_times_out_after_the_last_key() {
+        let last_key = Instant::now();
+        let pending_chord = PendingChord {
+            keys: vec![super_key(Keysym::space)],
+            last_key,
+            timer: None,
+            hint: None,
+        };
+        let timeout = Duration::from_secs(2);
+        assert!(!pending_chord.timed_out(last_key, timeout));
+        assert!(!pending_chord.timed_out(last_key + Duration::from_millis(1999), timeout));
+        assert!(pending_chord.timed_out(last_key + timeout, timeout));
+        // A clock going backwards does not end the chord
+        assert!(!pending_chord.timed_out(last_key - Duration::from_secs(1), timeout));
+    }
+}
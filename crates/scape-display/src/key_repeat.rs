@@ -0,0 +1,116 @@
+use crate::{action::Action, State};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use smithay::input::keyboard::{Keycode, Keysym, ModifiersState};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Repeat settings of the keyboard, clients and repeating key bindings use the same ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyRepeatConfig {
+    /// Repeats per second, 0 disables repeating
+    pub rate: i32,
+    /// Milliseconds a key has to be held until it repeats
+    pub delay: i32,
+}
+
+impl Default for KeyRepeatConfig {
+    fn default() -> Self {
+        KeyRepeatConfig {
+            rate: 20,
+            delay: 400,
+        }
+    }
+}
+
+/// Key binding which is repeated while its key is held
+#[derive(Debug)]
+pub struct KeyRepeat {
+    keycode: Keycode,
+    timer: RegistrationToken,
+}
+
+impl State {
+    pub fn set_key_repeat(&mut self, config: KeyRepeatConfig) {
+        info!(?config, "Setting key repeat");
+        match self.keyboard() {
+            Ok(keyboard) => keyboard.change_repeat_info(config.rate, config.delay),
+            // The keyboard is created with the config once the seat exists
+            Err(err) => warn!(%err, "Unable to set key repeat"),
+        }
+        self.key_repeat_config = config;
+    }
+
+    /// Whether the action of a key binding is repeated while the keys are held. Moving a window
+    /// in the move mode always repeats, bindings of the config only if they were mapped so.
+    pub fn action_repeats(
+        &self,
+        action: &Action,
+        modifiers: ModifiersState,
+        keysym: Keysym,
+    ) -> bool {
+        match action {
+            Action::MoveModeStep { .. } => true,
+            Action::Callback(_) => self
+                .key_binding(modifiers, keysym)
+                .is_some_and(|binding| binding.repeat),
+            _ => false,
+        }
+    }
+
+    /// Repeats the binding of the pressed key after the repeat delay until the key is released
+    pub fn start_key_repeat(&mut self, keycode: Keycode, keysym: Keysym) {
+        self.stop_key_repeat();
+        let KeyRepeatConfig { rate, delay } = self.key_repeat_config;
+        if rate <= 0 {
+            return;
+        }
+        let interval = Duration::from_secs_f64(1.0 / rate as f64);
+        let delay = Duration::from_millis(delay.max(0) as u64);
+
+        let res =
+            self.loop_handle
+                .insert_source(Timer::from_duration(delay), move |_, _, state| {
+                    // The binding is looked up again, so released modifiers or a left mode end it
+                    let action = state.keyboard().ok().and_then(|keyboard| {
+                        let modifiers = keyboard.modifier_state();
+                        state
+                            .process_keyboard_shortcut(modifiers, keysym)
+                            .filter(|action| state.action_repeats(action, modifiers, keysym))
+                    });
+                    match action {
+                        Some(action) => {
+                            state.execute(action);
+                            TimeoutAction::ToDuration(interval)
+                        }
+                        None => {
+                            state.key_repeat = None;
+                            TimeoutAction::Drop
+                        }
+                    }
+                });
+        match res {
+            Ok(timer) => self.key_repeat = Some(KeyRepeat { keycode, timer }),
+            Err(err) => warn!(?err, "Unable to start key repeat timer"),
+        }
+    }
+
+    /// Stops repeating once the repeated key is released
+    pub fn key_released(&mut self, keycode: Keycode) {
+        if self
+            .key_repeat
+            .as_ref()
+            .is_some_and(|key_repeat| key_repeat.keycode == keycode)
+        {
+            self.stop_key_repeat();
+        }
+    }
+
+    pub fn stop_key_repeat(&mut self) {
+        if let Some(key_repeat) = self.key_repeat.take() {
+            self.loop_handle.remove(key_repeat.timer);
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use crate::{
+    egui_window::{EguiAppState, EguiWindow},
+    error::{Result, ScapeError},
+    pointer_bindings::{BTN_LEFT, BTN_MIDDLE, BTN_RIGHT},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use egui::{Align, Context, Frame, Layout, RichText};
+use smithay::{
+    input::keyboard::{Keysym, ModifiersState},
+    reexports::wayland_server::Resource,
+    utils::{Logical, Point, Rectangle, Size},
+    wayland::seat::WaylandFocus,
+};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const OVERLAY_WIDTH: i32 = 360;
+const OVERLAY_HEIGHT: i32 = 320;
+/// Distance of the overlay to the edges of the output
+const OVERLAY_MARGIN: i32 = 16;
+/// Keystrokes shown at the same time, older ones are dropped
+const MAX_KEYSTROKES: usize = 6;
+/// Interval in which fading keystrokes are redrawn
+const FADE_INTERVAL: Duration = Duration::from_millis(50);
+/// Shown instead of the key if the focused window is masked
+const MASKED_KEY: &str = "•";
+
+/// Corner of the output the keystroke visualizer is placed in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl OverlayCorner {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "top_left" => Some(OverlayCorner::TopLeft),
+            "top_right" => Some(OverlayCorner::TopRight),
+            "bottom_left" => Some(OverlayCorner::BottomLeft),
+            "bottom_right" => Some(OverlayCorner::BottomRight),
+            _ => None,
+        }
+    }
+
+    fn is_bottom(self) -> bool {
+        matches!(self, OverlayCorner::BottomLeft | OverlayCorner::BottomRight)
+    }
+
+    fn is_right(self) -> bool {
+        matches!(self, OverlayCorner::TopRight | OverlayCorner::BottomRight)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeystrokeVisualizerConfig {
+    pub corner: OverlayCorner,
+    /// Time until a keystroke disappeared completely
+    pub fade: Duration,
+    /// Keys typed into windows with these app ids are masked, e.g. for password managers
+    pub masked_app_ids: Vec<String>,
+}
+
+impl Default for KeystrokeVisualizerConfig {
+    fn default() -> Self {
+        KeystrokeVisualizerConfig {
+            corner: OverlayCorner::default(),
+            fade: Duration::from_secs(2),
+            masked_app_ids: Vec::new(),
+        }
+    }
+}
+
+/// Shows recently pressed keys and buttons, e.g. for screencasts
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeystrokeVisualizer {
+    keystrokes: Vec<(String, Instant)>,
+    fade: Duration,
+    corner: OverlayCorner,
+}
+
+impl KeystrokeVisualizer {
+    fn push(&mut self, label: String) {
+        self.keystrokes.push((label, Instant::now()));
+        if self.keystrokes.len() > MAX_KEYSTROKES {
+            self.keystrokes.remove(0);
+        }
+    }
+
+    /// Drops keystrokes which faded out, returns whether any are left
+    fn prune(&mut self) -> bool {
+        let fade = self.fade;
+        self.keystrokes
+            .retain(|(_, pressed)| pressed.elapsed() < fade);
+        !self.keystrokes.is_empty()
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        let layout = match (self.corner.is_bottom(), self.corner.is_right()) {
+            (true, true) => Layout::bottom_up(Align::Max),
+            (true, false) => Layout::bottom_up(Align::Min),
+            (false, true) => Layout::top_down(Align::Max),
+            (false, false) => Layout::top_down(Align::Min),
+        };
+        egui::CentralPanel::default()
+            .frame(Frame::none())
+            .show(ctx, |ui| {
+                ui.with_layout(layout, |ui| {
+                    for (label, pressed) in self.keystrokes.iter().rev() {
+                        let age = pressed.elapsed().as_secs_f32() / self.fade.as_secs_f32();
+                        let opacity = (1.0 - age).clamp(0.0, 1.0);
+                        let color = ui.visuals().text_color().gamma_multiply(opacity);
+                        Frame::popup(ui.style())
+                            .multiply_with_opacity(opacity)
+                            .show(ui, |ui| {
+                                ui.label(RichText::new(label).heading().color(color))
+                            });
+                    }
+                });
+            });
+    }
+}
+
+impl From<KeystrokeVisualizer> for EguiAppState {
+    fn from(keystroke_visualizer: KeystrokeVisualizer) -> Self {
+        EguiAppState::KeystrokeVisualizer(keystroke_visualizer)
+    }
+}
+
+#[derive(Debug)]
+pub struct KeystrokeVisualizerState {
+    window: EguiWindow,
+    space_name: String,
+    fade_timer: Option<RegistrationToken>,
+}
+
+fn modifiers_label(modifiers: ModifiersState) -> String {
+    let mut label = String::new();
+    for (pressed, name) in [
+        (modifiers.logo, "Super"),
+        (modifiers.ctrl, "Ctrl"),
+        (modifiers.alt, "Alt"),
+        (modifiers.shift, "Shift"),
+    ] {
+        if pressed {
+            label.push_str(name);
+            label.push('+');
+        }
+    }
+    label
+}
+
+fn button_name(button: u32) -> String {
+    match button {
+        BTN_LEFT => "Left click".to_string(),
+        BTN_RIGHT => "Right click".to_string(),
+        BTN_MIDDLE => "Middle click".to_string(),
+        _ => format!("Button {button:#x}"),
+    }
+}
+
+impl State {
+    pub fn set_keystroke_visualizer_config(&mut self, config: KeystrokeVisualizerConfig) {
+        self.keystroke_visualizer_config = config;
+        if self.keystroke_visualizer.is_some() {
+            // Show the overlay again to apply the new position
+            self.hide_keystroke_visualizer();
+            if let Err(err) = self.show_keystroke_visualizer() {
+                warn!(%err, "Unable to show keystroke visualizer");
+            }
+        }
+    }
+
+    pub fn toggle_keystroke_visualizer(&mut self) -> Result<()> {
+        if self.keystroke_visualizer.is_some() {
+            self.hide_keystroke_visualizer();
+            Ok(())
+        } else {
+            self.show_keystroke_visualizer()
+        }
+    }
+
+    fn show_keystroke_visualizer(&mut self) -> Result<()> {
+        let space_name = self.space_under_pointer()?;
+        let space = self.space(&space_name)?;
+        let pointer_location = self.pointer_location();
+        let output = space
+            .output_under(pointer_location)
+            .next()
+            .or_else(|| space.outputs().next())
+            .ok_or_else(|| ScapeError::NoOutput(space_name.clone()))?;
+        let output_geometry = space
+            .output_geometry(output)
+            .ok_or_else(|| ScapeError::NoOutput(space_name.clone()))?;
+
+        let config = &self.keystroke_visualizer_config;
+        let size = Size::<i32, Logical>::from((OVERLAY_WIDTH, OVERLAY_HEIGHT));
+        let x = if config.corner.is_right() {
+            output_geometry.size.w - size.w - OVERLAY_MARGIN
+        } else {
+            OVERLAY_MARGIN
+        };
+        let y = if config.corner.is_bottom() {
+            output_geometry.size.h - size.h - OVERLAY_MARGIN
+        } else {
+            OVERLAY_MARGIN
+        };
+        let geometry = Rectangle::from_loc_and_size(
+            output_geometry.loc + Point::<i32, Logical>::from((x, y)),
+            size,
+        );
+
+        let window = EguiWindow::new(KeystrokeVisualizer {
+            keystrokes: Vec::new(),
+            fade: config.fade,
+            corner: config.corner,
+        });
+        self.apply_theme_to_window(&WorkspaceWindow::from(window.clone()));
+        window.position(geometry.size);
+        self.space_mut(&space_name)?.map_element(
+            WorkspaceWindow::from(window.clone()),
+            geometry.loc,
+            false,
+        );
+        info!("Keystroke visualizer shown");
+
+        self.keystroke_visualizer = Some(KeystrokeVisualizerState {
+            window,
+            space_name,
+            fade_timer: None,
+        });
+        Ok(())
+    }
+
+    fn hide_keystroke_visualizer(&mut self) {
+        let Some(keystroke_visualizer) = self.keystroke_visualizer.take() else {
+            return;
+        };
+        if let Some(fade_timer) = keystroke_visualizer.fade_timer {
+            self.loop_handle.remove(fade_timer);
+        }
+        if let Some(space) = self.spaces.get_mut(&keystroke_visualizer.space_name) {
+            space.unmap_elem(&WorkspaceWindow::from(keystroke_visualizer.window));
+        }
+        self.backend_data.schedule_render();
+        info!("Keystroke visualizer hidden");
+    }
+
+    /// Whether keys typed into the focused surface must not be shown, like on the lock screen
+    fn keystrokes_masked(&self) -> bool {
+        if self.session_lock.is_some() {
+            return true;
+        }
+        let Some(focus) = self
+            .keyboard()
+            .ok()
+            .and_then(|keyboard| keyboard.current_focus())
+        else {
+            return false;
+        };
+        // Password and PIN fields are masked in every window
+        if focus
+            .wl_surface()
+            .is_some_and(|surface| self.text_input_is_secret(&surface.id()))
+        {
+            return true;
+        }
+        let masked_app_ids = &self.keystroke_visualizer_config.masked_app_ids;
+        WorkspaceWindow::try_from(focus)
+            .is_ok_and(|window| masked_app_ids.contains(&window.app_id()))
+    }
+
+    /// Shows a pressed key in the keystroke visualizer, if it is enabled
+    pub fn visualize_key(&mut self, modifiers: ModifiersState, keysym: Keysym) {
+        if self.keystroke_visualizer.is_none() || keysym.is_modifier_key() {
+            return;
+        }
+        let shortcut = modifiers.logo || modifiers.ctrl || modifiers.alt;
+        let key = if self.keystrokes_masked() && !shortcut {
+            MASKED_KEY.to_string()
+        } else {
+            ::xkbcommon::xkb::keysym_get_name(keysym)
+        };
+        self.visualize(format!("{}{key}", modifiers_label(modifiers)));
+    }
+
+    /// Shows a pressed pointer button in the keystroke visualizer, if it is enabled
+    pub fn visualize_button(&mut self, button: u32) {
+        if self.keystroke_visualizer.is_none() {
+            return;
+        }
+        let modifiers = self
+            .keyboard()
+            .map(|keyboard| keyboard.modifier_state())
+            .unwrap_or_default();
+        self.visualize(format!(
+            "{}{}",
+            modifiers_label(modifiers),
+            button_name(button)
+        ));
+    }
+
+    fn visualize(&mut self, label: String) {
+        let Some(keystroke_visualizer) = &mut self.keystroke_visualizer else {
+            return;
+        };
+        keystroke_visualizer
+            .window
+            .with_keystroke_visualizer(|visualizer| {
+                visualizer.push(label);
+            });
+        self.backend_data.schedule_render();
+
+        if keystroke_visualizer.fade_timer.is_some() {
+            return;
+        }
+        let res =
+            self.loop_handle
+                .insert_source(Timer::from_duration(FADE_INTERVAL), |_, _, state| {
+                    state.backend_data.schedule_render();
+                    let Some(keystroke_visualizer) = &mut state.keystroke_visualizer else {
+                        return TimeoutAction::Drop;
+                    };
+                    let visible = keystroke_visualizer
+                        .window
+                        .with_keystroke_visualizer(KeystrokeVisualizer::prune)
+                        .unwrap_or(false);
+                    if visible {
+                        TimeoutAction::ToDuration(FADE_INTERVAL)
+                    } else {
+                        keystroke_visualizer.fade_timer = None;
+                        TimeoutAction::Drop
+                    }
+                });
+        match res {
+            Ok(token) => keystroke_visualizer.fade_timer = Some(token),
+            Err(err) => warn!(?err, "Unable to start keystroke visualizer fade timer"),
+        }
+    }
+}
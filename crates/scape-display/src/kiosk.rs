@@ -0,0 +1,204 @@
+use crate::{input_handler::Mods, workspace_window::WorkspaceWindow, State};
+use calloop::{
+    channel::{self, Event},
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use smithay::{
+    input::keyboard::{Keysym, ModifiersState},
+    utils::{Logical, Rectangle},
+};
+use std::{process::ExitStatus, thread, time::Duration};
+use tracing::{error, info, warn};
+
+/// Time after which an exited kiosk application is started again
+const RESPAWN_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KioskConfig {
+    pub command: String,
+    pub args: Vec<String>,
+    /// Key which leaves the kiosk mode together with `escape_mods`, all other bindings are
+    /// disabled while the kiosk mode is active
+    pub escape_key: Keysym,
+    pub escape_mods: Mods,
+}
+
+/// Single application which is shown fullscreen and restarted whenever it exits
+#[derive(Debug)]
+pub struct Kiosk {
+    config: KioskConfig,
+    /// Process of the running application
+    pid: Option<u32>,
+    exit_watch: Option<RegistrationToken>,
+    respawn_timer: Option<RegistrationToken>,
+}
+
+impl Kiosk {
+    pub fn is_escape(&self, modifiers: ModifiersState, keysym: Keysym) -> bool {
+        let mods = Mods {
+            caps_lock: false,
+            ..modifiers.into()
+        };
+        keysym == self.config.escape_key && mods == self.config.escape_mods
+    }
+}
+
+impl State {
+    /// Enters the kiosk mode. The application is started once the compositor is ready, a running
+    /// kiosk application is kept if the command did not change, e.g. when the config is reloaded.
+    pub fn start_kiosk(&mut self, config: KioskConfig) {
+        if let Some(kiosk) = &mut self.kiosk {
+            if kiosk.config.command == config.command && kiosk.config.args == config.args {
+                kiosk.config = config;
+                return;
+            }
+            self.leave_kiosk();
+        }
+
+        info!(command = config.command, "Entering kiosk mode");
+        self.kiosk = Some(Kiosk {
+            config,
+            pid: None,
+            exit_watch: None,
+            respawn_timer: None,
+        });
+        if self.ready_state.on_ready_called {
+            self.spawn_kiosk_app();
+        }
+    }
+
+    /// Leaves the kiosk mode, the application keeps running but is not restarted anymore
+    pub fn leave_kiosk(&mut self) {
+        let Some(kiosk) = self.kiosk.take() else {
+            return;
+        };
+        info!(command = kiosk.config.command, "Leaving kiosk mode");
+        if let Some(exit_watch) = kiosk.exit_watch {
+            self.loop_handle.remove(exit_watch);
+        }
+        if let Some(respawn_timer) = kiosk.respawn_timer {
+            self.loop_handle.remove(respawn_timer);
+        }
+    }
+
+    pub fn spawn_kiosk_app(&mut self) {
+        let Some(kiosk) = &self.kiosk else {
+            return;
+        };
+        let KioskConfig { command, args, .. } = kiosk.config.clone();
+        info!(command, "Starting kiosk application");
+
+        let mut child = match self.command(&command, &args).spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                error!(command, %err, "Failed to start kiosk application");
+                self.schedule_kiosk_respawn();
+                return;
+            }
+        };
+        let pid = child.id();
+
+        let (sender, receiver) = channel::channel();
+        let res = thread::Builder::new()
+            .name("kiosk app".to_string())
+            .spawn(move || {
+                if let Ok(status) = child.wait() {
+                    // The receiver is gone if the kiosk mode was left in the meantime
+                    let _ = sender.send(status);
+                }
+            });
+        if let Err(err) = res {
+            warn!(
+                ?err,
+                "Unable to watch kiosk application, it is not restarted"
+            );
+        }
+        let res = self
+            .loop_handle
+            .insert_source(receiver, move |event, _, state| {
+                if let Event::Msg(status) = event {
+                    state.kiosk_app_exited(pid, status);
+                }
+            });
+        let exit_watch = match res {
+            Ok(token) => Some(token),
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Unable to watch kiosk application, it is not restarted"
+                );
+                None
+            }
+        };
+
+        if let Some(kiosk) = &mut self.kiosk {
+            kiosk.pid = Some(pid);
+            kiosk.exit_watch = exit_watch;
+        }
+    }
+
+    fn kiosk_app_exited(&mut self, pid: u32, status: ExitStatus) {
+        let Some(kiosk) = &mut self.kiosk else {
+            return;
+        };
+        if kiosk.pid != Some(pid) {
+            return;
+        }
+        warn!(command = kiosk.config.command, %status, "Kiosk application exited");
+        kiosk.pid = None;
+        if let Some(exit_watch) = kiosk.exit_watch.take() {
+            self.loop_handle.remove(exit_watch);
+        }
+        self.schedule_kiosk_respawn();
+    }
+
+    fn schedule_kiosk_respawn(&mut self) {
+        let res =
+            self.loop_handle
+                .insert_source(Timer::from_duration(RESPAWN_DELAY), |_, _, state| {
+                    if let Some(kiosk) = &mut state.kiosk {
+                        kiosk.respawn_timer = None;
+                    }
+                    state.spawn_kiosk_app();
+                    TimeoutAction::Drop
+                });
+        match res {
+            Ok(token) => {
+                if let Some(kiosk) = &mut self.kiosk {
+                    kiosk.respawn_timer = Some(token);
+                }
+            }
+            Err(err) => warn!(?err, "Unable to restart kiosk application"),
+        }
+    }
+
+    /// Maps the window fullscreen on the first output of the space while the kiosk mode is
+    /// active. Child windows like dialogs are placed as usual.
+    pub fn place_kiosk_window(
+        &mut self,
+        space_name: &str,
+        window: &WorkspaceWindow,
+        activate: bool,
+        send_configure: bool,
+    ) -> Option<Rectangle<i32, Logical>> {
+        self.kiosk.as_ref()?;
+        let WorkspaceWindow::ApplicationWindow(application_window) = window else {
+            return None;
+        };
+        if window
+            .toplevel()
+            .is_some_and(|toplevel| toplevel.parent().is_some())
+        {
+            return None;
+        }
+
+        let space = self.spaces.get_mut(space_name)?;
+        let output = space.outputs().next()?.clone();
+        let geometry = space.output_geometry(&output)?;
+        application_window.set_fullscreen(true);
+        window.position(geometry.loc, geometry.size, geometry.size, send_configure);
+        space.map_element(window.clone(), geometry.loc, activate);
+        Some(geometry)
+    }
+}
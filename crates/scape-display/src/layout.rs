@@ -0,0 +1,390 @@
+use crate::{
+    command::{CommandEvent, OutputChange},
+    config::{ConfigLayout, ConfigOutput, ConfigOutputDevice, ConfigOutputSettings},
+    state::set_active_space,
+    State,
+};
+use anyhow::{bail, Context};
+use smithay::{
+    desktop::layer_map_for_output,
+    output::{Mode as OutputMode, Output, Scale},
+    utils::{Logical, Physical, Point, Rectangle, Size, Transform},
+};
+use std::collections::{HashMap, HashSet};
+use tracing::{info, warn};
+
+/// Smallest scale in percent the scale actions set
+const MIN_SCALE_PERCENTAGE: usize = 25;
+
+/// Output arrangement before a layout got applied, used to roll back a failed layout
+struct LayoutSnapshot {
+    spaces: HashMap<String, Vec<(Output, Point<i32, Logical>)>>,
+    outputs: Vec<(Output, Point<i32, Logical>, Scale)>,
+    disabled_outputs: HashSet<String>,
+}
+
+impl State {
+    /// Validates and applies a complete layout. If any output fails to configure, the
+    /// previous layout is restored and the error is returned.
+    pub fn apply_layout(&mut self, layout: ConfigLayout) -> anyhow::Result<()> {
+        self.validate_layout(&layout)?;
+
+        let snapshot = self.layout_snapshot();
+        if let Err(err) = self.apply_validated_layout(layout) {
+            warn!(?err, "Unable to apply layout, rolling back");
+            self.restore_layout(snapshot);
+            return Err(err);
+        }
+
+        self.start_outputs();
+        Ok(())
+    }
+
+    /// Changes mode, transform, scale or position of a single output or turns it off or on.
+    /// Unset settings are kept.
+    pub fn configure_output(&mut self, settings: ConfigOutputSettings) -> anyhow::Result<()> {
+        let Some(output) = self.outputs.get(&settings.name).cloned() else {
+            bail!("Output {} does not exist", settings.name);
+        };
+        let others_enabled = self
+            .outputs
+            .keys()
+            .any(|name| *name != settings.name && !self.disabled_outputs.contains_key(name));
+        if settings.enabled == Some(false) && !others_enabled {
+            bail!("Output {} is the only enabled output", settings.name);
+        }
+        let mode = settings
+            .mode
+            .as_deref()
+            .map(|mode| find_mode(&output, mode))
+            .transpose()?;
+        let transform = settings
+            .transform
+            .as_deref()
+            .map(transform_from_name)
+            .transpose()?;
+        let scale = match settings.scale {
+            Some(scale) if scale <= 0.0 => {
+                bail!("Output {} has an invalid scale {scale}", output.name())
+            }
+            Some(scale) if scale.fract() == 0.0 => Some(Scale::Integer(scale as i32)),
+            Some(scale) => Some(Scale::Fractional(scale)),
+            None => None,
+        };
+        let position = settings.position.map(Point::<i32, Logical>::from);
+
+        if settings.enabled == Some(true) {
+            self.enable_output(&settings.name)?;
+        }
+        if let Some(mode) = mode.filter(|mode| output.current_mode() != Some(*mode)) {
+            self.backend_data.set_output_mode(&output, mode)?;
+        }
+        output.change_current_state(mode, transform, scale, position);
+        for space in self.spaces.values_mut() {
+            if let Some(geometry) = space.output_geometry(&output) {
+                space.map_output(&output, position.unwrap_or(geometry.loc));
+            }
+        }
+        layer_map_for_output(&output).arrange();
+        if settings.enabled == Some(false) {
+            self.disable_output(&settings.name)?;
+        }
+        self.backend_data.schedule_render();
+
+        let enabled = settings.enabled;
+        info!(
+            output = output.name(),
+            ?mode,
+            ?transform,
+            ?scale,
+            ?position,
+            ?enabled,
+            "Output configured"
+        );
+        self.broadcast_event(CommandEvent::Output {
+            change: OutputChange::Configured,
+            output: ConfigOutputDevice::from(&output),
+        });
+        Ok(())
+    }
+
+    /// Sets the scale of the output in percent, e.g. 150 for 1.5. Multiples of 100 use an integer
+    /// scale. The outputs of its spaces are lined up again.
+    pub fn set_output_scale(&mut self, output: &Output, percentage: usize) {
+        let percentage = percentage.max(MIN_SCALE_PERCENTAGE);
+        let scale = if percentage % 100 == 0 {
+            Scale::Integer((percentage / 100) as i32)
+        } else {
+            Scale::Fractional(percentage as f64 / 100.0)
+        };
+        output.change_current_state(None, None, Some(scale), None);
+        info!(output = output.name(), ?scale, "Output scale changed");
+        self.output_size_changed(output);
+    }
+
+    /// Rotates or flips the output, the outputs of its spaces are lined up again
+    pub fn set_output_transform(&mut self, output: &Output, transform: Transform) {
+        output.change_current_state(None, Some(transform), None, None);
+        // The kept buffers were rendered with the previous transform
+        self.backend_data.reset_buffers(output);
+        info!(
+            output = output.name(),
+            transform = transform_name(transform),
+            "Output transform changed"
+        );
+        self.output_size_changed(output);
+    }
+
+    /// Arranges the layer surfaces of the output for its new logical size and lines up the
+    /// outputs of its spaces again, so that they do not overlap
+    fn output_size_changed(&mut self, output: &Output) {
+        layer_map_for_output(output).arrange();
+        let space_names = self
+            .spaces
+            .iter()
+            .filter(|(_, space)| space.output_geometry(output).is_some())
+            .map(|(space_name, _)| space_name.clone())
+            .collect::<Vec<_>>();
+        for space_name in space_names {
+            self.fixup_positions(&space_name);
+        }
+        self.backend_data.schedule_render();
+        self.broadcast_event(CommandEvent::Output {
+            change: OutputChange::Configured,
+            output: ConfigOutputDevice::from(output),
+        });
+    }
+
+    fn validate_layout(&self, layout: &ConfigLayout) -> anyhow::Result<()> {
+        let mut seen_outputs = HashSet::new();
+        for (space_name, config_outputs) in &layout.spaces {
+            let mut geometries: Vec<(&str, Rectangle<i32, Logical>)> = Vec::new();
+            for config_output in config_outputs {
+                let name = config_output.name.as_str();
+                if !self.outputs.contains_key(name) {
+                    bail!("Output {name} in space {space_name} does not exist");
+                }
+                if !seen_outputs.insert(name) {
+                    bail!("Output {name} is part of more than one space");
+                }
+                if config_output.scale < 1 {
+                    bail!("Output {name} has an invalid scale {}", config_output.scale);
+                }
+                if config_output.disabled {
+                    continue;
+                }
+
+                let geometry = output_geometry(config_output);
+                if let Some((other, _)) = geometries
+                    .iter()
+                    .find(|(_, other_geometry)| other_geometry.overlaps(geometry))
+                {
+                    bail!("Output {name} overlaps output {other} in space {space_name}");
+                }
+                geometries.push((name, geometry));
+            }
+        }
+
+        let all_disabled = layout
+            .spaces
+            .values()
+            .flatten()
+            .all(|config_output| config_output.disabled);
+        if !self.outputs.is_empty() && all_disabled {
+            bail!("Layout does not enable any output");
+        }
+        Ok(())
+    }
+
+    fn layout_snapshot(&self) -> LayoutSnapshot {
+        LayoutSnapshot {
+            spaces: self
+                .spaces
+                .iter()
+                .map(|(space_name, space)| {
+                    let outputs = space
+                        .outputs()
+                        .filter_map(|output| {
+                            Some((output.clone(), space.output_geometry(output)?.loc))
+                        })
+                        .collect();
+                    (space_name.clone(), outputs)
+                })
+                .collect(),
+            outputs: self
+                .outputs
+                .values()
+                .map(|output| {
+                    (
+                        output.clone(),
+                        output.current_location(),
+                        output.current_scale(),
+                    )
+                })
+                .collect(),
+            disabled_outputs: self.disabled_outputs.keys().cloned().collect(),
+        }
+    }
+
+    fn apply_validated_layout(&mut self, layout: ConfigLayout) -> anyhow::Result<()> {
+        let mut enabled_outputs = Vec::new();
+        let mut disabled_outputs = Vec::new();
+        for (space_name, config_outputs) in layout.spaces {
+            let space = self.spaces.entry(space_name.clone()).or_default();
+
+            for config_output in &config_outputs {
+                let output = &self.outputs[&config_output.name];
+                if config_output.disabled {
+                    disabled_outputs.push(config_output.name.clone());
+                    continue;
+                }
+                enabled_outputs.push(config_output.name.clone());
+
+                let position: Point<i32, Logical> = (config_output.x, config_output.y).into();
+                output.change_current_state(
+                    None,
+                    None,
+                    Some(Scale::Integer(config_output.scale)),
+                    Some(position),
+                );
+                space.map_output(output, position);
+                if config_output.default {
+                    set_active_space(output, &space_name);
+                }
+            }
+
+            // clean up no longer mapped outputs
+            for (output_name, output) in &self.outputs {
+                if !config_outputs
+                    .iter()
+                    .any(|config_output| config_output.name == *output_name)
+                {
+                    space.unmap_output(output);
+                }
+            }
+        }
+
+        for output_name in disabled_outputs {
+            self.disable_output(&output_name)?;
+        }
+        for output_name in enabled_outputs {
+            self.enable_output(&output_name)?;
+        }
+        Ok(())
+    }
+
+    fn restore_layout(&mut self, snapshot: LayoutSnapshot) {
+        // Power states first, so that windows are moved back with their outputs
+        let currently_disabled = self.disabled_outputs.keys().cloned().collect::<Vec<_>>();
+        for output_name in currently_disabled {
+            if !snapshot.disabled_outputs.contains(&output_name) {
+                if let Err(err) = self.enable_output(&output_name) {
+                    warn!(
+                        ?err,
+                        output_name, "Unable to re-enable output during rollback"
+                    );
+                }
+            }
+        }
+        for output_name in &snapshot.disabled_outputs {
+            if let Err(err) = self.disable_output(output_name) {
+                warn!(
+                    ?err,
+                    output_name, "Unable to re-disable output during rollback"
+                );
+            }
+        }
+
+        for (output, location, scale) in snapshot.outputs {
+            output.change_current_state(None, None, Some(scale), Some(location));
+        }
+        for (space_name, space) in self.spaces.iter_mut() {
+            let mapped_outputs = space.outputs().cloned().collect::<Vec<_>>();
+            for output in mapped_outputs {
+                space.unmap_output(&output);
+            }
+            for (output, location) in snapshot.spaces.get(space_name).into_iter().flatten() {
+                space.map_output(output, *location);
+            }
+        }
+        info!("Restored previous layout");
+    }
+}
+
+/// Finds the mode of the output matching `mode` in the form of `2560x1440@144`. Without a
+/// refresh rate the highest one is used.
+fn find_mode(output: &Output, mode: &str) -> anyhow::Result<OutputMode> {
+    let (size, refresh) = parse_mode(mode)?;
+    output
+        .modes()
+        .into_iter()
+        .filter(|output_mode| output_mode.size == size)
+        .min_by_key(|output_mode| match refresh {
+            Some(refresh) => (output_mode.refresh - refresh).abs(),
+            None => -output_mode.refresh,
+        })
+        .with_context(|| format!("Output {} does not support mode {mode}", output.name()))
+}
+
+/// Splits a mode in the form of `2560x1440@144` into its size and the refresh rate in mHz
+pub fn parse_mode(mode: &str) -> anyhow::Result<(Size<i32, Physical>, Option<i32>)> {
+    let (size, refresh) = match mode.split_once('@') {
+        Some((size, refresh)) => (size, Some(refresh)),
+        None => (mode, None),
+    };
+    let (width, height) = size
+        .split_once('x')
+        .with_context(|| format!("Invalid mode {mode}"))?;
+    let size = Size::from((
+        width
+            .parse()
+            .with_context(|| format!("Invalid width in mode {mode}"))?,
+        height
+            .parse()
+            .with_context(|| format!("Invalid height in mode {mode}"))?,
+    ));
+    // Refresh rates of modes are in mHz
+    let refresh = refresh
+        .map(|refresh| refresh.parse::<f64>())
+        .transpose()
+        .with_context(|| format!("Invalid refresh rate in mode {mode}"))?
+        .map(|refresh| (refresh * 1000.0).round() as i32);
+    Ok((size, refresh))
+}
+
+pub fn transform_from_name(name: &str) -> anyhow::Result<Transform> {
+    let transform = match name {
+        "normal" | "0" => Transform::Normal,
+        "90" => Transform::_90,
+        "180" => Transform::_180,
+        "270" => Transform::_270,
+        "flipped" => Transform::Flipped,
+        "flipped-90" => Transform::Flipped90,
+        "flipped-180" => Transform::Flipped180,
+        "flipped-270" => Transform::Flipped270,
+        _ => bail!("Unknown transform {name}"),
+    };
+    Ok(transform)
+}
+
+/// Name of the transform in the form `transform_from_name` accepts
+pub fn transform_name(transform: Transform) -> &'static str {
+    match transform {
+        Transform::Normal => "normal",
+        Transform::_90 => "90",
+        Transform::_180 => "180",
+        Transform::_270 => "270",
+        Transform::Flipped => "flipped",
+        Transform::Flipped90 => "flipped-90",
+        Transform::Flipped180 => "flipped-180",
+        Transform::Flipped270 => "flipped-270",
+    }
+}
+
+fn output_geometry(config_output: &ConfigOutput) -> Rectangle<i32, Logical> {
+    let scale = config_output.scale.max(1);
+    Rectangle::from_loc_and_size(
+        (config_output.x, config_output.y),
+        (config_output.width / scale, config_output.height / scale),
+    )
+}
@@ -0,0 +1,141 @@
+use crate::{
+    action::Action,
+    error::{Result, ScapeError},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{
+    desktop::space::SpaceElement,
+    utils::{Logical, Rectangle},
+};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// Placement of a window at the time the layout was saved
+#[derive(Debug, Clone)]
+struct SnapshotWindow {
+    app_id: String,
+    space: String,
+    /// Zone the window filled, floating windows are restored at their geometry instead
+    zone: Option<String>,
+    geometry: Rectangle<i32, Logical>,
+}
+
+/// Windows of a saved layout, restored by their app id
+#[derive(Debug, Clone, Default)]
+pub struct LayoutSnapshot {
+    windows: Vec<SnapshotWindow>,
+}
+
+impl State {
+    /// Remembers space and zone of all windows under the name, replacing an earlier layout
+    pub fn save_layout(&mut self, name: String) {
+        let mut windows = Vec::new();
+        for (space_name, space) in &self.spaces {
+            for window in space.elements() {
+                if !matches!(window, WorkspaceWindow::ApplicationWindow(_)) {
+                    continue;
+                }
+                let Some(location) = space.element_location(window) else {
+                    continue;
+                };
+                let geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+                let zone = self.zone_at(geometry);
+                windows.push(SnapshotWindow {
+                    app_id: window.app_id(),
+                    space: space_name.clone(),
+                    zone,
+                    geometry,
+                });
+            }
+        }
+        info!(name, windows = windows.len(), "Saving layout");
+        self.layout_snapshots
+            .insert(name, LayoutSnapshot { windows });
+    }
+
+    /// Moves the windows back to the spaces and zones of the saved layout. Windows are matched
+    /// by their app id, missing ones are spawned if `commands` has a command for their app id.
+    pub fn restore_layout(&mut self, name: &str, commands: &HashMap<String, String>) -> Result<()> {
+        let snapshot = self
+            .layout_snapshots
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ScapeError::LayoutNotFound(name.to_string()))?;
+        info!(name, "Restoring layout");
+
+        let mut windows = self
+            .spaces
+            .iter()
+            .flat_map(|(space_name, space)| {
+                space
+                    .elements()
+                    .filter(|window| matches!(window, WorkspaceWindow::ApplicationWindow(_)))
+                    .map(move |window| (window.clone(), space_name.clone()))
+            })
+            .collect::<Vec<_>>();
+        // Windows of the same app are assigned in the order they were opened
+        windows.sort_by_key(|(window, _)| window.id());
+
+        for entry in snapshot.windows {
+            if !self.spaces.contains_key(&entry.space) {
+                warn!(
+                    space = entry.space,
+                    "Space of the saved layout does not exist anymore"
+                );
+                continue;
+            }
+            let Some(index) = windows
+                .iter()
+                .position(|(window, _)| window.app_id() == entry.app_id)
+            else {
+                if let Some(command) = commands.get(&entry.app_id) {
+                    self.execute(Action::Spawn {
+                        command: command.clone(),
+                        args: Vec::new(),
+                        zone: entry.zone,
+                        env: Vec::new(),
+                        cwd: None,
+                    });
+                }
+                continue;
+            };
+            let (window, space_name) = windows.remove(index);
+            self.restore_window(&window, &space_name, &entry);
+        }
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    fn restore_window(
+        &mut self,
+        window: &WorkspaceWindow,
+        space_name: &str,
+        entry: &SnapshotWindow,
+    ) {
+        if space_name != entry.space {
+            if let Some(space) = self.spaces.get_mut(space_name) {
+                space.unmap_elem(window);
+            }
+        }
+        if let Some(zone) = entry
+            .zone
+            .as_deref()
+            .filter(|zone| self.zones.contains_key(*zone))
+        {
+            self.place_window(&entry.space, window, false, Some(zone), true);
+            return;
+        }
+
+        let Some(space) = self.spaces.get_mut(&entry.space) else {
+            return;
+        };
+        let bounds = space
+            .output_under(entry.geometry.loc.to_f64())
+            .next()
+            .and_then(|output| space.output_geometry(output))
+            .map_or(entry.geometry.size, |output_geometry| output_geometry.size);
+        window.position(entry.geometry.loc, entry.geometry.size, bounds, true);
+        space.map_element(window.clone(), entry.geometry.loc, false);
+    }
+}
@@ -1,28 +1,74 @@
 pub mod action;
 pub mod application_window;
+pub mod bar;
+pub mod clipboard;
+pub mod color_profile;
 pub mod command;
+pub mod command_line;
 pub mod composition;
 pub mod config;
 pub mod config_watcher;
 pub mod cursor;
 pub mod dbus;
+pub mod direction;
 pub mod drawing;
 pub mod egui;
 pub mod egui_window;
+pub mod error;
 pub mod focus;
+pub mod focus_history;
 pub mod grabs;
+pub mod idle;
+pub mod input_config;
 pub mod input_handler;
+pub mod key_chord;
+pub mod key_repeat;
+pub mod keystroke_visualizer;
+pub mod kiosk;
+pub mod layout;
+pub mod layout_snapshot;
+pub mod lua_timer;
+pub mod magnifier;
+pub mod modifier_indicator;
+pub mod move_mode;
+pub mod output_identifier;
+pub mod output_profile;
 pub mod pipewire;
+pub mod pointer_bindings;
+pub mod pointer_capture;
 pub mod protocols;
+pub mod recording;
 pub mod render;
+pub mod scratchpad;
+pub mod screenshot;
 pub mod shell;
+pub mod sleep;
+pub mod snap;
+pub mod socket;
+pub mod spaces;
+pub mod spawn_capture;
 pub mod ssd;
+pub mod stacking;
+pub mod startup;
 pub mod state;
+pub mod suspension;
+pub mod synthetic_input;
+pub mod theme;
+pub mod thumbnail;
 pub mod udev;
+pub mod virtual_output;
 pub mod wayland;
+pub mod window_close;
+pub mod window_placeholder;
+pub mod window_resize;
+pub mod window_rules;
+pub mod window_state;
 pub mod winit;
 pub mod workspace_window;
 pub mod xwayland;
+pub mod zone_layout;
+pub mod zone_tabs;
+pub mod zone_tree;
 
 use calloop::channel::Channel;
 use scape_shared::{Comms, DisplayMessage, GlobalArgs};
@@ -0,0 +1,51 @@
+use crate::{config::LuaCallback, State};
+use calloop::timer::{TimeoutAction, Timer};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use tracing::warn;
+
+static NEXT_TIMER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Id returned to the config, so that it can cancel the timer later
+pub fn next_timer_id() -> u64 {
+    NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+impl State {
+    /// Calls the callback after `delay`. If `repeat` is set, it is called again in the same
+    /// interval until the timer is removed.
+    pub fn add_lua_timer(&mut self, id: u64, delay: Duration, repeat: bool, callback: LuaCallback) {
+        let res =
+            self.loop_handle
+                .insert_source(Timer::from_duration(delay), move |_, _, state| {
+                    state.call_config("Timer callback", &callback, ());
+                    if repeat {
+                        TimeoutAction::ToDuration(delay)
+                    } else {
+                        state.lua_timers.remove(&id);
+                        TimeoutAction::Drop
+                    }
+                });
+        match res {
+            Ok(token) => {
+                self.lua_timers.insert(id, token);
+            }
+            Err(err) => warn!(?err, id, "Unable to start timer"),
+        }
+    }
+
+    pub fn remove_lua_timer(&mut self, id: u64) {
+        if let Some(token) = self.lua_timers.remove(&id) {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    /// Removes the timers of the config, e.g. before it is reloaded
+    pub fn clear_lua_timers(&mut self) {
+        for (_, token) in self.lua_timers.drain() {
+            self.loop_handle.remove(token);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Magnifier of an output. The output is rendered into a texture like usual and only a region
+//! around the pointer is presented, scaled up to the whole output. The region follows the pointer
+//! smoothly instead of jumping along with it.
+
+use crate::{
+    error::Result,
+    render::{AsGlowRenderer, OutputRenderElements},
+    workspace_window::WorkspaceWindowRenderElement,
+    State,
+};
+use anyhow::anyhow;
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::OutputDamageTracker,
+            element::{
+                texture::{TextureRenderBuffer, TextureRenderElement},
+                Kind, RenderElement, Wrap,
+            },
+            gles::GlesTexture,
+            Bind, ImportAll, ImportMem, Offscreen, Renderer, Unbind,
+        },
+    },
+    output::Output,
+    utils::{Logical, Physical, Point, Rectangle, Size, Transform},
+};
+use std::time::Instant;
+use tracing::info;
+
+/// Highest magnification, the region shown is this many times smaller than the output
+const MAX_MAGNIFICATION: f64 = 16.0;
+/// Fraction of the remaining distance to the pointer the region pans per second, higher values
+/// follow the pointer more closely
+const PAN_SPEED: f64 = 12.0;
+/// Distance in logical pixels below which the region stops panning
+const PAN_THRESHOLD: f64 = 0.5;
+
+/// Magnified output
+#[derive(Debug)]
+pub struct Magnifier {
+    /// Factor the region is scaled up by, always above 1
+    level: f64,
+    /// Center of the magnified region relative to the output, `None` until the first frame
+    center: Option<Point<f64, Logical>>,
+    /// Whether the region has not reached the pointer yet, further frames are needed then
+    panning: bool,
+    last_frame: Instant,
+    /// Texture the output is rendered into, recreated when the output changes its size
+    buffer: Option<MagnifierBuffer>,
+}
+
+#[derive(Debug)]
+struct MagnifierBuffer {
+    size: Size<i32, Physical>,
+    scale: f64,
+    texture: TextureRenderBuffer<GlesTexture>,
+    damage_tracker: OutputDamageTracker,
+    /// The texture keeps its content between frames, only the first one is rendered fully
+    age: usize,
+}
+
+impl Magnifier {
+    fn new(level: f64) -> Self {
+        Self {
+            level,
+            center: None,
+            panning: false,
+            last_frame: Instant::now(),
+            buffer: None,
+        }
+    }
+
+    pub fn is_panning(&self) -> bool {
+        self.panning
+    }
+
+    /// Moves the magnified region towards the pointer. The region is kept within the output, so
+    /// the pointer is only centered away from the edges.
+    fn pan(&mut self, output_geometry: Rectangle<i32, Logical>, pointer: Point<f64, Logical>) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_frame).as_secs_f64();
+        self.last_frame = now;
+
+        let size = output_geometry.size.to_f64();
+        let (half_w, half_h) = (size.w / self.level / 2.0, size.h / self.level / 2.0);
+        let pointer = if output_geometry.to_f64().contains(pointer) {
+            pointer - output_geometry.loc.to_f64()
+        } else {
+            self.center
+                .unwrap_or(pointer - output_geometry.loc.to_f64())
+        };
+        let target = Point::from((
+            pointer.x.clamp(half_w, size.w - half_w),
+            pointer.y.clamp(half_h, size.h - half_h),
+        ));
+        let Some(center) = self.center else {
+            self.center = Some(target);
+            self.panning = false;
+            return;
+        };
+
+        let offset = target - center;
+        if offset.x.abs() < PAN_THRESHOLD && offset.y.abs() < PAN_THRESHOLD {
+            self.center = Some(target);
+            self.panning = false;
+            return;
+        }
+        let progress = 1.0 - (-elapsed * PAN_SPEED).exp();
+        self.center = Some(center + offset.upscale(progress));
+        self.panning = true;
+    }
+
+    /// Renders the elements of the output into the texture and returns the element presenting
+    /// the magnified region in their place
+    pub fn magnify<R>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+        output_geometry: Rectangle<i32, Logical>,
+        pointer: Point<f64, Logical>,
+        elements: &[OutputRenderElements<R>],
+        clear_color: [f32; 4],
+    ) -> anyhow::Result<OutputRenderElements<R>>
+    where
+        R: Renderer
+            + ImportAll
+            + ImportMem
+            + AsGlowRenderer
+            + Offscreen<GlesTexture>
+            + Bind<GlesTexture>
+            + Unbind,
+        <R as Renderer>::TextureId: Clone + 'static,
+        WorkspaceWindowRenderElement<R>: RenderElement<R>,
+        OutputRenderElements<R>: RenderElement<R>,
+    {
+        self.pan(output_geometry, pointer);
+        let center = self.center.unwrap_or_default();
+
+        let scale = output.current_scale().fractional_scale();
+        let size = output
+            .current_mode()
+            .map(|mode| output.current_transform().transform_size(mode.size))
+            .ok_or_else(|| anyhow!("Output {} has no mode", output.name()))?;
+        let current = self
+            .buffer
+            .as_ref()
+            .is_some_and(|buffer| buffer.size == size && buffer.scale == scale);
+        if !current {
+            let texture = renderer
+                .create_buffer(Fourcc::Abgr8888, (size.w, size.h).into())
+                .map_err(|err| anyhow!("Unable to create magnifier texture: {err}"))?;
+            self.buffer = Some(MagnifierBuffer {
+                size,
+                scale,
+                // Rendering into a texture flips it vertically
+                texture: TextureRenderBuffer::from_texture(
+                    renderer.glow_renderer(),
+                    texture,
+                    1,
+                    Transform::Flipped180,
+                    None,
+                ),
+                damage_tracker: OutputDamageTracker::new(size, scale, Transform::Normal),
+                age: 0,
+            });
+        }
+        let buffer = self.buffer.as_mut().unwrap();
+
+        let texture_size = Size::<i32, Logical>::from((size.w, size.h));
+        let damage_tracker = &mut buffer.damage_tracker;
+        let age = buffer.age;
+        buffer.texture.render().draw(|texture| {
+            renderer
+                .bind(texture.clone())
+                .map_err(|err| anyhow!("Unable to bind magnifier texture: {err}"))?;
+            let damage = damage_tracker
+                .render_output(renderer, age, elements, clear_color)
+                .map_err(|err| anyhow!("Unable to render magnified output: {err}"))?
+                .damage
+                .cloned()
+                .unwrap_or_default();
+            renderer
+                .unbind()
+                .map_err(|err| anyhow!("Unable to unbind magnifier texture: {err}"))?;
+            anyhow::Ok(
+                damage
+                    .into_iter()
+                    .map(|rect| {
+                        Rectangle::<i32, Logical>::from_loc_and_size(
+                            (rect.loc.x, rect.loc.y),
+                            (rect.size.w, rect.size.h),
+                        )
+                        .to_buffer(1, Transform::Flipped180, &texture_size)
+                    })
+                    .collect(),
+            )
+        })?;
+        buffer.age = 1;
+
+        // The texture has one pixel per physical pixel of the output
+        let region_size = Size::<f64, Logical>::from((
+            output_geometry.size.w as f64 / self.level,
+            output_geometry.size.h as f64 / self.level,
+        ));
+        let region = Rectangle::from_loc_and_size(
+            (center - Point::from((region_size.w / 2.0, region_size.h / 2.0))).upscale(scale),
+            region_size.upscale(scale),
+        );
+        let element = TextureRenderElement::from_texture_render_buffer(
+            (0.0, 0.0),
+            &buffer.texture,
+            None,
+            Some(region),
+            Some(output_geometry.size),
+            Kind::Unspecified,
+        );
+        // Drawn through the GLES renderer on every backend, like egui windows
+        Ok(OutputRenderElements::Window(Wrap::from(
+            WorkspaceWindowRenderElement::from(element),
+        )))
+    }
+}
+
+impl State {
+    /// Changes the magnification of the output under the pointer by `delta`, the magnifier is
+    /// turned off once it goes down to 1
+    pub fn zoom(&mut self, delta: f64) -> Result<()> {
+        let output = self.output_or_under_pointer(None)?;
+        let current = self
+            .magnifiers
+            .get(&output.name())
+            .map_or(1.0, |magnifier| magnifier.level);
+        let level = (current + delta).clamp(1.0, MAX_MAGNIFICATION);
+        if level == current {
+            return Ok(());
+        }
+        info!(output = output.name(), level, "Changing magnification");
+        if level <= 1.0 {
+            self.magnifiers.remove(&output.name());
+        } else {
+            self.magnifiers
+                .entry(output.name())
+                .or_insert_with(|| Magnifier::new(level))
+                .level = level;
+        }
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{
+    egui_window::{EguiAppState, EguiWindow},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use egui::{Align2, Area, Context, Frame, Id, RichText};
+use smithay::{
+    input::keyboard::ModifiersState,
+    utils::{Logical, Point, Rectangle, Size},
+};
+use std::time::Duration;
+use tracing::warn;
+
+const INDICATOR_WIDTH: i32 = 360;
+const INDICATOR_HEIGHT: i32 = 140;
+/// Distance of the indicator to the bottom edge of the output
+const INDICATOR_MARGIN: i32 = 64;
+/// Time the caps lock and num lock OSD is shown
+const OSD_DURATION: Duration = Duration::from_millis(1500);
+
+/// Masks of the real modifiers, xkbcommon keeps their indices fixed
+const SHIFT_MASK: u32 = 1 << 0;
+const CTRL_MASK: u32 = 1 << 2;
+const ALT_MASK: u32 = 1 << 3;
+const LOGO_MASK: u32 = 1 << 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifierIndicatorConfig {
+    /// Show an OSD when caps lock or num lock is toggled
+    pub lock_keys: bool,
+    /// Show an indicator while modifiers are latched or locked, e.g. by sticky keys
+    pub sticky_modifiers: bool,
+}
+
+impl Default for ModifierIndicatorConfig {
+    fn default() -> Self {
+        ModifierIndicatorConfig {
+            lock_keys: true,
+            sticky_modifiers: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifierIndicator {
+    osd: Option<String>,
+    sticky: Option<String>,
+}
+
+impl ModifierIndicator {
+    pub fn show(&mut self, ctx: &Context) {
+        Area::new(Id::new("modifier_indicator"))
+            .anchor(Align2::CENTER_BOTTOM, [0.0, 0.0])
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        if let Some(osd) = &self.osd {
+                            ui.label(RichText::new(osd).heading().strong());
+                        }
+                        if let Some(sticky) = &self.sticky {
+                            ui.label(RichText::new(sticky).heading());
+                        }
+                    });
+                });
+            });
+    }
+}
+
+impl From<ModifierIndicator> for EguiAppState {
+    fn from(modifier_indicator: ModifierIndicator) -> Self {
+        EguiAppState::ModifierIndicator(modifier_indicator)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ModifierIndicatorState {
+    /// Window and the space it is mapped in, only exists while something is shown
+    window: Option<(EguiWindow, String)>,
+    osd: Option<String>,
+    sticky: Option<String>,
+    osd_timer: Option<RegistrationToken>,
+    /// Modifiers of the previous key event to detect toggled lock keys
+    modifiers: Option<ModifiersState>,
+}
+
+/// Names of the modifiers which are latched or locked, `None` if there are none
+fn sticky_label(modifiers: &ModifiersState) -> Option<String> {
+    let latched = modifiers.serialized.latched;
+    let locked = modifiers.serialized.locked;
+    let names = [
+        (LOGO_MASK, "Super"),
+        (CTRL_MASK, "Ctrl"),
+        (ALT_MASK, "Alt"),
+        (SHIFT_MASK, "Shift"),
+    ]
+    .into_iter()
+    .filter_map(|(mask, name)| {
+        if locked & mask != 0 {
+            Some(format!("{name} (locked)"))
+        } else if latched & mask != 0 {
+            Some(name.to_string())
+        } else {
+            None
+        }
+    })
+    .collect::<Vec<_>>();
+    (!names.is_empty()).then(|| format!("Sticky: {}", names.join(" + ")))
+}
+
+fn lock_key_label(name: &str, on: bool) -> String {
+    format!("{name} {}", if on { "on" } else { "off" })
+}
+
+impl State {
+    pub fn set_modifier_indicator_config(&mut self, config: ModifierIndicatorConfig) {
+        if !config.lock_keys {
+            self.modifier_indicator.osd = None;
+        }
+        if !config.sticky_modifiers {
+            self.modifier_indicator.sticky = None;
+        }
+        self.modifier_indicator_config = config;
+        self.refresh_modifier_indicator();
+    }
+
+    /// Shows toggled lock keys and sticky modifiers, called after every key event
+    pub fn update_modifier_indicator(&mut self, modifiers: ModifiersState) {
+        let previous = self.modifier_indicator.modifiers.replace(modifiers);
+        let mut changed = false;
+
+        // The first key event only tells the initial state of the lock keys
+        if let Some(previous) = previous.filter(|_| self.modifier_indicator_config.lock_keys) {
+            let toggled = [
+                ("Caps Lock", previous.caps_lock, modifiers.caps_lock),
+                ("Num Lock", previous.num_lock, modifiers.num_lock),
+            ]
+            .into_iter()
+            .filter(|(_, before, after)| before != after)
+            .map(|(name, _, on)| lock_key_label(name, on))
+            .collect::<Vec<_>>();
+            if !toggled.is_empty() {
+                self.modifier_indicator.osd = Some(toggled.join(", "));
+                self.restart_osd_timer();
+                changed = true;
+            }
+        }
+
+        if self.modifier_indicator_config.sticky_modifiers {
+            let sticky = sticky_label(&modifiers);
+            if self.modifier_indicator.sticky != sticky {
+                self.modifier_indicator.sticky = sticky;
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.refresh_modifier_indicator();
+        }
+    }
+
+    fn restart_osd_timer(&mut self) {
+        if let Some(osd_timer) = self.modifier_indicator.osd_timer.take() {
+            self.loop_handle.remove(osd_timer);
+        }
+        let res =
+            self.loop_handle
+                .insert_source(Timer::from_duration(OSD_DURATION), |_, _, state| {
+                    state.modifier_indicator.osd_timer = None;
+                    state.modifier_indicator.osd = None;
+                    state.refresh_modifier_indicator();
+                    TimeoutAction::Drop
+                });
+        match res {
+            Ok(token) => self.modifier_indicator.osd_timer = Some(token),
+            Err(err) => warn!(?err, "Unable to start lock key OSD timer"),
+        }
+    }
+
+    /// Maps, updates or unmaps the indicator window depending on what has to be shown
+    fn refresh_modifier_indicator(&mut self) {
+        let content = ModifierIndicator {
+            osd: self.modifier_indicator.osd.clone(),
+            sticky: self.modifier_indicator.sticky.clone(),
+        };
+        let visible = content.osd.is_some() || content.sticky.is_some();
+
+        match self.modifier_indicator.window.take() {
+            Some((window, space_name)) if visible => {
+                window.with_modifier_indicator(|indicator| *indicator = content);
+                self.modifier_indicator.window = Some((window, space_name));
+            }
+            Some((window, space_name)) => {
+                if let Some(space) = self.spaces.get_mut(&space_name) {
+                    space.unmap_elem(&WorkspaceWindow::from(window));
+                }
+            }
+            None if visible => self.show_modifier_indicator(content),
+            None => {}
+        }
+        self.backend_data.schedule_render();
+    }
+
+    fn show_modifier_indicator(&mut self, content: ModifierIndicator) {
+        let Ok(space_name) = self.space_under_pointer() else {
+            return;
+        };
+        let space = &self.spaces[&space_name];
+        let pointer_location = self.pointer_location();
+        let Some(output_geometry) = space
+            .output_under(pointer_location)
+            .next()
+            .or_else(|| space.outputs().next())
+            .and_then(|output| space.output_geometry(output))
+        else {
+            return;
+        };
+
+        let size = Size::<i32, Logical>::from((INDICATOR_WIDTH, INDICATOR_HEIGHT));
+        let geometry = Rectangle::from_loc_and_size(
+            output_geometry.loc
+                + Point::<i32, Logical>::from((
+                    (output_geometry.size.w - size.w) / 2,
+                    output_geometry.size.h - size.h - INDICATOR_MARGIN,
+                )),
+            size,
+        );
+
+        let window = EguiWindow::new(content);
+        self.apply_theme_to_window(&WorkspaceWindow::from(window.clone()));
+        window.position(geometry.size);
+        if let Some(space) = self.spaces.get_mut(&space_name) {
+            space.map_element(WorkspaceWindow::from(window.clone()), geometry.loc, false);
+        }
+        self.modifier_indicator.window = Some((window, space_name));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    action::Action,
+    error::{Result, ScapeError},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{
+    input::keyboard::{Keysym, ModifiersState},
+    utils::{Logical, Point},
+};
+use tracing::info;
+
+/// Step sizes and grid used while moving a window with the keyboard
+#[derive(Debug, Clone, Copy)]
+pub struct MoveModeConfig {
+    /// Distance a window moves per key press
+    pub step: i32,
+    /// Distance a window moves per key press while shift is held
+    pub large_step: i32,
+    /// Size of the grid windows snap to, values below 2 disable snapping
+    pub grid: i32,
+}
+
+impl Default for MoveModeConfig {
+    fn default() -> Self {
+        Self {
+            step: 16,
+            large_step: 128,
+            grid: 16,
+        }
+    }
+}
+
+/// The window which is currently moved with the keyboard
+#[derive(Debug)]
+pub struct MoveMode {
+    window: WorkspaceWindow,
+    space_name: String,
+}
+
+impl State {
+    pub fn toggle_move_mode(&mut self) -> Result<()> {
+        if self.move_mode.take().is_some() {
+            info!("Leaving move mode");
+            return Ok(());
+        }
+
+        let focus = self.keyboard()?.current_focus();
+        let window = focus
+            .and_then(|focus| WorkspaceWindow::try_from(focus).ok())
+            .filter(|window| matches!(window, WorkspaceWindow::ApplicationWindow(_)))
+            .ok_or_else(|| ScapeError::WindowNotMapped("focused window".to_string()))?;
+        let space_name = self
+            .space_of_window(&window)
+            .ok_or_else(|| ScapeError::WindowNotMapped(window.app_id()))?;
+
+        info!(app_id = window.app_id(), "Entering move mode");
+        self.move_mode = Some(MoveMode { window, space_name });
+        Ok(())
+    }
+
+    /// Translates key presses into move mode actions. While the move mode is active all keys are
+    /// consumed, arrows move the window and escape or return leave the mode.
+    pub fn move_mode_key(&self, modifiers: ModifiersState, keysym: Keysym) -> Option<Action> {
+        let move_mode = self.move_mode.as_ref()?;
+        let window_mapped = self
+            .spaces
+            .get(&move_mode.space_name)
+            .is_some_and(|space| space.elements().any(|window| *window == move_mode.window));
+        if !window_mapped {
+            // Leave the move mode without swallowing the key once the window is gone
+            return (keysym == Keysym::Escape).then_some(Action::ToggleMoveMode);
+        }
+
+        let step = if modifiers.shift {
+            self.move_mode_config.large_step
+        } else {
+            self.move_mode_config.step
+        };
+        let action = match keysym {
+            Keysym::Left => Action::MoveModeStep { x: -step, y: 0 },
+            Keysym::Right => Action::MoveModeStep { x: step, y: 0 },
+            Keysym::Up => Action::MoveModeStep { x: 0, y: -step },
+            Keysym::Down => Action::MoveModeStep { x: 0, y: step },
+            Keysym::Escape | Keysym::Return => Action::ToggleMoveMode,
+            _ => Action::None,
+        };
+        Some(action)
+    }
+
+    pub fn move_mode_step(&mut self, delta: Point<i32, Logical>) -> Result<()> {
+        let Some(MoveMode { window, space_name }) = &self.move_mode else {
+            return Ok(());
+        };
+        let (window, space_name) = (window.clone(), space_name.clone());
+        let grid = self.move_mode_config.grid;
+
+        let space = self.space_mut(&space_name)?;
+        let Some(location) = space.element_location(&window) else {
+            self.move_mode = None;
+            return Err(ScapeError::WindowNotMapped(window.app_id()));
+        };
+        let target = Point::from((
+            snap_to_grid(location.x, delta.x, grid),
+            snap_to_grid(location.y, delta.y, grid),
+        ));
+        space.map_element(window, target, true);
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+}
+
+/// Moves `position` by `delta` and snaps the result to the grid, while always moving at least
+/// one grid cell into the direction of `delta`
+fn snap_to_grid(position: i32, delta: i32, grid: i32) -> i32 {
+    let target = position + delta;
+    if grid < 2 || delta == 0 {
+        return target;
+    }
+
+    let snapped = (target as f64 / grid as f64).round() as i32 * grid;
+    if delta > 0 && snapped <= position {
+        snapped + grid
+    } else if delta < 0 && snapped >= position {
+        snapped - grid
+    } else {
+        snapped
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    egui_window::{EguiAppState, EguiWindow},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use egui::{Align2, Area, Context, Frame, Id, RichText, Stroke};
+use smithay::output::Output;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Time the outputs are identified for
+const IDENTIFY_DURATION: Duration = Duration::from_secs(5);
+/// Distance between the lines of the test pattern grid in logical pixels
+const GRID_SPACING: f32 = 100.0;
+const NAME_SIZE: f32 = 96.0;
+
+/// Shows name, mode and scale of an output, optionally on top of a test pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputIdentifier {
+    name: String,
+    description: String,
+    test_pattern: bool,
+}
+
+impl OutputIdentifier {
+    fn new(output: &Output, test_pattern: bool) -> Self {
+        let mode = output
+            .current_mode()
+            .map(|mode| {
+                format!(
+                    "{}x{} @ {:.2} Hz",
+                    mode.size.w,
+                    mode.size.h,
+                    mode.refresh as f64 / 1000.0
+                )
+            })
+            .unwrap_or_else(|| "Unknown mode".to_string());
+        let description = format!(
+            "{mode}, scale {}",
+            output.current_scale().fractional_scale()
+        );
+        OutputIdentifier {
+            name: output.name(),
+            description,
+            test_pattern,
+        }
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        if self.test_pattern {
+            egui::CentralPanel::default()
+                .frame(Frame::none())
+                .show(ctx, |ui| {
+                    let rect = ctx.screen_rect();
+                    let visuals = ui.visuals();
+                    let painter = ui.painter();
+                    let stroke = Stroke::new(1.0, visuals.weak_text_color());
+                    let accent = Stroke::new(2.0, visuals.selection.bg_fill);
+
+                    painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+                    let mut x = rect.left();
+                    while x <= rect.right() {
+                        painter.vline(x, rect.y_range(), stroke);
+                        x += GRID_SPACING;
+                    }
+                    let mut y = rect.top();
+                    while y <= rect.bottom() {
+                        painter.hline(rect.x_range(), y, stroke);
+                        y += GRID_SPACING;
+                    }
+                    // Edges show overscan, the diagonals and the circle a wrong aspect ratio
+                    painter.rect_stroke(rect.shrink(1.0), 0.0, accent);
+                    painter.line_segment([rect.left_top(), rect.right_bottom()], accent);
+                    painter.line_segment([rect.right_top(), rect.left_bottom()], accent);
+                    painter.circle_stroke(rect.center(), rect.height() / 3.0, accent);
+                });
+        }
+
+        Area::new(Id::new("output_identifier"))
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.label(RichText::new(&self.name).size(NAME_SIZE).strong());
+                        ui.label(RichText::new(&self.description).heading());
+                    });
+                });
+            });
+    }
+}
+
+impl From<OutputIdentifier> for EguiAppState {
+    fn from(output_identifier: OutputIdentifier) -> Self {
+        EguiAppState::OutputIdentifier(output_identifier)
+    }
+}
+
+#[derive(Debug)]
+pub struct OutputIdentifierState {
+    windows: Vec<(String, EguiWindow)>,
+    test_pattern: bool,
+    hide_timer: Option<RegistrationToken>,
+}
+
+impl State {
+    /// Shows name, mode and scale on every output for a few seconds
+    pub fn identify_outputs(&mut self) {
+        if self
+            .output_identifiers
+            .as_ref()
+            .is_some_and(|identifiers| identifiers.test_pattern)
+        {
+            // The test pattern shows the same information already
+            return;
+        }
+        self.hide_output_identifiers();
+        self.show_output_identifiers(false);
+
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(IDENTIFY_DURATION),
+            |_, _, state| {
+                if let Some(identifiers) = &mut state.output_identifiers {
+                    identifiers.hide_timer = None;
+                }
+                state.hide_output_identifiers();
+                TimeoutAction::Drop
+            },
+        );
+        match res {
+            Ok(token) => {
+                if let Some(identifiers) = &mut self.output_identifiers {
+                    identifiers.hide_timer = Some(token);
+                }
+            }
+            Err(err) => warn!(?err, "Unable to start output identifier timer"),
+        }
+    }
+
+    /// Shows a test pattern with alignment grid on every output until toggled again
+    pub fn toggle_test_pattern(&mut self) {
+        let test_pattern = self
+            .output_identifiers
+            .as_ref()
+            .is_some_and(|identifiers| identifiers.test_pattern);
+        self.hide_output_identifiers();
+        if !test_pattern {
+            self.show_output_identifiers(true);
+        }
+    }
+
+    fn show_output_identifiers(&mut self, test_pattern: bool) {
+        let mut windows = Vec::new();
+        for (space_name, space) in self.spaces.iter_mut() {
+            let outputs = space.outputs().cloned().collect::<Vec<_>>();
+            for output in outputs {
+                let Some(geometry) = space.output_geometry(&output) else {
+                    continue;
+                };
+                let window = EguiWindow::new(OutputIdentifier::new(&output, test_pattern));
+                window.position(geometry.size);
+                space.map_element(WorkspaceWindow::from(window.clone()), geometry.loc, false);
+                windows.push((space_name.clone(), window));
+            }
+        }
+        for (_, window) in &windows {
+            self.apply_theme_to_window(&WorkspaceWindow::from(window.clone()));
+        }
+        info!(outputs = windows.len(), test_pattern, "Identifying outputs");
+
+        self.output_identifiers = Some(OutputIdentifierState {
+            windows,
+            test_pattern,
+            hide_timer: None,
+        });
+        self.backend_data.schedule_render();
+    }
+
+    fn hide_output_identifiers(&mut self) {
+        let Some(identifiers) = self.output_identifiers.take() else {
+            return;
+        };
+        if let Some(hide_timer) = identifiers.hide_timer {
+            self.loop_handle.remove(hide_timer);
+        }
+        for (space_name, window) in identifiers.windows {
+            if let Some(space) = self.spaces.get_mut(&space_name) {
+                space.unmap_elem(&WorkspaceWindow::from(window));
+            }
+        }
+        self.backend_data.schedule_render();
+    }
+}
@@ -0,0 +1,279 @@
+use crate::{
+    config::{ConfigLayout, ConfigOutput, ConfigOutputSettings, LuaCallback},
+    udev::OutputSerial,
+    State,
+};
+use smithay::{
+    output::Output,
+    utils::{Logical, Size},
+};
+use std::collections::HashMap;
+use tracing::info;
+
+/// Matches any output
+const ANY_OUTPUT: &str = "*";
+/// Space the outputs of a profile are mapped into if it does not name one
+pub const DEFAULT_PROFILE_SPACE: &str = "main";
+
+/// Settings of a single output in a profile
+#[derive(Debug, Clone)]
+pub struct ProfileOutput {
+    /// Connector name like `DP-1`, the description `make model serial` or `*` for any output
+    pub criteria: String,
+    /// Mode in the form of `2560x1440@144`, the current mode is kept otherwise
+    pub mode: Option<String>,
+    pub transform: Option<String>,
+    pub scale: i32,
+    /// Outputs without a position are placed right of the ones before them
+    pub position: Option<(i32, i32)>,
+    pub disabled: bool,
+}
+
+impl ProfileOutput {
+    fn matches(&self, output: &Output) -> bool {
+        self.criteria == ANY_OUTPUT
+            || self.criteria == output.name()
+            || self.criteria == output_description(output)
+    }
+}
+
+/// Layout which is applied automatically while exactly its outputs are connected
+#[derive(Debug, Clone)]
+pub struct OutputProfile {
+    pub name: String,
+    pub space: String,
+    pub outputs: Vec<ProfileOutput>,
+    /// Called with the outputs once the profile was applied, e.g. to set up zones
+    pub callback: Option<LuaCallback>,
+}
+
+impl OutputProfile {
+    /// Assigns a different connected output to every output of the profile, in the order of the
+    /// profile. Connected outputs which are left over prevent the profile from matching.
+    fn assign(&self, outputs: &[Output]) -> Option<Vec<Output>> {
+        if self.outputs.len() != outputs.len() {
+            return None;
+        }
+        let mut assigned = Vec::with_capacity(outputs.len());
+        self.assign_remaining(outputs, &mut assigned)
+            .then_some(assigned)
+    }
+
+    fn assign_remaining(&self, outputs: &[Output], assigned: &mut Vec<Output>) -> bool {
+        let Some(profile_output) = self.outputs.get(assigned.len()) else {
+            return true;
+        };
+        for output in outputs {
+            if assigned.contains(output) || !profile_output.matches(output) {
+                continue;
+            }
+            assigned.push(output.clone());
+            // A wildcard may have taken the output a later, more specific entry needs
+            if self.assign_remaining(outputs, assigned) {
+                return true;
+            }
+            assigned.pop();
+        }
+        false
+    }
+}
+
+/// Identifies an output independent of the connector, in the same form as kanshi does
+pub fn output_description(output: &Output) -> String {
+    let properties = output.physical_properties();
+    let mut description = format!("{} {}", properties.make, properties.model);
+    if let Some(OutputSerial(serial)) = output.user_data().get::<OutputSerial>() {
+        description.push(' ');
+        description.push_str(serial);
+    }
+    description
+}
+
+impl State {
+    /// Adds the profile, replacing a profile with the same name
+    pub fn add_output_profile(&mut self, profile: OutputProfile) {
+        self.output_profiles
+            .retain(|other| other.name != profile.name);
+        self.output_profiles.push(profile);
+    }
+
+    /// Applies the first profile which matches the connected outputs. Returns whether a profile
+    /// matched, even if it failed to apply.
+    pub fn apply_output_profiles(&mut self) -> bool {
+        let mut outputs = self.outputs.values().cloned().collect::<Vec<_>>();
+        outputs.sort_by_key(|output| output.name());
+        let Some((profile, assigned)) = self.output_profiles.iter().find_map(|profile| {
+            let assigned = profile.assign(&outputs)?;
+            Some((profile.clone(), assigned))
+        }) else {
+            return false;
+        };
+
+        info!(profile = profile.name, "Applying output profile");
+        match self.apply_output_profile(&profile, &assigned) {
+            Ok(config_outputs) => {
+                if let Some(callback) = &profile.callback {
+                    self.call_config("Output profile callback", callback, config_outputs);
+                }
+            }
+            Err(err) => self.config.report_error(
+                &format!("Output profile {}", profile.name),
+                format!("{err:#}"),
+            ),
+        }
+        true
+    }
+
+    fn apply_output_profile(
+        &mut self,
+        profile: &OutputProfile,
+        outputs: &[Output],
+    ) -> anyhow::Result<Vec<ConfigOutput>> {
+        let mut config_outputs = Vec::new();
+        let mut next_x = 0;
+        for (profile_output, output) in profile.outputs.iter().zip(outputs) {
+            if profile_output.mode.is_some() || profile_output.transform.is_some() {
+                self.configure_output(ConfigOutputSettings {
+                    name: output.name(),
+                    mode: profile_output.mode.clone(),
+                    transform: profile_output.transform.clone(),
+                    ..Default::default()
+                })?;
+            }
+
+            let mut config_output = ConfigOutput::from(output);
+            // Rotated outputs are higher than wide
+            let size = Size::<i32, Logical>::from((config_output.width, config_output.height));
+            let size = output.current_transform().transform_size(size);
+            let (x, y) = profile_output.position.unwrap_or((next_x, 0));
+            config_output.x = x;
+            config_output.y = y;
+            config_output.width = size.w;
+            config_output.height = size.h;
+            config_output.scale = profile_output.scale;
+            config_output.disabled = profile_output.disabled;
+            if !config_output.disabled {
+                next_x = next_x.max(x + size.w / profile_output.scale.max(1));
+            }
+            config_outputs.push(config_output);
+        }
+
+        self.apply_layout(ConfigLayout {
+            spaces: HashMap::from([(profile.space.clone(), config_outputs.clone())]),
+        })?;
+        Ok(config_outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smithay::output::{PhysicalProperties, Subpixel};
+
+    fn output(name: &str, model: &str, serial: Option<&str>) -> Output {
+        let output = Output::new(
+            name.to_string(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Dell".to_string(),
+                model: model.to_string(),
+            },
+        );
+        if let Some(serial) = serial {
+            output
+                .user_data()
+                .insert_if_missing_threadsafe(|| OutputSerial(serial.to_string()));
+        }
+        output
+    }
+
+    fn profile(criteria: &[&str]) -> OutputProfile {
+        OutputProfile {
+            name: "test".to_string(),
+            space: DEFAULT_PROFILE_SPACE.to_string(),
+            outputs: criteria
+                .iter()
+                .map(|criteria| ProfileOutput {
+                    criteria: criteria.to_string(),
+                    mode: None,
+                    transform: None,
+                    scale: 1,
+                    position: None,
+                    disabled: false,
+                })
+                .collect(),
+            callback: None,
+        }
+    }
+
+    fn names(outputs: Option<Vec<Output>>) -> Option<Vec<String>> {
+        outputs.map(|outputs| outputs.iter().map(Output::name).collect())
+    }
+
+    #[test]
+    fn exact_match_assigns_in_profile_order() {
+        let outputs = [
+            output("DP-1", "U2720Q", None),
+            output("eDP-1", "Panel", None),
+        ];
+        let assigned = profile(&["eDP-1", "DP-1"]).assign(&outputs);
+        assert_eq!(
+            names(assigned),
+            Some(vec!["eDP-1".to_string(), "DP-1".to_string()])
+        );
+    }
+
+    #[test]
+    fn description_matches_on_any_connector() {
+        let outputs = [
+            output("DP-1", "U2720Q", Some("ABC123")),
+            output("DP-2", "U2720Q", Some("XYZ789")),
+        ];
+        let assigned = profile(&["Dell U2720Q XYZ789", "Dell U2720Q ABC123"]).assign(&outputs);
+        assert_eq!(
+            names(assigned),
+            Some(vec!["DP-2".to_string(), "DP-1".to_string()])
+        );
+    }
+
+    #[test]
+    fn wildcard_leaves_outputs_to_specific_entries() {
+        let outputs = [
+            output("DP-1", "U2720Q", None),
+            output("eDP-1", "Panel", None),
+        ];
+        // The wildcard would take DP-1 first, which the second entry needs
+        let assigned = profile(&["*", "DP-1"]).assign(&outputs);
+        assert_eq!(
+            names(assigned),
+            Some(vec!["eDP-1".to_string(), "DP-1".to_string()])
+        );
+    }
+
+    #[test]
+    fn superset_does_not_match() {
+        let outputs = [
+            output("DP-1", "U2720Q", None),
+            output("DP-2", "U2720Q", None),
+            output("eDP-1", "Panel", None),
+        ];
+        // More outputs are connected than the profile has
+        assert!(profile(&["eDP-1", "DP-1"]).assign(&outputs).is_none());
+        // The profile has more outputs than are connected
+        let profile = profile(&["eDP-1", "DP-1", "DP-2", "HDMI-A-1"]);
+        assert!(profile.assign(&outputs).is_none());
+    }
+
+    #[test]
+    fn no_match() {
+        let outputs = [
+            output("DP-1", "U2720Q", None),
+            output("eDP-1", "Panel", None),
+        ];
+        assert!(profile(&["eDP-1", "HDMI-A-1"]).assign(&outputs).is_none());
+        // Two entries can not share a single output
+        assert!(profile(&["DP-1", "DP-1"]).assign(&outputs).is_none());
+        assert!(profile(&["eDP-1"]).assign(&[]).is_none());
+    }
+}
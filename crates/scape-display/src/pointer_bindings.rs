@@ -0,0 +1,161 @@
+use crate::{
+    config::LuaCallback,
+    error::Result,
+    grabs::{
+        PointerMoveSurfaceGrab, PointerResizeSurfaceGrab, ResizeData, ResizeEdge, ResizeState,
+    },
+    input_handler::Mods,
+    shell::SurfaceData,
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{
+    desktop::space::SpaceElement,
+    input::pointer::{Focus, GrabStartData as PointerGrabStartData},
+    utils::{Logical, Point, Serial},
+    wayland::{compositor::with_states, seat::WaylandFocus},
+};
+use std::cell::RefCell;
+use tracing::debug;
+
+pub const BTN_LEFT: u32 = 0x110;
+pub const BTN_RIGHT: u32 = 0x111;
+pub const BTN_MIDDLE: u32 = 0x112;
+
+/// Returns the button code for names like `left` or `right`
+pub fn button_from_name(name: &str) -> Option<u32> {
+    match name {
+        "left" => Some(BTN_LEFT),
+        "right" => Some(BTN_RIGHT),
+        "middle" => Some(BTN_MIDDLE),
+        _ => None,
+    }
+}
+
+/// What happens if a mapped pointer button is pressed together with the modifiers
+#[derive(Debug, Clone)]
+pub enum ButtonBinding {
+    /// Moves the window under the pointer until the button is released
+    Move,
+    /// Resizes the window under the pointer at the edges closest to the pointer
+    Resize,
+    Callback(LuaCallback),
+}
+
+impl State {
+    pub fn map_button(&mut self, button: u32, mods: Mods, binding: ButtonBinding) {
+        self.button_maps
+            .entry(mods)
+            .or_default()
+            .insert(button, binding);
+    }
+
+    /// Binding of the button for the currently pressed modifiers
+    pub fn button_binding(&self, button: u32) -> Option<ButtonBinding> {
+        if self.session_lock.is_some() || self.kiosk.is_some() {
+            return None;
+        }
+        let modifiers = self.keyboard().ok()?.modifier_state();
+        self.button_maps
+            .get(&modifiers.into())
+            .and_then(|maps| maps.get(&button))
+            .cloned()
+    }
+
+    /// Application window under the pointer, along with its space and location
+    fn window_under_pointer(&self) -> Option<(WorkspaceWindow, String, Point<i32, Logical>)> {
+        let pointer_location = self.pointer_location();
+        let space_name = self.space_at(pointer_location)?;
+        let space = self.spaces.get(&space_name)?;
+        let (window, _) = space.element_under(pointer_location)?;
+        if !matches!(window, WorkspaceWindow::ApplicationWindow(_)) {
+            return None;
+        }
+        let location = space.element_location(window)?;
+        Some((window.clone(), space_name, location))
+    }
+
+    /// Moves the window under the pointer until `button` is released
+    pub fn start_pointer_move(&mut self, button: u32, serial: Serial) -> Result<()> {
+        let pointer = self.pointer()?;
+        let Some((window, space_name, initial_window_location)) = self.window_under_pointer()
+        else {
+            debug!("No window under the pointer to move");
+            return Ok(());
+        };
+
+        let grab = PointerMoveSurfaceGrab {
+            start_data: PointerGrabStartData {
+                focus: None,
+                button,
+                location: pointer.current_location(),
+            },
+            window,
+            space_name,
+            initial_window_location,
+            drop_target: None,
+        };
+        pointer.set_grab(self, grab, serial, Focus::Clear);
+        Ok(())
+    }
+
+    /// Resizes the window under the pointer until `button` is released. The edges closest to the
+    /// pointer are moved.
+    pub fn start_pointer_resize(&mut self, button: u32, serial: Serial) -> Result<()> {
+        let pointer = self.pointer()?;
+        let Some((window, space_name, initial_window_location)) = self.window_under_pointer()
+        else {
+            debug!("No window under the pointer to resize");
+            return Ok(());
+        };
+
+        let location = pointer.current_location();
+        let initial_window_size = window.geometry().size;
+        let relative = location - initial_window_location.to_f64();
+        let horizontal = if relative.x < initial_window_size.w as f64 / 2.0 {
+            ResizeEdge::LEFT
+        } else {
+            ResizeEdge::RIGHT
+        };
+        let vertical = if relative.y < initial_window_size.h as f64 / 2.0 {
+            ResizeEdge::TOP
+        } else {
+            ResizeEdge::BOTTOM
+        };
+        let edges = horizontal | vertical;
+
+        if let Some(surface) = window.wl_surface() {
+            with_states(&surface, |states| {
+                states
+                    .data_map
+                    .insert_if_missing(|| RefCell::new(SurfaceData::default()));
+                states
+                    .data_map
+                    .get::<RefCell<SurfaceData>>()
+                    .unwrap()
+                    .borrow_mut()
+                    .resize_state = ResizeState::Resizing(ResizeData {
+                    edges,
+                    initial_window_location,
+                    initial_window_size,
+                });
+            });
+        }
+
+        let grab = PointerResizeSurfaceGrab {
+            start_data: PointerGrabStartData {
+                focus: None,
+                button,
+                location,
+            },
+            window,
+            space_name,
+            edges,
+            initial_window_location,
+            initial_window_size,
+            last_window_size: initial_window_size,
+        };
+        pointer.set_grab(self, grab, serial, Focus::Clear);
+        Ok(())
+    }
+}
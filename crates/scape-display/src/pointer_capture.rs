@@ -0,0 +1,162 @@
+use crate::{
+    error::{Result, ScapeError},
+    render::CustomRenderElements,
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{
+    backend::renderer::{
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            Kind,
+        },
+        ImportAll, ImportMem, Renderer,
+    },
+    desktop::Space,
+    input::keyboard::{Keysym, ModifiersState},
+    output::Output,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Scale},
+    wayland::{
+        pointer_constraints::{with_pointer_constraint, PointerConstraint},
+        seat::WaylandFocus,
+    },
+};
+use std::borrow::Cow;
+use tracing::info;
+
+/// Edge length of the indicator square
+const INDICATOR_SIZE: i32 = 12;
+/// Distance of the indicator to the top right corner of the output
+const INDICATOR_MARGIN: i32 = 8;
+const LOCKED_COLOR: [f32; 4] = [0.9, 0.3, 0.2, 0.9];
+const CONFINED_COLOR: [f32; 4] = [0.9, 0.7, 0.2, 0.9];
+
+/// Whether the key combination breaks the active pointer constraint. It works even while a
+/// client inhibits keyboard shortcuts, so a game can never keep the pointer captured.
+pub fn is_break_pointer_constraint_key(modifiers: ModifiersState, keysym: Keysym) -> bool {
+    modifiers.logo && keysym == Keysym::Escape
+}
+
+/// A client which currently locks or confines the pointer
+#[derive(Debug)]
+pub struct PointerCapture {
+    pub surface: WlSurface,
+    /// `true` if the pointer is locked, `false` if it is confined
+    pub locked: bool,
+    buffer: SolidColorBuffer,
+}
+
+impl PointerCapture {
+    fn new(surface: WlSurface, locked: bool) -> Self {
+        let color = if locked { LOCKED_COLOR } else { CONFINED_COLOR };
+        Self {
+            surface,
+            locked,
+            buffer: SolidColorBuffer::new((INDICATOR_SIZE, INDICATOR_SIZE), color),
+        }
+    }
+
+    /// Indicator in the top right corner of the output the pointer is on
+    pub fn render_element<R>(
+        &self,
+        space: &Space<WorkspaceWindow>,
+        output: &Output,
+        pointer_location: Point<f64, Logical>,
+    ) -> Option<CustomRenderElements<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+    {
+        let output_geometry = space.output_geometry(output)?;
+        if !output_geometry.to_f64().contains(pointer_location) {
+            return None;
+        }
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let location = Point::<i32, Logical>::from((
+            output_geometry.size.w - INDICATOR_SIZE - INDICATOR_MARGIN,
+            INDICATOR_MARGIN,
+        ))
+        .to_physical_precise_round(scale);
+        Some(CustomRenderElements::Solid(
+            SolidColorRenderElement::from_buffer(
+                &self.buffer,
+                location,
+                scale,
+                1.0,
+                Kind::Unspecified,
+            ),
+        ))
+    }
+}
+
+impl State {
+    /// Updates the capture indicator and notifies the config after pointer constraints of the
+    /// focused surface got activated or deactivated
+    pub fn update_pointer_capture(&mut self) {
+        let Some(pointer) = self.pointer.clone() else {
+            return;
+        };
+        let captured = pointer
+            .current_focus()
+            .and_then(|focus| focus.wl_surface().map(Cow::into_owned))
+            .and_then(|surface| {
+                let locked = with_pointer_constraint(&surface, &pointer, |constraint| {
+                    constraint
+                        .filter(|constraint| constraint.is_active())
+                        .map(|constraint| matches!(&*constraint, PointerConstraint::Locked(_)))
+                })?;
+                Some((surface, locked))
+            });
+
+        let changed = match (&self.pointer_capture, &captured) {
+            (Some(capture), Some((surface, locked))) => {
+                capture.surface != *surface || capture.locked != *locked
+            }
+            (None, None) => false,
+            _ => true,
+        };
+        if !changed {
+            return;
+        }
+
+        let app_id = captured
+            .as_ref()
+            .map(|(surface, _)| surface)
+            .or(self
+                .pointer_capture
+                .as_ref()
+                .map(|capture| &capture.surface))
+            .and_then(|surface| self.window_and_space_for_surface(surface))
+            .map(|(window, _)| window.app_id());
+        info!(
+            ?app_id,
+            captured = captured.is_some(),
+            "Pointer capture changed"
+        );
+        self.pointer_capture =
+            captured.map(|(surface, locked)| PointerCapture::new(surface, locked));
+        self.backend_data.schedule_render();
+        self.on_pointer_capture(self.pointer_capture.is_some(), app_id);
+    }
+
+    /// Deactivates the constraint of the capturing client. It is not activated again until the
+    /// pointer left the surface.
+    pub fn break_pointer_constraint(&mut self) -> Result<()> {
+        let pointer = self.pointer.clone().ok_or(ScapeError::NoPointer)?;
+        let Some(capture) = &self.pointer_capture else {
+            return Ok(());
+        };
+        let surface = capture.surface.clone();
+
+        with_pointer_constraint(&surface, &pointer, |constraint| {
+            if let Some(constraint) = constraint {
+                constraint.deactivate();
+            }
+        });
+        info!("Pointer constraint broken");
+        self.broken_pointer_constraint = Some(surface);
+        self.update_pointer_capture();
+        Ok(())
+    }
+}
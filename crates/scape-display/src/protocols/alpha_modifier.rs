@@ -0,0 +1,4 @@
+use crate::State;
+use smithay::delegate_alpha_modifier;
+
+delegate_alpha_modifier!(State);
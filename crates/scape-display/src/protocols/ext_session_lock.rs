@@ -9,6 +9,7 @@ use smithay::{
 };
 
 use crate::{state::SessionLock, State};
+use tracing::info;
 
 impl SessionLockHandler for State {
     fn lock_state(&mut self) -> &mut smithay::wayland::session_lock::SessionLockManagerState {
@@ -16,6 +17,10 @@ impl SessionLockHandler for State {
     }
 
     fn lock(&mut self, locker: SessionLocker) {
+        if self.kiosk.is_some() {
+            info!("Rejecting session lock in kiosk mode");
+            return;
+        }
         // Reject lock if session lock exists and is still valid
         if let Some(session_lock) = self.session_lock.as_ref() {
             if self
@@ -48,16 +53,14 @@ impl SessionLockHandler for State {
         //         .schedule_render(&self.common.event_loop_handle, &output);
         // }
 
-        let maybe_elem = self
-            .spaces
-            .values()
-            .next()
-            .unwrap()
-            .elements()
-            .next_back()
-            .cloned();
+        let maybe_elem = self.space_under_pointer().ok().and_then(|space_name| {
+            self.spaces
+                .get(&space_name)?
+                .elements()
+                .next_back()
+                .cloned()
+        });
         if let Some(elem) = maybe_elem {
-            // TODO: Handle multiple spaces
             let keyboard = self.seat.as_ref().unwrap().get_keyboard().unwrap();
             let serial = SERIAL_COUNTER.next_serial();
             keyboard.set_focus(self, Some(elem.into()), serial);
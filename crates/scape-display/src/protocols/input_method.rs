@@ -1,13 +1,10 @@
-use crate::{state::ActiveSpace, State};
+use crate::State;
 use smithay::{
     delegate_input_method_manager,
     desktop::{space::SpaceElement, PopupKind, PopupManager},
     reexports::wayland_server::protocol::wl_surface::WlSurface,
     utils::Rectangle,
-    wayland::{
-        compositor::with_states,
-        input_method::{InputMethodHandler, PopupSurface},
-    },
+    wayland::input_method::{InputMethodHandler, PopupSurface},
 };
 use tracing::warn;
 
@@ -25,19 +22,8 @@ impl InputMethodHandler for State {
     }
 
     fn parent_geometry(&self, parent: &WlSurface) -> Rectangle<i32, smithay::utils::Logical> {
-        let space_name = with_states(parent, |surface_data| {
-            surface_data
-                .data_map
-                .get::<ActiveSpace>()
-                .unwrap()
-                .0
-                .to_owned()
-        });
-        self.spaces[&space_name]
-            .elements()
-            .find_map(|window| {
-                (window.wl_surface().as_deref() == Some(parent)).then(|| window.geometry())
-            })
+        self.window_and_space_for_surface(parent)
+            .map(|(window, _)| window.geometry())
             .unwrap_or_default()
     }
 
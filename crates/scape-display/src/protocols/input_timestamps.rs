@@ -0,0 +1,183 @@
+use crate::State;
+use smithay::reexports::wayland_protocols::wp::input_timestamps::zv1::server::zwp_input_timestamps_manager_v1::{
+    Request as ManagerRequest, ZwpInputTimestampsManagerV1,
+};
+use smithay::reexports::wayland_protocols::wp::input_timestamps::zv1::server::zwp_input_timestamps_v1::{
+    Request as TimestampsRequest, ZwpInputTimestampsV1,
+};
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+const MANAGER_VERSION: u32 = 1;
+
+pub trait InputTimestampsHandler {
+    fn input_timestamps_state(&mut self) -> &mut InputTimestampsManagerState;
+}
+
+/// Input device a timestamps object is subscribed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputTimestampsKind {
+    Keyboard,
+    Pointer,
+    Touch,
+}
+
+#[derive(Debug, Default)]
+pub struct InputTimestampsManagerState {
+    timestamps: Vec<ZwpInputTimestampsV1>,
+}
+
+impl InputTimestampsManagerState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<ZwpInputTimestampsManagerV1, ()>,
+        D: Dispatch<ZwpInputTimestampsManagerV1, ()>,
+        D: Dispatch<ZwpInputTimestampsV1, InputTimestampsKind>,
+        D: InputTimestampsHandler,
+        D: 'static,
+    {
+        display.create_global::<D, ZwpInputTimestampsManagerV1, _>(MANAGER_VERSION, ());
+
+        Self::default()
+    }
+
+    /// Sends `time` in microseconds to all timestamp objects of the client of `surface`, which
+    /// are subscribed to `kind`
+    pub fn send_timestamp(&self, kind: InputTimestampsKind, surface: &WlSurface, time: u64) {
+        let seconds = time / 1_000_000;
+        let nanoseconds = (time % 1_000_000) * 1_000;
+        for timestamps in &self.timestamps {
+            if timestamps.data::<InputTimestampsKind>() == Some(&kind)
+                && timestamps.id().same_client_as(&surface.id())
+            {
+                timestamps.timestamp((seconds >> 32) as u32, seconds as u32, nanoseconds as u32);
+            }
+        }
+    }
+}
+
+impl<D> GlobalDispatch<ZwpInputTimestampsManagerV1, (), D> for InputTimestampsManagerState
+where
+    D: GlobalDispatch<ZwpInputTimestampsManagerV1, ()>,
+    D: Dispatch<ZwpInputTimestampsManagerV1, ()>,
+    D: Dispatch<ZwpInputTimestampsV1, InputTimestampsKind>,
+    D: InputTimestampsHandler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        manager: New<ZwpInputTimestampsManagerV1>,
+        _manager_state: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+}
+
+impl<D> Dispatch<ZwpInputTimestampsManagerV1, (), D> for InputTimestampsManagerState
+where
+    D: Dispatch<ZwpInputTimestampsManagerV1, ()>,
+    D: Dispatch<ZwpInputTimestampsV1, InputTimestampsKind>,
+    D: InputTimestampsHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _manager: &ZwpInputTimestampsManagerV1,
+        request: ManagerRequest,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let (id, kind) = match request {
+            ManagerRequest::GetKeyboardTimestamps { id, .. } => (id, InputTimestampsKind::Keyboard),
+            ManagerRequest::GetPointerTimestamps { id, .. } => (id, InputTimestampsKind::Pointer),
+            ManagerRequest::GetTouchTimestamps { id, .. } => (id, InputTimestampsKind::Touch),
+            ManagerRequest::Destroy => return,
+            _ => unreachable!(),
+        };
+        let timestamps = data_init.init(id, kind);
+        state.input_timestamps_state().timestamps.push(timestamps);
+    }
+}
+
+impl<D> Dispatch<ZwpInputTimestampsV1, InputTimestampsKind, D> for InputTimestampsManagerState
+where
+    D: Dispatch<ZwpInputTimestampsV1, InputTimestampsKind>,
+    D: InputTimestampsHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        timestamps: &ZwpInputTimestampsV1,
+        request: TimestampsRequest,
+        _data: &InputTimestampsKind,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            TimestampsRequest::Destroy => {
+                state
+                    .input_timestamps_state()
+                    .timestamps
+                    .retain(|other| other != timestamps);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        timestamps: &ZwpInputTimestampsV1,
+        _data: &InputTimestampsKind,
+    ) {
+        state
+            .input_timestamps_state()
+            .timestamps
+            .retain(|other| other != timestamps);
+    }
+}
+
+#[allow(missing_docs)]
+macro_rules! delegate_input_timestamps {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::input_timestamps::zv1::server::zwp_input_timestamps_manager_v1::ZwpInputTimestampsManagerV1: ()
+        ] => $crate::protocols::input_timestamps::InputTimestampsManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::input_timestamps::zv1::server::zwp_input_timestamps_manager_v1::ZwpInputTimestampsManagerV1: ()
+        ] => $crate::protocols::input_timestamps::InputTimestampsManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::input_timestamps::zv1::server::zwp_input_timestamps_v1::ZwpInputTimestampsV1: $crate::protocols::input_timestamps::InputTimestampsKind
+        ] => $crate::protocols::input_timestamps::InputTimestampsManagerState);
+    };
+}
+
+impl InputTimestampsHandler for State {
+    fn input_timestamps_state(&mut self) -> &mut InputTimestampsManagerState {
+        &mut self.input_timestamps_state
+    }
+}
+
+delegate_input_timestamps!(State);
+
+impl State {
+    /// Sends the microsecond timestamp of the input event currently being processed to the client
+    /// of `surface`. Has to be called right before the event itself is sent to the client.
+    pub fn send_input_timestamp(&self, kind: InputTimestampsKind, surface: Option<&WlSurface>) {
+        if let Some(surface) = surface {
+            self.input_timestamps_state
+                .send_timestamp(kind, surface, self.input_time_usec);
+        }
+    }
+}
@@ -1,6 +1,8 @@
+pub mod alpha_modifier;
 pub mod ext_session_lock;
 pub mod fractional_scale;
 pub mod input_method;
+pub mod input_timestamps;
 pub mod keyboard_shortcuts_inhibit;
 pub mod linux_dmabuf;
 pub mod linux_drm_syncobj;
@@ -21,7 +23,10 @@ pub mod wlr_layer_shell;
 pub mod wlr_screencopy;
 pub mod xdg_activation;
 pub mod xdg_decoration;
+pub mod xdg_dialog;
 pub mod xdg_foreign;
+pub mod xdg_foreign_v1;
 pub mod xdg_shell;
+pub mod xdg_toplevel_drag;
 pub mod xwayland_keyboard_grab;
 pub mod xwayland_shell;
@@ -1,7 +1,6 @@
 use crate::State;
 use smithay::{
     delegate_pointer_constraints,
-    desktop::space::SpaceElement,
     input::pointer::PointerHandle,
     reexports::wayland_server::protocol::wl_surface::WlSurface,
     utils::{Logical, Point},
@@ -16,10 +15,13 @@ impl PointerConstraintsHandler for State {
         let Some(current_focus) = pointer.current_focus() else {
             return;
         };
-        if current_focus.wl_surface().as_deref() == Some(surface) {
+        if current_focus.wl_surface().as_deref() == Some(surface)
+            && self.broken_pointer_constraint.as_ref() != Some(surface)
+        {
             with_pointer_constraint(surface, pointer, |constraint| {
                 constraint.unwrap().activate();
             });
+            self.update_pointer_capture();
         }
     }
 
@@ -29,13 +31,19 @@ impl PointerConstraintsHandler for State {
         pointer: &PointerHandle<Self>,
         location: Point<f64, Logical>,
     ) {
-        if with_pointer_constraint(surface, pointer, |constraint| {
+        if !with_pointer_constraint(surface, pointer, |constraint| {
             constraint.map_or(false, |c| c.is_active())
         }) {
-            if let Some((window, _)) = self.window_and_space_for_surface(surface) {
-                let origin = window.geometry().loc.to_f64();
-                pointer.set_location(origin + location);
-            }
+            return;
+        }
+
+        // The hint is relative to the surface, which is under the locked pointer. It is applied
+        // once the lock is released, so the cursor reappears where the client last placed it.
+        let Some((under, surface_loc)) = self.surface_under(pointer.current_location()) else {
+            return;
+        };
+        if under.wl_surface().as_deref() == Some(surface) {
+            self.pointer_lock_hint = Some(surface_loc + location);
         }
     }
 }
@@ -1,4 +1,106 @@
 use crate::State;
-use smithay::delegate_text_input_manager;
+use smithay::reexports::wayland_protocols::wp::text_input::zv3::server::zwp_text_input_v3::{
+    self, ContentPurpose, ZwpTextInputV3,
+};
+use smithay::reexports::wayland_server::{
+    backend::{ClientId, ObjectId},
+    Client, DataInit, Dispatch, DisplayHandle, Resource, WEnum,
+};
+use smithay::wayland::text_input::{TextInputManagerState, TextInputUserData};
 
-delegate_text_input_manager!(State);
+/// Content purpose of a text input, it is double-buffered like the rest of its state
+#[derive(Debug, Default, Clone)]
+pub struct TextInputPurpose {
+    client: Option<ClientId>,
+    pending_enabled: Option<bool>,
+    pending_purpose: Option<ContentPurpose>,
+    enabled: bool,
+    purpose: Option<ContentPurpose>,
+}
+
+impl TextInputPurpose {
+    fn is_secret(&self) -> bool {
+        self.enabled
+            && matches!(
+                self.purpose,
+                Some(ContentPurpose::Password | ContentPurpose::Pin)
+            )
+    }
+}
+
+smithay::reexports::wayland_server::delegate_global_dispatch!(State: [
+    smithay::reexports::wayland_protocols::wp::text_input::zv3::server::zwp_text_input_manager_v3::ZwpTextInputManagerV3: ()
+] => TextInputManagerState);
+smithay::reexports::wayland_server::delegate_dispatch!(State: [
+    smithay::reexports::wayland_protocols::wp::text_input::zv3::server::zwp_text_input_manager_v3::ZwpTextInputManagerV3: ()
+] => TextInputManagerState);
+
+/// Text inputs are handled by smithay, only their content purpose is tracked here, so that keys
+/// typed into password fields are not shown
+impl Dispatch<ZwpTextInputV3, TextInputUserData> for State {
+    fn request(
+        state: &mut Self,
+        client: &Client,
+        text_input: &ZwpTextInputV3,
+        request: zwp_text_input_v3::Request,
+        data: &TextInputUserData,
+        display: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let purpose = state
+            .text_input_purposes
+            .entry(text_input.id())
+            .or_default();
+        purpose.client = Some(client.id());
+        match &request {
+            zwp_text_input_v3::Request::Enable => {
+                purpose.pending_enabled = Some(true);
+                // Enabling resets the state of the text input
+                purpose.pending_purpose = Some(ContentPurpose::Normal);
+            }
+            zwp_text_input_v3::Request::Disable => purpose.pending_enabled = Some(false),
+            zwp_text_input_v3::Request::SetContentType {
+                purpose: WEnum::Value(content_purpose),
+                ..
+            } => purpose.pending_purpose = Some(*content_purpose),
+            zwp_text_input_v3::Request::Commit => {
+                if let Some(enabled) = purpose.pending_enabled.take() {
+                    purpose.enabled = enabled;
+                }
+                if let Some(content_purpose) = purpose.pending_purpose.take() {
+                    purpose.purpose = Some(content_purpose);
+                }
+            }
+            _ => {}
+        }
+
+        <TextInputManagerState as Dispatch<ZwpTextInputV3, TextInputUserData, Self>>::request(
+            state, client, text_input, request, data, display, data_init,
+        );
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        client: ClientId,
+        text_input: &ZwpTextInputV3,
+        data: &TextInputUserData,
+    ) {
+        state.text_input_purposes.remove(&text_input.id());
+        <TextInputManagerState as Dispatch<ZwpTextInputV3, TextInputUserData, Self>>::destroyed(
+            state, client, text_input, data,
+        );
+    }
+}
+
+impl State {
+    /// Whether a text input of the client with the object expects a password or a PIN. Text
+    /// inputs follow the keyboard focus, so this tells if keys typed into it are secret.
+    pub fn text_input_is_secret(&self, object_id: &ObjectId) -> bool {
+        let Ok(client) = self.display_handle.get_client(object_id.clone()) else {
+            return false;
+        };
+        self.text_input_purposes
+            .values()
+            .any(|purpose| purpose.client == Some(client.id()) && purpose.is_secret())
+    }
+}
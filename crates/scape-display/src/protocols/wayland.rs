@@ -1,4 +1,5 @@
 use crate::{
+    clipboard::{self, SelectionData},
     focus::{KeyboardFocusTarget, PointerFocusTarget},
     state::DndIcon,
     State,
@@ -44,7 +45,7 @@ impl DataDeviceHandler for State {
 impl ClientDndGrabHandler for State {
     fn started(
         &mut self,
-        _source: Option<WlDataSource>,
+        source: Option<WlDataSource>,
         icon: Option<WlSurface>,
         _seat: Seat<Self>,
     ) {
@@ -63,10 +64,14 @@ impl ClientDndGrabHandler for State {
             (0, 0).into()
         };
         self.dnd_icon = icon.map(|surface| DndIcon { surface, offset });
+        // The client may attach a toplevel to the drag at any point while it is ongoing
+        self.toplevel_drag_source = source;
     }
 
     fn dropped(&mut self, _seat: Seat<Self>) {
         self.dnd_icon = None;
+        // A dragged toplevel stays where it was dropped
+        self.toplevel_drag_source = None;
     }
 }
 
@@ -83,7 +88,7 @@ impl OutputHandler for State {}
 delegate_output!(State);
 
 impl SelectionHandler for State {
-    type SelectionUserData = ();
+    type SelectionUserData = SelectionData;
 
     fn new_selection(
         &mut self,
@@ -107,8 +112,13 @@ impl SelectionHandler for State {
         mime_type: String,
         fd: OwnedFd,
         _seat: Seat<Self>,
-        _user_data: &(),
+        user_data: &SelectionData,
     ) {
+        match user_data {
+            SelectionData::Text(text) => return clipboard::send_text(text.clone(), fd),
+            SelectionData::Image(png) => return clipboard::send_image(png.clone(), fd),
+            SelectionData::Xwayland => {}
+        }
         let Some(ref mut xwayland_state) = &mut self.xwayland_state else {
             return;
         };
@@ -145,6 +155,17 @@ impl SeatHandler for State {
             .and_then(|s| dh.get_client(s.id()).ok());
         set_data_device_focus(dh, seat, focus.clone());
         set_primary_focus(dh, seat, focus);
+        self.update_focused_window(target);
+
+        if let Some(KeyboardFocusTarget::Window(window)) = target {
+            if let Some((window, space_name)) = window
+                .wl_surface()
+                .and_then(|surface| self.window_and_space_for_surface(&surface))
+            {
+                self.record_focus(&window, &space_name);
+                self.on_window_focus(&window, &space_name);
+            }
+        }
     }
 
     fn cursor_image(&mut self, _seat: &Seat<Self>, status: CursorImageStatus) {
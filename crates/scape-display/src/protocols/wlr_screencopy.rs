@@ -21,6 +21,11 @@ impl ScreencopyHandler for State {
     }
 
     fn frame(&mut self, frame: Screencopy) {
+        // Copied after the next frame of the output, which is rendered on a timer
+        if self.is_virtual_output(&frame.output) {
+            self.screencopy_frames.push(frame);
+            return;
+        }
         match &self.backend_data {
             crate::state::BackendData::None => panic!("Cannot craete screencopy without backend"),
             crate::state::BackendData::Udev(udev_data) => {
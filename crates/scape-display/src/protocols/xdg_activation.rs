@@ -1,15 +1,13 @@
-use crate::{state::ActiveSpace, State};
+use crate::State;
 use smithay::{
     delegate_xdg_activation,
     input::Seat,
     reexports::wayland_server::protocol::wl_surface::WlSurface,
-    wayland::{
-        compositor::with_states,
-        xdg_activation::{
-            XdgActivationHandler, XdgActivationState, XdgActivationToken, XdgActivationTokenData,
-        },
+    wayland::xdg_activation::{
+        XdgActivationHandler, XdgActivationState, XdgActivationToken, XdgActivationTokenData,
     },
 };
+use tracing::warn;
 
 impl XdgActivationHandler for State {
     fn activation_state(&mut self) -> &mut XdgActivationState {
@@ -37,20 +35,11 @@ impl XdgActivationHandler for State {
     ) {
         if token_data.timestamp.elapsed().as_secs() < 10 {
             // Just grant the wish
-            let space_name = with_states(&surface, |surface_data| {
-                surface_data
-                    .data_map
-                    .get::<ActiveSpace>()
-                    .unwrap()
-                    .0
-                    .to_owned()
-            });
-            let w = self.spaces[&space_name]
-                .elements()
-                .find(|window| window.wl_surface().map(|s| *s == surface).unwrap_or(false))
-                .cloned();
-            if let Some(window) = w {
-                self.focus_window(window, &space_name);
+            // Windows on a hidden space are shown by switching to their space
+            if let Some((window, space_name)) = self.window_and_space_for_surface(&surface) {
+                if let Err(err) = self.show_window(window, &space_name) {
+                    warn!(%err, "Unable to activate window");
+                }
             }
         }
     }
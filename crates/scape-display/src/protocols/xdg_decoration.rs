@@ -7,18 +7,24 @@ use smithay::{
 
 impl XdgDecorationHandler for State {
     fn new_decoration(&mut self, toplevel: ToplevelSurface) {
+        let mode = self
+            .window_rule_decoration_mode(&toplevel)
+            .unwrap_or(DecorationMode::ClientSide);
         toplevel.with_pending_state(|state| {
-            state.decoration_mode = Some(DecorationMode::ClientSide);
+            state.decoration_mode = Some(mode);
         });
         toplevel.send_configure();
     }
 
     fn request_mode(&mut self, toplevel: ToplevelSurface, mode: DecorationMode) {
-        toplevel.with_pending_state(|state| {
-            state.decoration_mode = Some(match mode {
+        let mode = self
+            .window_rule_decoration_mode(&toplevel)
+            .unwrap_or(match mode {
                 DecorationMode::ServerSide => DecorationMode::ServerSide,
                 _ => DecorationMode::ClientSide,
             });
+        toplevel.with_pending_state(|state| {
+            state.decoration_mode = Some(mode);
         });
 
         if toplevel.is_initial_configure_sent() {
@@ -27,8 +33,11 @@ impl XdgDecorationHandler for State {
     }
 
     fn unset_mode(&mut self, toplevel: ToplevelSurface) {
+        let mode = self
+            .window_rule_decoration_mode(&toplevel)
+            .unwrap_or(DecorationMode::ClientSide);
         toplevel.with_pending_state(|state| {
-            state.decoration_mode = Some(DecorationMode::ClientSide);
+            state.decoration_mode = Some(mode);
         });
         if toplevel.is_initial_configure_sent() {
             toplevel.send_pending_configure();
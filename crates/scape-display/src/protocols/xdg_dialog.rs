@@ -0,0 +1,23 @@
+use smithay::{
+    delegate_xdg_dialog,
+    wayland::shell::xdg::{dialog::XdgDialogHandler, ToplevelSurface},
+};
+
+use crate::{
+    shell::{set_dialog_state, DialogState},
+    State,
+};
+
+impl XdgDialogHandler for State {
+    fn modal_changed(&mut self, toplevel: ToplevelSurface, is_modal: bool) {
+        let dialog = if is_modal {
+            DialogState::Pending
+        } else {
+            DialogState::NotModal
+        };
+        set_dialog_state(toplevel.wl_surface(), dialog);
+        self.place_dialog(&toplevel);
+    }
+}
+
+delegate_xdg_dialog!(State);
@@ -0,0 +1,360 @@
+use crate::State;
+use smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_exported_v1::{
+    Request as ExportedRequest, ZxdgExportedV1,
+};
+use smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_exporter_v1::{
+    Request as ExporterRequest, ZxdgExporterV1,
+};
+use smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_imported_v1::{
+    Request as ImportedRequest, ZxdgImportedV1,
+};
+use smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_importer_v1::{
+    Request as ImporterRequest, ZxdgImporterV1,
+};
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+use smithay::wayland::shell::xdg::{ToplevelSurface, XdgShellHandler};
+use std::sync::Mutex;
+use tracing::warn;
+
+const EXPORTER_VERSION: u32 = 1;
+const IMPORTER_VERSION: u32 = 1;
+/// Number of random bytes a handle consists of
+const HANDLE_BYTES: usize = 16;
+
+/// The unstable v1 of xdg-foreign, which is still used by GTK3 and portals built on top of it.
+/// The v2 is provided by smithay.
+pub trait XdgForeignV1Handler: XdgShellHandler {
+    fn xdg_foreign_v1_state(&mut self) -> &mut XdgForeignV1State;
+}
+
+#[derive(Debug)]
+struct Export {
+    handle: String,
+    surface: WlSurface,
+}
+
+#[derive(Debug)]
+pub struct ImportedData {
+    handle: String,
+    /// Toplevels which got parented to the imported surface
+    children: Mutex<Vec<ToplevelSurface>>,
+}
+
+#[derive(Debug, Default)]
+pub struct XdgForeignV1State {
+    exports: Vec<Export>,
+    imports: Vec<ZxdgImportedV1>,
+}
+
+impl XdgForeignV1State {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<ZxdgExporterV1, ()>,
+        D: GlobalDispatch<ZxdgImporterV1, ()>,
+        D: Dispatch<ZxdgExporterV1, ()>,
+        D: Dispatch<ZxdgImporterV1, ()>,
+        D: Dispatch<ZxdgExportedV1, String>,
+        D: Dispatch<ZxdgImportedV1, ImportedData>,
+        D: XdgForeignV1Handler,
+        D: 'static,
+    {
+        display.create_global::<D, ZxdgExporterV1, _>(EXPORTER_VERSION, ());
+        display.create_global::<D, ZxdgImporterV1, _>(IMPORTER_VERSION, ());
+
+        Self::default()
+    }
+
+    fn exported_surface(&self, handle: &str) -> Option<&WlSurface> {
+        self.exports
+            .iter()
+            .find(|export| export.handle == handle && export.surface.is_alive())
+            .map(|export| &export.surface)
+    }
+}
+
+fn generate_handle() -> Option<String> {
+    let mut bytes = [0u8; HANDLE_BYTES];
+    // SAFETY: the buffer is valid for writes of its whole length
+    let len = unsafe { libc::getrandom(bytes.as_mut_ptr().cast(), bytes.len(), 0) };
+    if len != bytes.len() as isize {
+        return None;
+    }
+    Some(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Removes the parent relationship of all toplevels which got parented through `imported`
+fn unparent_children<D: XdgForeignV1Handler>(state: &mut D, imported: &ImportedData) {
+    let children = std::mem::take(&mut *imported.children.lock().unwrap());
+    for child in children {
+        if child.alive() && child.set_parent(None) {
+            state.parent_changed(child);
+        }
+    }
+}
+
+fn remove_export<D: XdgForeignV1Handler>(state: &mut D, handle: &str) {
+    let foreign_state = state.xdg_foreign_v1_state();
+    foreign_state
+        .exports
+        .retain(|export| export.handle != handle);
+
+    let (imports, remaining) = std::mem::take(&mut foreign_state.imports)
+        .into_iter()
+        .partition::<Vec<_>, _>(|imported| {
+            imported
+                .data::<ImportedData>()
+                .is_some_and(|data| data.handle == handle)
+        });
+    foreign_state.imports = remaining;
+    for imported in imports {
+        imported.destroyed();
+        if let Some(data) = imported.data::<ImportedData>() {
+            unparent_children(state, data);
+        }
+    }
+}
+
+impl<D> GlobalDispatch<ZxdgExporterV1, (), D> for XdgForeignV1State
+where
+    D: GlobalDispatch<ZxdgExporterV1, ()>,
+    D: Dispatch<ZxdgExporterV1, ()>,
+    D: Dispatch<ZxdgExportedV1, String>,
+    D: XdgForeignV1Handler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        exporter: New<ZxdgExporterV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(exporter, ());
+    }
+}
+
+impl<D> GlobalDispatch<ZxdgImporterV1, (), D> for XdgForeignV1State
+where
+    D: GlobalDispatch<ZxdgImporterV1, ()>,
+    D: Dispatch<ZxdgImporterV1, ()>,
+    D: Dispatch<ZxdgImportedV1, ImportedData>,
+    D: XdgForeignV1Handler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        importer: New<ZxdgImporterV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(importer, ());
+    }
+}
+
+impl<D> Dispatch<ZxdgExporterV1, (), D> for XdgForeignV1State
+where
+    D: Dispatch<ZxdgExporterV1, ()>,
+    D: Dispatch<ZxdgExportedV1, String>,
+    D: XdgForeignV1Handler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _exporter: &ZxdgExporterV1,
+        request: ExporterRequest,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ExporterRequest::Export { id, surface } => {
+                let Some(handle) = generate_handle() else {
+                    warn!("Unable to generate a handle for an exported surface");
+                    return;
+                };
+                let exported = data_init.init(id, handle.clone());
+                exported.handle(handle.clone());
+                state
+                    .xdg_foreign_v1_state()
+                    .exports
+                    .push(Export { handle, surface });
+            }
+            ExporterRequest::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZxdgExportedV1, String, D> for XdgForeignV1State
+where
+    D: Dispatch<ZxdgExportedV1, String>,
+    D: XdgForeignV1Handler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _exported: &ZxdgExportedV1,
+        request: ExportedRequest,
+        handle: &String,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ExportedRequest::Destroy => remove_export(state, handle),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, _exported: &ZxdgExportedV1, handle: &String) {
+        remove_export(state, handle);
+    }
+}
+
+impl<D> Dispatch<ZxdgImporterV1, (), D> for XdgForeignV1State
+where
+    D: Dispatch<ZxdgImporterV1, ()>,
+    D: Dispatch<ZxdgImportedV1, ImportedData>,
+    D: XdgForeignV1Handler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _importer: &ZxdgImporterV1,
+        request: ImporterRequest,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ImporterRequest::Import { id, handle } => {
+                let foreign_state = state.xdg_foreign_v1_state();
+                let exists = foreign_state.exported_surface(&handle).is_some();
+                let imported = data_init.init(
+                    id,
+                    ImportedData {
+                        handle,
+                        children: Mutex::new(Vec::new()),
+                    },
+                );
+                if exists {
+                    foreign_state.imports.push(imported);
+                } else {
+                    imported.destroyed();
+                }
+            }
+            ImporterRequest::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZxdgImportedV1, ImportedData, D> for XdgForeignV1State
+where
+    D: Dispatch<ZxdgImportedV1, ImportedData>,
+    D: XdgForeignV1Handler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        imported: &ZxdgImportedV1,
+        request: ImportedRequest,
+        data: &ImportedData,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ImportedRequest::SetParentOf { surface } => {
+                let Some(parent) = state
+                    .xdg_foreign_v1_state()
+                    .exported_surface(&data.handle)
+                    .cloned()
+                else {
+                    return;
+                };
+                let toplevel = state
+                    .xdg_shell_state()
+                    .toplevel_surfaces()
+                    .iter()
+                    .find(|toplevel| toplevel.wl_surface() == &surface)
+                    .cloned();
+                let Some(toplevel) = toplevel else {
+                    warn!("Imported surface can only be the parent of a toplevel");
+                    return;
+                };
+
+                if toplevel.set_parent(Some(&parent)) {
+                    data.children.lock().unwrap().push(toplevel.clone());
+                    state.parent_changed(toplevel);
+                }
+            }
+            ImportedRequest::Destroy => {
+                state
+                    .xdg_foreign_v1_state()
+                    .imports
+                    .retain(|other| other != imported);
+                unparent_children(state, data);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        imported: &ZxdgImportedV1,
+        _data: &ImportedData,
+    ) {
+        state
+            .xdg_foreign_v1_state()
+            .imports
+            .retain(|other| other != imported);
+    }
+}
+
+#[allow(missing_docs)]
+macro_rules! delegate_xdg_foreign_v1 {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_exporter_v1::ZxdgExporterV1: ()
+        ] => $crate::protocols::xdg_foreign_v1::XdgForeignV1State);
+
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_importer_v1::ZxdgImporterV1: ()
+        ] => $crate::protocols::xdg_foreign_v1::XdgForeignV1State);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_exporter_v1::ZxdgExporterV1: ()
+        ] => $crate::protocols::xdg_foreign_v1::XdgForeignV1State);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_importer_v1::ZxdgImporterV1: ()
+        ] => $crate::protocols::xdg_foreign_v1::XdgForeignV1State);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_exported_v1::ZxdgExportedV1: String
+        ] => $crate::protocols::xdg_foreign_v1::XdgForeignV1State);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::foreign::zv1::server::zxdg_imported_v1::ZxdgImportedV1: $crate::protocols::xdg_foreign_v1::ImportedData
+        ] => $crate::protocols::xdg_foreign_v1::XdgForeignV1State);
+    };
+}
+
+impl XdgForeignV1Handler for State {
+    fn xdg_foreign_v1_state(&mut self) -> &mut XdgForeignV1State {
+        &mut self.xdg_foreign_v1_state
+    }
+}
+
+delegate_xdg_foreign_v1!(State);
@@ -3,12 +3,12 @@ use crate::grabs::{
     PointerMoveSurfaceGrab, PointerResizeSurfaceGrab, ResizeData, ResizeEdge, ResizeState,
     TouchMoveSurfaceGrab, TouchResizeSurfaceGrab,
 };
-use crate::shell::SurfaceData;
+use crate::shell::{dialog_state, set_dialog_state, DialogState, SurfaceData};
 use crate::workspace_window::WorkspaceWindow;
 use crate::{application_window::ApplicationWindow, state::State};
 use smithay::delegate_xdg_shell;
 use smithay::desktop::Space;
-use smithay::utils::{Logical, Point, Rectangle};
+use smithay::utils::{Logical, Point};
 use smithay::wayland::compositor;
 use smithay::{
     desktop::{
@@ -31,8 +31,8 @@ use smithay::{
         compositor::with_states,
         seat::WaylandFocus,
         shell::xdg::{
-            Configure, PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler,
-            XdgShellState, XdgToplevelSurfaceData,
+            Configure, PopupSurface, PositionerState, ShellClient, ToplevelSurface,
+            XdgShellHandler, XdgShellState, XdgToplevelSurfaceData,
         },
     },
 };
@@ -51,37 +51,71 @@ impl XdgShellHandler for State {
         let window = WorkspaceWindow::from(ApplicationWindow(Window::new_wayland_window(
             surface.clone(),
         )));
-        // TODO: Handle multiple spaces
-        self.place_window(
-            &self.spaces.keys().next().unwrap().clone(),
-            &window,
-            true,
-            None,
-            false,
-        );
-        let keyboard = self.seat.as_ref().unwrap().get_keyboard().unwrap();
-        let serial = SERIAL_COUNTER.next_serial();
-        keyboard.set_focus(self, Some(window.into()), serial);
+        // New windows open on the space of the output under the pointer
+        let Ok(default_space) = self.space_under_pointer() else {
+            warn!("Unable to place new toplevel, there is no space");
+            return;
+        };
+        let space_name = self.window_rule_space(&window, &default_space);
+        self.place_window(&space_name, &window, true, None, false);
+        self.apply_window_rules(&window);
+        // Windows opening on a hidden space do not take the focus
+        if self.is_space_shown(&space_name) {
+            let keyboard = self.seat.as_ref().unwrap().get_keyboard().unwrap();
+            let serial = SERIAL_COUNTER.next_serial();
+            keyboard.set_focus(self, Some(window.into()), serial);
+        }
 
         compositor::add_post_commit_hook(surface.wl_surface(), |state: &mut Self, _, surface| {
-            // TODO: Handle multiple spaces
-            handle_toplevel_commit(state.spaces.values_mut().next().unwrap(), surface);
+            for space in state.spaces.values_mut() {
+                handle_toplevel_commit(space, surface);
+            }
+        });
+
+        // Clients set app id and title right after creating the toplevel
+        self.loop_handle.insert_idle(move |state| {
+            if let Some((window, space_name)) =
+                state.window_and_space_for_surface(surface.wl_surface())
+            {
+                state.on_window_open(&window, &space_name);
+                state.startup_window_opened(&window.app_id());
+            }
         });
     }
 
     fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
         if let Some((window, space_name)) = self.window_and_space_for_surface(surface.wl_surface())
         {
+            if self
+                .window_thumbnail
+                .as_ref()
+                .is_some_and(|thumbnail| thumbnail.window == window)
+            {
+                self.hide_window_thumbnail();
+            }
+
+            if let Some(window_id) = window.id() {
+                self.stop_closing(window_id);
+            }
+            self.on_window_close(&window, &space_name);
+
             let space = self.spaces.get_mut(&space_name).unwrap();
             space.unmap_elem(&window);
 
             let maybe_window = space.elements().next_back().cloned();
-            if let Some(window) = maybe_window {
+            if let Some(window) = maybe_window.filter(|_| self.is_space_shown(&space_name)) {
                 self.focus_window(window, &space_name);
             }
         }
     }
 
+    fn parent_changed(&mut self, surface: ToplevelSurface) {
+        if dialog_state(surface.wl_surface()) == DialogState::Placed {
+            set_dialog_state(surface.wl_surface(), DialogState::Pending);
+        }
+        self.place_dialog(&surface);
+    }
+
     fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
         // Do not send a configure here, the initial configure
         // of a xdg_surface has to be sent during the commit if
@@ -240,6 +274,10 @@ impl XdgShellHandler for State {
         pointer.set_grab(self, grab, serial, Focus::Clear);
     }
 
+    fn client_pong(&mut self, client: ShellClient) {
+        self.shell_client_ponged(&client);
+    }
+
     fn ack_configure(&mut self, surface: WlSurface, configure: Configure) {
         if let Configure::Toplevel(configure) = configure {
             if let Some(serial) = with_states(&surface, |states| {
@@ -310,42 +348,22 @@ impl XdgShellHandler for State {
     fn fullscreen_request(
         &mut self,
         surface: ToplevelSurface,
-        mut wl_output: Option<wl_output::WlOutput>,
+        wl_output: Option<wl_output::WlOutput>,
     ) {
         if surface
             .current_state()
             .capabilities
             .contains(xdg_toplevel::WmCapabilities::Fullscreen)
         {
-            // NOTE: This is only one part of the solution. We can set the
-            // location and configure size here, but the surface should be rendered fullscreen
-            // independently from its buffer size
-            let wl_surface = surface.wl_surface();
-
-            let Some((_window, space_name)) = self.window_and_space_for_surface(wl_surface) else {
-                return;
-            };
-            let Some(space) = self.spaces.get_mut(&space_name) else {
-                return;
-            };
-
-            let output_geometry = fullscreen_output_geometry(wl_surface, wl_output.as_ref(), space);
-
-            if let Some(geometry) = output_geometry {
-                let output = wl_output
-                    .as_ref()
-                    .and_then(Output::from_resource)
-                    .unwrap_or_else(|| space.outputs().next().unwrap().clone());
-                let client = self.display_handle.get_client(wl_surface.id()).unwrap();
-                for output in output.client_outputs(&client) {
-                    wl_output = Some(output);
+            if let Some((window, space_name)) =
+                self.window_and_space_for_surface(surface.wl_surface())
+            {
+                let output = wl_output.as_ref().and_then(Output::from_resource);
+                if let Err(err) =
+                    self.set_window_fullscreen(&window, &space_name, true, output, false)
+                {
+                    warn!(%err, "Unable to make window fullscreen");
                 }
-
-                surface.with_pending_state(|state| {
-                    state.states.set(xdg_toplevel::State::Fullscreen);
-                    state.size = Some(geometry.size);
-                    state.fullscreen_output = wl_output;
-                });
             }
         }
         // The protocol demands us to always reply with a configure,
@@ -354,94 +372,57 @@ impl XdgShellHandler for State {
     }
 
     fn unfullscreen_request(&mut self, surface: ToplevelSurface) {
-        if !surface
-            .current_state()
-            .states
-            .contains(xdg_toplevel::State::Fullscreen)
-        {
+        let Some((window, space_name)) = self.window_and_space_for_surface(surface.wl_surface())
+        else {
             return;
+        };
+        if let Err(err) = self.set_window_fullscreen(&window, &space_name, false, None, true) {
+            warn!(%err, "Unable to leave fullscreen");
         }
-
-        let ret = surface.with_pending_state(|state| {
-            state.states.unset(xdg_toplevel::State::Fullscreen);
-            state.size = None;
-            state.fullscreen_output.take()
-        });
-        if let Some(output) = ret {
-            let output = Output::from_resource(&output).unwrap();
-            self.backend_data.reset_buffers(&output);
-        }
-        surface.send_pending_configure();
     }
 
     fn maximize_request(&mut self, surface: ToplevelSurface) {
-        // NOTE: This should use layer-shell when it is implemented to
-        // get the correct maximum size
         if surface
             .current_state()
             .capabilities
             .contains(xdg_toplevel::WmCapabilities::Maximize)
         {
-            let (window, space_name) = self
-                .window_and_space_for_surface(surface.wl_surface())
-                .unwrap();
-            let space = self.spaces.get_mut(&space_name).unwrap();
-            let outputs_for_window = space.outputs_for_element(&window);
-            let output = outputs_for_window
-                .first()
-                // The window hasn't been mapped yet, use the primary output instead
-                .or_else(|| space.outputs().next())
-                // Assumes that at least one output exists
-                .expect("No outputs found");
-            let geometry = space.output_geometry(output).unwrap();
-
-            surface.with_pending_state(|state| {
-                state.states.set(xdg_toplevel::State::Maximized);
-                state.size = Some(geometry.size);
-            });
-            space.map_element(window, geometry.loc, true);
+            if let Some((window, space_name)) =
+                self.window_and_space_for_surface(surface.wl_surface())
+            {
+                if let Err(err) = self.set_window_maximized(&window, &space_name, true, false) {
+                    warn!(%err, "Unable to maximize window");
+                }
+            }
         }
-
         // The protocol demands us to always reply with a configure,
         // regardless if we fulfilled the request or not
         surface.send_configure();
     }
 
     fn unmaximize_request(&mut self, surface: ToplevelSurface) {
-        if !surface
-            .current_state()
-            .states
-            .contains(xdg_toplevel::State::Maximized)
-        {
+        let Some((window, space_name)) = self.window_and_space_for_surface(surface.wl_surface())
+        else {
             return;
+        };
+        if let Err(err) = self.set_window_maximized(&window, &space_name, false, true) {
+            warn!(%err, "Unable to unmaximize window");
         }
-
-        surface.with_pending_state(|state| {
-            state.states.unset(xdg_toplevel::State::Maximized);
-            state.size = None;
-        });
-        surface.send_pending_configure();
     }
 
     fn grab(&mut self, surface: PopupSurface, seat: wl_seat::WlSeat, serial: Serial) {
         let seat: Seat<State> = Seat::from_resource(&seat).unwrap();
         let kind = PopupKind::Xdg(surface);
         if let Some(root) = find_popup_root_surface(&kind).ok().and_then(|root| {
-            // TODO: Properly handle multiple spaces
             self.spaces
                 .values()
-                .next()
-                .unwrap()
-                .elements()
+                .flat_map(|space| space.elements())
                 .find(|w| w.wl_surface().map(|s| *s == root).unwrap_or(false))
                 .cloned()
                 .map(KeyboardFocusTarget::from)
                 .or_else(|| {
-                    self.spaces
+                    self.outputs
                         .values()
-                        .next()
-                        .unwrap()
-                        .outputs()
                         .find_map(|o| {
                             let map = layer_map_for_output(o);
                             map.layer_for_surface(&root, WindowSurfaceType::TOPLEVEL)
@@ -482,31 +463,25 @@ impl XdgShellHandler for State {
     fn app_id_changed(&mut self, surface: ToplevelSurface) {
         if let Some((window, space_name)) = self.window_and_space_for_surface(surface.wl_surface())
         {
-            self.place_window(&space_name, &window, false, None, false);
+            // Window rules can only match once the app id is known
+            let rule_space_name = self.window_rule_space(&window, &space_name);
+            if rule_space_name != space_name {
+                if let Some(space) = self.spaces.get_mut(&space_name) {
+                    space.unmap_elem(&window);
+                }
+            }
+            self.place_window(&rule_space_name, &window, false, None, false);
+            self.apply_window_rules(&window);
         }
     }
-}
 
-fn fullscreen_output_geometry(
-    wl_surface: &WlSurface,
-    wl_output: Option<&wl_output::WlOutput>,
-    space: &mut Space<WorkspaceWindow>,
-) -> Option<Rectangle<i32, Logical>> {
-    // First test if a specific output has been requested
-    // if the requested output is not found ignore the request
-    wl_output
-        .and_then(Output::from_resource)
-        .or_else(|| {
-            let w = space.elements().find(|window| {
-                window
-                    .wl_surface()
-                    .map(|s| &*s == wl_surface)
-                    .unwrap_or(false)
-            });
-            w.and_then(|w| space.outputs_for_element(w).first().cloned())
-        })
-        .as_ref()
-        .and_then(|o| space.output_geometry(o))
+    fn title_changed(&mut self, surface: ToplevelSurface) {
+        if let Some((window, space_name)) = self.window_and_space_for_surface(surface.wl_surface())
+        {
+            self.apply_window_rules(&window);
+            self.on_window_title_changed(&window, &space_name);
+        }
+    }
 }
 
 impl State {
@@ -642,6 +617,7 @@ impl State {
             window,
             space_name,
             initial_window_location,
+            drop_target: None,
         };
 
         pointer.set_grab(self, grab, serial, Focus::Clear);
@@ -0,0 +1,236 @@
+use crate::{workspace_window::WorkspaceWindow, State};
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::XdgToplevel;
+use smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_manager_v1::{
+    Request as ManagerRequest, XdgToplevelDragManagerV1,
+};
+use smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_v1::{
+    Error as DragError, Request as DragRequest, XdgToplevelDragV1,
+};
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::protocol::wl_data_source::WlDataSource;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, Weak,
+};
+use smithay::utils::{Logical, Point};
+use std::sync::Mutex;
+
+const MANAGER_VERSION: u32 = 1;
+
+pub trait XdgToplevelDragHandler {
+    fn xdg_toplevel_drag_state(&mut self) -> &mut XdgToplevelDragManagerState;
+}
+
+#[derive(Debug, Default)]
+pub struct ToplevelDragData {
+    data_source: Option<Weak<WlDataSource>>,
+    /// The attached toplevel and the offset of the pointer relative to its geometry
+    toplevel: Option<(XdgToplevel, Point<i32, Logical>)>,
+}
+
+#[derive(Debug, Default)]
+pub struct XdgToplevelDragManagerState {
+    drags: Vec<XdgToplevelDragV1>,
+}
+
+impl XdgToplevelDragManagerState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<XdgToplevelDragManagerV1, ()>,
+        D: Dispatch<XdgToplevelDragManagerV1, ()>,
+        D: Dispatch<XdgToplevelDragV1, Mutex<ToplevelDragData>>,
+        D: XdgToplevelDragHandler,
+        D: 'static,
+    {
+        display.create_global::<D, XdgToplevelDragManagerV1, _>(MANAGER_VERSION, ());
+
+        Self::default()
+    }
+
+    /// Returns the toplevel attached to the drag of the data source together with the pointer
+    /// offset relative to the toplevel geometry
+    pub fn attached_toplevel(
+        &self,
+        data_source: &WlDataSource,
+    ) -> Option<(XdgToplevel, Point<i32, Logical>)> {
+        self.drags.iter().find_map(|drag| {
+            let data = drag.data::<Mutex<ToplevelDragData>>()?.lock().unwrap();
+            let source = data.data_source.as_ref()?.upgrade().ok()?;
+            if &source != data_source {
+                return None;
+            }
+            data.toplevel.clone()
+        })
+    }
+}
+
+impl<D> GlobalDispatch<XdgToplevelDragManagerV1, (), D> for XdgToplevelDragManagerState
+where
+    D: GlobalDispatch<XdgToplevelDragManagerV1, ()>,
+    D: Dispatch<XdgToplevelDragManagerV1, ()>,
+    D: Dispatch<XdgToplevelDragV1, Mutex<ToplevelDragData>>,
+    D: XdgToplevelDragHandler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        manager: New<XdgToplevelDragManagerV1>,
+        _manager_state: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+}
+
+impl<D> Dispatch<XdgToplevelDragManagerV1, (), D> for XdgToplevelDragManagerState
+where
+    D: GlobalDispatch<XdgToplevelDragManagerV1, ()>,
+    D: Dispatch<XdgToplevelDragManagerV1, ()>,
+    D: Dispatch<XdgToplevelDragV1, Mutex<ToplevelDragData>>,
+    D: XdgToplevelDragHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _manager: &XdgToplevelDragManagerV1,
+        request: ManagerRequest,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ManagerRequest::GetXdgToplevelDrag { id, data_source } => {
+                let drag = data_init.init(
+                    id,
+                    Mutex::new(ToplevelDragData {
+                        data_source: Some(data_source.downgrade()),
+                        toplevel: None,
+                    }),
+                );
+                state.xdg_toplevel_drag_state().drags.push(drag);
+            }
+            ManagerRequest::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<XdgToplevelDragV1, Mutex<ToplevelDragData>, D> for XdgToplevelDragManagerState
+where
+    D: Dispatch<XdgToplevelDragV1, Mutex<ToplevelDragData>>,
+    D: XdgToplevelDragHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        drag: &XdgToplevelDragV1,
+        request: DragRequest,
+        data: &Mutex<ToplevelDragData>,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            DragRequest::Attach {
+                toplevel,
+                x_offset,
+                y_offset,
+            } => {
+                let already_attached = state.xdg_toplevel_drag_state().drags.iter().any(|other| {
+                    other != drag
+                        && other
+                            .data::<Mutex<ToplevelDragData>>()
+                            .and_then(|data| data.lock().unwrap().toplevel.clone())
+                            .is_some_and(|(other_toplevel, _)| other_toplevel == toplevel)
+                });
+                if already_attached {
+                    drag.post_error(
+                        DragError::ToplevelAttached,
+                        "toplevel is already attached to another drag",
+                    );
+                    return;
+                }
+                data.lock().unwrap().toplevel = Some((toplevel, (x_offset, y_offset).into()));
+            }
+            DragRequest::Destroy => {
+                state
+                    .xdg_toplevel_drag_state()
+                    .drags
+                    .retain(|other| other != drag);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        drag: &XdgToplevelDragV1,
+        _data: &Mutex<ToplevelDragData>,
+    ) {
+        state
+            .xdg_toplevel_drag_state()
+            .drags
+            .retain(|other| other != drag);
+    }
+}
+
+#[allow(missing_docs)]
+macro_rules! delegate_xdg_toplevel_drag {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_manager_v1::XdgToplevelDragManagerV1: ()
+        ] => $crate::protocols::xdg_toplevel_drag::XdgToplevelDragManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_manager_v1::XdgToplevelDragManagerV1: ()
+        ] => $crate::protocols::xdg_toplevel_drag::XdgToplevelDragManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_v1::XdgToplevelDragV1: std::sync::Mutex<$crate::protocols::xdg_toplevel_drag::ToplevelDragData>
+        ] => $crate::protocols::xdg_toplevel_drag::XdgToplevelDragManagerState);
+    };
+}
+
+impl XdgToplevelDragHandler for State {
+    fn xdg_toplevel_drag_state(&mut self) -> &mut XdgToplevelDragManagerState {
+        &mut self.xdg_toplevel_drag_state
+    }
+}
+
+delegate_xdg_toplevel_drag!(State);
+
+impl State {
+    /// The window which follows the pointer during the current drag and the pointer offset
+    /// relative to its geometry
+    pub fn dragged_toplevel(&self) -> Option<(WorkspaceWindow, Point<i32, Logical>)> {
+        let data_source = self.toplevel_drag_source.as_ref()?;
+        let (xdg_toplevel, offset) = self
+            .xdg_toplevel_drag_state
+            .attached_toplevel(data_source)?;
+        let toplevel = self
+            .xdg_shell_state
+            .toplevel_surfaces()
+            .iter()
+            .find(|toplevel| toplevel.xdg_toplevel() == &xdg_toplevel)?
+            .clone();
+        let (window, _) = self.window_and_space_for_surface(toplevel.wl_surface())?;
+        Some((window, offset))
+    }
+
+    /// Moves the toplevel attached to the current drag along with the pointer
+    pub fn update_toplevel_drag(&mut self) {
+        let Some((window, offset)) = self.dragged_toplevel() else {
+            return;
+        };
+        let Some(space_name) = self.space_of_window(&window) else {
+            return;
+        };
+        let location = self.pointer_location().to_i32_round() - offset;
+        if let Some(space) = self.spaces.get_mut(&space_name) {
+            space.map_element(window, location, true);
+        }
+    }
+}
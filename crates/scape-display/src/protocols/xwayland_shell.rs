@@ -1,6 +1,8 @@
+use crate::clipboard::{self, SelectionData};
+use crate::error::{Result, ScapeError};
 use crate::focus::KeyboardFocusTarget;
 use crate::grabs::{PointerMoveSurfaceGrab, PointerResizeSurfaceGrab, ResizeData, ResizeState};
-use crate::shell::SurfaceData;
+use crate::shell::{take_restore_geometry, SurfaceData};
 use crate::workspace_window::WorkspaceWindow;
 use crate::{application_window::ApplicationWindow, State};
 use smithay::delegate_xwayland_shell;
@@ -25,28 +27,26 @@ use smithay::{
         },
     },
     xwayland::{
-        xwm::{Reorder, ResizeEdge as X11ResizeEdge, XwmId},
+        xwm::{Reorder, ResizeEdge as X11ResizeEdge, WmWindowProperty, XwmId},
         X11Surface, X11Wm, XwmHandler,
     },
 };
 use std::{cell::RefCell, os::fd::OwnedFd};
 use tracing::{error, trace, warn};
 
-#[derive(Debug, Default)]
-struct OldGeometry(RefCell<Option<Rectangle<i32, Logical>>>);
-impl OldGeometry {
-    pub fn save(&self, geo: Rectangle<i32, Logical>) {
-        *self.0.borrow_mut() = Some(geo);
-    }
-
-    pub fn restore(&self) -> Option<Rectangle<i32, Logical>> {
-        self.0.borrow_mut().take()
+fn log_xwm_error(request: &str, result: Result<()>) {
+    if let Err(err) = result {
+        warn!(%err, request, "Unable to handle xwayland request");
     }
 }
 
 impl XwmHandler for State {
     fn xwm_state(&mut self, _xwm: XwmId) -> &mut X11Wm {
-        self.xwayland_state.as_mut().unwrap().wm.as_mut().unwrap()
+        // The xwm only sends requests after it was started and stored in the state
+        self.xwayland_state
+            .as_mut()
+            .and_then(|xwayland_state| xwayland_state.wm.as_mut())
+            .expect("Xwm requests are only sent while the xwm is running")
     }
 
     fn new_window(&mut self, _xwm: XwmId, _window: X11Surface) {
@@ -58,65 +58,35 @@ impl XwmHandler for State {
     }
 
     fn map_window_request(&mut self, _xwm: XwmId, x11_surface: X11Surface) {
-        if x11_surface.is_override_redirect() {
-            // Don't do anything for override-redirect windows
-            return;
-        }
-
-        if let Err(err) = x11_surface.set_mapped(true) {
-            error!(?err, ?x11_surface, "Unable to map x11 surface");
-            return;
-        }
-
-        let window = WorkspaceWindow::from(ApplicationWindow(Window::new_x11_window(
-            x11_surface.clone(),
-        )));
-        // TODO: Handle multiple spaces
-        let space_name = self.spaces.keys().next().unwrap().clone();
-        let rect = self.place_window(&space_name, &window, true, None, false);
-        let _bbox = self.spaces[&space_name].element_bbox(&window).unwrap();
-        x11_surface.configure(Some(rect)).unwrap();
-        window.set_ssd(!x11_surface.is_decorated());
-
-        let keyboard = self.seat.as_ref().unwrap().get_keyboard().unwrap();
-        let serial = SERIAL_COUNTER.next_serial();
-        keyboard.set_focus(self, Some(window.into()), serial);
+        let result = self.map_x11_window(&x11_surface);
+        log_xwm_error("map_window", result);
     }
 
     fn mapped_override_redirect_window(&mut self, _xwm: XwmId, x11_surface: X11Surface) {
         let location = x11_surface.geometry().loc;
-        // TODO: Handle multiple spaces
-        let space_name = self.spaces.keys().next().unwrap().clone();
-
-        self.spaces.get_mut(&space_name).unwrap().map_element(
-            WorkspaceWindow::from(ApplicationWindow(Window::new_x11_window(x11_surface))),
-            // TODO: Check why wired starts with a crazy high value
-            if location.x > 10_000 {
-                (0, 0)
-            } else {
-                (location.x, location.y)
-            },
-            true,
-        );
+        // Shown on the space of the output it is placed on
+        let space_name = self
+            .space_at(location.to_f64())
+            .map_or_else(|| self.space_under_pointer(), Ok);
+        let result = space_name.and_then(|space_name| {
+            self.space_mut(&space_name)?.map_element(
+                WorkspaceWindow::from(ApplicationWindow(Window::new_x11_window(x11_surface))),
+                // TODO: Check why wired starts with a crazy high value
+                if location.x > 10_000 {
+                    (0, 0)
+                } else {
+                    (location.x, location.y)
+                },
+                true,
+            );
+            Ok(())
+        });
+        log_xwm_error("mapped_override_redirect_window", result);
     }
 
     fn unmapped_window(&mut self, _xwm: XwmId, x11_surface: X11Surface) {
-        let Some(wl_surface) = x11_surface.wl_surface() else {
-            return;
-        };
-        let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
-            return;
-        };
-        let space = self.spaces.get_mut(&space_name).unwrap();
-        space.unmap_elem(&window);
-        if !x11_surface.is_override_redirect() {
-            x11_surface.set_mapped(false).unwrap();
-        }
-
-        let maybe_window = space.elements().next_back().cloned();
-        if let Some(window) = maybe_window {
-            self.focus_window(window, &space_name);
-        }
+        let result = self.unmap_x11_window(&x11_surface);
+        log_xwm_error("unmapped_window", result);
     }
 
     fn destroyed_window(&mut self, _xwm: XwmId, _window: X11Surface) {}
@@ -155,71 +125,39 @@ impl XwmHandler for State {
         let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
             return;
         };
-        let space = self.spaces.get_mut(&space_name).unwrap();
-        space.map_element(window, geometry.loc, false);
+        if let Ok(space) = self.space_mut(&space_name) {
+            space.map_element(window, geometry.loc, false);
+        }
         // TODO: We don't properly handle the order of override-redirect windows here,
         //       they are always mapped top and then never reordered.
     }
 
     fn maximize_request(&mut self, _xwm: XwmId, x11_surface: X11Surface) {
-        self.maximize_request_x11(&x11_surface);
+        let result = self.set_x11_window_state(&x11_surface, |state, window, space_name| {
+            state.set_window_maximized(window, space_name, true, true)
+        });
+        log_xwm_error("maximize", result);
     }
 
     fn unmaximize_request(&mut self, _xwm: XwmId, x11_surface: X11Surface) {
-        let Some(wl_surface) = x11_surface.wl_surface() else {
-            return;
-        };
-        let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
-            return;
-        };
-        let space = self.spaces.get_mut(&space_name).unwrap();
-
-        x11_surface.set_maximized(false).unwrap();
-        if let Some(old_geo) = x11_surface
-            .user_data()
-            .get::<OldGeometry>()
-            .and_then(|data| data.restore())
-        {
-            x11_surface.configure(old_geo).unwrap();
-            space.map_element(window, old_geo.loc, false);
-        }
+        let result = self.set_x11_window_state(&x11_surface, |state, window, space_name| {
+            state.set_window_maximized(window, space_name, false, true)
+        });
+        log_xwm_error("unmaximize", result);
     }
 
     fn fullscreen_request(&mut self, _xwm: XwmId, x11_surface: X11Surface) {
-        let Some(wl_surface) = x11_surface.wl_surface() else {
-            return;
-        };
-        let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
-            return;
-        };
-        let space = self.spaces.get_mut(&space_name).unwrap();
-
-        let outputs_for_window = space.outputs_for_element(&window);
-        let output = outputs_for_window
-            .first()
-            // The window hasn't been mapped yet, use the primary output instead
-            .or_else(|| space.outputs().next())
-            // Assumes that at least one output exists
-            .expect("No outputs found");
-        let geometry = space.output_geometry(output).unwrap();
-
-        x11_surface.set_fullscreen(true).unwrap();
-        window.set_ssd(false);
-        x11_surface.configure(geometry).unwrap();
+        let result = self.set_x11_window_state(&x11_surface, |state, window, space_name| {
+            state.set_window_fullscreen(window, space_name, true, None, true)
+        });
+        log_xwm_error("fullscreen", result);
     }
 
     fn unfullscreen_request(&mut self, _xwm: XwmId, x11_surface: X11Surface) {
-        let Some(wl_surface) = x11_surface.wl_surface() else {
-            return;
-        };
-        let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
-            return;
-        };
-        // let space = self.spaces.get_mut(&space_name).unwrap();
-
-        x11_surface.set_fullscreen(false).unwrap();
-        window.set_ssd(!x11_surface.is_decorated());
-        self.place_window(&space_name, &window, false, None, true);
+        let result = self.set_x11_window_state(&x11_surface, |state, window, space_name| {
+            state.set_window_fullscreen(window, space_name, false, None, true)
+        });
+        log_xwm_error("unfullscreen", result);
     }
 
     fn resize_request(
@@ -229,46 +167,8 @@ impl XwmHandler for State {
         _button: u32,
         edges: X11ResizeEdge,
     ) {
-        // luckily anvil only supports one seat anyway...
-        let start_data = self.pointer.as_ref().unwrap().grab_start_data().unwrap();
-
-        let Some(wl_surface) = x11_surface.wl_surface() else {
-            return;
-        };
-        let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
-            return;
-        };
-        let space = self.spaces.get_mut(&space_name).unwrap();
-
-        let geometry = window.geometry();
-        let loc = space.element_location(&window).unwrap();
-        let (initial_window_location, initial_window_size) = (loc, geometry.size);
-
-        with_states(&wl_surface, move |states| {
-            states
-                .data_map
-                .get::<RefCell<SurfaceData>>()
-                .unwrap()
-                .borrow_mut()
-                .resize_state = ResizeState::Resizing(ResizeData {
-                edges: edges.into(),
-                initial_window_location,
-                initial_window_size,
-            });
-        });
-
-        let grab = PointerResizeSurfaceGrab {
-            start_data,
-            window,
-            space_name,
-            edges: edges.into(),
-            initial_window_location,
-            initial_window_size,
-            last_window_size: initial_window_size,
-        };
-
-        let pointer = self.pointer.clone().unwrap();
-        pointer.set_grab(self, grab, SERIAL_COUNTER.next_serial(), Focus::Clear);
+        let result = self.resize_request_x11(&x11_surface, edges);
+        log_xwm_error("resize", result);
     }
 
     fn move_request(&mut self, _xwm: XwmId, window: X11Surface, _button: u32) {
@@ -276,7 +176,7 @@ impl XwmHandler for State {
     }
 
     fn allow_selection_access(&mut self, _xwm: XwmId, _selection: SelectionTarget) -> bool {
-        if let Some(keyboard) = self.seat.as_ref().unwrap().get_keyboard() {
+        if let Ok(keyboard) = self.keyboard() {
             // check that an X11 window is focused
             if let Some(KeyboardFocusTarget::Window(window)) = keyboard.current_focus() {
                 return window.is_x11();
@@ -292,21 +192,28 @@ impl XwmHandler for State {
         mime_type: String,
         fd: OwnedFd,
     ) {
+        let Ok(seat) = self.seat() else {
+            return;
+        };
         match selection {
             SelectionTarget::Clipboard => {
-                if let Err(err) =
-                    request_data_device_client_selection(self.seat.as_ref().unwrap(), mime_type, fd)
-                {
-                    error!(
-                        ?err,
-                        "Failed to request current wayland clipboard for Xwayland",
-                    );
+                // Text set by the config and screenshots are not owned by a client
+                match current_data_device_selection_userdata(seat) {
+                    Some(SelectionData::Text(text)) => clipboard::send_text(text, fd),
+                    Some(SelectionData::Image(png)) => clipboard::send_image(png, fd),
+                    _ => {
+                        if let Err(err) = request_data_device_client_selection(seat, mime_type, fd)
+                        {
+                            error!(
+                                ?err,
+                                "Failed to request current wayland clipboard for Xwayland",
+                            );
+                        }
+                    }
                 }
             }
             SelectionTarget::Primary => {
-                if let Err(err) =
-                    request_primary_client_selection(self.seat.as_ref().unwrap(), mime_type, fd)
-                {
+                if let Err(err) = request_primary_client_selection(seat, mime_type, fd) {
                     error!(
                         ?err,
                         "Failed to request current wayland primary selection for Xwayland",
@@ -318,33 +225,36 @@ impl XwmHandler for State {
 
     fn new_selection(&mut self, _xwm: XwmId, selection: SelectionTarget, mime_types: Vec<String>) {
         trace!(?selection, ?mime_types, "Got Selection from X11",);
+        let Ok(seat) = self.seat() else {
+            return;
+        };
+        let dh = &self.display_handle;
         // TODO check, that focused windows is X11 window before doing this
         match selection {
-            SelectionTarget::Clipboard => set_data_device_selection(
-                &self.display_handle,
-                self.seat.as_ref().unwrap(),
-                mime_types,
-                (),
-            ),
-            SelectionTarget::Primary => set_primary_selection(
-                &self.display_handle,
-                self.seat.as_ref().unwrap(),
-                mime_types,
-                (),
-            ),
+            SelectionTarget::Clipboard => {
+                set_data_device_selection(dh, seat, mime_types, SelectionData::Xwayland)
+            }
+            SelectionTarget::Primary => {
+                set_primary_selection(dh, seat, mime_types, SelectionData::Xwayland)
+            }
         }
     }
 
     fn cleared_selection(&mut self, _xwm: XwmId, selection: SelectionTarget) {
+        let Ok(seat) = self.seat() else {
+            return;
+        };
         match selection {
             SelectionTarget::Clipboard => {
-                if current_data_device_selection_userdata(self.seat.as_ref().unwrap()).is_some() {
-                    clear_data_device_selection(&self.display_handle, self.seat.as_ref().unwrap())
+                // Text set by the config stays when X11 clears its clipboard
+                if let Some(SelectionData::Xwayland) = current_data_device_selection_userdata(seat)
+                {
+                    clear_data_device_selection(&self.display_handle, seat)
                 }
             }
             SelectionTarget::Primary => {
-                if current_primary_selection_userdata(self.seat.as_ref().unwrap()).is_some() {
-                    clear_primary_selection(&self.display_handle, self.seat.as_ref().unwrap())
+                if let Some(SelectionData::Xwayland) = current_primary_selection_userdata(seat) {
+                    clear_primary_selection(&self.display_handle, seat)
                 }
             }
         }
@@ -355,9 +265,18 @@ impl XwmHandler for State {
     fn property_notify(
         &mut self,
         _xwm: XwmId,
-        _window: X11Surface,
-        _property: smithay::xwayland::xwm::WmWindowProperty,
+        x11_surface: X11Surface,
+        property: WmWindowProperty,
     ) {
+        if !matches!(property, WmWindowProperty::Title) {
+            return;
+        }
+        let Some(wl_surface) = x11_surface.wl_surface() else {
+            return;
+        };
+        if let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) {
+            self.on_window_title_changed(&window, &space_name);
+        }
     }
 
     fn minimize_request(&mut self, _xwm: XwmId, _window: X11Surface) {}
@@ -366,70 +285,156 @@ impl XwmHandler for State {
 }
 
 impl State {
-    pub fn maximize_request_x11(&mut self, x11_surface: &X11Surface) {
+    fn map_x11_window(&mut self, x11_surface: &X11Surface) -> Result<()> {
+        if x11_surface.is_override_redirect() {
+            // Don't do anything for override-redirect windows
+            return Ok(());
+        }
+
+        x11_surface.set_mapped(true)?;
+
+        let window = WorkspaceWindow::from(ApplicationWindow(Window::new_x11_window(
+            x11_surface.clone(),
+        )));
+        let space_name = self.window_rule_space(&window, &self.space_under_pointer()?);
+        let rect = self.place_window(&space_name, &window, true, None, false);
+        x11_surface.configure(Some(rect))?;
+        window.set_ssd(!x11_surface.is_decorated());
+        self.apply_window_rules(&window);
+        self.on_window_open(&window, &space_name);
+        self.startup_window_opened(&window.app_id());
+
+        if self.is_space_shown(&space_name) {
+            let keyboard = self.keyboard()?;
+            let serial = SERIAL_COUNTER.next_serial();
+            keyboard.set_focus(self, Some(window.into()), serial);
+        }
+        Ok(())
+    }
+
+    fn unmap_x11_window(&mut self, x11_surface: &X11Surface) -> Result<()> {
         let Some(wl_surface) = x11_surface.wl_surface() else {
-            return;
+            return Ok(());
         };
         let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
-            return;
+            return Ok(());
         };
-        let space = self.spaces.get_mut(&space_name).unwrap();
-
-        let old_geo = space.element_bbox(&window).unwrap();
-        let outputs_for_window = space.outputs_for_element(&window);
-        let output = outputs_for_window
-            .first()
-            // The window hasn't been mapped yet, use the primary output instead
-            .or_else(|| space.outputs().next())
-            // Assumes that at least one output exists
-            .expect("No outputs found");
-        let geometry = space.output_geometry(output).unwrap();
-
-        x11_surface.set_maximized(true).unwrap();
-        x11_surface.configure(geometry).unwrap();
-        x11_surface
-            .user_data()
-            .insert_if_missing(OldGeometry::default);
-        x11_surface
-            .user_data()
-            .get::<OldGeometry>()
-            .unwrap()
-            .save(old_geo);
-        space.map_element(window, geometry.loc, false);
+        if let Some(window_id) = window.id() {
+            self.stop_closing(window_id);
+        }
+        self.on_window_close(&window, &space_name);
+        let space = self.space_mut(&space_name)?;
+        space.unmap_elem(&window);
+        let maybe_window = space.elements().next_back().cloned();
+
+        if !x11_surface.is_override_redirect() {
+            x11_surface.set_mapped(false)?;
+        }
+
+        if let Some(window) = maybe_window.filter(|_| self.is_space_shown(&space_name)) {
+            self.focus_window(window, &space_name);
+        }
+        Ok(())
+    }
+
+    fn resize_request_x11(&mut self, x11_surface: &X11Surface, edges: X11ResizeEdge) -> Result<()> {
+        // luckily anvil only supports one seat anyway...
+        let pointer = self.pointer()?;
+        let start_data = pointer.grab_start_data().ok_or(ScapeError::NoGrab)?;
+
+        let Some(wl_surface) = x11_surface.wl_surface() else {
+            return Ok(());
+        };
+        let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
+            return Ok(());
+        };
+
+        let geometry = window.geometry();
+        let loc = self
+            .space(&space_name)?
+            .element_location(&window)
+            .ok_or_else(|| ScapeError::WindowNotMapped(space_name.clone()))?;
+        let (initial_window_location, initial_window_size) = (loc, geometry.size);
+
+        with_states(&wl_surface, move |states| {
+            states
+                .data_map
+                .insert_if_missing(|| RefCell::new(SurfaceData::default()));
+            states
+                .data_map
+                .get::<RefCell<SurfaceData>>()
+                .unwrap()
+                .borrow_mut()
+                .resize_state = ResizeState::Resizing(ResizeData {
+                edges: edges.into(),
+                initial_window_location,
+                initial_window_size,
+            });
+        });
+
+        let grab = PointerResizeSurfaceGrab {
+            start_data,
+            window,
+            space_name,
+            edges: edges.into(),
+            initial_window_location,
+            initial_window_size,
+            last_window_size: initial_window_size,
+        };
+
+        pointer.set_grab(self, grab, SERIAL_COUNTER.next_serial(), Focus::Clear);
+        Ok(())
+    }
+
+    /// Runs the state change on the window of the surface, if it is mapped
+    fn set_x11_window_state(
+        &mut self,
+        x11_surface: &X11Surface,
+        change: impl FnOnce(&mut Self, &WorkspaceWindow, &str) -> Result<()>,
+    ) -> Result<()> {
+        let Some(wl_surface) = x11_surface.wl_surface() else {
+            return Ok(());
+        };
+        let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
+            return Ok(());
+        };
+        change(self, &window, &space_name)
     }
 
     pub fn move_request_x11(&mut self, x11_surface: &X11Surface) {
+        let result = self.try_move_request_x11(x11_surface);
+        log_xwm_error("move", result);
+    }
+
+    fn try_move_request_x11(&mut self, x11_surface: &X11Surface) -> Result<()> {
         // luckily anvil only supports one seat anyway...
-        let Some(start_data) = self.pointer.as_ref().unwrap().grab_start_data() else {
-            return;
+        let pointer = self.pointer()?;
+        let Some(start_data) = pointer.grab_start_data() else {
+            return Ok(());
         };
 
         let Some(wl_surface) = x11_surface.wl_surface() else {
-            return;
+            return Ok(());
         };
         let Some((window, space_name)) = self.window_and_space_for_surface(&wl_surface) else {
-            return;
+            return Ok(());
         };
-        let space = self.spaces.get_mut(&space_name).unwrap();
 
-        let mut initial_window_location = space.element_location(&window).unwrap();
+        let mut initial_window_location = self
+            .space(&space_name)?
+            .element_location(&window)
+            .ok_or_else(|| ScapeError::WindowNotMapped(space_name.clone()))?;
 
         // If surface is maximized then unmaximize it
         if x11_surface.is_maximized() {
-            x11_surface.set_maximized(false).unwrap();
+            x11_surface.set_maximized(false)?;
             let pos = self.pointer_location();
             initial_window_location = (pos.x as i32, pos.y as i32).into();
-            if let Some(old_geo) = x11_surface
-                .user_data()
-                .get::<OldGeometry>()
-                .and_then(|data| data.restore())
-            {
-                x11_surface
-                    .configure(Rectangle::from_loc_and_size(
-                        initial_window_location,
-                        old_geo.size,
-                    ))
-                    .unwrap();
+            if let Some(old_geo) = take_restore_geometry(&wl_surface) {
+                x11_surface.configure(Rectangle::from_loc_and_size(
+                    initial_window_location,
+                    old_geo.size,
+                ))?;
             }
         }
 
@@ -438,10 +443,11 @@ impl State {
             window,
             space_name,
             initial_window_location,
+            drop_target: None,
         };
 
-        let pointer = self.pointer.clone().unwrap();
         pointer.set_grab(self, grab, SERIAL_COUNTER.next_serial(), Focus::Clear);
+        Ok(())
     }
 }
 
@@ -0,0 +1,445 @@
+//! Recording of an output into a video file. Frames the output renders anyway are copied and
+//! piped as raw RGBA into ffmpeg. It encodes with VAAPI if the GPU supports the codec and falls
+//! back to a software encoder otherwise. Only outputs of the udev backend can be recorded.
+
+use crate::{
+    error::Result,
+    render::{CustomRenderElements, GlMultiRenderer},
+    screenshot::{new_file, user_dir},
+    state::BackendData,
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use anyhow::{anyhow, bail, ensure, Context};
+use calloop::channel::{self, Event};
+use smithay::{
+    backend::{
+        allocator::{gbm::GbmBuffer, Fourcc},
+        drm::{compositor::RenderFrameResult, gbm::GbmFramebuffer},
+        renderer::{
+            element::{solid::SolidColorRenderElement, Id, Kind, RenderElement},
+            gles::GlesTexture,
+            utils::CommitCounter,
+            Bind, ExportMem, ImportAll, ImportMem, Offscreen, Renderer,
+        },
+    },
+    desktop::Space,
+    output::Output,
+    utils::{Logical, Physical, Rectangle, Scale, Size},
+};
+use std::{
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Frames waiting for the encoder, further frames are dropped until it caught up
+const MAX_QUEUED_FRAMES: usize = 8;
+/// Edge length of the indicator square
+const INDICATOR_SIZE: i32 = 12;
+/// Distance of the indicator to the top left corner of the output
+const INDICATOR_MARGIN: i32 = 8;
+const INDICATOR_COLOR: [f32; 4] = [0.9, 0.1, 0.1, 0.9];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    H264,
+    Vp9,
+}
+
+impl VideoCodec {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let codec = match name {
+            "h264" => VideoCodec::H264,
+            "vp9" => VideoCodec::Vp9,
+            _ => return None,
+        };
+        Some(codec)
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "mp4",
+            VideoCodec::Vp9 => "webm",
+        }
+    }
+
+    fn hardware_encoder(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "h264_vaapi",
+            VideoCodec::Vp9 => "vp9_vaapi",
+        }
+    }
+
+    fn software_encoder(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "libx264",
+            VideoCodec::Vp9 => "libvpx-vp9",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordingConfig {
+    /// Directory recordings are saved to, the videos directory of the user if none is set
+    pub directory: Option<PathBuf>,
+    pub codec: VideoCodec,
+    /// Frames captured per second
+    pub fps: u32,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            codec: VideoCodec::H264,
+            fps: 30,
+        }
+    }
+}
+
+/// Copy of a rendered frame which was not read back yet
+struct FrameCopy(<GlMultiRenderer<'static> as ExportMem>::TextureMapping);
+
+impl fmt::Debug for FrameCopy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameCopy").finish_non_exhaustive()
+    }
+}
+
+/// Output which is being recorded
+#[derive(Debug)]
+pub struct Recording {
+    output: Output,
+    /// Size of the frames in pixels, the recording stops if the output changes its size
+    size: Size<i32, Physical>,
+    /// Frames for the encoder thread, dropping the sender finishes the video
+    frames: SyncSender<Vec<u8>>,
+    /// Frames rendered sooner after the last recorded one are skipped
+    interval: Duration,
+    last_frame: Option<Instant>,
+    /// The last frame is read back once the next one is rendered, until then the GPU had time
+    /// to finish the copy and the compositor does not wait for it
+    pending: Option<FrameCopy>,
+    /// Copying or encoding failed, the recording is stopped after the frame
+    failed: bool,
+    /// Element of the indicator, it is left out of the video
+    indicator: Id,
+}
+
+impl Recording {
+    /// Indicator in the top left corner of the recorded output. It is not part of the video.
+    pub fn render_element<R>(
+        &self,
+        space: &Space<WorkspaceWindow>,
+        output: &Output,
+    ) -> Option<CustomRenderElements<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+    {
+        if *output != self.output || space.output_geometry(output).is_none() {
+            return None;
+        }
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let geometry = Rectangle::<i32, Logical>::from_loc_and_size(
+            (INDICATOR_MARGIN, INDICATOR_MARGIN),
+            (INDICATOR_SIZE, INDICATOR_SIZE),
+        )
+        .to_physical_precise_round(scale);
+        Some(CustomRenderElements::Solid(SolidColorRenderElement::new(
+            self.indicator.clone(),
+            geometry,
+            CommitCounter::default(),
+            INDICATOR_COLOR,
+            Kind::Unspecified,
+        )))
+    }
+
+    /// Passes the previous frame to the encoder and copies the frame which was just rendered,
+    /// unless it follows the previous one too closely
+    pub fn render_frame<'a, 'b, E>(
+        &mut self,
+        renderer: &mut GlMultiRenderer<'a>,
+        render_result: &RenderFrameResult<'b, GbmBuffer, GbmFramebuffer, E>,
+    ) where
+        E: RenderElement<GlMultiRenderer<'a>>,
+    {
+        if self.failed {
+            return;
+        }
+        if let Err(err) = self.record_frame(renderer, render_result) {
+            warn!(output = self.output.name(), "Stopping recording: {err:#}");
+            self.failed = true;
+        }
+    }
+
+    fn record_frame<'a, 'b, E>(
+        &mut self,
+        renderer: &mut GlMultiRenderer<'a>,
+        render_result: &RenderFrameResult<'b, GbmBuffer, GbmFramebuffer, E>,
+    ) -> anyhow::Result<()>
+    where
+        E: RenderElement<GlMultiRenderer<'a>>,
+    {
+        if let Some(FrameCopy(mapping)) = self.pending.take() {
+            let pixels = renderer
+                .map_texture(&mapping)
+                .map_err(|err| anyhow!("Unable to read frame: {err}"))?;
+            match self.frames.try_send(pixels.to_vec()) {
+                // The encoder is behind, ffmpeg timestamps the frames, so the video keeps its
+                // speed with a lower frame rate
+                Ok(()) | Err(TrySendError::Full(_)) => {}
+                Err(TrySendError::Disconnected(_)) => bail!("Encoder stopped"),
+            }
+        }
+        if self
+            .last_frame
+            .is_some_and(|last_frame| last_frame.elapsed() < self.interval)
+        {
+            return Ok(());
+        }
+
+        ensure!(
+            frame_size(&self.output) == Some(self.size),
+            "Output changed its size"
+        );
+        let transform = self.output.current_transform();
+        let scale = self.output.current_scale().fractional_scale();
+        let mode_size = self
+            .output
+            .current_mode()
+            .context("Output has no mode")?
+            .size;
+        // Drawing area after the output transform, like for screencopy
+        let damage = transform
+            .transform_rect_in(Rectangle::from_loc_and_size((0, 0), self.size), &mode_size);
+        let buffer = Offscreen::<GlesTexture>::create_buffer(
+            renderer,
+            Fourcc::Abgr8888,
+            Size::from((self.size.w, self.size.h)),
+        )
+        .map_err(|err| anyhow!("Unable to create offscreen buffer: {err}"))?;
+        renderer
+            .bind(buffer)
+            .map_err(|err| anyhow!("Unable to bind offscreen buffer: {err}"))?;
+        render_result
+            .blit_frame_result(
+                damage.size,
+                transform,
+                scale,
+                renderer,
+                [damage],
+                [self.indicator.clone()],
+            )
+            .map_err(|err| anyhow!("Unable to copy frame: {err:?}"))?;
+        let mapping = renderer
+            .copy_framebuffer(
+                Rectangle::from_loc_and_size((0, 0), (self.size.w, self.size.h)),
+                Fourcc::Abgr8888,
+            )
+            .map_err(|err| anyhow!("Unable to copy framebuffer: {err}"))?;
+        self.pending = Some(FrameCopy(mapping));
+        self.last_frame = Some(Instant::now());
+        Ok(())
+    }
+}
+
+/// Size of the frames of the output in pixels, after its transform
+fn frame_size(output: &Output) -> Option<Size<i32, Physical>> {
+    let mode = output.current_mode()?;
+    Some(output.current_transform().transform_size(mode.size))
+}
+
+impl State {
+    /// Starts recording the output with the name, the output under the pointer if none is given,
+    /// or stops its recording
+    pub fn toggle_recording(&mut self, output_name: Option<String>) -> Result<()> {
+        let output = self.output_or_under_pointer(output_name)?;
+        if self.recordings.remove(&output.name()).is_some() {
+            info!(output = output.name(), "Stopping recording");
+            self.backend_data.schedule_render();
+            return Ok(());
+        }
+        if let Err(err) = self.start_recording(output) {
+            warn!("Unable to start recording: {err:#}");
+        }
+        Ok(())
+    }
+
+    fn start_recording(&mut self, output: Output) -> anyhow::Result<()> {
+        let config = self.recording_config.clone();
+        ensure!(config.fps > 0, "Invalid frame rate {}", config.fps);
+        ensure!(
+            matches!(self.backend_data, BackendData::Udev(_)),
+            "Recording needs the udev backend"
+        );
+        let size = frame_size(&output).context("Output has no mode")?;
+        let directory = config
+            .directory
+            .clone()
+            .unwrap_or_else(default_recording_dir);
+
+        let (frames, receiver) = mpsc::sync_channel(MAX_QUEUED_FRAMES);
+        let (sender, finished) = channel::channel();
+        let (fps, codec) = (config.fps, config.codec);
+        thread::Builder::new()
+            .name("recording".to_string())
+            .spawn(move || {
+                let size = (size.w as u32, size.h as u32);
+                // The receiver is only gone if the compositor stops
+                let _ = sender.send(encode(receiver, size, fps, codec, &directory));
+            })
+            .context("Unable to start encoder thread")?;
+        self.loop_handle
+            .insert_source(finished, |event, _, _| match event {
+                Event::Msg(Ok(path)) => info!(path = %path.display(), "Saved recording"),
+                Event::Msg(Err(err)) => warn!("Recording failed: {err:#}"),
+                Event::Closed => {}
+            })
+            .map_err(|err| anyhow!("Unable to wait for the encoder: {err}"))?;
+
+        info!(output = output.name(), ?codec, fps, "Started recording");
+        self.recordings.insert(
+            output.name(),
+            Recording {
+                output,
+                size,
+                frames,
+                interval: Duration::from_secs_f64(1.0 / fps as f64),
+                last_frame: None,
+                pending: None,
+                failed: false,
+                indicator: Id::new(),
+            },
+        );
+        // The indicator causes the first frame
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Removes the recording of the output if recording its last frame failed
+    pub fn stop_failed_recording(&mut self, output: &Output) {
+        if self
+            .recordings
+            .get(&output.name())
+            .is_some_and(|recording| recording.failed)
+        {
+            self.recordings.remove(&output.name());
+            // Hides the indicator
+            self.backend_data.schedule_render();
+        }
+    }
+}
+
+/// Directory of videos of the user, recordings are saved there unless the config sets another
+/// one
+fn default_recording_dir() -> PathBuf {
+    user_dir("XDG_VIDEOS_DIR", "Videos")
+}
+
+/// First render node, which VAAPI encodes with
+fn render_node() -> Option<PathBuf> {
+    let mut nodes = fs::read_dir("/dev/dri")
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("renderD"))
+        })
+        .collect::<Vec<_>>();
+    nodes.sort();
+    nodes.into_iter().next()
+}
+
+/// Whether ffmpeg is able to encode a test frame with the hardware encoder of the codec
+fn vaapi_supported(render_node: &Path, codec: VideoCodec) -> bool {
+    Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-vaapi_device"])
+        .arg(render_node)
+        .args(["-f", "lavfi", "-i", "color=size=64x64", "-frames:v", "1"])
+        .args([
+            "-vf",
+            "format=nv12,hwupload",
+            "-c:v",
+            codec.hardware_encoder(),
+        ])
+        .args(["-f", "null", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Feeds the frames to ffmpeg until the sender is dropped and returns the path of the video
+fn encode(
+    frames: Receiver<Vec<u8>>,
+    (width, height): (u32, u32),
+    fps: u32,
+    codec: VideoCodec,
+    directory: &Path,
+) -> anyhow::Result<PathBuf> {
+    let path = new_file(directory, "recording", codec.extension())?;
+    let hardware = render_node().filter(|node| vaapi_supported(node, codec));
+    // Encoders need an even width and height
+    let crop = "crop=trunc(iw/2)*2:trunc(ih/2)*2";
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-hide_banner", "-loglevel", "error"])
+        .args(["-f", "rawvideo", "-pixel_format", "rgba"])
+        .args(["-video_size", &format!("{width}x{height}")])
+        .args([
+            "-framerate",
+            &fps.to_string(),
+            "-use_wallclock_as_timestamps",
+            "1",
+        ]);
+    match &hardware {
+        Some(render_node) => {
+            command
+                .arg("-vaapi_device")
+                .arg(render_node)
+                .args(["-i", "-", "-vf", &format!("{crop},format=nv12,hwupload")])
+                .args(["-c:v", codec.hardware_encoder()]);
+        }
+        None => {
+            command
+                .args(["-i", "-", "-vf", &format!("{crop},format=yuv420p")])
+                .args(["-c:v", codec.software_encoder()]);
+        }
+    }
+    info!(
+        path = %path.display(),
+        encoder = if hardware.is_some() {
+            codec.hardware_encoder()
+        } else {
+            codec.software_encoder()
+        },
+        "Encoding recording"
+    );
+    let mut child = command
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .context("Unable to start ffmpeg")?;
+
+    let mut stdin = child.stdin.take().context("No input of ffmpeg")?;
+    let res = frames
+        .iter()
+        .try_for_each(|frame| stdin.write_all(&frame))
+        .context("Unable to pass frame to ffmpeg");
+    // Closing the input makes ffmpeg finish the video
+    drop(stdin);
+    let status = child.wait().context("Unable to wait for ffmpeg")?;
+    res?;
+    ensure!(status.success(), "ffmpeg failed with {status}");
+    Ok(path)
+}
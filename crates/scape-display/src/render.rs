@@ -2,9 +2,10 @@ use std::fmt::Debug;
 
 #[cfg(feature = "debug")]
 use crate::drawing::FpsElement;
-use crate::drawing::{PointerRenderElement, CLEAR_COLOR};
+use crate::drawing::PointerRenderElement;
 use crate::{
     state::SessionLock,
+    thumbnail::WindowThumbnail,
     workspace_window::{WorkspaceWindow, WorkspaceWindowRenderElement},
 };
 use smithay::backend::renderer::element::{Element, Id, UnderlyingStorage};
@@ -19,6 +20,7 @@ use smithay::{
         renderer::{
             damage::{Error as OutputDamageTrackerError, OutputDamageTracker, RenderOutputResult},
             element::{
+                solid::SolidColorRenderElement,
                 surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
                 utils::{
                     ConstrainAlign, ConstrainScaleBehavior, CropRenderElement,
@@ -112,6 +114,7 @@ smithay::backend::renderer::element::render_elements! {
     pub CustomRenderElements<R> where R: ImportAll + ImportMem;
     Pointer=PointerRenderElement<R>,
     Surface=WaylandSurfaceRenderElement<R>,
+    Solid=SolidColorRenderElement,
     // Note: We would like to borrow this element instead, but that would introduce
     // a feature-dependent lifetime, which introduces a lot more feature bounds
     // as the whole type changes and we can't have an unused lifetime (for when "debug" is disabled)
@@ -125,6 +128,7 @@ impl<R: Renderer + Debug> Debug for CustomRenderElements<R> {
         match self {
             Self::Pointer(arg0) => f.debug_tuple("Pointer").field(arg0).finish(),
             Self::Surface(arg0) => f.debug_tuple("Surface").field(arg0).finish(),
+            Self::Solid(arg0) => f.debug_tuple("Solid").field(arg0).finish(),
             #[cfg(feature = "debug")]
             Self::Fps(arg0) => f.debug_tuple("Fps").field(arg0).finish(),
             Self::_GenericCatcher(arg0) => f.debug_tuple("_GenericCatcher").field(arg0).finish(),
@@ -433,6 +437,52 @@ where
         })
 }
 
+/// Renders the window of the thumbnail scaled down to fit into the thumbnail geometry
+pub fn window_thumbnail_elements<R, C>(
+    renderer: &mut R,
+    space: &Space<WorkspaceWindow>,
+    output: &Output,
+    window_thumbnail: &WindowThumbnail,
+) -> Vec<C>
+where
+    R: Renderer + ImportAll + ImportMem + AsGlowRenderer,
+    <R as Renderer>::TextureId: Clone + 'static,
+    WorkspaceWindowRenderElement<R>: RenderElement<R>,
+    C: From<
+        CropRenderElement<
+            RelocateRenderElement<RescaleRenderElement<WorkspaceWindowRenderElement<R>>>,
+        >,
+    >,
+{
+    let Some(output_geometry) = space.output_geometry(output) else {
+        return Vec::new();
+    };
+    if !output_geometry.overlaps(window_thumbnail.geometry) {
+        return Vec::new();
+    }
+
+    let constrain_behavior = ConstrainBehavior {
+        reference: ConstrainReference::BoundingBox,
+        behavior: ConstrainScaleBehavior::Fit,
+        align: ConstrainAlign::CENTER,
+    };
+    let constrain = Rectangle::from_loc_and_size(
+        window_thumbnail.geometry.loc - output_geometry.loc,
+        window_thumbnail.geometry.size,
+    );
+    constrain_space_element(
+        renderer,
+        &window_thumbnail.window,
+        constrain.loc,
+        1.0,
+        output.current_scale().fractional_scale(),
+        constrain,
+        constrain_behavior,
+    )
+    .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
 #[cfg_attr(feature = "profiling", profiling::function)]
 pub fn output_elements<R>(
     output: &Output,
@@ -440,7 +490,9 @@ pub fn output_elements<R>(
     custom_elements: impl IntoIterator<Item = CustomRenderElements<R>>,
     renderer: &mut R,
     show_window_preview: bool,
+    window_thumbnail: &Option<WindowThumbnail>,
     session_lock: &Option<SessionLock>,
+    clear_color: [f32; 4],
 ) -> (Vec<OutputRenderElements<R>>, [f32; 4])
 where
     R: Renderer + ImportAll + ImportMem + AsGlowRenderer,
@@ -450,7 +502,7 @@ where
     if let Some(session_lock) = session_lock {
         return (
             session_lock_elements(renderer, output, session_lock),
-            CLEAR_COLOR,
+            clear_color,
         );
     }
 
@@ -463,6 +515,15 @@ where
         output_render_elements.extend(space_preview_elements(renderer, space, output));
     }
 
+    if let Some(window_thumbnail) = window_thumbnail {
+        output_render_elements.extend(window_thumbnail_elements(
+            renderer,
+            space,
+            output,
+            window_thumbnail,
+        ));
+    }
+
     let space_elements = smithay::desktop::space::space_render_elements::<_, WorkspaceWindow, _>(
         renderer,
         [space],
@@ -472,7 +533,7 @@ where
     .expect("output without mode?");
     output_render_elements.extend(space_elements.into_iter().map(OutputRenderElements::Space));
 
-    (output_render_elements, CLEAR_COLOR)
+    (output_render_elements, clear_color)
 }
 
 fn session_lock_elements<R>(
@@ -509,7 +570,9 @@ pub fn render_output<'a, 'damage, R>(
     damage_tracker: &'damage mut OutputDamageTracker,
     age: usize,
     show_window_preview: bool,
+    window_thumbnail: &Option<WindowThumbnail>,
     session_lock: &Option<SessionLock>,
+    clear_color: [f32; 4],
 ) -> Result<RenderOutputResult<'damage>, OutputDamageTrackerError<R>>
 where
     R: Renderer + ImportAll + ImportMem + AsGlowRenderer,
@@ -523,7 +586,9 @@ where
         custom_elements,
         renderer,
         show_window_preview,
+        window_thumbnail,
         session_lock,
+        clear_color,
     );
     damage_tracker.render_output(renderer, age, &elements, clear_color)
 }
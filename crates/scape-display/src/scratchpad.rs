@@ -0,0 +1,188 @@
+use crate::{
+    error::{Result, ScapeError},
+    state::active_space,
+    window_placeholder::is_descendant,
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{
+    desktop::{layer_map_for_output, space::SpaceElement},
+    utils::{IsAlive, Point, Rectangle, SERIAL_COUNTER},
+};
+use tracing::{info, warn};
+
+/// Share of the output a scratchpad window gets if it did not choose a size itself
+const DEFAULT_SIZE_FACTOR: f64 = 0.6;
+
+/// Marks an application window as the window of the named scratchpad
+#[derive(Debug)]
+pub struct ScratchpadWindow(pub String);
+
+#[derive(Debug, Default)]
+pub struct Scratchpad {
+    /// Process started for the scratchpad until its first window is mapped
+    pid: Option<u32>,
+    /// Window of the scratchpad, it stays tracked while it is hidden in no space
+    window: Option<WorkspaceWindow>,
+}
+
+impl State {
+    /// Shows the scratchpad window on the output under the pointer or hides it if it is shown
+    /// there already. The command is started on first use and whenever its window was closed.
+    pub fn toggle_scratchpad(&mut self, name: String, command: String, args: Vec<String>) {
+        let scratchpad = self.scratchpads.entry(name.clone()).or_default();
+        if scratchpad
+            .window
+            .as_ref()
+            .is_some_and(|window| !window.alive())
+        {
+            scratchpad.window = None;
+            scratchpad.pid = None;
+        }
+        let starting = scratchpad.pid.is_some();
+
+        match scratchpad.window.clone() {
+            Some(window) => {
+                if let Err(err) = self.toggle_scratchpad_window(&window) {
+                    warn!(%err, name, "Unable to toggle scratchpad");
+                }
+            }
+            // The process is started already, its window is not mapped yet
+            None if starting => {}
+            None => {
+                info!(name, command, "Starting scratchpad");
+                let pid = self.spawn(&command, &args, &[], None);
+                if let Some(scratchpad) = self.scratchpads.get_mut(&name) {
+                    scratchpad.pid = pid;
+                }
+            }
+        }
+    }
+
+    fn toggle_scratchpad_window(&mut self, window: &WorkspaceWindow) -> Result<()> {
+        // Without an output no space is shown where the scratchpad could appear
+        let output = self.output_under_pointer().ok_or(ScapeError::NoSpace)?;
+        let target_space = active_space(&output).ok_or(ScapeError::NoSpace)?;
+        let current_space = self
+            .spaces
+            .iter()
+            .find(|(_, space)| space.elements().any(|element| element == window))
+            .map(|(space_name, _)| space_name.clone());
+
+        if let Some(current_space) = current_space {
+            self.space_mut(&current_space)?.unmap_elem(window);
+            if current_space == target_space {
+                self.hide_scratchpad_window(window, &current_space)?;
+                return Ok(());
+            }
+        }
+
+        // Centered on the output, the window keeps the size it had before it was hidden
+        let space = self.space(&target_space)?;
+        let output_geometry = space
+            .output_geometry(&output)
+            .map(|geometry| {
+                let zone = layer_map_for_output(&output).non_exclusive_zone();
+                Rectangle::from_loc_and_size(geometry.loc + zone.loc, zone.size)
+            })
+            .ok_or_else(|| ScapeError::OutputNotFound(output.name()))?;
+        let mut size = window.geometry().size;
+        if size.w <= 0 || size.h <= 0 {
+            size = output_geometry
+                .size
+                .to_f64()
+                .upscale(DEFAULT_SIZE_FACTOR)
+                .to_i32_round();
+        }
+        let position = output_geometry.loc
+            + Point::from((
+                (output_geometry.size.w - size.w) / 2,
+                (output_geometry.size.h - size.h) / 2,
+            ));
+
+        info!(
+            app_id = window.app_id(),
+            space_name = target_space,
+            "Showing scratchpad"
+        );
+        window.position(position, size, output_geometry.size, true);
+        self.space_mut(&target_space)?
+            .map_element(window.clone(), position, true);
+        self.focus_window(window.clone(), &target_space);
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Moves the focus to the topmost remaining window of the space if the hidden window had it
+    fn hide_scratchpad_window(&mut self, window: &WorkspaceWindow, space_name: &str) -> Result<()> {
+        info!(app_id = window.app_id(), space_name, "Hiding scratchpad");
+        if let WorkspaceWindow::ApplicationWindow(application_window) = window {
+            application_window.set_suspended(true);
+        }
+
+        let keyboard = self.keyboard()?;
+        let focused = keyboard
+            .current_focus()
+            .and_then(|focus| WorkspaceWindow::try_from(focus).ok())
+            .is_some_and(|focus| &focus == window);
+        if focused {
+            let next = self
+                .space(space_name)?
+                .elements()
+                .rev()
+                .find(|element| matches!(element, WorkspaceWindow::ApplicationWindow(_)))
+                .cloned();
+            match next {
+                Some(next) => self.focus_window(next, space_name),
+                None => keyboard.set_focus(self, None, SERIAL_COUNTER.next_serial()),
+            }
+        }
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Whether the window belongs to a scratchpad. The first window of a started scratchpad
+    /// process becomes its window.
+    pub fn claim_scratchpad_window(&mut self, window: &WorkspaceWindow) -> bool {
+        let WorkspaceWindow::ApplicationWindow(application_window) = window else {
+            return false;
+        };
+        if application_window
+            .user_data()
+            .get::<ScratchpadWindow>()
+            .is_some()
+        {
+            return true;
+        }
+        if !self
+            .scratchpads
+            .values()
+            .any(|scratchpad| scratchpad.window.is_none() && scratchpad.pid.is_some())
+        {
+            return false;
+        }
+        let Some(pid) = self.window_pid(window) else {
+            return false;
+        };
+        let Some((name, scratchpad)) = self.scratchpads.iter_mut().find(|(_, scratchpad)| {
+            scratchpad.window.is_none()
+                && scratchpad
+                    .pid
+                    .is_some_and(|ancestor| is_descendant(pid, ancestor))
+        }) else {
+            return false;
+        };
+
+        info!(
+            name,
+            app_id = window.app_id(),
+            "Window of scratchpad mapped"
+        );
+        scratchpad.window = Some(window.clone());
+        scratchpad.pid = None;
+        application_window
+            .user_data()
+            .insert_if_missing(|| ScratchpadWindow(name.clone()));
+        true
+    }
+}
@@ -0,0 +1,640 @@
+//! Screenshots of outputs, windows and regions selected with the pointer. They are rendered
+//! offscreen, independent of what the outputs currently show, so the cursor and overlays of the
+//! compositor are left out. The PNG is encoded and written on a separate thread.
+
+use crate::{
+    action::Action,
+    error::{Result, ScapeError},
+    render::{output_elements, AsGlowRenderer, CustomRenderElements, OutputRenderElements},
+    state::{active_space, BackendData, SessionLock},
+    workspace_window::{WorkspaceWindow, WorkspaceWindowRenderElement},
+    State,
+};
+use anyhow::{anyhow, bail, Context};
+use calloop::channel::{self, Event};
+use image::{ImageFormat, RgbaImage};
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::OutputDamageTracker,
+            element::{solid::SolidColorRenderElement, AsRenderElements, Id, Kind, RenderElement},
+            gles::GlesTexture,
+            utils::CommitCounter,
+            Bind, ExportMem, ImportAll, ImportMem, Offscreen, Renderer,
+        },
+    },
+    desktop::{space::SpaceElement, Space},
+    input::{
+        keyboard::Keysym,
+        pointer::{CursorIcon, CursorImageStatus},
+    },
+    output::Output,
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
+
+/// Width of the border around a selected region
+const BORDER_WIDTH: i32 = 2;
+const BORDER_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+/// Tint of the selected region, or of the whole output before the selection started
+const FILL_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.3];
+
+/// What a screenshot captures
+#[derive(Debug, Clone)]
+pub enum ScreenshotTarget {
+    /// The output with the name, the output under the pointer if none is given
+    Output(Option<String>),
+    /// The window with the id, or the focused window, without the windows covering it
+    Window(Option<u64>),
+    /// A region which is selected by dragging the pointer
+    Region,
+}
+
+impl ScreenshotTarget {
+    pub fn from_name(name: &str, output: Option<String>, window: Option<u64>) -> Option<Self> {
+        let target = match name {
+            "output" => ScreenshotTarget::Output(output),
+            "window" => ScreenshotTarget::Window(window),
+            "region" => ScreenshotTarget::Region,
+            _ => return None,
+        };
+        Some(target)
+    }
+}
+
+/// What is rendered into the image of a screenshot or a frame of a recording
+#[derive(Debug)]
+pub enum Capture {
+    /// Part of the output, relative to its top left corner
+    Output {
+        output: Output,
+        space_name: String,
+        region: Rectangle<i32, Logical>,
+    },
+    Window {
+        window: WorkspaceWindow,
+        scale: f64,
+    },
+}
+
+/// Selection of the region for a screenshot, the pointer is dragged from one corner to the
+/// opposite one. Escape aborts the selection.
+#[derive(Debug)]
+pub struct RegionSelect {
+    /// Where the drag started and the output the region is limited to, `None` until a button is
+    /// pressed
+    start: Option<(Point<f64, Logical>, Output)>,
+    clipboard: bool,
+    fill: Id,
+    borders: [Id; 4],
+}
+
+impl RegionSelect {
+    fn new(clipboard: bool) -> Self {
+        Self {
+            start: None,
+            clipboard,
+            fill: Id::new(),
+            borders: [Id::new(), Id::new(), Id::new(), Id::new()],
+        }
+    }
+
+    /// Region between the start of the drag and the pointer, limited to the output the drag
+    /// started on
+    fn region(
+        &self,
+        space: &Space<WorkspaceWindow>,
+        pointer_location: Point<f64, Logical>,
+    ) -> Option<(Output, Rectangle<i32, Logical>)> {
+        let (start, output) = self.start.as_ref()?;
+        let output_geometry = space.output_geometry(output)?;
+        let (start, end) = (start.to_i32_round(), pointer_location.to_i32_round());
+        let region = Rectangle::from_loc_and_size(
+            (start.x.min(end.x), start.y.min(end.y)),
+            ((start.x - end.x).abs(), (start.y - end.y).abs()),
+        );
+        let region = region
+            .intersection(output_geometry)
+            .filter(|region| !region.is_empty())?;
+        Some((output.clone(), region))
+    }
+
+    /// Tints the whole output until the drag starts, then the selected region with a border
+    pub fn render_elements<R>(
+        &self,
+        space: &Space<WorkspaceWindow>,
+        output: &Output,
+        pointer_location: Point<f64, Logical>,
+    ) -> Vec<CustomRenderElements<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+    {
+        let Some(output_geometry) = space.output_geometry(output) else {
+            return Vec::new();
+        };
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let solid = |id: &Id, geometry: Rectangle<i32, Physical>, color: [f32; 4]| {
+            CustomRenderElements::Solid(SolidColorRenderElement::new(
+                id.clone(),
+                geometry,
+                CommitCounter::default(),
+                color,
+                Kind::Unspecified,
+            ))
+        };
+
+        if self.start.is_none() {
+            let geometry = Rectangle::from_loc_and_size((0, 0), output_geometry.size)
+                .to_physical_precise_round(scale);
+            return vec![solid(&self.fill, geometry, FILL_COLOR)];
+        }
+        let Some((_, region)) = self
+            .region(space, pointer_location)
+            .filter(|(region_output, _)| region_output == output)
+        else {
+            return Vec::new();
+        };
+
+        let region = Rectangle::from_loc_and_size(region.loc - output_geometry.loc, region.size)
+            .to_physical_precise_round(scale);
+        let border = ((BORDER_WIDTH as f64 * scale.x).round() as i32).max(1);
+        let (loc, size) = (region.loc, region.size);
+        let borders = [
+            Rectangle::from_loc_and_size(loc, (size.w, border)),
+            Rectangle::from_loc_and_size((loc.x, loc.y + size.h - border), (size.w, border)),
+            Rectangle::from_loc_and_size(loc, (border, size.h)),
+            Rectangle::from_loc_and_size((loc.x + size.w - border, loc.y), (border, size.h)),
+        ];
+        self.borders
+            .iter()
+            .zip(borders)
+            .map(|(id, geometry)| solid(id, geometry, BORDER_COLOR))
+            .chain([solid(&self.fill, region, FILL_COLOR)])
+            .collect()
+    }
+}
+
+impl State {
+    /// Captures the target and saves it as PNG in the screenshot directory. With `clipboard` the
+    /// image is offered in the clipboard as well.
+    pub fn screenshot(&mut self, target: ScreenshotTarget, clipboard: bool) -> Result<()> {
+        let capture = match target {
+            ScreenshotTarget::Output(output_name) => {
+                let output = self.output_or_under_pointer(output_name)?;
+                self.output_capture(output)?
+            }
+            ScreenshotTarget::Window(window_id) => {
+                let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+                    return Ok(());
+                };
+                let (space_name, window) = self.window_by_id(window_id)?;
+                let scale = self
+                    .space(&space_name)?
+                    .outputs_for_element(&window)
+                    .first()
+                    .map_or(1.0, |output| output.current_scale().fractional_scale());
+                Capture::Window { window, scale }
+            }
+            ScreenshotTarget::Region => {
+                info!("Selecting region for screenshot");
+                self.region_select = Some(RegionSelect::new(clipboard));
+                self.cursor_state
+                    .update_status(CursorImageStatus::Named(CursorIcon::Crosshair));
+                self.backend_data.schedule_render();
+                return Ok(());
+            }
+        };
+        self.take_screenshot(&capture, clipboard);
+        Ok(())
+    }
+
+    /// Capture of the whole output with the space it currently shows
+    pub fn output_capture(&self, output: Output) -> Result<Capture> {
+        let space_name = active_space(&output).ok_or(ScapeError::NoSpace)?;
+        let size = self
+            .space(&space_name)?
+            .output_geometry(&output)
+            .ok_or_else(|| ScapeError::NoOutput(space_name.clone()))?
+            .size;
+        Ok(Capture::Output {
+            output,
+            space_name,
+            region: Rectangle::from_loc_and_size((0, 0), size),
+        })
+    }
+
+    /// Starts the drag of the region selection on a button press and takes the screenshot once
+    /// the button is released. Returns `false` if no region is being selected.
+    pub fn region_select_button(&mut self, pressed: bool) -> bool {
+        let Some(started) = self
+            .region_select
+            .as_ref()
+            .map(|region_select| region_select.start.is_some())
+        else {
+            return false;
+        };
+        match (pressed, started) {
+            (true, false) => {
+                let start = self
+                    .output_under_pointer()
+                    .map(|output| (self.pointer_location(), output));
+                if let Some(region_select) = &mut self.region_select {
+                    region_select.start = start;
+                }
+                return true;
+            }
+            // Other buttons during the drag and buttons held before the selection are ignored
+            (true, true) | (false, false) => return true,
+            (false, true) => {}
+        }
+
+        let Some(region_select) = self.region_select.take() else {
+            return true;
+        };
+        self.end_region_select();
+        let region = region_select.start.as_ref().and_then(|(_, output)| {
+            let space_name = active_space(output)?;
+            let space = self.spaces.get(&space_name)?;
+            let (output, region) = region_select.region(space, self.pointer_location())?;
+            let output_location = space.output_geometry(&output)?.loc;
+            Some(Capture::Output {
+                output,
+                space_name,
+                region: Rectangle::from_loc_and_size(region.loc - output_location, region.size),
+            })
+        });
+        match region {
+            Some(capture) => self.take_screenshot(&capture, region_select.clipboard),
+            None => info!("Selected region is empty, no screenshot is taken"),
+        }
+        true
+    }
+
+    /// Consumes all keys while a region is being selected, escape aborts the selection
+    pub fn region_select_key(&self, keysym: Keysym) -> Option<Action> {
+        self.region_select.as_ref()?;
+        let action = match keysym {
+            Keysym::Escape => Action::CancelScreenshot,
+            _ => Action::None,
+        };
+        Some(action)
+    }
+
+    pub fn cancel_screenshot(&mut self) {
+        if self.region_select.take().is_some() {
+            info!("Screenshot aborted");
+            self.end_region_select();
+        }
+    }
+
+    fn end_region_select(&mut self) {
+        self.cursor_state
+            .update_status(CursorImageStatus::default_named());
+        self.backend_data.schedule_render();
+    }
+
+    fn take_screenshot(&mut self, capture: &Capture, clipboard: bool) {
+        let image = match self.capture(capture) {
+            Ok(image) => image,
+            Err(err) => {
+                warn!("Unable to take screenshot: {err:#}");
+                return;
+            }
+        };
+        let directory = self
+            .screenshot_dir
+            .clone()
+            .unwrap_or_else(default_screenshot_dir);
+
+        let (sender, receiver) = channel::channel();
+        let res = thread::Builder::new()
+            .name("screenshot".to_string())
+            .spawn(move || {
+                // The receiver is only gone if the compositor stops
+                let _ = sender.send(save_png(&image, &directory));
+            });
+        if let Err(err) = res {
+            warn!(?err, "Unable to start saving the screenshot");
+            return;
+        }
+        let res = self
+            .loop_handle
+            .insert_source(receiver, move |event, _, state| {
+                let Event::Msg(result) = event else {
+                    return;
+                };
+                match result {
+                    Ok((path, png)) => {
+                        info!(path = %path.display(), "Saved screenshot");
+                        if clipboard {
+                            state.set_clipboard_image(png);
+                        }
+                    }
+                    Err(err) => warn!("Unable to save screenshot: {err:#}"),
+                }
+            });
+        if let Err(err) = res {
+            warn!(?err, "Unable to wait for the screenshot to be saved");
+        }
+    }
+
+    /// Renders the capture offscreen and reads the image back
+    pub fn capture(&mut self, capture: &Capture) -> anyhow::Result<RgbaImage> {
+        let clear_color = self.theme.palette().background;
+        match &mut self.backend_data {
+            BackendData::Udev(udev_data) => render_capture(
+                &mut udev_data.primary_renderer()?,
+                &self.spaces,
+                &self.session_lock,
+                clear_color,
+                capture,
+            ),
+            BackendData::Winit(winit_data) => render_capture(
+                winit_data.renderer(),
+                &self.spaces,
+                &self.session_lock,
+                clear_color,
+                capture,
+            ),
+            BackendData::None => bail!("Screenshots need a backend to render"),
+        }
+    }
+}
+
+fn render_capture<R>(
+    renderer: &mut R,
+    spaces: &HashMap<String, Space<WorkspaceWindow>>,
+    session_lock: &Option<SessionLock>,
+    clear_color: [f32; 4],
+    capture: &Capture,
+) -> anyhow::Result<RgbaImage>
+where
+    R: Renderer
+        + ImportAll
+        + ImportMem
+        + AsGlowRenderer
+        + Offscreen<GlesTexture>
+        + Bind<GlesTexture>
+        + ExportMem,
+    <R as Renderer>::TextureId: Clone + 'static,
+    WorkspaceWindowRenderElement<R>: RenderElement<R>,
+    OutputRenderElements<R>: RenderElement<R>,
+{
+    match capture {
+        Capture::Output {
+            output,
+            space_name,
+            region,
+        } => {
+            let space = spaces
+                .get(space_name)
+                .with_context(|| format!("Space {space_name} does not exist"))?;
+            let output_geometry = space
+                .output_geometry(output)
+                .context("Output is not mapped")?;
+            let scale = Scale::from(output.current_scale().fractional_scale());
+            let (elements, clear_color) = output_elements(
+                output,
+                space,
+                Vec::<CustomRenderElements<R>>::new(),
+                renderer,
+                false,
+                &None,
+                session_lock,
+                clear_color,
+            );
+            render_image(
+                renderer,
+                output_geometry.size.to_physical_precise_round(scale),
+                scale,
+                &elements,
+                clear_color,
+                region.to_physical_precise_round(scale),
+            )
+        }
+        Capture::Window { window, scale } => {
+            let scale = Scale::from(*scale);
+            let geometry = window.geometry();
+            let elements: Vec<WorkspaceWindowRenderElement<R>> = window.render_elements(
+                renderer,
+                (Point::from((0, 0)) - geometry.loc).to_physical_precise_round(scale),
+                scale,
+                1.0,
+            );
+            let size = geometry.size.to_physical_precise_round(scale);
+            // Parts of the window which are not drawn stay transparent
+            render_image(
+                renderer,
+                size,
+                scale,
+                &elements,
+                [0.0; 4],
+                Rectangle::from_loc_and_size((0, 0), size),
+            )
+        }
+    }
+}
+
+/// Renders the elements upright into an offscreen buffer of the size and reads the region back
+fn render_image<R, E>(
+    renderer: &mut R,
+    size: Size<i32, Physical>,
+    scale: Scale<f64>,
+    elements: &[E],
+    clear_color: [f32; 4],
+    region: Rectangle<i32, Physical>,
+) -> anyhow::Result<RgbaImage>
+where
+    R: Renderer + Offscreen<GlesTexture> + Bind<GlesTexture> + ExportMem,
+    E: RenderElement<R>,
+{
+    let region = region
+        .intersection(Rectangle::from_loc_and_size((0, 0), size))
+        .filter(|region| !region.is_empty())
+        .context("Captured region is empty")?;
+    let buffer = renderer
+        .create_buffer(Fourcc::Abgr8888, Size::from((size.w, size.h)))
+        .map_err(|err| anyhow!("Unable to create offscreen buffer: {err}"))?;
+    renderer
+        .bind(buffer)
+        .map_err(|err| anyhow!("Unable to bind offscreen buffer: {err}"))?;
+
+    let mut damage_tracker = OutputDamageTracker::new(size, scale, Transform::Normal);
+    damage_tracker
+        .render_output(renderer, 0, elements, clear_color)
+        .map_err(|err| anyhow!("Unable to render: {err}"))?;
+
+    let mapping = renderer
+        .copy_framebuffer(
+            Rectangle::from_loc_and_size(
+                (region.loc.x, region.loc.y),
+                (region.size.w, region.size.h),
+            ),
+            Fourcc::Abgr8888,
+        )
+        .map_err(|err| anyhow!("Unable to copy framebuffer: {err}"))?;
+    let pixels = renderer
+        .map_texture(&mapping)
+        .map_err(|err| anyhow!("Unable to read framebuffer: {err}"))?;
+    RgbaImage::from_raw(region.size.w as u32, region.size.h as u32, pixels.to_vec())
+        .context("Framebuffer has an unexpected size")
+}
+
+/// Directory of pictures of the user, screenshots are saved there unless the config sets
+/// another one
+fn default_screenshot_dir() -> PathBuf {
+    user_dir("XDG_PICTURES_DIR", "Pictures")
+}
+
+/// Directory of the user with the key in `user-dirs.dirs`, e.g. `XDG_PICTURES_DIR`. The keys are
+/// no environment variables. Without an entry it is the fallback directory in the home directory,
+/// and the temporary directory if there is no home directory either.
+pub fn user_dir(key: &str, fallback: &str) -> PathBuf {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|config_home| config_home.is_absolute())
+        .or_else(|| home.as_ref().map(|home| home.join(".config")))
+        .and_then(|config_home| fs::read_to_string(config_home.join("user-dirs.dirs")).ok())
+        .and_then(|user_dirs| parse_user_dir(&user_dirs, key, home.as_deref()))
+        .or_else(|| home.map(|home| home.join(fallback)))
+        .unwrap_or_else(env::temp_dir)
+}
+
+/// Value of the key in `user-dirs.dirs`. The file is a shell script, but like for xdg-user-dir only
+/// lines of the form `KEY="$HOME/dir"` and `KEY="/dir"` are supported. The last one counts.
+fn parse_user_dir(user_dirs: &str, key: &str, home: Option<&Path>) -> Option<PathBuf> {
+    user_dirs
+        .lines()
+        .filter_map(|line| {
+            let value = line
+                .trim()
+                .strip_prefix(key)?
+                .strip_prefix('=')?
+                .strip_prefix('"')?
+                .strip_suffix('"')?;
+            match value.strip_prefix("$HOME") {
+                Some("") => home.map(Path::to_path_buf),
+                Some(relative) => Some(home?.join(relative.strip_prefix('/')?)),
+                None => value.starts_with('/').then(|| PathBuf::from(value)),
+            }
+        })
+        .last()
+}
+
+/// Encodes the image and writes it to a new file in the directory. Returns the path and the PNG
+/// data.
+fn save_png(image: &RgbaImage, directory: &Path) -> anyhow::Result<(PathBuf, Vec<u8>)> {
+    let mut png = Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, ImageFormat::Png)
+        .context("Unable to encode PNG")?;
+    let png = png.into_inner();
+
+    let path = new_file(directory, "screenshot", "png")?;
+    fs::write(&path, &png).with_context(|| format!("Unable to write {}", path.display()))?;
+    Ok((path, png))
+}
+
+/// Path in the directory named after the prefix and the current time, e.g.
+/// `screenshot-2024-06-01-12-30-05.png`. A number is appended if the file already exists. The
+/// directory is created if it is missing.
+pub fn new_file(directory: &Path, prefix: &str, extension: &str) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(directory)
+        .with_context(|| format!("Unable to create {}", directory.display()))?;
+    let name = format!("{prefix}-{}", local_time());
+    (0..)
+        .map(|number| match number {
+            0 => directory.join(format!("{name}.{extension}")),
+            number => directory.join(format!("{name}-{number}.{extension}")),
+        })
+        .find(|path| !path.exists())
+        .with_context(|| format!("No file name left in {}", directory.display()))
+}
+
+/// Current local time in the form `2024-06-01-12-30-05`
+fn local_time() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs()) as libc::time_t;
+    // SAFETY: all fields of tm are integers or a pointer, for which zero is valid
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are valid for the duration of the call
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return now.to_string();
+    }
+    format!(
+        "{}-{:02}-{:02}-{:02}-{:02}-{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_DIRS: &str = r#"# This file is written by xdg-user-dirs-update
+# XDG_VIDEOS_DIR="$HOME/Commented"
+XDG_DESKTOP_DIR="$HOME/Desktop"
+XDG_PICTURES_DIR="$HOME/Bilder"
+XDG_VIDEOS_DIR="/srv/videos"
+XDG_MUSIC_DIR="$HOME"
+XDG_PICTURES_DIR="$HOME/Screenshots"
+"#;
+
+    #[test]
+    fn user_dir_relative_to_home() {
+        let home = Path::new("/home/user");
+        assert_eq!(
+            parse_user_dir(USER_DIRS, "XDG_DESKTOP_DIR", Some(home)),
+            Some(PathBuf::from("/home/user/Desktop"))
+        );
+        assert_eq!(
+            parse_user_dir(USER_DIRS, "XDG_MUSIC_DIR", Some(home)),
+            Some(PathBuf::from("/home/user"))
+        );
+        assert_eq!(parse_user_dir(USER_DIRS, "XDG_DESKTOP_DIR", None), None);
+    }
+
+    #[test]
+    fn user_dir_absolute_and_last_entry() {
+        let home = Some(Path::new("/home/user"));
+        assert_eq!(
+            parse_user_dir(USER_DIRS, "XDG_VIDEOS_DIR", home),
+            Some(PathBuf::from("/srv/videos"))
+        );
+        assert_eq!(
+            parse_user_dir(USER_DIRS, "XDG_PICTURES_DIR", home),
+            Some(PathBuf::from("/home/user/Screenshots"))
+        );
+    }
+
+    #[test]
+    fn user_dir_missing_or_unsupported() {
+        let home = Some(Path::new("/home/user"));
+        assert_eq!(parse_user_dir(USER_DIRS, "XDG_DOCUMENTS_DIR", home), None);
+        assert_eq!(parse_user_dir(USER_DIRS, "XDG_VIDEOS", home), None);
+        assert_eq!(
+            parse_user_dir(r#"XDG_VIDEOS_DIR="Videos""#, "XDG_VIDEOS_DIR", home),
+            None
+        );
+        assert_eq!(
+            parse_user_dir(r#"XDG_VIDEOS_DIR="$HOMEVideos""#, "XDG_VIDEOS_DIR", home),
+            None
+        );
+    }
+}
@@ -1,6 +1,4 @@
-use crate::{
-    grabs::ResizeState, state::ActiveSpace, workspace_window::WorkspaceWindow, ClientState, State,
-};
+use crate::{grabs::ResizeState, workspace_window::WorkspaceWindow, ClientState, State};
 use smithay::{
     backend::renderer::utils::on_commit_buffer_handler,
     desktop::{
@@ -8,6 +6,7 @@ use smithay::{
         WindowSurfaceType,
     },
     input::pointer::{CursorImageStatus, CursorImageSurfaceData},
+    output::Output,
     reexports::{
         calloop::Interest,
         wayland_server::{
@@ -25,11 +24,11 @@ use smithay::{
         },
         dmabuf::get_dmabuf,
         drm_syncobj::DrmSyncobjCachedState,
-        shell::xdg::XdgToplevelSurfaceData,
+        shell::xdg::{ToplevelSurface, XdgToplevelSurfaceData},
     },
     xwayland::XWaylandClientData,
 };
-use std::cell::RefCell;
+use std::{cell::RefCell, collections::HashMap};
 use tracing::info;
 
 impl BufferHandler for State {
@@ -52,19 +51,6 @@ impl CompositorHandler for State {
     }
 
     fn new_surface(&mut self, surface: &WlSurface) {
-        with_states(surface, |surface_data| {
-            surface_data.data_map.insert_if_missing_threadsafe(|| {
-                ActiveSpace(
-                    self.spaces
-                        .iter()
-                        .next()
-                        .expect("There should always be a space")
-                        .0
-                        .to_owned(),
-                )
-            })
-        });
-
         add_pre_commit_hook::<Self, _>(surface, move |state, _dh, surface| {
             let mut acquire_point = None;
             let maybe_dmabuf = with_states(surface, |surface_data| {
@@ -132,6 +118,12 @@ impl CompositorHandler for State {
             if let Some((window, space_name)) = self.window_and_space_for_surface(&root) {
                 window.on_commit();
 
+                if &root == surface {
+                    if let Some(toplevel) = window.toplevel().cloned() {
+                        self.place_dialog(&toplevel);
+                    }
+                }
+
                 if &root == surface {
                     let buffer_offset = with_states(surface, |states| {
                         states
@@ -189,16 +181,7 @@ impl CompositorHandler for State {
             });
         }
 
-        let space_name = with_states(surface, |surface_data| {
-            surface_data
-                .data_map
-                .get::<ActiveSpace>()
-                .unwrap()
-                .0
-                .to_owned()
-        });
-
-        ensure_initial_configure(surface, &self.spaces[&space_name], &mut self.popups);
+        ensure_initial_configure(surface, &self.spaces, &self.outputs, &mut self.popups);
 
         // TODO: Only schedule the output that is affected
         self.backend_data.schedule_render();
@@ -210,15 +193,12 @@ impl State {
         &self,
         surface: &WlSurface,
     ) -> Option<(WorkspaceWindow, String)> {
-        self.spaces
-            .iter()
-            .map(|(space_name, space)| {
-                space
-                    .elements()
-                    .find(|window| window.wl_surface().map(|s| &*s == surface).unwrap_or(false))
-                    .map(|window| (window.to_owned(), space_name.clone()))
-            })
-            .next()?
+        self.spaces.iter().find_map(|(space_name, space)| {
+            space
+                .elements()
+                .find(|window| window.wl_surface().map(|s| &*s == surface).unwrap_or(false))
+                .map(|window| (window.to_owned(), space_name.clone()))
+        })
     }
 
     pub fn space_of_window(&self, window: &WorkspaceWindow) -> Option<String> {
@@ -235,12 +215,211 @@ impl State {
 pub struct SurfaceData {
     pub geometry: Option<Rectangle<i32, Logical>>,
     pub resize_state: ResizeState,
+    pub dialog: DialogState,
+    /// Placement the window had before it was toggled floating, it returns there when toggled
+    /// again
+    pub floating: Option<FloatingState>,
+    /// Geometry the window had before it was maximized or made fullscreen
+    pub restore_geometry: Option<Rectangle<i32, Logical>>,
+    /// Shown on every space of its output, it moves along when the output switches its space
+    pub sticky: bool,
+}
+
+/// Zone and geometry of a window before it was popped out of its zone
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatingState {
+    pub zone: Option<String>,
+    pub geometry: Rectangle<i32, Logical>,
+}
+
+/// Placement state of a toplevel which was hinted as modal dialog through xdg-dialog
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DialogState {
+    #[default]
+    NotModal,
+    /// Modal, but not yet centered over its parent because its size is still unknown
+    Pending,
+    Placed,
+}
+
+pub fn dialog_state(surface: &WlSurface) -> DialogState {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<RefCell<SurfaceData>>()
+            .map(|data| data.borrow().dialog)
+            .unwrap_or_default()
+    })
+}
+
+pub fn set_dialog_state(surface: &WlSurface, dialog: DialogState) {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing(|| RefCell::new(SurfaceData::default()));
+        states
+            .data_map
+            .get::<RefCell<SurfaceData>>()
+            .unwrap()
+            .borrow_mut()
+            .dialog = dialog;
+    });
+}
+
+pub fn floating_state(surface: &WlSurface) -> Option<FloatingState> {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<RefCell<SurfaceData>>()
+            .and_then(|data| data.borrow().floating.clone())
+    })
+}
+
+pub fn set_floating_state(surface: &WlSurface, floating: Option<FloatingState>) {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing(|| RefCell::new(SurfaceData::default()));
+        states
+            .data_map
+            .get::<RefCell<SurfaceData>>()
+            .unwrap()
+            .borrow_mut()
+            .floating = floating;
+    });
+}
+
+/// Remembers the geometry to return to when the window leaves the maximized or fullscreen state
+pub fn set_restore_geometry(surface: &WlSurface, geometry: Option<Rectangle<i32, Logical>>) {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing(|| RefCell::new(SurfaceData::default()));
+        states
+            .data_map
+            .get::<RefCell<SurfaceData>>()
+            .unwrap()
+            .borrow_mut()
+            .restore_geometry = geometry;
+    });
+}
+
+pub fn take_restore_geometry(surface: &WlSurface) -> Option<Rectangle<i32, Logical>> {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<RefCell<SurfaceData>>()
+            .and_then(|data| data.borrow_mut().restore_geometry.take())
+    })
+}
+
+pub fn is_sticky(surface: &WlSurface) -> bool {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<RefCell<SurfaceData>>()
+            .is_some_and(|data| data.borrow().sticky)
+    })
+}
+
+pub fn set_sticky(surface: &WlSurface, sticky: bool) {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing(|| RefCell::new(SurfaceData::default()));
+        states
+            .data_map
+            .get::<RefCell<SurfaceData>>()
+            .unwrap()
+            .borrow_mut()
+            .sticky = sticky;
+    });
+}
+
+impl State {
+    /// Centers a pending modal dialog over its parent window and raises it above the parent
+    pub fn place_dialog(&mut self, toplevel: &ToplevelSurface) {
+        if dialog_state(toplevel.wl_surface()) != DialogState::Pending {
+            return;
+        }
+
+        if !toplevel.is_initial_configure_sent() {
+            // Let the dialog pick its own size instead of filling a zone
+            toplevel.with_pending_state(|state| {
+                state.size = None;
+            });
+            return;
+        }
+
+        let Some(parent) = toplevel.parent() else {
+            return;
+        };
+        let Some((window, space_name)) = self.window_and_space_for_surface(toplevel.wl_surface())
+        else {
+            return;
+        };
+        let Some((parent_window, parent_space_name)) = self.window_and_space_for_surface(&parent)
+        else {
+            return;
+        };
+        if space_name != parent_space_name {
+            return;
+        }
+
+        let size = window.geometry().size;
+        if size.w <= 0 || size.h <= 0 {
+            return;
+        }
+
+        let space = self.spaces.get_mut(&space_name).unwrap();
+        let Some(parent_geometry) = space.element_geometry(&parent_window) else {
+            return;
+        };
+        let location = parent_geometry.loc
+            + Point::from((
+                (parent_geometry.size.w - size.w) / 2,
+                (parent_geometry.size.h - size.h) / 2,
+            ));
+        space.map_element(window, location, true);
+        set_dialog_state(toplevel.wl_surface(), DialogState::Placed);
+    }
+
+    /// Raises all child toplevels of `parent` above it, modal dialogs above the other children.
+    /// Children might belong to other clients, if they got parented through xdg-foreign.
+    ///
+    /// Returns the top most modal dialog.
+    pub fn raise_child_toplevels(
+        &mut self,
+        space_name: &str,
+        parent: &WorkspaceWindow,
+    ) -> Option<WorkspaceWindow> {
+        let parent_surface = parent.wl_surface()?.into_owned();
+        let space = self.spaces.get_mut(space_name)?;
+        let (dialogs, children): (Vec<_>, Vec<_>) = space
+            .elements()
+            .filter(|window| {
+                window
+                    .toplevel()
+                    .is_some_and(|toplevel| toplevel.parent().as_ref() == Some(&parent_surface))
+            })
+            .cloned()
+            .partition(|window| {
+                window.toplevel().is_some_and(|toplevel| {
+                    dialog_state(toplevel.wl_surface()) != DialogState::NotModal
+                })
+            });
+        for child in children.iter().chain(&dialogs) {
+            space.raise_element(child, true);
+        }
+        dialogs.into_iter().next_back()
+    }
 }
 
 // TODO: Try to find a better way to do this (this seems inefficient)
 fn ensure_initial_configure(
     surface: &WlSurface,
-    space: &Space<WorkspaceWindow>,
+    spaces: &HashMap<String, Space<WorkspaceWindow>>,
+    outputs: &HashMap<String, Output>,
     popups: &mut PopupManager,
 ) {
     with_surface_tree_upward(
@@ -255,8 +434,9 @@ fn ensure_initial_configure(
         |_, _, _| true,
     );
 
-    if let Some(window) = space
-        .elements()
+    if let Some(window) = spaces
+        .values()
+        .flat_map(|space| space.elements())
         .find(|window| window.wl_surface().map(|s| &*s == surface).unwrap_or(false))
         .cloned()
     {
@@ -301,7 +481,7 @@ fn ensure_initial_configure(
         return;
     };
 
-    if let Some(output) = space.outputs().find(|o| {
+    if let Some(output) = outputs.values().find(|o| {
         let map = layer_map_for_output(o);
         map.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
             .is_some()
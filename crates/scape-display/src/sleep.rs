@@ -0,0 +1,102 @@
+use crate::State;
+use calloop::channel::{self, Event, Sender};
+use std::{sync::mpsc, thread, time::Duration};
+use tracing::{info, warn};
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::OwnedFd,
+};
+
+/// Time the suspend hook of the config has before the system suspends anyway
+const SUSPEND_HOOK_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug)]
+enum SleepEvent {
+    /// The system is about to suspend, the sender is notified once the suspend hook ran
+    Suspend(mpsc::Sender<()>),
+    Resume,
+}
+
+impl State {
+    /// Runs the suspend and resume hooks of the config when logind puts the system to sleep
+    pub fn start_sleep_monitor(&mut self) {
+        let (sender, channel) = channel::channel();
+        let res = self.loop_handle.insert_source(channel, |event, _, state| {
+            let Event::Msg(event) = event else {
+                return;
+            };
+            match event {
+                SleepEvent::Suspend(hook_ran) => {
+                    state.on_suspend();
+                    // Exports of the config apply their changes in idle callbacks, e.g. a
+                    // screen locker is only spawned after them
+                    state.loop_handle.insert_idle(move |_| {
+                        let _ = hook_ran.send(());
+                    });
+                }
+                SleepEvent::Resume => state.on_resume(),
+            }
+        });
+        if let Err(err) = res {
+            warn!(?err, "Unable to insert sleep monitor");
+            return;
+        }
+
+        let res = thread::Builder::new()
+            .name("sleep monitor".to_string())
+            .spawn(move || {
+                if let Err(err) = monitor_sleep(sender) {
+                    warn!(%err, "Unable to monitor sleep of the system");
+                }
+            });
+        if let Err(err) = res {
+            warn!(?err, "Unable to start sleep monitor thread");
+        }
+    }
+}
+
+/// Forwards the sleep signals of logind. A delay inhibitor lock keeps the system awake until the
+/// suspend hook ran, it is taken again after every resume.
+fn monitor_sleep(sender: Sender<SleepEvent>) -> zbus::Result<()> {
+    let connection = Connection::system()?;
+    let proxy = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    let inhibit = || {
+        let res = proxy.call::<_, _, OwnedFd>(
+            "Inhibit",
+            &(
+                "sleep",
+                "scape",
+                "Running the suspend hook of the config",
+                "delay",
+            ),
+        );
+        res.map_err(|err| warn!(%err, "Unable to delay suspend"))
+            .ok()
+    };
+
+    let mut inhibitor = inhibit();
+    for message in proxy.receive_signal("PrepareForSleep")? {
+        let suspending: bool = message.body().deserialize()?;
+        info!(suspending, "System sleep state changes");
+        if suspending {
+            let (hook_ran, hook_done) = mpsc::channel();
+            if sender.send(SleepEvent::Suspend(hook_ran)).is_err() {
+                break;
+            }
+            let _ = hook_done.recv_timeout(SUSPEND_HOOK_TIMEOUT);
+            // Releasing the lock lets logind continue with the suspend
+            drop(inhibitor.take());
+        } else {
+            inhibitor = inhibit();
+            if sender.send(SleepEvent::Resume).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,396 @@
+use crate::{
+    bar::BarItem,
+    egui_window::{EguiAppState, EguiWindow},
+    render::CustomRenderElements,
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use egui::Context;
+use smithay::{
+    backend::renderer::{
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            Kind,
+        },
+        ImportAll, ImportMem, Renderer,
+    },
+    desktop::Space,
+    output::Output,
+    utils::{Logical, Point, Rectangle, Scale, Size},
+};
+use tracing::warn;
+
+/// Distance to the left/right edge of an output at which a dragged window snaps
+const SNAP_EDGE_THRESHOLD: f64 = 8.0;
+/// Height of the area at the top and bottom of an edge which snaps to a quarter instead of a half
+const SNAP_CORNER_SIZE: f64 = 96.0;
+const SNAP_PREVIEW_ALPHA: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapTarget {
+    LeftHalf,
+    RightHalf,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl SnapTarget {
+    pub fn from_pointer(
+        location: Point<f64, Logical>,
+        output_geometry: Rectangle<i32, Logical>,
+    ) -> Option<Self> {
+        let geometry = output_geometry.to_f64();
+        let left = location.x - geometry.loc.x <= SNAP_EDGE_THRESHOLD;
+        let right = geometry.loc.x + geometry.size.w - location.x <= SNAP_EDGE_THRESHOLD;
+        let top = location.y - geometry.loc.y <= SNAP_CORNER_SIZE;
+        let bottom = geometry.loc.y + geometry.size.h - location.y <= SNAP_CORNER_SIZE;
+
+        match (left, right, top, bottom) {
+            (true, _, true, _) => Some(SnapTarget::TopLeft),
+            (true, _, _, true) => Some(SnapTarget::BottomLeft),
+            (true, _, _, _) => Some(SnapTarget::LeftHalf),
+            (_, true, true, _) => Some(SnapTarget::TopRight),
+            (_, true, _, true) => Some(SnapTarget::BottomRight),
+            (_, true, _, _) => Some(SnapTarget::RightHalf),
+            _ => None,
+        }
+    }
+
+    pub fn geometry(&self, output_geometry: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        let half_width = output_geometry.size.w / 2;
+        let half_height = output_geometry.size.h / 2;
+        let (x, y, w, h) = match self {
+            SnapTarget::LeftHalf => (0, 0, half_width, output_geometry.size.h),
+            SnapTarget::RightHalf => (half_width, 0, half_width, output_geometry.size.h),
+            SnapTarget::TopLeft => (0, 0, half_width, half_height),
+            SnapTarget::TopRight => (half_width, 0, half_width, half_height),
+            SnapTarget::BottomLeft => (0, half_height, half_width, half_height),
+            SnapTarget::BottomRight => (half_width, half_height, half_width, half_height),
+        };
+        Rectangle::from_loc_and_size(
+            output_geometry.loc + Point::from((x, y)),
+            Size::from((w, h)),
+        )
+    }
+
+    /// The half which is left over after snapping to this target
+    fn complement(&self) -> Option<Self> {
+        match self {
+            SnapTarget::LeftHalf => Some(SnapTarget::RightHalf),
+            SnapTarget::RightHalf => Some(SnapTarget::LeftHalf),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to a window which is dropped after dragging it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DropBehavior {
+    /// Fill a half or quarter of the output when dropped at its left or right edge
+    #[default]
+    EdgeSnap,
+    /// Fill the zone under the pointer
+    Zone,
+    /// Stay where it was dropped
+    Float,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DragConfig {
+    pub drop: DropBehavior,
+    /// Preview the area the window fills once it is dropped
+    pub highlight: bool,
+}
+
+impl Default for DragConfig {
+    fn default() -> Self {
+        DragConfig {
+            drop: DropBehavior::default(),
+            highlight: true,
+        }
+    }
+}
+
+/// Area a dragged window fills once it is dropped
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropTarget {
+    /// Edge snap target and the geometry of the output it belongs to
+    Snap(SnapTarget, Rectangle<i32, Logical>),
+    Zone(String),
+    /// Indicator of another space in the bar and its geometry, the window moves to the space
+    Space(String, Rectangle<i32, Logical>),
+}
+
+#[derive(Debug)]
+pub struct SnapPreview {
+    geometry: Rectangle<i32, Logical>,
+    buffer: SolidColorBuffer,
+}
+
+impl SnapPreview {
+    pub fn render_element<R>(
+        &self,
+        space: &Space<WorkspaceWindow>,
+        output: &Output,
+    ) -> Option<CustomRenderElements<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+    {
+        let output_geometry = space.output_geometry(output)?;
+        if !output_geometry.overlaps(self.geometry) {
+            return None;
+        }
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let location = (self.geometry.loc - output_geometry.loc).to_physical_precise_round(scale);
+        Some(CustomRenderElements::Solid(
+            SolidColorRenderElement::from_buffer(
+                &self.buffer,
+                location,
+                scale,
+                1.0,
+                Kind::Unspecified,
+            ),
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SnapAssistSelection {
+    Window(usize),
+    Dismissed,
+}
+
+/// Lists the other windows of a space to fill the half left over by a snapped window
+#[derive(Debug, PartialEq, Clone)]
+pub struct SnapAssist {
+    app_ids: Vec<String>,
+    selection: Option<SnapAssistSelection>,
+}
+
+impl SnapAssist {
+    pub fn selection(&self) -> Option<SnapAssistSelection> {
+        self.selection
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Fill the other half");
+            ui.separator();
+
+            for (index, app_id) in self.app_ids.iter().enumerate() {
+                if ui.button(app_id).clicked() {
+                    self.selection = Some(SnapAssistSelection::Window(index));
+                }
+            }
+
+            ui.separator();
+            if ui.button("Dismiss").clicked() {
+                self.selection = Some(SnapAssistSelection::Dismissed);
+            }
+        });
+    }
+}
+
+impl From<SnapAssist> for EguiAppState {
+    fn from(snap_assist: SnapAssist) -> Self {
+        EguiAppState::SnapAssist(snap_assist)
+    }
+}
+
+#[derive(Debug)]
+pub struct SnapAssistState {
+    pub window: EguiWindow,
+    space_name: String,
+    candidates: Vec<WorkspaceWindow>,
+    geometry: Rectangle<i32, Logical>,
+    output_geometry: Rectangle<i32, Logical>,
+}
+
+impl State {
+    pub fn set_snap_preview(&mut self, geometry: Option<Rectangle<i32, Logical>>) {
+        if self.snap_preview.as_ref().map(|preview| preview.geometry) == geometry {
+            return;
+        }
+
+        self.snap_preview = geometry.map(|geometry| {
+            let mut color = self.theme.palette().border_focused;
+            color[3] = SNAP_PREVIEW_ALPHA;
+            SnapPreview {
+                geometry,
+                buffer: SolidColorBuffer::new(geometry.size, color),
+            }
+        });
+        self.backend_data.schedule_render();
+    }
+
+    /// Target of a window dragged to the location, depending on the drop behavior of the config
+    pub fn drop_target(
+        &self,
+        space_name: &str,
+        location: Point<f64, Logical>,
+    ) -> Option<DropTarget> {
+        // Space indicators in the bar take precedence over the drop behavior
+        if let Some((BarItem::Space(target), geometry)) = self.bar_item_at(location) {
+            return (target != space_name).then_some(DropTarget::Space(target, geometry));
+        }
+        match self.drag_config.drop {
+            DropBehavior::EdgeSnap => {
+                let space = self.spaces.get(space_name)?;
+                let output_geometry = space
+                    .output_under(location)
+                    .next()
+                    .and_then(|output| space.output_geometry(output))?;
+                SnapTarget::from_pointer(location, output_geometry)
+                    .map(|target| DropTarget::Snap(target, output_geometry))
+            }
+            DropBehavior::Zone => self.zone_under(location).map(DropTarget::Zone),
+            DropBehavior::Float => None,
+        }
+    }
+
+    /// Highlights the area of the drop target, unless the config turned highlights off. Space
+    /// indicators are always highlighted, the dragged window does not show where it goes.
+    pub fn preview_drop_target(&mut self, target: Option<&DropTarget>) {
+        let geometry = target
+            .filter(|target| self.drag_config.highlight || matches!(target, DropTarget::Space(..)))
+            .and_then(|target| match target {
+                DropTarget::Snap(snap_target, output_geometry) => {
+                    Some(snap_target.geometry(*output_geometry))
+                }
+                DropTarget::Zone(zone_name) => self
+                    .zones
+                    .get(zone_name)
+                    .map(|zone| self.zone_window_geometry(zone)),
+                DropTarget::Space(_, geometry) => Some(*geometry),
+            });
+        self.set_snap_preview(geometry);
+    }
+
+    pub fn drop_window(&mut self, space_name: &str, window: &WorkspaceWindow, target: DropTarget) {
+        match target {
+            DropTarget::Snap(snap_target, output_geometry) => {
+                self.snap_window(space_name, window, snap_target, output_geometry);
+            }
+            DropTarget::Zone(zone_name) => {
+                self.set_snap_preview(None);
+                if !self.spaces.contains_key(space_name) {
+                    warn!(space_name, "Space of dropped window not found");
+                    return;
+                }
+                self.place_window(space_name, window, true, Some(&zone_name), true);
+            }
+            DropTarget::Space(target, _) => {
+                self.set_snap_preview(None);
+                let Some(window_id) = window.id() else {
+                    return;
+                };
+                if let Err(err) = self.move_window_to_space(Some(window_id), &target) {
+                    warn!(
+                        ?err,
+                        space_name = target,
+                        "Unable to move dropped window to space"
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn snap_window(
+        &mut self,
+        space_name: &str,
+        window: &WorkspaceWindow,
+        target: SnapTarget,
+        output_geometry: Rectangle<i32, Logical>,
+    ) {
+        self.set_snap_preview(None);
+        let Some(space) = self.spaces.get_mut(space_name) else {
+            warn!(space_name, "Space of snapped window not found");
+            return;
+        };
+
+        let geometry = target.geometry(output_geometry);
+        window.position(geometry.loc, geometry.size, output_geometry.size, true);
+        space.map_element(window.clone(), geometry.loc, true);
+
+        if let Some(complement) = target.complement() {
+            self.show_snap_assist(
+                space_name,
+                window,
+                complement.geometry(output_geometry),
+                output_geometry,
+            );
+        }
+    }
+
+    fn show_snap_assist(
+        &mut self,
+        space_name: &str,
+        snapped_window: &WorkspaceWindow,
+        geometry: Rectangle<i32, Logical>,
+        output_geometry: Rectangle<i32, Logical>,
+    ) {
+        self.dismiss_snap_assist();
+
+        let candidates = self.spaces[space_name]
+            .elements()
+            .filter(|window| {
+                *window != snapped_window && matches!(window, WorkspaceWindow::ApplicationWindow(_))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return;
+        }
+
+        let window = EguiWindow::new(SnapAssist {
+            app_ids: candidates.iter().map(WorkspaceWindow::app_id).collect(),
+            selection: None,
+        });
+        self.apply_theme_to_window(&WorkspaceWindow::from(window.clone()));
+        window.position(geometry.size);
+        self.spaces.get_mut(space_name).unwrap().map_element(
+            WorkspaceWindow::from(window.clone()),
+            geometry.loc,
+            true,
+        );
+
+        self.snap_assist = Some(SnapAssistState {
+            window,
+            space_name: space_name.to_string(),
+            candidates,
+            geometry,
+            output_geometry,
+        });
+    }
+
+    pub fn finish_snap_assist(&mut self, selection: SnapAssistSelection) {
+        let Some(snap_assist) = self.snap_assist.take() else {
+            return;
+        };
+        let Some(space) = self.spaces.get_mut(&snap_assist.space_name) else {
+            return;
+        };
+        space.unmap_elem(&WorkspaceWindow::from(snap_assist.window));
+
+        if let SnapAssistSelection::Window(index) = selection {
+            let window = &snap_assist.candidates[index];
+            if space.elements().any(|w| w == window) {
+                let geometry = snap_assist.geometry;
+                window.position(
+                    geometry.loc,
+                    geometry.size,
+                    snap_assist.output_geometry.size,
+                    true,
+                );
+                space.map_element(window.clone(), geometry.loc, true);
+            }
+        }
+    }
+
+    pub fn dismiss_snap_assist(&mut self) {
+        self.finish_snap_assist(SnapAssistSelection::Dismissed);
+    }
+}
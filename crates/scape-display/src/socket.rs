@@ -0,0 +1,138 @@
+use crate::{ClientState, State};
+use calloop::{generic::Generic, Interest, Mode, PostAction};
+use std::{
+    env,
+    io::ErrorKind,
+    os::{
+        fd::{FromRawFd, RawFd},
+        unix::net::{UnixDatagram, UnixListener},
+    },
+    sync::Arc,
+};
+use tracing::{info, warn};
+
+/// First file descriptor passed by systemd, see sd_listen_fds(3)
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// A wayland listening socket which was created by systemd or a login manager
+#[derive(Debug)]
+pub struct ActivatedSocket {
+    listener: UnixListener,
+    name: String,
+}
+
+impl ActivatedSocket {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Takes over the first socket passed via `LISTEN_FDS`, if the compositor was socket activated
+pub fn activated_socket() -> Option<ActivatedSocket> {
+    let listen_pid = env::var("LISTEN_PID").ok()?;
+    let listen_fds = env::var("LISTEN_FDS").ok()?;
+    let listen_fdnames = env::var("LISTEN_FDNAMES").ok();
+
+    // Children must not try to take over the socket as well
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    if listen_pid.parse::<u32>().ok()? != std::process::id() {
+        return None;
+    }
+    let fd_count = listen_fds.parse::<RawFd>().ok()?;
+    if fd_count < 1 {
+        return None;
+    }
+    if fd_count > 1 {
+        warn!(
+            fd_count,
+            "Received more than one socket through socket activation, only using the first one"
+        );
+    }
+
+    // SAFETY: systemd hands over ownership of the file descriptors starting at SD_LISTEN_FDS_START
+    let listener = unsafe {
+        let flags = libc::fcntl(SD_LISTEN_FDS_START, libc::F_GETFD);
+        if flags < 0 {
+            warn!("Socket passed through socket activation is not valid");
+            return None;
+        }
+        libc::fcntl(SD_LISTEN_FDS_START, libc::F_SETFD, flags | libc::FD_CLOEXEC);
+        UnixListener::from_raw_fd(SD_LISTEN_FDS_START)
+    };
+    if let Err(err) = listener.set_nonblocking(true) {
+        warn!(?err, "Unable to set activated socket to non-blocking");
+        return None;
+    }
+
+    let name = listener
+        .local_addr()
+        .ok()
+        .and_then(|addr| {
+            addr.as_pathname()?
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .or_else(|| {
+            listen_fdnames
+                .and_then(|names| names.split(':').next().map(str::to_string))
+                .filter(|name| name.starts_with("wayland-"))
+        })
+        .or_else(|| env::var("WAYLAND_DISPLAY").ok())?;
+
+    Some(ActivatedSocket { listener, name })
+}
+
+/// Tells the service manager that the compositor finished starting up
+pub fn notify_ready() {
+    let Ok(notify_socket) = env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+    env::remove_var("NOTIFY_SOCKET");
+
+    let result =
+        UnixDatagram::unbound().and_then(|socket| socket.send_to(b"READY=1", &notify_socket));
+    if let Err(err) = result {
+        warn!(?err, notify_socket, "Unable to notify service manager");
+    }
+}
+
+impl State {
+    /// Starts accepting clients on the activated socket.
+    ///
+    /// Until this is called connecting clients wait in the listen backlog of the socket.
+    pub fn accept_activated_socket(&mut self, socket: ActivatedSocket) {
+        info!(
+            socket_name = socket.name,
+            "Accepting clients on activated socket"
+        );
+        let res = self.loop_handle.insert_source(
+            Generic::new(socket.listener, Interest::READ, Mode::Level),
+            |_, listener, state| {
+                loop {
+                    match listener.accept() {
+                        Ok((client_stream, _)) => {
+                            if let Err(err) = state
+                                .display_handle
+                                .insert_client(client_stream, Arc::new(ClientState::default()))
+                            {
+                                warn!("Error adding wayland client: {}", err);
+                            }
+                        }
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            warn!(?err, "Error accepting wayland client");
+                            break;
+                        }
+                    }
+                }
+                Ok(PostAction::Continue)
+            },
+        );
+        if let Err(err) = res {
+            warn!(?err, "Unable to listen on activated socket");
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use crate::{
+    command::{CommandEvent, SpaceChange},
+    direction::Direction,
+    error::{Result, ScapeError},
+    shell::{is_sticky, set_sticky},
+    state::{active_space, set_active_space},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{
+    desktop::space::SpaceElement,
+    output::Output,
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
+};
+use tracing::{info, warn};
+
+/// Output a window is moved to, either by its name or next to the output of the window
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputTarget {
+    Direction(Direction),
+    Name(String),
+}
+
+impl OutputTarget {
+    /// Directions are given as `left`, `right`, `up` or `down`, anything else is an output name
+    pub fn from_name(name: &str) -> Self {
+        Direction::from_name(name)
+            .map(OutputTarget::Direction)
+            .unwrap_or_else(|| OutputTarget::Name(name.to_string()))
+    }
+}
+
+impl State {
+    /// Adds an empty space, existing spaces are kept as they are
+    pub fn create_space(&mut self, space_name: String) {
+        if self.spaces.contains_key(&space_name) {
+            return;
+        }
+        info!(space_name, "Creating space");
+        self.spaces.insert(space_name, Default::default());
+    }
+
+    /// Shows the space on the output, the output under the pointer if none is given
+    pub fn switch_space(&mut self, space_name: &str, output_name: Option<&str>) -> Result<()> {
+        self.space(space_name)?;
+        let output = match output_name {
+            Some(output_name) => self
+                .outputs
+                .get(output_name)
+                .cloned()
+                .ok_or_else(|| ScapeError::OutputNotFound(output_name.to_string()))?,
+            None => self
+                .output_under_pointer()
+                .ok_or_else(|| ScapeError::NoOutput(space_name.to_string()))?,
+        };
+
+        let previous_space = active_space(&output);
+        if previous_space.as_deref() == Some(space_name) {
+            return Ok(());
+        }
+        info!(output = output.name(), space_name, "Switching space");
+
+        let location = output.current_location();
+        let focused = self.focused_window();
+        let mut sticky_windows = Vec::new();
+        if let Some(previous_space) = previous_space
+            .as_ref()
+            .and_then(|name| self.spaces.get_mut(name))
+        {
+            // Sticky windows on the output come along to the new space, in their stacking order
+            sticky_windows = previous_space
+                .elements()
+                .filter(|window| {
+                    window
+                        .wl_surface()
+                        .is_some_and(|surface| is_sticky(&surface))
+                })
+                .filter(|window| previous_space.outputs_for_element(window).contains(&output))
+                .filter_map(|window| {
+                    Some((window.clone(), previous_space.element_location(window)?))
+                })
+                .collect::<Vec<_>>();
+            for (window, _) in &sticky_windows {
+                previous_space.unmap_elem(window);
+            }
+            previous_space.unmap_output(&output);
+        }
+        self.switch_zone_tree(previous_space.as_deref(), space_name);
+        let space = self.space_mut(space_name)?;
+        if space.output_geometry(&output).is_none() {
+            space.map_output(&output, location);
+        }
+        for (window, location) in &sticky_windows {
+            space.map_element(window.clone(), *location, false);
+        }
+        set_active_space(&output, space_name);
+
+        // The focused window is not visible anymore unless it is sticky, focus the topmost window
+        // of the space on the output instead
+        let sticky = |window: &WorkspaceWindow| sticky_windows.iter().any(|(w, _)| w == window);
+        let window = focused.filter(|window| sticky(window)).or_else(|| {
+            space
+                .elements()
+                .rev()
+                .filter(|window| matches!(window, WorkspaceWindow::ApplicationWindow(_)))
+                .filter(|window| !sticky(window))
+                .find(|window| space.outputs_for_element(window).contains(&output))
+                .or_else(|| sticky_windows.last().map(|(window, _)| window))
+                .cloned()
+        });
+        match window {
+            Some(window) => self.focus_window(window, space_name),
+            None => {
+                let keyboard = self.keyboard()?;
+                keyboard.set_focus(self, None, SERIAL_COUNTER.next_serial());
+            }
+        }
+        self.broadcast_event(CommandEvent::Space {
+            change: SpaceChange::Switched,
+            space: space_name.to_string(),
+            output: output.name(),
+        });
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Focuses the window, a window on a hidden space is shown by switching the output under the
+    /// pointer to its space first
+    pub fn show_window(&mut self, window: WorkspaceWindow, space_name: &str) -> Result<()> {
+        if !self.is_space_shown(space_name) {
+            self.switch_space(space_name, None)?;
+        }
+        self.focus_window(window, space_name);
+        Ok(())
+    }
+
+    /// Whether an output shows the space
+    pub fn is_space_shown(&self, space_name: &str) -> bool {
+        self.outputs
+            .values()
+            .any(|output| active_space(output).as_deref() == Some(space_name))
+    }
+
+    /// Window with the id and the name of the space it is on
+    pub fn window_by_id(&self, window_id: u64) -> Result<(String, WorkspaceWindow)> {
+        self.spaces
+            .iter()
+            .find_map(|(space_name, space)| {
+                let window = space
+                    .elements()
+                    .find(|window| window.id() == Some(window_id))?;
+                Some((space_name.clone(), window.clone()))
+            })
+            .ok_or(ScapeError::WindowNotFound(window_id))
+    }
+
+    /// Makes the window with the id, or the focused window, visible on every space of its output
+    /// or pins it back to the space it is on
+    pub fn toggle_sticky(&mut self, window_id: Option<u64>) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (space_name, window) = self.window_by_id(window_id)?;
+        let Some(surface) = window.wl_surface() else {
+            warn!(
+                window_id,
+                "Unable to toggle sticky, the window has no surface yet"
+            );
+            return Ok(());
+        };
+        let sticky = !is_sticky(&surface);
+        info!(window_id, space_name, sticky, "Toggling sticky window");
+        set_sticky(&surface, sticky);
+        Ok(())
+    }
+
+    /// Moves the window with the id, or the focused window if none is given, to the space
+    pub fn move_window_to_space(&mut self, window_id: Option<u64>, space_name: &str) -> Result<()> {
+        self.space(space_name)?;
+        let window = match window_id {
+            Some(window_id) => self.window_by_id(window_id)?.1,
+            None => {
+                let Some(focus) = self.keyboard()?.current_focus() else {
+                    return Ok(());
+                };
+                let Ok(window) = WorkspaceWindow::try_from(focus) else {
+                    return Ok(());
+                };
+                window
+            }
+        };
+        let Some(previous_space) = self
+            .spaces
+            .iter()
+            .find(|(_, space)| space.elements().any(|w| *w == window))
+            .map(|(name, _)| name.clone())
+        else {
+            return Ok(());
+        };
+        if previous_space == space_name {
+            return Ok(());
+        }
+        info!(
+            app_id = window.app_id(),
+            space_name, "Moving window to space"
+        );
+
+        self.space_mut(&previous_space)?.unmap_elem(&window);
+        self.place_window(space_name, &window, false, None, true);
+
+        let visible = self.is_space_shown(space_name);
+        let focused = self
+            .keyboard()?
+            .current_focus()
+            .and_then(|focus| WorkspaceWindow::try_from(focus).ok())
+            .is_some_and(|focus| focus == window);
+        if focused && !visible {
+            let keyboard = self.keyboard()?;
+            keyboard.set_focus(self, None, SERIAL_COUNTER.next_serial());
+        }
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Logical geometry of the output, also if no space is shown on it
+    pub fn output_rect(&self, output: &Output) -> Rectangle<i32, Logical> {
+        let geometry = self
+            .spaces
+            .values()
+            .find_map(|space| space.output_geometry(output));
+        if let Some(geometry) = geometry {
+            return geometry;
+        }
+        let size = output
+            .current_mode()
+            .map(|mode| mode.size)
+            .unwrap_or_default();
+        let size = output
+            .current_transform()
+            .transform_size(size)
+            .to_f64()
+            .to_logical(output.current_scale().fractional_scale())
+            .to_i32_round();
+        Rectangle::from_loc_and_size(output.current_location(), size)
+    }
+
+    /// Moves the window with the id, or the focused window, to the space shown on the target
+    /// output. A window in a zone moves to the zone at the same place on the target output,
+    /// other windows keep their relative position and their size in physical pixels.
+    pub fn move_window_to_output(
+        &mut self,
+        window_id: Option<u64>,
+        target: &OutputTarget,
+    ) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (space_name, window) = self.window_by_id(window_id)?;
+        let space = self.space(&space_name)?;
+        let location = space
+            .element_location(&window)
+            .ok_or_else(|| ScapeError::WindowNotMapped(space_name.clone()))?;
+        let geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+        let source = space
+            .outputs_for_element(&window)
+            .into_iter()
+            .next()
+            .or_else(|| self.output_under_pointer())
+            .ok_or(ScapeError::NoOutputs)?;
+        let source_rect = self.output_rect(&source);
+
+        let target = match target {
+            OutputTarget::Name(output_name) => self
+                .outputs
+                .get(output_name)
+                .cloned()
+                .ok_or_else(|| ScapeError::OutputNotFound(output_name.clone()))?,
+            OutputTarget::Direction(direction) => {
+                let outputs = self
+                    .outputs
+                    .values()
+                    .filter(|output| **output != source)
+                    .map(|output| (output.clone(), self.output_rect(output)));
+                let Some(output) = direction.closest(source_rect, outputs) else {
+                    info!(?direction, "No output to move the window to");
+                    return Ok(());
+                };
+                output
+            }
+        };
+        if target == source {
+            return Ok(());
+        }
+        let target_space = active_space(&target).ok_or(ScapeError::NoSpace)?;
+        let target_rect = self.output_rect(&target);
+        info!(
+            app_id = window.app_id(),
+            output = target.name(),
+            "Moving window to output"
+        );
+
+        if target_space != space_name {
+            self.space_mut(&space_name)?.unmap_elem(&window);
+        }
+        if let WorkspaceWindow::ApplicationWindow(application_window) = &window {
+            if application_window.is_fullscreen() {
+                self.set_window_fullscreen(&window, &target_space, true, Some(target), true)?;
+                self.backend_data.schedule_render();
+                return Ok(());
+            }
+        }
+
+        // Same place relative to the output, as fraction of its size
+        let relative = |point: Point<i32, Logical>| {
+            (
+                (point.x - source_rect.loc.x) as f64 / source_rect.size.w.max(1) as f64,
+                (point.y - source_rect.loc.y) as f64 / source_rect.size.h.max(1) as f64,
+            )
+        };
+        let on_target = |(x, y): (f64, f64)| {
+            target_rect.loc
+                + Point::from((
+                    (x * target_rect.size.w as f64).round() as i32,
+                    (y * target_rect.size.h as f64).round() as i32,
+                ))
+        };
+
+        let center = geometry.loc + Point::from((geometry.size.w / 2, geometry.size.h / 2));
+        let target_zone = self
+            .zone_at(geometry)
+            .and_then(|_| self.zone_under(on_target(relative(center)).to_f64()));
+        if let Some(zone) = target_zone {
+            self.place_window(&target_space, &window, true, Some(&zone), true);
+        } else {
+            // Outputs with a higher scale show logical pixels larger
+            let factor = source.current_scale().fractional_scale()
+                / target.current_scale().fractional_scale();
+            let size = (
+                ((geometry.size.w as f64 * factor).round() as i32)
+                    .min(target_rect.size.w)
+                    .max(1),
+                ((geometry.size.h as f64 * factor).round() as i32)
+                    .min(target_rect.size.h)
+                    .max(1),
+            );
+            let mut loc = on_target(relative(geometry.loc));
+            loc.x = loc.x.min(target_rect.loc.x + target_rect.size.w - size.0);
+            loc.y = loc.y.min(target_rect.loc.y + target_rect.size.h - size.1);
+            let geometry = Rectangle::from_loc_and_size(loc, size);
+            self.place_window_at(&target_space, &window, geometry, true)?;
+        }
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Output under the pointer in the space it shows, the first output if the pointer is on none
+    /// The output with the name, the output under the pointer if none is given
+    pub fn output_or_under_pointer(&self, output_name: Option<String>) -> Result<Output> {
+        match output_name {
+            Some(output_name) => self
+                .outputs
+                .get(&output_name)
+                .cloned()
+                .ok_or(ScapeError::OutputNotFound(output_name)),
+            None => self.output_under_pointer().ok_or(ScapeError::NoOutputs),
+        }
+    }
+
+    /// Space of the focused window, the space under the pointer if no window is focused
+    pub fn focused_space(&self) -> Option<String> {
+        match self.focused_window().and_then(|window| window.id()) {
+            Some(window_id) => self.window_by_id(window_id).ok().map(|(space, _)| space),
+            None => active_space(&self.output_under_pointer()?),
+        }
+    }
+
+    /// Output at the location in the global compositor space
+    pub fn output_at(&self, location: Point<f64, Logical>) -> Option<Output> {
+        self.outputs
+            .values()
+            .find(|output| self.output_rect(output).to_f64().contains(location))
+            .cloned()
+    }
+
+    /// Name of the space shown on the output at the location
+    pub fn space_at(&self, location: Point<f64, Logical>) -> Option<String> {
+        active_space(&self.output_at(location)?)
+    }
+
+    /// Name of the space shown on the output under the pointer, any space if no output shows one
+    /// yet
+    pub fn space_under_pointer(&self) -> Result<String> {
+        self.output_under_pointer()
+            .and_then(|output| active_space(&output))
+            .or_else(|| self.spaces.keys().next().cloned())
+            .ok_or(ScapeError::NoSpace)
+    }
+
+    pub fn output_under_pointer(&self) -> Option<Output> {
+        let pointer_location = self.pointer_location();
+        self.outputs
+            .values()
+            .find(|output| {
+                active_space(output)
+                    .and_then(|space_name| self.spaces.get(&space_name))
+                    .and_then(|space| space.output_geometry(output))
+                    .is_some_and(|geometry| geometry.to_f64().contains(pointer_location))
+            })
+            .or_else(|| self.outputs.values().next())
+            .cloned()
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{config::LuaCallback, State};
+use calloop::channel::{self, Event, Sender};
+use std::{collections::HashMap, process::Output, thread};
+use tracing::{error, info, warn};
+
+/// Result of a command, sent from the thread which waits for it
+#[derive(Debug)]
+pub struct CaptureResult {
+    id: u64,
+    output: std::io::Result<Output>,
+}
+
+/// Commands whose output is captured, their callbacks are called once they exited
+#[derive(Debug, Default)]
+pub struct SpawnCaptures {
+    sender: Option<Sender<CaptureResult>>,
+    callbacks: HashMap<u64, LuaCallback>,
+    next_id: u64,
+}
+
+impl State {
+    /// Runs the command on a separate thread and calls `callback` with the exit code, stdout and
+    /// stderr once it exited. The exit code is nil if the command was killed by a signal or could
+    /// not be started, in the latter case stderr contains the error.
+    pub fn spawn_capture(&mut self, command: &str, args: &[String], callback: LuaCallback) {
+        let Some(sender) = self.spawn_capture_sender() else {
+            return;
+        };
+        info!(command, "Starting program with captured output");
+
+        let captures = &mut self.spawn_captures;
+        let id = captures.next_id;
+        captures.next_id += 1;
+        captures.callbacks.insert(id, callback);
+
+        let mut cmd = self.command(command, args);
+        let res = thread::Builder::new()
+            .name("spawn capture".to_string())
+            .spawn(move || {
+                let output = cmd.output();
+                // The receiver only disappears when the compositor shuts down
+                let _ = sender.send(CaptureResult { id, output });
+            });
+        if let Err(err) = res {
+            warn!(?err, command, "Unable to start spawn capture thread");
+            self.spawn_captures.callbacks.remove(&id);
+        }
+    }
+
+    /// Sender for the results of captured commands, the receiver is inserted on first use
+    fn spawn_capture_sender(&mut self) -> Option<Sender<CaptureResult>> {
+        if let Some(sender) = &self.spawn_captures.sender {
+            return Some(sender.clone());
+        }
+
+        let (sender, receiver) = channel::channel();
+        let res = self.loop_handle.insert_source(receiver, |event, _, state| {
+            if let Event::Msg(result) = event {
+                state.spawn_capture_finished(result);
+            }
+        });
+        if let Err(err) = res {
+            warn!(?err, "Unable to receive captured command output");
+            return None;
+        }
+        self.spawn_captures.sender = Some(sender.clone());
+        Some(sender)
+    }
+
+    fn spawn_capture_finished(&mut self, result: CaptureResult) {
+        let Some(callback) = self.spawn_captures.callbacks.remove(&result.id) else {
+            return;
+        };
+        let (code, stdout, stderr) = match result.output {
+            Ok(output) => (
+                output.status.code(),
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ),
+            Err(err) => {
+                error!(%err, "Failed to run program with captured output");
+                (None, String::new(), err.to_string())
+            }
+        };
+
+        self.call_config("Spawn capture callback", &callback, (code, stdout, stderr));
+    }
+}
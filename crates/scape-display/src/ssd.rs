@@ -1,4 +1,4 @@
-use crate::{application_window::ApplicationWindow, State};
+use crate::{action::Action, application_window::ApplicationWindow, theme::Palette, State};
 use smithay::{
     backend::renderer::{
         element::{
@@ -10,7 +10,6 @@ use smithay::{
     desktop::WindowSurface,
     input::Seat,
     utils::{Logical, Point, Serial},
-    wayland::shell::xdg::XdgShellHandler,
 };
 use std::cell::{RefCell, RefMut};
 
@@ -28,18 +27,29 @@ pub struct HeaderBar {
     pub background: SolidColorBuffer,
     pub close_button: SolidColorBuffer,
     pub maximize_button: SolidColorBuffer,
+    pub palette: Palette,
+    pending_drag: Option<PendingDrag>,
 }
 
-const BG_COLOR: [f32; 4] = [0.75f32, 0.9f32, 0.78f32, 1f32];
-const MAX_COLOR: [f32; 4] = [1f32, 0.965f32, 0.71f32, 1f32];
-const CLOSE_COLOR: [f32; 4] = [1f32, 0.66f32, 0.612f32, 1f32];
-const MAX_COLOR_HOVER: [f32; 4] = [0.71f32, 0.624f32, 0f32, 1f32];
-const CLOSE_COLOR_HOVER: [f32; 4] = [0.75f32, 0.11f32, 0.016f32, 1f32];
+/// Press on the title which turns into moving the window once it is dragged far enough
+#[derive(Debug, Clone, Copy)]
+struct PendingDrag {
+    start: Point<f64, Logical>,
+    serial: Serial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HeaderBarButton {
+    Close,
+    Maximize,
+}
 
 // pub const HEADER_BAR_HEIGHT: i32 = 32;
 pub const HEADER_BAR_HEIGHT: i32 = 0;
 const BUTTON_HEIGHT: u32 = HEADER_BAR_HEIGHT as u32;
 const BUTTON_WIDTH: u32 = 32;
+/// Distance in logical pixels a press on the title has to be dragged to move the window
+pub const DEFAULT_DRAG_THRESHOLD: f64 = 8.0;
 
 impl HeaderBar {
     pub fn pointer_enter(&mut self, loc: Point<f64, Logical>) {
@@ -48,118 +58,97 @@ impl HeaderBar {
 
     pub fn pointer_leave(&mut self) {
         self.pointer_loc = None;
+        self.pending_drag = None;
     }
 
-    pub fn clicked(
-        &mut self,
-        seat: &Seat<State>,
-        state: &mut State,
-        window: &ApplicationWindow,
-        serial: Serial,
-    ) {
-        match self.pointer_loc.as_ref() {
-            Some(loc) if loc.x >= (self.width - BUTTON_WIDTH) as f64 => {
-                match window.0.underlying_surface() {
-                    WindowSurface::Wayland(toplevel) => toplevel.send_close(),
-                    WindowSurface::X11(w) => {
-                        let _ = w.close();
-                    }
-                };
-            }
-            Some(loc) if loc.x >= (self.width - (BUTTON_WIDTH * 2)) as f64 => {
-                match window.0.underlying_surface() {
-                    WindowSurface::Wayland(w) => state.maximize_request(w.clone()),
-                    WindowSurface::X11(w) => {
-                        let surface = w.clone();
-                        state
-                            .loop_handle
-                            .insert_idle(move |state| state.maximize_request_x11(&surface));
-                    }
-                };
-            }
-            Some(_) => {
-                match window.0.underlying_surface() {
-                    WindowSurface::Wayland(w) => {
-                        let seat = seat.clone();
-                        let toplevel = w.clone();
-                        state.loop_handle.insert_idle(move |state| {
-                            state.move_request_xdg(&toplevel, &seat, serial)
-                        });
-                    }
-                    WindowSurface::X11(w) => {
-                        let window = w.clone();
-                        state
-                            .loop_handle
-                            .insert_idle(move |state| state.move_request_x11(&window));
-                    }
-                };
-            }
-            _ => {}
+    pub fn set_palette(&mut self, palette: Palette) {
+        if self.palette != palette {
+            self.palette = palette;
+            // force the buttons to be redrawn
+            self.width = 0;
+        }
+    }
+
+    /// Buttons act when they are released. A press on the title only starts moving the window
+    /// once it is dragged further than the drag threshold, so quick clicks are no micro moves.
+    pub fn pressed(&mut self, serial: Serial) {
+        let Some(loc) = self.pointer_loc else {
+            return;
         };
+        if self.button_at(loc).is_none() {
+            self.pending_drag = Some(PendingDrag { start: loc, serial });
+        }
     }
 
-    pub fn touch_down(
-        &mut self,
-        seat: &Seat<State>,
-        state: &mut State,
-        window: &ApplicationWindow,
-        serial: Serial,
-    ) {
-        match self.pointer_loc.as_ref() {
-            Some(loc) if loc.x >= (self.width - BUTTON_WIDTH) as f64 => {}
-            Some(loc) if loc.x >= (self.width - (BUTTON_WIDTH * 2)) as f64 => {}
-            Some(_) => {
-                match window.0.underlying_surface() {
-                    WindowSurface::Wayland(w) => {
-                        let seat = seat.clone();
-                        let toplevel = w.clone();
-                        state.loop_handle.insert_idle(move |state| {
-                            state.move_request_xdg(&toplevel, &seat, serial)
-                        });
-                    }
-                    WindowSurface::X11(w) => {
-                        let window = w.clone();
-                        state
-                            .loop_handle
-                            .insert_idle(move |state| state.move_request_x11(&window));
-                    }
-                };
-            }
-            _ => {}
+    pub fn released(&mut self, state: &mut State, window: &ApplicationWindow) {
+        self.pending_drag = None;
+        let Some(button) = self.pointer_loc.and_then(|loc| self.button_at(loc)) else {
+            return;
         };
+        match button {
+            HeaderBarButton::Close => match window.0.underlying_surface() {
+                WindowSurface::Wayland(toplevel) => toplevel.send_close(),
+                WindowSurface::X11(w) => {
+                    let _ = w.close();
+                }
+            },
+            HeaderBarButton::Maximize => {
+                // The window is still the target of the pointer event which is handled here
+                let window = Some(window.id());
+                state
+                    .loop_handle
+                    .insert_idle(move |state| state.execute(Action::ToggleMaximize { window }));
+            }
+        }
     }
 
-    pub fn touch_up(
+    /// Updates the pointer or touch location and starts moving the window once a press on the
+    /// title was dragged further than the drag threshold
+    pub fn moved(
         &mut self,
-        _seat: &Seat<State>,
+        seat: &Seat<State>,
         state: &mut State,
         window: &ApplicationWindow,
-        _serial: Serial,
+        loc: Point<f64, Logical>,
     ) {
-        match self.pointer_loc.as_ref() {
-            Some(loc) if loc.x >= (self.width - BUTTON_WIDTH) as f64 => {
-                match window.0.underlying_surface() {
-                    WindowSurface::Wayland(w) => w.send_close(),
-                    WindowSurface::X11(w) => {
-                        let _ = w.close();
-                    }
-                };
+        self.pointer_loc = Some(loc);
+        let Some(pending_drag) = self.pending_drag else {
+            return;
+        };
+        let distance = loc - pending_drag.start;
+        if distance.x.hypot(distance.y) < state.drag_threshold {
+            return;
+        }
+        self.pending_drag = None;
+
+        match window.0.underlying_surface() {
+            WindowSurface::Wayland(w) => {
+                let seat = seat.clone();
+                let toplevel = w.clone();
+                let serial = pending_drag.serial;
+                state
+                    .loop_handle
+                    .insert_idle(move |state| state.move_request_xdg(&toplevel, &seat, serial));
             }
-            Some(loc) if loc.x >= (self.width - (BUTTON_WIDTH * 2)) as f64 => {
-                match window.0.underlying_surface() {
-                    WindowSurface::Wayland(w) => state.maximize_request(w.clone()),
-                    WindowSurface::X11(w) => {
-                        let surface = w.clone();
-                        state
-                            .loop_handle
-                            .insert_idle(move |state| state.maximize_request_x11(&surface));
-                    }
-                };
+            WindowSurface::X11(w) => {
+                let window = w.clone();
+                state
+                    .loop_handle
+                    .insert_idle(move |state| state.move_request_x11(&window));
             }
-            _ => {}
         };
     }
 
+    fn button_at(&self, loc: Point<f64, Logical>) -> Option<HeaderBarButton> {
+        if loc.x >= (self.width - BUTTON_WIDTH) as f64 {
+            Some(HeaderBarButton::Close)
+        } else if loc.x >= (self.width - (BUTTON_WIDTH * 2)) as f64 {
+            Some(HeaderBarButton::Maximize)
+        } else {
+            None
+        }
+    }
+
     pub fn redraw(&mut self, width: u32) {
         if width == 0 {
             self.width = 0;
@@ -167,7 +156,7 @@ impl HeaderBar {
         }
 
         self.background
-            .update((width as i32, HEADER_BAR_HEIGHT), BG_COLOR);
+            .update((width as i32, HEADER_BAR_HEIGHT), self.palette.header_bar);
 
         let mut needs_redraw_buttons = false;
         if width != self.width {
@@ -184,7 +173,7 @@ impl HeaderBar {
         {
             self.close_button.update(
                 (BUTTON_WIDTH as i32, BUTTON_HEIGHT as i32),
-                CLOSE_COLOR_HOVER,
+                self.palette.close_button_hover,
             );
             self.close_button_hover = true;
         } else if !self
@@ -194,8 +183,10 @@ impl HeaderBar {
             .unwrap_or(false)
             && (needs_redraw_buttons || self.close_button_hover)
         {
-            self.close_button
-                .update((BUTTON_WIDTH as i32, BUTTON_HEIGHT as i32), CLOSE_COLOR);
+            self.close_button.update(
+                (BUTTON_WIDTH as i32, BUTTON_HEIGHT as i32),
+                self.palette.close_button,
+            );
             self.close_button_hover = false;
         }
 
@@ -208,8 +199,10 @@ impl HeaderBar {
             .unwrap_or(false)
             && (needs_redraw_buttons || !self.maximize_button_hover)
         {
-            self.maximize_button
-                .update((BUTTON_WIDTH as i32, BUTTON_HEIGHT as i32), MAX_COLOR_HOVER);
+            self.maximize_button.update(
+                (BUTTON_WIDTH as i32, BUTTON_HEIGHT as i32),
+                self.palette.maximize_button_hover,
+            );
             self.maximize_button_hover = true;
         } else if !self
             .pointer_loc
@@ -220,8 +213,10 @@ impl HeaderBar {
             .unwrap_or(false)
             && (needs_redraw_buttons || self.maximize_button_hover)
         {
-            self.maximize_button
-                .update((BUTTON_WIDTH as i32, BUTTON_HEIGHT as i32), MAX_COLOR);
+            self.maximize_button.update(
+                (BUTTON_WIDTH as i32, BUTTON_HEIGHT as i32),
+                self.palette.maximize_button,
+            );
             self.maximize_button_hover = false;
         }
     }
@@ -284,6 +279,8 @@ impl ApplicationWindow {
                     background: SolidColorBuffer::default(),
                     close_button: SolidColorBuffer::default(),
                     maximize_button: SolidColorBuffer::default(),
+                    palette: Palette::light(),
+                    pending_drag: None,
                 },
             })
         });
@@ -297,4 +294,8 @@ impl ApplicationWindow {
     pub fn set_ssd(&self, _ssd: bool) {
         self.decoration_state().is_ssd = false;
     }
+
+    pub fn set_palette(&self, palette: Palette) {
+        self.decoration_state().header_bar.set_palette(palette);
+    }
 }
@@ -0,0 +1,50 @@
+//! Stacking layers of toplevels. Spaces keep their elements sorted by z-index, so windows are
+//! only raised within their layer and pinned windows stay above all normal ones. Layer shell
+//! surfaces of the top and overlay layers, egui overlays and lock surfaces are still drawn above
+//! pinned windows.
+
+use crate::{error::Result, workspace_window::WorkspaceWindow, State};
+use smithay::desktop::space::RenderZindex;
+use tracing::info;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StackingLayer {
+    #[default]
+    Normal,
+    /// Above all other toplevels of the space
+    Pinned,
+}
+
+impl StackingLayer {
+    pub fn z_index(self) -> u8 {
+        match self {
+            StackingLayer::Normal => RenderZindex::Shell as u8,
+            // Between the windows and the top layer of the layer shell
+            StackingLayer::Pinned => RenderZindex::Shell as u8 + 1,
+        }
+    }
+}
+
+impl State {
+    /// Keeps the window with the id, or the focused window, above all other windows, or puts a
+    /// pinned window back into the normal stacking order
+    pub fn toggle_pin(&mut self, window_id: Option<u64>) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (space_name, window) = self.window_by_id(window_id)?;
+        let WorkspaceWindow::ApplicationWindow(application_window) = &window else {
+            return Ok(());
+        };
+        let layer = match application_window.stacking_layer() {
+            StackingLayer::Normal => StackingLayer::Pinned,
+            StackingLayer::Pinned => StackingLayer::Normal,
+        };
+        info!(window_id, ?layer, "Changing stacking layer of window");
+        application_window.set_stacking_layer(layer);
+        // The space only sorts elements by their layer when they are mapped or raised
+        self.space_mut(&space_name)?.raise_element(&window, false);
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+}
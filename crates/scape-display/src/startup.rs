@@ -0,0 +1,217 @@
+use crate::{action::Action, State};
+use calloop::{
+    channel::{self, Event, Sender},
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use std::{
+    collections::VecDeque,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Interval in which the session bus is asked for the owner of a name
+const DBUS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Condition a startup entry has to fulfill before the following entries are started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadyCondition {
+    /// A window with the app id got mapped
+    Window { app_id: String },
+    /// The name got acquired on the session bus
+    DbusName { name: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct StartupEntry {
+    pub command: String,
+    pub args: Vec<String>,
+    pub wait_for: Option<ReadyCondition>,
+    /// Time after which the following entries are started even if the condition is not met
+    pub timeout: Duration,
+}
+
+#[derive(Debug)]
+struct ReadyWait {
+    command: String,
+    condition: ReadyCondition,
+    timeout_timer: RegistrationToken,
+    dbus_watch: Option<RegistrationToken>,
+}
+
+/// Startup entries which are not started yet, because an earlier entry is not ready
+#[derive(Debug, Default)]
+pub struct StartupQueue {
+    entries: VecDeque<StartupEntry>,
+    waiting: Option<ReadyWait>,
+}
+
+impl State {
+    /// Starts the entries in order. Entries after one with a ready condition are held back until
+    /// the condition is met or timed out.
+    pub fn run_startup(&mut self, entries: Vec<StartupEntry>) {
+        self.startup_queue.entries.extend(entries);
+        if self.startup_queue.waiting.is_none() {
+            self.continue_startup();
+        }
+    }
+
+    fn continue_startup(&mut self) {
+        while let Some(entry) = self.startup_queue.entries.pop_front() {
+            self.execute(Action::Spawn {
+                command: entry.command.clone(),
+                args: entry.args,
+                zone: None,
+                env: Vec::new(),
+                cwd: None,
+            });
+            let Some(condition) = entry.wait_for else {
+                continue;
+            };
+            if self.ready_condition_met(&condition) {
+                continue;
+            }
+            self.wait_for_ready(entry.command, condition, entry.timeout);
+            return;
+        }
+        info!("All startup entries started");
+    }
+
+    fn ready_condition_met(&self, condition: &ReadyCondition) -> bool {
+        match condition {
+            ReadyCondition::Window { app_id } => self
+                .spaces
+                .values()
+                .flat_map(|space| space.elements())
+                .any(|window| window.app_id() == *app_id),
+            // Checked on the dbus thread, so that the event loop is not blocked
+            ReadyCondition::DbusName { .. } => false,
+        }
+    }
+
+    fn wait_for_ready(&mut self, command: String, condition: ReadyCondition, timeout: Duration) {
+        info!(
+            command,
+            ?condition,
+            "Waiting for startup entry to become ready"
+        );
+        let res = self
+            .loop_handle
+            .insert_source(Timer::from_duration(timeout), |_, _, state| {
+                if let Some(waiting) = &state.startup_queue.waiting {
+                    warn!(
+                        command = waiting.command,
+                        condition = ?waiting.condition,
+                        "Startup entry did not become ready in time"
+                    );
+                }
+                state.startup_entry_ready();
+                TimeoutAction::Drop
+            });
+        let timeout_timer = match res {
+            Ok(token) => token,
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Unable to start startup timeout, not waiting for the entry"
+                );
+                self.continue_startup();
+                return;
+            }
+        };
+
+        let dbus_watch = match &condition {
+            ReadyCondition::DbusName { name } => {
+                let (sender, receiver) = channel::channel();
+                watch_dbus_name(name.clone(), timeout, sender);
+                let res = self.loop_handle.insert_source(receiver, |event, _, state| {
+                    if let Event::Msg(()) = event {
+                        state.startup_entry_ready();
+                    }
+                });
+                match res {
+                    Ok(token) => Some(token),
+                    Err(err) => {
+                        warn!(?err, "Unable to watch dbus name, waiting for the timeout");
+                        None
+                    }
+                }
+            }
+            ReadyCondition::Window { .. } => None,
+        };
+
+        self.startup_queue.waiting = Some(ReadyWait {
+            command,
+            condition,
+            timeout_timer,
+            dbus_watch,
+        });
+    }
+
+    /// Continues the startup if it waits for a window with this app id
+    pub fn startup_window_opened(&mut self, app_id: &str) {
+        let window_awaited =
+            self.startup_queue
+                .waiting
+                .as_ref()
+                .is_some_and(|waiting| match &waiting.condition {
+                    ReadyCondition::Window { app_id: awaited } => awaited == app_id,
+                    ReadyCondition::DbusName { .. } => false,
+                });
+        if window_awaited {
+            self.startup_entry_ready();
+        }
+    }
+
+    fn startup_entry_ready(&mut self) {
+        let Some(waiting) = self.startup_queue.waiting.take() else {
+            return;
+        };
+        info!(command = waiting.command, "Startup entry is ready");
+        self.loop_handle.remove(waiting.timeout_timer);
+        if let Some(dbus_watch) = waiting.dbus_watch {
+            self.loop_handle.remove(dbus_watch);
+        }
+        self.continue_startup();
+    }
+}
+
+/// Polls the session bus until `name` has an owner, at most for `timeout`
+fn watch_dbus_name(name: String, timeout: Duration, sender: Sender<()>) {
+    let res = thread::Builder::new()
+        .name("startup dbus watch".to_string())
+        .spawn(move || {
+            let connection = match zbus::blocking::Connection::session() {
+                Ok(connection) => connection,
+                Err(err) => {
+                    warn!(%err, "Unable to connect to the session bus");
+                    return;
+                }
+            };
+            let proxy = match zbus::blocking::fdo::DBusProxy::new(&connection) {
+                Ok(proxy) => proxy,
+                Err(err) => {
+                    warn!(%err, "Unable to create dbus proxy");
+                    return;
+                }
+            };
+            let Ok(bus_name) = zbus::names::BusName::try_from(name.as_str()) else {
+                warn!(name, "Invalid dbus name");
+                return;
+            };
+
+            let deadline = Instant::now() + timeout;
+            while Instant::now() < deadline {
+                if proxy.name_has_owner(bus_name.clone()).unwrap_or(false) {
+                    // The receiver is gone if the startup stopped waiting already
+                    let _ = sender.send(());
+                    return;
+                }
+                thread::sleep(DBUS_POLL_INTERVAL);
+            }
+        });
+    if let Err(err) = res {
+        warn!(?err, "Unable to start dbus watch thread");
+    }
+}
@@ -1,29 +1,72 @@
-use crate::composition::Zone;
-use crate::config::Config;
+use crate::bar::Bar;
+use crate::color_profile::ColorProfile;
+use crate::command::auth::CommandAuth;
+use crate::command::{i3::I3Subscriber, server::CommandSubscriber};
+use crate::composition::{Zone, ZoneGaps};
+use crate::config::{Config, LuaCallback};
 use crate::cursor::CursorState;
 use crate::egui_window::EguiWindow;
-use crate::input_handler::Mods;
+use crate::error::ScapeError;
+use crate::focus::{FocusConfig, PendingFocus};
+use crate::focus_history::FocusHistory;
+use crate::idle::IdleState;
+use crate::input_config::InputConfig;
+use crate::input_handler::{KeyBinding, KeyboardConfig, Mods, DEFAULT_KEY_MODE};
+use crate::key_chord::{KeyChord, KeyChordConfig, PendingChord};
+use crate::key_repeat::{KeyRepeat, KeyRepeatConfig};
+use crate::keystroke_visualizer::{KeystrokeVisualizerConfig, KeystrokeVisualizerState};
+use crate::kiosk::Kiosk;
+use crate::layout_snapshot::LayoutSnapshot;
+use crate::magnifier::Magnifier;
+use crate::modifier_indicator::{ModifierIndicatorConfig, ModifierIndicatorState};
+use crate::move_mode::{MoveMode, MoveModeConfig};
+use crate::output_identifier::OutputIdentifierState;
+use crate::output_profile::OutputProfile;
 use crate::pipewire::{Pipewire, VideoStream};
+use crate::pointer_bindings::ButtonBinding;
+use crate::pointer_capture::PointerCapture;
+use crate::protocols::input_timestamps::InputTimestampsManagerState;
+use crate::protocols::text_input::TextInputPurpose;
 use crate::protocols::wlr_screencopy::{Screencopy, ScreencopyManagerState};
+use crate::protocols::xdg_foreign_v1::XdgForeignV1State;
+use crate::protocols::xdg_toplevel_drag::XdgToplevelDragManagerState;
+use crate::recording::{Recording, RecordingConfig};
+use crate::scratchpad::Scratchpad;
+use crate::screenshot::RegionSelect;
+use crate::snap::{DragConfig, SnapAssistState, SnapPreview};
+use crate::socket::{activated_socket, notify_ready, ActivatedSocket};
+use crate::spawn_capture::SpawnCaptures;
+use crate::ssd::DEFAULT_DRAG_THRESHOLD;
+use crate::startup::StartupQueue;
+use crate::theme::{Theme, ThemeVariant};
+use crate::thumbnail::WindowThumbnail;
 use crate::udev::{schedule_initial_render, schedule_render, UdevOutputId};
+use crate::virtual_output::VirtualOutput;
+use crate::window_close::ClosingWindow;
+use crate::window_placeholder::PendingPlacement;
+use crate::window_rules::WindowRule;
 use crate::workspace_window::WorkspaceWindow;
 use crate::xwayland::XWaylandState;
+use crate::zone_layout::OutputZoneLayouts;
+use crate::zone_tabs::TabBar;
+use crate::zone_tree::ZoneTree;
 use crate::{udev::UdevData, winit::WinitData};
 use anyhow::{anyhow, Result};
 use calloop::generic::Generic;
-use calloop::{EventLoop, Interest, LoopHandle, LoopSignal, Mode, PostAction};
-use mlua::Function as LuaFunction;
+use calloop::{EventLoop, Interest, LoopHandle, LoopSignal, Mode, PostAction, RegistrationToken};
 use smithay::backend::drm::{DrmDeviceFd, DrmNode};
-use smithay::input::keyboard::{Keysym, LedState};
+use smithay::input::keyboard::{Keycode, Keysym, LedState};
 use smithay::reexports::gbm::Device as GbmDevice;
 use smithay::reexports::wayland_protocols::ext::session_lock::v1::server::ext_session_lock_v1::ExtSessionLockV1;
 use smithay::utils::Logical;
+use smithay::wayland::alpha_modifier::AlphaModifierState;
 use smithay::wayland::dmabuf::ImportNotifier;
 use smithay::wayland::drm_syncobj::DrmSyncobjState;
 use smithay::wayland::selection::primary_selection::PrimarySelectionState;
 use smithay::wayland::selection::wlr_data_control::DataControlState;
 use smithay::wayland::session_lock::LockSurface;
 use smithay::wayland::session_lock::SessionLockManagerState;
+use smithay::wayland::shell::xdg::dialog::XdgDialogState;
 use smithay::wayland::single_pixel_buffer::SinglePixelBufferState;
 use smithay::wayland::tablet_manager::TabletManagerState;
 use smithay::wayland::xdg_foreign::XdgForeignState;
@@ -41,14 +84,25 @@ use smithay::{
         session::Session,
     },
     desktop::{
-        utils::{surface_primary_scanout_output, update_surface_primary_scanout_output},
+        utils::{
+            send_dmabuf_feedback_surface_tree, send_frames_surface_tree,
+            surface_primary_scanout_output, update_surface_primary_scanout_output,
+            with_surfaces_surface_tree,
+        },
         PopupManager, Space,
     },
-    input::{keyboard::XkbConfig, pointer::PointerHandle, Seat, SeatState},
-    output::Output,
+    input::{
+        keyboard::{KeyboardHandle, XkbConfig},
+        pointer::PointerHandle,
+        Seat, SeatState,
+    },
+    output::{Mode as OutputMode, Output},
     reexports::wayland_server::{
-        backend::{ClientData, ClientId, DisconnectReason},
-        protocol::wl_surface::{self, WlSurface},
+        backend::{ClientData, ClientId, DisconnectReason, ObjectId},
+        protocol::{
+            wl_data_source::WlDataSource,
+            wl_surface::{self, WlSurface},
+        },
         Display, DisplayHandle,
     },
     utils::{Clock, Monotonic, Point},
@@ -78,7 +132,12 @@ use smithay::{
     },
 };
 use std::collections::{HashMap, HashSet};
-use std::{sync::Arc, time::Duration};
+use std::{
+    cell::RefCell,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use tracing::{error, info, warn};
 
 #[derive(Debug, Default)]
@@ -107,13 +166,29 @@ pub struct SessionLock {
     pub surfaces: HashMap<Output, LockSurface>,
 }
 
+/// Space shown on an output, it changes when the output switches to another space
 #[derive(Debug)]
-pub struct ActiveSpace(pub String);
+struct OutputSpace(Mutex<String>);
+
+/// Name of the space which is shown on the output
+pub fn active_space(output: &Output) -> Option<String> {
+    let OutputSpace(space_name) = output.user_data().get::<OutputSpace>()?;
+    Some(space_name.lock().unwrap().clone())
+}
 
+pub fn set_active_space(output: &Output, space_name: &str) {
+    let user_data = output.user_data();
+    user_data.insert_if_missing_threadsafe(|| OutputSpace(Mutex::new(space_name.to_string())));
+    let OutputSpace(active) = user_data.get::<OutputSpace>().unwrap();
+    *active.lock().unwrap() = space_name.to_string();
+}
+
+/// Remembers where an output and its windows were before the output got disabled
 #[derive(Debug)]
-pub struct WindowRule {
-    pub app_id: String,
-    pub zone: String,
+pub struct DisabledOutput {
+    space_name: Option<String>,
+    position: Point<i32, Logical>,
+    windows: Vec<(WorkspaceWindow, Point<i32, Logical>)>,
 }
 
 #[derive(Debug)]
@@ -129,8 +204,26 @@ pub struct State {
     pub outputs: HashMap<String, Output>,
     pub spaces: HashMap<String, Space<WorkspaceWindow>>,
     pub started_outputs: HashSet<Output>,
+    pub disabled_outputs: HashMap<String, DisabledOutput>,
+    /// Outputs created at runtime which are only rendered offscreen
+    pub virtual_outputs: HashMap<String, VirtualOutput>,
+    pub color_profiles: HashMap<String, ColorProfile>,
     pub zones: HashMap<String, Zone>,
+    pub zone_gaps: ZoneGaps,
     pub default_zone: Option<String>,
+    /// Named zone sets by the name of their output
+    pub zone_layouts: HashMap<String, OutputZoneLayouts>,
+    /// Zones split at runtime by the name of their space
+    pub zone_trees: HashMap<String, ZoneTree>,
+    /// Zones showing their windows as tabs
+    pub tabbed_zones: HashSet<String>,
+    /// Tab bars of the tabbed zones on the shown spaces, see `refresh_tab_bars`
+    pub tab_bars: Vec<TabBar>,
+    pub bar_enabled: bool,
+    /// Bars of the outputs, see `refresh_bars`
+    pub bars: Vec<Bar>,
+    /// Window whose bar entry is hovered and which the thumbnail shows
+    pub bar_hover: Option<WorkspaceWindow>,
 
     // smithay state
     pub compositor_state: CompositorState,
@@ -150,14 +243,28 @@ pub struct State {
     pub fractional_scale_manager_state: FractionalScaleManagerState,
     pub session_lock_state: SessionLockManagerState,
     pub xdg_foreign_state: XdgForeignState,
+    pub xdg_foreign_v1_state: XdgForeignV1State,
+    pub input_timestamps_state: InputTimestampsManagerState,
+    pub xdg_toplevel_drag_state: XdgToplevelDragManagerState,
     pub xwayland_shell_state: XWaylandShellState,
     pub single_pixel_buffer_state: SinglePixelBufferState,
     pub session_lock: Option<SessionLock>,
 
     pub dnd_icon: Option<DndIcon>,
+    pub toplevel_drag_source: Option<WlDataSource>,
 
     // input-related fields
     pub suppressed_keys: Vec<Keysym>,
+    /// Buttons which triggered a binding, their release is not forwarded to the client
+    pub suppressed_buttons: Vec<u32>,
+    /// Time of the input event currently being processed in microseconds
+    pub input_time_usec: u64,
+    /// Global cursor position hinted by the client of the last active pointer lock
+    pub pointer_lock_hint: Option<Point<f64, Logical>>,
+    pub pointer_capture: Option<PointerCapture>,
+    /// Surface whose pointer constraint got broken by the user, it stays inactive until the
+    /// pointer leaves the surface
+    pub broken_pointer_constraint: Option<WlSurface>,
     pub cursor_state: CursorState,
     pub seat: Option<Seat<State>>,
     pub clock: Clock<Monotonic>,
@@ -170,20 +277,96 @@ pub struct State {
     pub last_node: Option<DrmNode>,
 
     pub config: Config,
+    pub theme: Theme,
+    /// Forwards the theme variant to the settings portal, which broadcasts it to clients
+    pub theme_variant_sender: Option<calloop::channel::Sender<ThemeVariant>>,
 
     pub socket_name: Option<String>,
+    /// Who may use the command sockets, everyone who can reach them by default
+    pub command_auth: CommandAuth,
+    /// Connections of the command socket which receive events
+    pub command_subscribers: RefCell<Vec<CommandSubscriber>>,
+    /// Whether input events may be injected through the command socket
+    pub allow_synthetic_input: bool,
+    /// Connections of the i3 socket which receive events
+    pub i3_subscribers: RefCell<Vec<I3Subscriber>>,
+    pub activated_socket: Option<ActivatedSocket>,
+    pub connector_change_timer: Option<RegistrationToken>,
+    /// Layouts applied automatically depending on the connected outputs
+    pub output_profiles: Vec<OutputProfile>,
+    pub config_reload_timer: Option<RegistrationToken>,
 
     pub ready_state: ReadyState,
 
-    pub key_maps: HashMap<Mods, HashMap<Keysym, LuaFunction<'static>>>,
+    /// Mapped keys by key mode
+    pub key_maps: HashMap<String, HashMap<Mods, HashMap<Keysym, KeyBinding>>>,
+    pub key_mode: String,
+    /// Bindings of more than one key in a row
+    pub key_chords: Vec<KeyChord>,
+    pub key_chord_config: KeyChordConfig,
+    pub pending_chord: Option<PendingChord>,
+    pub button_maps: HashMap<Mods, HashMap<u32, ButtonBinding>>,
     pub tab_index: usize,
-    pub window_rules: HashMap<String, WindowRule>,
+    /// Focus order of the windows by the name of their space
+    pub focus_history: HashMap<String, FocusHistory>,
+    pub window_rules: Vec<WindowRule>,
 
     pub screencopy_frames: Vec<Screencopy>,
     pub pipewire: Option<Pipewire>,
     pub video_streams: Vec<VideoStream>,
 
     pub debug_ui: Option<EguiWindow>,
+    pub first_run_wizard: Option<EguiWindow>,
+    pub snap_preview: Option<SnapPreview>,
+    pub snap_assist: Option<SnapAssistState>,
+    pub keystroke_visualizer: Option<KeystrokeVisualizerState>,
+    pub keystroke_visualizer_config: KeystrokeVisualizerConfig,
+    /// Content purposes of the text inputs, see `text_input_is_secret`
+    pub text_input_purposes: HashMap<ObjectId, TextInputPurpose>,
+    pub startup_queue: StartupQueue,
+    /// Placeholders of spawned applications which did not map a window yet
+    pub window_placeholders: Vec<PendingPlacement>,
+    /// Windows which were asked to close by their id
+    pub closing_windows: HashMap<u64, ClosingWindow>,
+    /// Timers started by the config by their id
+    pub lua_timers: HashMap<u64, RegistrationToken>,
+    pub spawn_captures: SpawnCaptures,
+    pub output_identifiers: Option<OutputIdentifierState>,
+    pub kiosk: Option<Kiosk>,
+    pub idle: IdleState,
+    pub keyboard_config: KeyboardConfig,
+    pub key_repeat_config: KeyRepeatConfig,
+    /// Key binding which is repeated while its key is held
+    pub key_repeat: Option<KeyRepeat>,
+    /// Release callbacks of the bindings whose keys are held, by the keycode of the key
+    pub held_bindings: HashMap<Keycode, LuaCallback>,
+    /// Layouts saved by the config by their name
+    pub layout_snapshots: HashMap<String, LayoutSnapshot>,
+    /// Scratchpads by their name, they survive config reloads with their windows
+    pub scratchpads: HashMap<String, Scratchpad>,
+    /// Distance a press on a header bar has to be dragged, by pointer or touch, to move the window
+    pub drag_threshold: f64,
+    /// What happens to windows dropped after dragging them
+    pub drag_config: DragConfig,
+    pub focus_config: FocusConfig,
+    /// Window the pointer rests on, focused once the focus delay passed
+    pub pending_focus: Option<PendingFocus>,
+    /// libinput settings of the config, applied to devices when they are added
+    pub input_configs: Vec<InputConfig>,
+    pub modifier_indicator: ModifierIndicatorState,
+    pub modifier_indicator_config: ModifierIndicatorConfig,
+    pub window_thumbnail: Option<WindowThumbnail>,
+    pub move_mode: Option<MoveMode>,
+    pub move_mode_config: MoveModeConfig,
+    /// Region of a screenshot which is being selected with the pointer
+    pub region_select: Option<RegionSelect>,
+    /// Directory screenshots are saved to, the pictures directory of the user if none is set
+    pub screenshot_dir: Option<PathBuf>,
+    /// Outputs which are being recorded by their name
+    pub recordings: HashMap<String, Recording>,
+    pub recording_config: RecordingConfig,
+    /// Magnified outputs by their name
+    pub magnifiers: HashMap<String, Magnifier>,
 }
 
 #[derive(Debug)]
@@ -232,6 +415,11 @@ impl State {
         let fractional_scale_manager_state =
             FractionalScaleManagerState::new::<Self>(&display_handle);
         let xdg_foreign_state = XdgForeignState::new::<Self>(&display_handle);
+        let xdg_foreign_v1_state = XdgForeignV1State::new::<Self>(&display_handle);
+        // xdg-toplevel-tag-v1 and xdg-session-management are not part of wayland-protocols 0.32.4,
+        // windows are identified by their app id until they are available
+        let input_timestamps_state = InputTimestampsManagerState::new::<Self>(&display_handle);
+        let xdg_toplevel_drag_state = XdgToplevelDragManagerState::new::<Self>(&display_handle);
         let xwayland_shell_state = XWaylandShellState::new::<Self>(&display_handle);
         let single_pixel_buffer_state = SinglePixelBufferState::new::<Self>(&display_handle);
         let _text_input_manager_state = TextInputManagerState::new::<Self>(&display_handle);
@@ -253,6 +441,10 @@ impl State {
                 .map_or(true, |client_state| client_state.security_context.is_none())
         });
         ScreencopyManagerState::new::<Self>(&display_handle);
+        AlphaModifierState::new::<Self>(&display_handle);
+        // wp-fifo-v1 and wp-commit-timing-v1 need wayland-protocols 0.32.5 and a newer smithay
+        // than the pinned revision, they are added together with that update
+        XdgDialogState::new::<Self>(&display_handle);
 
         let keyboard_shortcuts_inhibit_state =
             KeyboardShortcutsInhibitState::new::<Self>(&display_handle);
@@ -285,9 +477,18 @@ impl State {
             xwayland_shell_state,
             fractional_scale_manager_state,
             xdg_foreign_state,
+            xdg_foreign_v1_state,
+            input_timestamps_state,
+            xdg_toplevel_drag_state,
             single_pixel_buffer_state,
             dnd_icon: None,
+            toplevel_drag_source: None,
             suppressed_keys: Vec::new(),
+            suppressed_buttons: Vec::new(),
+            input_time_usec: 0,
+            pointer_lock_hint: None,
+            pointer_capture: None,
+            broken_pointer_constraint: None,
             cursor_state: CursorState::default(),
             seat: None,
             pointer: None,
@@ -297,22 +498,84 @@ impl State {
             session_paused: false,
             last_node: None,
             config: Config::new(),
+            theme: Theme::default(),
+            theme_variant_sender: None,
             socket_name: None,
+            command_auth: CommandAuth::default(),
+            command_subscribers: RefCell::new(Vec::new()),
+            allow_synthetic_input: false,
+            i3_subscribers: RefCell::new(Vec::new()),
+            activated_socket: None,
+            connector_change_timer: None,
+            output_profiles: Vec::new(),
+            config_reload_timer: None,
             ready_state: ReadyState::default(),
             outputs: HashMap::new(),
             started_outputs: HashSet::new(),
+            disabled_outputs: HashMap::new(),
+            virtual_outputs: HashMap::new(),
+            color_profiles: HashMap::new(),
             spaces: {
                 let mut spaces = HashMap::new();
                 spaces.insert(String::from("main"), Space::default());
                 spaces
             },
             zones: HashMap::new(),
+            zone_gaps: ZoneGaps::default(),
             default_zone: None,
+            zone_layouts: HashMap::new(),
+            zone_trees: HashMap::new(),
+            tabbed_zones: HashSet::new(),
+            tab_bars: Vec::new(),
+            bar_enabled: false,
+            bars: Vec::new(),
+            bar_hover: None,
             key_maps: HashMap::new(),
+            key_mode: DEFAULT_KEY_MODE.to_string(),
+            key_chords: Vec::new(),
+            key_chord_config: KeyChordConfig::default(),
+            pending_chord: None,
+            button_maps: HashMap::new(),
             tab_index: 0,
-            window_rules: HashMap::new(),
+            focus_history: HashMap::new(),
+            window_rules: Vec::new(),
             screencopy_frames: Vec::new(),
             debug_ui: None,
+            first_run_wizard: None,
+            snap_preview: None,
+            snap_assist: None,
+            keystroke_visualizer: None,
+            keystroke_visualizer_config: KeystrokeVisualizerConfig::default(),
+            text_input_purposes: HashMap::new(),
+            startup_queue: StartupQueue::default(),
+            window_placeholders: Vec::new(),
+            closing_windows: HashMap::new(),
+            lua_timers: HashMap::new(),
+            spawn_captures: SpawnCaptures::default(),
+            output_identifiers: None,
+            kiosk: None,
+            idle: IdleState::default(),
+            keyboard_config: KeyboardConfig::default(),
+            key_repeat_config: KeyRepeatConfig::default(),
+            key_repeat: None,
+            held_bindings: HashMap::new(),
+            layout_snapshots: HashMap::new(),
+            scratchpads: HashMap::new(),
+            drag_threshold: DEFAULT_DRAG_THRESHOLD,
+            drag_config: DragConfig::default(),
+            focus_config: FocusConfig::default(),
+            pending_focus: None,
+            input_configs: Vec::new(),
+            modifier_indicator: ModifierIndicatorState::default(),
+            modifier_indicator_config: ModifierIndicatorConfig::default(),
+            window_thumbnail: None,
+            move_mode: None,
+            move_mode_config: MoveModeConfig::default(),
+            region_select: None,
+            screenshot_dir: None,
+            recordings: HashMap::new(),
+            recording_config: RecordingConfig::default(),
+            magnifiers: HashMap::new(),
             pipewire: None,
             video_streams: Vec::new(),
         })
@@ -326,20 +589,30 @@ impl State {
         info!("Initializing state");
 
         // init wayland clients
-        let source = ListeningSocketSource::new_auto()?;
-        let socket_name = source.socket_name().to_string_lossy().into_owned();
-        self.loop_handle
-            .insert_source(source, |client_stream, _, state| {
-                if let Err(err) = state
-                    .display_handle
-                    .insert_client(client_stream, Arc::new(ClientState::default()))
-                {
-                    warn!("Error adding wayland client: {}", err);
-                };
-            })
-            .expect("Failed to init wayland socket source");
+        let socket_name = if let Some(socket) = activated_socket() {
+            // Clients of an activated socket are only accepted once the compositor is ready
+            let socket_name = socket.name().to_string();
+            self.activated_socket = Some(socket);
+            socket_name
+        } else {
+            let source = ListeningSocketSource::new_auto()?;
+            let socket_name = source.socket_name().to_string_lossy().into_owned();
+            self.loop_handle
+                .insert_source(source, |client_stream, _, state| {
+                    if let Err(err) = state
+                        .display_handle
+                        .insert_client(client_stream, Arc::new(ClientState::default()))
+                    {
+                        warn!("Error adding wayland client: {}", err);
+                    };
+                })
+                .expect("Failed to init wayland socket source");
+            socket_name
+        };
         info!(socket_name, "Listening on wayland socket");
         ::std::env::set_var("WAYLAND_DISPLAY", &socket_name);
+        self.start_command_server(&socket_name);
+        self.start_i3_server(&socket_name);
         self.socket_name = Some(socket_name);
 
         self.loop_handle
@@ -365,13 +638,17 @@ impl State {
             .new_wl_seat(&self.display_handle, seat_name.clone());
 
         let pointer = seat.add_pointer();
+        let keyboard_config = &self.keyboard_config;
         seat.add_keyboard(
             XkbConfig {
-                layout: "de",
+                model: keyboard_config.model.as_deref().unwrap_or_default(),
+                layout: &keyboard_config.layouts.join(","),
+                variant: &keyboard_config.variants.join(","),
+                options: keyboard_config.options.clone(),
                 ..Default::default()
             },
-            400,
-            20,
+            self.key_repeat_config.delay,
+            self.key_repeat_config.rate,
         )
         .expect("Failed to initialize the keyboard");
 
@@ -384,11 +661,44 @@ impl State {
             error!(err = %e, "Failed to start XWayland");
         }
 
+        self.start_theme_auto_switch();
+        self.start_sleep_monitor();
+
         Ok(())
     }
 
     pub fn pointer_location(&self) -> Point<f64, Logical> {
-        self.pointer.as_ref().unwrap().current_location()
+        self.pointer
+            .as_ref()
+            .map(PointerHandle::current_location)
+            .unwrap_or_default()
+    }
+
+    pub fn seat(&self) -> crate::error::Result<&Seat<State>> {
+        self.seat.as_ref().ok_or(ScapeError::NoSeat)
+    }
+
+    pub fn keyboard(&self) -> crate::error::Result<KeyboardHandle<State>> {
+        self.seat()?.get_keyboard().ok_or(ScapeError::NoKeyboard)
+    }
+
+    pub fn pointer(&self) -> crate::error::Result<PointerHandle<State>> {
+        self.pointer.clone().ok_or(ScapeError::NoPointer)
+    }
+
+    pub fn space(&self, space_name: &str) -> crate::error::Result<&Space<WorkspaceWindow>> {
+        self.spaces
+            .get(space_name)
+            .ok_or_else(|| ScapeError::SpaceNotFound(space_name.to_string()))
+    }
+
+    pub fn space_mut(
+        &mut self,
+        space_name: &str,
+    ) -> crate::error::Result<&mut Space<WorkspaceWindow>> {
+        self.spaces
+            .get_mut(space_name)
+            .ok_or_else(|| ScapeError::SpaceNotFound(space_name.to_string()))
     }
 
     pub fn check_readyness(&mut self) {
@@ -397,7 +707,12 @@ impl State {
             && self.ready_state.xwayland_ready
         {
             self.ready_state.on_ready_called = true;
+            if let Some(socket) = self.activated_socket.take() {
+                self.accept_activated_socket(socket);
+            }
+            notify_ready();
             self.on_startup();
+            self.spawn_kiosk_app();
         }
     }
 
@@ -425,6 +740,87 @@ impl State {
 
         self.loop_handle.insert_idle(State::backend_ready);
     }
+
+    pub fn disable_output(&mut self, output_name: &str) -> Result<()> {
+        if self.disabled_outputs.contains_key(output_name) {
+            return Ok(());
+        }
+        let Some(output) = self.outputs.get(output_name).cloned() else {
+            return Err(anyhow!("Output to disable {output_name} not found"));
+        };
+        info!(output_name, "Disabling output");
+
+        let mut disabled_output = DisabledOutput {
+            space_name: None,
+            position: output.current_location(),
+            windows: Vec::new(),
+        };
+        if let Some((space_name, space)) = self
+            .spaces
+            .iter_mut()
+            .find(|(_, space)| space.outputs().any(|o| *o == output))
+        {
+            let output_geometry = space.output_geometry(&output).unwrap();
+            let target_geometry = space
+                .outputs()
+                .filter(|o| **o != output)
+                .find_map(|o| space.output_geometry(o));
+            let windows = space
+                .elements()
+                .filter(|window| {
+                    space
+                        .element_geometry(window)
+                        .map_or(false, |geometry| output_geometry.contains(geometry.loc))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            // move the windows to another output of the same space
+            for window in windows {
+                let location = space.element_location(&window).unwrap();
+                if let Some(target_geometry) = target_geometry {
+                    let new_location = location - output_geometry.loc + target_geometry.loc;
+                    space.map_element(window.clone(), new_location, false);
+                }
+                disabled_output.windows.push((window, location));
+            }
+
+            space.unmap_output(&output);
+            disabled_output.space_name = Some(space_name.clone());
+        }
+
+        self.disabled_outputs
+            .insert(output_name.to_string(), disabled_output);
+        self.backend_data.set_output_power(&output, false)
+    }
+
+    pub fn enable_output(&mut self, output_name: &str) -> Result<()> {
+        let Some(disabled_output) = self.disabled_outputs.remove(output_name) else {
+            return Ok(());
+        };
+        let Some(output) = self.outputs.get(output_name).cloned() else {
+            return Err(anyhow!("Output to enable {output_name} not found"));
+        };
+        info!(output_name, "Enabling output");
+
+        if let Some(space) = disabled_output
+            .space_name
+            .and_then(|space_name| self.spaces.get_mut(&space_name))
+        {
+            if space.output_geometry(&output).is_none() {
+                space.map_output(&output, disabled_output.position);
+            }
+
+            // restore the windows which were on the output, unless they got closed in the meantime
+            for (window, location) in disabled_output.windows {
+                if space.elements().any(|w| *w == window) {
+                    space.map_element(window, location, false);
+                }
+            }
+        }
+
+        self.backend_data.set_output_power(&output, true)
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -441,9 +837,12 @@ pub fn post_repaint(
     dmabuf_feedback: Option<SurfaceDmabufFeedback<'_>>,
     time: impl Into<Duration>,
     cursor_state: &CursorState,
+    dnd_icon: Option<&DndIcon>,
+    session_lock: Option<&SessionLock>,
 ) {
     let time = time.into();
-    // TODO: Check proper value for this
+    // Windows which are not shown on this output, including occluded and therefore suspended
+    // windows, still get a frame callback at this rate for clients ignoring the suspended state
     let throttle = Some(Duration::from_secs(1));
 
     cursor_state.send_frame(output, time);
@@ -520,6 +919,78 @@ pub fn post_repaint(
             );
         }
     }
+
+    // Lock surfaces cover the whole output and are therefore good direct scan-out candidates
+    let lock_surface = session_lock.and_then(|session_lock| session_lock.surfaces.get(output));
+    if let Some(lock_surface) = lock_surface {
+        post_repaint_surface(
+            lock_surface.wl_surface(),
+            output,
+            render_element_states,
+            dmabuf_feedback,
+            time,
+            throttle,
+        );
+    }
+    if let Some(dnd_icon) = dnd_icon {
+        post_repaint_surface(
+            &dnd_icon.surface,
+            output,
+            render_element_states,
+            dmabuf_feedback,
+            time,
+            throttle,
+        );
+    }
+}
+
+/// Sends frame callbacks and dmabuf feedback to a surface tree which is not part of the space
+fn post_repaint_surface(
+    surface: &WlSurface,
+    output: &Output,
+    render_element_states: &RenderElementStates,
+    dmabuf_feedback: Option<SurfaceDmabufFeedback<'_>>,
+    time: Duration,
+    throttle: Option<Duration>,
+) {
+    with_surfaces_surface_tree(surface, |surface, states| {
+        let primary_scanout_output = update_surface_primary_scanout_output(
+            surface,
+            output,
+            states,
+            render_element_states,
+            default_primary_scanout_output_compare,
+        );
+
+        if let Some(output) = primary_scanout_output {
+            with_fractional_scale(states, |fraction_scale| {
+                fraction_scale.set_preferred_scale(output.current_scale().fractional_scale());
+            });
+        }
+    });
+
+    send_frames_surface_tree(
+        surface,
+        output,
+        time,
+        throttle,
+        surface_primary_scanout_output,
+    );
+    if let Some(dmabuf_feedback) = dmabuf_feedback {
+        send_dmabuf_feedback_surface_tree(
+            surface,
+            output,
+            surface_primary_scanout_output,
+            |surface, _| {
+                select_dmabuf_feedback(
+                    surface,
+                    render_element_states,
+                    dmabuf_feedback.render_feedback,
+                    dmabuf_feedback.scanout_feedback,
+                )
+            },
+        );
+    }
 }
 
 #[derive(Debug)]
@@ -596,6 +1067,12 @@ impl BackendData {
         }
     }
 
+    pub fn configure_input_devices(&mut self, configs: &[InputConfig]) {
+        if let BackendData::Udev(ref mut udev_data) = self {
+            udev_data.configure_input_devices(configs);
+        }
+    }
+
     pub fn update_led_state(&mut self, led_state: LedState) {
         if let BackendData::Udev(ref mut udev_data) = self {
             udev_data.update_led_state(led_state)
@@ -659,10 +1136,37 @@ impl BackendData {
 
     fn start_output(&mut self, output: &Output, loop_handle: LoopHandle<'static, State>) {
         info!(?output, "Starting output");
+        // Virtual outputs have no drm surface and start rendering when they are created
+        if let BackendData::Udev(ref mut udev_data) = self {
+            if let Some(UdevOutputId { device_id, crtc }) = output.user_data().get::<UdevOutputId>()
+            {
+                schedule_initial_render(udev_data, *device_id, *crtc, loop_handle);
+            }
+        }
+    }
+
+    pub fn set_output_power(&mut self, output: &Output, enabled: bool) -> Result<()> {
         if let BackendData::Udev(ref mut udev_data) = self {
-            let UdevOutputId { device_id, crtc } =
-                output.user_data().get::<UdevOutputId>().unwrap();
-            schedule_initial_render(udev_data, *device_id, *crtc, loop_handle);
+            udev_data.set_output_power(output, enabled)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_output_mode(&mut self, output: &Output, mode: OutputMode) -> Result<()> {
+        match self {
+            BackendData::Udev(udev_data) => udev_data.set_output_mode(output, mode),
+            _ => Err(anyhow!(
+                "Output modes can only be changed on the udev backend"
+            )),
+        }
+    }
+
+    pub fn set_gamma(&mut self, output: &Output, profile: Option<&ColorProfile>) -> Result<()> {
+        match self {
+            BackendData::Udev(udev_data) => udev_data.set_gamma(output, profile),
+            _ => Err(anyhow!(
+                "Gamma ramps are only supported on the udev backend"
+            )),
         }
     }
 
@@ -0,0 +1,32 @@
+use crate::{state::active_space, workspace_window::WorkspaceWindow, State};
+use smithay::{desktop::utils::surface_primary_scanout_output, wayland::compositor::with_states};
+
+impl State {
+    /// Suspends windows which are not visible, so well-behaved clients stop rendering. Windows
+    /// are hidden if their space is not shown, they are on no output or were fully occluded in
+    /// the last rendered frame. Called after every rendered frame.
+    pub fn update_window_suspension(&mut self) {
+        let screen_off = self.idle.screen_off();
+        for (space_name, space) in &self.spaces {
+            let shown = !screen_off
+                && self
+                    .outputs
+                    .values()
+                    .any(|output| active_space(output).as_deref() == Some(space_name.as_str()));
+            for window in space.elements() {
+                let WorkspaceWindow::ApplicationWindow(application_window) = window else {
+                    continue;
+                };
+                let visible = shown
+                    && !space.outputs_for_element(window).is_empty()
+                    && window.wl_surface().is_some_and(|surface| {
+                        // Occluded surfaces are not rendered and lose their primary output
+                        with_states(&surface, |states| {
+                            surface_primary_scanout_output(&surface, states).is_some()
+                        })
+                    });
+                application_window.set_suspended(!visible);
+            }
+        }
+    }
+}
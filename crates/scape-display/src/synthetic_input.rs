@@ -0,0 +1,378 @@
+//! Input events which are not read from a device but sent over the command socket, so that
+//! automated tests and accessibility tools can drive the compositor. They take the same path
+//! through the input handler as events of real devices.
+
+use crate::State;
+use anyhow::ensure;
+use serde::{Deserialize, Serialize};
+use smithay::{
+    backend::input::{
+        AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+        DeviceCapability, Event, InputBackend, InputEvent, KeyState, KeyboardKeyEvent,
+        PointerAxisEvent, PointerButtonEvent, PointerMotionAbsoluteEvent, PointerMotionEvent,
+        TouchDownEvent, TouchEvent, TouchFrameEvent, TouchMotionEvent, TouchSlot, TouchUpEvent,
+        UnusedEvent,
+    },
+    input::keyboard::Keycode,
+};
+use std::{path::PathBuf, time::Duration};
+use tracing::info;
+
+/// Offset between evdev codes and the key codes of xkb
+const EVDEV_OFFSET: u32 = 8;
+
+/// Event to inject, positions are fractions of the area of the outputs from 0 to 1, like the
+/// ones of touchscreens and tablets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyntheticEvent {
+    /// Key with the evdev code, e.g. 30 for A
+    Key {
+        key: u32,
+        pressed: bool,
+    },
+    /// Moves the pointer by the distance in pixels
+    PointerMotion {
+        dx: f64,
+        dy: f64,
+    },
+    /// Moves the pointer to the position
+    PointerPosition {
+        x: f64,
+        y: f64,
+    },
+    /// Button with the evdev code, e.g. 272 for the left button
+    PointerButton {
+        button: u32,
+        pressed: bool,
+    },
+    /// Scrolls by the distance in pixels
+    PointerAxis {
+        #[serde(default)]
+        horizontal: f64,
+        #[serde(default)]
+        vertical: f64,
+    },
+    TouchDown {
+        slot: u32,
+        x: f64,
+        y: f64,
+    },
+    TouchMotion {
+        slot: u32,
+        x: f64,
+        y: f64,
+    },
+    TouchUp {
+        slot: u32,
+    },
+}
+
+/// Backend of the injected events
+#[derive(Debug)]
+pub struct SyntheticInput;
+
+/// The single device all injected events come from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntheticDevice;
+
+impl Device for SyntheticDevice {
+    fn id(&self) -> String {
+        "synthetic".to_string()
+    }
+
+    fn name(&self) -> String {
+        "scape synthetic input".to_string()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(
+            capability,
+            DeviceCapability::Keyboard | DeviceCapability::Pointer | DeviceCapability::Touch
+        )
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Event with the time it was injected at in microseconds
+#[derive(Debug, Clone)]
+pub struct Synthetic<T> {
+    time: u64,
+    event: T,
+}
+
+impl<T> Synthetic<T> {
+    fn new(time: u64, event: T) -> Self {
+        Synthetic { time, event }
+    }
+}
+
+impl<T> Event<SyntheticInput> for Synthetic<T> {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> SyntheticDevice {
+        SyntheticDevice
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Key {
+    key: u32,
+    pressed: bool,
+}
+
+impl KeyboardKeyEvent<SyntheticInput> for Synthetic<Key> {
+    fn key_code(&self) -> Keycode {
+        (self.event.key + EVDEV_OFFSET).into()
+    }
+
+    fn state(&self) -> KeyState {
+        if self.event.pressed {
+            KeyState::Pressed
+        } else {
+            KeyState::Released
+        }
+    }
+
+    fn count(&self) -> u32 {
+        u32::from(self.event.pressed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Motion {
+    dx: f64,
+    dy: f64,
+}
+
+impl PointerMotionEvent<SyntheticInput> for Synthetic<Motion> {
+    fn delta_x(&self) -> f64 {
+        self.event.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.event.dy
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        self.event.dx
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.event.dy
+    }
+}
+
+/// Position as fractions of the area of the outputs, in the touch slot for touch events
+#[derive(Debug, Clone)]
+pub struct Position {
+    slot: u32,
+    x: f64,
+    y: f64,
+}
+
+impl AbsolutePositionEvent<SyntheticInput> for Synthetic<Position> {
+    fn x(&self) -> f64 {
+        self.event.x
+    }
+
+    fn y(&self) -> f64 {
+        self.event.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.event.x.clamp(0.0, 1.0) * width as f64
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.event.y.clamp(0.0, 1.0) * height as f64
+    }
+}
+
+impl PointerMotionAbsoluteEvent<SyntheticInput> for Synthetic<Position> {}
+
+impl TouchEvent<SyntheticInput> for Synthetic<Position> {
+    fn slot(&self) -> TouchSlot {
+        Some(self.event.slot).into()
+    }
+}
+
+impl TouchDownEvent<SyntheticInput> for Synthetic<Position> {}
+impl TouchMotionEvent<SyntheticInput> for Synthetic<Position> {}
+
+#[derive(Debug, Clone)]
+pub struct Button {
+    button: u32,
+    pressed: bool,
+}
+
+impl PointerButtonEvent<SyntheticInput> for Synthetic<Button> {
+    fn button_code(&self) -> u32 {
+        self.event.button
+    }
+
+    fn state(&self) -> ButtonState {
+        if self.event.pressed {
+            ButtonState::Pressed
+        } else {
+            ButtonState::Released
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scroll {
+    horizontal: f64,
+    vertical: f64,
+}
+
+impl PointerAxisEvent<SyntheticInput> for Synthetic<Scroll> {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        match axis {
+            Axis::Horizontal => Some(self.event.horizontal),
+            Axis::Vertical => Some(self.event.vertical),
+        }
+    }
+
+    fn amount_v120(&self, _axis: Axis) -> Option<f64> {
+        None
+    }
+
+    fn source(&self) -> AxisSource {
+        AxisSource::Continuous
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+/// Touch slot which is lifted
+#[derive(Debug, Clone)]
+pub struct Lift {
+    slot: u32,
+}
+
+impl TouchEvent<SyntheticInput> for Synthetic<Lift> {
+    fn slot(&self) -> TouchSlot {
+        Some(self.event.slot).into()
+    }
+}
+
+impl TouchUpEvent<SyntheticInput> for Synthetic<Lift> {}
+
+/// Ends the touch events which belong together
+#[derive(Debug, Clone)]
+pub struct Frame;
+
+impl TouchFrameEvent<SyntheticInput> for Synthetic<Frame> {}
+
+impl InputBackend for SyntheticInput {
+    type Device = SyntheticDevice;
+    type KeyboardKeyEvent = Synthetic<Key>;
+    type PointerAxisEvent = Synthetic<Scroll>;
+    type PointerButtonEvent = Synthetic<Button>;
+    type PointerMotionEvent = Synthetic<Motion>;
+    type PointerMotionAbsoluteEvent = Synthetic<Position>;
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+    type TouchDownEvent = Synthetic<Position>;
+    type TouchUpEvent = Synthetic<Lift>;
+    type TouchMotionEvent = Synthetic<Position>;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = Synthetic<Frame>;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+    type SwitchToggleEvent = UnusedEvent;
+    type SpecialEvent = UnusedEvent;
+}
+
+impl State {
+    /// Passes the event to the input handler as if it came from a device. Only allowed if the
+    /// compositor was started with `--allow-synthetic-input`.
+    pub fn inject_input(&mut self, event: SyntheticEvent) -> anyhow::Result<()> {
+        ensure!(
+            self.allow_synthetic_input,
+            "Synthetic input is disabled, start scape with --allow-synthetic-input"
+        );
+        info!(?event, "Injecting input");
+        let time = Duration::from(self.clock.now()).as_micros() as u64;
+        let touch = matches!(
+            event,
+            SyntheticEvent::TouchDown { .. }
+                | SyntheticEvent::TouchMotion { .. }
+                | SyntheticEvent::TouchUp { .. }
+        );
+        if touch
+            && self
+                .seat
+                .as_ref()
+                .is_some_and(|seat| seat.get_touch().is_none())
+        {
+            // Adds touch to the seat
+            self.process_input_event(InputEvent::<SyntheticInput>::DeviceAdded {
+                device: SyntheticDevice,
+            });
+        }
+
+        let input_event = match event {
+            SyntheticEvent::Key { key, pressed } => InputEvent::Keyboard {
+                event: Synthetic::new(time, Key { key, pressed }),
+            },
+            SyntheticEvent::PointerMotion { dx, dy } => InputEvent::PointerMotion {
+                event: Synthetic::new(time, Motion { dx, dy }),
+            },
+            SyntheticEvent::PointerPosition { x, y } => InputEvent::PointerMotionAbsolute {
+                event: Synthetic::new(time, Position { slot: 0, x, y }),
+            },
+            SyntheticEvent::PointerButton { button, pressed } => InputEvent::PointerButton {
+                event: Synthetic::new(time, Button { button, pressed }),
+            },
+            SyntheticEvent::PointerAxis {
+                horizontal,
+                vertical,
+            } => InputEvent::PointerAxis {
+                event: Synthetic::new(
+                    time,
+                    Scroll {
+                        horizontal,
+                        vertical,
+                    },
+                ),
+            },
+            SyntheticEvent::TouchDown { slot, x, y } => InputEvent::TouchDown {
+                event: Synthetic::new(time, Position { slot, x, y }),
+            },
+            SyntheticEvent::TouchMotion { slot, x, y } => InputEvent::TouchMotion {
+                event: Synthetic::new(time, Position { slot, x, y }),
+            },
+            SyntheticEvent::TouchUp { slot } => InputEvent::TouchUp {
+                event: Synthetic::new(time, Lift { slot }),
+            },
+        };
+        self.process_input_event(input_event);
+        if touch {
+            self.process_input_event(InputEvent::<SyntheticInput>::TouchFrame {
+                event: Synthetic::new(time, Frame),
+            });
+        }
+        Ok(())
+    }
+}
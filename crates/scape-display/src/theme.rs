@@ -0,0 +1,218 @@
+use crate::{workspace_window::WorkspaceWindow, State};
+use calloop::timer::{TimeoutAction, Timer};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const AUTO_SWITCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Colors of all compositor-drawn UI for a single theme variant
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: [f32; 4],
+    pub header_bar: [f32; 4],
+    pub close_button: [f32; 4],
+    pub close_button_hover: [f32; 4],
+    pub maximize_button: [f32; 4],
+    pub maximize_button_hover: [f32; 4],
+    pub border: [f32; 4],
+    pub border_focused: [f32; 4],
+    pub text: [f32; 4],
+}
+
+impl Palette {
+    pub fn light() -> Self {
+        Palette {
+            background: [0.8, 0.8, 0.9, 1.0],
+            header_bar: [0.75, 0.9, 0.78, 1.0],
+            close_button: [1.0, 0.66, 0.612, 1.0],
+            close_button_hover: [0.75, 0.11, 0.016, 1.0],
+            maximize_button: [1.0, 0.965, 0.71, 1.0],
+            maximize_button_hover: [0.71, 0.624, 0.0, 1.0],
+            border: [0.6, 0.6, 0.7, 1.0],
+            border_focused: [0.3, 0.5, 0.9, 1.0],
+            text: [0.1, 0.1, 0.1, 1.0],
+        }
+    }
+
+    pub fn dark() -> Self {
+        Palette {
+            background: [0.12, 0.12, 0.16, 1.0],
+            header_bar: [0.2, 0.28, 0.22, 1.0],
+            close_button: [0.6, 0.25, 0.22, 1.0],
+            close_button_hover: [0.85, 0.2, 0.1, 1.0],
+            maximize_button: [0.55, 0.5, 0.25, 1.0],
+            maximize_button_hover: [0.8, 0.7, 0.1, 1.0],
+            border: [0.25, 0.25, 0.3, 1.0],
+            border_focused: [0.4, 0.6, 1.0, 1.0],
+            text: [0.9, 0.9, 0.9, 1.0],
+        }
+    }
+
+    /// Sets a color by its name, returns false if there is no color with that name
+    pub fn set_color(&mut self, name: &str, color: [f32; 4]) -> bool {
+        let field = match name {
+            "background" => &mut self.background,
+            "header_bar" => &mut self.header_bar,
+            "close_button" => &mut self.close_button,
+            "close_button_hover" => &mut self.close_button_hover,
+            "maximize_button" => &mut self.maximize_button,
+            "maximize_button_hover" => &mut self.maximize_button_hover,
+            "border" => &mut self.border,
+            "border_focused" => &mut self.border_focused,
+            "text" => &mut self.text,
+            _ => return false,
+        };
+        *field = color;
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeVariant {
+    Light,
+    Dark,
+}
+
+/// Local hours at which the light and the dark variant become active
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoSwitch {
+    pub light_from: u32,
+    pub dark_from: u32,
+}
+
+impl AutoSwitch {
+    fn variant_at(&self, hour: u32) -> ThemeVariant {
+        let is_light = if self.light_from <= self.dark_from {
+            (self.light_from..self.dark_from).contains(&hour)
+        } else {
+            hour >= self.light_from || hour < self.dark_from
+        };
+        if is_light {
+            ThemeVariant::Light
+        } else {
+            ThemeVariant::Dark
+        }
+    }
+}
+
+impl Default for AutoSwitch {
+    fn default() -> Self {
+        AutoSwitch {
+            light_from: 7,
+            dark_from: 19,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub light: Palette,
+    pub dark: Palette,
+    pub variant: ThemeVariant,
+    pub auto_switch: Option<AutoSwitch>,
+    pub font_size: f32,
+    pub border_width: i32,
+    pub spacing: i32,
+}
+
+impl Theme {
+    pub fn palette(&self) -> &Palette {
+        match self.variant {
+            ThemeVariant::Light => &self.light,
+            ThemeVariant::Dark => &self.dark,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            light: Palette::light(),
+            dark: Palette::dark(),
+            variant: ThemeVariant::Light,
+            auto_switch: None,
+            font_size: 14.0,
+            border_width: 2,
+            spacing: 8,
+        }
+    }
+}
+
+fn local_hour() -> u32 {
+    // SAFETY: `time` accepts a null pointer and `localtime_r` only writes into the provided `tm`
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        tm.tm_hour as u32
+    }
+}
+
+impl State {
+    pub fn set_theme(&mut self, mut theme: Theme) {
+        if let Some(auto_switch) = theme.auto_switch {
+            theme.variant = auto_switch.variant_at(local_hour());
+        }
+        if self.theme == theme {
+            return;
+        }
+
+        info!(variant = ?theme.variant, "Applying theme");
+        let variant_changed = self.theme.variant != theme.variant;
+        self.theme = theme;
+        if variant_changed {
+            self.broadcast_theme_variant();
+        }
+
+        for window in self.spaces.values().flat_map(|space| space.elements()) {
+            self.apply_theme_to_window(window);
+        }
+        for output in self.outputs.values() {
+            self.backend_data.reset_buffers(output);
+        }
+        self.backend_data.schedule_render();
+    }
+
+    /// Switches between the light and the dark variant and stops switching automatically
+    pub fn toggle_theme_variant(&mut self) {
+        let mut theme = self.theme.clone();
+        theme.variant = match theme.variant {
+            ThemeVariant::Light => ThemeVariant::Dark,
+            ThemeVariant::Dark => ThemeVariant::Light,
+        };
+        theme.auto_switch = None;
+        self.set_theme(theme);
+    }
+
+    /// Lets GTK and Qt applications follow the theme through the settings portal
+    pub fn broadcast_theme_variant(&self) {
+        let Some(sender) = &self.theme_variant_sender else {
+            return;
+        };
+        if let Err(err) = sender.send(self.theme.variant) {
+            warn!(%err, "Unable to broadcast theme variant");
+        }
+    }
+
+    pub fn apply_theme_to_window(&self, window: &WorkspaceWindow) {
+        match window {
+            WorkspaceWindow::ApplicationWindow(window) => window.set_palette(*self.theme.palette()),
+            WorkspaceWindow::EguiWindow(window) => window.set_theme(&self.theme),
+        }
+    }
+
+    pub fn start_theme_auto_switch(&mut self) {
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(AUTO_SWITCH_INTERVAL),
+            |_, _, state| {
+                if state.theme.auto_switch.is_some() {
+                    state.set_theme(state.theme.clone());
+                }
+                TimeoutAction::ToDuration(AUTO_SWITCH_INTERVAL)
+            },
+        );
+        if let Err(err) = res {
+            warn!(?err, "Unable to start theme auto switch timer");
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{workspace_window::WorkspaceWindow, State};
+use smithay::utils::{Logical, Rectangle};
+use tracing::warn;
+
+/// Live, scaled down rendering of a window, e.g. shown while hovering its entry in a bar
+#[derive(Debug, Clone)]
+pub struct WindowThumbnail {
+    pub window: WorkspaceWindow,
+    /// Area the thumbnail is fitted into, in global coordinates
+    pub geometry: Rectangle<i32, Logical>,
+}
+
+impl State {
+    /// Shows a thumbnail of the first window with the given app id inside of `geometry`
+    pub fn show_window_thumbnail(&mut self, app_id: &str, geometry: Rectangle<i32, Logical>) {
+        let window = self
+            .spaces
+            .values()
+            .flat_map(|space| space.elements())
+            .find(|window| window.app_id() == app_id)
+            .cloned();
+        let Some(window) = window else {
+            warn!(app_id, "No window found to show a thumbnail for");
+            return;
+        };
+
+        self.window_thumbnail = Some(WindowThumbnail { window, geometry });
+        self.backend_data.schedule_render();
+    }
+
+    pub fn hide_window_thumbnail(&mut self) {
+        if self.window_thumbnail.take().is_some() {
+            self.backend_data.schedule_render();
+        }
+    }
+}
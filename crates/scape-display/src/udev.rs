@@ -1,16 +1,26 @@
+use crate::bar::Bar;
+use crate::color_profile::{ColorProfile, GammaRamp};
 use crate::cursor::CursorState;
+use crate::input_config::{configure_input_device, InputConfig};
+use crate::magnifier::Magnifier;
 use crate::pipewire::VideoStream;
+use crate::pointer_capture::PointerCapture;
 use crate::protocols::presentation_time::take_presentation_feedback;
 use crate::protocols::wlr_screencopy::Screencopy;
+use crate::recording::Recording;
 use crate::render::GlMultiRenderer;
-use crate::state::{ActiveSpace, BackendData, DndIcon, SessionLock, SurfaceDmabufFeedback};
+use crate::screenshot::RegionSelect;
+use crate::snap::SnapPreview;
+use crate::state::{active_space, BackendData, DndIcon, SessionLock, SurfaceDmabufFeedback};
+use crate::thumbnail::WindowThumbnail;
 use crate::workspace_window::WorkspaceWindow;
+use crate::zone_tabs::TabBar;
 use crate::{
     drawing::*,
     render::*,
     state::{post_repaint, State},
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use smithay::backend::allocator::format::FormatSet;
 use smithay::backend::allocator::gbm::GbmBuffer;
 use smithay::backend::drm::compositor::RenderFrameResult;
@@ -29,7 +39,7 @@ use smithay::backend::renderer::{ExportMem, ImportMemWl, Offscreen};
 use smithay::delegate_drm_lease;
 use smithay::input::keyboard::LedState;
 use smithay::reexports::drm::control::Device;
-use smithay::reexports::drm::control::{connector, ModeTypeFlags};
+use smithay::reexports::drm::control::{connector, Mode as DrmMode, ModeTypeFlags};
 use smithay::reexports::input::DeviceCapability;
 use smithay::reexports::wayland_server::protocol::wl_shm;
 use smithay::utils::{Rectangle, Size};
@@ -100,6 +110,10 @@ use tracing::{error, info, trace, warn};
 // - we might need some work-arounds, if one supports modifiers, but the other does not
 //
 // So lets just pick `ARGB2101010` (10-bit) or `ARGB8888` (8-bit) for now, they are widely supported.
+//
+// TODO: HDR needs wp-color-management-v1, which is neither part of the wayland-protocols version we
+// depend on nor of smithay, and a renderer which can blend in a linear color space. Until then
+// HDR-capable connectors are only detected and driven with the 10-bit formats below.
 const SUPPORTED_FORMATS: &[Fourcc] = &[
     Fourcc::Abgr2101010,
     Fourcc::Argb2101010,
@@ -114,6 +128,10 @@ pub struct UdevOutputId {
     pub crtc: crtc::Handle,
 }
 
+/// Serial number from the EDID of an output, used to tell identical displays apart
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputSerial(pub String);
+
 pub struct UdevData {
     pub session: LibSeatSession,
     dmabuf_state: Option<(DmabufState, DmabufGlobal)>,
@@ -124,6 +142,8 @@ pub struct UdevData {
     fps_texture: Option<MultiTexture>,
     debug_flags: DebugFlags,
     keyboards: Vec<smithay::reexports::input::Device>,
+    /// All libinput devices, so changed input configs can be applied to them
+    input_devices: Vec<smithay::reexports::input::Device>,
     pub loop_handle: LoopHandle<'static, State>,
     pub syncobj_state: Option<DrmSyncobjState>,
 }
@@ -182,6 +202,93 @@ impl UdevData {
         }
     }
 
+    pub fn set_output_power(&mut self, output: &Output, enabled: bool) -> Result<()> {
+        let Some(&UdevOutputId { device_id, crtc }) = output.user_data().get::<UdevOutputId>()
+        else {
+            return Ok(());
+        };
+        let Some(surface) = self
+            .backends
+            .get_mut(&device_id)
+            .and_then(|gpu| gpu.surfaces.get_mut(&crtc))
+        else {
+            return Ok(());
+        };
+
+        if enabled {
+            surface
+                .compositor
+                .reset_state()
+                .with_context(|| format!("Unable to reset state of output {}", output.name()))?;
+            schedule_render(self, device_id, crtc);
+        } else {
+            surface
+                .compositor
+                .clear()
+                .with_context(|| format!("Unable to power off output {}", output.name()))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_output_mode(&mut self, output: &Output, mode: WlMode) -> Result<()> {
+        let Some(&UdevOutputId { device_id, crtc }) = output.user_data().get::<UdevOutputId>()
+        else {
+            return Ok(());
+        };
+        let Some(device) = self.backends.get_mut(&device_id) else {
+            return Ok(());
+        };
+        let Some(surface) = device.surfaces.get_mut(&crtc) else {
+            return Ok(());
+        };
+
+        let drm_mode = surface
+            .compositor
+            .surface()
+            .pending_connectors()
+            .into_iter()
+            .filter_map(|connector| device.drm.get_connector(connector, false).ok())
+            .flat_map(|info| info.modes().to_vec())
+            .find(|drm_mode| WlMode::from(*drm_mode) == mode)
+            .ok_or_else(|| anyhow!("Output {} does not support mode {mode:?}", output.name()))?;
+        surface
+            .compositor
+            .use_mode(drm_mode)
+            .with_context(|| format!("Unable to change mode of output {}", output.name()))?;
+        schedule_render(self, device_id, crtc);
+        Ok(())
+    }
+
+    pub fn set_gamma(&mut self, output: &Output, profile: Option<&ColorProfile>) -> Result<()> {
+        let Some(&UdevOutputId { device_id, crtc }) = output.user_data().get::<UdevOutputId>()
+        else {
+            return Ok(());
+        };
+        let Some(device) = self.backends.get(&device_id) else {
+            return Ok(());
+        };
+
+        let gamma_size = device
+            .drm
+            .get_crtc(crtc)
+            .context("Unable to query crtc")?
+            .gamma_length() as usize;
+        if gamma_size == 0 {
+            return Err(anyhow!(
+                "Output {} does not support gamma ramps",
+                output.name()
+            ));
+        }
+        let ramp = profile
+            .map(|profile| profile.gamma_ramp(gamma_size))
+            .unwrap_or_else(|| GammaRamp::linear(gamma_size));
+        device
+            .drm
+            .set_gamma(crtc, &ramp.red, &ramp.green, &ramp.blue)
+            .context("Unable to set gamma ramp")?;
+        Ok(())
+    }
+
     pub fn early_import(&mut self, surface: &wl_surface::WlSurface) {
         if let Err(err) = self.gpus.early_import(self.primary_gpu, surface) {
             tracing::warn!("Early buffer import failed: {}", err);
@@ -194,6 +301,12 @@ impl UdevData {
         }
     }
 
+    pub fn configure_input_devices(&mut self, configs: &[InputConfig]) {
+        for device in self.input_devices.iter_mut() {
+            configure_input_device(device, configs);
+        }
+    }
+
     pub fn dmabuf_state(&mut self) -> &mut DmabufState {
         &mut self.dmabuf_state.as_mut().unwrap().0
     }
@@ -216,6 +329,13 @@ impl UdevData {
             notifier.failed();
         }
     }
+
+    /// Renderer of the primary gpu, for rendering which is not tied to a drm surface
+    pub fn primary_renderer(&mut self) -> Result<GlMultiRenderer<'_>> {
+        self.gpus
+            .single_renderer(&self.primary_gpu)
+            .map_err(|err| anyhow!("No renderer for the primary gpu: {err}"))
+    }
 }
 
 fn select_primary_gpu(session: &LibSeatSession) -> Result<DrmNode> {
@@ -264,6 +384,7 @@ pub fn init_udev(event_loop: &mut EventLoop<'static, State>) -> Result<BackendDa
         fps_texture: None,
         debug_flags: DebugFlags::empty(),
         keyboards: Vec::new(),
+        input_devices: Vec::new(),
         loop_handle: loop_handle.clone(),
         syncobj_state: None,
     };
@@ -284,6 +405,9 @@ pub fn init_udev(event_loop: &mut EventLoop<'static, State>) -> Result<BackendDa
     loop_handle
         .insert_source(libinput_backend, move |mut event, _, state| {
             if let InputEvent::DeviceAdded { device } = &mut event {
+                configure_input_device(device, &state.input_configs);
+                let udev_data = state.backend_data.udev_mut();
+                udev_data.input_devices.push(device.clone());
                 if device.has_capability(DeviceCapability::Keyboard) {
                     if let Some(led_state) = state
                         .seat
@@ -296,6 +420,8 @@ pub fn init_udev(event_loop: &mut EventLoop<'static, State>) -> Result<BackendDa
                     state.backend_data.udev_mut().keyboards.push(device.clone());
                 }
             } else if let InputEvent::DeviceRemoved { device } = &event {
+                let udev_data = state.backend_data.udev_mut();
+                udev_data.input_devices.retain(|item| item != device);
                 if device.has_capability(DeviceCapability::Keyboard) {
                     state
                         .backend_data
@@ -653,6 +779,20 @@ impl SurfaceComposition {
         }
     }
 
+    fn clear(&mut self) -> Result<(), SwapBuffersError> {
+        match self {
+            SurfaceComposition::Compositor(c) => c.clear().map_err(Into::<SwapBuffersError>::into),
+        }
+    }
+
+    fn use_mode(&mut self, mode: DrmMode) -> Result<(), SwapBuffersError> {
+        match self {
+            SurfaceComposition::Compositor(c) => {
+                c.use_mode(mode).map_err(Into::<SwapBuffersError>::into)
+            }
+        }
+    }
+
     #[cfg_attr(feature = "profiling", profiling::function)]
     fn queue_frame(
         &mut self,
@@ -928,6 +1068,19 @@ fn connector_connected(
         })
         .unwrap_or(false);
 
+    let hdr_capable = device
+        .drm
+        .get_properties(connector.handle())
+        .ok()
+        .is_some_and(|props| {
+            props.into_iter().any(|(handle, _)| {
+                device
+                    .drm
+                    .get_property(handle)
+                    .is_ok_and(|info| info.name().to_str() == Ok("HDR_OUTPUT_METADATA"))
+            })
+        });
+
     let display_info =
         smithay_drm_extras::display_info::for_connector(&device.drm, connector.handle());
     let make = display_info
@@ -938,6 +1091,7 @@ fn connector_connected(
         .as_ref()
         .and_then(|info| info.model())
         .unwrap_or_else(|| "Unknown".into());
+    let serial = display_info.as_ref().and_then(|info| info.serial());
 
     if non_desktop {
         info!(
@@ -975,6 +1129,10 @@ fn connector_connected(
             }
         };
 
+        if hdr_capable {
+            info!(output_name, "Connector supports HDR metadata");
+        }
+
         let (phys_w, phys_h) = connector.size().unwrap_or((0, 0));
         let output = Output::new(
             output_name.clone(),
@@ -987,6 +1145,9 @@ fn connector_connected(
         );
         let global = output.create_global::<State>(&state.display_handle);
 
+        for drm_mode in connector.modes() {
+            output.add_mode(WlMode::from(*drm_mode));
+        }
         output.set_preferred(wl_mode);
         output.change_current_state(Some(wl_mode), None, None, None);
 
@@ -996,6 +1157,11 @@ fn connector_connected(
                 crtc,
                 device_id: node,
             });
+        if let Some(serial) = serial {
+            output
+                .user_data()
+                .insert_if_missing_threadsafe(|| OutputSerial(serial));
+        }
 
         #[cfg(feature = "debug")]
         let fps_element = udev_data.fps_texture.clone().map(FpsElement::new);
@@ -1084,8 +1250,9 @@ fn connector_connected(
 
         device.surfaces.insert(crtc, surface);
 
-        state.outputs.insert(output_name, output);
+        state.outputs.insert(output_name, output.clone());
 
+        state.on_output_added(&output);
         state.on_connector_change();
     }
 }
@@ -1128,6 +1295,8 @@ fn connector_disconnected(
 
         if let Some(output) = output {
             state.outputs.retain(|_, o| o != &output);
+            state.disabled_outputs.remove(&output.name());
+            state.on_output_removed(&output);
         }
 
         state.on_connector_change();
@@ -1463,6 +1632,8 @@ fn render_surface_crtc(state: &mut State, node: DrmNode, crtc: crtc::Handle) {
     let location = state.pointer_location();
     #[cfg(feature = "profiling")]
     profiling::scope!("render_surface", &format!("{crtc:?}"));
+    state.refresh_tab_bars();
+    state.refresh_bars();
     let udev_data = state.backend_data.udev_mut();
     let device = if let Some(device) = udev_data.backends.get_mut(&node) {
         device
@@ -1501,7 +1672,11 @@ fn render_surface_crtc(state: &mut State, node: DrmNode, crtc: crtc::Handle) {
         return;
     };
 
-    let Some(ActiveSpace(space_name)) = output.user_data().get::<ActiveSpace>() else {
+    if state.disabled_outputs.contains_key(&output.name()) || state.idle.screen_off() {
+        return;
+    }
+
+    let Some(space_name) = active_space(&output) else {
         error!(
             output = output.name(),
             "Cannot render output, because it has no active space"
@@ -1509,7 +1684,14 @@ fn render_surface_crtc(state: &mut State, node: DrmNode, crtc: crtc::Handle) {
         return;
     };
 
-    let space = &state.spaces[space_name];
+    let space = &state.spaces[&space_name];
+
+    // Frames of other outputs are copied when those are rendered
+    let (mut screencopy_frames, other_screencopy_frames) =
+        std::mem::take(&mut state.screencopy_frames)
+            .into_iter()
+            .partition(|screencopy| screencopy.output == output);
+    state.screencopy_frames = other_screencopy_frames;
 
     let _result = render_surface(
         surface,
@@ -1521,10 +1703,31 @@ fn render_surface_crtc(state: &mut State, node: DrmNode, crtc: crtc::Handle) {
         &state.dnd_icon,
         &state.clock,
         state.show_window_preview,
+        &state.window_thumbnail,
         &state.session_lock,
-        &mut state.screencopy_frames,
+        &mut screencopy_frames,
         &mut state.video_streams,
+        state.theme.palette().background,
+        &state.snap_preview,
+        &state.pointer_capture,
+        &state.region_select,
+        &mut state.recordings,
+        &state.tab_bars,
+        &state.bars,
+        state.magnifiers.get_mut(&output.name()),
     );
+    // The magnified region keeps panning towards the pointer
+    if state
+        .magnifiers
+        .get(&output.name())
+        .is_some_and(Magnifier::is_panning)
+    {
+        schedule_render(state.backend_data.udev_mut(), node, crtc);
+    }
+    state.loop_handle.insert_idle(move |state| {
+        state.update_window_suspension();
+        state.stop_failed_recording(&output);
+    });
 
     // TODO: Handle result errors differently depending on the type
     // for example, try to regain the render context after it was lost
@@ -1591,9 +1794,18 @@ fn render_surface<'a>(
     dnd_icon: &Option<DndIcon>,
     clock: &Clock<Monotonic>,
     show_window_preview: bool,
+    window_thumbnail: &Option<WindowThumbnail>,
     session_lock: &Option<SessionLock>,
     screencopy_frames: &mut Vec<Screencopy>,
     video_streams: &mut Vec<VideoStream>,
+    clear_color: [f32; 4],
+    snap_preview: &Option<SnapPreview>,
+    pointer_capture: &Option<PointerCapture>,
+    region_select: &Option<RegionSelect>,
+    recordings: &mut HashMap<String, Recording>,
+    tab_bars: &[TabBar],
+    bars: &[Bar],
+    magnifier: Option<&mut Magnifier>,
 ) -> Result<bool, SwapBuffersError> {
     let output_geometry = space.output_geometry(output).unwrap();
     let scale = Scale::from(output.current_scale().fractional_scale());
@@ -1667,6 +1879,30 @@ fn render_surface<'a>(
         }
     }
 
+    if let Some(snap_preview) = snap_preview {
+        custom_elements.extend(snap_preview.render_element(space, output));
+    }
+
+    if let Some(pointer_capture) = pointer_capture {
+        custom_elements.extend(pointer_capture.render_element(space, output, pointer_location));
+    }
+
+    if let Some(region_select) = region_select {
+        custom_elements.extend(region_select.render_elements(space, output, pointer_location));
+    }
+
+    if let Some(recording) = recordings.get(&output.name()) {
+        custom_elements.extend(recording.render_element(space, output));
+    }
+
+    for tab_bar in tab_bars {
+        custom_elements.extend(tab_bar.render_elements(space, output));
+    }
+
+    for bar in bars {
+        custom_elements.extend(bar.render_elements(space, output));
+    }
+
     #[cfg(feature = "debug")]
     if let Some(element) = surface.fps_element.as_mut() {
         element.update_fps(surface.fps.avg().round() as u32);
@@ -1674,14 +1910,29 @@ fn render_surface<'a>(
         custom_elements.push(CustomRenderElements::Fps(element.clone()));
     }
 
-    let (elements, clear_color) = output_elements(
+    let (mut elements, clear_color) = output_elements(
         output,
         space,
         custom_elements,
         renderer,
         show_window_preview,
+        window_thumbnail,
         session_lock,
+        clear_color,
     );
+    if let Some(magnifier) = magnifier {
+        match magnifier.magnify(
+            renderer,
+            output,
+            output_geometry,
+            pointer_location,
+            &elements,
+            clear_color,
+        ) {
+            Ok(element) => elements = vec![element],
+            Err(err) => warn!("Unable to magnify output: {err:#}"),
+        }
+    }
     let res =
         surface
             .compositor
@@ -1772,6 +2023,9 @@ fn render_surface<'a>(
     for video_stream in video_streams {
         video_stream.render_frame(renderer, &res, output);
     }
+    if let Some(recording) = recordings.get_mut(&output.name()) {
+        recording.render_frame(renderer, &res);
+    }
 
     let rendered = !res.is_empty;
 
@@ -1788,6 +2042,8 @@ fn render_surface<'a>(
             }),
         clock.now(),
         cursor_state,
+        dnd_icon.as_ref(),
+        session_lock.as_ref(),
     );
 
     if rendered {
@@ -0,0 +1,339 @@
+//! Outputs without a display behind them, e.g. to share a screen which does not exist or to run
+//! tests without monitors. They are rendered into an offscreen buffer at the refresh rate of
+//! their mode, screencopy clients capture them like any other output.
+
+use crate::{
+    layout::parse_mode,
+    protocols::{presentation_time::take_presentation_feedback, wlr_screencopy::Screencopy},
+    render::{render_output, AsGlowRenderer, CustomRenderElements, OutputRenderElements},
+    state::{active_space, post_repaint, BackendData, SessionLock},
+    thumbnail::WindowThumbnail,
+    workspace_window::{WorkspaceWindow, WorkspaceWindowRenderElement},
+    State,
+};
+use anyhow::{anyhow, ensure, Context};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            self,
+            damage::{OutputDamageTracker, RenderOutputResult},
+            element::RenderElement,
+            gles::GlesTexture,
+            Bind, BufferType, ExportMem, ImportAll, ImportMem, Offscreen, Renderer, Texture,
+        },
+    },
+    desktop::Space,
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    reexports::{
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+        wayland_server::{backend::GlobalId, protocol::wl_shm},
+    },
+    utils::{Point, Rectangle, Size},
+    wayland::shm,
+};
+use std::{mem, time::Duration};
+use tracing::{info, warn};
+
+/// Virtual outputs are named with this prefix and a number
+const NAME_PREFIX: &str = "VIRTUAL-";
+/// Refresh rate in mHz of virtual outputs created without one
+const DEFAULT_REFRESH: i32 = 60_000;
+
+#[derive(Debug)]
+pub struct VirtualOutput {
+    output: Output,
+    global: GlobalId,
+    damage_tracker: OutputDamageTracker,
+    /// Buffer the output is rendered into, recreated when the mode changes
+    buffer: Option<GlesTexture>,
+    /// Renders the next frame
+    timer: RegistrationToken,
+}
+
+impl State {
+    /// Adds an output with the mode in the form of `1920x1080@60` and returns its name. The
+    /// config places it like a newly connected output.
+    pub fn create_virtual_output(&mut self, mode: &str) -> anyhow::Result<String> {
+        let (size, refresh) = parse_mode(mode)?;
+        let refresh = refresh.unwrap_or(DEFAULT_REFRESH);
+        ensure!(size.w > 0 && size.h > 0, "Invalid size in mode {mode}");
+        ensure!(refresh > 0, "Invalid refresh rate in mode {mode}");
+        ensure!(
+            !matches!(self.backend_data, BackendData::None),
+            "Virtual outputs need a backend to render"
+        );
+        let name = (1..)
+            .map(|number| format!("{NAME_PREFIX}{number}"))
+            .find(|name| !self.outputs.contains_key(name))
+            .context("No name left for a virtual output")?;
+
+        let output = Output::new(
+            name.clone(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "scape".to_string(),
+                model: "Virtual".to_string(),
+            },
+        );
+        let global = output.create_global::<State>(&self.display_handle);
+        let mode = Mode { size, refresh };
+        output.add_mode(mode);
+        output.set_preferred(mode);
+        output.change_current_state(Some(mode), None, None, None);
+
+        let timer_name = name.clone();
+        let timer = self
+            .loop_handle
+            .insert_source(Timer::immediate(), move |_, _, state| {
+                state.virtual_output_frame(&timer_name)
+            })
+            .map_err(|err| anyhow!("Unable to insert render timer: {err}"))?;
+
+        info!(name, ?mode, "Created virtual output");
+        self.virtual_outputs.insert(
+            name.clone(),
+            VirtualOutput {
+                damage_tracker: OutputDamageTracker::from_output(&output),
+                output: output.clone(),
+                global,
+                buffer: None,
+                timer,
+            },
+        );
+        self.outputs.insert(name.clone(), output.clone());
+        self.on_output_added(&output);
+        self.on_connector_change();
+        Ok(name)
+    }
+
+    /// Removes an output created by `create_virtual_output`
+    pub fn destroy_virtual_output(&mut self, name: &str) -> anyhow::Result<()> {
+        let virtual_output = self
+            .virtual_outputs
+            .remove(name)
+            .with_context(|| format!("Virtual output {name} not found"))?;
+        info!(name, "Destroying virtual output");
+        self.loop_handle.remove(virtual_output.timer);
+        self.display_handle
+            .remove_global::<State>(virtual_output.global);
+
+        let output = virtual_output.output;
+        for space in self.spaces.values_mut() {
+            space.unmap_output(&output);
+        }
+        self.outputs.remove(name);
+        self.disabled_outputs.remove(name);
+        self.started_outputs.remove(&output);
+        self.on_output_removed(&output);
+        self.on_connector_change();
+        Ok(())
+    }
+
+    pub fn is_virtual_output(&self, output: &Output) -> bool {
+        self.virtual_outputs.contains_key(&output.name())
+    }
+
+    /// Renders a frame of the virtual output and schedules the next one
+    fn virtual_output_frame(&mut self, name: &str) -> TimeoutAction {
+        let Some(virtual_output) = self.virtual_outputs.get(name) else {
+            return TimeoutAction::Drop;
+        };
+        let output = virtual_output.output.clone();
+        if let Err(err) = self.render_virtual_output(&output) {
+            warn!(name, "Unable to render virtual output: {err:#}");
+        }
+        TimeoutAction::ToDuration(frame_duration(&output))
+    }
+
+    fn render_virtual_output(&mut self, output: &Output) -> anyhow::Result<()> {
+        if self.disabled_outputs.contains_key(&output.name()) || self.idle.screen_off() {
+            return Ok(());
+        }
+        // The config did not place the output yet
+        let Some(space) = active_space(output).and_then(|space| self.spaces.get(&space)) else {
+            return Ok(());
+        };
+        let Some(virtual_output) = self.virtual_outputs.get_mut(&output.name()) else {
+            return Ok(());
+        };
+
+        let (screencopies, other_screencopies) = mem::take(&mut self.screencopy_frames)
+            .into_iter()
+            .partition(|screencopy| screencopy.output == *output);
+        self.screencopy_frames = other_screencopies;
+
+        let clear_color = self.theme.palette().background;
+        let result = match &mut self.backend_data {
+            BackendData::Udev(udev_data) => render_offscreen(
+                &mut udev_data.primary_renderer()?,
+                virtual_output,
+                space,
+                self.show_window_preview,
+                &self.window_thumbnail,
+                &self.session_lock,
+                clear_color,
+                screencopies,
+            )?,
+            BackendData::Winit(winit_data) => render_offscreen(
+                winit_data.renderer(),
+                virtual_output,
+                space,
+                self.show_window_preview,
+                &self.window_thumbnail,
+                &self.session_lock,
+                clear_color,
+                screencopies,
+            )?,
+            BackendData::None => return Ok(()),
+        };
+
+        let time = self.clock.now();
+        post_repaint(
+            output,
+            &result.states,
+            space,
+            None,
+            time,
+            &self.cursor_state,
+            self.dnd_icon.as_ref(),
+            self.session_lock.as_ref(),
+        );
+        if result.damage.is_some_and(|damage| !damage.is_empty()) {
+            take_presentation_feedback(output, space, &result.states).presented(
+                time,
+                frame_duration(output),
+                0,
+                wp_presentation_feedback::Kind::empty(),
+            );
+        }
+
+        self.update_window_suspension();
+        Ok(())
+    }
+}
+
+fn frame_duration(output: &Output) -> Duration {
+    let refresh = output
+        .current_mode()
+        .map_or(DEFAULT_REFRESH, |mode| mode.refresh);
+    Duration::from_secs_f64(1_000f64 / refresh as f64)
+}
+
+/// Renders the output without a pointer into its buffer and copies the result into the buffers
+/// of the screencopy frames
+#[allow(clippy::too_many_arguments)]
+fn render_offscreen<'a, R>(
+    renderer: &mut R,
+    virtual_output: &'a mut VirtualOutput,
+    space: &Space<WorkspaceWindow>,
+    show_window_preview: bool,
+    window_thumbnail: &Option<WindowThumbnail>,
+    session_lock: &Option<SessionLock>,
+    clear_color: [f32; 4],
+    screencopies: Vec<Screencopy>,
+) -> anyhow::Result<RenderOutputResult<'a>>
+where
+    R: Renderer
+        + ImportAll
+        + ImportMem
+        + AsGlowRenderer
+        + Offscreen<GlesTexture>
+        + Bind<GlesTexture>
+        + ExportMem,
+    <R as Renderer>::TextureId: Clone + 'static,
+    WorkspaceWindowRenderElement<R>: RenderElement<R>,
+    OutputRenderElements<R>: RenderElement<R>,
+{
+    let mode_size = virtual_output
+        .output
+        .current_mode()
+        .context("Virtual output has no mode")?
+        .size;
+    let buffer_size = Size::from((mode_size.w, mode_size.h));
+    // A kept buffer still holds the last frame, so only the damage has to be drawn
+    let (buffer, age) = match &virtual_output.buffer {
+        Some(buffer) if buffer.size() == buffer_size => (buffer.clone(), 1),
+        _ => {
+            let buffer = renderer
+                .create_buffer(Fourcc::Abgr8888, buffer_size)
+                .map_err(|err| anyhow!("Unable to create offscreen buffer: {err}"))?;
+            virtual_output.buffer = Some(buffer.clone());
+            (buffer, 0)
+        }
+    };
+    renderer
+        .bind(buffer)
+        .map_err(|err| anyhow!("Unable to bind offscreen buffer: {err}"))?;
+
+    let result = render_output(
+        &virtual_output.output,
+        space,
+        Vec::<CustomRenderElements<R>>::new(),
+        renderer,
+        &mut virtual_output.damage_tracker,
+        age,
+        show_window_preview,
+        window_thumbnail,
+        session_lock,
+        clear_color,
+    )
+    .map_err(|err| anyhow!("Unable to render: {err}"))?;
+
+    for screencopy in screencopies {
+        match copy_to_screencopy(renderer, &screencopy) {
+            Ok(()) => screencopy.submit(),
+            Err(err) => {
+                warn!("Unable to copy virtual output: {err:#}");
+                screencopy.failed();
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Copies the region of the frame from the bound buffer into the shm buffer of the client
+fn copy_to_screencopy<R: ExportMem>(
+    renderer: &mut R,
+    screencopy: &Screencopy,
+) -> anyhow::Result<()> {
+    let buffer_type = renderer::buffer_type(screencopy.buffer());
+    ensure!(
+        matches!(buffer_type, Some(BufferType::Shm)),
+        "Unsupported buffer type: {buffer_type:?}"
+    );
+
+    let region = screencopy.region();
+    let region = Rectangle {
+        loc: Point::from((region.loc.x, region.loc.y)),
+        size: Size::from((region.size.w, region.size.h)),
+    };
+    let mapping = renderer
+        .copy_framebuffer(region, Fourcc::Argb8888)
+        .map_err(|err| anyhow!("Unable to copy framebuffer: {err}"))?;
+    let pixels = renderer
+        .map_texture(&mapping)
+        .map_err(|err| anyhow!("Unable to read framebuffer: {err}"))?;
+
+    shm::with_buffer_contents_mut(
+        screencopy.buffer(),
+        |shm_buffer_ptr, shm_len, buffer_data| {
+            ensure!(
+                buffer_data.format == wl_shm::Format::Argb8888
+                    && buffer_data.stride == region.size.w * 4
+                    && buffer_data.height == region.size.h
+                    && shm_len == pixels.len(),
+                "Invalid buffer format"
+            );
+            // The length of both buffers was checked above
+            unsafe { shm_buffer_ptr.copy_from(pixels.as_ptr(), shm_len) };
+            Ok(())
+        },
+    )
+    .context("Unable to access screencopy buffer")?
+}
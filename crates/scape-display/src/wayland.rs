@@ -1,4 +1,4 @@
-use crate::{dbus, egui::debug_ui::DebugState, state::BackendData, State};
+use crate::{dbus, egui::debug_ui::DebugState, egui_window::EguiWindow, state::BackendData, State};
 use anyhow::Context;
 use calloop::EventLoop;
 use scape_shared::GlobalArgs;
@@ -12,12 +12,17 @@ pub fn run(args: &GlobalArgs) -> anyhow::Result<()> {
     let backend_data = create_backend_data(args, &mut event_loop, display.handle())?;
 
     let mut state = State::new(&display, &mut event_loop)?;
+    state.allow_synthetic_input = args.allow_synthetic_input;
     state.load_config(args)?;
     state.init(display, backend_data)?;
 
+    let (theme_variant_sender, theme_variants) = calloop::channel::channel();
+    state.theme_variant_sender = Some(theme_variant_sender);
+    state.broadcast_theme_variant();
+
     // thread running dbus services
     thread::spawn(move || {
-        let _ = dbus::run_dbus_services();
+        let _ = dbus::run_dbus_services(theme_variants);
     });
 
     run_loop(state, &mut event_loop)
@@ -73,7 +78,26 @@ fn run_loop(mut state: State, event_loop: &mut EventLoop<State>) -> anyhow::Resu
                 state.backend_data.schedule_render();
             }
         }
+
+        if let Some(config) = state
+            .first_run_wizard
+            .as_ref()
+            .and_then(EguiWindow::first_run_config)
+        {
+            state.finish_first_run_wizard(config);
+        }
+
+        if let Some(selection) = state
+            .snap_assist
+            .as_ref()
+            .and_then(|snap_assist| snap_assist.window.snap_assist_selection())
+        {
+            state.finish_snap_assist(selection);
+        }
     })?;
 
+    state.on_shutdown();
+    // Exports of the config apply their changes in idle callbacks, which need one more dispatch
+    event_loop.dispatch(Duration::ZERO, &mut state)?;
     Ok(())
 }
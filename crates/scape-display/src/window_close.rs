@@ -0,0 +1,195 @@
+use crate::{error::Result, workspace_window::WorkspaceWindow, State};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use smithay::{
+    reexports::{
+        wayland_server::{backend::DisconnectReason, Resource},
+        x11rb::protocol::xproto::Window,
+    },
+    utils::SERIAL_COUNTER,
+    wayland::shell::xdg::{PingError, ShellClient},
+};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Time a window has to close or to answer the ping before it counts as not responding
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Window which was asked to close and is still open
+#[derive(Debug)]
+pub struct ClosingWindow {
+    /// X11 windows which do not support pings are not pinged
+    ping: Option<Ping>,
+    timeout: Option<RegistrationToken>,
+    /// The window neither closed nor answered in time, closing it again kills its client
+    not_responding: bool,
+}
+
+/// Ping sent to the client of a closing window
+#[derive(Debug)]
+enum Ping {
+    /// Ping of the `xdg_wm_base` of the client, the answer covers all windows of the client
+    Xdg(ShellClient),
+    /// `_NET_WM_PING` of the X11 window
+    X11(Window),
+}
+
+impl State {
+    /// Asks the window with the id, or the focused window, to close, and pings its client. If the
+    /// window neither closes nor answers in time, the config is notified and closing the window
+    /// again kills its client.
+    pub fn close_window(&mut self, window_id: Option<u64>) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (space_name, window) = self.window_by_id(window_id)?;
+        if let Some(closing_window) = self.closing_windows.get(&window_id) {
+            if closing_window.not_responding {
+                return self.kill_window(Some(window_id));
+            }
+            // Still waiting for an answer
+            return Ok(());
+        }
+
+        info!(window_id, "Closing window");
+        if window.close() {
+            self.space_mut(&space_name)?.unmap_elem(&window);
+            return Ok(());
+        }
+
+        let ping = match &window {
+            WorkspaceWindow::ApplicationWindow(window) => {
+                if let Some(toplevel) = window.toplevel() {
+                    let shell_client = toplevel.client();
+                    match shell_client.send_ping(SERIAL_COUNTER.next_serial()) {
+                        // The answer to the pending ping is just as good
+                        Ok(()) | Err(PingError::PingAlreadyPending(_)) => {
+                            Some(Ping::Xdg(shell_client))
+                        }
+                        Err(err) => {
+                            warn!(?err, window_id, "Unable to ping window");
+                            None
+                        }
+                    }
+                } else if let Some(x11_surface) = window.x11_surface() {
+                    let x11_window = x11_surface.window_id();
+                    self.ping_x11_window(x11_window)
+                        .then_some(Ping::X11(x11_window))
+                } else {
+                    None
+                }
+            }
+            WorkspaceWindow::EguiWindow(_) => None,
+        };
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(CLOSE_TIMEOUT),
+            move |_, _, state| {
+                state.close_timed_out(window_id);
+                TimeoutAction::Drop
+            },
+        );
+        let timeout = match res {
+            Ok(token) => Some(token),
+            Err(err) => {
+                warn!(?err, "Unable to start close timeout");
+                None
+            }
+        };
+        self.closing_windows.insert(
+            window_id,
+            ClosingWindow {
+                ping,
+                timeout,
+                not_responding: false,
+            },
+        );
+        Ok(())
+    }
+
+    /// Kills the client of the window with the id, or of the focused window
+    pub fn kill_window(&mut self, window_id: Option<u64>) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (_, window) = self.window_by_id(window_id)?;
+        self.stop_closing(window_id);
+
+        // Only the connection of the client is closed, its process is left alone
+        let WorkspaceWindow::ApplicationWindow(window) = window else {
+            return Ok(());
+        };
+        info!(window_id, "Killing client of window");
+        if let Some(x11_surface) = window.x11_surface() {
+            if let Err(err) = self.kill_x11_client(x11_surface.window_id()) {
+                warn!(?err, window_id, "Unable to kill X11 client");
+            }
+        } else if let Some(client) = window
+            .toplevel()
+            .and_then(|toplevel| toplevel.wl_surface().client())
+        {
+            self.display_handle
+                .backend_handle()
+                .kill_client(client.id(), DisconnectReason::ConnectionClosed);
+        }
+        Ok(())
+    }
+
+    /// Called when a client answered the ping of its `xdg_wm_base`. Its closing windows are
+    /// responsive, but may stay open, e.g. to ask for unsaved changes.
+    pub fn shell_client_ponged(&mut self, shell_client: &ShellClient) {
+        self.stop_closing_answered(
+            |ping| matches!(ping, Ping::Xdg(pinged) if pinged == shell_client),
+        );
+    }
+
+    /// Called when the X11 window answered `_NET_WM_PING`
+    pub fn x11_window_ponged(&mut self, x11_window: Window) {
+        self.stop_closing_answered(
+            |ping| matches!(ping, Ping::X11(pinged) if *pinged == x11_window),
+        );
+    }
+
+    fn stop_closing_answered(&mut self, answered: impl Fn(&Ping) -> bool) {
+        let window_ids = self
+            .closing_windows
+            .iter()
+            .filter(|(_, closing_window)| closing_window.ping.as_ref().is_some_and(&answered))
+            .map(|(window_id, _)| *window_id)
+            .collect::<Vec<_>>();
+        for window_id in window_ids {
+            self.stop_closing(window_id);
+        }
+    }
+
+    /// Forgets that the window was asked to close, e.g. because it is gone
+    pub fn stop_closing(&mut self, window_id: u64) {
+        if let Some(timeout) = self
+            .closing_windows
+            .remove(&window_id)
+            .and_then(|closing_window| closing_window.timeout)
+        {
+            self.loop_handle.remove(timeout);
+        }
+    }
+
+    fn close_timed_out(&mut self, window_id: u64) {
+        let Some(closing_window) = self.closing_windows.get_mut(&window_id) else {
+            return;
+        };
+        // The timer is removed once this callback returns
+        closing_window.timeout = None;
+        closing_window.not_responding = true;
+        let Ok((space_name, window)) = self.window_by_id(window_id) else {
+            self.closing_windows.remove(&window_id);
+            return;
+        };
+        warn!(
+            window_id,
+            app_id = window.app_id(),
+            "Window is not responding"
+        );
+        self.on_window_not_responding(&window, &space_name);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    egui_window::{EguiAppState, EguiWindow},
+    error::{Result, ScapeError},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+use egui::{Context, RichText, Spinner};
+use smithay::reexports::wayland_server::Resource;
+use std::{fs, time::Duration};
+use tracing::{debug, info, warn};
+
+/// Time after which a placeholder is removed if the application did not map a window
+const PLACEHOLDER_TIMEOUT: Duration = Duration::from_secs(30);
+/// Interval in which the spinner of the placeholders is redrawn
+const REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+/// Parent processes which are checked to find the process which spawned a window
+const MAX_PROCESS_DEPTH: usize = 8;
+
+/// Zone a spawned application was started in, it keeps the window in the zone when it is placed
+/// again, e.g. after the app id changed
+struct SpawnZone(String);
+
+/// Shown in the zone of an application which is still starting
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowPlaceholder {
+    name: String,
+}
+
+impl WindowPlaceholder {
+    pub fn show(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space((ui.available_height() / 2.0 - 48.0).max(0.0));
+                // TODO: Show the icon of the desktop entry once icons can be looked up
+                ui.add(Spinner::new().size(48.0));
+                ui.label(RichText::new(&self.name).heading());
+            });
+        });
+    }
+}
+
+impl From<WindowPlaceholder> for EguiAppState {
+    fn from(window_placeholder: WindowPlaceholder) -> Self {
+        EguiAppState::WindowPlaceholder(window_placeholder)
+    }
+}
+
+#[derive(Debug)]
+pub struct PendingPlacement {
+    /// Process which was spawned, windows of it or of its children claim the placeholder
+    pid: u32,
+    zone: String,
+    window: EguiWindow,
+    space_name: String,
+    timeout: Option<RegistrationToken>,
+}
+
+/// Returns the parent process of `pid`
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name can contain spaces, the fields after it are separated by spaces
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Whether `pid` is `ancestor` or one of its children
+pub fn is_descendant(mut pid: u32, ancestor: u32) -> bool {
+    for _ in 0..MAX_PROCESS_DEPTH {
+        if pid == ancestor {
+            return true;
+        }
+        match parent_pid(pid) {
+            Some(parent) if parent > 1 => pid = parent,
+            _ => return false,
+        }
+    }
+    false
+}
+
+impl State {
+    /// Shows a placeholder in `zone` until the first window of the process with `pid` is mapped
+    pub fn show_window_placeholder(&mut self, name: &str, pid: u32, zone: String) -> Result<()> {
+        let placeholder_zone = self
+            .zones
+            .get(&zone)
+            .ok_or_else(|| ScapeError::ZoneNotFound(zone.clone()))?;
+        let geometry = self.zone_window_geometry(placeholder_zone);
+        // Shown on the space of the output the zone is on
+        let space_name = self.space_of_zone(placeholder_zone)?;
+
+        let window = EguiWindow::new(WindowPlaceholder {
+            name: name.to_string(),
+        });
+        self.apply_theme_to_window(&WorkspaceWindow::from(window.clone()));
+        window.position(geometry.size);
+        self.space_mut(&space_name)?.map_element(
+            WorkspaceWindow::from(window.clone()),
+            geometry.loc,
+            false,
+        );
+
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(PLACEHOLDER_TIMEOUT),
+            move |_, _, state| {
+                info!(
+                    pid,
+                    "Application did not map a window in time, removing placeholder"
+                );
+                if let Some(index) = state
+                    .window_placeholders
+                    .iter()
+                    .position(|placeholder| placeholder.pid == pid)
+                {
+                    let mut placeholder = state.window_placeholders.remove(index);
+                    placeholder.timeout = None;
+                    state.remove_window_placeholder(placeholder);
+                }
+                TimeoutAction::Drop
+            },
+        );
+        let timeout = match res {
+            Ok(token) => Some(token),
+            Err(err) => {
+                warn!(?err, "Unable to start placeholder timeout");
+                None
+            }
+        };
+
+        if self.window_placeholders.is_empty() {
+            self.start_placeholder_repaint();
+        }
+        self.window_placeholders.push(PendingPlacement {
+            pid,
+            zone,
+            window,
+            space_name,
+            timeout,
+        });
+        info!(name, pid, "Window placeholder shown");
+        Ok(())
+    }
+
+    fn start_placeholder_repaint(&mut self) {
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(REPAINT_INTERVAL),
+            |_, _, state| {
+                if state.window_placeholders.is_empty() {
+                    return TimeoutAction::Drop;
+                }
+                state.backend_data.schedule_render();
+                TimeoutAction::ToDuration(REPAINT_INTERVAL)
+            },
+        );
+        if let Err(err) = res {
+            warn!(?err, "Unable to start placeholder repaint timer");
+        }
+    }
+
+    fn remove_window_placeholder(&mut self, placeholder: PendingPlacement) {
+        if let Some(timeout) = placeholder.timeout {
+            self.loop_handle.remove(timeout);
+        }
+        if let Some(space) = self.spaces.get_mut(&placeholder.space_name) {
+            space.unmap_elem(&WorkspaceWindow::from(placeholder.window));
+        }
+        self.backend_data.schedule_render();
+    }
+
+    pub fn window_pid(&self, window: &WorkspaceWindow) -> Option<u32> {
+        let WorkspaceWindow::ApplicationWindow(window) = window else {
+            return None;
+        };
+        if let Some(x11_surface) = window.x11_surface() {
+            return x11_surface.pid();
+        }
+        let credentials = window
+            .toplevel()?
+            .wl_surface()
+            .client()?
+            .get_credentials(&self.display_handle)
+            .ok()?;
+        u32::try_from(credentials.pid).ok()
+    }
+
+    /// Zone the window was spawned in. The first window of a spawned process replaces the
+    /// placeholder of the process.
+    pub fn spawn_zone(&mut self, window: &WorkspaceWindow) -> Option<String> {
+        let WorkspaceWindow::ApplicationWindow(application_window) = window else {
+            return None;
+        };
+        if let Some(SpawnZone(zone)) = application_window.user_data().get::<SpawnZone>() {
+            return Some(zone.clone());
+        }
+        if self.window_placeholders.is_empty() {
+            return None;
+        }
+
+        let pid = self.window_pid(window)?;
+        let index = self
+            .window_placeholders
+            .iter()
+            .position(|placeholder| is_descendant(pid, placeholder.pid))?;
+        let placeholder = self.window_placeholders.remove(index);
+        let zone = placeholder.zone.clone();
+        debug!(pid, zone, "Window replaces placeholder");
+        self.remove_window_placeholder(placeholder);
+
+        application_window
+            .user_data()
+            .insert_if_missing(|| SpawnZone(zone.clone()));
+        Some(zone)
+    }
+}
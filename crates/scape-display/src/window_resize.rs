@@ -0,0 +1,228 @@
+//! Resizing windows with the keyboard. A window in a zone resizes its zone together with the
+//! neighboring zones sharing the edge, like a split in a tiling layout. Other windows are resized
+//! directly.
+
+use crate::{
+    composition::{Zone, EDGE_TOLERANCE},
+    error::{Result, ScapeError},
+    grabs::ResizeEdge,
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use anyhow::bail;
+use smithay::{
+    desktop::space::SpaceElement,
+    utils::{Logical, Rectangle, Size},
+    wayland::{compositor::with_states, shell::xdg::SurfaceCachedState},
+};
+use tracing::info;
+
+/// Zones are not shrunk below this width and height
+const MIN_ZONE_SIZE: i32 = 64;
+
+#[derive(Debug, Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+/// Edges of the window, the trailing edge is the right or bottom one
+const EDGES: [(ResizeEdge, Axis, bool); 4] = [
+    (ResizeEdge::LEFT, Axis::X, false),
+    (ResizeEdge::RIGHT, Axis::X, true),
+    (ResizeEdge::TOP, Axis::Y, false),
+    (ResizeEdge::BOTTOM, Axis::Y, true),
+];
+
+impl Axis {
+    /// Start and length of the rectangle along the axis
+    fn span(self, rectangle: Rectangle<i32, Logical>) -> (i32, i32) {
+        match self {
+            Axis::X => (rectangle.loc.x, rectangle.size.w),
+            Axis::Y => (rectangle.loc.y, rectangle.size.h),
+        }
+    }
+
+    fn set_span(self, rectangle: &mut Rectangle<i32, Logical>, start: i32, length: i32) {
+        match self {
+            Axis::X => (rectangle.loc.x, rectangle.size.w) = (start, length),
+            Axis::Y => (rectangle.loc.y, rectangle.size.h) = (start, length),
+        }
+    }
+
+    fn length(self, size: Size<i32, Logical>) -> i32 {
+        match self {
+            Axis::X => size.w,
+            Axis::Y => size.h,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Axis::X => Axis::Y,
+            Axis::Y => Axis::X,
+        }
+    }
+}
+
+pub fn resize_edge_from_name(name: &str) -> anyhow::Result<ResizeEdge> {
+    let edge = match name {
+        "left" => ResizeEdge::LEFT,
+        "right" => ResizeEdge::RIGHT,
+        "top" => ResizeEdge::TOP,
+        "bottom" => ResizeEdge::BOTTOM,
+        "top_left" => ResizeEdge::TOP_LEFT,
+        "top_right" => ResizeEdge::TOP_RIGHT,
+        "bottom_left" => ResizeEdge::BOTTOM_LEFT,
+        "bottom_right" => ResizeEdge::BOTTOM_RIGHT,
+        _ => bail!("Unknown edge {name}"),
+    };
+    Ok(edge)
+}
+
+impl State {
+    /// Moves the edge of the window with the id, or of the focused window, outwards by `delta`
+    /// logical pixels, a negative delta shrinks the window
+    pub fn resize_window(
+        &mut self,
+        window_id: Option<u64>,
+        edge: ResizeEdge,
+        delta: i32,
+    ) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (space_name, window) = self.window_by_id(window_id)?;
+        let location = self
+            .space(&space_name)?
+            .element_location(&window)
+            .ok_or_else(|| ScapeError::WindowNotMapped(space_name.clone()))?;
+        let geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+
+        if let Some(zone_name) = self.zone_at(geometry) {
+            self.resize_zone(&zone_name, edge, delta);
+            return Ok(());
+        }
+
+        let (min_size, max_size) = size_limits(&window);
+        let mut resized = geometry;
+        for (flag, axis, trailing) in EDGES {
+            if !edge.contains(flag) {
+                continue;
+            }
+            let (start, length) = axis.span(geometry);
+            let (min, max) = (axis.length(min_size).max(1), axis.length(max_size));
+            let new_length = (length + delta).clamp(min, max.max(min));
+            let new_start = if trailing {
+                start
+            } else {
+                start + length - new_length
+            };
+            axis.set_span(&mut resized, new_start, new_length);
+        }
+        if resized == geometry {
+            return Ok(());
+        }
+        info!(window_id, ?edge, delta, "Resizing window");
+        self.place_window_at(&space_name, &window, resized, true)?;
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Moves the edge of the zone and the adjacent edges of its neighbors. Outer edges of the
+    /// layout stay where they are.
+    fn resize_zone(&mut self, zone_name: &str, edge: ResizeEdge, delta: i32) {
+        let mut zones = self.zones.values().cloned().collect::<Vec<Zone>>();
+        let Some(index) = zones.iter().position(|zone| zone.name == zone_name) else {
+            return;
+        };
+
+        let mut changed = false;
+        for (flag, axis, trailing) in EDGES {
+            if !edge.contains(flag) {
+                continue;
+            }
+            let zone = zones[index].geometry;
+            let (start, length) = axis.span(zone);
+            let (cross_start, cross_length) = axis.other().span(zone);
+            let edge_position = if trailing { start + length } else { start };
+            let neighbors = zones
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .filter(|(_, neighbor)| {
+                    let (start, length) = axis.span(neighbor.geometry);
+                    let (other_start, other_length) = axis.other().span(neighbor.geometry);
+                    let facing_edge = if trailing { start } else { start + length };
+                    (facing_edge - edge_position).abs() <= EDGE_TOLERANCE
+                        && other_start < cross_start + cross_length
+                        && cross_start < other_start + other_length
+                })
+                .map(|(neighbor, _)| neighbor)
+                .collect::<Vec<_>>();
+            let Some(smallest_neighbor) = neighbors
+                .iter()
+                .map(|neighbor| axis.span(zones[*neighbor].geometry).1)
+                .min()
+            else {
+                continue;
+            };
+
+            let max_shrink = length - MIN_ZONE_SIZE;
+            let max_grow = smallest_neighbor - MIN_ZONE_SIZE;
+            if max_shrink < 0 || max_grow < 0 {
+                continue;
+            }
+            let delta = delta.clamp(-max_shrink, max_grow);
+            if delta == 0 {
+                continue;
+            }
+            let zone = &mut zones[index].geometry;
+            if trailing {
+                axis.set_span(zone, start, length + delta);
+            } else {
+                axis.set_span(zone, start - delta, length + delta);
+            }
+            for neighbor in neighbors {
+                let geometry = &mut zones[neighbor].geometry;
+                let (start, length) = axis.span(*geometry);
+                if trailing {
+                    axis.set_span(geometry, start + delta, length - delta);
+                } else {
+                    axis.set_span(geometry, start, length - delta);
+                }
+            }
+            changed = true;
+        }
+
+        if changed {
+            info!(zone_name, ?edge, delta, "Resizing zone");
+            // Windows filling the zones move along with them
+            self.set_zones(zones, self.zone_gaps);
+            self.backend_data.schedule_render();
+        }
+    }
+}
+
+/// Minimum and maximum size the client accepts, unlimited dimensions are `i32::MAX`
+fn size_limits(window: &WorkspaceWindow) -> (Size<i32, Logical>, Size<i32, Logical>) {
+    let (min_size, max_size) = if let Some(x11_surface) = window.x11_surface() {
+        (
+            x11_surface.min_size().unwrap_or_default(),
+            x11_surface.max_size().unwrap_or_default(),
+        )
+    } else if let Some(surface) = window.wl_surface() {
+        with_states(&surface, |states| {
+            let mut guard = states.cached_state.get::<SurfaceCachedState>();
+            let data = guard.current();
+            (data.min_size, data.max_size)
+        })
+    } else {
+        Default::default()
+    };
+    let unlimited = |size: i32| if size <= 0 { i32::MAX } else { size };
+    (
+        min_size,
+        (unlimited(max_size.w), unlimited(max_size.h)).into(),
+    )
+}
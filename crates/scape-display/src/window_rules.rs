@@ -0,0 +1,226 @@
+use crate::{focus::KeyboardFocusTarget, workspace_window::WorkspaceWindow, State};
+use smithay::{
+    desktop::find_popup_root_surface,
+    reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+    reexports::wayland_server::Resource,
+    utils::{Logical, Size},
+    wayland::{seat::WaylandFocus, shell::xdg::ToplevelSurface},
+};
+use tracing::{debug, warn};
+
+/// Criteria a window has to fulfill for a rule to apply. Unset criteria match every window.
+#[derive(Debug, Default, Clone)]
+pub struct WindowMatch {
+    pub app_id: Option<String>,
+    pub title: Option<String>,
+}
+
+impl WindowMatch {
+    fn matches(&self, window: &WorkspaceWindow) -> bool {
+        self.app_id
+            .as_ref()
+            .map_or(true, |app_id| *app_id == window.app_id())
+            && self
+                .title
+                .as_ref()
+                .map_or(true, |title| *title == window.title())
+    }
+}
+
+/// Properties applied to a window when it is mapped. Unset properties keep the defaults.
+#[derive(Debug, Default, Clone)]
+pub struct WindowProperties {
+    /// Floating windows are centered on the output instead of being placed into a zone
+    pub floating: Option<bool>,
+    pub zone: Option<String>,
+    pub space: Option<String>,
+    pub size: Option<Size<i32, Logical>>,
+    pub opacity: Option<f32>,
+    /// Opacity while the window is not focused, `opacity` applies if it is unset
+    pub opacity_unfocused: Option<f32>,
+    /// Whether the window is decorated by the compositor
+    pub ssd: Option<bool>,
+    /// Multiplier of the scroll distance sent to the client
+    pub scroll_factor: Option<f64>,
+    /// Sends vertical scrolling as horizontal scrolling and the other way around
+    pub swap_scroll_axes: Option<bool>,
+}
+
+impl WindowProperties {
+    /// Overrides all properties which are set in `other`
+    fn merge(&mut self, other: &WindowProperties) {
+        if other.floating.is_some() {
+            self.floating = other.floating;
+        }
+        if other.zone.is_some() {
+            self.zone.clone_from(&other.zone);
+        }
+        if other.space.is_some() {
+            self.space.clone_from(&other.space);
+        }
+        if other.size.is_some() {
+            self.size = other.size;
+        }
+        if other.opacity.is_some() {
+            self.opacity = other.opacity;
+        }
+        if other.opacity_unfocused.is_some() {
+            self.opacity_unfocused = other.opacity_unfocused;
+        }
+        if other.ssd.is_some() {
+            self.ssd = other.ssd;
+        }
+        if other.scroll_factor.is_some() {
+            self.scroll_factor = other.scroll_factor;
+        }
+        if other.swap_scroll_axes.is_some() {
+            self.swap_scroll_axes = other.swap_scroll_axes;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WindowRule {
+    pub matches: WindowMatch,
+    pub properties: WindowProperties,
+}
+
+impl State {
+    pub fn add_window_rule(&mut self, window_rule: WindowRule) {
+        if let Some(zone) = &window_rule.properties.zone {
+            if !self.zones.contains_key(zone) {
+                // Zones are often set after the rules, so this is not an error yet
+                debug!(
+                    zone,
+                    "Window rule refers to a zone which does not exist yet"
+                );
+            }
+        }
+        self.window_rules.push(window_rule);
+    }
+
+    /// Properties of all rules matching the window, later rules override earlier ones. The kiosk
+    /// mode disables server side decorations.
+    pub fn window_properties(&self, window: &WorkspaceWindow) -> WindowProperties {
+        let mut properties = WindowProperties::default();
+        for rule in &self.window_rules {
+            if rule.matches.matches(window) {
+                properties.merge(&rule.properties);
+            }
+        }
+        if self.kiosk.is_some() {
+            // Kiosk applications are fullscreen, a header bar would only allow to move them
+            properties.ssd = Some(false);
+        }
+        properties
+    }
+
+    /// Space the window should be mapped to according to the window rules, `default` if no rule
+    /// requests an existing space
+    pub fn window_rule_space(&self, window: &WorkspaceWindow, default: &str) -> String {
+        match self.window_properties(window).space {
+            Some(space_name) if self.spaces.contains_key(&space_name) => space_name,
+            Some(space_name) => {
+                warn!(
+                    space_name,
+                    "Window rule refers to a space which does not exist"
+                );
+                default.to_string()
+            }
+            None => default.to_string(),
+        }
+    }
+
+    /// Decoration mode the window rules enforce for the toplevel
+    pub fn window_rule_decoration_mode(
+        &self,
+        toplevel: &ToplevelSurface,
+    ) -> Option<DecorationMode> {
+        let (window, _) = self.window_and_space_for_surface(toplevel.wl_surface())?;
+        self.window_properties(&window).ssd.map(decoration_mode)
+    }
+
+    /// Scroll factor and whether the scroll axes are swapped for the client the pointer is over.
+    /// Popups and subsurfaces use the rules of a window of the same client.
+    pub fn pointer_focus_scroll(&self) -> (f64, bool) {
+        let client = self
+            .pointer
+            .as_ref()
+            .and_then(|pointer| pointer.current_focus())
+            .and_then(|focus| focus.wl_surface()?.client());
+        let properties = client
+            .and_then(|client| {
+                self.spaces
+                    .values()
+                    .flat_map(|space| space.elements())
+                    .find(|window| {
+                        window
+                            .wl_surface()
+                            .is_some_and(|surface| surface.client().as_ref() == Some(&client))
+                    })
+            })
+            .map(|window| self.window_properties(window))
+            .unwrap_or_default();
+        (
+            properties.scroll_factor.unwrap_or(1.0),
+            properties.swap_scroll_axes.unwrap_or(false),
+        )
+    }
+
+    /// Applies the properties of the window rules which are not about the placement
+    pub fn apply_window_rules(&self, window: &WorkspaceWindow) {
+        let properties = self.window_properties(window);
+        let WorkspaceWindow::ApplicationWindow(application_window) = window else {
+            return;
+        };
+
+        let opacity = properties.opacity.unwrap_or(1.0);
+        application_window.set_opacity(opacity, properties.opacity_unfocused.unwrap_or(opacity));
+
+        let Some(ssd) = properties.ssd else {
+            return;
+        };
+        match application_window.toplevel() {
+            Some(toplevel) => {
+                toplevel.with_pending_state(|state| {
+                    state.decoration_mode = Some(decoration_mode(ssd));
+                });
+                if toplevel.is_initial_configure_sent() {
+                    toplevel.send_pending_configure();
+                }
+            }
+            None => window.set_ssd(ssd),
+        }
+    }
+
+    /// Marks the window of the keyboard focus as focused and all others as unfocused, so that
+    /// they are rendered with their unfocused opacity
+    pub fn update_focused_window(&mut self, target: Option<&KeyboardFocusTarget>) {
+        let focused_surface = match target {
+            Some(KeyboardFocusTarget::Window(window)) => {
+                window.wl_surface().map(|surface| surface.into_owned())
+            }
+            // Menus of a window keep it focused
+            Some(KeyboardFocusTarget::Popup(popup)) => find_popup_root_surface(popup).ok(),
+            _ => None,
+        };
+        for window in self.spaces.values().flat_map(|space| space.elements()) {
+            if let WorkspaceWindow::ApplicationWindow(application_window) = window {
+                let focused = focused_surface.is_some()
+                    && application_window.wl_surface().as_deref() == focused_surface.as_ref();
+                if application_window.is_focused() != focused {
+                    application_window.set_focused(focused);
+                    self.backend_data.schedule_render();
+                }
+            }
+        }
+    }
+}
+
+fn decoration_mode(ssd: bool) -> DecorationMode {
+    if ssd {
+        DecorationMode::ServerSide
+    } else {
+        DecorationMode::ClientSide
+    }
+}
@@ -0,0 +1,195 @@
+//! Fullscreen and maximized windows. Requests of xdg and X11 clients and the actions of the
+//! config go through the same functions, so windows behave alike no matter who changed them.
+
+use crate::{
+    error::{Result, ScapeError},
+    shell::{set_restore_geometry, take_restore_geometry},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{
+    desktop::{layer_map_for_output, space::SpaceElement},
+    output::Output,
+    reexports::wayland_server::Resource,
+    utils::Rectangle,
+};
+use tracing::info;
+
+impl State {
+    /// Toggles fullscreen of the window with the id, or of the focused window
+    pub fn toggle_fullscreen(&mut self, window_id: Option<u64>) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (space_name, window) = self.window_by_id(window_id)?;
+        let WorkspaceWindow::ApplicationWindow(application_window) = &window else {
+            return Ok(());
+        };
+        let fullscreen = !application_window.is_fullscreen();
+        self.set_window_fullscreen(&window, &space_name, fullscreen, None, true)
+    }
+
+    /// Toggles whether the window with the id, or the focused window, is maximized
+    pub fn toggle_maximize(&mut self, window_id: Option<u64>) -> Result<()> {
+        let Some(window_id) = window_id.or_else(|| self.focused_window()?.id()) else {
+            return Ok(());
+        };
+        let (space_name, window) = self.window_by_id(window_id)?;
+        let WorkspaceWindow::ApplicationWindow(application_window) = &window else {
+            return Ok(());
+        };
+        let maximized = !application_window.is_maximized();
+        self.set_window_maximized(&window, &space_name, maximized, true)
+    }
+
+    /// Makes the window cover the output, the output it is on if none is given. Leaving the
+    /// fullscreen state puts the window back to the geometry it had before.
+    pub fn set_window_fullscreen(
+        &mut self,
+        window: &WorkspaceWindow,
+        space_name: &str,
+        fullscreen: bool,
+        output: Option<Output>,
+        send_configure: bool,
+    ) -> Result<()> {
+        let WorkspaceWindow::ApplicationWindow(application_window) = window else {
+            return Ok(());
+        };
+        if !fullscreen {
+            if !application_window.is_fullscreen() {
+                return Ok(());
+            }
+            info!(app_id = window.app_id(), "Leaving fullscreen");
+            application_window.set_fullscreen(false);
+            if let Some(toplevel) = window.toplevel() {
+                let output = toplevel.with_pending_state(|state| state.fullscreen_output.take());
+                if let Some(output) = output.as_ref().and_then(Output::from_resource) {
+                    self.backend_data.reset_buffers(&output);
+                }
+            }
+            if let Some(x11_surface) = window.x11_surface() {
+                window.set_ssd(!x11_surface.is_decorated());
+            }
+            return self.restore_window_geometry(window, space_name, send_configure);
+        }
+
+        let output = match output {
+            Some(output) => output,
+            None => self.output_for_window(window, space_name)?,
+        };
+        let geometry = self
+            .space(space_name)?
+            .output_geometry(&output)
+            .ok_or_else(|| ScapeError::NoOutput(space_name.to_string()))?;
+        info!(
+            app_id = window.app_id(),
+            output = output.name(),
+            "Making window fullscreen"
+        );
+        self.save_restore_geometry(window, space_name)?;
+        application_window.set_fullscreen(true);
+        if let Some(toplevel) = window.toplevel() {
+            // The client has to know the output by the resource it bound
+            let wl_output = self
+                .display_handle
+                .get_client(toplevel.wl_surface().id())
+                .ok()
+                .and_then(|client| output.client_outputs(&client).into_iter().last());
+            toplevel.with_pending_state(|state| state.fullscreen_output = wl_output);
+        }
+        if window.x11_surface().is_some() {
+            window.set_ssd(false);
+        }
+        self.place_window_at(space_name, window, geometry, send_configure)
+    }
+
+    /// Makes the window fill the usable area of its output. Unmaximizing it puts it back to the
+    /// geometry it had before.
+    pub fn set_window_maximized(
+        &mut self,
+        window: &WorkspaceWindow,
+        space_name: &str,
+        maximized: bool,
+        send_configure: bool,
+    ) -> Result<()> {
+        let WorkspaceWindow::ApplicationWindow(application_window) = window else {
+            return Ok(());
+        };
+        if !maximized {
+            if !application_window.is_maximized() {
+                return Ok(());
+            }
+            info!(app_id = window.app_id(), "Unmaximizing window");
+            application_window.set_maximized(false);
+            return self.restore_window_geometry(window, space_name, send_configure);
+        }
+
+        let output = self.output_for_window(window, space_name)?;
+        let output_geometry = self
+            .space(space_name)?
+            .output_geometry(&output)
+            .ok_or_else(|| ScapeError::NoOutput(space_name.to_string()))?;
+        let usable_zone = layer_map_for_output(&output).non_exclusive_zone();
+        let geometry =
+            Rectangle::from_loc_and_size(output_geometry.loc + usable_zone.loc, usable_zone.size);
+        info!(
+            app_id = window.app_id(),
+            output = output.name(),
+            "Maximizing window"
+        );
+        self.save_restore_geometry(window, space_name)?;
+        application_window.set_maximized(true);
+        self.place_window_at(space_name, window, geometry, send_configure)
+    }
+
+    /// Output the window is on, or the first output of the space if it isn't mapped yet
+    fn output_for_window(&self, window: &WorkspaceWindow, space_name: &str) -> Result<Output> {
+        let space = self.space(space_name)?;
+        space
+            .outputs_for_element(window)
+            .into_iter()
+            .next()
+            .or_else(|| space.outputs().next().cloned())
+            .ok_or_else(|| ScapeError::NoOutput(space_name.to_string()))
+    }
+
+    /// Remembers the current geometry of the window, unless it is already maximized or
+    /// fullscreen and its current geometry is not the one to return to
+    fn save_restore_geometry(&self, window: &WorkspaceWindow, space_name: &str) -> Result<()> {
+        let WorkspaceWindow::ApplicationWindow(application_window) = window else {
+            return Ok(());
+        };
+        if application_window.is_fullscreen() || application_window.is_maximized() {
+            return Ok(());
+        }
+        let Some(surface) = window.wl_surface() else {
+            return Ok(());
+        };
+        let geometry = self
+            .space(space_name)?
+            .element_location(window)
+            .map(|location| Rectangle::from_loc_and_size(location, window.geometry().size));
+        set_restore_geometry(&surface, geometry);
+        Ok(())
+    }
+
+    /// Moves the window back to the geometry it had before it was maximized or made fullscreen,
+    /// windows without one are placed like new windows
+    fn restore_window_geometry(
+        &mut self,
+        window: &WorkspaceWindow,
+        space_name: &str,
+        send_configure: bool,
+    ) -> Result<()> {
+        let geometry = window
+            .wl_surface()
+            .and_then(|surface| take_restore_geometry(&surface));
+        match geometry {
+            Some(geometry) => self.place_window_at(space_name, window, geometry, send_configure),
+            None => {
+                self.place_window(space_name, window, false, None, send_configure);
+                Ok(())
+            }
+        }
+    }
+}
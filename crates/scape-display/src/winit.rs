@@ -5,7 +5,7 @@ use crate::drawing::FPS_NUMBERS_PNG;
 use crate::{
     protocols::presentation_time::take_presentation_feedback,
     render::CustomRenderElements,
-    state::{post_repaint, ActiveSpace, BackendData, State},
+    state::{active_space, post_repaint, BackendData, State},
 };
 use anyhow::{anyhow, Result};
 use calloop::timer::{TimeoutAction, Timer};
@@ -83,6 +83,10 @@ impl WinitData {
         &mut self.dmabuf_state.0
     }
 
+    pub fn renderer(&mut self) -> &mut GlowRenderer {
+        self.backend.renderer()
+    }
+
     pub fn dmabuf_imported(
         &mut self,
         _global: &DmabufGlobal,
@@ -198,7 +202,8 @@ pub fn init_winit(
         .handle()
         .insert_source(Timer::immediate(), |_event, &mut (), state| {
             let output = state.backend_data.winit().output.clone();
-            state.outputs.insert("winit".into(), output);
+            state.outputs.insert("winit".into(), output.clone());
+            state.on_output_added(&output);
             state.on_connector_change();
             TimeoutAction::Drop
         })
@@ -228,6 +233,8 @@ pub fn init_winit(
 }
 
 fn run_tick(state: &mut State) {
+    state.refresh_tab_bars();
+    state.refresh_bars();
     let winit_data = state.backend_data.winit_mut();
     let mut handle_events = false;
     let mut stop_rendering = false;
@@ -288,6 +295,10 @@ fn run_tick(state: &mut State) {
             .unwrap();
     }
 
+    if state.disabled_outputs.contains_key(OUTPUT_NAME) {
+        return;
+    }
+
     // drawing logic
     {
         let backend = &mut winit_data.backend;
@@ -319,7 +330,7 @@ fn run_tick(state: &mut State) {
         *full_redraw = full_redraw.saturating_sub(1);
         let space = state
             .spaces
-            .get_mut(&output.user_data().get::<ActiveSpace>().unwrap().0)
+            .get_mut(&active_space(&output).unwrap())
             .unwrap();
         let damage_tracker = &mut winit_data.damage_tracker;
         let show_window_preview = state.show_window_preview;
@@ -385,29 +396,76 @@ fn run_tick(state: &mut State) {
                 }
             }
 
+            if let Some(snap_preview) = &state.snap_preview {
+                elements.extend(snap_preview.render_element(space, &output));
+            }
+
+            if let Some(pointer_capture) = &state.pointer_capture {
+                elements.extend(pointer_capture.render_element(space, &output, cursor_pos));
+            }
+
+            if let Some(region_select) = &state.region_select {
+                elements.extend(region_select.render_elements(space, &output, cursor_pos));
+            }
+
+            if let Some(recording) = state.recordings.get(&output.name()) {
+                elements.extend(recording.render_element(space, &output));
+            }
+
+            for tab_bar in &state.tab_bars {
+                elements.extend(tab_bar.render_elements(space, &output));
+            }
+
+            for bar in &state.bars {
+                elements.extend(bar.render_elements(space, &output));
+            }
+
             #[cfg(feature = "debug")]
             elements.push(CustomRenderElements::Fps(fps_element.clone()));
 
-            crate::render::render_output(
+            let (mut elements, clear_color) = crate::render::output_elements(
                 &output,
                 space,
                 elements,
                 renderer,
-                damage_tracker,
-                age,
                 show_window_preview,
+                &state.window_thumbnail,
                 &state.session_lock,
-            )
-            .map_err(|err| match err {
-                OutputDamageTrackerError::Rendering(err) => err.into(),
-                _ => unreachable!(),
-            })
+                state.theme.palette().background,
+            );
+            if let (Some(magnifier), Some(output_geometry)) = (
+                state.magnifiers.get_mut(&output.name()),
+                space.output_geometry(&output),
+            ) {
+                match magnifier.magnify(
+                    renderer,
+                    &output,
+                    output_geometry,
+                    cursor_pos,
+                    &elements,
+                    clear_color,
+                ) {
+                    Ok(element) => elements = vec![element],
+                    Err(err) => warn!("Unable to magnify output: {err:#}"),
+                }
+            }
+            damage_tracker
+                .render_output(renderer, age, &elements, clear_color)
+                .map_err(|err| match err {
+                    OutputDamageTrackerError::Rendering(err) => err.into(),
+                    _ => unreachable!(),
+                })
         });
 
         match render_res {
             Ok(render_output_result) => {
-                let has_rendered = render_output_result.damage.is_some();
-                if let Some(damage) = render_output_result.damage {
+                // An empty damage would be presented as a full frame update to the host
+                let damage = render_output_result
+                    .damage
+                    .filter(|damage| !damage.is_empty());
+                let has_rendered = damage.is_some();
+                if let Some(damage) = damage {
+                    // Only the damaged region is presented to the host compositor
                     if let Err(err) = backend.submit(Some(damage)) {
                         warn!("Failed to submit buffer: {}", err);
                     }
@@ -423,7 +481,12 @@ fn run_tick(state: &mut State) {
                     None,
                     time,
                     &state.cursor_state,
+                    dnd_icon,
+                    state.session_lock.as_ref(),
                 );
+                state
+                    .loop_handle
+                    .insert_idle(|state| state.update_window_suspension());
 
                 if has_rendered {
                     let mut output_presentation_feedback =
@@ -53,6 +53,20 @@ impl WorkspaceWindow {
         }
     }
 
+    pub fn title(&self) -> String {
+        match self {
+            WorkspaceWindow::ApplicationWindow(w) => w.title(),
+            WorkspaceWindow::EguiWindow(w) => w.app_id(),
+        }
+    }
+
+    pub fn id(&self) -> Option<u64> {
+        match self {
+            WorkspaceWindow::ApplicationWindow(w) => Some(w.id()),
+            WorkspaceWindow::EguiWindow(_) => None,
+        }
+    }
+
     pub fn position(
         &self,
         location: Point<i32, Logical>,
@@ -112,7 +126,7 @@ impl WorkspaceWindow {
         &self,
         position: Point<f64, Logical>,
         window_type: WindowSurfaceType,
-    ) -> Option<(PointerFocusTarget, Point<i32, Logical>)> {
+    ) -> Option<(PointerFocusTarget, Point<f64, Logical>)> {
         match self {
             WorkspaceWindow::ApplicationWindow(w) => w.surface_under(position, window_type),
             _ => None,
@@ -1,18 +1,54 @@
 use crate::State;
 use anyhow::anyhow;
+use calloop::channel::{self, Sender};
 use smithay::{
+    reexports::x11rb::{
+        self,
+        connection::Connection,
+        protocol::{
+            xproto::{
+                AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConnectionExt as _,
+                EventMask, Window,
+            },
+            Event,
+        },
+        rust_connection::RustConnection,
+        CURRENT_TIME,
+    },
     utils::{Point, Size},
     wayland::xwayland_keyboard_grab::XWaylandKeyboardGrabState,
     xwayland::{X11Wm, XWayland, XWaylandEvent},
 };
-use std::process::Stdio;
-use tracing::{error, info};
+use std::{process::Stdio, sync::Arc, thread};
+use tracing::{error, info, warn};
+
+x11rb::atom_manager! {
+    pub ClientAtoms: ClientAtomsCookie {
+        WM_PROTOCOLS,
+        _NET_WM_PING,
+    }
+}
 
 #[derive(Debug)]
 pub struct XWaylandState {
     pub wm: Option<X11Wm>,
     pub display_number: Option<u32>,
     pub grab_state: XWaylandKeyboardGrabState,
+    /// Connection besides the one of the window manager, it pings and kills clients
+    pub client: Option<X11Client>,
+}
+
+#[derive(Debug)]
+pub struct X11Client {
+    connection: Arc<RustConnection>,
+    atoms: ClientAtoms,
+}
+
+#[derive(Debug)]
+enum X11ClientMessage {
+    Connected(X11Client),
+    /// The window answered `_NET_WM_PING`
+    Pong(Window),
 }
 
 impl State {
@@ -60,6 +96,7 @@ impl State {
 
                     ::std::env::set_var("DISPLAY", format!("{display_number}"));
                     info!("XWayland started");
+                    state.connect_x11_client(display_number);
 
                     state.xwayland_ready();
                 }
@@ -81,8 +118,132 @@ impl State {
             display_number: None,
             wm: None,
             grab_state,
+            client: None,
         });
 
         Ok(())
     }
+
+    /// Opens the connection which pings and kills X11 clients. It waits for events on its own
+    /// thread, the window manager receives everything else.
+    fn connect_x11_client(&mut self, display_number: u32) {
+        let (sender, channel) = channel::channel::<X11ClientMessage>();
+        let res = self
+            .loop_handle
+            .insert_source(channel, |event, _, state| match event {
+                channel::Event::Msg(X11ClientMessage::Connected(client)) => {
+                    if let Some(xwayland_state) = &mut state.xwayland_state {
+                        xwayland_state.client = Some(client);
+                    }
+                }
+                channel::Event::Msg(X11ClientMessage::Pong(window)) => {
+                    state.x11_window_ponged(window);
+                }
+                channel::Event::Closed => {
+                    if let Some(xwayland_state) = &mut state.xwayland_state {
+                        xwayland_state.client = None;
+                    }
+                }
+            });
+        if let Err(err) = res {
+            warn!(
+                ?err,
+                "Unable to receive events of the X11 client connection"
+            );
+            return;
+        }
+        thread::spawn(move || {
+            if let Err(err) = run_x11_client(display_number, sender) {
+                warn!(?err, "X11 client connection failed");
+            }
+        });
+    }
+
+    /// Sends `_NET_WM_PING` to the X11 window, if it supports it. Returns false without a
+    /// connection to Xwayland.
+    pub fn ping_x11_window(&self, window: Window) -> bool {
+        let Some(client) = self.xwayland_state.as_ref().and_then(|x| x.client.as_ref()) else {
+            return false;
+        };
+        let connection = client.connection.clone();
+        let atoms = client.atoms;
+        // Reading the supported protocols waits for Xwayland, which may wait for the compositor
+        thread::spawn(move || {
+            if let Err(err) = send_ping(&connection, atoms, window) {
+                warn!(?err, window, "Unable to ping X11 window");
+            }
+        });
+        true
+    }
+
+    /// Disconnects the X11 client which owns the window, like `XKillClient`
+    pub fn kill_x11_client(&self, window: Window) -> anyhow::Result<()> {
+        let client = self
+            .xwayland_state
+            .as_ref()
+            .and_then(|x| x.client.as_ref())
+            .ok_or_else(|| anyhow!("Xwayland is not connected"))?;
+        client.connection.kill_client(window)?;
+        client.connection.flush()?;
+        Ok(())
+    }
+}
+
+fn run_x11_client(display_number: u32, sender: Sender<X11ClientMessage>) -> anyhow::Result<()> {
+    let (connection, screen) = RustConnection::connect(Some(&format!(":{display_number}")))?;
+    let connection = Arc::new(connection);
+    let atoms = ClientAtoms::new(&*connection)?.reply()?;
+    // Clients answer pings by sending them to the root window
+    let root = connection.setup().roots[screen].root;
+    connection
+        .change_window_attributes(
+            root,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::SUBSTRUCTURE_NOTIFY),
+        )?
+        .check()?;
+    let client = X11Client {
+        connection: connection.clone(),
+        atoms,
+    };
+    if sender.send(X11ClientMessage::Connected(client)).is_err() {
+        return Ok(());
+    }
+
+    loop {
+        let Event::ClientMessage(event) = connection.wait_for_event()? else {
+            continue;
+        };
+        let data = event.data.as_data32();
+        if event.type_ == atoms.WM_PROTOCOLS
+            && data[0] == atoms._NET_WM_PING
+            && sender.send(X11ClientMessage::Pong(data[2])).is_err()
+        {
+            return Ok(());
+        }
+    }
+}
+
+fn send_ping(
+    connection: &RustConnection,
+    atoms: ClientAtoms,
+    window: Window,
+) -> anyhow::Result<()> {
+    let protocols = connection
+        .get_property(false, window, atoms.WM_PROTOCOLS, AtomEnum::ATOM, 0, 32)?
+        .reply()?;
+    let supported = protocols
+        .value32()
+        .is_some_and(|mut protocols| protocols.any(|protocol| protocol == atoms._NET_WM_PING));
+    if !supported {
+        return Ok(());
+    }
+    let event = ClientMessageEvent::new(
+        32,
+        window,
+        atoms.WM_PROTOCOLS,
+        [atoms._NET_WM_PING, CURRENT_TIME, window, 0, 0],
+    );
+    connection.send_event(false, window, EventMask::NO_EVENT, event)?;
+    connection.flush()?;
+    Ok(())
 }
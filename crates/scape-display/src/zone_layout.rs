@@ -0,0 +1,170 @@
+//! Named sets of zones per output. One set of every output is active, its zones are used like the
+//! ones set with `set_zones`. Switching to another set moves the windows of zones which vanish
+//! into the zones of the new set.
+
+use crate::{
+    composition::{Zone, ZoneGaps},
+    error::{Result, ScapeError},
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{desktop::space::SpaceElement, utils::Rectangle};
+use tracing::info;
+
+#[derive(Debug, Clone)]
+pub struct ZoneLayout {
+    pub name: String,
+    pub zones: Vec<Zone>,
+}
+
+/// Zone layouts of an output and which one is active
+#[derive(Debug, Clone)]
+pub struct OutputZoneLayouts {
+    layouts: Vec<ZoneLayout>,
+    active: usize,
+}
+
+impl OutputZoneLayouts {
+    fn active(&self) -> &ZoneLayout {
+        &self.layouts[self.active]
+    }
+}
+
+impl State {
+    /// Sets the zone layouts of the output and the gaps of all zones. The layout with the name of
+    /// the active one stays active, otherwise the first one is activated.
+    pub fn set_zone_layouts(
+        &mut self,
+        output_name: String,
+        layouts: Vec<ZoneLayout>,
+        gaps: ZoneGaps,
+    ) {
+        if layouts.is_empty() {
+            if let Some(previous) = self.zone_layouts.remove(&output_name) {
+                let zones = self
+                    .zones
+                    .values()
+                    .filter(|zone| !previous.active().zones.iter().any(|z| z.name == zone.name))
+                    .cloned()
+                    .collect();
+                self.set_zones(zones, gaps);
+            }
+            return;
+        }
+        let previous = self
+            .zone_layouts
+            .get(&output_name)
+            .map(|layouts| layouts.active().clone());
+        let active = previous
+            .as_ref()
+            .and_then(|previous| {
+                layouts
+                    .iter()
+                    .position(|layout| layout.name == previous.name)
+            })
+            .unwrap_or_default();
+        self.zone_layouts
+            .insert(output_name.clone(), OutputZoneLayouts { layouts, active });
+        self.apply_zone_layout(&output_name, previous, gaps);
+    }
+
+    /// Activates the zone layout with the name on the output with the name, or the next layout if
+    /// none is given. The output under the pointer is used if no output is given.
+    pub fn cycle_zone_layout(
+        &mut self,
+        output_name: Option<String>,
+        layout_name: Option<&str>,
+    ) -> Result<()> {
+        let output = self.output_or_under_pointer(output_name)?;
+        let layouts = self
+            .zone_layouts
+            .get_mut(&output.name())
+            .ok_or_else(|| ScapeError::NoZoneLayouts(output.name()))?;
+        let previous = layouts.active().clone();
+        layouts.active = match layout_name {
+            Some(layout_name) => layouts
+                .layouts
+                .iter()
+                .position(|layout| layout.name == layout_name)
+                .ok_or_else(|| ScapeError::ZoneLayoutNotFound(layout_name.to_string()))?,
+            None => (layouts.active + 1) % layouts.layouts.len(),
+        };
+        info!(
+            output = output.name(),
+            layout = layouts.active().name.as_str(),
+            "Switching zone layout"
+        );
+        self.apply_zone_layout(&output.name(), Some(previous), self.zone_gaps);
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Replaces the zones of the previous layout with the ones of the active layout. Windows in
+    /// zones with the same name move along with their zone, windows in zones which vanish are
+    /// distributed over the new zones in the order of their previous zones.
+    fn apply_zone_layout(
+        &mut self,
+        output_name: &str,
+        previous: Option<ZoneLayout>,
+        gaps: ZoneGaps,
+    ) {
+        let Some(layout) = self
+            .zone_layouts
+            .get(output_name)
+            .map(|layouts| layouts.active().clone())
+        else {
+            return;
+        };
+        let previous_zones = previous.map(|previous| previous.zones).unwrap_or_default();
+
+        // Topmost window first within each of the vanishing zones
+        let mut displaced = self
+            .spaces
+            .iter()
+            .flat_map(|(space_name, space)| {
+                space
+                    .elements()
+                    .rev()
+                    .filter(|window| matches!(window, WorkspaceWindow::ApplicationWindow(_)))
+                    .filter_map(move |window| {
+                        let location = space.element_location(window)?;
+                        let geometry =
+                            Rectangle::from_loc_and_size(location, window.geometry().size);
+                        Some((space_name.clone(), window.clone(), geometry))
+                    })
+            })
+            .filter_map(|(space_name, window, geometry)| {
+                let zone_name = self.zone_at(geometry)?;
+                if layout.zones.iter().any(|zone| zone.name == zone_name) {
+                    return None;
+                }
+                let index = previous_zones
+                    .iter()
+                    .position(|zone| zone.name == zone_name)?;
+                Some((index, space_name, window))
+            })
+            .collect::<Vec<_>>();
+        displaced.sort_by_key(|(index, _, _)| *index);
+
+        let zones = self
+            .zones
+            .values()
+            .filter(|zone| {
+                !previous_zones
+                    .iter()
+                    .any(|previous| previous.name == zone.name)
+            })
+            .cloned()
+            .chain(layout.zones.iter().cloned())
+            .collect();
+        self.set_zones(zones, gaps);
+
+        if layout.zones.is_empty() {
+            return;
+        }
+        for (position, (_, space_name, window)) in displaced.into_iter().enumerate() {
+            let zone = &layout.zones[position % layout.zones.len()].name;
+            self.place_window(&space_name, &window, false, Some(zone), true);
+        }
+    }
+}
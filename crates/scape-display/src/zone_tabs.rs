@@ -0,0 +1,215 @@
+//! Zones showing their windows as tabs. The windows of a tabbed zone fill it below a tab bar with
+//! a tab for each of them, the topmost window is the active tab. Clicking a tab or cycling the
+//! tabs raises the window.
+
+use crate::{
+    error::{Result, ScapeError},
+    render::CustomRenderElements,
+    state::active_space,
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{
+    backend::renderer::{
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            Kind,
+        },
+        ImportAll, ImportMem, Renderer,
+    },
+    desktop::Space,
+    output::Output,
+    utils::{Logical, Point, Rectangle, Scale},
+};
+use tracing::info;
+
+/// Height of the tab bar above the windows of a tabbed zone in logical pixels
+pub const TAB_BAR_HEIGHT: i32 = 24;
+/// Space between two tabs
+const TAB_GAP: i32 = 2;
+
+/// Tab bar of a tabbed zone on a shown space
+#[derive(Debug)]
+pub struct TabBar {
+    zone: String,
+    space_name: String,
+    geometry: Rectangle<i32, Logical>,
+    /// Windows in the order of their tabs, which is the order they were created in
+    windows: Vec<WorkspaceWindow>,
+    buffers: Vec<SolidColorBuffer>,
+}
+
+impl TabBar {
+    fn tab_geometry(&self, index: usize) -> Rectangle<i32, Logical> {
+        let count = self.windows.len() as i32;
+        let index = index as i32;
+        let x = self.geometry.size.w * index / count;
+        let width = self.geometry.size.w * (index + 1) / count - x;
+        Rectangle::from_loc_and_size(
+            (self.geometry.loc.x + x, self.geometry.loc.y),
+            ((width - TAB_GAP).max(1), self.geometry.size.h),
+        )
+    }
+
+    pub fn render_elements<R>(
+        &self,
+        space: &Space<WorkspaceWindow>,
+        output: &Output,
+    ) -> Vec<CustomRenderElements<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+    {
+        let Some(output_geometry) = space.output_geometry(output) else {
+            return Vec::new();
+        };
+        if !output_geometry.overlaps(self.geometry) {
+            return Vec::new();
+        }
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        self.buffers
+            .iter()
+            .enumerate()
+            .map(|(index, buffer)| {
+                let location = (self.tab_geometry(index).loc - output_geometry.loc)
+                    .to_physical_precise_round(scale);
+                CustomRenderElements::Solid(SolidColorRenderElement::from_buffer(
+                    buffer,
+                    location,
+                    scale,
+                    1.0,
+                    Kind::Unspecified,
+                ))
+            })
+            .collect()
+    }
+}
+
+impl State {
+    /// Shows the windows of the zone with the name, or of the zone of the focused window, as tabs,
+    /// or side by side again
+    pub fn toggle_tabbed(&mut self, zone_name: Option<String>) -> Result<()> {
+        let Some(zone_name) = zone_name.or_else(|| self.focused_zone()) else {
+            info!("Not toggling tabs, the focused window is in no zone");
+            return Ok(());
+        };
+        if !self.zones.contains_key(&zone_name) {
+            return Err(ScapeError::ZoneNotFound(zone_name));
+        }
+        let windows = self
+            .spaces
+            .keys()
+            .flat_map(|space_name| {
+                self.windows_in_zones(space_name, &[zone_name.as_str()])
+                    .into_iter()
+                    .map(move |window| (space_name.clone(), window))
+            })
+            .collect::<Vec<_>>();
+
+        let tabbed = !self.tabbed_zones.remove(&zone_name);
+        if tabbed {
+            self.tabbed_zones.insert(zone_name.clone());
+        }
+        info!(zone = zone_name, tabbed, "Toggling tabbed zone");
+        // Bottom to top, so that placing the windows again keeps the active tab
+        for (space_name, window) in windows.iter().rev() {
+            self.place_window(space_name, window, false, Some(&zone_name), true);
+        }
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Focuses the window `offset` tabs away from the focused window in its zone. This works in
+    /// every zone, in zones which are not tabbed the window is raised above the others.
+    pub fn cycle_tab(&mut self, offset: i32) {
+        let Some(window) = self.focused_window() else {
+            return;
+        };
+        let (Some(zone_name), Some(space_name)) =
+            (self.focused_zone(), self.space_of_window(&window))
+        else {
+            return;
+        };
+        let mut windows = self.windows_in_zones(&space_name, &[zone_name.as_str()]);
+        windows.sort_by_key(WorkspaceWindow::id);
+        let Some(index) = windows.iter().position(|w| *w == window) else {
+            return;
+        };
+        let index = (index as i32 + offset).rem_euclid(windows.len() as i32) as usize;
+        self.focus_window(windows[index].clone(), &space_name);
+    }
+
+    /// Window of the tab at the location and the name of its space
+    pub fn tab_at(&self, location: Point<f64, Logical>) -> Option<(String, WorkspaceWindow)> {
+        self.tab_bars.iter().find_map(|tab_bar| {
+            let index = (0..tab_bar.windows.len())
+                .find(|index| tab_bar.tab_geometry(*index).to_f64().contains(location))?;
+            Some((tab_bar.space_name.clone(), tab_bar.windows[index].clone()))
+        })
+    }
+
+    /// Updates the tab bars of the tabbed zones on the shown spaces before they are rendered
+    pub fn refresh_tab_bars(&mut self) {
+        let mut previous = std::mem::take(&mut self.tab_bars);
+        let palette = *self.theme.palette();
+        let focused = self.focused_window();
+
+        let mut tab_bars = Vec::new();
+        for output in self.outputs.values() {
+            let Some(space_name) = active_space(output) else {
+                continue;
+            };
+            let output_geometry = self.output_rect(output);
+            for zone_name in &self.tabbed_zones {
+                let Some(zone) = self.zones.get(zone_name) else {
+                    continue;
+                };
+                let window_geometry = self.zone_window_geometry(zone);
+                if !output_geometry.overlaps(window_geometry) {
+                    continue;
+                }
+                let mut windows = self.windows_in_zones(&space_name, &[zone_name.as_str()]);
+                if windows.is_empty() {
+                    continue;
+                }
+                let active = windows[0].clone();
+                windows.sort_by_key(WorkspaceWindow::id);
+
+                // The buffers are kept, so that unchanged tabs cause no damage
+                let mut buffers = previous
+                    .iter_mut()
+                    .find(|tab_bar| tab_bar.zone == *zone_name && tab_bar.space_name == space_name)
+                    .map(|tab_bar| std::mem::take(&mut tab_bar.buffers))
+                    .unwrap_or_default();
+                buffers.resize_with(windows.len(), SolidColorBuffer::default);
+                let mut tab_bar = TabBar {
+                    zone: zone_name.clone(),
+                    space_name: space_name.clone(),
+                    geometry: Rectangle::from_loc_and_size(
+                        (
+                            window_geometry.loc.x,
+                            window_geometry.loc.y - TAB_BAR_HEIGHT,
+                        ),
+                        (window_geometry.size.w, TAB_BAR_HEIGHT),
+                    ),
+                    windows,
+                    buffers: Vec::new(),
+                };
+                for (index, mut buffer) in buffers.into_iter().enumerate() {
+                    let window = &tab_bar.windows[index];
+                    let color = if focused.as_ref() == Some(window) {
+                        palette.border_focused
+                    } else if *window == active {
+                        palette.header_bar
+                    } else {
+                        palette.border
+                    };
+                    buffer.update(tab_bar.tab_geometry(index).size, color);
+                    tab_bar.buffers.push(buffer);
+                }
+                tab_bars.push(tab_bar);
+            }
+        }
+        self.tab_bars = tab_bars;
+    }
+}
@@ -0,0 +1,545 @@
+//! Zones split at runtime. A zone is split into two halves, which can be split further, so the
+//! zones set by the config become the roots of a tree of zones. Every space has its own tree, it
+//! is applied to the zones of the output while the space is shown.
+
+use crate::{
+    composition::Zone,
+    error::{Result, ScapeError},
+    state::active_space,
+    workspace_window::WorkspaceWindow,
+    State,
+};
+use smithay::{desktop::space::SpaceElement, utils::Rectangle};
+use std::collections::HashMap;
+use tracing::info;
+
+/// How a zone is split, `Horizontal` puts the halves side by side, `Vertical` above each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitAxis {
+    Horizontal,
+    Vertical,
+}
+
+impl SplitAxis {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "horizontal" => Some(SplitAxis::Horizontal),
+            "vertical" => Some(SplitAxis::Vertical),
+            _ => None,
+        }
+    }
+}
+
+/// Zone which was replaced by two child zones
+#[derive(Debug, Clone)]
+struct ZoneSplit {
+    zone: String,
+    axis: SplitAxis,
+    children: [String; 2],
+}
+
+/// Splits of the zones of a space, parents before their children
+#[derive(Debug, Clone, Default)]
+pub struct ZoneTree {
+    splits: Vec<ZoneSplit>,
+}
+
+impl ZoneSplit {
+    /// Split of the zone into halves named after it
+    fn new(zone_name: String, axis: SplitAxis) -> Self {
+        ZoneSplit {
+            children: [format!("{zone_name}-1"), format!("{zone_name}-2")],
+            zone: zone_name,
+            axis,
+        }
+    }
+
+    /// Replaces the zone by its halves, zones which were split or removed already are skipped
+    fn apply(&self, zones: &mut HashMap<String, Zone>, default_zone: &mut Option<String>) {
+        let Some(zone) = zones.remove(&self.zone) else {
+            return;
+        };
+        let geometry = zone.geometry;
+        let halves = match self.axis {
+            SplitAxis::Horizontal => {
+                let width = geometry.size.w / 2;
+                [
+                    Rectangle::from_loc_and_size(geometry.loc, (width, geometry.size.h)),
+                    Rectangle::from_loc_and_size(
+                        (geometry.loc.x + width, geometry.loc.y),
+                        (geometry.size.w - width, geometry.size.h),
+                    ),
+                ]
+            }
+            SplitAxis::Vertical => {
+                let height = geometry.size.h / 2;
+                [
+                    Rectangle::from_loc_and_size(geometry.loc, (geometry.size.w, height)),
+                    Rectangle::from_loc_and_size(
+                        (geometry.loc.x, geometry.loc.y + height),
+                        (geometry.size.w, geometry.size.h - height),
+                    ),
+                ]
+            }
+        };
+        // The first half takes over being the default zone
+        if default_zone.as_deref() == Some(self.zone.as_str()) {
+            *default_zone = Some(self.children[0].clone());
+        }
+        for (index, (name, geometry)) in self.children.iter().zip(halves).enumerate() {
+            zones.insert(
+                name.clone(),
+                Zone {
+                    name: name.clone(),
+                    geometry,
+                    default: zone.default && index == 0,
+                },
+            );
+        }
+    }
+
+    /// Replaces the halves by the zone they were split from. Resized halves are kept, the zone
+    /// covers both of them.
+    fn revert(&self, zones: &mut HashMap<String, Zone>, default_zone: &mut Option<String>) {
+        let [first, second] = &self.children;
+        let (Some(first), Some(second)) = (zones.get(first), zones.get(second)) else {
+            return;
+        };
+        let zone = Zone {
+            name: self.zone.clone(),
+            geometry: first.geometry.merge(second.geometry),
+            default: first.default || second.default,
+        };
+        for child in &self.children {
+            zones.remove(child);
+            if default_zone.as_deref() == Some(child) {
+                *default_zone = Some(self.zone.clone());
+            }
+        }
+        zones.insert(zone.name.clone(), zone);
+    }
+}
+
+impl ZoneTree {
+    fn split_of_child(&self, zone_name: &str) -> Option<&ZoneSplit> {
+        self.splits
+            .iter()
+            .find(|split| split.children.iter().any(|child| child == zone_name))
+    }
+
+    /// Removes the split and the splits of its children, children last
+    fn remove(&mut self, zone_name: &str) -> Vec<ZoneSplit> {
+        let Some(index) = self.splits.iter().position(|split| split.zone == zone_name) else {
+            return Vec::new();
+        };
+        let split = self.splits.remove(index);
+        let mut removed = vec![split.clone()];
+        for child in &split.children {
+            removed.extend(self.remove(child));
+        }
+        removed
+    }
+}
+
+impl State {
+    /// Splits the zone with the name, or the zone of the focused window, into two halves. The
+    /// windows in the zone are distributed over the halves, topmost window first.
+    pub fn split_zone(&mut self, zone_name: Option<String>, axis: SplitAxis) -> Result<()> {
+        let Some(zone_name) = zone_name.or_else(|| self.focused_zone()) else {
+            info!("Not splitting zone, the focused window is in no zone");
+            return Ok(());
+        };
+        let zone = self
+            .zones
+            .get(&zone_name)
+            .cloned()
+            .ok_or_else(|| ScapeError::ZoneNotFound(zone_name.clone()))?;
+        let space_name = self.space_of_zone(&zone)?;
+        let windows = self.windows_in_zones(&space_name, &[zone_name.as_str()]);
+
+        let split = ZoneSplit::new(zone_name, axis);
+        info!(space_name, zone = split.zone, ?axis, "Splitting zone");
+        split.apply(&mut self.zones, &mut self.default_zone);
+        self.zone_trees
+            .entry(space_name.clone())
+            .or_default()
+            .splits
+            .push(split.clone());
+
+        for (index, window) in windows.iter().enumerate() {
+            let zone = &split.children[index % split.children.len()];
+            self.place_window(&space_name, window, false, Some(zone), true);
+        }
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Merges the zone with the name, or the zone of the focused window, with the other half of
+    /// the zone it was split from. Windows of both halves move into the merged zone.
+    pub fn merge_zone(&mut self, zone_name: Option<String>) -> Result<()> {
+        let Some(zone_name) = zone_name.or_else(|| self.focused_zone()) else {
+            info!("Not merging zone, the focused window is in no zone");
+            return Ok(());
+        };
+        let zone = self
+            .zones
+            .get(&zone_name)
+            .cloned()
+            .ok_or_else(|| ScapeError::ZoneNotFound(zone_name.clone()))?;
+        let space_name = self.space_of_zone(&zone)?;
+        let parent = self
+            .zone_trees
+            .get(&space_name)
+            .and_then(|tree| tree.split_of_child(&zone_name))
+            .map(|split| split.zone.clone())
+            .ok_or_else(|| ScapeError::ZoneNotSplit(zone_name.clone()))?;
+
+        let removed = self
+            .zone_trees
+            .get_mut(&space_name)
+            .map(|tree| tree.remove(&parent))
+            .unwrap_or_default();
+        let leaves = removed
+            .iter()
+            .flat_map(|split| split.children.iter())
+            .filter(|child| self.zones.contains_key(*child))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let windows = self.windows_in_zones(&space_name, &leaves);
+
+        info!(space_name, zone = parent, "Merging zone");
+        for split in removed.iter().rev() {
+            split.revert(&mut self.zones, &mut self.default_zone);
+        }
+        for window in &windows {
+            self.place_window(&space_name, window, false, Some(&parent), true);
+        }
+        self.backend_data.schedule_render();
+        Ok(())
+    }
+
+    /// Replaces the splits of the previous space of an output with the ones of the space shown
+    /// now. Windows are not moved, the windows of the space were placed in its zones already.
+    pub fn switch_zone_tree(&mut self, previous_space: Option<&str>, space_name: &str) {
+        if let Some(tree) = previous_space
+            .and_then(|name| self.zone_trees.get(name))
+            .cloned()
+        {
+            for split in tree.splits.iter().rev() {
+                split.revert(&mut self.zones, &mut self.default_zone);
+            }
+        }
+        self.apply_zone_tree(space_name);
+    }
+
+    /// Splits the zones again after they were replaced, for every space which is shown
+    pub fn apply_zone_trees(&mut self) {
+        let space_names = self
+            .outputs
+            .values()
+            .filter_map(active_space)
+            .collect::<Vec<_>>();
+        for space_name in space_names {
+            self.apply_zone_tree(&space_name);
+        }
+    }
+
+    fn apply_zone_tree(&mut self, space_name: &str) {
+        if let Some(tree) = self.zone_trees.get(space_name).cloned() {
+            for split in &tree.splits {
+                split.apply(&mut self.zones, &mut self.default_zone);
+            }
+        }
+    }
+
+    /// Zone of the focused window
+    pub fn focused_zone(&self) -> Option<String> {
+        let window = self.focused_window()?;
+        let space_name = self.space_of_window(&window)?;
+        let location = self.spaces.get(&space_name)?.element_location(&window)?;
+        self.zone_at(Rectangle::from_loc_and_size(
+            location,
+            window.geometry().size,
+        ))
+    }
+
+    /// Space shown on the output the zone is on
+    pub fn space_of_zone(&self, zone: &Zone) -> Result<String> {
+        let output = self
+            .outputs
+            .values()
+            .find(|output| self.output_rect(output).overlaps(zone.geometry))
+            .ok_or(ScapeError::NoOutputs)?;
+        active_space(output).ok_or(ScapeError::NoSpace)
+    }
+
+    /// Windows of the space in the zones, topmost window first
+    pub fn windows_in_zones(&self, space_name: &str, zone_names: &[&str]) -> Vec<WorkspaceWindow> {
+        let Some(space) = self.spaces.get(space_name) else {
+            return Vec::new();
+        };
+        space
+            .elements()
+            .rev()
+            .filter(|window| matches!(window, WorkspaceWindow::ApplicationWindow(_)))
+            .filter(|window| {
+                space
+                    .element_location(window)
+                    .and_then(|location| {
+                        self.zone_at(Rectangle::from_loc_and_size(
+                            location,
+                            window.geometry().size,
+                        ))
+                    })
+                    .is_some_and(|zone| zone_names.contains(&zone.as_str()))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Zones = Vec<(String, (i32, i32, i32, i32), bool)>;
+
+    /// Zones of an output with the default zone `left` next to `right`
+    fn output_zones() -> (HashMap<String, Zone>, Option<String>) {
+        let zones = [("left", 0, true), ("right", 100, false)]
+            .into_iter()
+            .map(|(name, x, default)| {
+                let zone = Zone {
+                    name: name.to_string(),
+                    geometry: Rectangle::from_loc_and_size((x, 0), (100, 80)),
+                    default,
+                };
+                (name.to_string(), zone)
+            })
+            .collect();
+        (zones, Some("left".to_string()))
+    }
+
+    fn sorted(zones: &HashMap<String, Zone>) -> Zones {
+        let mut zones = zones
+            .values()
+            .map(|zone| {
+                let geometry = zone.geometry;
+                (
+                    zone.name.clone(),
+                    (
+                        geometry.loc.x,
+                        geometry.loc.y,
+                        geometry.size.w,
+                        geometry.size.h,
+                    ),
+                    zone.default,
+                )
+            })
+            .collect::<Zones>();
+        zones.sort();
+        zones
+    }
+
+    fn split(
+        tree: &mut ZoneTree,
+        zones: &mut HashMap<String, Zone>,
+        default_zone: &mut Option<String>,
+        zone_name: &str,
+        axis: SplitAxis,
+    ) {
+        let split = ZoneSplit::new(zone_name.to_string(), axis);
+        split.apply(zones, default_zone);
+        tree.splits.push(split);
+    }
+
+    /// Merges the zone with its other half like `State::merge_zone`
+    fn merge(
+        tree: &mut ZoneTree,
+        zones: &mut HashMap<String, Zone>,
+        default_zone: &mut Option<String>,
+        zone_name: &str,
+    ) {
+        let parent = tree.split_of_child(zone_name).unwrap().zone.clone();
+        for split in tree.remove(&parent).iter().rev() {
+            split.revert(zones, default_zone);
+        }
+    }
+
+    #[test]
+    fn nested_splits_merge_back() {
+        let (mut zones, mut default_zone) = output_zones();
+        let original = sorted(&zones);
+        let mut tree = ZoneTree::default();
+
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "left",
+            SplitAxis::Horizontal,
+        );
+        let after_first = sorted(&zones);
+        assert_eq!(
+            after_first,
+            [
+                ("left-1".to_string(), (0, 0, 50, 80), true),
+                ("left-2".to_string(), (50, 0, 50, 80), false),
+                ("right".to_string(), (100, 0, 100, 80), false),
+            ]
+        );
+        assert_eq!(default_zone.as_deref(), Some("left-1"));
+
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "left-1",
+            SplitAxis::Vertical,
+        );
+        assert_eq!(
+            sorted(&zones)[..2],
+            [
+                ("left-1-1".to_string(), (0, 0, 50, 40), true),
+                ("left-1-2".to_string(), (0, 40, 50, 40), false),
+            ]
+        );
+        assert_eq!(default_zone.as_deref(), Some("left-1-1"));
+
+        merge(&mut tree, &mut zones, &mut default_zone, "left-1-2");
+        assert_eq!(sorted(&zones), after_first);
+        assert_eq!(default_zone.as_deref(), Some("left-1"));
+
+        merge(&mut tree, &mut zones, &mut default_zone, "left-2");
+        assert_eq!(sorted(&zones), original);
+        assert_eq!(default_zone.as_deref(), Some("left"));
+        assert!(tree.splits.is_empty());
+    }
+
+    #[test]
+    fn merging_reverts_the_splits_of_both_halves() {
+        let (mut zones, mut default_zone) = output_zones();
+        let original = sorted(&zones);
+        let mut tree = ZoneTree::default();
+
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "left",
+            SplitAxis::Vertical,
+        );
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "left-1",
+            SplitAxis::Horizontal,
+        );
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "left-2",
+            SplitAxis::Horizontal,
+        );
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "left-1-1",
+            SplitAxis::Vertical,
+        );
+        assert_eq!(zones.len(), 6);
+        assert_eq!(default_zone.as_deref(), Some("left-1-1-1"));
+
+        // The halves of `left` are split themselves, all of them are reverted
+        merge(&mut tree, &mut zones, &mut default_zone, "left-2-1");
+        merge(&mut tree, &mut zones, &mut default_zone, "left-2");
+        assert_eq!(sorted(&zones), original);
+        assert_eq!(default_zone.as_deref(), Some("left"));
+        assert!(tree.splits.is_empty());
+    }
+
+    #[test]
+    fn default_zone_is_only_handed_to_the_first_half() {
+        let (mut zones, mut default_zone) = output_zones();
+        let mut tree = ZoneTree::default();
+
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "right",
+            SplitAxis::Horizontal,
+        );
+        assert_eq!(default_zone.as_deref(), Some("left"));
+        assert!(!zones["right-1"].default && !zones["right-2"].default);
+
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "left",
+            SplitAxis::Horizontal,
+        );
+        // Merging from the second half still hands the default zone back to the parent
+        merge(&mut tree, &mut zones, &mut default_zone, "left-2");
+        assert_eq!(default_zone.as_deref(), Some("left"));
+        assert!(zones["left"].default);
+    }
+
+    #[test]
+    fn merged_zone_covers_resized_halves() {
+        let (mut zones, mut default_zone) = output_zones();
+        let mut tree = ZoneTree::default();
+
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "right",
+            SplitAxis::Vertical,
+        );
+        zones.get_mut("right-2").unwrap().geometry.size.h = 60;
+        merge(&mut tree, &mut zones, &mut default_zone, "right-1");
+        assert_eq!(
+            zones["right"].geometry,
+            Rectangle::from_loc_and_size((100, 0), (100, 100))
+        );
+    }
+
+    #[test]
+    fn switching_spaces_restores_the_tree() {
+        let (mut zones, mut default_zone) = output_zones();
+        let original = sorted(&zones);
+        let mut tree = ZoneTree::default();
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "left",
+            SplitAxis::Horizontal,
+        );
+        split(
+            &mut tree,
+            &mut zones,
+            &mut default_zone,
+            "left-2",
+            SplitAxis::Vertical,
+        );
+        let split_zones = sorted(&zones);
+
+        for split in tree.splits.iter().rev() {
+            split.revert(&mut zones, &mut default_zone);
+        }
+        assert_eq!(sorted(&zones), original);
+        assert_eq!(default_zone.as_deref(), Some("left"));
+
+        for split in &tree.splits {
+            split.apply(&mut zones, &mut default_zone);
+        }
+        assert_eq!(sorted(&zones), split_zones);
+        assert_eq!(default_zone.as_deref(), Some("left-1"));
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 
 /// A Wayland compositor for efficient workflows
 #[derive(Parser, Debug, Default)]
@@ -16,25 +16,238 @@ pub struct GlobalArgs {
     #[arg(short, long)]
     pub config: Option<String>,
 
+    /// Run the config to find mistakes and exit without starting the compositor
+    #[arg(long)]
+    pub check: bool,
+
+    /// Accept input events from `scape cli input`, for automated tests and accessibility tools.
+    /// Every program which can reach the command socket can control the session then.
+    #[arg(long)]
+    pub allow_synthetic_input: bool,
+
     /// Optional sub-commands to run
     #[clap(subcommand)]
     pub command: Option<Command>,
 }
 
+/// Sub-commands of scape, it runs the compositor if none is given
 #[derive(Debug, Subcommand)]
 pub enum Command {
+    /// Control the running instance of scape
     Cli(CliArgs),
 }
 
+/// Arguments of `scape cli`. `SCAPE_SOCKET` selects the socket, e.g. one forwarded over SSH from
+/// another machine, and `SCAPE_TOKEN` is sent along if the instance requires a token.
 #[derive(Args, Debug)]
 pub struct CliArgs {
+    /// Command which is sent to the running instance
     #[clap(subcommand)]
     pub cli_command: CliCommand,
 }
 
+/// Commands for the running instance
 #[derive(Clone, Debug, Subcommand)]
 pub enum CliCommand {
-    CloseWindow { window_name: String },
+    /// Close the window with the given name
+    CloseWindow {
+        /// Id, title or app id of the window
+        window_name: String,
+    },
+    /// Run a command registered by the config with `scape.register_command`
+    Run {
+        /// Name the command was registered with
+        name: String,
+        /// Arguments passed to the command callback
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Print the windows of all spaces as JSON
+    ListWindows,
+    /// Print the connected outputs as JSON
+    ListOutputs,
+    /// Print the spaces with their outputs and windows as JSON
+    ListSpaces,
+    /// Focus the window with the given id
+    Focus {
+        /// Id of the window, as printed by `list-windows`
+        id: u64,
+    },
+    /// Move the window with the given id into a zone, to a position or to a space
+    #[command(group = ArgGroup::new("target").required(true))]
+    MoveWindow {
+        /// Id of the window, as printed by `list-windows`
+        id: u64,
+        /// Name of the zone
+        #[arg(group = "target")]
+        zone: Option<String>,
+        /// Global position of the top left corner, the window leaves its zone
+        #[arg(
+            long,
+            group = "target",
+            num_args = 2,
+            value_names = ["X", "Y"],
+            allow_negative_numbers = true
+        )]
+        position: Option<Vec<i32>>,
+        /// Name of the space
+        #[arg(long, group = "target")]
+        space: Option<String>,
+    },
+    /// Show a space
+    SwitchSpace {
+        /// Name of the space
+        name: String,
+        /// Output which shows the space, the output under the pointer if none is given
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Change an output immediately
+    Output {
+        /// Name of the output, as printed by `list-outputs`
+        name: String,
+        #[clap(subcommand)]
+        setting: OutputSetting,
+    },
+    /// Create an output without a display, which is only rendered offscreen, e.g. for
+    /// screencasting or testing. Prints the name of the new output.
+    CreateOutput {
+        /// Size and optionally the refresh rate, e.g. 1920x1080 or 1920x1080@60
+        mode: String,
+    },
+    /// Remove an output created by `create-output`
+    DestroyOutput {
+        /// Name of the output, as printed by `create-output`
+        name: String,
+    },
+    /// Inject an input event, scape has to be started with --allow-synthetic-input
+    Input {
+        #[clap(subcommand)]
+        event: SyntheticInput,
+    },
+    /// Quit the compositor
+    Quit,
+    /// Execute the config again to update key bindings, zones, rules and the output layout,
+    /// windows are kept
+    ReloadConfig,
+    /// Print events as JSON lines until the compositor stops
+    Subscribe {
+        /// Comma-separated kinds of events: window, space (or workspace) and output
+        #[arg(value_delimiter = ',', required = true)]
+        events: Vec<String>,
+    },
+    /// Send a request in the JSON form of the command socket and print the response data, e.g.
+    /// `{"request": "get_windows"}`
+    Msg {
+        /// JSON object with the `request` field naming the request
+        request: String,
+    },
+}
+
+/// Setting of `scape cli output`
+#[derive(Clone, Debug, Subcommand)]
+pub enum OutputSetting {
+    /// Set the scale
+    Scale {
+        /// Scale like 1.5
+        scale: f64,
+    },
+    /// Move the output in the layout
+    #[command(allow_negative_numbers = true)]
+    Position {
+        /// Horizontal position of the top left corner
+        x: i32,
+        /// Vertical position of the top left corner
+        y: i32,
+    },
+    /// Set the mode
+    Mode {
+        /// Mode in the form of 3840x2160@120, the refresh rate is optional
+        mode: String,
+    },
+    /// Rotate or flip the output
+    Transform {
+        /// normal, 90, 180, 270, flipped, flipped-90, flipped-180 or flipped-270
+        transform: String,
+    },
+    /// Turn the output off, its windows move to another output of its space
+    Off,
+    /// Turn the output on again
+    On,
+}
+
+/// Event of `scape cli input`, positions are fractions of the area of the outputs from 0 to 1
+#[derive(Clone, Debug, Subcommand)]
+pub enum SyntheticInput {
+    /// Press or release a key
+    Key {
+        /// Evdev code of the key, e.g. 30 for A
+        key: u32,
+        /// Whether the key is pressed or released
+        state: PressState,
+    },
+    /// Move the pointer by a distance
+    #[command(allow_negative_numbers = true)]
+    Motion {
+        /// Horizontal distance in pixels
+        dx: f64,
+        /// Vertical distance in pixels
+        dy: f64,
+    },
+    /// Move the pointer to a position
+    Position {
+        /// Horizontal position
+        x: f64,
+        /// Vertical position
+        y: f64,
+    },
+    /// Press or release a pointer button
+    Button {
+        /// Evdev code of the button, e.g. 272 for the left button
+        button: u32,
+        /// Whether the button is pressed or released
+        state: PressState,
+    },
+    /// Scroll
+    #[command(allow_negative_numbers = true)]
+    Scroll {
+        /// Horizontal distance in pixels
+        horizontal: f64,
+        /// Vertical distance in pixels
+        vertical: f64,
+    },
+    /// Put a finger down
+    TouchDown {
+        /// Slot which tells the fingers apart
+        slot: u32,
+        /// Horizontal position
+        x: f64,
+        /// Vertical position
+        y: f64,
+    },
+    /// Move a finger
+    TouchMotion {
+        /// Slot of the finger
+        slot: u32,
+        /// Horizontal position
+        x: f64,
+        /// Vertical position
+        y: f64,
+    },
+    /// Lift a finger
+    TouchUp {
+        /// Slot of the finger
+        slot: u32,
+    },
+}
+
+/// State of a key or button
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PressState {
+    /// The key or button goes down
+    Press,
+    /// The key or button goes up
+    Release,
 }
 
 /// Parses and returns the command lines arguments
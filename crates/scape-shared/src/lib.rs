@@ -10,7 +10,13 @@ mod main_message;
 mod renderer_message;
 
 pub use args::get_global_args;
+pub use args::CliArgs;
+pub use args::CliCommand;
+pub use args::Command;
 pub use args::GlobalArgs;
+pub use args::OutputSetting;
+pub use args::PressState;
+pub use args::SyntheticInput;
 pub use comms::Comms;
 pub use display_message::DisplayMessage;
 pub use input_message::InputMessage;
@@ -4,6 +4,19 @@ scape.on_startup(function()
 	scape.spawn("wezterm")
 end)
 
+-- Suspend waits a moment for this hook, enough to start a screen locker
+scape.on_suspend(function()
+	scape.spawn("swaylock")
+end)
+
+scape.on_resume(function()
+	print("Resumed from suspend")
+end)
+
+scape.on_shutdown(function()
+	print("Shutting down")
+end)
+
 local space = "main"
 
 scape.on_connector_change(function(outputs)
@@ -16,11 +29,14 @@ scape.on_connector_change(function(outputs)
 	main_output.disabled = false
 	main_output.scale = 1
 
+	-- The layout is validated and applied as a whole, on failure the previous layout is kept
 	scape.set_layout({
 		[space] = {
 			main_output,
 		},
-	})
+	}, function(err)
+		print("Layout was not applied: " .. err)
+	end)
 	scape.set_zones({
 		{
 			name = "left",
@@ -47,6 +63,35 @@ scape.on_connector_change(function(outputs)
 	})
 end)
 
+-- Runs for every connected output, before on_connector_change updates the layout
+scape.on_output_added(function(output)
+	local serial = output.serial or "without serial"
+	print(string.format("Connected %s: %s %s, %s", output.name, output.make, output.model, serial))
+	for _, mode in ipairs(output.modes) do
+		if mode.preferred then
+			print("Preferred mode " .. mode.mode)
+		end
+	end
+end)
+
+scape.on_output_removed(function(output)
+	print("Disconnected " .. output.name)
+end)
+
+-- Applied instead of on_connector_change while exactly these outputs are connected. Outputs are
+-- matched by connector name, by "make model serial" or by "*".
+scape.add_output_profile({
+	name = "docked",
+	space = space,
+	outputs = {
+		{ output = "eDP-1", disabled = true },
+		{ output = "*", position = { 0, 0 } },
+	},
+	callback = function(outputs)
+		print("Docked profile applied to " .. outputs[2].name)
+	end,
+})
+
 scape.map_key({
 	key = "a",
 	mods = "shift|super",
@@ -9,7 +9,8 @@ use calloop::{
     EventLoop, LoopHandle,
 };
 use scape_shared::{
-    get_global_args, Comms, DisplayMessage, GlobalArgs, InputMessage, MainMessage, RendererMessage,
+    get_global_args, Command, Comms, DisplayMessage, GlobalArgs, InputMessage, MainMessage,
+    RendererMessage,
 };
 use std::{
     panic::UnwindSafe,
@@ -67,6 +68,20 @@ fn main() -> anyhow::Result<()> {
     // Get a static reference to the global args, so that they can be sent across threads
     let args = Box::leak(Box::new(get_global_args()));
 
+    // The cli talks to a running instance instead of starting one
+    if let Some(Command::Cli(cli_args)) = &args.command {
+        return scape_display::command::client::run(cli_args.cli_command.clone());
+    }
+
+    if args.check {
+        let warnings = scape_display::config::check_config(args).context("Config is invalid")?;
+        for warning in warnings {
+            eprintln!("Warning: {warning}");
+        }
+        println!("Config is valid");
+        return Ok(());
+    }
+
     setup_logging(args.log_file.as_deref());
     #[cfg(feature = "profiling")]
     setup_profiling();